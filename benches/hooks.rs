@@ -0,0 +1,24 @@
+//! Stop hook latency for small, medium and large sessions.
+//!
+//! Run with `cargo bench`. The `claude-reliability bench` command runs the
+//! same fixtures without criterion, and can fail when they are over budget.
+
+// criterion's macros generate undocumented functions
+#![allow(missing_docs)]
+
+use claude_reliability::hooks::{BenchSize, StopHookFixture};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+fn stop_hook(c: &mut Criterion) {
+    let mut group = c.benchmark_group("stop_hook");
+    for size in BenchSize::ALL {
+        let fixture = StopHookFixture::create(size).expect("fixture should be created");
+        group.bench_with_input(BenchmarkId::from_parameter(size.name()), &fixture, |b, fixture| {
+            b.iter(|| fixture.run().expect("stop hook should run"));
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, stop_hook);
+criterion_main!(benches);
@@ -0,0 +1,137 @@
+//! Turning long-unanswered questions into assumptions.
+//!
+//! Some users would rather the agent kept going than waited on them. With
+//! `assume_after_hours` set, a question nobody has answered for that long is
+//! answered with an assumption: the first of its suggested answers, or
+//! otherwise the answer the sub-agent recommends. The work items waiting on
+//! the question are then unblocked.
+//!
+//! Assumptions are recorded as such ([`Question::assumed`]), listed first in
+//! handoff summaries, and undone with `claude-reliability question reopen`.
+//! Answering the question normally replaces the assumption.
+
+use crate::error::Result;
+use crate::tasks::{Question, TaskStore};
+use crate::traits::SubAgent;
+use chrono::{DateTime, Duration, Utc};
+
+/// Format of question timestamps in the task database.
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// Answer questions left unanswered for `after_hours` with assumptions.
+///
+/// Deferred questions are left alone, as are questions the sub-agent cannot
+/// suggest an answer for. Does nothing when `after_hours` is 0. Returns the
+/// questions that were answered.
+///
+/// # Errors
+///
+/// Returns an error if the store cannot be read or updated.
+pub fn assume_expired<S: TaskStore + ?Sized>(
+    store: &S,
+    sub_agent: &dyn SubAgent,
+    after_hours: u32,
+    now: DateTime<Utc>,
+) -> Result<Vec<Question>> {
+    if after_hours == 0 {
+        return Ok(Vec::new());
+    }
+    let now_stamp = now.format(TIMESTAMP_FORMAT).to_string();
+    let cutoff =
+        (now - Duration::hours(i64::from(after_hours))).format(TIMESTAMP_FORMAT).to_string();
+
+    let mut assumed = Vec::new();
+    for question in store.list_questions(true)? {
+        if question.created_at > cutoff || question.is_deferred(&now_stamp) {
+            continue;
+        }
+        let Some(answer) = choose_assumption(&question, sub_agent) else {
+            continue;
+        };
+        if let Some(question) = store.assume_answer(&question.id, &answer)? {
+            assumed.push(question);
+        }
+    }
+    Ok(assumed)
+}
+
+/// Pick the answer to assume for a question, if there is one.
+fn choose_assumption(question: &Question, sub_agent: &dyn SubAgent) -> Option<String> {
+    if let Some(suggestion) = question.suggestions.first() {
+        return Some(suggestion.answer.clone());
+    }
+    match sub_agent.suggest_answers(&question.text) {
+        Ok(suggestions) => suggestions.into_iter().next().map(|s| s.answer),
+        Err(e) => {
+            tracing::warn!("Failed to choose an assumption for question {}: {e}", question.id);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tasks::{AnswerSuggestion, InMemoryTaskStore};
+    use crate::testing::{FailingSubAgent, MockSubAgent};
+
+    fn suggestion(answer: &str) -> AnswerSuggestion {
+        AnswerSuggestion { answer: answer.to_string(), tradeoffs: String::new() }
+    }
+
+    fn hours_later(hours: i64) -> DateTime<Utc> {
+        Utc::now() + Duration::hours(hours)
+    }
+
+    #[test]
+    fn test_assume_expired() {
+        let store = InMemoryTaskStore::new();
+        let suggested = store.create_question("Which database?").unwrap();
+        store
+            .set_question_suggestions(
+                &suggested.id,
+                &[suggestion("SQLite"), suggestion("Postgres")],
+            )
+            .unwrap();
+        let asked = store.create_question("Which licence?").unwrap();
+        let deferred = store.create_question("Which logo?").unwrap();
+        store.defer_question(&deferred.id, Some("9999-01-01 00:00:00")).unwrap();
+        let mut sub_agent = MockSubAgent::new();
+        sub_agent.expect_answer_suggestions(vec![suggestion("MIT"), suggestion("GPL")]);
+
+        // Nothing has waited long enough yet
+        assert!(assume_expired(&store, &sub_agent, 24, hours_later(1)).unwrap().is_empty());
+
+        let assumed = assume_expired(&store, &sub_agent, 24, hours_later(25)).unwrap();
+        let answers: Vec<(&str, Option<&str>)> =
+            assumed.iter().map(|q| (q.id.as_str(), q.answer.as_deref())).collect();
+        assert_eq!(
+            answers,
+            vec![(suggested.id.as_str(), Some("SQLite")), (asked.id.as_str(), Some("MIT"))]
+        );
+        assert!(assumed.iter().all(|q| q.assumed));
+        assert_eq!(store.list_questions(true).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_assume_expired_disabled() {
+        let store = InMemoryTaskStore::new();
+        store.create_question("Which database?").unwrap();
+        let sub_agent = MockSubAgent::new();
+
+        assert!(assume_expired(&store, &sub_agent, 0, hours_later(1000)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_assume_expired_without_suggestion() {
+        let store = InMemoryTaskStore::new();
+        store.create_question("What is the API key?").unwrap();
+        let mut sub_agent = MockSubAgent::new();
+        sub_agent.expect_answer_suggestions(Vec::new());
+
+        assert!(assume_expired(&store, &sub_agent, 1, hours_later(2)).unwrap().is_empty());
+        let failing = FailingSubAgent::new("unavailable");
+        assert!(assume_expired(&store, &failing, 1, hours_later(2)).unwrap().is_empty());
+        assert_eq!(store.list_questions(true).unwrap().len(), 1);
+    }
+}
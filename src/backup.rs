@@ -0,0 +1,340 @@
+//! Rolling backups of the project database.
+//!
+//! When a session starts and the newest automatic backup is older than
+//! `backup_interval_hours`, the database is copied into
+//! `.claude-reliability/backups/` and only the newest `backup_keep` automatic
+//! backups are kept. `claude-reliability backup now` takes one on demand, and
+//! `backup restore <file>` puts one back after copying the database it
+//! replaces alongside the backups, so a restore can itself be undone.
+
+use crate::config::ProjectConfig;
+use crate::error::{Error, Result};
+use crate::paths;
+use crate::recovery;
+use crate::tasks::SqliteTaskStore;
+use chrono::{NaiveDateTime, Utc};
+use rusqlite::{Connection, OptionalExtension};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// Default hours between automatic backups.
+pub const BACKUP_INTERVAL_HOURS: u32 = 24;
+
+/// Default number of automatic backups kept.
+pub const BACKUP_KEEP: u32 = 7;
+
+/// Audit log operation recording a restored backup.
+pub const RESTORE_OPERATION: &str = "database_restored";
+
+/// File name prefix of automatic and on-demand backups.
+const BACKUP_PREFIX: &str = "backup-";
+
+/// Format of the timestamp in backup file names.
+const TIMESTAMP_FORMAT: &str = "%Y%m%dT%H%M%S%.3fZ";
+
+/// A backup file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BackupFile {
+    /// Path of the backup.
+    pub path: String,
+    /// Size of the backup in bytes.
+    pub size_bytes: u64,
+}
+
+/// What restoring a backup did.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RestoreReport {
+    /// The backup that was restored.
+    pub restored_from: String,
+    /// Where the replaced database was copied to, if there was one.
+    pub previous_database: Option<String>,
+}
+
+/// Back up the database now, then delete the automatic backups beyond `keep`.
+///
+/// Returns the new backup.
+///
+/// # Errors
+///
+/// Returns an error if there is no database or it cannot be copied.
+pub fn create(base_dir: &Path, keep: u32) -> Result<BackupFile> {
+    let db_path = paths::project_db_path(base_dir);
+    if !db_path.exists() {
+        return Err(Error::FileNotFound(db_path));
+    }
+    // Opening the store first recovers a corrupted database rather than copying it
+    SqliteTaskStore::new(&db_path)?;
+
+    let dir = paths::project_backups_dir(base_dir);
+    std::fs::create_dir_all(&dir)
+        .map_err(|source| Error::FileAccess { path: dir.clone(), source })?;
+    let path = dir.join(format!("{BACKUP_PREFIX}{}.sqlite3", Utc::now().format(TIMESTAMP_FORMAT)));
+    Connection::open(&db_path)?.execute("VACUUM INTO ?1", [path.display().to_string()])?;
+    prune(&dir, keep)?;
+
+    Ok(BackupFile {
+        size_bytes: std::fs::metadata(&path).map_or(0, |m| m.len()),
+        path: path.display().to_string(),
+    })
+}
+
+/// Back up the database if the newest backup is older than the configured interval.
+///
+/// Failures are logged rather than returned, since this runs inside hooks.
+pub fn run_if_due(base_dir: &Path) -> Option<BackupFile> {
+    let config = ProjectConfig::load_from(base_dir).ok().flatten().unwrap_or_default();
+    if config.backup_interval_hours == 0 || !paths::project_db_path(base_dir).exists() {
+        return None;
+    }
+    let dir = paths::project_backups_dir(base_dir);
+    let due = newest_backup_time(&dir).map_or(true, |newest| {
+        Utc::now().naive_utc().signed_duration_since(newest).num_minutes()
+            >= i64::from(config.backup_interval_hours) * 60
+    });
+    if !due {
+        return None;
+    }
+    match create(base_dir, config.backup_keep) {
+        Ok(backup) => Some(backup),
+        Err(e) => {
+            tracing::warn!("Database backup failed: {e}");
+            None
+        }
+    }
+}
+
+/// List the files in the backups directory, newest first.
+///
+/// Besides the automatic backups, this includes the copies of corrupted
+/// databases and of databases replaced by a restore.
+///
+/// # Errors
+///
+/// Returns an error if the backups directory cannot be read.
+pub fn list(base_dir: &Path) -> Result<Vec<BackupFile>> {
+    let dir = paths::project_backups_dir(base_dir);
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let entries = std::fs::read_dir(&dir)
+        .map_err(|source| Error::FileAccess { path: dir.clone(), source })?;
+    let mut backups: Vec<(std::time::SystemTime, BackupFile)> = entries
+        .filter_map(std::result::Result::ok)
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "sqlite3"))
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            let backup =
+                BackupFile { path: entry.path().display().to_string(), size_bytes: metadata.len() };
+            Some((metadata.modified().ok()?, backup))
+        })
+        .collect();
+    backups.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| b.1.path.cmp(&a.1.path)));
+    Ok(backups.into_iter().map(|(_, backup)| backup).collect())
+}
+
+/// Replace the database with a backup.
+///
+/// The current database, if any, is first copied into the backups directory.
+///
+/// # Errors
+///
+/// Returns an error if the file is missing, is not a readable work item
+/// database, or cannot be copied into place.
+pub fn restore(base_dir: &Path, file: &Path) -> Result<RestoreReport> {
+    if !file.is_file() {
+        return Err(Error::FileNotFound(file.to_path_buf()));
+    }
+    check_restorable(file)?;
+
+    let db_path = paths::project_db_path(base_dir);
+    let previous_database = if db_path.exists() {
+        Some(recovery::back_up(&db_path, "pre-restore")?)
+    } else {
+        if let Some(parent) = db_path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|source| Error::FileAccess { path: parent.to_path_buf(), source })?;
+        }
+        None
+    };
+
+    // Bring the backup's own write-ahead log into it before it takes the database's place
+    let staging = recovery::with_suffix(&db_path, ".restoring");
+    std::fs::copy(file, &staging)?;
+    let wal = recovery::with_suffix(file, "-wal");
+    if wal.exists() {
+        std::fs::copy(wal, recovery::with_suffix(&staging, "-wal"))?;
+    }
+    Connection::open(&staging)?.execute_batch("PRAGMA wal_checkpoint(TRUNCATE);")?;
+    recovery::remove_sidecars(&staging)?;
+    recovery::remove_sidecars(&db_path)?;
+    std::fs::rename(&staging, &db_path)?;
+
+    let report = RestoreReport {
+        restored_from: file.display().to_string(),
+        previous_database: previous_database.map(|p| p.display().to_string()),
+    };
+    let details = report.previous_database.as_ref().map_or_else(
+        || format!("Restored from {}", report.restored_from),
+        |previous| {
+            format!(
+                "Restored from {}; the replaced database is at {previous}",
+                report.restored_from
+            )
+        },
+    );
+    SqliteTaskStore::new(&db_path)?.record_event(RESTORE_OPERATION, &details)?;
+    Ok(report)
+}
+
+/// Check that a file is an undamaged work item database.
+fn check_restorable(file: &Path) -> Result<()> {
+    let invalid = |reason: String| Error::InvalidBackup { path: file.to_path_buf(), reason };
+    if let Some(problem) = recovery::find_corruption(file) {
+        return Err(invalid(problem));
+    }
+    let has_tasks = Connection::open(file)
+        .and_then(|conn| {
+            conn.query_row(
+                "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'tasks'",
+                [],
+                |_| Ok(()),
+            )
+            .optional()
+        })
+        .map_err(|e| invalid(e.to_string()))?;
+    if has_tasks.is_none() {
+        return Err(invalid("it has no work items table".to_string()));
+    }
+    Ok(())
+}
+
+/// The automatic backups in a directory with when each was taken, newest first.
+fn automatic_backups(dir: &Path) -> Vec<(NaiveDateTime, PathBuf)> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut backups: Vec<(NaiveDateTime, PathBuf)> = entries
+        .filter_map(std::result::Result::ok)
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let timestamp = name.strip_prefix(BACKUP_PREFIX)?.strip_suffix(".sqlite3")?;
+            let taken = NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT).ok()?;
+            Some((taken, entry.path()))
+        })
+        .collect();
+    backups.sort_by(|a, b| b.cmp(a));
+    backups
+}
+
+/// When the newest automatic backup in a directory was taken.
+fn newest_backup_time(dir: &Path) -> Option<NaiveDateTime> {
+    automatic_backups(dir).first().map(|(taken, _)| *taken)
+}
+
+/// Delete the automatic backups beyond the newest `keep` (0 keeps them all).
+fn prune(dir: &Path, keep: u32) -> Result<()> {
+    if keep == 0 {
+        return Ok(());
+    }
+    let keep = usize::try_from(keep).unwrap_or(usize::MAX);
+    for (_, path) in automatic_backups(dir).into_iter().skip(keep) {
+        std::fs::remove_file(&path).map_err(|source| Error::FileAccess { path, source })?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tasks::{Priority, TaskFilter, TaskStore};
+    use tempfile::TempDir;
+
+    fn task_titles(base_dir: &Path) -> Vec<String> {
+        let store = SqliteTaskStore::for_project(base_dir).unwrap();
+        store.list_tasks(TaskFilter::default()).unwrap().into_iter().map(|t| t.title).collect()
+    }
+
+    #[test]
+    fn test_create_keeps_newest_backups() {
+        let dir = TempDir::new().unwrap();
+        let store = SqliteTaskStore::for_project(dir.path()).unwrap();
+        store.create_task("Fix parser", "", Priority::High).unwrap();
+
+        let backups_dir = paths::project_backups_dir(dir.path());
+        std::fs::create_dir_all(&backups_dir).unwrap();
+        for old in ["20200101T000000.000Z", "20200102T000000.000Z"] {
+            std::fs::write(backups_dir.join(format!("backup-{old}.sqlite3")), "").unwrap();
+        }
+        std::fs::write(backups_dir.join("corrupt-20200101T000000.000Z.sqlite3"), "").unwrap();
+
+        let backup = create(dir.path(), 2).unwrap();
+        assert!(backup.size_bytes > 0);
+        let remaining: Vec<PathBuf> =
+            automatic_backups(&backups_dir).into_iter().map(|(_, p)| p).collect();
+        assert_eq!(
+            remaining,
+            vec![
+                PathBuf::from(&backup.path),
+                backups_dir.join("backup-20200102T000000.000Z.sqlite3")
+            ]
+        );
+        // Only automatic backups are pruned
+        assert_eq!(list(dir.path()).unwrap().len(), 3);
+        assert!(recovery::find_corruption(Path::new(&backup.path)).is_none());
+    }
+
+    #[test]
+    fn test_run_if_due() {
+        let dir = TempDir::new().unwrap();
+        assert_eq!(run_if_due(dir.path()), None);
+
+        SqliteTaskStore::for_project(dir.path()).unwrap();
+        assert!(run_if_due(dir.path()).is_some());
+        assert_eq!(run_if_due(dir.path()), None);
+
+        ProjectConfig { backup_interval_hours: 0, ..Default::default() }
+            .save_to(dir.path())
+            .unwrap();
+        let backups_dir = paths::project_backups_dir(dir.path());
+        for (_, path) in automatic_backups(&backups_dir) {
+            std::fs::remove_file(path).unwrap();
+        }
+        assert_eq!(run_if_due(dir.path()), None);
+    }
+
+    #[test]
+    fn test_restore_replaces_database() {
+        let dir = TempDir::new().unwrap();
+        let store = SqliteTaskStore::for_project(dir.path()).unwrap();
+        store.create_task("Fix parser", "", Priority::High).unwrap();
+        let backup = create(dir.path(), BACKUP_KEEP).unwrap();
+        store.create_task("Agent mistake", "", Priority::Low).unwrap();
+
+        let report = restore(dir.path(), Path::new(&backup.path)).unwrap();
+        assert_eq!(task_titles(dir.path()), vec!["Fix parser"]);
+        let log = store.get_audit_log(None, None).unwrap();
+        assert!(log.iter().any(|entry| entry.operation == RESTORE_OPERATION), "{log:?}");
+
+        // The replaced database can itself be restored
+        let previous = report.previous_database.unwrap();
+        restore(dir.path(), Path::new(&previous)).unwrap();
+        assert_eq!(task_titles(dir.path()).len(), 2);
+    }
+
+    #[test]
+    fn test_restore_rejects_unusable_files() {
+        let dir = TempDir::new().unwrap();
+        let missing = dir.path().join("missing.sqlite3");
+        assert!(matches!(restore(dir.path(), &missing), Err(Error::FileNotFound(_))));
+
+        let garbage = dir.path().join("garbage.sqlite3");
+        std::fs::write(&garbage, "not a database ".repeat(500)).unwrap();
+        assert!(matches!(restore(dir.path(), &garbage), Err(Error::InvalidBackup { .. })));
+
+        let other = dir.path().join("other.sqlite3");
+        Connection::open(&other).unwrap().execute_batch("CREATE TABLE notes (x);").unwrap();
+        let err = restore(dir.path(), &other).unwrap_err();
+        assert!(err.to_string().contains("it has no work items table"), "{err}");
+        assert!(!paths::project_db_path(dir.path()).exists());
+    }
+}
@@ -115,7 +115,10 @@ pub fn sync_beads_to_tasks(runner: &dyn CommandRunner, base_dir: &Path) -> Resul
 /// Internal helper to sync issues to a task store.
 ///
 /// This is extracted to allow testing the sync logic with mock stores.
-fn sync_issues_to_store(issues: &[BeadsIssue], store: &dyn TaskStore) -> Result<SyncResult> {
+fn sync_issues_to_store<S: TaskStore + ?Sized>(
+    issues: &[BeadsIssue],
+    store: &S,
+) -> Result<SyncResult> {
     let mut result = SyncResult::default();
 
     // Get all existing tasks once to check for duplicates
@@ -138,19 +141,8 @@ fn sync_issues_to_store(issues: &[BeadsIssue], store: &dyn TaskStore) -> Result<
         // Map beads priority to task priority
         let priority = Priority::from_u8(issue.priority).unwrap_or(Priority::Medium);
 
-        // Build description with beads reference
-        let description = if issue.description.is_empty() {
-            beads_marker
-        } else {
-            format!("{beads_marker}\n\n{}", issue.description)
-        };
-
-        // Build title with type prefix if available
-        let title = if issue.r#type.is_empty() {
-            issue.title.clone()
-        } else {
-            format!("[{}] {}", issue.r#type, issue.title)
-        };
+        let (title, description) =
+            beads_task_text(&issue.id, &issue.r#type, &issue.title, &issue.description);
 
         // Create the task
         match store.create_task(&title, &description, priority) {
@@ -162,6 +154,25 @@ fn sync_issues_to_store(issues: &[BeadsIssue], store: &dyn TaskStore) -> Result<
     Ok(result)
 }
 
+/// The title and description of the task for a beads issue.
+///
+/// The title is prefixed with the issue type, if it has one, and the
+/// description starts with a marker that [`extract_beads_id`] finds.
+#[must_use]
+pub fn beads_task_text(
+    id: &str,
+    issue_type: &str,
+    title: &str,
+    description: &str,
+) -> (String, String) {
+    let marker = format!("{BEADS_MARKER_PREFIX}{id}]");
+    let description =
+        if description.is_empty() { marker } else { format!("{marker}\n\n{description}") };
+    let title =
+        if issue_type.is_empty() { title.to_string() } else { format!("[{issue_type}] {title}") };
+    (title, description)
+}
+
 /// Extract the beads issue ID from a task description if present.
 ///
 /// Returns the issue ID (e.g., "proj-123") if the description contains a beads marker.
@@ -0,0 +1,25 @@
+//! Audit log CLI subcommands.
+//!
+//! Provides the command for exporting the audit log and session data.
+
+use clap::Subcommand;
+use std::path::PathBuf;
+
+/// Audit log commands.
+#[derive(Subcommand, Debug, Clone)]
+pub enum AuditLogCommand {
+    /// Export the audit log and session data for analysis.
+    ///
+    /// Writes `audit_log`, `task_transitions`, `hook_events` and `sessions`
+    /// files into the output directory. Hook events are only recorded when
+    /// `debug_logging` is enabled.
+    Export {
+        /// File format: csv or jsonl
+        #[arg(long, default_value = "csv")]
+        format: String,
+
+        /// Directory to write to (default: .claude-reliability/exports)
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+}
@@ -0,0 +1,29 @@
+//! Backup CLI subcommands.
+//!
+//! Provides commands for backing up the database and restoring a backup.
+
+use clap::Subcommand;
+use std::path::PathBuf;
+
+/// Database backup commands.
+///
+/// The session start hook backs the database up into
+/// `.claude-reliability/backups/` every `backup_interval_hours` (a day by
+/// default), keeping the newest `backup_keep` (7 by default).
+#[derive(Subcommand, Debug, Clone)]
+pub enum BackupCommand {
+    /// Back the database up now.
+    Now,
+
+    /// List the backups, newest first.
+    List,
+
+    /// Replace the database with a backup.
+    ///
+    /// The current database is copied into the backups directory first, so
+    /// the restore can be undone.
+    Restore {
+        /// The backup file to restore.
+        file: PathBuf,
+    },
+}
@@ -0,0 +1,17 @@
+//! Handoff CLI subcommands.
+//!
+//! Provides commands for reading the handoff summaries written at the end
+//! of each autonomous run.
+
+use clap::Subcommand;
+
+/// Session handoff commands.
+///
+/// When a session stops after modifying the project, a summary of the run
+/// (work items completed, commits, open questions and next steps) is written
+/// to `.claude-reliability/handoffs/<timestamp>.md`.
+#[derive(Subcommand, Debug, Clone)]
+pub enum HandoffCommand {
+    /// Print the most recent handoff summary.
+    Latest,
+}
@@ -0,0 +1,17 @@
+//! Maintenance CLI subcommands.
+//!
+//! Provides a command for running database maintenance on demand.
+
+use clap::Subcommand;
+
+/// Database maintenance commands.
+///
+/// Maintenance rebuilds the search indexes, optimizes the database and
+/// clears expired markers. The session start hook already runs it once a
+/// day; run it by hand after importing a lot of work items, or when hooks
+/// feel slow.
+#[derive(Subcommand, Debug, Clone)]
+pub enum MaintenanceCommand {
+    /// Run maintenance now and report the database size.
+    Run,
+}
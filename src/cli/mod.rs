@@ -3,20 +3,35 @@
 //! This module provides the command-line interface with two-level commands
 //! for managing work items, how-to guides, questions, and other operations.
 
+mod audit_log;
+mod backup;
+mod handoff;
 mod howto;
+mod maintenance;
 mod question;
 mod run;
+mod session;
+mod simulate;
+mod transcript;
 mod work;
 
 #[cfg(test)]
 mod tests;
 
+pub use audit_log::AuditLogCommand;
+pub use backup::BackupCommand;
+pub use handoff::HandoffCommand;
 pub use howto::HowToCommand;
+pub use maintenance::MaintenanceCommand;
 pub use question::QuestionCommand;
 pub use run::{run, CliOutput};
-pub use work::WorkCommand;
+pub use session::SessionCommand;
+pub use simulate::SimulateCommand;
+pub use transcript::TranscriptCommand;
+pub use work::{TemplateCommand, WorkCommand};
 
 use clap::{Parser, Subcommand};
+use std::path::PathBuf;
 
 /// Claude reliability CLI - work tracking and session management.
 ///
@@ -71,12 +86,137 @@ pub enum Command {
     #[command(subcommand)]
     Question(QuestionCommand),
 
+    /// Session handoff summaries - digests of each autonomous run.
+    ///
+    /// A summary is written whenever a session stops after modifying the
+    /// project, covering completed work, commits, open questions and next steps.
+    #[command(subcommand)]
+    Handoff(HandoffCommand),
+
+    /// Session registry - which sessions are alive and what each is doing.
+    ///
+    /// Every hook call updates its session's state (working, blocked or
+    /// stopped) and last-seen time.
+    #[command(subcommand)]
+    Session(SessionCommand),
+
+    /// Transcript search - find messages and tool calls across sessions.
+    ///
+    /// Searches the transcripts of every session the hooks have seen, to
+    /// find when the agent touched a file or discussed a topic.
+    #[command(subcommand)]
+    Transcript(TranscriptCommand),
+
+    /// Simulations - see which stop check fires in a fabricated situation.
+    ///
+    /// Nothing in the project is changed.
+    #[command(subcommand)]
+    Simulate(SimulateCommand),
+
+    /// Time the stop hook against fabricated sessions.
+    ///
+    /// Builds small, medium and large transcripts and work item databases in
+    /// a scratch directory and runs the stop hook against each, with git and
+    /// the sub-agent answered from memory, then prints the median and slowest
+    /// run. With `--assert-under`, fails when a median run is over the budget,
+    /// to catch performance regressions in CI. `cargo bench` runs the same
+    /// fixtures under criterion.
+    Bench {
+        /// Only time this size: small, medium or large
+        #[arg(long)]
+        size: Option<String>,
+
+        /// How many timed runs for each size
+        #[arg(long, default_value_t = 20, value_parser = clap::value_parser!(u16).range(1..))]
+        runs: u16,
+
+        /// Fail if a median run takes longer than this, e.g. 200ms or 1s
+        #[arg(long)]
+        assert_under: Option<String>,
+    },
+
+    /// Database maintenance - rebuild search indexes and prune expired state.
+    ///
+    /// Runs automatically at most once a day when a session starts.
+    #[command(subcommand)]
+    Maintenance(MaintenanceCommand),
+
+    /// Database backups - back up the work items now, or restore a backup.
+    ///
+    /// Backups are also taken automatically when a session starts, once a day
+    /// by default.
+    #[command(subcommand)]
+    Backup(BackupCommand),
+
+    /// Print metrics in the Prometheus text format.
+    ///
+    /// Shows stop decisions by reason, quality check and sub-agent durations,
+    /// and work item counts. Hooks only record metrics when `metrics_file` is
+    /// set in the config.
+    Metrics,
+
+    /// Summarize recent activity as a Markdown report.
+    ///
+    /// Covers work items created and completed, questions asked and
+    /// answered, stop blocks by reason and the quality check failure rate,
+    /// ready to paste into a team update.
+    Report {
+        /// How far back to report, e.g. 7d, 24h or 90m
+        #[arg(long, default_value = "7d")]
+        since: String,
+
+        /// Have a sub-agent add a short narrative summary
+        #[arg(long)]
+        narrate: bool,
+    },
+
+    /// Show the effective stop hook settings for this project.
+    ///
+    /// Lists the thresholds and limits the stop hook uses, noting the default
+    /// of each one the config file changes, and reports template overrides
+    /// that are being ignored. Use this to explain why the hooks behave
+    /// differently in different projects.
+    Doctor,
+
+    /// Re-run a hook against captured input to debug its decision.
+    ///
+    /// The hook runs against a scratch copy of the project's data, so
+    /// replaying never changes the real session state. Pass `--at` with an
+    /// audit log entry ID (see `audit-log`) to rewind work items to how they
+    /// were at that point. Prints the hook's full output, including the stop
+    /// hook's checks log.
+    Replay {
+        /// The hook to run: stop, session-start, user-prompt-submit,
+        /// pre-tool-use or post-tool-use
+        #[arg(long)]
+        hook: String,
+
+        /// File containing the hook's JSON input
+        #[arg(long)]
+        input: PathBuf,
+
+        /// Transcript to use instead of the input's `transcript_path`
+        #[arg(long)]
+        transcript: Option<PathBuf>,
+
+        /// Rewind work items to this audit log entry before running
+        #[arg(long)]
+        at: Option<i64>,
+    },
+
     /// Get the audit log for work item changes.
     ///
     /// Shows a history of changes to work items, including creation,
     /// updates, status changes, and dependency modifications.
+    ///
+    /// Use `audit-log export` to write the log, task transitions, hook events
+    /// and sessions to files for analysis.
     #[command(name = "audit-log")]
     AuditLog {
+        /// Export instead of printing entries
+        #[command(subcommand)]
+        command: Option<AuditLogCommand>,
+
         /// Filter by work item ID
         #[arg(long)]
         work_id: Option<String>,
@@ -94,79 +234,188 @@ pub enum Command {
     ///
     /// Do NOT use for "too much work" - that's normal. Just work through
     /// tasks one at a time using priority order.
+    ///
+    /// Requests are limited per hour, and repeated rejections file a
+    /// question for the user instead.
     #[command(name = "emergency-stop")]
     EmergencyStop {
-        /// Explanation of why you need to stop
+        /// Explanation of why you need to stop, naming the concrete blocker
         explanation: String,
     },
 
+    /// Request a one-time change to the reliability config, database or settings hooks.
+    ///
+    /// A reviewer checks the justification; if approved, the next change to
+    /// one of these files, or the next `disable` or `enable`, within 10
+    /// minutes is allowed. Every request is
+    /// recorded in the audit log.
+    #[command(name = "unlock-protected")]
+    UnlockProtected {
+        /// Why the change is needed and who asked for it
+        justification: String,
+    },
+
+    /// Work through ready work items with headless Claude sessions.
+    ///
+    /// Each session runs `claude -p` in single work item mode on one ready
+    /// item. When it exits the item is left complete, or gets a note and,
+    /// if the session failed or timed out, is marked stuck. Returns once no
+    /// untried item is ready.
+    #[command(name = "run-worker")]
+    RunWorker {
+        /// How many sessions to run at once; above 1, each session gets its
+        /// own git worktree on a `worker/<id>` branch
+        #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u16).range(1..))]
+        max_parallel: u16,
+
+        /// Minutes a session may run before it is stopped
+        #[arg(long, default_value_t = 120, value_parser = clap::value_parser!(u32).range(1..))]
+        session_timeout_minutes: u32,
+    },
+
     // === Utility Commands ===
     /// Show version information.
     Version,
 
     /// Ensure config file exists (create with defaults if not).
+    ///
+    /// With `--profile`, also apply a preset of policy settings: `strict`,
+    /// `standard` or `permissive`. Any key can be changed in the config file
+    /// afterwards; running the command again re-applies the preset.
     #[command(name = "ensure-config")]
-    EnsureConfig,
+    EnsureConfig {
+        /// Preset to apply: strict, standard or permissive
+        #[arg(long)]
+        profile: Option<String>,
+    },
 
-    /// Ensure .gitignore has required entries.
+    /// Ensure .gitignore has the managed block of required entries, and show what was added.
     #[command(name = "ensure-gitignore")]
     EnsureGitignore,
 
     /// Print session intro message.
     Intro,
 
+    /// Disable every hook in this project until `enable` is run or the time is up.
+    ///
+    /// While disabled, hooks allow everything and only print a one-line notice.
+    /// The agent needs an approved `unlock-protected` request to run this.
+    Disable {
+        /// How long to disable the hooks for, e.g. 30m, 2h or 1d
+        #[arg(long = "for", value_name = "DURATION")]
+        duration: Option<String>,
+    },
+
+    /// Enable the hooks again after `disable`.
+    Enable,
+
     // === Hook Commands (receive JSON from stdin) ===
     /// Run the stop hook (stdin: JSON hook input).
     ///
     /// This is called by the plugin system when Claude attempts to stop.
     /// Not intended for direct use.
     #[command(hide = true)]
-    Stop,
+    Stop {
+        /// Write a timing profile of the hook to `.claude-reliability/profiles/`.
+        #[arg(long)]
+        profile: bool,
+    },
+
+    /// Run the session start hook (stdin: JSON hook input).
+    ///
+    /// This is called by the plugin system when a session starts or resumes.
+    /// Not intended for direct use.
+    #[command(name = "session-start", hide = true)]
+    SessionStart {
+        /// Write a timing profile of the hook to `.claude-reliability/profiles/`.
+        #[arg(long)]
+        profile: bool,
+    },
 
     /// Run the user prompt submit hook (stdin: JSON hook input).
     ///
     /// This is called by the plugin system when user submits a prompt.
     /// Not intended for direct use.
     #[command(name = "user-prompt-submit", hide = true)]
-    UserPromptSubmit,
+    UserPromptSubmit {
+        /// Write a timing profile of the hook to `.claude-reliability/profiles/`.
+        #[arg(long)]
+        profile: bool,
+    },
 
     /// Run the pre-tool-use hook (stdin: JSON hook input).
     ///
     /// This is called by the plugin system before tool execution.
     /// Not intended for direct use.
     #[command(name = "pre-tool-use", hide = true)]
-    PreToolUse,
+    PreToolUse {
+        /// Write a timing profile of the hook to `.claude-reliability/profiles/`.
+        #[arg(long)]
+        profile: bool,
+    },
 
     /// Run the post-tool-use hook (stdin: JSON hook input).
     ///
     /// This is called by the plugin system after tool execution.
     /// Not intended for direct use.
     #[command(name = "post-tool-use", hide = true)]
-    PostToolUse,
+    PostToolUse {
+        /// Write a timing profile of the hook to `.claude-reliability/profiles/`.
+        #[arg(long)]
+        profile: bool,
+    },
 }
 
 impl Command {
     /// Returns true if this command requires stdin input.
     #[must_use]
     pub const fn needs_stdin(&self) -> bool {
-        matches!(self, Self::Stop | Self::PreToolUse | Self::PostToolUse | Self::UserPromptSubmit)
+        matches!(
+            self,
+            Self::Stop { .. }
+                | Self::SessionStart { .. }
+                | Self::PreToolUse { .. }
+                | Self::PostToolUse { .. }
+                | Self::UserPromptSubmit { .. }
+        )
     }
 
     /// Returns true if this is a hook command (invoked by the plugin system).
     #[must_use]
     pub const fn is_hook(&self) -> bool {
-        matches!(self, Self::Stop | Self::PreToolUse | Self::PostToolUse | Self::UserPromptSubmit)
+        matches!(
+            self,
+            Self::Stop { .. }
+                | Self::SessionStart { .. }
+                | Self::PreToolUse { .. }
+                | Self::PostToolUse { .. }
+                | Self::UserPromptSubmit { .. }
+        )
     }
 
     /// Returns the hook type name for logging, or None for non-hook commands.
     #[must_use]
     pub const fn hook_type(&self) -> Option<&'static str> {
         match self {
-            Self::Stop => Some("stop"),
-            Self::UserPromptSubmit => Some("user-prompt-submit"),
-            Self::PreToolUse => Some("pre-tool-use"),
-            Self::PostToolUse => Some("post-tool-use"),
+            Self::Stop { .. } => Some("stop"),
+            Self::SessionStart { .. } => Some("session-start"),
+            Self::UserPromptSubmit { .. } => Some("user-prompt-submit"),
+            Self::PreToolUse { .. } => Some("pre-tool-use"),
+            Self::PostToolUse { .. } => Some("post-tool-use"),
             _ => None,
         }
     }
+
+    /// Returns true if this is a hook command asked to write a timing profile.
+    #[must_use]
+    pub const fn profile(&self) -> bool {
+        match self {
+            Self::Stop { profile }
+            | Self::SessionStart { profile }
+            | Self::UserPromptSubmit { profile }
+            | Self::PreToolUse { profile }
+            | Self::PostToolUse { profile } => *profile,
+            _ => false,
+        }
+    }
 }
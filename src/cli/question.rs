@@ -44,11 +44,25 @@ pub enum QuestionCommand {
         /// The question text (required)
         #[arg(short, long)]
         text: String,
+
+        /// Priority: 0=critical, 1=high, 2=medium (default), 3=low, 4=backlog
+        #[arg(short, long)]
+        priority: Option<u8>,
+    },
+
+    /// Set how urgently a question needs an answer.
+    SetPriority {
+        /// Question ID
+        id: String,
+
+        /// Priority: 0=critical, 1=high, 2=medium, 3=low, 4=backlog
+        priority: u8,
     },
 
     /// Get a question by ID.
     ///
-    /// Shows the question text and answer if answered.
+    /// Shows the question text, any suggested answers, and the answer if answered.
+    #[command(alias = "show")]
     Get {
         /// Question ID
         id: String,
@@ -62,8 +76,48 @@ pub enum QuestionCommand {
         id: String,
 
         /// The answer to provide
-        #[arg(short, long)]
-        answer: String,
+        #[arg(short, long, required_unless_present = "pick")]
+        answer: Option<String>,
+
+        /// Answer with one of the question's suggested answers (1-based)
+        #[arg(long, conflicts_with = "answer")]
+        pick: Option<usize>,
+    },
+
+    /// Answer all unanswered questions in your editor.
+    ///
+    /// Opens the questions in `$VISUAL` or `$EDITOR` (falling back to `vi`) as
+    /// a Markdown document with an "Answer" section under each one, those
+    /// blocking the most requested work first. Questions left without an
+    /// answer stay unanswered. Deferred questions are left out.
+    AnswerAll,
+
+    /// Defer a question, leaving it out of `answer-all` and the questions
+    /// shown to the user until the given time.
+    ///
+    /// Work items linked to the question stay blocked.
+    Defer {
+        /// Question ID
+        id: String,
+
+        /// When to bring the question back: a duration such as `2h` or `3d`,
+        /// or a UTC date or time such as `2026-11-02` or `2026-11-02 09:00`
+        #[arg(long, required_unless_present = "clear")]
+        until: Option<String>,
+
+        /// Stop deferring the question
+        #[arg(long, conflicts_with = "until")]
+        clear: bool,
+    },
+
+    /// Make an answered question unanswered again.
+    ///
+    /// Undoes an answer assumed because nobody answered in time (see
+    /// `assume_after_hours`), or any other answer. Work items linked to the
+    /// question are blocked again.
+    Reopen {
+        /// Question ID
+        id: String,
     },
 
     /// Delete a question.
@@ -74,7 +128,11 @@ pub enum QuestionCommand {
         id: String,
     },
 
-    /// List all questions.
+    /// List all questions, those blocking the most requested work first.
+    ///
+    /// Each question shows the open requested work items waiting on its
+    /// answer. Questions blocking the same amount of work are ordered by
+    /// priority, then age.
     List {
         /// Only show unanswered questions
         #[arg(short, long)]
@@ -125,4 +183,12 @@ pub enum QuestionCommand {
         /// Work item ID
         work_id: String,
     },
+
+    /// Send new questions and read back answers on the configured channels.
+    ///
+    /// With `question_delivery` configured, new questions are posted as
+    /// comments on a GitHub issue or emailed. This command posts any not yet
+    /// sent, and answers questions from `/answer <question-id>` comments on the
+    /// issue and from email replies. Run it periodically, e.g. from cron.
+    Sync,
 }
@@ -2,19 +2,35 @@
 //!
 //! This module handles running CLI commands and producing output.
 
-use crate::cli::{Command, HowToCommand, QuestionCommand, WorkCommand};
+use crate::cli::{
+    AuditLogCommand, BackupCommand, Command, HandoffCommand, HowToCommand, MaintenanceCommand,
+    QuestionCommand, SessionCommand, SimulateCommand, TemplateCommand, TranscriptCommand,
+    WorkCommand,
+};
 use crate::command::RealCommandRunner;
 use crate::config;
 use crate::hooks::{
-    parse_hook_input, run_post_tool_use, run_pre_tool_use, run_stop_hook,
-    run_user_prompt_submit_hook, PostToolUseInput, StopHookConfig,
+    parse_hook_input, resume_context, run_post_tool_use, run_pre_tool_use, run_session_start_hook,
+    run_stop_hook, run_user_prompt_submit_hook, Deadline, PostToolUseInput, SessionStartInput,
+    StopHookConfig,
 };
+use crate::metrics;
 use crate::paths;
+use crate::replay;
 use crate::session;
+use crate::storage::{Marker, SessionRecord, SessionState};
 use crate::subagent::RealSubAgent;
+use crate::subagent_logging;
+use crate::tasks::graph::{find_blockers, render_graph, GraphFormat};
+use crate::tasks::import::{import, ImportFormat};
+use crate::tasks::similar::find_similar_open_tasks;
 use crate::tasks::{
-    HowToUpdate, Priority, SqliteTaskStore, Status, TaskFilter, TaskStore, TaskUpdate,
+    self, HowToUpdate, Priority, Problem, SqliteTaskStore, Status, TaskFilter, TaskStore,
+    TaskTemplate, TaskUpdate,
 };
+use crate::templates;
+use crate::vcs;
+use crate::worker::WorkerOutcome;
 use serde::Serialize;
 use std::path::{Path, PathBuf};
 use std::process::ExitCode;
@@ -89,10 +105,49 @@ fn verify_binary_location() -> Option<CliOutput> {
     }
 }
 
+/// Get the directory a hook fired in from its input.
+///
+/// Claude Code reports the session's working directory as `cwd`.
+pub fn hook_cwd(stdin: &str) -> Option<PathBuf> {
+    let value: serde_json::Value = serde_json::from_str(stdin).ok()?;
+    value.get("cwd")?.as_str().filter(|s| !s.is_empty()).map(PathBuf::from)
+}
+
+/// Change into the root of the git worktree the hook fired in.
+///
+/// Falls back to the process working directory when the input has no `cwd`,
+/// and does nothing outside a git repository.
+#[cfg(not(test))]
+fn enter_worktree_root(stdin: &str) {
+    let Some(dir) = hook_cwd(stdin).or_else(|| std::env::current_dir().ok()) else {
+        return;
+    };
+    let runner = RealCommandRunner::new();
+    if let Ok(Some(root)) = crate::git::worktree_root(&runner, &dir) {
+        let _ = std::env::set_current_dir(root);
+    }
+}
+
 /// Run a CLI command with the given stdin input.
 pub fn run(command: Command, stdin: &str) -> CliOutput {
+    // Hooks operate on the worktree the session is in, so that config, session
+    // markers and the task database are kept per worktree.
+    #[cfg(not(test))]
+    if command.is_hook() {
+        enter_worktree_root(stdin);
+    }
+
+    // With `--profile`, the hook's spans are timed and written out at the end
+    let profiler = command.profile().then(crate::profiling::Profiler::start);
+
     // Log hook events for debugging when enabled
     if let Some(hook_type) = command.hook_type() {
+        #[cfg(not(test))]
+        crate::logging::init_hook_logging(
+            Path::new("."),
+            hook_type,
+            profiler.as_ref().map(crate::profiling::Profiler::layer),
+        );
         crate::hook_logging::log_hook_event(hook_type, stdin);
     }
 
@@ -105,23 +160,163 @@ pub fn run(command: Command, stdin: &str) -> CliOutput {
         }
     }
 
+    let is_hook = command.is_hook();
+    let hook_type = command.hook_type();
+    if is_hook {
+        if let Some(output) = disabled_hook_output(&command) {
+            return output;
+        }
+    }
+    let is_stop = matches!(command, Command::Stop { .. });
+    // Markers are scoped to the hook's session; other commands see every session's
+    session::set_current_session(if is_hook { hook_session_id(stdin) } else { None }.as_deref());
+
+    // A crashing hook reports the crash and gives the configured decision
+    let output = match hook_type {
+        Some(hook_type) => {
+            let _span =
+                profiler.as_ref().map(|_| tracing::info_span!("hook", hook = hook_type).entered());
+            match crate::hooks::catch_panic(hook_type, Path::new("."), || dispatch(command, stdin))
+            {
+                Ok(output) => output,
+                Err(crash) => hook_crash_output(&crash, failure_mode()),
+            }
+        }
+        None => dispatch(command, stdin),
+    };
+
+    if let Some(hook_type) = hook_type {
+        record_hook_session(stdin, is_stop, &output);
+        if let Err(e) = metrics::export(Path::new(".")) {
+            tracing::warn!("Could not export metrics: {e}");
+        }
+        if let Some(profiler) = profiler {
+            match profiler.write(Path::new("."), hook_type) {
+                Ok(path) => tracing::info!("Wrote timing profile to {}", path.display()),
+                Err(e) => tracing::warn!("Could not write timing profile: {e}"),
+            }
+        }
+    }
+    output
+}
+
+/// The output of a hook while all hooks are disabled, if they are.
+///
+/// The hook allows everything; the session start hook tells the agent why.
+fn disabled_hook_output(command: &Command) -> Option<CliOutput> {
+    let notice = match session::hooks_disabled(Path::new("."))? {
+        session::HooksDisabled::UntilEnabled => {
+            "claude-reliability hooks are disabled; run `claude-reliability enable` to turn them \
+             back on."
+                .to_string()
+        }
+        session::HooksDisabled::Until(until) => format!(
+            "claude-reliability hooks are disabled until {}; run `claude-reliability enable` to \
+             turn them back on sooner.",
+            until.format("%Y-%m-%d %H:%M UTC")
+        ),
+    };
+    Some(if matches!(command, Command::SessionStart { .. }) {
+        CliOutput { exit_code: ExitCode::SUCCESS, stdout: vec![notice], stderr: vec![] }
+    } else {
+        CliOutput { exit_code: ExitCode::SUCCESS, stdout: vec![], stderr: vec![notice] }
+    })
+}
+
+/// Run the handler for a command.
+fn dispatch(command: Command, stdin: &str) -> CliOutput {
     match command {
         Command::Version => run_version(),
-        Command::EnsureConfig => run_ensure_config(),
+        Command::EnsureConfig { profile } => run_ensure_config(profile.as_deref()),
         Command::EnsureGitignore => run_ensure_gitignore(),
         Command::Intro => run_intro(),
-        Command::Stop => run_stop_cmd(stdin),
-        Command::UserPromptSubmit => run_user_prompt_submit_cmd(stdin),
-        Command::PreToolUse => run_pre_tool_use_cmd(stdin),
-        Command::PostToolUse => run_post_tool_use_cmd(stdin),
+        Command::Disable { duration } => run_disable(duration.as_deref()),
+        Command::Enable => run_enable(),
+        Command::Stop { .. } => run_stop_cmd(stdin),
+        Command::SessionStart { .. } => run_session_start_cmd(stdin),
+        Command::UserPromptSubmit { .. } => run_user_prompt_submit_cmd(stdin),
+        Command::PreToolUse { .. } => run_pre_tool_use_cmd(stdin),
+        Command::PostToolUse { .. } => run_post_tool_use_cmd(stdin),
         Command::Work(cmd) => run_work_cmd(cmd),
         Command::Howto(cmd) => run_howto_cmd(cmd),
         Command::Question(cmd) => run_question_cmd(cmd),
-        Command::AuditLog { work_id, limit } => run_audit_log(work_id.as_ref(), limit),
-        Command::EmergencyStop { explanation } => run_emergency_stop(explanation),
+        Command::Handoff(cmd) => run_handoff_cmd(&cmd),
+        Command::AuditLog { command: Some(cmd), .. } => run_audit_log_cmd(&cmd),
+        Command::AuditLog { command: None, work_id, limit } => {
+            run_audit_log(work_id.as_ref(), limit)
+        }
+        Command::EmergencyStop { explanation } => run_emergency_stop(&explanation),
+        Command::UnlockProtected { justification } => run_unlock_protected(&justification),
+        Command::RunWorker { max_parallel, session_timeout_minutes } => {
+            run_worker_cmd(max_parallel, session_timeout_minutes)
+        }
+        Command::Session(cmd) => run_session_cmd(&cmd),
+        Command::Transcript(cmd) => run_transcript_cmd(&cmd),
+        Command::Simulate(cmd) => run_simulate_cmd(&cmd),
+        Command::Bench { size, runs, assert_under } => {
+            run_bench(size.as_deref(), runs, assert_under.as_deref())
+        }
+        Command::Maintenance(cmd) => run_maintenance_cmd(&cmd),
+        Command::Backup(cmd) => run_backup_cmd(&cmd),
+        Command::Metrics => run_metrics(),
+        Command::Report { since, narrate } => run_report(&since, narrate),
+        Command::Doctor => run_doctor(),
+        Command::Replay { hook, input, transcript, at } => {
+            run_replay(&hook, &input, transcript.as_deref(), at)
+        }
     }
 }
 
+/// The project's failure mode, for hooks that fail before reading the config.
+fn failure_mode() -> config::FailureMode {
+    config::ProjectConfig::load_from(Path::new(".")).ok().flatten().unwrap_or_default().failure_mode
+}
+
+/// The output of a hook that crashed, giving the failure mode's decision.
+///
+/// Blocking uses exit code 2, which every hook type treats as a block.
+pub fn hook_crash_output(crash: &crate::hooks::HookCrash, mode: config::FailureMode) -> CliOutput {
+    let (exit_code, action) = if mode.allows() {
+        (ExitCode::SUCCESS, "Allowing, as failure_mode is open.")
+    } else {
+        (ExitCode::from(2), "Blocking, as failure_mode is closed.")
+    };
+    CliOutput { exit_code, stdout: vec![], stderr: vec![format!("{}. {action}", crash.summary())] }
+}
+
+/// Get the session ID from a hook's input, if it has one.
+///
+/// Falls back to the transcript file name, which is the session ID.
+fn hook_session_id(stdin: &str) -> Option<String> {
+    let value: serde_json::Value = serde_json::from_str(stdin).ok()?;
+    value.get("session_id").and_then(|v| v.as_str()).map(str::to_string).or_else(|| {
+        let transcript = value.get("transcript_path").and_then(|v| v.as_str())?;
+        Path::new(transcript).file_stem().map(|stem| stem.to_string_lossy().into_owned())
+    })
+}
+
+/// Record a hook call in the session registry.
+///
+/// A stop that was blocked (exit code 2) leaves the session blocked; any
+/// other stop means it stopped. Every other hook means the session is working.
+fn record_hook_session(stdin: &str, is_stop: bool, output: &CliOutput) {
+    let Ok(value) = serde_json::from_str::<serde_json::Value>(stdin) else {
+        return;
+    };
+    let Some(session_id) = value.get("session_id").and_then(|v| v.as_str()) else {
+        return;
+    };
+    let transcript_path = value.get("transcript_path").and_then(|v| v.as_str());
+    let state = if !is_stop {
+        SessionState::Working
+    } else if output.exit_code == ExitCode::from(2) {
+        SessionState::Blocked
+    } else {
+        SessionState::Stopped
+    };
+    let _ = session::record_session_activity(Path::new("."), session_id, transcript_path, state);
+}
+
 // === Utility Commands ===
 
 fn run_version() -> CliOutput {
@@ -132,18 +327,33 @@ fn run_version() -> CliOutput {
     }
 }
 
-fn run_ensure_config() -> CliOutput {
+fn run_ensure_config(profile: Option<&str>) -> CliOutput {
     let runner = RealCommandRunner::new();
-    match config::ensure_config(&runner) {
+    let result = match profile.map(str::parse::<config::Profile>).transpose() {
+        Ok(Some(profile)) => config::apply_profile_in(&runner, Path::new("."), profile),
+        Ok(None) => config::ensure_config(&runner),
+        Err(e) => Err(e),
+    };
+    match result {
         Ok(config) => {
             let mut messages =
                 vec!["Config ensured at .claude/reliability-config.yaml".to_string()];
+            if let Some(profile) = config.profile.filter(|_| profile.is_some()) {
+                messages.push(format!("  profile: {}", profile.name()));
+            }
             messages.push(format!("  git_repo: {}", config.git_repo));
+            let vcs = vcs::resolve(config.vcs, Path::new("."));
+            messages.push(format!("  vcs: {}", format!("{vcs:?}").to_lowercase()));
             if let Some(ref cmd) = config.check_command {
                 messages.push(format!("  check_command: {cmd}"));
             } else {
                 messages.push("  check_command: (none)".to_string());
             }
+            let problems = templates::validate_overrides(Path::new("."));
+            if !problems.is_empty() {
+                messages.push("Ignoring invalid template overrides:".to_string());
+                messages.extend(problems.into_iter().map(|p| format!("  {p}")));
+            }
             CliOutput { exit_code: ExitCode::SUCCESS, stdout: vec![], stderr: messages }
         }
         Err(e) => CliOutput {
@@ -154,19 +364,66 @@ fn run_ensure_config() -> CliOutput {
     }
 }
 
+fn run_disable(duration: Option<&str>) -> CliOutput {
+    let until = match duration.map(parse_duration_minutes).transpose() {
+        Ok(minutes) => minutes
+            .map(|minutes| chrono::Utc::now() + chrono::Duration::minutes(i64::from(minutes))),
+        Err(e) => return error_output(e),
+    };
+    match session::disable_hooks(Path::new("."), until) {
+        Ok(()) => success_output(until.map_or_else(
+            || "All hooks disabled until `claude-reliability enable` is run.".to_string(),
+            |until| format!("All hooks disabled until {}.", until.format("%Y-%m-%d %H:%M UTC")),
+        )),
+        Err(e) => error_output(e.to_string()),
+    }
+}
+
+fn run_enable() -> CliOutput {
+    match session::enable_hooks(Path::new(".")) {
+        Ok(true) => success_output("Hooks enabled.".to_string()),
+        Ok(false) => success_output("Hooks were not disabled.".to_string()),
+        Err(e) => error_output(e.to_string()),
+    }
+}
+
+fn run_worker_cmd(max_parallel: u16, session_timeout_minutes: u32) -> CliOutput {
+    let runner = RealCommandRunner::new();
+    let timeout = std::time::Duration::from_secs(u64::from(session_timeout_minutes) * 60);
+    match crate::worker::run_worker(&runner, Path::new("."), usize::from(max_parallel), timeout) {
+        Ok(results) if results.is_empty() => {
+            success_output("No ready work items to run.".to_string())
+        }
+        Ok(results) => {
+            let stdout = results
+                .iter()
+                .map(|r| {
+                    let outcome = match &r.outcome {
+                        WorkerOutcome::Completed => "completed".to_string(),
+                        WorkerOutcome::Unfinished => "not finished".to_string(),
+                        WorkerOutcome::Failed(reason) => format!("stuck (session {reason})"),
+                    };
+                    let branch =
+                        r.branch.as_ref().map_or_else(String::new, |b| format!(" (on {b})"));
+                    format!("{}: {} - {outcome}{branch}", r.id, r.title)
+                })
+                .collect();
+            CliOutput { exit_code: ExitCode::SUCCESS, stdout, stderr: vec![] }
+        }
+        Err(e) => error_output(e.to_string()),
+    }
+}
+
 fn run_ensure_gitignore() -> CliOutput {
     match config::ensure_gitignore(Path::new(".")) {
-        Ok(modified) => {
-            let msg = if modified {
-                "Updated .gitignore with claude-reliability entries"
+        Ok(added) => {
+            let mut messages = if added.is_empty() {
+                vec![".gitignore already has required entries".to_string()]
             } else {
-                ".gitignore already has required entries"
+                vec!["Updated .gitignore with claude-reliability entries:".to_string()]
             };
-            CliOutput {
-                exit_code: ExitCode::SUCCESS,
-                stdout: vec![],
-                stderr: vec![msg.to_string()],
-            }
+            messages.extend(added.iter().map(|line| format!("+{line}")));
+            CliOutput { exit_code: ExitCode::SUCCESS, stdout: vec![], stderr: messages }
         }
         Err(e) => CliOutput {
             exit_code: ExitCode::from(1),
@@ -180,9 +437,10 @@ fn run_intro() -> CliOutput {
     use crate::templates;
     use tera::Context;
 
-    let message = templates::render("messages/session_intro.tera", &Context::new())
-        .expect("session_intro.tera template should always render");
-    CliOutput { exit_code: ExitCode::SUCCESS, stdout: vec![], stderr: vec![message] }
+    let mut messages = vec![templates::render("messages/session_intro.tera", &Context::new())
+        .expect("session_intro.tera template should always render")];
+    messages.extend(resume_context(Path::new("."), None, false));
+    CliOutput { exit_code: ExitCode::SUCCESS, stdout: vec![], stderr: messages }
 }
 
 // === Hook Commands ===
@@ -194,22 +452,12 @@ fn run_stop_cmd(stdin: &str) -> CliOutput {
     let project_config = match config::ensure_config(&runner) {
         Ok(c) => c,
         Err(e) => {
-            eprintln!("Warning: Could not load config: {e}");
+            tracing::warn!("Could not load config: {e}");
             config::ProjectConfig::default()
         }
     };
 
-    let config = StopHookConfig {
-        git_repo: project_config.git_repo,
-        quality_check_enabled: project_config.check_command.is_some(),
-        quality_check_command: project_config.check_command,
-        require_push: project_config.require_push,
-        base_dir: None,
-        explain_stops: project_config.explain_stops,
-        auto_work_on_tasks: project_config.auto_work_on_tasks,
-        auto_work_idle_minutes: project_config.auto_work_idle_minutes,
-        single_work_item_id: crate::single_work_item::get_single_work_item_id(),
-    };
+    let config = stop_hook_config(project_config);
 
     let input = match parse_hook_input(stdin) {
         Ok(i) => i,
@@ -227,24 +475,108 @@ fn run_stop_cmd(stdin: &str) -> CliOutput {
             let exit_code = exit_code_from_i32(result.exit_code);
             let outputs_json = exit_code == ExitCode::SUCCESS;
 
-            if outputs_json && !result.messages.is_empty() {
-                let first_msg = &result.messages[0];
-                if first_msg.starts_with('{') {
-                    CliOutput { exit_code, stdout: result.messages, stderr: vec![] }
-                } else {
-                    let system_message = result.messages.join("\n");
-                    let json = serde_json::json!({"systemMessage": system_message});
-                    CliOutput { exit_code, stdout: vec![json.to_string()], stderr: vec![] }
+            if outputs_json && result.messages.first().is_some_and(|m| m.starts_with('{')) {
+                return CliOutput { exit_code, stdout: result.messages, stderr: vec![] };
+            }
+
+            // The reason code goes in the JSON on stdout. When blocking, Claude Code
+            // ignores stdout and shows the messages on stderr to the agent.
+            let mut json = result.reason_json();
+            let stderr = if outputs_json {
+                if !result.messages.is_empty() {
+                    json.insert("systemMessage".to_string(), result.messages.join("\n").into());
                 }
+                vec![]
             } else {
-                CliOutput { exit_code, stdout: vec![], stderr: result.messages }
-            }
+                result.messages
+            };
+            let stdout = if json.is_empty() {
+                vec![]
+            } else {
+                vec![serde_json::Value::Object(json).to_string()]
+            };
+            CliOutput { exit_code, stdout, stderr }
         }
-        Err(e) => CliOutput {
-            exit_code: ExitCode::from(1),
-            stdout: vec![],
-            stderr: vec![format!("Error running stop hook: {e}")],
-        },
+        Err(e) => hook_error_output(&e, "running stop hook", config.failure_mode),
+    }
+}
+
+/// Build the stop hook configuration from the project config.
+fn stop_hook_config(project_config: config::ProjectConfig) -> StopHookConfig {
+    let auto_work_schedule =
+        project_config.auto_work_hours.as_deref().and_then(|hours| match hours.parse() {
+            Ok(schedule) => Some(schedule),
+            Err(e) => {
+                tracing::warn!("Ignoring auto_work_hours: {e}");
+                None
+            }
+        });
+    StopHookConfig {
+        git_repo: project_config.git_repo,
+        quality_check_enabled: project_config.check_command.is_some(),
+        quality_check_options: project_config.check_run_options(),
+        quality_check_progress: project_config.check_progress,
+        quality_check_command: project_config.check_command,
+        require_push: project_config.require_push,
+        base_dir: None,
+        explain_stops: project_config.explain_stops,
+        auto_work_on_tasks: project_config.auto_work_on_tasks,
+        auto_work_idle_minutes: project_config.auto_work_idle_minutes,
+        single_work_item_id: crate::single_work_item::assigned_work_item_id(Path::new(".")),
+        block_escalation_threshold: project_config.block_escalation_threshold,
+        injection_loop_limit: project_config.injection_loop_limit,
+        repeated_output_limit: project_config.repeated_output_limit,
+        max_session_cost_usd: project_config.max_session_cost_usd,
+        packages: project_config.packages,
+        branch_rules: project_config.branch_rules,
+        model_rules: project_config.model_rules,
+        branch: None,
+        wip_commits: false,
+        auto_wip_commit: project_config.auto_wip_commit,
+        auto_push: project_config.auto_push,
+        commit_policy: project_config.commit_policy,
+        git_status: project_config.git_status,
+        require_pr: project_config.require_pr,
+        handoff_summaries: project_config.handoff_summaries,
+        commit_split_files: project_config.commit_split_files,
+        commit_split_lines: project_config.commit_split_lines,
+        generated_files: project_config.generated_files,
+        sensitive_files: project_config.sensitive_files,
+        binary_files: project_config.binary_files,
+        message_verbosity: project_config.message_verbosity,
+        context_budget: project_config.context_budget,
+        tag_blocks: project_config.tag_blocks,
+        assume_after_hours: project_config.assume_after_hours,
+        require_commit_approval: !project_config.auto_confirm_commit,
+        require_push_approval: !project_config.auto_confirm_push,
+        require_continue_approval: !project_config.auto_answer_continue,
+        user_recency_minutes: Some(project_config.user_recency_minutes),
+        api_error_threshold: Some(project_config.api_error_threshold),
+        max_files_to_show: Some(project_config.max_files_to_show),
+        auto_work_decline_limit: Some(project_config.auto_work_decline_limit),
+        auto_work_cooldown_minutes: Some(project_config.auto_work_cooldown_minutes),
+        auto_work_schedule,
+        auto_work_max_prompts: project_config.auto_work_max_prompts,
+        auto_work_check_in_every: project_config.auto_work_check_in_every,
+        failure_mode: project_config.failure_mode,
+        escalation: project_config.escalation,
+        stop_checks: project_config.stop_checks,
+        vcs: vcs::resolve(project_config.vcs, Path::new(".")),
+        idle_source: project_config.idle_source,
+        idle_heartbeat_file: project_config.idle_heartbeat_file,
+        deadline: Deadline::from_env_or_config(project_config.hook_timeout_seconds),
+    }
+}
+
+fn run_session_start_cmd(stdin: &str) -> CliOutput {
+    let input: SessionStartInput = serde_json::from_str(stdin).unwrap_or_default();
+
+    let output = run_session_start_hook(&input, None);
+    if output.hook_specific_output.is_some() {
+        let json = serde_json::to_string(&output).expect("output should serialize");
+        CliOutput { exit_code: ExitCode::SUCCESS, stdout: vec![json], stderr: vec![] }
+    } else {
+        CliOutput { exit_code: ExitCode::SUCCESS, stdout: vec![], stderr: vec![] }
     }
 }
 
@@ -263,11 +595,7 @@ fn run_user_prompt_submit_cmd(stdin: &str) -> CliOutput {
                 CliOutput { exit_code: ExitCode::SUCCESS, stdout: vec![], stderr: vec![] }
             }
         }
-        Err(e) => CliOutput {
-            exit_code: ExitCode::from(1),
-            stdout: vec![],
-            stderr: vec![format!("Error running user-prompt-submit hook: {e}")],
-        },
+        Err(e) => hook_error_output(&e, "running user-prompt-submit hook", failure_mode()),
     }
 }
 
@@ -302,9 +630,13 @@ fn run_post_tool_use_cmd(stdin: &str) -> CliOutput {
         }
     };
 
-    match run_post_tool_use(&input, Path::new(".")) {
+    let runner = RealCommandRunner::new();
+    match run_post_tool_use(&input, Path::new("."), &runner) {
         Ok(()) => CliOutput { exit_code: ExitCode::SUCCESS, stdout: vec![], stderr: vec![] },
-        Err(e) => CliOutput { exit_code: ExitCode::from(1), stdout: vec![], stderr: vec![e] },
+        Err(e) => {
+            let exit_code = if failure_mode().allows() { 1 } else { 2 };
+            CliOutput { exit_code: ExitCode::from(exit_code), stdout: vec![], stderr: vec![e] }
+        }
     }
 }
 
@@ -317,48 +649,174 @@ fn run_work_cmd(cmd: WorkCommand) -> CliOutput {
     };
 
     match cmd {
-        WorkCommand::Create { title, description, priority } => {
-            work_create(&store, &title, &description, priority)
+        WorkCommand::Create { title, description, priority, package, force, template } => {
+            let args = WorkCreateArgs { title, description, priority, package, force, template };
+            work_create(&store, args)
         }
         WorkCommand::Get { id } => work_get(&store, &id),
-        WorkCommand::Update { id, title, description, priority, status } => {
-            work_update(&store, &id, title, description, priority, status.as_ref())
+        WorkCommand::Update { id, title, description, priority, status, package, confirm_dod } => {
+            let args =
+                WorkUpdateArgs { title, description, priority, status, package, confirm_dod };
+            work_update(&store, &id, args)
         }
         WorkCommand::Delete { id } => work_delete(&store, &id),
-        WorkCommand::List { status, priority, max_priority, ready_only, limit, offset } => {
-            work_list(&store, status.as_ref(), priority, max_priority, ready_only, limit, offset)
+        WorkCommand::List {
+            status,
+            priority,
+            max_priority,
+            ready_only,
+            limit,
+            offset,
+            package,
+            waiting,
+        } => {
+            let args = WorkListArgs {
+                status,
+                priority,
+                max_priority,
+                ready_only,
+                limit,
+                offset,
+                package,
+                waiting,
+            };
+            work_list(&store, args)
         }
         WorkCommand::Search { query, limit } => work_search(&store, &query, limit),
         WorkCommand::Next => work_next(&store),
         WorkCommand::On { id } => work_on(&store, &id),
+        WorkCommand::Assign { id, chain, spawn, clear } => {
+            work_assign(id.as_deref().filter(|_| !clear), chain, spawn)
+        }
         WorkCommand::Request { ids } => work_request(&store, &ids),
         WorkCommand::RequestAll => work_request_all(&store),
         WorkCommand::Incomplete => work_incomplete(&store),
         WorkCommand::Blocked => work_blocked(&store),
         WorkCommand::AddDep { id, depends_on } => work_add_dep(&store, &id, &depends_on),
         WorkCommand::RemoveDep { id, depends_on } => work_remove_dep(&store, &id, &depends_on),
+        WorkCommand::Merge { id, other } => work_merge(&store, &id, &other),
+        WorkCommand::Split { id, titles } => work_split(&store, &id, &titles),
+        WorkCommand::Check { id, add, done } => work_check(&store, &id, &add, &done),
         WorkCommand::AddNote { id, content } => work_add_note(&store, &id, &content),
         WorkCommand::Notes { id, limit } => work_notes(&store, &id, limit),
         WorkCommand::LinkHowTo { id, howto_id } => work_link_howto(&store, &id, &howto_id),
         WorkCommand::UnlinkHowTo { id, howto_id } => work_unlink_howto(&store, &id, &howto_id),
+        WorkCommand::Snooze { duration, clear } => work_snooze(duration.as_deref(), clear),
+        WorkCommand::Graph { format } => work_graph(&store, &format),
+        WorkCommand::Import { format, file } => work_import(&store, &format, &file),
+        WorkCommand::Blockers => work_blockers(&store),
+        WorkCommand::Template(cmd) => run_template_cmd(&store, cmd),
     }
 }
 
-fn work_create(store: &SqliteTaskStore, title: &str, description: &str, priority: u8) -> CliOutput {
-    let priority = match Priority::from_u8(priority) {
+/// A new work item for `work create`, as given on the command line.
+struct WorkCreateArgs {
+    title: String,
+    description: String,
+    priority: Option<u8>,
+    package: Option<String>,
+    force: bool,
+    template: Option<String>,
+}
+
+fn work_create(store: &SqliteTaskStore, args: WorkCreateArgs) -> CliOutput {
+    let WorkCreateArgs { title, description, priority, package, force, template } = args;
+    let priority = match priority.map(Priority::from_u8).transpose() {
         Ok(p) => p,
         Err(e) => return error_output(e.to_string()),
     };
 
-    match store.create_task(title, description, priority) {
+    if !force {
+        match find_similar_open_tasks(store, &title) {
+            Ok(similar) if !similar.is_empty() => {
+                let list: Vec<String> =
+                    similar.iter().map(|t| format!("- [{}] {}", t.id, t.title)).collect();
+                return error_output(format!(
+                    "Similar open work items already exist:\n{}\n\n\
+                     Add details to one with `work add-note`, or pass --force to create a new item anyway.",
+                    list.join("\n")
+                ));
+            }
+            Ok(_) => {}
+            Err(e) => return error_output(e.to_string()),
+        }
+    }
+
+    let created = template.map_or_else(
+        || store.create_task(&title, &description, priority.unwrap_or_default()),
+        |template| store.create_from_template(&template, &title, &description, priority),
+    );
+    let created = created.and_then(|task| match package {
+        Some(package) => store
+            .update_task(&task.id, TaskUpdate { package: Some(package), ..Default::default() })
+            .map(|updated| updated.unwrap_or(task)),
+        None => Ok(task),
+    });
+    match created {
         Ok(task) => {
-            let output = WorkItemOutput::from_task(&task, vec![], vec![]);
+            let guidance = store.get_task_guidance(&task.id).unwrap_or_default();
+            let output = WorkItemOutput::from_task(&task, vec![], guidance);
             json_output(&output)
         }
         Err(e) => error_output(e.to_string()),
     }
 }
 
+fn run_template_cmd(store: &SqliteTaskStore, cmd: TemplateCommand) -> CliOutput {
+    match cmd {
+        TemplateCommand::Save { name, description, priority, checklist, howtos } => {
+            let priority = match Priority::from_u8(priority) {
+                Ok(p) => p,
+                Err(e) => return error_output(e.to_string()),
+            };
+            let template = TaskTemplate { name, description, priority, checklist, howtos };
+            match store.save_template(&template) {
+                Ok(()) => json_output(&template),
+                Err(e) => error_output(e.to_string()),
+            }
+        }
+        TemplateCommand::Show { name } => match store.get_template(&name) {
+            Ok(Some(template)) => json_output(&template),
+            Ok(None) => error_output(format!("Template not found: {name}")),
+            Err(e) => error_output(e.to_string()),
+        },
+        TemplateCommand::List => match store.list_templates() {
+            Ok(templates) => json_output(&templates),
+            Err(e) => error_output(e.to_string()),
+        },
+        TemplateCommand::Delete { name } => match store.delete_template(&name) {
+            Ok(true) => success_output(format!("Template deleted: {name}")),
+            Ok(false) => error_output(format!("Template not found: {name}")),
+            Err(e) => error_output(e.to_string()),
+        },
+        TemplateCommand::Export { name } => template_export(store, name.as_deref()),
+    }
+}
+
+/// Templates in their TOML export format, one `[[template]]` table each.
+#[derive(Debug, Serialize)]
+struct TemplateExport {
+    template: Vec<TaskTemplate>,
+}
+
+fn template_export(store: &SqliteTaskStore, name: Option<&str>) -> CliOutput {
+    let templates = match name {
+        Some(name) => match store.get_template(name) {
+            Ok(Some(template)) => vec![template],
+            Ok(None) => return error_output(format!("Template not found: {name}")),
+            Err(e) => return error_output(e.to_string()),
+        },
+        None => match store.list_templates() {
+            Ok(templates) => templates,
+            Err(e) => return error_output(e.to_string()),
+        },
+    };
+    match toml::to_string(&TemplateExport { template: templates }) {
+        Ok(text) => success_output(text.trim_end().to_string()),
+        Err(e) => error_output(format!("Failed to export templates: {e}")),
+    }
+}
+
 fn work_get(store: &SqliteTaskStore, id: &str) -> CliOutput {
     match store.get_task(id) {
         Ok(Some(task)) => {
@@ -375,6 +833,12 @@ fn work_get(store: &SqliteTaskStore, id: &str) -> CliOutput {
                 task: WorkItemOutput::from_task(&task, deps, guidance),
                 notes: notes.into_iter().map(NoteOutput::from).collect(),
                 howtos,
+                checklist: store
+                    .get_checklist(&task.id)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(ChecklistItemOutput::from)
+                    .collect(),
             };
             json_output(&output)
         }
@@ -383,29 +847,60 @@ fn work_get(store: &SqliteTaskStore, id: &str) -> CliOutput {
     }
 }
 
-fn work_update(
-    store: &SqliteTaskStore,
-    id: &str,
+/// Changes for `work update`, as given on the command line.
+struct WorkUpdateArgs {
     title: Option<String>,
     description: Option<String>,
     priority: Option<u8>,
-    status: Option<&String>,
-) -> CliOutput {
+    status: Option<String>,
+    package: Option<String>,
+    confirm_dod: Vec<String>,
+}
+
+fn work_update(store: &SqliteTaskStore, id: &str, args: WorkUpdateArgs) -> CliOutput {
+    let WorkUpdateArgs { title, description, priority, status, package, confirm_dod } = args;
     let priority = match priority.map(Priority::from_u8).transpose() {
         Ok(p) => p,
         Err(e) => return error_output(e.to_string()),
     };
 
-    let status = match status.map(|s| Status::from_str(s)).transpose() {
+    let status = match status.as_deref().map(Status::from_str).transpose() {
         Ok(s) => s,
         Err(e) => return error_output(e.to_string()),
     };
 
-    let update =
-        TaskUpdate { title, description, priority, status, in_progress: None, requested: None };
+    let mut acknowledged = Vec::new();
+    if status == Some(Status::Complete) {
+        match store.get_task(id) {
+            Ok(Some(task)) if task.status != Status::Complete => {
+                match check_definition_of_done(&confirm_dod) {
+                    Ok(items) => acknowledged = items,
+                    Err(e) => return error_output(format!("Cannot complete {id}. {e}")),
+                }
+            }
+            Ok(_) => {}
+            Err(e) => return error_output(e.to_string()),
+        }
+    }
+
+    let update = TaskUpdate {
+        title,
+        description,
+        priority,
+        status,
+        in_progress: None,
+        requested: None,
+        package,
+    };
 
     match store.update_task(id, update) {
         Ok(Some(task)) => {
+            for item in &acknowledged {
+                let operation = crate::definition_of_done::CONFIRM_OPERATION;
+                if let Err(e) = store.record_task_event(id, operation, item) {
+                    return error_output(e.to_string());
+                }
+            }
             let deps = store.get_dependencies(&task.id).unwrap_or_default();
             let guidance = store.get_task_guidance(&task.id).unwrap_or_default();
             let output = WorkItemOutput::from_task(&task, deps, guidance);
@@ -416,6 +911,25 @@ fn work_update(
     }
 }
 
+/// Check the project's definition of done before completing a work item.
+///
+/// Returns the acknowledged items to record in the audit log.
+fn check_definition_of_done(confirmed: &[String]) -> Result<Vec<String>, String> {
+    use crate::definition_of_done;
+
+    let base_dir = std::env::current_dir().unwrap_or_default();
+    let project_config =
+        config::ProjectConfig::load_from(&base_dir).ok().flatten().unwrap_or_default();
+    if project_config.definition_of_done.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let runner = RealCommandRunner::new();
+    let evidence = definition_of_done::gather_evidence(&base_dir, &project_config, &runner)
+        .map_err(|e| e.to_string())?;
+    definition_of_done::check(&project_config.definition_of_done, &evidence, confirmed)
+}
+
 fn work_delete(store: &SqliteTaskStore, id: &str) -> CliOutput {
     match store.delete_task(id) {
         Ok(true) => success_output(format!("Work item deleted: {id}")),
@@ -424,16 +938,30 @@ fn work_delete(store: &SqliteTaskStore, id: &str) -> CliOutput {
     }
 }
 
-fn work_list(
-    store: &SqliteTaskStore,
-    status: Option<&String>,
+/// Filters for `work list`, as given on the command line.
+struct WorkListArgs {
+    status: Option<String>,
     priority: Option<u8>,
     max_priority: Option<u8>,
     ready_only: bool,
     limit: Option<usize>,
     offset: Option<usize>,
-) -> CliOutput {
-    let status = match status.map(|s| Status::from_str(s)).transpose() {
+    package: Option<String>,
+    waiting: Option<bool>,
+}
+
+fn work_list(store: &SqliteTaskStore, args: WorkListArgs) -> CliOutput {
+    let WorkListArgs {
+        status,
+        priority,
+        max_priority,
+        ready_only,
+        limit,
+        offset,
+        package,
+        waiting,
+    } = args;
+    let status = match status.as_deref().map(Status::from_str).transpose() {
         Ok(s) => s,
         Err(e) => return error_output(e.to_string()),
     };
@@ -455,6 +983,8 @@ fn work_list(
         ready_only,
         limit: Some(limit.unwrap_or(DEFAULT_RESULT_LIMIT)),
         offset,
+        package,
+        waiting,
     };
 
     match store.list_tasks(filter) {
@@ -514,7 +1044,7 @@ fn work_search(store: &SqliteTaskStore, query: &str, limit: Option<usize>) -> Cl
 
 fn work_next(store: &SqliteTaskStore) -> CliOutput {
     // In single work item mode, always return the assigned item (or "no items" if done)
-    if let Some(single_id) = crate::single_work_item::get_single_work_item_id() {
+    if let Some(single_id) = crate::single_work_item::assigned_work_item_id(Path::new(".")) {
         return match store.get_task(&single_id) {
             Ok(Some(task))
                 if !matches!(
@@ -586,7 +1116,7 @@ fn work_next(store: &SqliteTaskStore) -> CliOutput {
 
 fn work_on(store: &SqliteTaskStore, id: &str) -> CliOutput {
     // In single work item mode, reject attempts to work on a different item
-    if let Some(single_id) = crate::single_work_item::get_single_work_item_id() {
+    if let Some(single_id) = crate::single_work_item::assigned_work_item_id(Path::new(".")) {
         if id != single_id {
             return error_output(format!(
                 "Single work item mode: can only work on {single_id}, not {id}"
@@ -625,6 +1155,120 @@ fn work_request_all(store: &SqliteTaskStore) -> CliOutput {
     }
 }
 
+fn work_blockers(store: &SqliteTaskStore) -> CliOutput {
+    match find_blockers(store) {
+        Ok(blockers) if blockers.is_empty() => {
+            success_output("Nothing is blocking the requested work.".to_string())
+        }
+        Ok(blockers) => json_output(&blockers),
+        Err(e) => error_output(e.to_string()),
+    }
+}
+
+fn work_graph(store: &SqliteTaskStore, format: &str) -> CliOutput {
+    let format = match format.parse::<GraphFormat>() {
+        Ok(f) => f,
+        Err(e) => return error_output(e.to_string()),
+    };
+    match render_graph(store, format) {
+        Ok(graph) => success_output(graph.trim_end().to_string()),
+        Err(e) => error_output(e.to_string()),
+    }
+}
+
+fn work_import(store: &SqliteTaskStore, format: &str, file: &Path) -> CliOutput {
+    let format = match format.parse::<ImportFormat>() {
+        Ok(f) => f,
+        Err(e) => return error_output(e.to_string()),
+    };
+    let content = match std::fs::read_to_string(file) {
+        Ok(content) => content,
+        Err(e) => return error_output(format!("Failed to read {}: {e}", file.display())),
+    };
+    match import(store, format, &content) {
+        Ok(report) => json_output(&report),
+        Err(e) => error_output(format!("Import failed: {e}")),
+    }
+}
+
+fn work_assign(id: Option<&str>, chain: bool, spawn: bool) -> CliOutput {
+    let base_dir = Path::new(".");
+    let Some(id) = id else {
+        return match session::clear_work_item_assignment(base_dir) {
+            Ok(true) => success_output("Work item assignment removed.".to_string()),
+            Ok(false) => success_output("No work item was assigned.".to_string()),
+            Err(e) => error_output(e.to_string()),
+        };
+    };
+
+    let (id, title) = match crate::single_work_item::assign(base_dir, id, chain) {
+        Ok(assigned) => assigned,
+        Err(e) => return error_output(e),
+    };
+    let chained = if chain { ", chaining on to the next ready item when done" } else { "" };
+    let message = format!("Assigned {id}: {title}{chained}.");
+    if !spawn {
+        return success_output(message);
+    }
+
+    let prompt = format!(
+        "Work on work item {id} ({title}) until it is complete. \
+         Start with `claude-reliability work on {id}`."
+    );
+    match std::process::Command::new("claude").arg(prompt).status() {
+        Ok(status) if status.success() => success_output(message),
+        Ok(status) => error_output(format!("{message} The Claude session exited with {status}.")),
+        Err(e) => error_output(format!("{message} Failed to start Claude: {e}")),
+    }
+}
+
+fn work_snooze(duration: Option<&str>, clear: bool) -> CliOutput {
+    let base_dir = Path::new(".");
+    if clear {
+        return match session::resume_auto_work(base_dir) {
+            Ok(()) => success_output("Auto-work prompting resumed.".to_string()),
+            Err(e) => error_output(e.to_string()),
+        };
+    }
+
+    let minutes = match parse_duration_minutes(duration.unwrap_or_default()) {
+        Ok(m) => m,
+        Err(e) => return error_output(e),
+    };
+    let until = chrono::Utc::now() + chrono::Duration::minutes(i64::from(minutes));
+    match session::pause_auto_work(base_dir, until) {
+        Ok(()) => success_output(format!(
+            "Auto-work prompting snoozed until {}.",
+            until.format("%Y-%m-%d %H:%M UTC")
+        )),
+        Err(e) => error_output(e.to_string()),
+    }
+}
+
+/// Parse a duration such as `30m`, `2h` or `1d` into minutes. Plain numbers are minutes.
+///
+/// # Errors
+///
+/// Returns an error if the duration is not a positive number with a known unit.
+pub fn parse_duration_minutes(duration: &str) -> Result<u32, String> {
+    let duration = duration.trim();
+    let (number, unit) = duration
+        .find(|c: char| !c.is_ascii_digit())
+        .map_or((duration, "m"), |idx| duration.split_at(idx));
+    let multiplier = match unit.trim() {
+        "m" | "min" | "mins" | "minutes" => 1,
+        "h" | "hr" | "hrs" | "hours" => 60,
+        "d" | "day" | "days" => 24 * 60,
+        _ => return Err(format!("Invalid duration '{duration}': use e.g. 30m, 2h or 1d")),
+    };
+    number
+        .parse::<u32>()
+        .ok()
+        .and_then(|n| n.checked_mul(multiplier))
+        .filter(|&minutes| minutes > 0)
+        .ok_or_else(|| format!("Invalid duration '{duration}': use e.g. 30m, 2h or 1d"))
+}
+
 fn work_incomplete(store: &SqliteTaskStore) -> CliOutput {
     match store.get_incomplete_requested_work() {
         Ok(tasks) if tasks.is_empty() => success_output(
@@ -689,25 +1333,79 @@ fn work_remove_dep(store: &SqliteTaskStore, id: &str, depends_on: &str) -> CliOu
     }
 }
 
-fn work_add_note(store: &SqliteTaskStore, id: &str, content: &str) -> CliOutput {
-    match store.add_note(id, content) {
-        Ok(note) => {
-            let output = NoteWithWorkItemOutput {
-                id: note.id,
-                work_item_id: note.task_id,
-                content: note.content,
-                created_at: note.created_at,
-            };
-            json_output(&output)
+fn work_merge(store: &SqliteTaskStore, id: &str, other: &str) -> CliOutput {
+    match store.merge_tasks(id, other) {
+        Ok(task) => {
+            let deps = store.get_dependencies(&task.id).unwrap_or_default();
+            let guidance = store.get_task_guidance(&task.id).unwrap_or_default();
+            json_output(&WorkItemOutput::from_task(&task, deps, guidance))
         }
         Err(e) => error_output(e.to_string()),
     }
 }
 
-fn work_notes(store: &SqliteTaskStore, id: &str, limit: Option<usize>) -> CliOutput {
-    match store.get_notes(id) {
-        Ok(notes) => {
-            let max = limit.unwrap_or(DEFAULT_RESULT_LIMIT);
+fn work_split(store: &SqliteTaskStore, id: &str, titles: &[String]) -> CliOutput {
+    match store.split_task(id, titles) {
+        Ok(subtasks) => {
+            let outputs: Vec<WorkItemOutput> = subtasks
+                .iter()
+                .map(|t| {
+                    let deps = store.get_dependencies(&t.id).unwrap_or_default();
+                    let guidance = store.get_task_guidance(&t.id).unwrap_or_default();
+                    WorkItemOutput::from_task(t, deps, guidance)
+                })
+                .collect();
+            json_output(&outputs)
+        }
+        Err(e) => error_output(e.to_string()),
+    }
+}
+
+fn work_check(store: &SqliteTaskStore, id: &str, add: &[String], done: &[u32]) -> CliOutput {
+    match store.get_task(id) {
+        Ok(Some(_)) => {}
+        Ok(None) => return error_output(format!("Work item not found: {id}")),
+        Err(e) => return error_output(e.to_string()),
+    }
+    for text in add {
+        if let Err(e) = store.add_checklist_item(id, text) {
+            return error_output(e.to_string());
+        }
+    }
+    for &position in done {
+        if let Err(e) = store.check_checklist_item(id, position) {
+            return error_output(e.to_string());
+        }
+    }
+    match store.get_checklist(id) {
+        Ok(items) => {
+            let outputs: Vec<ChecklistItemOutput> =
+                items.into_iter().map(ChecklistItemOutput::from).collect();
+            json_output(&outputs)
+        }
+        Err(e) => error_output(e.to_string()),
+    }
+}
+
+fn work_add_note(store: &SqliteTaskStore, id: &str, content: &str) -> CliOutput {
+    match store.add_note(id, content) {
+        Ok(note) => {
+            let output = NoteWithWorkItemOutput {
+                id: note.id,
+                work_item_id: note.task_id,
+                content: note.content,
+                created_at: note.created_at,
+            };
+            json_output(&output)
+        }
+        Err(e) => error_output(e.to_string()),
+    }
+}
+
+fn work_notes(store: &SqliteTaskStore, id: &str, limit: Option<usize>) -> CliOutput {
+    match store.get_notes(id) {
+        Ok(notes) => {
+            let max = limit.unwrap_or(DEFAULT_RESULT_LIMIT);
             let outputs: Vec<NoteOutput> =
                 notes.into_iter().take(max).map(NoteOutput::from).collect();
             json_output(&outputs)
@@ -821,9 +1519,20 @@ fn run_question_cmd(cmd: QuestionCommand) -> CliOutput {
     };
 
     match cmd {
-        QuestionCommand::Create { text } => question_create(&store, &text),
+        QuestionCommand::Create { text, priority } => question_create(&store, &text, priority),
+        QuestionCommand::SetPriority { id, priority } => {
+            question_set_priority(&store, &id, priority)
+        }
         QuestionCommand::Get { id } => question_get(&store, &id),
-        QuestionCommand::Answer { id, answer } => question_answer(&store, &id, &answer),
+        QuestionCommand::Answer { id, answer, pick } => answer.map_or_else(
+            || question_pick(&store, &id, pick.unwrap_or_default()),
+            |answer| question_answer(&store, &id, &answer),
+        ),
+        QuestionCommand::AnswerAll => question_answer_all(&store),
+        QuestionCommand::Defer { id, until, clear: _ } => {
+            question_defer(&store, &id, until.as_deref())
+        }
+        QuestionCommand::Reopen { id } => question_reopen(&store, &id),
         QuestionCommand::Delete { id } => question_delete(&store, &id),
         QuestionCommand::List { unanswered_only, limit } => {
             question_list(&store, unanswered_only, limit)
@@ -836,12 +1545,65 @@ fn run_question_cmd(cmd: QuestionCommand) -> CliOutput {
             question_unlink(&store, &work_id, &question_id)
         }
         QuestionCommand::Blocking { work_id } => question_blocking(&store, &work_id),
+        QuestionCommand::Sync => question_sync(&store),
     }
 }
 
-fn question_create(store: &SqliteTaskStore, text: &str) -> CliOutput {
+fn question_sync(store: &SqliteTaskStore) -> CliOutput {
+    let base_dir = Path::new(".");
+    let delivery = config::ProjectConfig::load_from(base_dir)
+        .ok()
+        .flatten()
+        .unwrap_or_default()
+        .question_delivery;
+    if !delivery.is_enabled() && delivery.reply_maildir.is_none() {
+        return error_output(format!(
+            "No question delivery configured. Set question_delivery.github_issue or \
+             question_delivery.email_to in {}.",
+            config::CONFIG_FILE_PATH
+        ));
+    }
+    let runner = RealCommandRunner::new();
+    let synced = crate::storage::SqliteStore::new(base_dir).and_then(|state| {
+        crate::question_delivery::sync(base_dir, &delivery, store, &state, &runner)
+    });
+    match synced {
+        Ok(report) if report.errors.is_empty() => json_output(&report),
+        Ok(report) => {
+            let mut output = json_output(&report);
+            output.exit_code = ExitCode::from(1);
+            output
+        }
+        Err(e) => error_output(format!("Question sync failed: {e}")),
+    }
+}
+
+/// Send new questions on the configured delivery channels, ignoring failures;
+/// `question sync` retries them.
+fn deliver_new_questions(store: &SqliteTaskStore) {
+    let base_dir = Path::new(".");
+    let delivery = config::ProjectConfig::load_from(base_dir)
+        .ok()
+        .flatten()
+        .unwrap_or_default()
+        .question_delivery;
+    if !delivery.is_enabled() {
+        return;
+    }
+    let runner = RealCommandRunner::new();
+    if let Ok(state) = crate::storage::SqliteStore::new(base_dir) {
+        let _ = crate::question_delivery::deliver_pending(&delivery, store, &state, &runner);
+    }
+}
+
+fn question_create(store: &SqliteTaskStore, text: &str, priority: Option<u8>) -> CliOutput {
     use crate::traits::{CreateQuestionContext, CreateQuestionDecision, SubAgent as _};
 
+    let priority = match priority.map(Priority::from_u8).transpose() {
+        Ok(p) => p.unwrap_or_default(),
+        Err(e) => return error_output(e.to_string()),
+    };
+
     // Evaluate whether this question can be auto-answered
     let runner = RealCommandRunner::new();
     let sub_agent = RealSubAgent::new(&runner);
@@ -856,8 +1618,58 @@ fn question_create(store: &SqliteTaskStore, text: &str) -> CliOutput {
         Ok(CreateQuestionDecision::Create) | Err(_) => {}
     }
 
-    match store.create_question(text) {
-        Ok(question) => json_output(&QuestionOutput::from(&question)),
+    let created = store.create_question(text).and_then(|question| {
+        if priority == question.priority {
+            return Ok(question);
+        }
+        let id = question.id.clone();
+        store.set_question_priority(&id, priority).map(|updated| updated.unwrap_or(question))
+    });
+    let created = created.and_then(|question| suggest_question_answers(store, question));
+    match created {
+        Ok(question) => {
+            deliver_new_questions(store);
+            json_output(&QuestionOutput::from(&question))
+        }
+        Err(e) => error_output(e.to_string()),
+    }
+}
+
+/// Store sub-agent drafted candidate answers with a new question, if enabled.
+///
+/// Best effort: the question is returned unchanged if the sub-agent fails or
+/// has nothing to suggest.
+fn suggest_question_answers(
+    store: &SqliteTaskStore,
+    question: crate::tasks::Question,
+) -> crate::error::Result<crate::tasks::Question> {
+    use crate::traits::SubAgent as _;
+
+    let config =
+        config::ProjectConfig::load_from(Path::new(".")).ok().flatten().unwrap_or_default();
+    if !config.suggest_answers {
+        return Ok(question);
+    }
+    let runner = RealCommandRunner::new();
+    let sub_agent = RealSubAgent::new(&runner);
+    match sub_agent.suggest_answers(&question.text) {
+        Ok(suggestions) if !suggestions.is_empty() => {
+            let id = question.id.clone();
+            let updated = store.set_question_suggestions(&id, &suggestions)?;
+            Ok(updated.unwrap_or(question))
+        }
+        _ => Ok(question),
+    }
+}
+
+fn question_set_priority(store: &SqliteTaskStore, id: &str, priority: u8) -> CliOutput {
+    let priority = match Priority::from_u8(priority) {
+        Ok(p) => p,
+        Err(e) => return error_output(e.to_string()),
+    };
+    match store.set_question_priority(id, priority) {
+        Ok(Some(q)) => json_output(&QuestionOutput::from(&q)),
+        Ok(None) => error_output(format!("Question not found: {id}")),
         Err(e) => error_output(e.to_string()),
     }
 }
@@ -878,6 +1690,110 @@ fn question_answer(store: &SqliteTaskStore, id: &str, answer: &str) -> CliOutput
     }
 }
 
+fn question_pick(store: &SqliteTaskStore, id: &str, pick: usize) -> CliOutput {
+    let question = match store.get_question(id) {
+        Ok(Some(q)) => q,
+        Ok(None) => return error_output(format!("Question not found: {id}")),
+        Err(e) => return error_output(e.to_string()),
+    };
+    match pick.checked_sub(1).and_then(|index| question.suggestions.get(index)) {
+        Some(suggestion) => question_answer(store, id, &suggestion.answer),
+        None => error_output(format!(
+            "Question {id} has {} suggested answer(s); cannot pick {pick}",
+            question.suggestions.len()
+        )),
+    }
+}
+
+fn question_answer_all(store: &SqliteTaskStore) -> CliOutput {
+    use crate::tasks::answers;
+
+    let questions = match answers::pending_questions(store)
+        .and_then(|questions| answers::rank_by_impact(store, questions))
+    {
+        Ok(q) if q.is_empty() => return success_output("No unanswered questions.".to_string()),
+        Ok(q) => q.into_iter().map(|ranked| ranked.question).collect::<Vec<_>>(),
+        Err(e) => return error_output(e.to_string()),
+    };
+    let path =
+        std::env::temp_dir().join(format!("claude-reliability-answers-{}.md", std::process::id()));
+    if let Err(e) = std::fs::write(&path, answers::answer_document(&questions)) {
+        return error_output(format!("Failed to write {}: {e}", path.display()));
+    }
+
+    let edited = edit_file(&path).and_then(|()| {
+        std::fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {e}", path.display()))
+    });
+    let _ = std::fs::remove_file(&path);
+    match edited.map(|document| answers::apply_answers(store, &document)) {
+        Ok(Ok(report)) => json_output(&report),
+        Ok(Err(e)) => error_output(e.to_string()),
+        Err(e) => error_output(e),
+    }
+}
+
+/// Open a file in `$VISUAL` or `$EDITOR` (falling back to `vi`) and wait for it to close.
+fn edit_file(path: &Path) -> Result<(), String> {
+    let editor = ["VISUAL", "EDITOR"]
+        .iter()
+        .find_map(|var| std::env::var(var).ok().filter(|e| !e.trim().is_empty()))
+        .unwrap_or_else(|| "vi".to_string());
+    // Run through the shell so editors configured with arguments (`code --wait`) work
+    let status = std::process::Command::new("sh")
+        .arg("-c")
+        .arg(format!("{editor} \"$1\""))
+        .arg("sh")
+        .arg(path)
+        .status()
+        .map_err(|e| format!("Failed to run editor '{editor}': {e}"))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("Editor '{editor}' exited with {status}; no answers were saved"))
+    }
+}
+
+fn question_defer(store: &SqliteTaskStore, id: &str, until: Option<&str>) -> CliOutput {
+    let until = match until.map(parse_until).transpose() {
+        Ok(u) => u,
+        Err(e) => return error_output(e),
+    };
+    match store.defer_question(id, until.as_deref()) {
+        Ok(Some(q)) => json_output(&QuestionOutput::from(&q)),
+        Ok(None) => error_output(format!("Question not found: {id}")),
+        Err(e) => error_output(e.to_string()),
+    }
+}
+
+fn question_reopen(store: &SqliteTaskStore, id: &str) -> CliOutput {
+    match store.reopen_question(id) {
+        Ok(Some(q)) => json_output(&QuestionOutput::from(&q)),
+        Ok(None) => error_output(format!("Question not found: {id}")),
+        Err(e) => error_output(e.to_string()),
+    }
+}
+
+/// Parse a duration from now (`3d`) or a UTC date (`2026-11-02`) or time
+/// (`2026-11-02 09:00`) into a database timestamp.
+fn parse_until(value: &str) -> Result<String, String> {
+    const FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+    let value = value.trim();
+    if let Ok(date) = chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(date.and_time(chrono::NaiveTime::MIN).format(FORMAT).to_string());
+    }
+    for format in ["%Y-%m-%d %H:%M", "%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M", "%Y-%m-%dT%H:%M:%S"] {
+        if let Ok(time) = chrono::NaiveDateTime::parse_from_str(value, format) {
+            return Ok(time.format(FORMAT).to_string());
+        }
+    }
+    let minutes = parse_duration_minutes(value).map_err(|_| {
+        format!("Invalid time '{value}': use a duration such as 3d or a date such as 2026-11-02")
+    })?;
+    let until = chrono::Utc::now() + chrono::Duration::minutes(i64::from(minutes));
+    Ok(until.format(FORMAT).to_string())
+}
+
 fn question_delete(store: &SqliteTaskStore, id: &str) -> CliOutput {
     match store.delete_question(id) {
         Ok(true) => success_output(format!("Deleted question: {id}")),
@@ -891,11 +1807,23 @@ fn question_list(
     unanswered_only: bool,
     limit: Option<usize>,
 ) -> CliOutput {
-    match store.list_questions(unanswered_only) {
-        Ok(questions) => {
+    use crate::tasks::answers;
+
+    let ranked = store
+        .list_questions(unanswered_only)
+        .and_then(|questions| answers::rank_by_impact(store, questions));
+    match ranked {
+        Ok(ranked) => {
             let max = limit.unwrap_or(DEFAULT_RESULT_LIMIT);
-            let outputs: Vec<QuestionOutput> =
-                questions.iter().take(max).map(QuestionOutput::from).collect();
+            let outputs: Vec<QuestionListItem> = ranked
+                .into_iter()
+                .take(max)
+                .map(|r| QuestionListItem {
+                    question: QuestionOutput::from(&r.question),
+                    blocks_requested: r.blocked_requested.len(),
+                    blocked_requested: r.blocked_requested,
+                })
+                .collect();
             json_output(&outputs)
         }
         Err(e) => error_output(e.to_string()),
@@ -947,6 +1875,713 @@ fn question_blocking(store: &SqliteTaskStore, work_id: &str) -> CliOutput {
 
 // === Audit and Emergency Stop ===
 
+// === Handoff Commands ===
+
+fn run_handoff_cmd(cmd: &HandoffCommand) -> CliOutput {
+    match cmd {
+        HandoffCommand::Latest => handoff_latest(),
+    }
+}
+
+fn handoff_latest() -> CliOutput {
+    let Some(path) = crate::handoff::latest_handoff(Path::new(".")) else {
+        return error_output("No handoff summaries have been written yet".to_string());
+    };
+    match std::fs::read_to_string(&path) {
+        Ok(content) => success_output(content),
+        Err(e) => error_output(format!("Failed to read {}: {e}", path.display())),
+    }
+}
+
+// === Maintenance Commands ===
+
+fn run_maintenance_cmd(cmd: &MaintenanceCommand) -> CliOutput {
+    match cmd {
+        MaintenanceCommand::Run => match crate::maintenance::run(Path::new(".")) {
+            Ok(report) => json_output(&report),
+            Err(e) => error_output(format!("Maintenance failed: {e}")),
+        },
+    }
+}
+
+// === Backup Commands ===
+
+fn run_backup_cmd(cmd: &BackupCommand) -> CliOutput {
+    let base_dir = Path::new(".");
+    match cmd {
+        BackupCommand::Now => {
+            let keep = config::ProjectConfig::load_from(base_dir)
+                .ok()
+                .flatten()
+                .unwrap_or_default()
+                .backup_keep;
+            match crate::backup::create(base_dir, keep) {
+                Ok(backup) => json_output(&backup),
+                Err(e) => error_output(format!("Backup failed: {e}")),
+            }
+        }
+        BackupCommand::List => match crate::backup::list(base_dir) {
+            Ok(backups) => json_output(&backups),
+            Err(e) => error_output(format!("Failed to list backups: {e}")),
+        },
+        BackupCommand::Restore { file } => match crate::backup::restore(base_dir, file) {
+            Ok(report) => json_output(&report),
+            Err(e) => error_output(format!("Restore failed: {e}")),
+        },
+    }
+}
+
+// === Session Commands ===
+
+/// A session with its liveness, for `session list`.
+#[derive(Serialize)]
+struct SessionOutput {
+    #[serde(flatten)]
+    session: SessionRecord,
+    alive: bool,
+}
+
+/// A reported problem with how often its work item has hit problems, for
+/// `session problems`.
+#[derive(Serialize)]
+struct ProblemOutput {
+    #[serde(flatten)]
+    problem: Problem,
+    task_problem_count: usize,
+}
+
+fn run_session_cmd(cmd: &SessionCommand) -> CliOutput {
+    match cmd {
+        SessionCommand::List { alive } => session_list(*alive),
+        SessionCommand::Scrub => session_scrub(),
+        SessionCommand::Reset { marker } => session_reset(marker.as_deref()),
+        SessionCommand::Problems { limit } => session_problems(*limit),
+        SessionCommand::Pause { reason } => session_pause(reason),
+    }
+}
+
+fn run_transcript_cmd(cmd: &TranscriptCommand) -> CliOutput {
+    match cmd {
+        TranscriptCommand::Search { query, session, limit } => {
+            transcript_search(query, session.as_deref(), *limit)
+        }
+        TranscriptCommand::Export { path, format, output } => {
+            transcript_export(path, format, output.as_deref())
+        }
+    }
+}
+
+fn transcript_export(path: &Path, format: &str, output: Option<&Path>) -> CliOutput {
+    use crate::transcript_export::{export, TranscriptFormat};
+
+    let format = match format.parse::<TranscriptFormat>() {
+        Ok(f) => f,
+        Err(e) => return error_output(e.to_string()),
+    };
+    let rendered = match export(path, format) {
+        Ok(r) => r,
+        Err(e) => return error_output(format!("Export failed: {e}")),
+    };
+    let Some(output) = output else {
+        return success_output(rendered.trim_end().to_string());
+    };
+    match std::fs::write(output, rendered) {
+        Ok(()) => success_output(format!("Transcript written to {}", output.display())),
+        Err(e) => error_output(format!("Failed to write {}: {e}", output.display())),
+    }
+}
+
+fn transcript_search(query: &str, session_id: Option<&str>, limit: Option<usize>) -> CliOutput {
+    use crate::transcript_index;
+
+    if query.trim().is_empty() {
+        return error_output("Search query must not be empty".to_string());
+    }
+    let state = match crate::storage::SqliteStore::new(Path::new(".")) {
+        Ok(s) => s,
+        Err(e) => return error_output(e.to_string()),
+    };
+    if let Err(e) = transcript_index::index_sessions(&state) {
+        return error_output(format!("Failed to index transcripts: {e}"));
+    }
+    let limit = limit.unwrap_or(transcript_index::DEFAULT_LIMIT);
+    match transcript_index::search(&state, query, session_id, limit) {
+        Ok(matches) => json_output(&matches),
+        Err(e) => error_output(e.to_string()),
+    }
+}
+
+fn session_reset(marker: Option<&str>) -> CliOutput {
+    let markers = match marker.map(|name| (name, Marker::from_name(name))) {
+        None => Marker::ALL.to_vec(),
+        Some((_, Some(marker))) => vec![marker],
+        Some((name, None)) => {
+            let known: Vec<&str> = Marker::ALL.iter().map(|m| m.as_str()).collect();
+            return error_output(format!(
+                "Unknown marker '{name}'. Known markers: {}",
+                known.join(", ")
+            ));
+        }
+    };
+    let base_dir = Path::new(".");
+    let cleared: Vec<&str> = match session::active_markers(base_dir) {
+        Ok(active) => {
+            active.into_iter().filter(|m| markers.contains(m)).map(Marker::as_str).collect()
+        }
+        Err(e) => return error_output(e.to_string()),
+    };
+    if let Err(e) = session::clear_markers(base_dir, &markers) {
+        return error_output(e.to_string());
+    }
+    if cleared.is_empty() {
+        success_output("No markers were set".to_string())
+    } else {
+        success_output(format!("Cleared markers: {}", cleared.join(", ")))
+    }
+}
+
+fn session_pause(reason: &str) -> CliOutput {
+    let reason = reason.trim();
+    if reason.is_empty() {
+        return error_output("A reason for the pause is required".to_string());
+    }
+    match session::request_pause(Path::new("."), reason) {
+        Ok(()) => success_output(
+            "Session paused. The next stop will be allowed, and the next session will resume the pending work."
+                .to_string(),
+        ),
+        Err(e) => error_output(e.to_string()),
+    }
+}
+
+fn session_scrub() -> CliOutput {
+    let messages =
+        match open_store().and_then(|s| s.scrub_user_messages().map_err(|e| e.to_string())) {
+            Ok(n) => n,
+            Err(e) => return error_output(e),
+        };
+    let log_entries = match subagent_logging::scrub_subagent_log(Path::new(".")) {
+        Ok(n) => n,
+        Err(e) => return error_output(e.to_string()),
+    };
+    success_output(format!(
+        "Redacted secrets in {messages} user message(s) and {log_entries} sub-agent log entry(ies)"
+    ))
+}
+
+fn session_list(alive_only: bool) -> CliOutput {
+    let sessions = match session::list_sessions(Path::new(".")) {
+        Ok(s) => s,
+        Err(e) => return error_output(e.to_string()),
+    };
+    let now = chrono::Utc::now();
+    let output: Vec<SessionOutput> = sessions
+        .into_iter()
+        .map(|session| {
+            let alive = session::is_session_alive(&session, now);
+            SessionOutput { session, alive }
+        })
+        .filter(|s| s.alive || !alive_only)
+        .collect();
+    json_output(&output)
+}
+
+fn session_problems(limit: usize) -> CliOutput {
+    let Some(store) = tasks::open_project_store(Path::new(".")) else {
+        return json_output(&Vec::<ProblemOutput>::new());
+    };
+    let all = match store.list_problems(None) {
+        Ok(p) => p,
+        Err(e) => return error_output(e.to_string()),
+    };
+    let output: Vec<ProblemOutput> = all
+        .iter()
+        .take(limit)
+        .map(|problem| ProblemOutput {
+            task_problem_count: problem
+                .task_id
+                .as_ref()
+                .map_or(0, |id| all.iter().filter(|p| p.task_id.as_ref() == Some(id)).count()),
+            problem: problem.clone(),
+        })
+        .collect();
+    json_output(&output)
+}
+
+fn run_metrics() -> CliOutput {
+    match metrics::render(Path::new(".")) {
+        Ok(text) => success_output(text.trim_end().to_string()),
+        Err(e) => error_output(e.to_string()),
+    }
+}
+
+fn run_report(since: &str, narrate: bool) -> CliOutput {
+    let minutes = match parse_duration_minutes(since) {
+        Ok(m) => m,
+        Err(e) => return error_output(e),
+    };
+    let since = chrono::Utc::now() - chrono::Duration::minutes(i64::from(minutes));
+    let runner = RealCommandRunner::new();
+    let sub_agent = RealSubAgent::new(&runner);
+    let narrator: Option<&dyn crate::traits::SubAgent> = narrate.then_some(&sub_agent);
+    match crate::report::render(Path::new("."), since, narrator) {
+        Ok(markdown) => success_output(markdown.trim_end().to_string()),
+        Err(e) => error_output(format!("Report failed: {e}")),
+    }
+}
+
+/// Describe the switched-off stop checks and tier order for `doctor`.
+fn stop_checks_summary(policy: &config::StopChecksPolicy) -> String {
+    let tiers: Vec<&str> = policy.tiers().iter().map(|tier| tier.name()).collect();
+    let disabled =
+        if policy.disable.is_empty() { "none".to_string() } else { policy.disable.join(", ") };
+    format!("disabled: {disabled}; order: {}", tiers.join(", "))
+}
+
+/// Describe how uncommitted changes are looked for, for `doctor`.
+fn git_status_summary(policy: &config::GitStatusPolicy) -> String {
+    if !policy.is_fast() {
+        return "full".to_string();
+    }
+    let mut parts = vec!["fast".to_string()];
+    if !policy.paths.is_empty() {
+        parts.push(format!("paths: {}", policy.paths.join(", ")));
+    }
+    if policy.cache_untracked {
+        parts.push("untracked cached".to_string());
+    }
+    parts.join("; ")
+}
+
+/// Describe the committer and signatures required for `doctor`.
+fn commit_policy_summary(policy: &config::CommitPolicy) -> String {
+    if !policy.is_enabled() {
+        return "off".to_string();
+    }
+    let mut parts = Vec::new();
+    match (&policy.committer_name, &policy.committer_email) {
+        (Some(name), Some(email)) => parts.push(format!("committer {name} <{email}>")),
+        (Some(name), None) => parts.push(format!("committer {name}")),
+        (None, Some(email)) => parts.push(format!("committer <{email}>")),
+        (None, None) => {}
+    }
+    if policy.require_signature {
+        parts.push("signed".to_string());
+    }
+    parts.join(", ")
+}
+
+/// Describe where blockers are reported for `doctor`.
+fn escalation_summary(policy: &config::EscalationPolicy) -> String {
+    let mut destinations = Vec::new();
+    if policy.github_issues {
+        destinations.push(format!(
+            "github issues in {}",
+            policy.github_repo.as_deref().unwrap_or("this repository")
+        ));
+    }
+    if let Some(file) = &policy.blockers_file {
+        destinations.push(file.clone());
+    }
+    if destinations.is_empty() {
+        "off".to_string()
+    } else {
+        destinations.join(", ")
+    }
+}
+
+/// Describe the configured question delivery channels for `doctor`.
+fn question_delivery_summary(delivery: &config::QuestionDelivery) -> String {
+    let mut channels = Vec::new();
+    if let Some(issue) = &delivery.github_issue {
+        channels.push(format!("github issue {issue}"));
+    }
+    if let Some(to) = &delivery.email_to {
+        channels.push(format!("email to {to}"));
+    }
+    if channels.is_empty() {
+        "off".to_string()
+    } else {
+        channels.join(", ")
+    }
+}
+
+#[allow(clippy::too_many_lines)] // One line per setting
+fn run_doctor() -> CliOutput {
+    let base_dir = Path::new(".");
+    let (project_config, source) = match config::ProjectConfig::load_from(base_dir) {
+        Ok(Some(c)) => (c, format!("Config: {}", config::CONFIG_FILE_PATH)),
+        Ok(None) => (
+            config::ProjectConfig::default(),
+            format!("Config: {} not found, using defaults", config::CONFIG_FILE_PATH),
+        ),
+        Err(e) => return error_output(format!("Error loading config: {e}")),
+    };
+    let defaults = config::ProjectConfig::default();
+
+    let mut lines = vec![source, String::new(), "Stop hook settings:".to_string()];
+    let mut setting = |name: &str, value: String, default: String| {
+        if value == default {
+            lines.push(format!("  {name}: {value}"));
+        } else {
+            lines.push(format!("  {name}: {value} (default: {default})"));
+        }
+    };
+    setting(
+        "user_recency_minutes",
+        project_config.user_recency_minutes.to_string(),
+        defaults.user_recency_minutes.to_string(),
+    );
+    setting(
+        "api_error_threshold",
+        project_config.api_error_threshold.to_string(),
+        defaults.api_error_threshold.to_string(),
+    );
+    setting(
+        "max_files_to_show",
+        project_config.max_files_to_show.to_string(),
+        defaults.max_files_to_show.to_string(),
+    );
+    setting(
+        "context_budget",
+        project_config.context_budget.to_string(),
+        defaults.context_budget.to_string(),
+    );
+    setting(
+        "message_verbosity",
+        format!("{:?}", project_config.message_verbosity).to_lowercase(),
+        format!("{:?}", defaults.message_verbosity).to_lowercase(),
+    );
+    setting("tag_blocks", project_config.tag_blocks.to_string(), defaults.tag_blocks.to_string());
+    setting(
+        "suggest_answers",
+        project_config.suggest_answers.to_string(),
+        defaults.suggest_answers.to_string(),
+    );
+    setting(
+        "assume_after_hours",
+        project_config.assume_after_hours.to_string(),
+        defaults.assume_after_hours.to_string(),
+    );
+    setting(
+        "question_delivery",
+        question_delivery_summary(&project_config.question_delivery),
+        question_delivery_summary(&defaults.question_delivery),
+    );
+    setting(
+        "escalation",
+        escalation_summary(&project_config.escalation),
+        escalation_summary(&defaults.escalation),
+    );
+    setting(
+        "stop_checks",
+        stop_checks_summary(&project_config.stop_checks),
+        stop_checks_summary(&defaults.stop_checks),
+    );
+    setting(
+        "git_status",
+        git_status_summary(&project_config.git_status),
+        git_status_summary(&defaults.git_status),
+    );
+    setting(
+        "commit_policy",
+        commit_policy_summary(&project_config.commit_policy),
+        commit_policy_summary(&defaults.commit_policy),
+    );
+    setting(
+        "vcs",
+        format!("{:?}", project_config.vcs).to_lowercase(),
+        format!("{:?}", defaults.vcs).to_lowercase(),
+    );
+    setting(
+        "failure_mode",
+        format!("{:?}", project_config.failure_mode).to_lowercase(),
+        format!("{:?}", defaults.failure_mode).to_lowercase(),
+    );
+    setting(
+        "backup_interval_hours",
+        project_config.backup_interval_hours.to_string(),
+        defaults.backup_interval_hours.to_string(),
+    );
+    setting(
+        "backup_keep",
+        project_config.backup_keep.to_string(),
+        defaults.backup_keep.to_string(),
+    );
+    setting(
+        "block_escalation_threshold",
+        project_config.block_escalation_threshold.to_string(),
+        defaults.block_escalation_threshold.to_string(),
+    );
+    setting(
+        "injection_loop_limit",
+        project_config.injection_loop_limit.to_string(),
+        defaults.injection_loop_limit.to_string(),
+    );
+    setting(
+        "repeated_output_limit",
+        project_config.repeated_output_limit.to_string(),
+        defaults.repeated_output_limit.to_string(),
+    );
+    setting(
+        "max_session_cost_usd",
+        project_config.max_session_cost_usd.to_string(),
+        defaults.max_session_cost_usd.to_string(),
+    );
+    setting(
+        "auto_work_idle_minutes",
+        project_config.auto_work_idle_minutes.to_string(),
+        defaults.auto_work_idle_minutes.to_string(),
+    );
+    setting(
+        "auto_work_decline_limit",
+        project_config.auto_work_decline_limit.to_string(),
+        defaults.auto_work_decline_limit.to_string(),
+    );
+    setting(
+        "auto_work_cooldown_minutes",
+        project_config.auto_work_cooldown_minutes.to_string(),
+        defaults.auto_work_cooldown_minutes.to_string(),
+    );
+    setting(
+        "auto_work_max_prompts",
+        project_config.auto_work_max_prompts.to_string(),
+        defaults.auto_work_max_prompts.to_string(),
+    );
+    setting(
+        "auto_work_check_in_every",
+        project_config.auto_work_check_in_every.to_string(),
+        defaults.auto_work_check_in_every.to_string(),
+    );
+    setting(
+        "auto_work_hours",
+        project_config.auto_work_hours.as_deref().map_or_else(
+            || "any time".to_string(),
+            |hours| match hours.parse::<crate::schedule::WorkSchedule>() {
+                Ok(_) => hours.to_string(),
+                Err(e) => format!("{hours} ({e})"),
+            },
+        ),
+        "any time".to_string(),
+    );
+    setting(
+        "idle_source",
+        format!("{:?}", project_config.idle_source).to_lowercase(),
+        format!("{:?}", defaults.idle_source).to_lowercase(),
+    );
+    setting(
+        "require_task.scope",
+        format!("{:?}", project_config.require_task.scope).to_lowercase(),
+        format!("{:?}", defaults.require_task.scope).to_lowercase(),
+    );
+    setting(
+        "auto_confirm_commit",
+        project_config.auto_confirm_commit.to_string(),
+        defaults.auto_confirm_commit.to_string(),
+    );
+    setting(
+        "auto_confirm_push",
+        project_config.auto_confirm_push.to_string(),
+        defaults.auto_confirm_push.to_string(),
+    );
+    setting(
+        "auto_answer_continue",
+        project_config.auto_answer_continue.to_string(),
+        defaults.auto_answer_continue.to_string(),
+    );
+
+    let problems = templates::validate_overrides(base_dir);
+    if !problems.is_empty() {
+        lines.push(String::new());
+        lines.push("Ignoring invalid template overrides:".to_string());
+        lines.extend(problems.into_iter().map(|p| format!("  {p}")));
+    }
+    success_output(lines.join("\n"))
+}
+
+fn run_replay(hook: &str, input: &Path, transcript: Option<&Path>, at: Option<i64>) -> CliOutput {
+    if !replay::REPLAYABLE_HOOKS.contains(&hook) {
+        return error_output(format!(
+            "Unknown hook '{hook}'. Expected one of: {}",
+            replay::REPLAYABLE_HOOKS.join(", ")
+        ));
+    }
+    let stdin = match std::fs::read_to_string(input) {
+        Ok(content) => match replay::prepare_input(&content, transcript) {
+            Ok(stdin) => stdin,
+            Err(e) => return error_output(format!("Invalid hook input: {e}")),
+        },
+        Err(e) => return error_output(format!("Could not read {}: {e}", input.display())),
+    };
+    let snapshot = match replay::Snapshot::create(Path::new("."), at) {
+        Ok(snapshot) => snapshot,
+        Err(e) => return error_output(format!("Could not copy project state: {e}")),
+    };
+
+    let _env = snapshot.activate();
+    match hook {
+        "stop" => replay_stop(&stdin),
+        "session-start" => run_session_start_cmd(&stdin),
+        "user-prompt-submit" => run_user_prompt_submit_cmd(&stdin),
+        "pre-tool-use" => run_pre_tool_use_cmd(&stdin),
+        _ => run_post_tool_use_cmd(&stdin),
+    }
+}
+
+/// Build the stop hook configuration for a replay.
+///
+/// The hook runs against a copy of the project data, but checks that act
+/// outside it are switched off: a replay never commits, pushes, opens pull
+/// requests or issues, or spends time on sub-agents writing handoffs or
+/// answering questions with assumptions.
+pub fn replay_stop_config(project_config: config::ProjectConfig) -> StopHookConfig {
+    let mut config = stop_hook_config(project_config);
+    config.auto_wip_commit = false;
+    config.auto_push = false;
+    config.require_pr = false;
+    config.handoff_summaries = false;
+    config.assume_after_hours = 0;
+    config.escalation = config::EscalationPolicy::default();
+    config.message_verbosity = config::MessageVerbosity::Verbose;
+    config
+}
+
+/// Run the stop hook for a replay, printing the decision and full checks log.
+fn replay_stop(stdin: &str) -> CliOutput {
+    let runner = RealCommandRunner::new();
+    let sub_agent = RealSubAgent::new(&runner);
+    let project_config = config::ProjectConfig::load()
+        .ok()
+        .flatten()
+        .unwrap_or_else(|| config::ProjectConfig::detect_in(&runner, Path::new(".")));
+    let config = replay_stop_config(project_config);
+
+    let input = match parse_hook_input(stdin) {
+        Ok(i) => i,
+        Err(e) => return error_output(format!("Error parsing hook input: {e}")),
+    };
+    match run_stop_hook(&input, &config, &runner, &sub_agent) {
+        Ok(result) => {
+            let decision = if result.exit_code == 2 { "block" } else { "allow" };
+            let decided_by =
+                result.decided_by.as_ref().map_or_else(String::new, |c| format!(" by {c}"));
+            let mut stdout =
+                vec![format!("Decision: {decision}{decided_by} (exit code {})", result.exit_code)];
+            stdout.extend(result.messages);
+            CliOutput { exit_code: exit_code_from_i32(result.exit_code), stdout, stderr: vec![] }
+        }
+        Err(e) => hook_error_output(&e, "running stop hook", config.failure_mode),
+    }
+}
+
+fn run_simulate_cmd(cmd: &SimulateCommand) -> CliOutput {
+    match cmd {
+        SimulateCommand::Stop { scenario } => simulate_stop(scenario),
+    }
+}
+
+/// Run the stop hook against a scenario, printing the decision and full checks log.
+fn simulate_stop(scenario: &str) -> CliOutput {
+    use crate::hooks::{simulate_stop_with_config, StopScenario};
+
+    let scenario = match scenario.parse::<StopScenario>() {
+        Ok(s) => s,
+        Err(e) => return error_output(e.to_string()),
+    };
+    let runner = RealCommandRunner::new();
+    let project_config = config::ProjectConfig::load()
+        .ok()
+        .flatten()
+        .unwrap_or_else(|| config::ProjectConfig::detect_in(&runner, Path::new(".")));
+    match simulate_stop_with_config(scenario, &stop_hook_config(project_config)) {
+        Ok(result) => {
+            let decision = if result.exit_code == 2 { "block" } else { "allow" };
+            let decided_by =
+                result.decided_by.as_ref().map_or_else(String::new, |c| format!(" by {c}"));
+            let mut stdout = vec![
+                format!("Scenario: {} ({})", scenario.name(), scenario.description()),
+                format!("Decision: {decision}{decided_by} (exit code {})", result.exit_code),
+            ];
+            stdout.extend(result.messages);
+            success_output(stdout.join("\n"))
+        }
+        Err(e) => error_output(format!("Simulation failed: {e}")),
+    }
+}
+
+/// Time the stop hook against fabricated sessions, failing if over budget.
+fn run_bench(size: Option<&str>, runs: u16, assert_under: Option<&str>) -> CliOutput {
+    use crate::hooks::{bench_stop_hook, BenchSize};
+
+    let sizes = match size.map(str::parse::<BenchSize>).transpose() {
+        Ok(Some(size)) => vec![size],
+        Ok(None) => BenchSize::ALL.to_vec(),
+        Err(e) => return error_output(e.to_string()),
+    };
+    let budget = match assert_under.map(parse_duration_millis).transpose() {
+        Ok(budget) => budget.map(std::time::Duration::from_millis),
+        Err(e) => return error_output(e),
+    };
+
+    let mut stdout = Vec::new();
+    let mut over_budget = Vec::new();
+    for size in sizes {
+        let result = match bench_stop_hook(size, usize::from(runs)) {
+            Ok(result) => result,
+            Err(e) => return error_output(format!("Benchmark failed: {e}")),
+        };
+        stdout.push(format!(
+            "{} ({} transcript turns, {} work items): median {:.1?}, max {:.1?} over {} runs",
+            size.name(),
+            size.transcript_turns(),
+            size.work_items(),
+            result.median,
+            result.max,
+            result.runs,
+        ));
+        if budget.is_some_and(|budget| result.median > budget) {
+            over_budget.push(size.name());
+        }
+    }
+
+    if over_budget.is_empty() {
+        success_output(stdout.join("\n"))
+    } else {
+        CliOutput {
+            exit_code: ExitCode::from(1),
+            stdout,
+            stderr: vec![format!(
+                "Over the {} budget: {}",
+                assert_under.unwrap_or_default(),
+                over_budget.join(", ")
+            )],
+        }
+    }
+}
+
+/// Parse a duration such as `200ms` or `2s` into milliseconds. Plain numbers are milliseconds.
+///
+/// # Errors
+///
+/// Returns an error if the duration is not a positive number with a known unit.
+pub fn parse_duration_millis(duration: &str) -> Result<u64, String> {
+    let duration = duration.trim();
+    let (number, unit) = duration
+        .find(|c: char| !c.is_ascii_digit())
+        .map_or((duration, "ms"), |idx| duration.split_at(idx));
+    let multiplier = match unit.trim() {
+        "ms" => 1,
+        "s" | "sec" | "secs" | "seconds" => 1000,
+        _ => return Err(format!("Invalid duration '{duration}': use e.g. 200ms or 1s")),
+    };
+    number
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(multiplier))
+        .filter(|&millis| millis > 0)
+        .ok_or_else(|| format!("Invalid duration '{duration}': use e.g. 200ms or 1s"))
+}
+
 fn run_audit_log(work_id: Option<&String>, limit: Option<usize>) -> CliOutput {
     let store = match open_store() {
         Ok(s) => s,
@@ -960,50 +2595,126 @@ fn run_audit_log(work_id: Option<&String>, limit: Option<usize>) -> CliOutput {
     }
 }
 
-fn run_emergency_stop(explanation: String) -> CliOutput {
-    use crate::traits::{EmergencyStopContext, EmergencyStopDecision, SubAgent as _};
+fn run_audit_log_cmd(cmd: &AuditLogCommand) -> CliOutput {
+    use crate::export::{export, ExportFormat};
+
+    let base_dir = Path::new(".");
+    match cmd {
+        AuditLogCommand::Export { format, output } => {
+            let format = match format.parse::<ExportFormat>() {
+                Ok(f) => f,
+                Err(e) => return error_output(e.to_string()),
+            };
+            let output = output.clone().unwrap_or_else(|| paths::project_exports_dir(base_dir));
+            match export(base_dir, format, &output) {
+                Ok(files) => json_output(&files),
+                Err(e) => error_output(format!("Export failed: {e}")),
+            }
+        }
+    }
+}
+
+fn run_unlock_protected(justification: &str) -> CliOutput {
+    use crate::protected_change::{self, UnlockOutcome, GRANT_MINUTES};
 
     let runner = RealCommandRunner::new();
     let sub_agent = RealSubAgent::new(&runner);
     let base_dir = std::env::current_dir().unwrap_or_default();
 
-    let context = EmergencyStopContext { explanation };
+    match protected_change::request_unlock(&base_dir, justification, &sub_agent) {
+        Ok(UnlockOutcome::Approved) => success_output(format!(
+            "Change approved. The next change to a protected file in the next {GRANT_MINUTES} \
+             minutes is allowed."
+        )),
+        Ok(UnlockOutcome::Rejected(reason)) => error_output(format!(
+            "Change denied. {reason}\n\nAsk the user to make the change if it is needed."
+        )),
+        Err(e) => error_output(e.to_string()),
+    }
+}
+
+fn run_emergency_stop(explanation: &str) -> CliOutput {
+    use crate::emergency_stop::{self, EmergencyStopLimits, EmergencyStopOutcome};
 
-    match sub_agent.evaluate_emergency_stop(&context) {
-        Ok(EmergencyStopDecision::Accept(msg)) => {
-            if let Err(e) = session::set_emergency_stop(&base_dir) {
-                return error_output(e.to_string());
-            }
+    const STOP_INSTRUCTIONS: &str = "Please explain the problem clearly to the user, then stop.";
+
+    let runner = RealCommandRunner::new();
+    let sub_agent = RealSubAgent::new(&runner);
+    let base_dir = std::env::current_dir().unwrap_or_default();
+    let project_config =
+        config::ProjectConfig::load_from(&base_dir).ok().flatten().unwrap_or_default();
+    let limits = EmergencyStopLimits {
+        max_per_hour: project_config.emergency_stop_max_per_hour,
+        rejections_before_question: project_config.emergency_stop_rejections_before_question,
+    };
+    let store = match open_store() {
+        Ok(s) => s,
+        Err(e) => return error_output(e),
+    };
 
-            let message = msg.map_or_else(
-                || {
-                    "Emergency stop accepted.\n\n\
-                     Please explain the problem clearly to the user, then stop."
-                        .to_string()
-                },
-                |m| {
-                    format!(
-                        "Emergency stop accepted: {m}\n\n\
-                         Please explain the problem clearly to the user, then stop."
-                    )
-                },
+    match emergency_stop::request_emergency_stop(
+        &base_dir,
+        explanation,
+        limits,
+        project_config.failure_mode,
+        &sub_agent,
+        &store,
+    ) {
+        Ok(EmergencyStopOutcome::Accepted(msg)) => {
+            let accepted = msg.map_or_else(
+                || "Emergency stop accepted.".to_string(),
+                |msg| format!("Emergency stop accepted: {msg}"),
+            );
+            let reported = escalate_emergency_stop(
+                &base_dir,
+                &project_config.escalation,
+                &runner,
+                explanation,
             );
-            success_output(message)
+            success_output(format!("{accepted}{reported}\n\n{STOP_INSTRUCTIONS}"))
         }
-        Ok(EmergencyStopDecision::Reject(instructions)) => {
+        Ok(EmergencyStopOutcome::AcceptedUnevaluated) => success_output(format!(
+            "Emergency stop accepted (evaluation unavailable).\n\n{STOP_INSTRUCTIONS}"
+        )),
+        Ok(EmergencyStopOutcome::Rejected(instructions)) => {
             error_output(format!("Emergency stop denied. {instructions}"))
         }
-        Err(_) => {
-            // On failure, default to accepting (conservative)
-            if let Err(e) = session::set_emergency_stop(&base_dir) {
-                return error_output(e.to_string());
-            }
-            success_output(
-                "Emergency stop accepted (evaluation unavailable).\n\n\
-                 Please explain the problem clearly to the user, then stop."
-                    .to_string(),
-            )
-        }
+        Ok(EmergencyStopOutcome::Escalated { question_id, instructions }) => error_output(format!(
+            "Emergency stop denied. {instructions}\n\n\
+             Your requests have been rejected repeatedly, so question {question_id} was filed \
+             for the user. Continue with any work you can until they answer it."
+        )),
+        Err(e) => error_output(e.to_string()),
+    }
+}
+
+/// Report an accepted emergency stop as the `escalation` policy asks,
+/// describing where it went.
+fn escalate_emergency_stop(
+    base_dir: &Path,
+    policy: &config::EscalationPolicy,
+    runner: &dyn crate::traits::CommandRunner,
+    explanation: &str,
+) -> String {
+    use crate::emergency_stop::{self, BlockerCategory};
+    use crate::escalation::{self, Blocker};
+
+    let Some(category) = BlockerCategory::detect(explanation).filter(|_| policy.is_enabled())
+    else {
+        return String::new();
+    };
+    let blocker = Blocker::new(
+        base_dir,
+        "emergency stop",
+        category.name(),
+        explanation,
+        &emergency_stop::current_session_id(base_dir),
+    );
+    let report = escalation::escalate(base_dir, policy, runner, &blocker);
+    if report.reported_to.is_empty() {
+        String::new()
+    } else {
+        format!(" Reported to: {}.", report.reported_to.join(", "))
     }
 }
 
@@ -1030,6 +2741,18 @@ fn error_output(message: String) -> CliOutput {
     CliOutput { exit_code: ExitCode::from(1), stdout: vec![], stderr: vec![message] }
 }
 
+/// Report a hook failure with the exit code and hint for its kind of error.
+///
+/// When the failure mode is closed, exit code 2 blocks instead.
+fn hook_error_output(e: &crate::error::Error, doing: &str, mode: config::FailureMode) -> CliOutput {
+    let exit_code = if mode.allows() { e.exit_code() } else { 2 };
+    CliOutput {
+        exit_code: ExitCode::from(exit_code),
+        stdout: vec![],
+        stderr: vec![e.user_message(doing)],
+    }
+}
+
 fn exit_code_from_i32(code: i32) -> ExitCode {
     #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
     let code_u8 = if code < 0 {
@@ -1054,6 +2777,8 @@ struct WorkItemSummary {
     status: String,
     in_progress: bool,
     requested: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    package: Option<String>,
     #[serde(skip_serializing_if = "Vec::is_empty")]
     blocked_by: Vec<String>,
 }
@@ -1068,6 +2793,7 @@ impl WorkItemSummary {
             status: task.status.as_str().to_string(),
             in_progress: task.in_progress,
             requested: task.requested,
+            package: task.package.clone(),
             blocked_by,
         }
     }
@@ -1086,6 +2812,8 @@ struct WorkItemOutput {
     requested: bool,
     created_at: String,
     updated_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    package: Option<String>,
     dependencies: Vec<String>,
     guidance: Vec<String>,
 }
@@ -1103,19 +2831,22 @@ impl WorkItemOutput {
             requested: task.requested,
             created_at: task.created_at.clone(),
             updated_at: task.updated_at.clone(),
+            package: task.package.clone(),
             dependencies: deps,
             guidance,
         }
     }
 }
 
-/// Full work item with notes and how-tos.
+/// Full work item with notes, how-tos and checklist.
 #[derive(Debug, Serialize)]
 struct FullWorkItemOutput {
     #[serde(flatten)]
     task: WorkItemOutput,
     notes: Vec<NoteOutput>,
     howtos: Vec<HowToOutput>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    checklist: Vec<ChecklistItemOutput>,
 }
 
 /// Work item suggestion from `next` command.
@@ -1142,6 +2873,20 @@ impl From<crate::tasks::Note> for NoteOutput {
     }
 }
 
+/// Checklist item output.
+#[derive(Debug, Serialize)]
+struct ChecklistItemOutput {
+    position: u32,
+    text: String,
+    done: bool,
+}
+
+impl From<crate::tasks::ChecklistItem> for ChecklistItemOutput {
+    fn from(item: crate::tasks::ChecklistItem) -> Self {
+        Self { position: item.position, text: item.text, done: item.done }
+    }
+}
+
 /// Note output with work item ID.
 #[derive(Debug, Serialize)]
 struct NoteWithWorkItemOutput {
@@ -1182,6 +2927,23 @@ struct QuestionOutput {
     is_answered: bool,
     created_at: String,
     answered_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    deferred_until: Option<String>,
+    priority: u8,
+    priority_label: &'static str,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    suggestions: Vec<crate::tasks::AnswerSuggestion>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    assumed: bool,
+}
+
+/// Question list entry, with the requested work waiting on the answer.
+#[derive(Debug, Serialize)]
+struct QuestionListItem {
+    #[serde(flatten)]
+    question: QuestionOutput,
+    blocks_requested: usize,
+    blocked_requested: Vec<String>,
 }
 
 impl From<&crate::tasks::Question> for QuestionOutput {
@@ -1193,6 +2955,11 @@ impl From<&crate::tasks::Question> for QuestionOutput {
             is_answered: q.is_answered(),
             created_at: q.created_at.clone(),
             answered_at: q.answered_at.clone(),
+            deferred_until: q.deferred_until.clone(),
+            priority: q.priority.as_u8(),
+            priority_label: priority_label(q.priority),
+            suggestions: q.suggestions.clone(),
+            assumed: q.assumed,
         }
     }
 }
@@ -0,0 +1,44 @@
+//! Session CLI subcommands.
+//!
+//! Provides commands for inspecting the sessions the hooks have seen in
+//! this project, and for cleaning up what was recorded about them.
+
+use clap::Subcommand;
+
+/// Session registry commands.
+///
+/// Every hook call records its session, what the session is doing
+/// (working, blocked or stopped) and when it was last seen. A session is
+/// alive if it has not stopped and was seen in the last few minutes.
+#[derive(Subcommand, Debug, Clone)]
+pub enum SessionCommand {
+    /// List sessions, most recently seen first.
+    List {
+        /// Only show sessions that are alive
+        #[arg(long)]
+        alive: bool,
+    },
+    /// Redact credentials from already recorded user messages and sub-agent logs.
+    Scrub,
+    /// Clear stuck session markers (problem mode, emergency stop, etc.).
+    Reset {
+        /// Only clear this marker (e.g. `problem_mode`); all are cleared by default
+        #[arg(long)]
+        marker: Option<String>,
+    },
+    /// List problems the agent reported in problem mode, most recent first.
+    ///
+    /// Each problem shows how many problems have been reported on the same
+    /// work item, so recurring blockers stand out.
+    Problems {
+        /// Maximum number of problems to show
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+    },
+    /// Pause the session: the next stop is allowed without reflection or
+    /// auto-work, and the next session start resumes the pending work.
+    Pause {
+        /// Why the session is pausing
+        reason: String,
+    },
+}
@@ -0,0 +1,22 @@
+//! Simulation CLI subcommands.
+//!
+//! Provides commands for seeing how the hooks decide in fabricated
+//! situations, to understand and tune the checks without live sessions.
+
+use clap::Subcommand;
+
+/// Simulation commands.
+#[derive(Subcommand, Debug, Clone)]
+pub enum SimulateCommand {
+    /// Run the stop hook against a fabricated situation and show which check fires.
+    ///
+    /// The scenario's transcript, git repository and work items are created
+    /// in a scratch directory, and the stop hook runs there with this
+    /// project's configuration. The quality check is not run, and sub-agent
+    /// answers are canned. Prints the decision and the log of every check run.
+    Stop {
+        /// The situation: uncommitted, api-error or question
+        #[arg(long)]
+        scenario: String,
+    },
+}
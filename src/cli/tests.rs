@@ -46,15 +46,16 @@ impl Drop for FakeCommandGuard {
 fn test_command_needs_stdin() {
     // Commands that don't need stdin (won't block on terminal)
     assert!(!Command::Version.needs_stdin());
-    assert!(!Command::EnsureConfig.needs_stdin());
+    assert!(!Command::EnsureConfig { profile: None }.needs_stdin());
     assert!(!Command::EnsureGitignore.needs_stdin());
     assert!(!Command::Intro.needs_stdin());
 
     // Commands that need stdin (hooks that receive JSON input)
-    assert!(Command::Stop.needs_stdin());
-    assert!(Command::PreToolUse.needs_stdin());
-    assert!(Command::PostToolUse.needs_stdin());
-    assert!(Command::UserPromptSubmit.needs_stdin());
+    assert!(Command::Stop { profile: false }.needs_stdin());
+    assert!(Command::SessionStart { profile: false }.needs_stdin());
+    assert!(Command::PreToolUse { profile: false }.needs_stdin());
+    assert!(Command::PostToolUse { profile: false }.needs_stdin());
+    assert!(Command::UserPromptSubmit { profile: false }.needs_stdin());
 
     // Work commands don't need stdin
     assert!(!Command::Work(WorkCommand::Next).needs_stdin());
@@ -63,38 +64,53 @@ fn test_command_needs_stdin() {
 #[test]
 fn test_command_is_hook() {
     // Hook commands
-    assert!(Command::Stop.is_hook());
-    assert!(Command::PreToolUse.is_hook());
-    assert!(Command::PostToolUse.is_hook());
-    assert!(Command::UserPromptSubmit.is_hook());
+    assert!(Command::Stop { profile: false }.is_hook());
+    assert!(Command::SessionStart { profile: false }.is_hook());
+    assert!(Command::PreToolUse { profile: false }.is_hook());
+    assert!(Command::PostToolUse { profile: false }.is_hook());
+    assert!(Command::UserPromptSubmit { profile: false }.is_hook());
 
     // Non-hook commands
     assert!(!Command::Version.is_hook());
-    assert!(!Command::EnsureConfig.is_hook());
+    assert!(!Command::EnsureConfig { profile: None }.is_hook());
     assert!(!Command::EnsureGitignore.is_hook());
     assert!(!Command::Intro.is_hook());
+    assert!(!Command::Disable { duration: None }.is_hook());
     assert!(!Command::Work(WorkCommand::Next).is_hook());
     assert!(!Command::Howto(HowToCommand::List).is_hook());
-    assert!(!Command::AuditLog { work_id: None, limit: None }.is_hook());
+    assert!(!Command::AuditLog { command: None, work_id: None, limit: None }.is_hook());
     assert!(!Command::EmergencyStop { explanation: String::new() }.is_hook());
+    assert!(!Command::UnlockProtected { justification: String::new() }.is_hook());
 }
 
 #[test]
 fn test_command_hook_type() {
     // Hook commands return their type name
-    assert_eq!(Command::Stop.hook_type(), Some("stop"));
-    assert_eq!(Command::UserPromptSubmit.hook_type(), Some("user-prompt-submit"));
-    assert_eq!(Command::PreToolUse.hook_type(), Some("pre-tool-use"));
-    assert_eq!(Command::PostToolUse.hook_type(), Some("post-tool-use"));
+    assert_eq!(Command::Stop { profile: false }.hook_type(), Some("stop"));
+    assert_eq!(Command::SessionStart { profile: false }.hook_type(), Some("session-start"));
+    assert_eq!(
+        Command::UserPromptSubmit { profile: false }.hook_type(),
+        Some("user-prompt-submit")
+    );
+    assert_eq!(Command::PreToolUse { profile: false }.hook_type(), Some("pre-tool-use"));
+    assert_eq!(Command::PostToolUse { profile: false }.hook_type(), Some("post-tool-use"));
 
     // Non-hook commands return None
     assert_eq!(Command::Version.hook_type(), None);
-    assert_eq!(Command::EnsureConfig.hook_type(), None);
+    assert_eq!(Command::EnsureConfig { profile: None }.hook_type(), None);
     assert_eq!(Command::EnsureGitignore.hook_type(), None);
     assert_eq!(Command::Intro.hook_type(), None);
     assert_eq!(Command::Work(WorkCommand::Next).hook_type(), None);
 }
 
+#[test]
+fn test_command_profile() {
+    assert!(Command::Stop { profile: true }.profile());
+    assert!(Command::PostToolUse { profile: true }.profile());
+    assert!(!Command::SessionStart { profile: false }.profile());
+    assert!(!Command::EnsureConfig { profile: Some("strict".to_string()) }.profile());
+}
+
 #[test]
 fn test_run_version() {
     let output = run(Command::Version, "");
@@ -111,6 +127,34 @@ fn test_run_intro() {
     assert!(output.stderr[0].contains("Reliability Mode"));
 }
 
+#[test]
+#[serial_test::serial]
+fn test_run_session_start_via_cli() {
+    let dir = TempDir::new().unwrap();
+    let original_dir = std::env::current_dir().unwrap();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    // Nothing to resume in a fresh project
+    let output = run(Command::SessionStart { profile: false }, r#"{"source": "startup"}"#);
+    assert_eq!(output.exit_code, ExitCode::SUCCESS);
+    assert!(output.stdout.is_empty());
+
+    let handoffs = paths::project_handoffs_dir(std::path::Path::new("."));
+    std::fs::create_dir_all(&handoffs).unwrap();
+    std::fs::write(handoffs.join("2026-01-01T00-00-00Z.md"), "# Session Handoff\n").unwrap();
+
+    let output = run(Command::SessionStart { profile: false }, r#"{"source": "startup"}"#);
+    assert_eq!(output.exit_code, ExitCode::SUCCESS);
+    let json: serde_json::Value = serde_json::from_str(&output.stdout[0]).unwrap();
+    assert_eq!(json["hookSpecificOutput"]["hookEventName"], "SessionStart");
+
+    // The intro includes the same context
+    let output = run(Command::Intro, "");
+    assert!(output.stderr[1].contains("Resuming Previous Work"));
+
+    std::env::set_current_dir(original_dir).unwrap();
+}
+
 #[test]
 #[serial_test::serial]
 fn test_run_pre_tool_use_via_cli() {
@@ -119,7 +163,7 @@ fn test_run_pre_tool_use_via_cli() {
     std::env::set_current_dir(dir.path()).unwrap();
 
     let output = run(
-        Command::PreToolUse,
+        Command::PreToolUse { profile: false },
         r#"{"tool_name": "Read", "tool_input": {"file_path": "src/main.rs"}}"#,
     );
 
@@ -130,9 +174,31 @@ fn test_run_pre_tool_use_via_cli() {
     assert!(output.stderr[0].contains("allow"));
 }
 
+#[test]
+#[serial_test::serial]
+fn test_run_hook_with_profile() {
+    let dir = TempDir::new().unwrap();
+    let original_dir = std::env::current_dir().unwrap();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    let input = r#"{"tool_name": "Read", "tool_input": {"file_path": "src/main.rs"}}"#;
+    let output = run(Command::PreToolUse { profile: true }, input);
+    run(Command::PreToolUse { profile: false }, input);
+
+    std::env::set_current_dir(original_dir).unwrap();
+
+    assert_eq!(output.exit_code, ExitCode::SUCCESS);
+    let profiles: Vec<_> = std::fs::read_dir(paths::project_profiles_dir(dir.path()))
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+        .collect();
+    assert_eq!(profiles.len(), 1);
+    assert!(profiles[0].starts_with("pre-tool-use-"), "{profiles:?}");
+}
+
 #[test]
 fn test_run_pre_tool_use_invalid_json() {
-    let output = run(Command::PreToolUse, "not json");
+    let output = run(Command::PreToolUse { profile: false }, "not json");
     assert_eq!(output.exit_code, ExitCode::from(1));
     assert!(!output.stderr.is_empty());
     assert!(output.stderr[0].contains("Failed to parse"));
@@ -151,7 +217,7 @@ fn test_run_post_tool_use_via_cli() {
     std::env::set_current_dir(dir.path()).unwrap();
 
     let output = run(
-        Command::PostToolUse,
+        Command::PostToolUse { profile: false },
         r#"{"toolName": "ExitPlanMode", "toolResponse": {"filePath": "~/.claude/plans/test-plan.md"}}"#,
     );
 
@@ -163,7 +229,7 @@ fn test_run_post_tool_use_via_cli() {
 
 #[test]
 fn test_run_post_tool_use_invalid_json() {
-    let output = run(Command::PostToolUse, "not json");
+    let output = run(Command::PostToolUse { profile: false }, "not json");
     assert_eq!(output.exit_code, ExitCode::from(1));
     assert!(!output.stderr.is_empty());
     assert!(output.stderr[0].contains("Failed to parse"));
@@ -176,7 +242,10 @@ fn test_run_post_tool_use_unknown_tool() {
     let original_dir = std::env::current_dir().unwrap();
     std::env::set_current_dir(dir.path()).unwrap();
 
-    let output = run(Command::PostToolUse, r#"{"toolName": "UnknownTool", "toolResponse": {}}"#);
+    let output = run(
+        Command::PostToolUse { profile: false },
+        r#"{"toolName": "UnknownTool", "toolResponse": {}}"#,
+    );
 
     std::env::set_current_dir(original_dir).unwrap();
 
@@ -191,7 +260,7 @@ fn test_run_post_tool_use_exit_plan_mode_no_file_path() {
     std::env::set_current_dir(dir.path()).unwrap();
 
     let output = run(
-        Command::PostToolUse,
+        Command::PostToolUse { profile: false },
         r#"{"toolName": "ExitPlanMode", "toolResponse": {"plan": "content only"}}"#,
     );
 
@@ -209,7 +278,7 @@ fn test_run_stop_via_cli() {
     let original_dir = std::env::current_dir().unwrap();
     std::env::set_current_dir(dir.path()).unwrap();
 
-    let output = run(Command::Stop, "not json input");
+    let output = run(Command::Stop { profile: false }, "not json input");
 
     std::env::set_current_dir(original_dir).unwrap();
 
@@ -258,11 +327,13 @@ fn test_run_stop_via_cli_in_temp_repo() {
     let original_dir = std::env::current_dir().unwrap();
     std::env::set_current_dir(dir_path).unwrap();
 
-    let output = run(Command::Stop, "{}");
+    let output = run(Command::Stop { profile: false }, "{}");
 
     std::env::set_current_dir(original_dir).unwrap();
 
     assert_eq!(output.exit_code, ExitCode::SUCCESS);
+    let json: serde_json::Value = serde_json::from_str(&output.stdout[0]).unwrap();
+    assert!(json["reasonCode"].is_string());
 }
 
 #[test]
@@ -278,7 +349,7 @@ fn test_run_ensure_config_via_cli() {
     let original_dir = std::env::current_dir().unwrap();
     std::env::set_current_dir(dir_path).unwrap();
 
-    let output = run(Command::EnsureConfig, "");
+    let output = run(Command::EnsureConfig { profile: None }, "");
 
     std::env::set_current_dir(original_dir).unwrap();
 
@@ -288,6 +359,28 @@ fn test_run_ensure_config_via_cli() {
     assert!(output.stderr.iter().any(|m| m.contains("check_command")));
 }
 
+#[test]
+#[serial_test::serial]
+fn test_run_ensure_config_with_profile() {
+    let dir = TempDir::new().unwrap();
+    let original_dir = std::env::current_dir().unwrap();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    let output = run(Command::EnsureConfig { profile: Some("strict".to_string()) }, "");
+    let invalid = run(Command::EnsureConfig { profile: Some("lenient".to_string()) }, "");
+
+    std::env::set_current_dir(original_dir).unwrap();
+
+    assert_eq!(output.exit_code, ExitCode::SUCCESS);
+    assert!(output.stderr.iter().any(|m| m == "  profile: strict"));
+    let config = crate::config::ProjectConfig::load_from(dir.path()).unwrap().unwrap();
+    assert_eq!(config.profile, Some(crate::config::Profile::Strict));
+    assert_eq!(config.failure_mode, crate::config::FailureMode::Closed);
+
+    assert_eq!(invalid.exit_code, ExitCode::from(1));
+    assert!(invalid.stderr.iter().any(|m| m.contains("invalid profile 'lenient'")));
+}
+
 #[test]
 #[serial_test::serial]
 fn test_run_ensure_config_with_justfile() {
@@ -304,7 +397,7 @@ fn test_run_ensure_config_with_justfile() {
     let original_dir = std::env::current_dir().unwrap();
     std::env::set_current_dir(dir_path).unwrap();
 
-    let output = run(Command::EnsureConfig, "");
+    let output = run(Command::EnsureConfig { profile: None }, "");
 
     std::env::set_current_dir(original_dir).unwrap();
 
@@ -320,10 +413,13 @@ fn test_run_ensure_gitignore_via_cli() {
 
     let original_dir = std::env::current_dir().unwrap();
     std::env::set_current_dir(dir_path).unwrap();
+    std::fs::write(dir_path.join("Cargo.toml"), "[package]\n").unwrap();
 
     let output = run(Command::EnsureGitignore, "");
     assert_eq!(output.exit_code, ExitCode::SUCCESS);
     assert!(output.stderr.iter().any(|m| m.contains(".gitignore")));
+    assert!(output.stderr.contains(&"+target/".to_string()), "stderr: {:?}", output.stderr);
+    assert!(output.stderr.contains(&"+# end claude-reliability managed".to_string()));
 
     let output2 = run(Command::EnsureGitignore, "");
     assert_eq!(output2.exit_code, ExitCode::SUCCESS);
@@ -341,7 +437,7 @@ fn test_run_user_prompt_submit_via_cli() {
     let original_dir = std::env::current_dir().unwrap();
     std::env::set_current_dir(dir_path).unwrap();
 
-    let output = run(Command::UserPromptSubmit, "");
+    let output = run(Command::UserPromptSubmit { profile: false }, "");
 
     std::env::set_current_dir(original_dir).unwrap();
 
@@ -360,7 +456,7 @@ fn test_run_user_prompt_submit_post_compaction() {
     std::env::set_current_dir(dir_path).unwrap();
 
     let input = r#"{"isCompactSummary": true}"#;
-    let output = run(Command::UserPromptSubmit, input);
+    let output = run(Command::UserPromptSubmit { profile: false }, input);
 
     std::env::set_current_dir(original_dir).unwrap();
 
@@ -428,7 +524,10 @@ fn test_work_create_and_get() {
         Command::Work(WorkCommand::Create {
             title: "Test task".to_string(),
             description: "Test description".to_string(),
-            priority: 1,
+            priority: Some(1),
+            package: None,
+            force: false,
+            template: None,
         }),
         "",
     );
@@ -464,7 +563,10 @@ fn test_work_list_and_next() {
         Command::Work(WorkCommand::Create {
             title: "High priority".to_string(),
             description: String::new(),
-            priority: 1,
+            priority: Some(1),
+            package: None,
+            force: false,
+            template: None,
         }),
         "",
     );
@@ -472,7 +574,10 @@ fn test_work_list_and_next() {
         Command::Work(WorkCommand::Create {
             title: "Low priority".to_string(),
             description: String::new(),
-            priority: 3,
+            priority: Some(3),
+            package: None,
+            force: false,
+            template: None,
         }),
         "",
     );
@@ -486,6 +591,8 @@ fn test_work_list_and_next() {
             ready_only: false,
             limit: None,
             offset: None,
+            package: None,
+            waiting: None,
         }),
         "",
     );
@@ -516,7 +623,10 @@ fn test_work_update_and_delete() {
         Command::Work(WorkCommand::Create {
             title: "Original".to_string(),
             description: String::new(),
-            priority: 2,
+            priority: Some(2),
+            package: None,
+            force: false,
+            template: None,
         }),
         "",
     );
@@ -532,6 +642,8 @@ fn test_work_update_and_delete() {
             description: None,
             priority: Some(0),
             status: None,
+            package: None,
+            confirm_dod: vec![],
         }),
         "",
     );
@@ -603,6 +715,7 @@ fn test_question_crud() {
     let output = run(
         Command::Question(QuestionCommand::Create {
             text: "What color should the button be?".to_string(),
+            priority: None,
         }),
         "",
     );
@@ -618,6 +731,279 @@ fn test_question_crud() {
     std::env::set_current_dir(original_dir).unwrap();
 }
 
+#[test]
+#[serial_test::serial]
+fn test_question_list_sorted_by_impact() {
+    use crate::tasks::{Priority, SqliteTaskStore, TaskStore, TaskUpdate};
+
+    let dir = TempDir::new().unwrap();
+    let original_dir = std::env::current_dir().unwrap();
+    std::env::set_current_dir(dir.path()).unwrap();
+    let store = SqliteTaskStore::for_project(dir.path()).unwrap();
+    let minor = store.create_question("Tabs or spaces?").unwrap();
+    let blocking = store.create_question("Which database?").unwrap();
+    let task = store.create_task("Add users table", "", Priority::Medium).unwrap();
+    store
+        .update_task(&task.id, TaskUpdate { requested: Some(true), ..Default::default() })
+        .unwrap();
+    store.link_task_to_question(&task.id, &blocking.id).unwrap();
+
+    let set_priority = run(
+        Command::Question(QuestionCommand::SetPriority { id: minor.id.clone(), priority: 0 }),
+        "",
+    );
+    let invalid = run(
+        Command::Question(QuestionCommand::SetPriority { id: minor.id.clone(), priority: 9 }),
+        "",
+    );
+    let listed =
+        run(Command::Question(QuestionCommand::List { unanswered_only: true, limit: None }), "");
+
+    std::env::set_current_dir(original_dir).unwrap();
+    assert_eq!(set_priority.exit_code, ExitCode::SUCCESS);
+    assert!(set_priority.stdout[0].contains("\"priority_label\": \"critical\""));
+    assert_eq!(invalid.exit_code, ExitCode::from(1));
+    let listed: serde_json::Value = serde_json::from_str(&listed.stdout[0]).unwrap();
+    assert_eq!(listed[0]["id"], serde_json::json!(blocking.id));
+    assert_eq!(listed[0]["blocks_requested"], serde_json::json!(1));
+    assert_eq!(listed[0]["blocked_requested"], serde_json::json!([task.id]));
+    assert_eq!(listed[1]["id"], serde_json::json!(minor.id));
+    assert_eq!(listed[1]["blocks_requested"], serde_json::json!(0));
+}
+
+#[test]
+#[serial_test::serial]
+fn test_question_sync_reads_email_replies() {
+    use crate::tasks::{SqliteTaskStore, TaskStore};
+
+    let dir = TempDir::new().unwrap();
+    let original_dir = std::env::current_dir().unwrap();
+    std::env::set_current_dir(dir.path()).unwrap();
+    let unconfigured = run(Command::Question(QuestionCommand::Sync), "");
+
+    let store = SqliteTaskStore::for_project(dir.path()).unwrap();
+    let question = store.create_question("Which licence?").unwrap();
+    std::fs::create_dir_all(dir.path().join(".claude")).unwrap();
+    std::fs::write(
+        dir.path().join(crate::config::CONFIG_FILE_PATH),
+        "question_delivery:\n  reply_maildir: replies\n  answer_from: [ann@example.com]\n",
+    )
+    .unwrap();
+    std::fs::create_dir_all(dir.path().join("replies/new")).unwrap();
+    std::fs::write(
+        dir.path().join("replies/new/1.eml"),
+        format!(
+            "From: ann@example.com\nSubject: Re: [question {}] Which licence?\n\nMIT\n",
+            question.id
+        ),
+    )
+    .unwrap();
+    let synced = run(Command::Question(QuestionCommand::Sync), "");
+
+    std::env::set_current_dir(original_dir).unwrap();
+    assert_eq!(unconfigured.exit_code, ExitCode::from(1));
+    assert!(unconfigured.stderr[0].contains("No question delivery configured"));
+    assert_eq!(synced.exit_code, ExitCode::SUCCESS);
+    let report: serde_json::Value = serde_json::from_str(&synced.stdout[0]).unwrap();
+    assert_eq!(report["answered"], serde_json::json!([question.id]));
+    assert_eq!(store.get_question(&question.id).unwrap().unwrap().answer.as_deref(), Some("MIT"));
+}
+
+#[test]
+#[serial_test::serial]
+fn test_question_defer_and_answer_all() {
+    use crate::tasks::{SqliteTaskStore, TaskStore};
+
+    let dir = TempDir::new().unwrap();
+    let original_dir = std::env::current_dir().unwrap();
+    std::env::set_current_dir(dir.path()).unwrap();
+    let store = SqliteTaskStore::for_project(dir.path()).unwrap();
+    let later = store.create_question("Which licence?").unwrap();
+    let now = store.create_question("What color should the button be?").unwrap();
+
+    let defer = |until: &str| {
+        run(
+            Command::Question(QuestionCommand::Defer {
+                id: later.id.clone(),
+                until: Some(until.to_string()),
+                clear: false,
+            }),
+            "",
+        )
+    };
+    let deferred = defer("2099-01-01");
+    let invalid = defer("someday");
+    std::env::set_var("VISUAL", r"sed -i 's/^### Answer$/### Answer\nBlue/'");
+    let answered = run(Command::Question(QuestionCommand::AnswerAll), "");
+    std::env::set_var("VISUAL", "false");
+    let failed = run(Command::Question(QuestionCommand::AnswerAll), "");
+    std::env::remove_var("VISUAL");
+
+    std::env::set_current_dir(original_dir).unwrap();
+    assert_eq!(deferred.exit_code, ExitCode::SUCCESS);
+    assert!(deferred.stdout[0].contains("\"deferred_until\": \"2099-01-01 00:00:00\""));
+    assert_eq!(invalid.exit_code, ExitCode::from(1));
+    assert_eq!(answered.exit_code, ExitCode::SUCCESS);
+    let report: serde_json::Value = serde_json::from_str(&answered.stdout[0]).unwrap();
+    assert_eq!(report["answered"], serde_json::json!([now.id]));
+    assert_eq!(store.get_question(&now.id).unwrap().unwrap().answer.as_deref(), Some("Blue"));
+    assert!(store.get_question(&later.id).unwrap().unwrap().answer.is_none());
+    // Only the deferred question is left, so there is nothing to answer
+    assert_eq!(failed.exit_code, ExitCode::SUCCESS);
+    assert_eq!(failed.stdout[0], "No unanswered questions.");
+}
+
+#[test]
+#[serial_test::serial]
+fn test_question_answer_pick_suggestion() {
+    use crate::tasks::{AnswerSuggestion, SqliteTaskStore, TaskStore};
+
+    let dir = TempDir::new().unwrap();
+    let original_dir = std::env::current_dir().unwrap();
+    std::env::set_current_dir(dir.path()).unwrap();
+    let store = SqliteTaskStore::for_project(dir.path()).unwrap();
+    let question = store.create_question("Which database?").unwrap();
+    let suggestions = vec![
+        AnswerSuggestion { answer: "Postgres".to_string(), tradeoffs: "Heavier".to_string() },
+        AnswerSuggestion { answer: "SQLite".to_string(), tradeoffs: "One writer".to_string() },
+    ];
+    store.set_question_suggestions(&question.id, &suggestions).unwrap();
+
+    let shown = run(Command::Question(QuestionCommand::Get { id: question.id.clone() }), "");
+    let pick = |pick: usize| {
+        run(
+            Command::Question(QuestionCommand::Answer {
+                id: question.id.clone(),
+                answer: None,
+                pick: Some(pick),
+            }),
+            "",
+        )
+    };
+    let out_of_range = pick(3);
+    let picked = pick(2);
+
+    std::env::set_current_dir(original_dir).unwrap();
+    let output: serde_json::Value = serde_json::from_str(&shown.stdout[0]).unwrap();
+    assert_eq!(output["suggestions"][1]["answer"], "SQLite");
+    assert_eq!(output["suggestions"][1]["tradeoffs"], "One writer");
+    assert_eq!(out_of_range.exit_code, ExitCode::from(1));
+    assert!(out_of_range.stderr[0].contains("has 2 suggested answer(s)"));
+    assert_eq!(picked.exit_code, ExitCode::SUCCESS);
+    assert_eq!(
+        store.get_question(&question.id).unwrap().unwrap().answer.as_deref(),
+        Some("SQLite")
+    );
+}
+
+#[test]
+#[serial_test::serial]
+fn test_question_reopen_assumption() {
+    use crate::tasks::{SqliteTaskStore, TaskStore};
+
+    let dir = TempDir::new().unwrap();
+    let original_dir = std::env::current_dir().unwrap();
+    std::env::set_current_dir(dir.path()).unwrap();
+    let store = SqliteTaskStore::for_project(dir.path()).unwrap();
+    let question = store.create_question("Which region?").unwrap();
+    store.assume_answer(&question.id, "eu-west-1").unwrap();
+
+    let shown = run(Command::Question(QuestionCommand::Get { id: question.id.clone() }), "");
+    let reopened = run(Command::Question(QuestionCommand::Reopen { id: question.id }), "");
+    let missing = run(Command::Question(QuestionCommand::Reopen { id: "nope".to_string() }), "");
+
+    std::env::set_current_dir(original_dir).unwrap();
+    let output: serde_json::Value = serde_json::from_str(&shown.stdout[0]).unwrap();
+    assert_eq!(output["assumed"], true);
+    assert_eq!(output["answer"], "eu-west-1");
+    assert_eq!(reopened.exit_code, ExitCode::SUCCESS);
+    let output: serde_json::Value = serde_json::from_str(&reopened.stdout[0]).unwrap();
+    assert!(output.get("assumed").is_none());
+    assert_eq!(output["is_answered"], false);
+    assert_eq!(missing.exit_code, ExitCode::from(1));
+}
+
+#[test]
+#[serial_test::serial]
+fn test_transcript_search() {
+    use crate::cli::TranscriptCommand;
+    use crate::storage::{SessionState, SqliteStore};
+
+    let dir = TempDir::new().unwrap();
+    let original_dir = std::env::current_dir().unwrap();
+    std::env::set_current_dir(dir.path()).unwrap();
+    let transcript = dir.path().join("s1.jsonl");
+    std::fs::write(
+        &transcript,
+        r#"{"type": "assistant", "timestamp": "2026-01-01T10:00:00Z", "message": {"content": [{"type": "tool_use", "name": "Write", "input": {"file_path": "docs/setup.md", "content": "Install it"}}]}}
+"#,
+    )
+    .unwrap();
+    let state = SqliteStore::new(dir.path()).unwrap();
+    state.touch_session("s1", Some(&transcript.to_string_lossy()), SessionState::Stopped).unwrap();
+
+    let search = |query: &str| {
+        run(
+            Command::Transcript(TranscriptCommand::Search {
+                query: query.to_string(),
+                session: None,
+                limit: None,
+            }),
+            "",
+        )
+    };
+    let found = search("docs/setup.md");
+    let missing = search("kubernetes");
+    let empty = search("  ");
+
+    std::env::set_current_dir(original_dir).unwrap();
+    assert_eq!(found.exit_code, ExitCode::SUCCESS);
+    let matches: serde_json::Value = serde_json::from_str(&found.stdout[0]).unwrap();
+    assert_eq!(matches[0]["session_id"], "s1");
+    assert_eq!(matches[0]["tool"], "Write");
+    assert_eq!(matches[0]["line"], 1);
+    assert_eq!(missing.stdout[0], "[]");
+    assert_eq!(empty.exit_code, ExitCode::from(1));
+}
+
+#[test]
+fn test_transcript_export() {
+    use crate::cli::TranscriptCommand;
+
+    let dir = TempDir::new().unwrap();
+    let transcript = dir.path().join("s1.jsonl");
+    std::fs::write(
+        &transcript,
+        r#"{"type": "user", "timestamp": "2026-01-01T10:00:00Z", "message": {"content": "Add a README"}}
+"#,
+    )
+    .unwrap();
+    let output = dir.path().join("s1.md");
+    let export = |format: &str, output: Option<PathBuf>| {
+        run(
+            Command::Transcript(TranscriptCommand::Export {
+                path: transcript.clone(),
+                format: format.to_string(),
+                output,
+            }),
+            "",
+        )
+    };
+
+    let printed = export("md", None);
+    assert_eq!(printed.exit_code, ExitCode::SUCCESS);
+    assert!(printed.stdout[0].starts_with("# Transcript: s1\n\n## User (2026-01-01 10:00:00 UTC)"));
+    assert!(printed.stdout[0].ends_with("Add a README"));
+
+    let written = export("markdown", Some(output.clone()));
+    assert_eq!(written.exit_code, ExitCode::SUCCESS);
+    assert!(std::fs::read_to_string(&output).unwrap().ends_with("Add a README\n"));
+
+    let invalid = export("html", None);
+    assert_eq!(invalid.exit_code, ExitCode::from(1));
+    assert!(invalid.stderr[0].contains("invalid transcript format"));
+}
+
 // === Audit log test ===
 
 #[test]
@@ -632,13 +1018,16 @@ fn test_audit_log() {
         Command::Work(WorkCommand::Create {
             title: "Audit test".to_string(),
             description: String::new(),
-            priority: 2,
+            priority: Some(2),
+            package: None,
+            force: false,
+            template: None,
         }),
         "",
     );
 
     // Get audit log
-    let output = run(Command::AuditLog { work_id: None, limit: None }, "");
+    let output = run(Command::AuditLog { command: None, work_id: None, limit: None }, "");
 
     assert_eq!(output.exit_code, ExitCode::SUCCESS);
     let entries: Vec<serde_json::Value> = serde_json::from_str(&output.stdout[0]).unwrap();
@@ -646,3 +1035,1023 @@ fn test_audit_log() {
 
     std::env::set_current_dir(original_dir).unwrap();
 }
+
+#[test]
+#[serial_test::serial]
+fn test_report() {
+    let dir = TempDir::new().unwrap();
+    let original_dir = std::env::current_dir().unwrap();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    run(
+        Command::Work(WorkCommand::Create {
+            title: "Report test".to_string(),
+            description: String::new(),
+            priority: Some(2),
+            package: None,
+            force: false,
+            template: None,
+        }),
+        "",
+    );
+    let output = run(Command::Report { since: "7d".to_string(), narrate: false }, "");
+    let invalid = run(Command::Report { since: "soon".to_string(), narrate: false }, "");
+
+    std::env::set_current_dir(original_dir).unwrap();
+    assert_eq!(output.exit_code, ExitCode::SUCCESS);
+    assert!(output.stdout[0].starts_with("# Activity Report"));
+    assert!(output.stdout[0].contains("- Created: 1\n"));
+    assert_eq!(invalid.exit_code, ExitCode::from(1));
+}
+
+#[test]
+#[serial_test::serial]
+fn test_audit_log_export() {
+    let dir = TempDir::new().unwrap();
+    let original_dir = std::env::current_dir().unwrap();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    run(
+        Command::Work(WorkCommand::Create {
+            title: "Export test".to_string(),
+            description: String::new(),
+            priority: Some(2),
+            package: None,
+            force: false,
+            template: None,
+        }),
+        "",
+    );
+    let export = |format: &str| {
+        run(
+            Command::AuditLog {
+                command: Some(AuditLogCommand::Export { format: format.to_string(), output: None }),
+                work_id: None,
+                limit: None,
+            },
+            "",
+        )
+    };
+    let output = export("csv");
+    let invalid = export("parquet");
+
+    std::env::set_current_dir(original_dir).unwrap();
+    assert_eq!(output.exit_code, ExitCode::SUCCESS);
+    let files: Vec<serde_json::Value> = serde_json::from_str(&output.stdout[0]).unwrap();
+    assert_eq!(files.len(), 4);
+    assert_eq!(files[0]["table"], "audit_log");
+    assert_eq!(files[0]["rows"], 1);
+    assert!(paths::project_exports_dir(dir.path()).join("task_transitions.csv").exists());
+    assert_eq!(invalid.exit_code, ExitCode::from(1));
+}
+
+#[test]
+#[serial_test::serial]
+fn test_disable_and_enable_hooks() {
+    let dir = TempDir::new().unwrap();
+    let original_dir = std::env::current_dir().unwrap();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    let disabled = run(Command::Disable { duration: None }, "");
+    let pre_tool_use = run(Command::PreToolUse { profile: false }, "not json");
+    let session_start = run(Command::SessionStart { profile: false }, "{}");
+    let enabled = run(Command::Enable, "");
+    let pre_tool_use_enabled = run(Command::PreToolUse { profile: false }, "not json");
+    let timed = run(Command::Disable { duration: Some("2h".to_string()) }, "");
+    let until = crate::session::hooks_disabled(dir.path());
+    let invalid = run(Command::Disable { duration: Some("soon".to_string()) }, "");
+    let enabled_again = run(Command::Enable, "");
+    let not_disabled = run(Command::Enable, "");
+
+    std::env::set_current_dir(original_dir).unwrap();
+
+    assert_eq!(disabled.exit_code, ExitCode::SUCCESS);
+    assert!(disabled.stdout[0].contains("claude-reliability enable"));
+    assert_eq!(pre_tool_use.exit_code, ExitCode::SUCCESS);
+    assert_eq!(pre_tool_use.stderr.len(), 1);
+    assert!(pre_tool_use.stderr[0].contains("hooks are disabled"));
+    assert!(session_start.stdout[0].contains("hooks are disabled"));
+
+    assert_eq!(enabled.stdout, vec!["Hooks enabled."]);
+    assert_eq!(pre_tool_use_enabled.exit_code, ExitCode::from(1));
+
+    assert!(timed.stdout[0].contains("disabled until"));
+    let Some(crate::session::HooksDisabled::Until(until)) = until else {
+        panic!("expected a time limit, got {until:?}");
+    };
+    let minutes = (until - chrono::Utc::now()).num_minutes();
+    assert!((118..=120).contains(&minutes), "unexpected length: {minutes}");
+    assert_eq!(invalid.exit_code, ExitCode::from(1));
+    assert_eq!(enabled_again.stdout, vec!["Hooks enabled."]);
+    assert_eq!(not_disabled.stdout, vec!["Hooks were not disabled."]);
+}
+
+#[test]
+#[serial_test::serial]
+fn test_work_snooze() {
+    let dir = TempDir::new().unwrap();
+    let original_dir = std::env::current_dir().unwrap();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    let snooze = |duration: &str| {
+        run(
+            Command::Work(WorkCommand::Snooze {
+                duration: Some(duration.to_string()),
+                clear: false,
+            }),
+            "",
+        )
+    };
+    let output = snooze("2h");
+    assert_eq!(output.exit_code, ExitCode::SUCCESS);
+    assert!(output.stdout[0].contains("snoozed until"));
+    let until = crate::session::auto_work_paused_until(std::path::Path::new(".")).unwrap();
+    let minutes = (until - chrono::Utc::now()).num_minutes();
+    assert!((118..=120).contains(&minutes), "unexpected snooze length: {minutes}");
+
+    assert_eq!(snooze("soon").exit_code, ExitCode::from(1));
+
+    let output = run(Command::Work(WorkCommand::Snooze { duration: None, clear: true }), "");
+    assert_eq!(output.exit_code, ExitCode::SUCCESS);
+    assert_eq!(crate::session::auto_work_paused_until(std::path::Path::new(".")), None);
+
+    std::env::set_current_dir(original_dir).unwrap();
+}
+
+#[test]
+#[serial_test::serial]
+fn test_work_assign() {
+    use crate::tasks::{Priority, SqliteTaskStore, TaskStore};
+
+    let dir = TempDir::new().unwrap();
+    let original_dir = std::env::current_dir().unwrap();
+    std::env::set_current_dir(dir.path()).unwrap();
+    std::env::remove_var("CLAUDE_RELIABILITY_SINGLE_WORK_ITEM");
+
+    let store = SqliteTaskStore::for_project(dir.path()).unwrap();
+    let task = store.create_task("Assigned task", "", Priority::Medium).unwrap();
+    let other = store.create_task("Other task", "", Priority::High).unwrap();
+
+    let assign = |id: Option<&str>, clear: bool| {
+        run(
+            Command::Work(WorkCommand::Assign {
+                id: id.map(str::to_string),
+                chain: true,
+                spawn: false,
+                clear,
+            }),
+            "",
+        )
+    };
+    let output = assign(Some(&task.id), false);
+    assert_eq!(output.exit_code, ExitCode::SUCCESS, "stderr: {:?}", output.stderr);
+    assert!(output.stdout[0].contains("Assigned task"));
+    assert!(output.stdout[0].contains("chaining"));
+
+    let output = run(Command::Work(WorkCommand::On { id: other.id.clone() }), "");
+    assert_eq!(output.exit_code, ExitCode::from(1));
+    assert!(output.stderr[0].contains("Single work item mode"));
+
+    assert_eq!(assign(Some("missing"), false).exit_code, ExitCode::from(1));
+
+    let output = assign(None, true);
+    assert_eq!(output.stdout, vec!["Work item assignment removed."]);
+    assert_eq!(crate::session::work_item_assignment(std::path::Path::new(".")), None);
+    assert_eq!(
+        run(Command::Work(WorkCommand::On { id: other.id }), "").exit_code,
+        ExitCode::SUCCESS
+    );
+
+    std::env::set_current_dir(original_dir).unwrap();
+}
+
+#[test]
+#[serial_test::serial]
+fn test_work_create_refuses_duplicates_without_force() {
+    let dir = TempDir::new().unwrap();
+    let original_dir = std::env::current_dir().unwrap();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    let create = |title: &str, force: bool| {
+        run(
+            Command::Work(WorkCommand::Create {
+                title: title.to_string(),
+                description: String::new(),
+                priority: Some(2),
+                package: None,
+                force,
+                template: None,
+            }),
+            "",
+        )
+    };
+    assert_eq!(create("Fix the login bug", false).exit_code, ExitCode::SUCCESS);
+
+    let output = create("Fix login bugs", false);
+    assert_eq!(output.exit_code, ExitCode::from(1));
+    let message = output.stderr.join("\n");
+    assert!(message.contains("Similar open work items"), "stderr: {message}");
+    assert!(message.contains("Fix the login bug"), "stderr: {message}");
+
+    assert_eq!(create("Fix login bugs", true).exit_code, ExitCode::SUCCESS);
+    assert_eq!(create("Write release notes", false).exit_code, ExitCode::SUCCESS);
+
+    std::env::set_current_dir(original_dir).unwrap();
+}
+
+#[test]
+#[serial_test::serial]
+fn test_work_merge_and_split() {
+    use crate::tasks::{Priority, SqliteTaskStore, TaskStore};
+
+    let dir = TempDir::new().unwrap();
+    let original_dir = std::env::current_dir().unwrap();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    let store = SqliteTaskStore::new(paths::project_db_path(dir.path())).unwrap();
+    let keep = store.create_task("Keep", "", Priority::Medium).unwrap();
+    let other = store.create_task("Other", "", Priority::Medium).unwrap();
+
+    let output =
+        run(Command::Work(WorkCommand::Merge { id: keep.id.clone(), other: other.id.clone() }), "");
+    assert_eq!(output.exit_code, ExitCode::SUCCESS);
+    assert!(store.get_task(&other.id).unwrap().is_none());
+
+    let output = run(
+        Command::Work(WorkCommand::Split {
+            id: keep.id.clone(),
+            titles: vec!["Part one".to_string(), "Part two".to_string()],
+        }),
+        "",
+    );
+    assert_eq!(output.exit_code, ExitCode::SUCCESS);
+    let subtasks: serde_json::Value = serde_json::from_str(&output.stdout[0]).unwrap();
+    assert_eq!(subtasks.as_array().unwrap().len(), 2);
+    assert_eq!(store.get_dependencies(&keep.id).unwrap().len(), 2);
+
+    let output = run(Command::Work(WorkCommand::Merge { id: keep.id.clone(), other: keep.id }), "");
+    assert_eq!(output.exit_code, ExitCode::from(1));
+
+    std::env::set_current_dir(original_dir).unwrap();
+}
+
+#[test]
+#[serial_test::serial]
+fn test_work_update_checks_definition_of_done() {
+    use crate::tasks::{Priority, SqliteTaskStore, Status, TaskStore};
+
+    let dir = TempDir::new().unwrap();
+    let original_dir = std::env::current_dir().unwrap();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    let project_config = crate::config::ProjectConfig {
+        definition_of_done: vec!["tests_updated".to_string(), "Changelog updated".to_string()],
+        ..Default::default()
+    };
+    project_config.save_to(dir.path()).unwrap();
+    let store = SqliteTaskStore::new(paths::project_db_path(dir.path())).unwrap();
+    let task = store.create_task("Ship it", "", Priority::High).unwrap();
+    let complete = |confirm_dod: &[&str]| {
+        run(
+            Command::Work(WorkCommand::Update {
+                id: task.id.clone(),
+                title: None,
+                description: None,
+                priority: None,
+                status: Some("complete".to_string()),
+                package: None,
+                confirm_dod: confirm_dod.iter().map(ToString::to_string).collect(),
+            }),
+            "",
+        )
+    };
+
+    let output = complete(&[]);
+    assert_eq!(output.exit_code, ExitCode::from(1));
+    let message = output.stderr.join("\n");
+    assert!(message.contains("tests_updated: no test files were changed"), "{message}");
+    assert!(message.contains("Changelog updated: needs acknowledging"), "{message}");
+    assert_eq!(store.get_task(&task.id).unwrap().unwrap().status, Status::Open);
+
+    crate::session::record_touched_file(dir.path(), "tests/ship.rs").unwrap();
+    let output = complete(&["changelog updated"]);
+    assert_eq!(output.exit_code, ExitCode::SUCCESS, "{:?}", output.stderr);
+    assert_eq!(store.get_task(&task.id).unwrap().unwrap().status, Status::Complete);
+
+    let audit = store.get_audit_log(Some(&task.id), None).unwrap();
+    let confirmation = audit.iter().find(|e| e.operation == "confirm_dod").unwrap();
+    assert_eq!(confirmation.details.as_deref(), Some("Changelog updated"));
+
+    std::env::set_current_dir(original_dir).unwrap();
+}
+
+#[test]
+#[serial_test::serial]
+fn test_work_template() {
+    use crate::cli::TemplateCommand;
+    use crate::tasks::{Priority, SqliteTaskStore, TaskStore};
+
+    let dir = TempDir::new().unwrap();
+    let original_dir = std::env::current_dir().unwrap();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    let output = run(
+        Command::Work(WorkCommand::Template(TemplateCommand::Save {
+            name: "bug".to_string(),
+            description: "Steps to reproduce:".to_string(),
+            priority: 1,
+            checklist: vec!["regression test added".to_string()],
+            howtos: vec![],
+        })),
+        "",
+    );
+    assert_eq!(output.exit_code, ExitCode::SUCCESS, "stderr: {:?}", output.stderr);
+
+    let create = |template: &str| {
+        run(
+            Command::Work(WorkCommand::Create {
+                title: "Crash on save".to_string(),
+                description: String::new(),
+                priority: None,
+                package: None,
+                force: true,
+                template: Some(template.to_string()),
+            }),
+            "",
+        )
+    };
+    let output = create("bug");
+    assert_eq!(output.exit_code, ExitCode::SUCCESS, "stderr: {:?}", output.stderr);
+    let created: serde_json::Value = serde_json::from_str(&output.stdout[0]).unwrap();
+    let store = SqliteTaskStore::new(paths::project_db_path(dir.path())).unwrap();
+    let task = store.get_task(created["id"].as_str().unwrap()).unwrap().unwrap();
+    assert_eq!(task.priority, Priority::High);
+    assert_eq!(task.description, "Steps to reproduce:");
+    assert_eq!(store.get_checklist(&task.id).unwrap().len(), 1);
+    assert_eq!(create("feature").exit_code, ExitCode::from(1));
+
+    let output =
+        run(Command::Work(WorkCommand::Template(TemplateCommand::Export { name: None })), "");
+    assert_eq!(output.exit_code, ExitCode::SUCCESS);
+    let exported = &output.stdout[0];
+    assert!(exported.contains("[[template]]"), "{exported}");
+    assert!(exported.contains("name = \"bug\""), "{exported}");
+    assert!(exported.contains("regression test added"), "{exported}");
+
+    let output = run(Command::Work(WorkCommand::Template(TemplateCommand::List)), "");
+    let templates: serde_json::Value = serde_json::from_str(&output.stdout[0]).unwrap();
+    assert_eq!(templates[0]["name"], "bug");
+
+    let delete = || {
+        run(
+            Command::Work(WorkCommand::Template(TemplateCommand::Delete {
+                name: "bug".to_string(),
+            })),
+            "",
+        )
+    };
+    assert_eq!(delete().exit_code, ExitCode::SUCCESS);
+    assert_eq!(delete().exit_code, ExitCode::from(1));
+    let output = run(
+        Command::Work(WorkCommand::Template(TemplateCommand::Show { name: "bug".to_string() })),
+        "",
+    );
+    assert_eq!(output.exit_code, ExitCode::from(1));
+
+    std::env::set_current_dir(original_dir).unwrap();
+}
+
+#[test]
+#[serial_test::serial]
+fn test_work_check() {
+    use crate::tasks::{Priority, SqliteTaskStore, Status, TaskStore, TaskUpdate};
+
+    let dir = TempDir::new().unwrap();
+    let original_dir = std::env::current_dir().unwrap();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    let store = SqliteTaskStore::new(paths::project_db_path(dir.path())).unwrap();
+    let task = store.create_task("Ship it", "", Priority::High).unwrap();
+    store.request_tasks(&[&task.id]).unwrap();
+    let check = |add: &[&str], done: Vec<u32>| {
+        run(
+            Command::Work(WorkCommand::Check {
+                id: task.id.clone(),
+                add: add.iter().map(ToString::to_string).collect(),
+                done,
+            }),
+            "",
+        )
+    };
+
+    let output = check(&["unit tests added", "docs updated"], vec![]);
+    assert_eq!(output.exit_code, ExitCode::SUCCESS);
+    let checklist: serde_json::Value = serde_json::from_str(&output.stdout[0]).unwrap();
+    assert_eq!(checklist[1]["position"], 2);
+    assert_eq!(checklist[1]["done"], false);
+
+    let complete = TaskUpdate { status: Some(Status::Complete), ..Default::default() };
+    assert!(store.update_task(&task.id, complete.clone()).is_err());
+
+    let output = check(&[], vec![1, 2]);
+    let checklist: serde_json::Value = serde_json::from_str(&output.stdout[0]).unwrap();
+    assert_eq!(checklist[0]["done"], true);
+    assert!(store.update_task(&task.id, complete).is_ok());
+
+    let output = run(Command::Work(WorkCommand::Get { id: task.id.clone() }), "");
+    assert!(output.stdout[0].contains("unit tests added"));
+
+    assert_eq!(check(&[], vec![7]).exit_code, ExitCode::from(1));
+    let output = run(
+        Command::Work(WorkCommand::Check { id: "missing".to_string(), add: vec![], done: vec![] }),
+        "",
+    );
+    assert_eq!(output.exit_code, ExitCode::from(1));
+
+    std::env::set_current_dir(original_dir).unwrap();
+}
+
+#[test]
+#[serial_test::serial]
+fn test_work_graph() {
+    let dir = TempDir::new().unwrap();
+    let original_dir = std::env::current_dir().unwrap();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    run(
+        Command::Work(WorkCommand::Create {
+            title: "Graph me".to_string(),
+            description: String::new(),
+            priority: Some(2),
+            package: None,
+            force: false,
+            template: None,
+        }),
+        "",
+    );
+    let graph =
+        |format: &str| run(Command::Work(WorkCommand::Graph { format: format.to_string() }), "");
+
+    let output = graph("dot");
+    assert_eq!(output.exit_code, ExitCode::SUCCESS);
+    assert!(output.stdout[0].starts_with("digraph work {"));
+    assert!(output.stdout[0].contains("Graph me"));
+
+    let output = graph("mermaid");
+    assert!(output.stdout[0].starts_with("flowchart LR"));
+
+    assert_eq!(graph("svg").exit_code, ExitCode::from(1));
+
+    std::env::set_current_dir(original_dir).unwrap();
+}
+
+#[test]
+#[serial_test::serial]
+fn test_work_import() {
+    let dir = TempDir::new().unwrap();
+    let original_dir = std::env::current_dir().unwrap();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    std::fs::write("TODO.md", "- [ ] Ship release P1\n  - [ ] Write changelog\n").unwrap();
+    let import = |format: &str, file: &str| {
+        run(
+            Command::Work(WorkCommand::Import {
+                format: format.to_string(),
+                file: PathBuf::from(file),
+            }),
+            "",
+        )
+    };
+    let imported = import("todo-md", "TODO.md");
+    let bad_format = import("jira", "TODO.md");
+    let missing = import("beads", "missing.jsonl");
+
+    std::env::set_current_dir(original_dir).unwrap();
+
+    assert_eq!(imported.exit_code, ExitCode::SUCCESS, "stderr: {:?}", imported.stderr);
+    let report: serde_json::Value = serde_json::from_str(&imported.stdout[0]).unwrap();
+    assert_eq!(report["created"], 2);
+    assert_eq!(report["dependencies_added"], 1);
+    assert!(bad_format.stderr[0].contains("invalid import format"));
+    assert!(missing.stderr[0].starts_with("Failed to read missing.jsonl"));
+}
+
+#[test]
+#[serial_test::serial]
+fn test_work_blockers() {
+    use crate::tasks::{Priority, SqliteTaskStore, TaskStore};
+
+    let dir = TempDir::new().unwrap();
+    let original_dir = std::env::current_dir().unwrap();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    let output = run(Command::Work(WorkCommand::Blockers), "");
+    assert_eq!(output.exit_code, ExitCode::SUCCESS);
+    assert!(output.stdout[0].contains("Nothing is blocking"));
+
+    let store = SqliteTaskStore::new(paths::project_db_path(dir.path())).unwrap();
+    let goal = store.create_task("Goal", "", Priority::High).unwrap();
+    let setup = store.create_task("Setup", "", Priority::Low).unwrap();
+    store.add_dependency(&goal.id, &setup.id).unwrap();
+    store.request_tasks(&[goal.id.as_str()]).unwrap();
+
+    let output = run(Command::Work(WorkCommand::Blockers), "");
+    assert_eq!(output.exit_code, ExitCode::SUCCESS);
+    let blockers: serde_json::Value = serde_json::from_str(&output.stdout[0]).unwrap();
+    assert_eq!(blockers[0]["id"], setup.id);
+    assert_eq!(blockers[0]["blocks"], 1);
+
+    std::env::set_current_dir(original_dir).unwrap();
+}
+
+#[test]
+fn test_hook_crash_output() {
+    let crash = crate::hooks::HookCrash {
+        hook: "stop".to_string(),
+        message: "database is locked".to_string(),
+        location: None,
+        crash_file: None,
+    };
+    let output = run::hook_crash_output(&crash, crate::config::FailureMode::Open);
+    assert_eq!(output.exit_code, ExitCode::SUCCESS);
+    assert_eq!(
+        output.stderr,
+        vec!["The stop hook crashed: database is locked. Allowing, as failure_mode is open."]
+    );
+
+    let output = run::hook_crash_output(&crash, crate::config::FailureMode::Closed);
+    assert_eq!(output.exit_code, ExitCode::from(2));
+    assert!(output.stderr[0].ends_with("Blocking, as failure_mode is closed."));
+}
+
+#[test]
+fn test_parse_duration_minutes() {
+    assert_eq!(run::parse_duration_minutes("45"), Ok(45));
+    assert_eq!(run::parse_duration_minutes("30m"), Ok(30));
+    assert_eq!(run::parse_duration_minutes("2h"), Ok(120));
+    assert_eq!(run::parse_duration_minutes("1 day"), Ok(1440));
+    assert!(run::parse_duration_minutes("0m").is_err());
+    assert!(run::parse_duration_minutes("2w").is_err());
+    assert!(run::parse_duration_minutes("").is_err());
+}
+
+// === Handoff tests ===
+
+#[test]
+#[serial_test::serial]
+fn test_handoff_latest() {
+    let dir = TempDir::new().unwrap();
+    let original_dir = std::env::current_dir().unwrap();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    let output = run(Command::Handoff(HandoffCommand::Latest), "");
+    assert_eq!(output.exit_code, ExitCode::from(1));
+
+    let handoffs = paths::project_handoffs_dir(std::path::Path::new("."));
+    std::fs::create_dir_all(&handoffs).unwrap();
+    std::fs::write(handoffs.join("2026-01-01T00-00-00Z.md"), "# Older\n").unwrap();
+    std::fs::write(handoffs.join("2026-02-01T00-00-00Z.md"), "# Session Handoff\n").unwrap();
+
+    let output = run(Command::Handoff(HandoffCommand::Latest), "");
+    assert_eq!(output.exit_code, ExitCode::SUCCESS);
+    assert_eq!(output.stdout, vec!["# Session Handoff\n"]);
+
+    std::env::set_current_dir(original_dir).unwrap();
+}
+
+// === Metrics tests ===
+
+#[test]
+#[serial_test::serial]
+fn test_stop_hook_exports_metrics() {
+    let dir = TempDir::new().unwrap();
+    let original_dir = std::env::current_dir().unwrap();
+    std::env::set_current_dir(dir.path()).unwrap();
+    let config = crate::config::ProjectConfig {
+        metrics_file: Some("metrics.prom".to_string()),
+        require_push: false,
+        ..Default::default()
+    };
+    config.save_to(dir.path()).unwrap();
+
+    run(Command::Stop { profile: false }, "{}");
+
+    let text = std::fs::read_to_string(dir.path().join("metrics.prom")).unwrap();
+    assert!(text.contains(r#"claude_reliability_stop_decisions_total{decision="allow""#));
+    let output = run(Command::Metrics, "");
+    assert_eq!(output.exit_code, ExitCode::SUCCESS);
+    assert_eq!(output.stdout[0].trim_end(), text.trim_end());
+
+    std::env::set_current_dir(original_dir).unwrap();
+}
+
+// === Doctor tests ===
+
+#[test]
+#[serial_test::serial]
+fn test_doctor_shows_effective_settings() {
+    let dir = TempDir::new().unwrap();
+    let original_dir = std::env::current_dir().unwrap();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    let output = run(Command::Doctor, "");
+    assert_eq!(output.exit_code, ExitCode::SUCCESS);
+    assert!(output.stdout[0].contains("not found, using defaults"));
+    assert!(output.stdout[0].contains("  user_recency_minutes: 5\n"));
+
+    let config = crate::config::ProjectConfig {
+        user_recency_minutes: 30,
+        max_files_to_show: 25,
+        auto_work_hours: Some("9am-5pm".to_string()),
+        ..Default::default()
+    };
+    config.save_to(dir.path()).unwrap();
+    let output = run(Command::Doctor, "");
+
+    std::env::set_current_dir(original_dir).unwrap();
+
+    let text = &output.stdout[0];
+    assert!(text.contains("  user_recency_minutes: 30 (default: 5)"), "{text}");
+    assert!(text.contains("  max_files_to_show: 25 (default: 10)"), "{text}");
+    assert!(text.contains("  api_error_threshold: 1\n"), "{text}");
+    assert!(text.contains("  auto_work_hours: 9am-5pm (Config error: invalid schedule"), "{text}");
+}
+
+#[test]
+#[serial_test::serial]
+fn test_maintenance_run() {
+    use crate::cli::MaintenanceCommand;
+
+    let dir = TempDir::new().unwrap();
+    let original_dir = std::env::current_dir().unwrap();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    let output = run(Command::Maintenance(MaintenanceCommand::Run), "");
+
+    std::env::set_current_dir(original_dir).unwrap();
+
+    assert_eq!(output.exit_code, ExitCode::SUCCESS, "stderr: {:?}", output.stderr);
+    let report: serde_json::Value = serde_json::from_str(&output.stdout[0]).unwrap();
+    assert_eq!(report["indexes_rebuilt"], 4);
+    assert!(report["size_bytes"].as_u64().unwrap() > 0);
+}
+
+#[test]
+#[serial_test::serial]
+fn test_backup_now_list_and_restore() {
+    use crate::cli::BackupCommand;
+
+    let dir = TempDir::new().unwrap();
+    let original_dir = std::env::current_dir().unwrap();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    let no_database = run(Command::Backup(BackupCommand::Now), "");
+    crate::tasks::SqliteTaskStore::for_project(dir.path()).unwrap();
+    let backup = run(Command::Backup(BackupCommand::Now), "");
+    let listed = run(Command::Backup(BackupCommand::List), "");
+    let backup_path: serde_json::Value = serde_json::from_str(&backup.stdout[0]).unwrap();
+    let file = PathBuf::from(backup_path["path"].as_str().unwrap());
+    let restored = run(Command::Backup(BackupCommand::Restore { file }), "");
+
+    std::env::set_current_dir(original_dir).unwrap();
+
+    assert_eq!(no_database.exit_code, ExitCode::from(1));
+    assert!(no_database.stderr[0].starts_with("Backup failed: File not found"));
+    assert_eq!(backup.exit_code, ExitCode::SUCCESS, "stderr: {:?}", backup.stderr);
+    let backups: Vec<serde_json::Value> = serde_json::from_str(&listed.stdout[0]).unwrap();
+    assert_eq!(backups.len(), 1);
+    assert_eq!(restored.exit_code, ExitCode::SUCCESS, "stderr: {:?}", restored.stderr);
+    let report: serde_json::Value = serde_json::from_str(&restored.stdout[0]).unwrap();
+    assert!(report["previous_database"].as_str().unwrap().contains("pre-restore-"));
+}
+
+#[test]
+#[serial_test::serial]
+fn test_stop_overloaded_backoff_reaches_stderr() {
+    let dir = TempDir::new().unwrap();
+    let original_dir = std::env::current_dir().unwrap();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    let transcript = dir.path().join("transcript.jsonl");
+    let error = serde_json::json!({
+        "type": "assistant",
+        "isApiErrorMessage": true,
+        "message": {"content": [{"type": "text", "text": "API Error: 529 overloaded_error"}]},
+    });
+    std::fs::write(&transcript, format!("{error}\n")).unwrap();
+    let input = serde_json::json!({"transcript_path": transcript}).to_string();
+    let first = run(Command::Stop { profile: false }, &input);
+    let second = run(Command::Stop { profile: false }, &input);
+
+    std::env::set_current_dir(original_dir).unwrap();
+    assert_eq!(first.exit_code, ExitCode::from(2));
+    assert!(first.stderr.join("\n").contains("The API was temporarily overloaded"));
+    assert_eq!(second.exit_code, ExitCode::from(2));
+    assert!(second.stderr.join("\n").contains("Still backing off from the overloaded API"));
+}
+
+// === Session registry tests ===
+
+#[test]
+#[serial_test::serial]
+fn test_hooks_update_session_registry() {
+    let dir = TempDir::new().unwrap();
+    let original_dir = std::env::current_dir().unwrap();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    let input = r#"{"session_id": "s1", "transcript_path": "/t/s1.jsonl"}"#;
+    run(Command::UserPromptSubmit { profile: false }, input);
+
+    let output = run(Command::Session(SessionCommand::List { alive: true }), "");
+    assert_eq!(output.exit_code, ExitCode::SUCCESS);
+    let sessions: Vec<serde_json::Value> = serde_json::from_str(&output.stdout[0]).unwrap();
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0]["session_id"], "s1");
+    assert_eq!(sessions[0]["transcript_path"], "/t/s1.jsonl");
+    assert_eq!(sessions[0]["state"], "working");
+    assert_eq!(sessions[0]["alive"], true);
+
+    // A stop that is allowed means the session is no longer alive
+    run(Command::Stop { profile: false }, input);
+    let output = run(Command::Session(SessionCommand::List { alive: true }), "");
+    assert_eq!(output.stdout, vec!["[]"]);
+    let output = run(Command::Session(SessionCommand::List { alive: false }), "");
+    let sessions: Vec<serde_json::Value> = serde_json::from_str(&output.stdout[0]).unwrap();
+    assert_eq!(sessions[0]["state"], "stopped");
+
+    std::env::set_current_dir(original_dir).unwrap();
+}
+
+#[test]
+#[serial_test::serial]
+fn test_hooks_scope_markers_to_their_session() {
+    let dir = TempDir::new().unwrap();
+    let original_dir = std::env::current_dir().unwrap();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    run(
+        Command::UserPromptSubmit { profile: false },
+        r#"{"session_id": "s1", "transcript_path": "/t/x.jsonl"}"#,
+    );
+    let from_id = crate::session::current_session();
+    run(Command::UserPromptSubmit { profile: false }, r#"{"transcript_path": "/t/s2.jsonl"}"#);
+    let from_transcript = crate::session::current_session();
+    run(Command::Metrics, "");
+    let unscoped = crate::session::current_session();
+
+    std::env::set_current_dir(original_dir).unwrap();
+
+    assert_eq!(from_id.as_deref(), Some("s1"));
+    assert_eq!(from_transcript.as_deref(), Some("s2"));
+    assert_eq!(unscoped, None);
+}
+
+#[test]
+#[serial_test::serial]
+fn test_session_reset() {
+    let dir = TempDir::new().unwrap();
+    let original_dir = std::env::current_dir().unwrap();
+    std::env::set_current_dir(dir.path()).unwrap();
+    let base = std::path::Path::new(".");
+
+    crate::session::enter_problem_mode(base).unwrap();
+    crate::session::set_emergency_stop(base).unwrap();
+
+    let unknown =
+        run(Command::Session(SessionCommand::Reset { marker: Some("bogus".to_string()) }), "");
+    let one = run(
+        Command::Session(SessionCommand::Reset { marker: Some("emergency_stop".to_string()) }),
+        "",
+    );
+    let still_in_problem_mode = crate::session::is_problem_mode_active(base);
+    let all = run(Command::Session(SessionCommand::Reset { marker: None }), "");
+    let again = run(Command::Session(SessionCommand::Reset { marker: None }), "");
+    let problem_mode_after = crate::session::is_problem_mode_active(base);
+
+    std::env::set_current_dir(original_dir).unwrap();
+
+    assert_eq!(unknown.exit_code, ExitCode::from(1));
+    assert!(unknown.stderr[0].contains("Known markers: problem_mode"));
+    assert_eq!(one.stdout, vec!["Cleared markers: emergency_stop"]);
+    assert!(still_in_problem_mode);
+    assert_eq!(all.stdout, vec!["Cleared markers: problem_mode"]);
+    assert_eq!(again.stdout, vec!["No markers were set"]);
+    assert!(!problem_mode_after);
+}
+
+#[test]
+#[serial_test::serial]
+fn test_session_problems() {
+    use crate::tasks::{Priority, SqliteTaskStore, TaskStore};
+
+    let dir = TempDir::new().unwrap();
+    let original_dir = std::env::current_dir().unwrap();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    let none = run(Command::Session(SessionCommand::Problems { limit: 20 }), "");
+    let store = SqliteTaskStore::for_project(dir.path()).unwrap();
+    let task = store.create_task("Deploy", "", Priority::High).unwrap();
+    store.record_problem("credentials", "No key", None, Some(&task.id), "s1").unwrap();
+    store.record_problem("other", "Odd", None, None, "s1").unwrap();
+    store.record_problem("credentials", "Still no key", None, Some(&task.id), "s2").unwrap();
+    let output = run(Command::Session(SessionCommand::Problems { limit: 2 }), "");
+
+    std::env::set_current_dir(original_dir).unwrap();
+
+    assert_eq!(none.stdout, vec!["[]"]);
+    assert_eq!(output.exit_code, ExitCode::SUCCESS);
+    let problems: Vec<serde_json::Value> = serde_json::from_str(&output.stdout[0]).unwrap();
+    assert_eq!(problems.len(), 2);
+    assert_eq!(problems[0]["explanation"], "Still no key");
+    assert_eq!(problems[0]["task_id"], task.id.as_str());
+    assert_eq!(problems[0]["task_problem_count"], 2);
+    assert_eq!(problems[1]["category"], "other");
+    assert_eq!(problems[1]["task_problem_count"], 0);
+}
+
+#[test]
+fn test_parse_duration_millis() {
+    assert_eq!(run::parse_duration_millis("150"), Ok(150));
+    assert_eq!(run::parse_duration_millis("200ms"), Ok(200));
+    assert_eq!(run::parse_duration_millis("2s"), Ok(2000));
+    assert!(run::parse_duration_millis("0ms").is_err());
+    assert!(run::parse_duration_millis("1m").is_err());
+    assert!(run::parse_duration_millis("").is_err());
+}
+
+#[test]
+#[serial_test::serial]
+fn test_bench() {
+    let output = run(
+        Command::Bench {
+            size: Some("small".to_string()),
+            runs: 2,
+            assert_under: Some("60s".to_string()),
+        },
+        "",
+    );
+    assert_eq!(output.exit_code, ExitCode::SUCCESS);
+    assert!(output.stdout[0].starts_with("small (10 transcript turns, 10 work items): median "));
+    assert!(output.stdout[0].ends_with(" over 2 runs"));
+
+    let over_budget = run(
+        Command::Bench {
+            size: Some("small".to_string()),
+            runs: 1,
+            assert_under: Some("1ms".to_string()),
+        },
+        "",
+    );
+    assert_eq!(over_budget.exit_code, ExitCode::from(1));
+    assert_eq!(over_budget.stdout.len(), 1);
+    assert_eq!(over_budget.stderr, vec!["Over the 1ms budget: small"]);
+
+    let invalid =
+        run(Command::Bench { size: Some("huge".to_string()), runs: 1, assert_under: None }, "");
+    assert!(invalid.stderr[0].contains("small, medium, large"));
+    let invalid =
+        run(Command::Bench { size: None, runs: 1, assert_under: Some("fast".to_string()) }, "");
+    assert!(invalid.stderr[0].contains("use e.g. 200ms"));
+}
+
+#[test]
+#[serial_test::serial]
+fn test_simulate_stop() {
+    let dir = TempDir::new().unwrap();
+    let original_dir = std::env::current_dir().unwrap();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    let output =
+        run(Command::Simulate(SimulateCommand::Stop { scenario: "uncommitted".to_string() }), "");
+    let invalid =
+        run(Command::Simulate(SimulateCommand::Stop { scenario: "bogus".to_string() }), "");
+    let project_files: Vec<_> = std::fs::read_dir(dir.path()).unwrap().collect();
+
+    std::env::set_current_dir(original_dir).unwrap();
+
+    assert_eq!(output.exit_code, ExitCode::SUCCESS);
+    assert!(output.stdout[0].starts_with("Scenario: uncommitted ("));
+    assert!(output.stdout[0].contains("Decision: block by uncommitted_changes (exit code 2)"));
+    assert!(output.stdout[0].contains("  api_error_loop: no errors"));
+    assert_eq!(invalid.exit_code, ExitCode::from(1));
+    assert!(invalid.stderr[0].contains("uncommitted, api-error, question"));
+    assert!(project_files.is_empty());
+}
+
+#[test]
+#[serial_test::serial]
+fn test_session_pause() {
+    let dir = TempDir::new().unwrap();
+    let original_dir = std::env::current_dir().unwrap();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    let empty = run(Command::Session(SessionCommand::Pause { reason: "  ".to_string() }), "");
+    let output =
+        run(Command::Session(SessionCommand::Pause { reason: "back at 3pm".to_string() }), "");
+    let pending = crate::session::pending_pause(std::path::Path::new("."));
+
+    std::env::set_current_dir(original_dir).unwrap();
+
+    assert_eq!(empty.exit_code, ExitCode::from(1));
+    assert_eq!(output.exit_code, ExitCode::SUCCESS);
+    assert!(output.stdout[0].contains("Session paused"));
+    assert_eq!(pending.as_deref(), Some("back at 3pm"));
+}
+
+#[test]
+#[serial_test::serial]
+fn test_session_scrub() {
+    use crate::tasks::{SqliteTaskStore, TaskStore};
+
+    let dir = TempDir::new().unwrap();
+    let original_dir = std::env::current_dir().unwrap();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    // Recorded before redaction existed
+    let db_path = paths::project_db_path(dir.path());
+    std::fs::create_dir_all(db_path.parent().unwrap()).unwrap();
+    let store = SqliteTaskStore::new(&db_path).unwrap();
+    store.record_user_message("Use password=hunter22222", "opening prompt", None, "s1").unwrap();
+
+    let output = run(Command::Session(SessionCommand::Scrub), "");
+    assert_eq!(output.exit_code, ExitCode::SUCCESS);
+    assert_eq!(
+        output.stdout,
+        vec!["Redacted secrets in 1 user message(s) and 0 sub-agent log entry(ies)"]
+    );
+    let messages = store.get_session_user_messages("s1").unwrap();
+    assert_eq!(messages[0].message, "Use password=[REDACTED:credential]");
+
+    std::env::set_current_dir(original_dir).unwrap();
+}
+
+#[test]
+#[serial_test::serial]
+fn test_replay_rejects_bad_arguments() {
+    let dir = TempDir::new().unwrap();
+    let original_dir = std::env::current_dir().unwrap();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    let replay = |hook: &str, input: &str| {
+        run(
+            Command::Replay {
+                hook: hook.to_string(),
+                input: PathBuf::from(input),
+                transcript: None,
+                at: None,
+            },
+            "",
+        )
+    };
+
+    let output = replay("pre-commit", "input.json");
+    assert_eq!(output.exit_code, ExitCode::from(1));
+    assert!(output.stderr[0].starts_with("Unknown hook 'pre-commit'"));
+
+    let output = replay("stop", "missing.json");
+    assert_eq!(output.exit_code, ExitCode::from(1));
+    assert!(output.stderr[0].starts_with("Could not read missing.json"));
+
+    std::fs::write("input.json", "[1, 2]").unwrap();
+    let output = replay("stop", "input.json");
+    assert_eq!(output.exit_code, ExitCode::from(1));
+    assert!(output.stderr[0].contains("hook input must be a JSON object"));
+
+    std::env::set_current_dir(original_dir).unwrap();
+}
+
+#[test]
+fn test_replay_stop_config_switches_off_outside_effects() {
+    let project_config = crate::config::ProjectConfig {
+        auto_push: true,
+        auto_wip_commit: true,
+        require_pr: true,
+        handoff_summaries: true,
+        assume_after_hours: 24,
+        escalation: crate::config::EscalationPolicy { github_issues: true, ..Default::default() },
+        ..Default::default()
+    };
+
+    let config = run::replay_stop_config(project_config);
+    assert!(!config.auto_push);
+    assert!(!config.auto_wip_commit);
+    assert!(!config.require_pr);
+    assert!(!config.handoff_summaries);
+    assert_eq!(config.assume_after_hours, 0);
+    assert!(!config.escalation.is_enabled());
+    assert_eq!(config.message_verbosity, crate::config::MessageVerbosity::Verbose);
+}
+
+#[test]
+fn test_hook_cwd_from_input() {
+    let cwd = run::hook_cwd(r#"{"session_id": "abc", "cwd": "/work/repo-feature"}"#);
+    assert_eq!(cwd, Some(std::path::PathBuf::from("/work/repo-feature")));
+}
+
+#[test]
+fn test_hook_cwd_missing_or_invalid() {
+    assert_eq!(run::hook_cwd(r#"{"session_id": "abc"}"#), None);
+    assert_eq!(run::hook_cwd(r#"{"cwd": ""}"#), None);
+    assert_eq!(run::hook_cwd("not json"), None);
+    assert_eq!(run::hook_cwd(""), None);
+}
@@ -0,0 +1,49 @@
+//! Transcript CLI subcommands.
+//!
+//! Provides commands for searching the transcripts of the sessions the hooks
+//! have seen in this project, and for exporting a transcript to share.
+
+use clap::Subcommand;
+use std::path::PathBuf;
+
+/// Transcript commands.
+#[derive(Subcommand, Debug, Clone)]
+pub enum TranscriptCommand {
+    /// Search session transcripts for messages and tool calls.
+    ///
+    /// Indexes the transcripts of recorded sessions first, reading only what
+    /// was added since the last search. Every word of the query must match
+    /// a message, or a tool call's name or input, so this finds when the
+    /// agent touched a file (`transcript search src/main.rs`) or discussed a
+    /// topic. Matches are listed most recent first.
+    Search {
+        /// Search query
+        query: String,
+
+        /// Only search this session's transcript
+        #[arg(long)]
+        session: Option<String>,
+
+        /// Maximum number of results
+        #[arg(short, long)]
+        limit: Option<usize>,
+    },
+
+    /// Render a JSONL transcript as readable Markdown.
+    ///
+    /// Shows each user and assistant turn with its timestamp, and collapses
+    /// each tool call into a `<details>` block with its input. Tool results,
+    /// API errors and system reminders are left out.
+    Export {
+        /// The transcript file
+        path: PathBuf,
+
+        /// Output format: md
+        #[arg(long, default_value = "md")]
+        format: String,
+
+        /// File to write to (default: print to stdout)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
@@ -10,7 +10,7 @@ use clap::Subcommand;
 /// Work items are the core unit of task tracking. Each item has:
 /// - A title and optional description
 /// - A priority (0=critical to 4=backlog)
-/// - A status (open, complete, abandoned, stuck, blocked)
+/// - A status (open, complete, abandoned, stuck, blocked, `in_review`, `blocked_external`)
 /// - Optional dependencies on other work items
 /// - Optional linked how-to guides and questions
 ///
@@ -54,9 +54,21 @@ pub enum WorkCommand {
         #[arg(short, long, default_value = "")]
         description: String,
 
-        /// Priority: 0=critical, 1=high, 2=medium, 3=low, 4=backlog
-        #[arg(short, long, default_value = "2")]
-        priority: u8,
+        /// Priority: 0=critical, 1=high, 2=medium (default), 3=low, 4=backlog
+        #[arg(short, long)]
+        priority: Option<u8>,
+
+        /// Workspace package the item belongs to
+        #[arg(long)]
+        package: Option<String>,
+
+        /// Create the item even if a similar open item already exists
+        #[arg(long)]
+        force: bool,
+
+        /// Start from a template's description, priority, checklist and how-tos
+        #[arg(long)]
+        template: Option<String>,
     },
 
     /// Get a work item by ID with full details.
@@ -87,9 +99,18 @@ pub enum WorkCommand {
         #[arg(short, long)]
         priority: Option<u8>,
 
-        /// New status: open, complete, abandoned, stuck, blocked
+        /// New status: open, complete, abandoned, stuck, blocked, in-review, blocked-external
         #[arg(short, long)]
         status: Option<String>,
+
+        /// New package (an empty name removes the item from its package)
+        #[arg(long)]
+        package: Option<String>,
+
+        /// Acknowledge a definition-of-done item that holds but could not be
+        /// verified (repeat for each item)
+        #[arg(long = "confirm-dod", value_name = "ITEM")]
+        confirm_dod: Vec<String>,
     },
 
     /// Delete a work item.
@@ -103,7 +124,7 @@ pub enum WorkCommand {
     /// Without filters, lists all open work items. Use --ready-only to
     /// see only items that aren't blocked by dependencies.
     List {
-        /// Filter by status: open, complete, abandoned, stuck, blocked
+        /// Filter by status: open, complete, abandoned, stuck, blocked, in-review, blocked-external
         #[arg(short, long)]
         status: Option<String>,
 
@@ -126,6 +147,14 @@ pub enum WorkCommand {
         /// Number of items to skip
         #[arg(long)]
         offset: Option<usize>,
+
+        /// Filter by package
+        #[arg(long)]
+        package: Option<String>,
+
+        /// Only items waiting (true) or not waiting (false) on review or a third party
+        #[arg(long)]
+        waiting: Option<bool>,
     },
 
     /// Search work items by text.
@@ -158,6 +187,31 @@ pub enum WorkCommand {
         id: String,
     },
 
+    /// Constrain sessions in this project to a single work item.
+    ///
+    /// Until the item is complete, the agent can only work on it and may not
+    /// stop. With `--chain`, the next ready item is assigned whenever the
+    /// current one is done, so items are worked on one at a time. The
+    /// `CLAUDE_RELIABILITY_SINGLE_WORK_ITEM` environment variable, if set,
+    /// takes precedence.
+    Assign {
+        /// Work item ID
+        #[arg(required_unless_present = "clear")]
+        id: Option<String>,
+
+        /// Assign the next ready item when this one is done
+        #[arg(long)]
+        chain: bool,
+
+        /// Start a Claude session working on the item
+        #[arg(long)]
+        spawn: bool,
+
+        /// Remove the current assignment
+        #[arg(long, conflicts_with_all = ["id", "chain", "spawn"])]
+        clear: bool,
+    },
+
     /// Mark work items as requested by the user.
     ///
     /// Requested items must be completed before the agent can stop.
@@ -211,6 +265,48 @@ pub enum WorkCommand {
         depends_on: String,
     },
 
+    /// Merge one work item into another.
+    ///
+    /// The first item is kept and takes over the second's notes,
+    /// dependencies, dependents and links; the second is deleted.
+    Merge {
+        /// Work item ID to keep
+        id: String,
+
+        /// Work item ID to merge into it
+        other: String,
+    },
+
+    /// Split a work item into subtasks.
+    ///
+    /// Each subtask starts with the item's priority, dependencies and
+    /// links, and the item then depends on all of them.
+    Split {
+        /// Work item ID to split
+        id: String,
+
+        /// Title of a subtask (repeat for each subtask)
+        #[arg(long = "into", value_name = "TITLE", required = true)]
+        titles: Vec<String>,
+    },
+
+    /// Manage a work item's acceptance criteria checklist.
+    ///
+    /// A requested item cannot be marked complete until every item on
+    /// its checklist is ticked off. Prints the checklist after any changes.
+    Check {
+        /// Work item ID
+        id: String,
+
+        /// Add a checklist item (repeat for each item)
+        #[arg(long = "add", value_name = "TEXT")]
+        add: Vec<String>,
+
+        /// Tick off the checklist item at this position, counting from 1 (repeatable)
+        #[arg(long = "done", value_name = "N")]
+        done: Vec<u32>,
+    },
+
     /// Add a note to a work item.
     ///
     /// Notes capture progress, findings, or context about the work.
@@ -259,4 +355,107 @@ pub enum WorkCommand {
         #[arg(long)]
         howto_id: String,
     },
+
+    /// Manage work item templates.
+    ///
+    /// Templates pre-fill new work items of a common kind (e.g. "bug" or
+    /// "dependency-upgrade") with a description scaffold, priority,
+    /// checklist items and linked how-tos. Use them with
+    /// `work create --template <name>`.
+    #[command(subcommand)]
+    Template(TemplateCommand),
+
+    /// Show what is holding up the requested work.
+    ///
+    /// Lists the open work items and unanswered questions that requested
+    /// work waits on, biggest bottleneck first, with how many items each
+    /// one blocks directly or transitively.
+    Blockers,
+
+    /// Export the dependency graph for Graphviz or Mermaid.
+    ///
+    /// Shows every work item with its dependencies and the unanswered
+    /// questions blocking it, colored by status, to explain why items
+    /// are not ready.
+    Graph {
+        /// Output format: dot or mermaid
+        #[arg(short, long, default_value = "dot")]
+        format: String,
+    },
+
+    /// Import work items from another task system.
+    ///
+    /// Reads a TODO.md checklist, a GitHub issues export (from
+    /// `gh issue list --state all --json number,title,body,labels,state`) or a
+    /// beads dump (`.beads/issues.jsonl`), mapping priorities and dependencies
+    /// where it can. Finished and already imported items are skipped.
+    Import {
+        /// Input format: todo-md, github-json or beads
+        #[arg(long = "from", value_name = "FORMAT")]
+        format: String,
+
+        /// The file to import
+        file: std::path::PathBuf,
+    },
+
+    /// Pause prompts to work on open items while the user is idle.
+    ///
+    /// Use this when the user wants the session to stay idle. Prompting
+    /// resumes after the given time, or with `--clear`.
+    Snooze {
+        /// How long to snooze for, e.g. `30m`, `2h` or `1d` (plain numbers are minutes)
+        #[arg(long = "for", value_name = "DURATION", required_unless_present = "clear")]
+        duration: Option<String>,
+
+        /// Resume prompting now
+        #[arg(long, conflicts_with = "duration")]
+        clear: bool,
+    },
+}
+
+/// Work item template commands.
+#[derive(Subcommand, Debug, Clone)]
+pub enum TemplateCommand {
+    /// Create a template, or replace the one with the same name.
+    Save {
+        /// Template name
+        name: String,
+
+        /// Description scaffold for new work items
+        #[arg(short, long, default_value = "")]
+        description: String,
+
+        /// Priority: 0=critical, 1=high, 2=medium, 3=low, 4=backlog
+        #[arg(short, long, default_value = "2")]
+        priority: u8,
+
+        /// Checklist item for new work items (repeat for each item)
+        #[arg(long = "check", value_name = "TEXT")]
+        checklist: Vec<String>,
+
+        /// How-to ID to link to new work items (repeat for each how-to)
+        #[arg(long = "howto", value_name = "HOWTO_ID")]
+        howtos: Vec<String>,
+    },
+
+    /// Show a template.
+    Show {
+        /// Template name
+        name: String,
+    },
+
+    /// List all templates.
+    List,
+
+    /// Delete a template.
+    Delete {
+        /// Template name
+        name: String,
+    },
+
+    /// Print templates as TOML, all of them unless a name is given.
+    Export {
+        /// Template name
+        name: Option<String>,
+    },
 }
@@ -0,0 +1,703 @@
+//! Real command execution implementation.
+
+mod parse;
+
+pub use parse::{parse, SimpleCommand, Word};
+
+use crate::error::{Error, Result};
+use crate::traits::{CommandOutput, CommandRunner, RunOptions, StreamOptions};
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// Format a command and its arguments into a string for error messages.
+pub fn format_command(program: &str, args: &[&str]) -> String {
+    if args.is_empty() {
+        program.to_string()
+    } else {
+        format!("{} {}", program, args.join(" "))
+    }
+}
+
+/// ETXTBSY error code (errno 26 on Linux).
+/// This error occurs when trying to execute a file that is currently being written.
+const ETXTBSY: i32 = 26;
+
+/// Spawn a command with retry logic for ETXTBSY errors.
+///
+/// ETXTBSY ("Text file busy") can occur on overlay filesystems (like Docker)
+/// when executing a script that was just created. The file may still be held
+/// open by the filesystem layer. A brief retry usually succeeds.
+///
+/// # Arguments
+/// * `spawn_fn` - A function that attempts to spawn the process
+///
+/// # Returns
+/// The spawned child process, or an error if spawning fails for non-ETXTBSY reasons.
+fn spawn_with_etxtbsy_retry<F>(mut spawn_fn: F) -> std::io::Result<Child>
+where
+    F: FnMut() -> std::io::Result<Child>,
+{
+    loop {
+        match spawn_fn() {
+            Ok(child) => return Ok(child),
+            Err(e) if e.raw_os_error() == Some(ETXTBSY) => {
+                // ETXTBSY - wait briefly and retry
+                std::thread::sleep(Duration::from_millis(1));
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Wait for a child process to exit.
+///
+/// With a timeout or heartbeat, polls the child every 100ms. If the timeout
+/// expires, kills the child and returns an error. A heartbeat prints a
+/// "still running" line to stderr at each interval.
+fn wait_for_exit(
+    child: &mut Child,
+    timeout: Option<Duration>,
+    heartbeat: Option<Duration>,
+    program: &str,
+    args: &[&str],
+) -> Result<ExitStatus> {
+    use std::time::Instant;
+
+    if timeout.is_none() && heartbeat.is_none() {
+        return Ok(child.wait()?);
+    }
+
+    let start = Instant::now();
+    let poll_interval = Duration::from_millis(100);
+    let mut next_heartbeat = heartbeat.unwrap_or_default();
+
+    loop {
+        // Use ? to propagate io::Error via the From trait (which is tested elsewhere)
+        if let Some(status) = child.try_wait()? {
+            return Ok(status);
+        }
+        let elapsed = start.elapsed();
+        // Still running - check timeout
+        if let Some(timeout) = timeout.filter(|timeout| elapsed >= *timeout) {
+            // Timeout expired - kill the child
+            let _ = child.kill();
+            let _ = child.wait(); // Reap the zombie
+
+            return Err(Error::CommandTimeout {
+                command: format_command(program, args),
+                timeout_secs: timeout.as_secs(),
+            });
+        }
+        if let Some(interval) = heartbeat {
+            if elapsed >= next_heartbeat {
+                eprintln!(
+                    "`{}` still running ({}s)",
+                    format_command(program, args),
+                    elapsed.as_secs()
+                );
+                next_heartbeat += interval;
+            }
+        }
+        std::thread::sleep(poll_interval);
+    }
+}
+
+/// Output read from a stream, up to a size limit.
+#[derive(Debug)]
+struct CappedOutput {
+    /// The bytes kept.
+    bytes: Vec<u8>,
+    /// How many further bytes were read and discarded.
+    discarded: usize,
+}
+
+impl CappedOutput {
+    /// Convert to text, noting any truncation.
+    fn into_text(self) -> String {
+        let mut text = String::from_utf8_lossy(&self.bytes).into_owned();
+        if self.discarded > 0 {
+            let _ = write!(text, "\n[output truncated: {} more bytes]", self.discarded);
+        }
+        text
+    }
+}
+
+/// Read a stream to the end, keeping at most `limit` bytes.
+///
+/// The rest is read and discarded so the writer never blocks on a full pipe.
+fn read_capped(mut reader: impl Read, limit: usize) -> std::io::Result<CappedOutput> {
+    let mut output = CappedOutput { bytes: Vec::new(), discarded: 0 };
+    let mut buf = [0u8; 8192];
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => return Ok(output),
+            Ok(n) => n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        let keep = n.min(limit - output.bytes.len());
+        output.bytes.extend_from_slice(&buf[..keep]);
+        output.discarded += n - keep;
+    }
+}
+
+/// Longest line kept whole when streaming; longer lines are split.
+const MAX_LINE_BYTES: u64 = 64 * 1024;
+
+/// The last lines of a stream, bounded in count and total size.
+#[derive(Debug)]
+struct TailBuffer {
+    lines: VecDeque<Vec<u8>>,
+    max_lines: usize,
+    max_bytes: usize,
+    bytes: usize,
+    dropped: usize,
+}
+
+impl TailBuffer {
+    const fn new(max_lines: usize, max_bytes: usize) -> Self {
+        Self { lines: VecDeque::new(), max_lines, max_bytes, bytes: 0, dropped: 0 }
+    }
+
+    /// Add a line, dropping the oldest lines to stay within bounds.
+    ///
+    /// The newest line is always kept, even if it alone exceeds the size bound.
+    fn push(&mut self, line: Vec<u8>) {
+        self.bytes += line.len();
+        self.lines.push_back(line);
+        while self.lines.len() > self.max_lines
+            || (self.bytes > self.max_bytes && self.lines.len() > 1)
+        {
+            let Some(old) = self.lines.pop_front() else { break };
+            self.bytes -= old.len();
+            self.dropped += 1;
+        }
+    }
+
+    /// Convert to text, noting any dropped lines.
+    fn into_text(self) -> String {
+        let mut text = String::new();
+        if self.dropped > 0 {
+            let _ = writeln!(text, "[{} earlier lines omitted]", self.dropped);
+        }
+        let bytes: Vec<u8> = self.lines.into_iter().flatten().collect();
+        text.push_str(&String::from_utf8_lossy(&bytes));
+        text
+    }
+}
+
+/// Where streamed output is copied as it is read.
+#[derive(Debug)]
+struct OutputStream {
+    log: Option<Mutex<File>>,
+    echo: bool,
+    heartbeat: Option<Duration>,
+    tail_lines: Option<usize>,
+}
+
+impl OutputStream {
+    /// Open the log file, if any, replacing previous contents.
+    fn open(options: &StreamOptions) -> std::io::Result<Self> {
+        let log = match &options.log_file {
+            Some(path) => {
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                Some(Mutex::new(File::create(path)?))
+            }
+            None => None,
+        };
+        Ok(Self {
+            log,
+            echo: options.echo,
+            heartbeat: options.heartbeat,
+            tail_lines: options.tail_lines,
+        })
+    }
+
+    /// Copy a line to the log and stderr.
+    ///
+    /// Write failures are ignored: the command's result matters more than its log.
+    fn write_line(&self, line: &[u8]) {
+        if let Some(log) = &self.log {
+            if let Ok(mut log) = log.lock() {
+                let _ = log.write_all(line);
+            }
+        }
+        if self.echo {
+            let _ = std::io::stderr().lock().write_all(line);
+        }
+    }
+}
+
+/// Read a stream line by line, copying each line out and keeping the tail.
+fn stream_lines(
+    reader: impl Read,
+    stream: &OutputStream,
+    max_bytes: usize,
+) -> std::io::Result<TailBuffer> {
+    let mut reader = BufReader::new(reader);
+    let mut tail = TailBuffer::new(stream.tail_lines.unwrap_or(usize::MAX), max_bytes);
+    loop {
+        let mut line = Vec::new();
+        if (&mut reader).take(MAX_LINE_BYTES).read_until(b'\n', &mut line)? == 0 {
+            return Ok(tail);
+        }
+        stream.write_line(&line);
+        tail.push(line);
+    }
+}
+
+/// A thread reading one of a child's output streams.
+type OutputReader = JoinHandle<std::io::Result<String>>;
+
+/// Read a stream on a background thread, so stdout and stderr drain together.
+fn spawn_reader(
+    reader: impl Read + Send + 'static,
+    limit: usize,
+    stream: Option<Arc<OutputStream>>,
+) -> OutputReader {
+    std::thread::spawn(move || match stream {
+        Some(stream) => stream_lines(reader, &stream, limit).map(TailBuffer::into_text),
+        None => read_capped(reader, limit).map(CappedOutput::into_text),
+    })
+}
+
+/// Collect a reader thread's output.
+fn collect_output(reader: Option<OutputReader>) -> Result<String> {
+    let Some(reader) = reader else {
+        return Ok(String::new());
+    };
+    Ok(reader.join().map_err(|_| std::io::Error::other("output reader panicked"))??)
+}
+
+/// Build the process to spawn for a command and its options.
+fn build_command(program: &str, args: &[&str], options: &RunOptions) -> Command {
+    let mut command = options.niceness.map_or_else(
+        || Command::new(program),
+        |niceness| {
+            let mut command = Command::new("nice");
+            command.args(["-n", &niceness.to_string(), program]);
+            command
+        },
+    );
+    command.args(args).stdout(Stdio::piped()).stderr(Stdio::piped());
+    if let Some(cwd) = &options.cwd {
+        command.current_dir(cwd);
+    }
+    if let Some(allowlist) = &options.env_allowlist {
+        command.env_clear();
+        for name in allowlist {
+            if let Some(value) = std::env::var_os(name) {
+                command.env(name, value);
+            }
+        }
+    }
+    command.envs(options.env.iter().map(|(name, value)| (name, value)));
+    command
+}
+
+/// Real command runner that executes shell commands.
+#[derive(Debug, Default, Clone)]
+pub struct RealCommandRunner;
+
+impl RealCommandRunner {
+    /// Create a new command runner.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self
+    }
+}
+
+impl RealCommandRunner {
+    /// Run a command, streaming its output if `stream` is given.
+    fn execute(
+        program: &str,
+        args: &[&str],
+        options: &RunOptions,
+        stream: Option<&StreamOptions>,
+    ) -> Result<CommandOutput> {
+        let _span =
+            tracing::debug_span!("command", command = %format_command(program, args)).entered();
+        let mut command = build_command(program, args, options);
+        let stream = stream.map(OutputStream::open).transpose()?.map(Arc::new);
+        let mut child = spawn_with_etxtbsy_retry(|| command.spawn()).map_err(|source| {
+            Error::CommandSpawn { command: format_command(program, args), source }
+        })?;
+
+        let limit = options.max_output_bytes.unwrap_or(usize::MAX);
+        let stdout = child.stdout.take().map(|out| spawn_reader(out, limit, stream.clone()));
+        let stderr = child.stderr.take().map(|err| spawn_reader(err, limit, stream.clone()));
+
+        let heartbeat = stream.as_ref().and_then(|stream| stream.heartbeat);
+        let status = wait_for_exit(&mut child, options.timeout, heartbeat, program, args)?;
+
+        let exit_code = status.code().unwrap_or(-1);
+        Ok(CommandOutput {
+            exit_code,
+            stdout: collect_output(stdout)?,
+            stderr: collect_output(stderr)?,
+        })
+    }
+}
+
+impl CommandRunner for RealCommandRunner {
+    fn run(
+        &self,
+        program: &str,
+        args: &[&str],
+        timeout: Option<Duration>,
+    ) -> Result<CommandOutput> {
+        self.run_with_options(program, args, &RunOptions { timeout, ..RunOptions::default() })
+    }
+
+    fn run_in_dir(
+        &self,
+        program: &str,
+        args: &[&str],
+        timeout: Option<Duration>,
+        cwd: &std::path::Path,
+    ) -> Result<CommandOutput> {
+        let options = RunOptions { timeout, ..RunOptions::new().cwd(cwd) };
+        self.run_with_options(program, args, &options)
+    }
+
+    fn run_with_options(
+        &self,
+        program: &str,
+        args: &[&str],
+        options: &RunOptions,
+    ) -> Result<CommandOutput> {
+        Self::execute(program, args, options, None)
+    }
+
+    fn run_streaming(
+        &self,
+        program: &str,
+        args: &[&str],
+        options: &RunOptions,
+        stream: &StreamOptions,
+    ) -> Result<CommandOutput> {
+        Self::execute(program, args, options, Some(stream))
+    }
+
+    fn is_available(&self, program: &str) -> bool {
+        Command::new("which")
+            .arg(program)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|s| s.success())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_echo() {
+        let runner = RealCommandRunner::new();
+        let output = runner.run("echo", &["hello"], None).unwrap();
+        assert!(output.success());
+        assert_eq!(output.stdout.trim(), "hello");
+    }
+
+    #[test]
+    fn test_run_failing_command() {
+        let runner = RealCommandRunner::new();
+        let output = runner.run("false", &[], None).unwrap();
+        assert!(!output.success());
+        assert_ne!(output.exit_code, 0);
+    }
+
+    #[test]
+    fn test_is_available() {
+        let runner = RealCommandRunner::new();
+        assert!(runner.is_available("echo"));
+        assert!(!runner.is_available("definitely_not_a_real_command_12345"));
+    }
+
+    #[test]
+    fn test_combined_output() {
+        let output =
+            CommandOutput { exit_code: 0, stdout: "out".to_string(), stderr: "err".to_string() };
+        assert_eq!(output.combined_output(), "out\nerr");
+
+        let stdout_only =
+            CommandOutput { exit_code: 0, stdout: "out".to_string(), stderr: String::new() };
+        assert_eq!(stdout_only.combined_output(), "out");
+
+        let stderr_only =
+            CommandOutput { exit_code: 0, stdout: String::new(), stderr: "err".to_string() };
+        assert_eq!(stderr_only.combined_output(), "err");
+    }
+
+    #[test]
+    fn test_run_nonexistent_command() {
+        let runner = RealCommandRunner::new();
+        // Running a command that doesn't exist should return an error
+        let result = runner.run("definitely_not_a_real_command_12345", &[], None);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_spawn_with_etxtbsy_retry_immediate_success() {
+        // Test that immediate success works
+        let mut call_count = 0;
+        let mut command = Command::new("true");
+        command.stdout(Stdio::piped()).stderr(Stdio::piped());
+
+        let result = spawn_with_etxtbsy_retry(|| {
+            call_count += 1;
+            command.spawn()
+        });
+
+        assert!(result.is_ok());
+        assert_eq!(call_count, 1);
+    }
+
+    #[test]
+    fn test_spawn_with_etxtbsy_retry_retries_on_etxtbsy() {
+        // Test that ETXTBSY errors trigger retries
+        let mut call_count = 0;
+        let mut command = Command::new("true");
+        command.stdout(Stdio::piped()).stderr(Stdio::piped());
+
+        let result = spawn_with_etxtbsy_retry(|| {
+            call_count += 1;
+            if call_count < 3 {
+                // Simulate ETXTBSY error
+                Err(std::io::Error::from_raw_os_error(ETXTBSY))
+            } else {
+                // Succeed on third attempt
+                command.spawn()
+            }
+        });
+
+        assert!(result.is_ok());
+        assert_eq!(call_count, 3); // Should have retried twice
+    }
+
+    #[test]
+    fn test_spawn_with_etxtbsy_retry_propagates_other_errors() {
+        // Test that non-ETXTBSY errors are propagated immediately
+        let mut call_count = 0;
+
+        let result = spawn_with_etxtbsy_retry(|| {
+            call_count += 1;
+            // Return ENOENT (No such file or directory) - should not retry
+            Err(std::io::Error::from_raw_os_error(2))
+        });
+
+        assert!(result.is_err());
+        assert_eq!(call_count, 1); // Should not have retried
+        assert_eq!(result.unwrap_err().raw_os_error(), Some(2));
+    }
+
+    #[test]
+    fn test_etxtbsy_constant() {
+        // Verify the ETXTBSY constant is correct for Linux
+        assert_eq!(ETXTBSY, 26);
+    }
+
+    #[test]
+    fn test_run_with_timeout_fast_command() {
+        // A fast command should complete well before the timeout
+        let runner = RealCommandRunner::new();
+        let output = runner.run("echo", &["hello"], Some(Duration::from_secs(10))).unwrap();
+        assert!(output.success());
+        assert_eq!(output.stdout.trim(), "hello");
+    }
+
+    #[test]
+    fn test_run_with_timeout_command_times_out() {
+        // A slow command should be killed when timeout expires
+        let runner = RealCommandRunner::new();
+        let result = runner.run("sleep", &["10"], Some(Duration::from_millis(100)));
+
+        assert!(result.is_err());
+        let err = result.unwrap_err();
+        let err_str = err.to_string();
+        assert!(err_str.contains("timed out"), "Error should mention timeout: {err_str}");
+    }
+
+    #[test]
+    fn test_format_command_empty_args() {
+        // Test format_command with empty args
+        let result = super::format_command("test_program", &[]);
+        assert_eq!(result, "test_program");
+    }
+
+    #[test]
+    fn test_format_command_with_args() {
+        // Test format_command with args
+        let result = super::format_command("echo", &["hello", "world"]);
+        assert_eq!(result, "echo hello world");
+    }
+
+    #[test]
+    fn test_run_with_timeout_empty_args_via_timeout_runner() {
+        // Test that TimeoutCommandRunner correctly formats command without args
+        use crate::testing::TimeoutCommandRunner;
+        let runner = TimeoutCommandRunner::new(300);
+        // Test with empty args
+        let result = runner.run("test_program", &[], None);
+
+        assert!(result.is_err());
+        let err = result.unwrap_err();
+        let err_str = err.to_string();
+        assert!(err_str.contains("timed out"), "Error should mention timeout: {err_str}");
+        // Error message should just be the program name without args
+        assert!(
+            err_str.contains("'test_program'"),
+            "Error should show command without args: {err_str}"
+        );
+    }
+
+    #[test]
+    fn test_run_without_timeout_waits_indefinitely() {
+        // Without a timeout, should wait for the command to complete
+        let runner = RealCommandRunner::new();
+        // Use a command that takes a bit but not too long
+        let output = runner.run("sleep", &["0.1"], None).unwrap();
+        assert!(output.success());
+    }
+
+    #[test]
+    fn test_run_in_dir_basic() {
+        use std::path::Path;
+
+        let runner = RealCommandRunner::new();
+        // Run pwd in /tmp to verify directory change
+        let output = runner.run_in_dir("pwd", &[], None, Path::new("/tmp")).unwrap();
+        assert!(output.success());
+        // pwd output should contain /tmp (may be /private/tmp on macOS)
+        assert!(
+            output.stdout.contains("/tmp"),
+            "Expected /tmp in output, got: {}",
+            output.stdout.trim()
+        );
+    }
+
+    #[test]
+    fn test_run_in_dir_with_timeout() {
+        use std::path::Path;
+
+        let runner = RealCommandRunner::new();
+        let output =
+            runner.run_in_dir("echo", &["hello"], Some(Duration::from_secs(10)), Path::new("/tmp"));
+        assert!(output.is_ok());
+        assert_eq!(output.unwrap().stdout.trim(), "hello");
+    }
+
+    #[test]
+    fn test_run_with_options_env_allowlist() {
+        let runner = RealCommandRunner::new();
+        let inherited = runner.run_with_options("env", &[], &RunOptions::new()).unwrap();
+        assert!(inherited.stdout.contains("HOME="));
+
+        let options = RunOptions::new().env_allowlist(["PATH"]).env("VISIBLE", "yes");
+        let output = runner.run_with_options("env", &[], &options).unwrap();
+        assert!(output.stdout.contains("VISIBLE=yes"));
+        assert!(output.stdout.contains("PATH="));
+        assert!(!output.stdout.contains("HOME="));
+    }
+
+    #[test]
+    fn test_run_with_options_caps_output() {
+        let runner = RealCommandRunner::new();
+        let options = RunOptions::new().max_output_bytes(10);
+        let output = runner
+            .run_with_options(
+                "sh",
+                &["-c", "head -c 200000 /dev/zero | tr '\\0' x; echo err >&2"],
+                &options,
+            )
+            .unwrap();
+        assert!(output.success());
+        assert_eq!(output.stdout, "xxxxxxxxxx\n[output truncated: 199990 more bytes]");
+        assert_eq!(output.stderr, "err\n");
+    }
+
+    #[test]
+    fn test_run_with_options_cwd_and_niceness() {
+        let runner = RealCommandRunner::new();
+        let options = RunOptions::new().cwd("/").niceness(5).timeout(Duration::from_secs(10));
+        let output = runner.run_with_options("sh", &["-c", "pwd; nice"], &options).unwrap();
+        let lines: Vec<&str> = output.stdout.lines().collect();
+        assert_eq!(lines[0], "/");
+        assert!(lines[1].parse::<i32>().unwrap() >= 5, "niceness: {}", lines[1]);
+    }
+
+    #[test]
+    fn test_read_capped() {
+        let output = read_capped(&b"hello world"[..], 5).unwrap();
+        assert_eq!(output.bytes, b"hello");
+        assert_eq!(output.discarded, 6);
+
+        let output = read_capped(&b"hi"[..], usize::MAX).unwrap();
+        assert_eq!(output.bytes, b"hi");
+        assert_eq!(output.discarded, 0);
+    }
+
+    #[test]
+    fn test_run_streaming_logs_output_and_keeps_tail() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let log = dir.path().join("logs/check.log");
+        let runner = RealCommandRunner::new();
+        let stream = StreamOptions {
+            log_file: Some(log.clone()),
+            tail_lines: Some(3),
+            ..Default::default()
+        };
+        let output = runner
+            .run_streaming(
+                "sh",
+                &["-c", "for i in 1 2 3 4 5 6 7 8 9 10; do echo line$i; done; echo oops >&2"],
+                &RunOptions::new(),
+                &stream,
+            )
+            .unwrap();
+
+        assert!(output.success());
+        assert_eq!(output.stdout, "[7 earlier lines omitted]\nline8\nline9\nline10\n");
+        assert_eq!(output.stderr, "oops\n");
+        let logged = std::fs::read_to_string(&log).unwrap();
+        assert!(logged.contains("line1\n"));
+        assert!(logged.contains("oops\n"));
+    }
+
+    #[test]
+    fn test_run_streaming_with_heartbeat_and_timeout() {
+        let runner = RealCommandRunner::new();
+        let stream =
+            StreamOptions { heartbeat: Some(Duration::from_millis(100)), ..Default::default() };
+        let options = RunOptions::new().timeout(Duration::from_secs(10));
+        let output = runner.run_streaming("sleep", &["0.3"], &options, &stream).unwrap();
+        assert!(output.success());
+
+        let options = RunOptions::new().timeout(Duration::from_millis(100));
+        let result = runner.run_streaming("sleep", &["10"], &options, &stream);
+        assert!(matches!(result, Err(crate::error::Error::CommandTimeout { .. })));
+    }
+
+    #[test]
+    fn test_tail_buffer_bounds_size() {
+        let mut tail = TailBuffer::new(usize::MAX, 10);
+        for line in ["aaaa\n", "bbbb\n", "cccc\n"] {
+            tail.push(line.as_bytes().to_vec());
+        }
+        assert_eq!(tail.into_text(), "[1 earlier lines omitted]\nbbbb\ncccc\n");
+
+        let mut tail = TailBuffer::new(2, 4);
+        tail.push(b"a very long line\n".to_vec());
+        assert_eq!(tail.into_text(), "a very long line\n");
+    }
+}
@@ -0,0 +1,699 @@
+//! Shell-aware parsing of Bash command lines.
+//!
+//! Hooks that police Bash tool calls need to know which programs a command
+//! line will run and with which arguments. Substring matching is fooled by
+//! quoting, chaining and indirection, so [`parse`] splits a command line into
+//! the [`SimpleCommand`]s it contains, including those nested in command
+//! substitutions, subshells, `eval` and `sh -c` scripts.
+//!
+//! The parser is forgiving: unterminated quotes run to the end of the input
+//! and unsupported syntax is read as plain words, so parsing never fails.
+
+/// Maximum nesting depth of substitutions and nested scripts that is parsed.
+const MAX_DEPTH: usize = 16;
+
+/// Shells whose `-c` argument is parsed as a nested script.
+const SHELLS: &[&str] = &["sh", "bash", "zsh", "dash", "ksh"];
+
+/// Reserved words that may precede the program of a simple command.
+const RESERVED_WORDS: &[&str] =
+    &["!", "{", "}", "if", "then", "elif", "else", "do", "while", "until", "time"];
+
+/// A shell word after quote removal.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Word {
+    /// The word's text with quotes removed. Expansions are kept verbatim.
+    pub text: String,
+    /// Whether the word contains a parameter, command or arithmetic
+    /// expansion, so its value is only known at run time.
+    pub expanded: bool,
+    /// Whether any of those expansions is unquoted, so it may be split into
+    /// several arguments at run time.
+    pub unquoted_expansion: bool,
+}
+
+impl Word {
+    const fn literal(text: String) -> Self {
+        Self { text, expanded: false, unquoted_expansion: false }
+    }
+}
+
+/// A simple command: one segment of a pipeline or command list.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SimpleCommand {
+    /// The words as written, including leading assignments and wrappers.
+    pub words: Vec<Word>,
+    /// Redirection targets and here-document bodies.
+    pub redirects: Vec<Word>,
+}
+
+impl SimpleCommand {
+    /// The words as written, as strings.
+    pub fn argv(&self) -> Vec<&str> {
+        self.words.iter().map(|word| word.text.as_str()).collect()
+    }
+
+    /// The words of the command that actually runs.
+    ///
+    /// Leading reserved words, variable assignments and wrapper commands
+    /// such as `env`, `sudo`, `nice` or `xargs` are skipped.
+    pub fn command_words(&self) -> &[Word] {
+        let mut words = self.words.as_slice();
+        while let Some(first) = words.first() {
+            if first.expanded {
+                break;
+            }
+            let skip =
+                if RESERVED_WORDS.contains(&first.text.as_str()) || is_assignment(&first.text) {
+                    1
+                } else {
+                    wrapper_len(words)
+                };
+            if skip == 0 {
+                break;
+            }
+            words = &words[skip.min(words.len())..];
+        }
+        words
+    }
+
+    /// The base name of the program that runs, if any.
+    pub fn program(&self) -> Option<&str> {
+        self.command_words().first().map(|word| basename(&word.text))
+    }
+
+    /// The arguments passed to the program that runs.
+    pub fn args(&self) -> &[Word] {
+        self.command_words().get(1..).unwrap_or_default()
+    }
+
+    /// The subcommand, such as `disable`, if this runs `claude-reliability`.
+    pub fn reliability_subcommand(&self) -> Option<&str> {
+        if self.program()? != "claude-reliability" {
+            return None;
+        }
+        self.args().iter().map(|word| word.text.as_str()).find(|arg| !arg.starts_with('-'))
+    }
+
+    /// The script run by `sh -c`-style invocations and `eval`.
+    fn nested_script(&self) -> Option<String> {
+        let program = self.program()?;
+        let args = self.args();
+        if program == "eval" {
+            let script: Vec<&str> = args.iter().map(|word| word.text.as_str()).collect();
+            return Some(script.join(" "));
+        }
+        if !SHELLS.contains(&program) {
+            return None;
+        }
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let flags = arg.text.strip_prefix('-')?;
+            if flags.starts_with('-') {
+                continue;
+            }
+            if flags.contains('c') {
+                return args.next().map(|script| script.text.clone());
+            }
+        }
+        None
+    }
+}
+
+/// Split a command line into the simple commands it runs.
+///
+/// Commands nested in command and process substitutions, backticks,
+/// subshells, `eval` and `sh -c` scripts are included after the command
+/// containing them.
+pub fn parse(command: &str) -> Vec<SimpleCommand> {
+    parse_nested(command, 0)
+}
+
+fn parse_nested(source: &str, depth: usize) -> Vec<SimpleCommand> {
+    let mut parser = Parser {
+        chars: source.chars().collect(),
+        pos: 0,
+        depth,
+        commands: Vec::new(),
+        substituted: Vec::new(),
+    };
+    parser.parse_list(false);
+    parser.commands
+}
+
+/// Whether a word is a variable assignment such as `FOO=bar` or `FOO+=bar`.
+fn is_assignment(text: &str) -> bool {
+    text.split_once('=').is_some_and(|(name, _)| {
+        let name = name.strip_suffix('+').unwrap_or(name);
+        name.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    })
+}
+
+fn basename(text: &str) -> &str {
+    text.rsplit('/').next().unwrap_or(text)
+}
+
+/// The number of leading words taken up by a wrapper command and its
+/// options, or zero if the first word is not a wrapper.
+fn wrapper_len(words: &[Word]) -> usize {
+    let Some(first) = words.first() else {
+        return 0;
+    };
+    match basename(&first.text) {
+        "command" | "builtin" | "exec" | "nohup" | "time" => skip_options(words, &["-a"]),
+        "env" => {
+            let mut index = skip_options(words, &["-u", "-C", "-S", "--unset", "--chdir"]);
+            while words.get(index).is_some_and(|word| is_assignment(&word.text)) {
+                index += 1;
+            }
+            index
+        }
+        "sudo" => {
+            skip_options(words, &["-u", "-g", "-h", "-p", "-C", "-D", "-U", "-r", "-t", "-T"])
+        }
+        "nice" => skip_options(words, &["-n", "--adjustment"]),
+        "timeout" => skip_options(words, &["-s", "-k", "--signal", "--kill-after"]) + 1,
+        "xargs" => skip_options(
+            words,
+            &["-I", "-L", "-n", "-P", "-d", "-E", "-s", "-a", "--max-args", "--delimiter"],
+        ),
+        _ => 0,
+    }
+}
+
+/// Skip the options following a wrapper command, returning the index of
+/// the first word after them.
+fn skip_options(words: &[Word], value_options: &[&str]) -> usize {
+    let mut index = 1;
+    while let Some(word) = words.get(index) {
+        let text = word.text.as_str();
+        if text == "--" {
+            return index + 1;
+        }
+        if !text.starts_with('-') || text == "-" {
+            break;
+        }
+        index += if value_options.contains(&text) { 2 } else { 1 };
+    }
+    index
+}
+
+/// Recursive-descent parser over the characters of a command line.
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    depth: usize,
+    commands: Vec<SimpleCommand>,
+    /// Commands from substitutions in the command being read, recorded
+    /// after it once it is complete.
+    substituted: Vec<SimpleCommand>,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).copied()
+    }
+
+    fn slice(&self, start: usize) -> String {
+        self.chars[start..self.pos].iter().collect()
+    }
+
+    /// Parse a command list up to the end of input or, when `in_parens` is
+    /// set, up to the `)` closing the enclosing substitution.
+    fn parse_list(&mut self, in_parens: bool) {
+        let mut current = SimpleCommand::default();
+        let mut heredocs: Vec<(String, bool)> = Vec::new();
+        let mut redirect = false;
+        let mut parens = 0usize;
+        while let Some(c) = self.peek() {
+            match c {
+                ' ' | '\t' => self.pos += 1,
+                '\\' if self.peek_at(1) == Some('\n') => self.pos += 2,
+                '\n' => {
+                    self.pos += 1;
+                    for (delimiter, strip_tabs) in std::mem::take(&mut heredocs) {
+                        let body = self.read_heredoc(&delimiter, strip_tabs);
+                        current.redirects.push(Word::literal(body));
+                    }
+                    self.finish(&mut current);
+                }
+                '#' => {
+                    while self.peek().is_some_and(|c| c != '\n') {
+                        self.pos += 1;
+                    }
+                }
+                '(' => {
+                    self.pos += 1;
+                    parens += 1;
+                    self.finish(&mut current);
+                }
+                ')' => {
+                    self.pos += 1;
+                    self.finish(&mut current);
+                    if parens == 0 && in_parens {
+                        return;
+                    }
+                    parens = parens.saturating_sub(1);
+                }
+                '&' if self.peek_at(1) == Some('>') => {
+                    self.pos += 2;
+                    if self.peek() == Some('>') {
+                        self.pos += 1;
+                    }
+                    redirect = true;
+                }
+                ';' | '|' | '&' => {
+                    self.pos += 1;
+                    self.finish(&mut current);
+                }
+                '<' | '>' if self.peek_at(1) == Some('(') => {
+                    let word = self.read_process_substitution();
+                    if redirect {
+                        current.redirects.push(word);
+                        redirect = false;
+                    } else {
+                        current.words.push(word);
+                    }
+                }
+                '<' | '>' => redirect = self.read_redirect(&mut heredocs),
+                _ if self.at_fd_redirect() => {
+                    while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+                        self.pos += 1;
+                    }
+                }
+                _ => {
+                    let word = self.read_word();
+                    if redirect {
+                        current.redirects.push(word);
+                        redirect = false;
+                    } else {
+                        current.words.push(word);
+                    }
+                }
+            }
+        }
+        self.finish(&mut current);
+    }
+
+    /// Record a completed simple command and any script nested inside it.
+    fn finish(&mut self, current: &mut SimpleCommand) {
+        let command = std::mem::take(current);
+        if command.words.is_empty() && command.redirects.is_empty() {
+            self.commands.append(&mut self.substituted);
+            return;
+        }
+        let nested = command
+            .nested_script()
+            .filter(|_| self.depth < MAX_DEPTH)
+            .map(|script| parse_nested(&script, self.depth + 1));
+        self.commands.push(command);
+        self.commands.append(&mut self.substituted);
+        self.commands.extend(nested.into_iter().flatten());
+    }
+
+    /// Whether the input continues with a file descriptor number directly
+    /// followed by a redirection operator, as in `2>&1`.
+    fn at_fd_redirect(&self) -> bool {
+        let digits = self.chars[self.pos..].iter().take_while(|c| c.is_ascii_digit()).count();
+        digits > 0 && matches!(self.peek_at(digits), Some('<' | '>'))
+    }
+
+    /// Consume a redirection operator, returning whether a target word
+    /// follows it. Here-document delimiters are queued in `heredocs`.
+    fn read_redirect(&mut self, heredocs: &mut Vec<(String, bool)>) -> bool {
+        let first = self.peek();
+        self.pos += 1;
+        if first == Some('<') && self.peek() == Some('<') {
+            self.pos += 1;
+            if self.peek() == Some('<') {
+                self.pos += 1;
+                return true;
+            }
+            let strip_tabs = self.peek() == Some('-');
+            if strip_tabs {
+                self.pos += 1;
+            }
+            while matches!(self.peek(), Some(' ' | '\t')) {
+                self.pos += 1;
+            }
+            heredocs.push((self.read_word().text, strip_tabs));
+            return false;
+        }
+        if matches!(self.peek(), Some('>' | '&' | '|')) {
+            self.pos += 1;
+        }
+        true
+    }
+
+    /// Read a here-document body up to its delimiter line.
+    fn read_heredoc(&mut self, delimiter: &str, strip_tabs: bool) -> String {
+        let mut body = Vec::new();
+        while self.pos < self.chars.len() {
+            let start = self.pos;
+            while self.peek().is_some_and(|c| c != '\n') {
+                self.pos += 1;
+            }
+            let line = self.slice(start);
+            if self.peek() == Some('\n') {
+                self.pos += 1;
+            }
+            let line = if strip_tabs { line.trim_start_matches('\t').to_string() } else { line };
+            if line == delimiter {
+                break;
+            }
+            body.push(line);
+        }
+        body.join("\n")
+    }
+
+    /// Read one word, removing quotes and parsing nested commands.
+    fn read_word(&mut self) -> Word {
+        let mut word = Word::default();
+        while let Some(c) = self.peek() {
+            match c {
+                ' ' | '\t' | '\n' | ';' | '&' | '|' | '(' | ')' | '<' | '>' => break,
+                '\\' => {
+                    self.pos += 1;
+                    if let Some(next) = self.peek() {
+                        self.pos += 1;
+                        if next != '\n' {
+                            word.text.push(next);
+                        }
+                    }
+                }
+                '\'' => {
+                    self.pos += 1;
+                    self.read_single_quoted(&mut word.text);
+                }
+                '"' => {
+                    self.pos += 1;
+                    word.expanded |= self.read_double_quoted(&mut word.text);
+                }
+                '$' => {
+                    let expanded = self.read_dollar(&mut word.text, false);
+                    word.expanded |= expanded;
+                    word.unquoted_expansion |= expanded;
+                }
+                '`' => {
+                    self.read_backticks(&mut word.text);
+                    word.expanded = true;
+                    word.unquoted_expansion = true;
+                }
+                _ => {
+                    word.text.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+        word
+    }
+
+    fn read_single_quoted(&mut self, text: &mut String) {
+        while let Some(c) = self.peek() {
+            self.pos += 1;
+            if c == '\'' {
+                return;
+            }
+            text.push(c);
+        }
+    }
+
+    /// Read the rest of a double-quoted string, returning whether it
+    /// contained an expansion.
+    fn read_double_quoted(&mut self, text: &mut String) -> bool {
+        let mut expanded = false;
+        while let Some(c) = self.peek() {
+            match c {
+                '"' => {
+                    self.pos += 1;
+                    break;
+                }
+                '\\' => {
+                    self.pos += 1;
+                    match self.peek() {
+                        Some(next @ ('$' | '`' | '"' | '\\')) => {
+                            self.pos += 1;
+                            text.push(next);
+                        }
+                        Some('\n') => self.pos += 1,
+                        _ => text.push('\\'),
+                    }
+                }
+                '$' => expanded |= self.read_dollar(text, true),
+                '`' => {
+                    self.read_backticks(text);
+                    expanded = true;
+                }
+                _ => {
+                    text.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+        expanded
+    }
+
+    /// Read a `$`-introduced construct, returning whether it is an
+    /// expansion rather than literal text.
+    fn read_dollar(&mut self, text: &mut String, quoted: bool) -> bool {
+        let start = self.pos;
+        self.pos += 1;
+        match self.peek() {
+            Some('\'') if !quoted => {
+                self.pos += 1;
+                self.read_ansi_c_quoted(text);
+                return false;
+            }
+            Some('"') if !quoted => {
+                self.pos += 1;
+                return self.read_double_quoted(text);
+            }
+            Some('(') if self.peek_at(1) == Some('(') => {
+                self.pos += 2;
+                self.skip_balanced('(', ')', 2);
+            }
+            Some('(') => {
+                self.pos += 1;
+                self.parse_substitution();
+            }
+            Some('{') => {
+                self.pos += 1;
+                self.skip_balanced('{', '}', 1);
+            }
+            Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+                while self.peek().is_some_and(|c| c.is_ascii_alphanumeric() || c == '_') {
+                    self.pos += 1;
+                }
+            }
+            Some(c) if c.is_ascii_digit() || "@*#?$!-".contains(c) => self.pos += 1,
+            _ => {
+                text.push('$');
+                return false;
+            }
+        }
+        text.push_str(&self.slice(start));
+        true
+    }
+
+    /// Read the rest of a `$'...'` string, decoding its escapes.
+    fn read_ansi_c_quoted(&mut self, text: &mut String) {
+        while let Some(c) = self.peek() {
+            self.pos += 1;
+            match c {
+                '\'' => return,
+                '\\' => {
+                    let Some(escape) = self.peek() else {
+                        text.push('\\');
+                        return;
+                    };
+                    self.pos += 1;
+                    match escape {
+                        'n' => text.push('\n'),
+                        't' => text.push('\t'),
+                        'r' => text.push('\r'),
+                        'e' | 'E' => text.push('\x1b'),
+                        'x' => self.push_code(text, 16, 2, 0),
+                        '0'..='7' => self.push_code(text, 8, 3, escape.to_digit(8).unwrap_or(0)),
+                        '\\' | '\'' | '"' | '?' => text.push(escape),
+                        _ => {
+                            text.push('\\');
+                            text.push(escape);
+                        }
+                    }
+                }
+                _ => text.push(c),
+            }
+        }
+    }
+
+    /// Decode a numeric escape with up to `max_digits` digits in total.
+    fn push_code(&mut self, text: &mut String, radix: u32, max_digits: usize, initial: u32) {
+        let mut value = initial;
+        let mut digits = usize::from(radix == 8);
+        while digits < max_digits {
+            let Some(digit) = self.peek().and_then(|c| c.to_digit(radix)) else {
+                break;
+            };
+            value = value * radix + digit;
+            digits += 1;
+            self.pos += 1;
+        }
+        text.extend(char::from_u32(value));
+    }
+
+    /// Read a backtick command substitution, parsing the commands in it.
+    fn read_backticks(&mut self, text: &mut String) {
+        let start = self.pos;
+        self.pos += 1;
+        let mut inner = String::new();
+        while let Some(c) = self.peek() {
+            self.pos += 1;
+            match c {
+                '`' => break,
+                '\\' if matches!(self.peek(), Some('`' | '\\' | '$')) => {
+                    inner.extend(self.peek());
+                    self.pos += 1;
+                }
+                _ => inner.push(c),
+            }
+        }
+        text.push_str(&self.slice(start));
+        if self.depth < MAX_DEPTH {
+            self.substituted.extend(parse_nested(&inner, self.depth + 1));
+        }
+    }
+
+    /// Read a `<(...)` or `>(...)` process substitution as a word.
+    fn read_process_substitution(&mut self) -> Word {
+        let start = self.pos;
+        self.pos += 2;
+        self.parse_substitution();
+        Word { text: self.slice(start), expanded: true, unquoted_expansion: true }
+    }
+
+    /// Parse the commands of a substitution whose `(` has been consumed.
+    fn parse_substitution(&mut self) {
+        if self.depth < MAX_DEPTH {
+            let outer_commands = std::mem::take(&mut self.commands);
+            let outer_substituted = std::mem::take(&mut self.substituted);
+            self.depth += 1;
+            self.parse_list(true);
+            self.depth -= 1;
+            let inner = std::mem::replace(&mut self.commands, outer_commands);
+            self.substituted = outer_substituted;
+            self.substituted.extend(inner);
+        } else {
+            self.skip_balanced('(', ')', 1);
+        }
+    }
+
+    /// Skip to just after the `close` that balances `depth` open brackets.
+    fn skip_balanced(&mut self, open: char, close: char, mut depth: usize) {
+        while let Some(c) = self.peek() {
+            self.pos += 1;
+            if c == open {
+                depth += 1;
+            } else if c == close {
+                depth -= 1;
+                if depth == 0 {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn argvs(command: &str) -> Vec<Vec<String>> {
+        parse(command).iter().map(|c| c.argv().into_iter().map(str::to_string).collect()).collect()
+    }
+
+    #[test]
+    fn test_parse_splits_command_lists() {
+        assert_eq!(
+            argvs("git add . && git commit -m 'a; b' || echo failed | tee log; ls &"),
+            vec![
+                vec!["git", "add", "."],
+                vec!["git", "commit", "-m", "a; b"],
+                vec!["echo", "failed"],
+                vec!["tee", "log"],
+                vec!["ls"],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_removes_quotes() {
+        assert_eq!(
+            argvs(r#"git commit --no-"verify" -m "say \"hi\"" 'it''s' \-n"#),
+            vec![vec!["git", "commit", "--no-verify", "-m", "say \"hi\"", "its", "-n"]]
+        );
+        assert_eq!(argvs(r"echo $'\x2d\x2dno\055verify\n'"), vec![vec!["echo", "--no-verify\n"]]);
+    }
+
+    #[test]
+    fn test_parse_marks_expansions() {
+        let commands = parse(r#"git commit $FLAGS -m "$MSG" ${X}y plain"#);
+        let words = &commands[0].words;
+        assert!(words[2].expanded && words[2].unquoted_expansion);
+        assert_eq!(words[2].text, "$FLAGS");
+        assert!(words[4].expanded && !words[4].unquoted_expansion);
+        assert_eq!(words[5].text, "${X}y");
+        assert!(words[5].unquoted_expansion);
+        assert!(!words[6].expanded);
+        assert!(!parse("echo $ 'a$'")[0].words[1].expanded);
+    }
+
+    #[test]
+    fn test_parse_includes_nested_commands() {
+        let programs = |command: &str| -> Vec<String> {
+            parse(command).iter().filter_map(|c| c.program().map(str::to_string)).collect()
+        };
+        assert_eq!(programs("echo \"$(git rev-parse HEAD)\""), vec!["echo", "git"]);
+        assert_eq!(programs("echo `ls`"), vec!["echo", "ls"]);
+        assert_eq!(programs("(cd sub && make) | cat"), vec!["cd", "make", "cat"]);
+        assert_eq!(programs("diff <(sort a) b"), vec!["diff", "sort"]);
+        assert_eq!(programs("bash -lc 'git push --force'"), vec!["bash", "git"]);
+        assert_eq!(programs("eval git status"), vec!["eval", "git"]);
+        assert_eq!(programs("echo $((1 + (2 * 3))) done"), vec!["echo"]);
+    }
+
+    #[test]
+    fn test_parse_handles_redirects_and_heredocs() {
+        let commands = parse("sqlite3 db.sqlite3 2>&1 > out.txt <<EOF\n.tables\nEOF\necho next");
+        assert_eq!(commands.len(), 2);
+        assert_eq!(commands[0].argv(), vec!["sqlite3", "db.sqlite3"]);
+        let redirects: Vec<&str> = commands[0].redirects.iter().map(|w| w.text.as_str()).collect();
+        assert_eq!(redirects, vec!["1", "out.txt", ".tables"]);
+        assert_eq!(commands[1].argv(), vec!["echo", "next"]);
+        assert_eq!(argvs("make &> log # git commit -n"), vec![vec!["make"]]);
+    }
+
+    #[test]
+    fn test_command_words_skip_wrappers() {
+        let command = &parse("FOO=1 sudo -u bob env -i BAR=2 nice -n 5 /usr/bin/git status")[0];
+        assert_eq!(command.program(), Some("git"));
+        assert_eq!(command.args().len(), 1);
+        assert_eq!(parse("timeout 10 cargo test")[0].program(), Some("cargo"));
+        assert_eq!(parse("{ git status; }")[0].program(), Some("git"));
+        assert_eq!(parse("ls | xargs -n 1 git add")[1].program(), Some("git"));
+        assert_eq!(parse("FOO=1")[0].program(), None);
+        assert_eq!(parse("$GIT commit")[0].program(), Some("$GIT"));
+    }
+
+    #[test]
+    fn test_parse_is_forgiving() {
+        assert_eq!(argvs("echo 'unterminated"), vec![vec!["echo", "unterminated"]]);
+        assert_eq!(argvs("echo \"$(ls"), vec![vec!["echo", "$(ls"], vec!["ls"]]);
+        assert!(parse("").is_empty());
+        assert!(!parse(&"$(".repeat(1000)).is_empty());
+    }
+}
@@ -3,17 +3,34 @@
 //! This module handles the `.claude/reliability-config.yaml` file which stores
 //! project-specific settings for the reliability hooks.
 
-use crate::error::Result;
-use crate::traits::CommandRunner;
+use crate::error::{Error, Result};
+use crate::traits::{CommandRunner, RunOptions};
+use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::SystemTime;
 
 /// Config file path relative to project root.
 pub const CONFIG_FILE_PATH: &str = ".claude/reliability-config.yaml";
 
+/// Configs read by [`ProjectConfig::load_cached`], by absolute config file
+/// path, with the file's modification time and length when it was read.
+#[allow(clippy::type_complexity)]
+static LOADED: Lazy<Mutex<HashMap<PathBuf, (FileStamp, Option<Arc<ProjectConfig>>)>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// A config file's modification time and length, or `None` if it is missing.
+type FileStamp = Option<(SystemTime, u64)>;
+
 /// Header comment for the managed gitignore section.
 const GITIGNORE_SECTION_HEADER: &str = "# claude-reliability managed";
 
+/// Comment that ends the managed gitignore section.
+const GITIGNORE_SECTION_END: &str = "# end claude-reliability managed";
+
 /// Start delimiter for managed binary instructions in CLAUDE.md.
 const CLAUDE_MD_SECTION_START: &str =
     "<!-- claude-reliability:binary-instructions managed section - DO NOT EDIT -->";
@@ -67,11 +84,48 @@ pub struct ProjectConfig {
     #[serde(default)]
     pub git_repo: bool,
 
+    /// The version control system the stop hook checks for uncommitted and
+    /// unpushed changes: `auto` (detected from `.jj` or `.git`), `git`, `jj`
+    /// or `none`. Pushing is only required when `require_push` is set.
+    #[serde(default, skip_serializing_if = "is_default_vcs")]
+    pub vcs: VcsKind,
+
+    /// How the stop hook checks for uncommitted changes in large repositories:
+    /// which subtrees to check, the cheaper status flags, and caching of the
+    /// untracked file list. Checks the whole tree by default.
+    #[serde(default, skip_serializing_if = "is_default_git_status_policy")]
+    pub git_status: GitStatusPolicy,
+
     /// Command to run for quality checks (e.g., "just check").
     /// None means no quality check command is configured.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub check_command: Option<String>,
 
+    /// Environment variables passed through to the quality check command
+    /// (e.g. `[PATH, HOME, CARGO_HOME]`). When set, the check runs with only these
+    /// variables from the hook's environment. None passes the full environment.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub check_env_allowlist: Option<Vec<String>>,
+
+    /// Niceness to run the quality check command at (e.g. 10), so long checks
+    /// don't starve the rest of the machine. None runs it at normal priority.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub check_niceness: Option<i32>,
+
+    /// Maximum bytes of quality check output to keep, from each of stdout and stderr.
+    /// Anything beyond this is discarded. Defaults to 1 MiB.
+    #[serde(
+        default = "default_check_output_limit",
+        skip_serializing_if = "is_default_check_output_limit"
+    )]
+    pub check_output_limit: usize,
+
+    /// Whether to echo quality check output to stderr while the check runs, with a
+    /// "still running" line every 30 seconds. The full output of the last check is
+    /// always written to `.claude-reliability/logs/quality-check.log`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub check_progress: bool,
+
     /// The title of the code review section in CLAUDE.md (e.g., "## Code Review").
     /// None means no code review section exists.
     #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -82,6 +136,14 @@ pub struct ProjectConfig {
     #[serde(default = "default_require_push")]
     pub require_push: bool,
 
+    /// Whether the stop hook pushes unpushed commits itself, rather than
+    /// blocking until the agent does. A branch without an upstream is pushed
+    /// with `git push -u origin <branch>`. The stop is only blocked if the
+    /// push fails or the branch has diverged from its upstream. Git only, and
+    /// not done when `auto_confirm_push` is off or on `main`/`master`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub auto_push: bool,
+
     /// Whether to explain why stops are permitted.
     /// When true, the stop hook always includes a message explaining the reason.
     #[serde(default, skip_serializing_if = "std::ops::Not::not")]
@@ -101,1964 +163,4562 @@ pub struct ProjectConfig {
     )]
     pub auto_work_idle_minutes: u32,
 
+    /// Number of auto-work prompts in a row without progress after which prompting
+    /// pauses for `auto_work_cooldown_minutes`. 0 disables the cooldown.
+    #[serde(
+        default = "default_auto_work_decline_limit",
+        skip_serializing_if = "is_default_auto_work_decline_limit"
+    )]
+    pub auto_work_decline_limit: u32,
+
+    /// Minutes auto-work prompting pauses for once the decline limit is reached.
+    #[serde(
+        default = "default_auto_work_cooldown_minutes",
+        skip_serializing_if = "is_default_auto_work_cooldown_minutes"
+    )]
+    pub auto_work_cooldown_minutes: u32,
+
+    /// Working hours during which auto-work prompting is allowed, such as
+    /// `"09:00-18:00 Mon-Fri"` (see [`crate::schedule`]). Unset means any time.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_work_hours: Option<String>,
+
+    /// Most auto-work prompts in one session. Once reached, the agent may stop
+    /// and wait for the user. 0 means no limit.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub auto_work_max_prompts: u32,
+
+    /// Number of work items the agent completes under auto-work before it is
+    /// asked to check in with a summary of them. 0 disables check-ins.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub auto_work_check_in_every: u32,
+
+    /// Where user activity is detected, besides the transcript, when deciding
+    /// whether the user is idle: `transcript` (messages only), `os` (keyboard
+    /// and mouse idle time) or `heartbeat` (a file the user touches).
+    #[serde(default, skip_serializing_if = "is_default_idle_source")]
+    pub idle_source: IdleSource,
+
+    /// Heartbeat file for `idle_source: heartbeat`. Relative paths are resolved
+    /// against the project; defaults to `.claude-reliability/heartbeat`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idle_heartbeat_file: Option<String>,
+
+    /// Seconds the stop, post-tool-use and session start hooks may run for
+    /// before they skip their remaining checks and report what they have
+    /// found, so that Claude Code does not kill them. Pre-tool-use checks
+    /// always run, since they guard the tool call. Set
+    /// this a little below the hook timeout in the plugin's hook settings.
+    /// `CLAUDE_RELIABILITY_HOOK_TIMEOUT` overrides it. Unset means no limit.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hook_timeout_seconds: Option<u32>,
+
+    /// What hooks decide when their own infrastructure fails: the database
+    /// cannot be read, a git command or the sub-agent fails, or the hook
+    /// crashes. `open` allows the stop or tool call, and `closed` blocks it.
+    /// The stop hook records the failure in its checks log, and crashes are
+    /// written to `.claude-reliability/crashes/` and the audit log.
+    #[serde(default, skip_serializing_if = "is_default_failure_mode")]
+    pub failure_mode: FailureMode,
+
+    /// Hours between the automatic backups of the database into
+    /// `.claude-reliability/backups/`, taken when a session starts.
+    /// 0 disables automatic backups.
+    #[serde(
+        default = "default_backup_interval_hours",
+        skip_serializing_if = "is_default_backup_interval_hours"
+    )]
+    pub backup_interval_hours: u32,
+
+    /// Number of automatic backups kept; older ones are deleted. 0 keeps them all.
+    #[serde(default = "default_backup_keep", skip_serializing_if = "is_default_backup_keep")]
+    pub backup_keep: u32,
+
+    /// Minutes after which a session marker (e.g. `must_reflect`, `problem_mode`)
+    /// expires, by marker name. Markers not listed expire after a day; 0 means never.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub marker_ttl_minutes: BTreeMap<String, u32>,
+
     /// Whether to enable debug logging.
     /// When true, hook events and subagent interactions are logged to `.claude-reliability/`.
     #[serde(default, skip_serializing_if = "std::ops::Not::not")]
     pub debug_logging: bool,
+
+    /// Number of consecutive identical stop blocks before escalating.
+    /// Escalation adds stronger guidance, then files a question, then allows the stop.
+    /// 0 disables escalation.
+    #[serde(
+        default = "default_block_escalation_threshold",
+        skip_serializing_if = "is_default_block_escalation_threshold"
+    )]
+    pub block_escalation_threshold: u32,
+
+    /// Number of times the stop hook may block a session, for any reason,
+    /// without a user message or a file change in between. Past this the stop
+    /// is allowed with a summary of the checks that kept blocking, so that a
+    /// mis-tuned check cannot keep the agent looping. 0 disables the limit.
+    #[serde(
+        default = "default_injection_loop_limit",
+        skip_serializing_if = "is_default_injection_loop_limit"
+    )]
+    pub injection_loop_limit: u32,
+
+    /// Number of near-identical assistant messages in a row at which the agent
+    /// counts as stuck in a loop. The stop is then allowed, rather than
+    /// blocked again, and a work item describing the loop is filed.
+    /// 0 disables the check.
+    #[serde(
+        default = "default_repeated_output_limit",
+        skip_serializing_if = "is_default_repeated_output_limit"
+    )]
+    pub repeated_output_limit: u32,
+
+    /// Estimated spend in dollars, at list prices, after which a session
+    /// starts no new work. Past it the stop hook skips requested-work and
+    /// auto-work prompts, still makes sure work is committed and pushed, and
+    /// then allows the stop with a summary of the spend. 0 means no limit.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub max_session_cost_usd: u32,
+
+    /// Per-branch overrides for the stop policy.
+    /// The first rule whose pattern matches the current branch applies. By
+    /// default `wip/*` branches only need a quick WIP commit, and other
+    /// branches follow the project settings.
+    #[serde(default = "default_branch_rules", skip_serializing_if = "is_default_branch_rules")]
+    pub branch_rules: Vec<BranchRule>,
+
+    /// Per-model overrides for the stop policy, for projects where models
+    /// with different reliability are used, such as stricter validation and
+    /// no auto-work for a cheaper model. The first rule whose pattern matches
+    /// the model of the session's last assistant message applies, after any
+    /// branch rule.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub model_rules: Vec<ModelRule>,
+
+    /// Whether to commit uncommitted changes automatically on WIP branches.
+    /// When true, branches whose rule sets `wip_commits` get a `wip: <summary>` commit
+    /// at stop time instead of a block. The summary is generated from the diff.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub auto_wip_commit: bool,
+
+    /// Whether finished feature branches must have a pull request.
+    /// When true, a pushed branch other than `main`/`master` gets a pull request
+    /// created with `gh` at stop time, and its URL is noted on completed work items.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub require_pr: bool,
+
+    /// Whether to write a handoff summary when a stop is allowed after modifying work.
+    /// Summaries are written to `.claude-reliability/handoffs/` by a sub-agent,
+    /// which can take up to two minutes per stop, so they are off by default.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub handoff_summaries: bool,
+
+    /// Number of uncommitted files at which the stop hook proposes splitting the
+    /// changes into several commits instead of asking for one. 0 disables the check.
+    #[serde(
+        default = "default_commit_split_files",
+        skip_serializing_if = "is_default_commit_split_files"
+    )]
+    pub commit_split_files: usize,
+
+    /// Number of changed lines in tracked files at which the stop hook proposes
+    /// splitting the changes into several commits. 0 disables the check.
+    #[serde(
+        default = "default_commit_split_lines",
+        skip_serializing_if = "is_default_commit_split_lines"
+    )]
+    pub commit_split_lines: usize,
+
+    /// Policy for newly added binary files, checked before each `git commit`.
+    #[serde(default, skip_serializing_if = "is_default_binary_files")]
+    pub binary_files: BinaryFilePolicy,
+
+    /// Names of files that hold credentials, which are blocked from being
+    /// committed. Checked before each `git commit`.
+    #[serde(default, skip_serializing_if = "is_default_sensitive_files")]
+    pub sensitive_files: SensitiveFilePolicy,
+
+    /// Files generated by tools rather than written by hand, which the
+    /// spelling, binary file and commit size checks leave out, and build
+    /// output, which must never be committed.
+    #[serde(default, skip_serializing_if = "is_default_generated_files")]
+    pub generated_files: GeneratedFilePolicy,
+
+    /// Spelling and terminology check for documentation and user-facing strings
+    /// added in a commit. Disabled by default.
+    #[serde(default, skip_serializing_if = "is_default_spelling")]
+    pub spelling: SpellingPolicy,
+
+    /// When a work item must be in progress before files are changed, and
+    /// which files can be changed without one.
+    #[serde(default, skip_serializing_if = "is_default_require_task")]
+    pub require_task: RequireTaskPolicy,
+
+    /// Stop hook checks to switch off, and the order the tiers of checks run
+    /// in. Checked when the config is loaded: the checks that let a stuck
+    /// agent stop cannot be switched off, and the tiers can only be reordered
+    /// where no tier relies on another having run first.
+    #[serde(default, skip_serializing_if = "is_default_stop_checks")]
+    pub stop_checks: StopChecksPolicy,
+
+    /// How much the stop hook says about its decision: `quiet` gives only the
+    /// decision and a one-line reason, `normal` the full message, and `verbose`
+    /// adds the log of every check run and untruncated file lists.
+    #[serde(default, skip_serializing_if = "is_default_message_verbosity")]
+    pub message_verbosity: MessageVerbosity,
+
+    /// Approximate number of characters the stop hook's messages may take up in
+    /// the agent's context. Quality check output is summarized to fit, keeping
+    /// errors and failures, and long lists are shortened. 0 disables the limit.
+    #[serde(default = "default_context_budget", skip_serializing_if = "is_default_context_budget")]
+    pub context_budget: usize,
+
+    /// Whether to end the stop hook's block messages with a machine-readable
+    /// tag such as `<!-- cr:block reason=uncommitted_changes check=uncommitted_changes -->`,
+    /// so a later pass over transcripts can measure how often each check blocks
+    /// and whether the agent resolved it.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub tag_blocks: bool,
+
+    /// Whether `question create` asks the sub-agent to draft two or three
+    /// candidate answers with their trade-offs, shown by `question show` so
+    /// the user can pick one instead of writing an answer from scratch.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub suggest_answers: bool,
+
+    /// Hours after which an unanswered question is answered with an
+    /// assumption chosen by the sub-agent, so the work waiting on it can go
+    /// ahead. Assumptions are recorded as such, flagged in handoff summaries
+    /// and undone with `question reopen`. 0 (the default) waits for the user.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub assume_after_hours: u32,
+
+    /// Where new questions are sent for teams not watching the terminal: a
+    /// GitHub issue to comment on, or an email address. Replies are read back
+    /// by `question sync`. Disabled by default.
+    #[serde(default, skip_serializing_if = "is_default_question_delivery")]
+    pub question_delivery: QuestionDelivery,
+
+    /// Where blockers are reported so they reach the team's normal triage:
+    /// a GitHub issue opened for each, or a markdown file they are appended
+    /// to. Used for accepted emergency stops and problems explained in
+    /// problem mode that name a kind of blocker. Disabled by default.
+    #[serde(default, skip_serializing_if = "is_default_escalation")]
+    pub escalation: EscalationPolicy,
+
+    /// The committer identity and signatures required on commits made in a
+    /// session. The stop hook checks the session's unpushed commits and
+    /// blocks with instructions to fix them. Disabled by default.
+    #[serde(default, skip_serializing_if = "is_default_commit_policy")]
+    pub commit_policy: CommitPolicy,
+
+    /// Whether to answer "yes" automatically when the agent asks for
+    /// permission to commit. When false, the stop is allowed so the user can
+    /// answer.
+    #[serde(default = "default_true", skip_serializing_if = "is_true")]
+    pub auto_confirm_commit: bool,
+
+    /// Whether to answer "yes" automatically when the agent asks for
+    /// permission to push. When false, the stop is allowed so the user can
+    /// answer.
+    #[serde(default = "default_true", skip_serializing_if = "is_true")]
+    pub auto_confirm_push: bool,
+
+    /// Whether to answer "yes" automatically when the agent asks whether to
+    /// continue. When false, the stop is allowed so the user can answer.
+    #[serde(default = "default_true", skip_serializing_if = "is_true")]
+    pub auto_answer_continue: bool,
+
+    /// Minutes since the user's last message within which they count as
+    /// active. Questions from the agent are only left for an active user.
+    #[serde(
+        default = "default_user_recency_minutes",
+        skip_serializing_if = "is_default_user_recency_minutes"
+    )]
+    pub user_recency_minutes: u32,
+
+    /// Number of consecutive API errors (other than overloaded errors) after
+    /// which the stop is allowed. Values below 1 are treated as 1.
+    #[serde(
+        default = "default_api_error_threshold",
+        skip_serializing_if = "is_default_api_error_threshold"
+    )]
+    pub api_error_threshold: u32,
+
+    /// Number of files listed in stop hook messages before the rest are
+    /// summarized as "... and N more".
+    #[serde(
+        default = "default_max_files_to_show",
+        skip_serializing_if = "is_default_max_files_to_show"
+    )]
+    pub max_files_to_show: usize,
+
+    /// Packages of a workspace or monorepo, each with its own quality checks.
+    /// When set, validation only runs the checks of packages with modified files.
+    #[serde(default, rename = "package", skip_serializing_if = "Vec::is_empty")]
+    pub packages: Vec<PackageConfig>,
+
+    /// Maximum number of `emergency-stop` requests per session in an hour. Further
+    /// requests are rejected without being evaluated. 0 disables the limit.
+    #[serde(
+        default = "default_emergency_stop_max_per_hour",
+        skip_serializing_if = "is_default_emergency_stop_max_per_hour"
+    )]
+    pub emergency_stop_max_per_hour: u32,
+
+    /// Number of consecutive `emergency-stop` rejections after which a question is
+    /// filed for the user instead. 0 disables escalation.
+    #[serde(
+        default = "default_emergency_stop_rejections_before_question",
+        skip_serializing_if = "is_default_emergency_stop_rejections_before_question"
+    )]
+    pub emergency_stop_rejections_before_question: u32,
+
+    /// Where to keep the working-memory database, instead of
+    /// `.claude-reliability/working-memory.sqlite3`.
+    /// Either a path (absolute, `~/`-prefixed or relative to the project), or `user`
+    /// to keep it under `$XDG_DATA_HOME/claude-reliability/projects/`. Keeping the
+    /// database outside the repository means it can never be committed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub db_path: Option<String>,
+
+    /// Whether to redact credentials from user messages and sub-agent prompts
+    /// before they are stored. Detected secrets are replaced with `[REDACTED:<type>]`.
+    #[serde(default = "default_redact_secrets", skip_serializing_if = "is_default_redact_secrets")]
+    pub redact_secrets: bool,
+
+    /// Whether the pre-commit secrets check reads the full contents of staged
+    /// and untracked files, rather than only the lines the commit adds. This
+    /// catches credentials added in earlier commits or left in untracked files.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub deep_secrets_scan: bool,
+
+    /// Filter for hook log files in `.claude-reliability/logs/`, in `RUST_LOG` syntax
+    /// (e.g. `debug` or `claude_reliability::hooks::stop=trace`).
+    /// The `CLAUDE_RELIABILITY_LOG` environment variable takes precedence. Defaults to `info`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_filter: Option<String>,
+
+    /// What must hold before a work item can be marked complete (see
+    /// [`crate::definition_of_done`]): `tests_updated`, `docs_touched`,
+    /// `quality_check_green`, `pushed`, or any statement to acknowledge.
+    /// Items that cannot be verified need `--confirm-dod <item>`. Empty disables the check.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub definition_of_done: Vec<String>,
+
+    /// File to export metrics to in the Prometheus text format, relative to the project.
+    /// When set, hooks record stop decisions, quality check and sub-agent durations, and
+    /// rewrite this file after every call. None disables metrics.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metrics_file: Option<String>,
+
+    /// The preset last applied with `ensure-config --profile`. Informational:
+    /// the preset's values are written into this file, where any key can be
+    /// changed afterwards.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<Profile>,
 }
 
-/// Default value for `require_push` - true by default.
-const fn default_require_push() -> bool {
-    true
+/// How much detail the stop hook includes in its messages.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MessageVerbosity {
+    /// Only the decision and a one-line reason.
+    Quiet,
+    /// The full message for the decision.
+    #[default]
+    Normal,
+    /// The full message, the log of every check run, and untruncated file lists.
+    Verbose,
 }
 
-/// Default value for `auto_work_on_tasks` - true by default.
-const fn default_auto_work_on_tasks() -> bool {
-    true
+/// Where user activity is detected when deciding whether the user is idle.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum IdleSource {
+    /// Only the time of the user's last message in the transcript.
+    #[default]
+    Transcript,
+    /// The operating system's keyboard and mouse idle time.
+    Os,
+    /// The modification time of a heartbeat file the user touches.
+    Heartbeat,
 }
 
-/// Default value for `auto_work_idle_minutes` - 15 minutes.
-const fn default_auto_work_idle_minutes() -> u32 {
-    15
+/// Which version control system a project uses.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum VcsKind {
+    /// Detected from the project directory.
+    #[default]
+    Auto,
+    /// Git.
+    Git,
+    /// Jujutsu.
+    Jj,
+    /// No version control; the uncommitted change and push checks are skipped.
+    None,
 }
 
-/// Check if idle minutes is the default value (for `skip_serializing_if`).
-#[allow(clippy::trivially_copy_pass_by_ref)] // Required signature for serde's skip_serializing_if
-const fn is_default_idle_minutes(val: &u32) -> bool {
-    *val == default_auto_work_idle_minutes()
+/// What a hook decides when it cannot do its job.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FailureMode {
+    /// Allow the tool call or stop, as if the hook had not run.
+    #[default]
+    Open,
+    /// Block the tool call or stop.
+    Closed,
 }
 
-impl Default for ProjectConfig {
-    fn default() -> Self {
-        Self {
-            git_repo: false,
-            check_command: None,
-            code_review_section: None,
-            require_push: true,
-            explain_stops: false,
-            auto_work_on_tasks: true,
-            auto_work_idle_minutes: 15,
-            debug_logging: false,
-        }
+impl FailureMode {
+    /// Whether a failure allows the tool call or stop.
+    #[must_use]
+    pub const fn allows(self) -> bool {
+        matches!(self, Self::Open)
     }
 }
 
-impl ProjectConfig {
-    /// Load config from the default location, returning None if not found.
-    ///
-    /// # Errors
-    ///
-    /// Returns an error if the file exists but cannot be parsed.
-    pub fn load() -> Result<Option<Self>> {
-        Self::load_from(Path::new("."))
-    }
+/// A named preset of policy settings, applied with `ensure-config --profile`.
+///
+/// A profile sets the pre-tool-use checks, how strict the stop hook is, and
+/// which questions are answered automatically. Other settings are left alone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Profile {
+    /// Every check on, hooks fail closed, and pushes need the user's answer.
+    Strict,
+    /// The default settings.
+    Standard,
+    /// Fewer checks and interruptions, for trusted or throwaway work.
+    Permissive,
+}
 
-    /// Load config from a specific base directory.
-    ///
-    /// # Errors
-    ///
-    /// Returns an error if the file exists but cannot be parsed.
-    pub fn load_from(base_dir: &Path) -> Result<Option<Self>> {
-        let config_path = base_dir.join(CONFIG_FILE_PATH);
-        if !config_path.exists() {
-            return Ok(None);
+impl Profile {
+    /// The name used in the config and on the command line.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Strict => "strict",
+            Self::Standard => "standard",
+            Self::Permissive => "permissive",
         }
+    }
 
-        let content = std::fs::read_to_string(&config_path)?;
-        let config: Self = serde_yaml::from_str(&content)?;
-        Ok(Some(config))
+    /// Set the settings this profile covers on `config`.
+    pub fn apply(self, config: &mut ProjectConfig) {
+        let strict = self == Self::Strict;
+        let permissive = self == Self::Permissive;
+
+        // Pre-tool-use checks
+        config.deep_secrets_scan = strict;
+        config.spelling.enabled = strict;
+        config.require_task.scope = match self {
+            Self::Strict => RequireTaskScope::All,
+            Self::Standard => RequireTaskScope::Edits,
+            Self::Permissive => RequireTaskScope::Off,
+        };
+        config.binary_files.suggest_lfs = permissive;
+        config.commit_split_files = if permissive { 0 } else { default_commit_split_files() };
+        config.commit_split_lines = if permissive { 0 } else { default_commit_split_lines() };
+
+        // Stop hook strictness
+        config.failure_mode = if strict { FailureMode::Closed } else { FailureMode::Open };
+        config.explain_stops = strict;
+        config.emergency_stop_max_per_hour =
+            if strict { 1 } else { default_emergency_stop_max_per_hour() };
+
+        // Automatic answers
+        config.auto_confirm_commit = true;
+        config.auto_confirm_push = !strict;
+        config.auto_answer_continue = !permissive;
+
+        config.profile = Some(self);
     }
+}
 
-    /// Save config to the default location.
-    ///
-    /// # Errors
-    ///
-    /// Returns an error if the file cannot be written.
-    pub fn save(&self) -> Result<()> {
-        self.save_to(Path::new("."))
+impl FromStr for Profile {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        match name.to_lowercase().as_str() {
+            "strict" => Ok(Self::Strict),
+            "standard" => Ok(Self::Standard),
+            "permissive" => Ok(Self::Permissive),
+            _ => Err(Error::Config(format!(
+                "invalid profile '{name}' (must be one of: strict, standard, permissive)"
+            ))),
+        }
     }
+}
 
-    /// Save config to a specific base directory.
-    ///
-    /// # Errors
-    ///
-    /// Returns an error if the file cannot be written.
-    pub fn save_to(&self, base_dir: &Path) -> Result<()> {
-        let config_path = base_dir.join(CONFIG_FILE_PATH);
+/// Stop policy overrides for branches matching a pattern.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BranchRule {
+    /// Branch name pattern. `*` matches any sequence of characters (e.g. `wip/*`).
+    pub pattern: String,
 
-        // Ensure .claude directory exists
-        if let Some(parent) = config_path.parent() {
-            std::fs::create_dir_all(parent)?;
-        }
+    /// Override for whether commits must be pushed before stopping.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub require_push: Option<bool>,
 
-        let content = serde_yaml::to_string(self)?;
-        std::fs::write(&config_path, content)?;
-        Ok(())
+    /// Override for whether quality checks run on uncommitted changes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quality_checks: Option<bool>,
+
+    /// Whether uncommitted changes only need a quick WIP commit rather than
+    /// passing quality checks first.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub wip_commits: bool,
+}
+
+impl BranchRule {
+    /// Check whether this rule's pattern matches the given branch name.
+    #[must_use]
+    pub fn matches(&self, branch: &str) -> bool {
+        glob_matches(&self.pattern, branch)
     }
+}
 
-    /// Detect project configuration by checking the environment.
-    ///
-    /// This checks:
-    /// - Whether `.git` directory exists (`git_repo`)
-    /// - Whether `just check` is viable (`check_command`)
-    pub fn detect(runner: &dyn CommandRunner) -> Self {
-        Self::detect_in(runner, Path::new("."))
+/// Stop policy overrides for sessions run by models matching a pattern.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ModelRule {
+    /// Model name pattern. `*` matches any sequence of characters (e.g. `*haiku*`).
+    pub pattern: String,
+
+    /// Override for whether commits must be pushed before stopping.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub require_push: Option<bool>,
+
+    /// Override for whether quality checks run on uncommitted changes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quality_checks: Option<bool>,
+
+    /// Override for whether to work on open tasks when the user is idle.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_work_on_tasks: Option<bool>,
+}
+
+impl ModelRule {
+    /// Check whether this rule's pattern matches the given model name.
+    #[must_use]
+    pub fn matches(&self, model: &str) -> bool {
+        glob_matches(&self.pattern, model)
     }
+}
 
-    /// Detect project configuration in a specific directory.
-    pub fn detect_in(runner: &dyn CommandRunner, base_dir: &Path) -> Self {
-        let git_repo = base_dir.join(".git").exists();
-        let check_command = detect_check_command(runner, base_dir);
-        let code_review_section = find_code_review_section(base_dir);
+/// Limits on binary files added in a commit.
+///
+/// Binary files stay in the repository's history forever, so large ones are
+/// caught before they are committed.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BinaryFilePolicy {
+    /// Size in KB above which a newly added binary file is flagged. 0 disables the check.
+    #[serde(default = "default_binary_max_kb")]
+    pub max_kb: u64,
 
-        // Only require push if there's a remote configured
-        let require_push = git_repo && has_git_remote(runner);
+    /// Extensions (without the dot) allowed up to `allowed_max_kb`, such as images.
+    #[serde(default = "default_binary_allowed_extensions")]
+    pub allowed_extensions: Vec<String>,
+
+    /// Size in KB up to which files with an allowed extension are accepted.
+    #[serde(default = "default_binary_allowed_max_kb")]
+    pub allowed_max_kb: u64,
+
+    /// Whether to allow the commit and suggest `git lfs track` instead of blocking it.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub suggest_lfs: bool,
+}
 
+impl Default for BinaryFilePolicy {
+    fn default() -> Self {
         Self {
-            git_repo,
-            check_command,
-            code_review_section,
-            require_push,
-            explain_stops: false,
-            auto_work_on_tasks: default_auto_work_on_tasks(),
-            auto_work_idle_minutes: default_auto_work_idle_minutes(),
-            debug_logging: false,
+            max_kb: default_binary_max_kb(),
+            allowed_extensions: default_binary_allowed_extensions(),
+            allowed_max_kb: default_binary_allowed_max_kb(),
+            suggest_lfs: false,
         }
     }
+}
 
-    /// Get the config file path for a base directory.
-    pub fn config_path(base_dir: &Path) -> PathBuf {
-        base_dir.join(CONFIG_FILE_PATH)
+impl BinaryFilePolicy {
+    /// Check whether a binary file of the given size may be committed.
+    #[must_use]
+    pub fn allows(&self, path: &str, size_kb: u64) -> bool {
+        if self.max_kb == 0 || size_kb <= self.max_kb {
+            return true;
+        }
+        let extension = Path::new(path).extension().map(|e| e.to_string_lossy().to_lowercase());
+        extension.is_some_and(|extension| {
+            self.allowed_extensions.iter().any(|allowed| allowed.eq_ignore_ascii_case(&extension))
+                && size_kb <= self.allowed_max_kb
+        })
     }
 }
 
-/// Detect an appropriate quality check command for the project.
+/// Files that usually hold credentials, and must not be committed.
 ///
-/// Returns `Some("just check")` if:
-/// - `just` is installed
-/// - A `justfile` or `Justfile` exists
-/// - The justfile defines a `check:` target
-///
-/// Otherwise returns `None`.
-fn detect_check_command(runner: &dyn CommandRunner, base_dir: &Path) -> Option<String> {
-    // Check if just is installed
-    if !runner.is_available("just") {
-        return None;
-    }
+/// A pattern without a `/` matches a file's name, and a pattern with one
+/// matches the end of its path. `*` matches any characters.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SensitiveFilePolicy {
+    /// Patterns of sensitive files. An empty list disables the check.
+    #[serde(default = "default_sensitive_file_patterns")]
+    pub patterns: Vec<String>,
+
+    /// Patterns of files that may be committed even though they match
+    /// `patterns`, such as templates with placeholder values.
+    #[serde(default = "default_sensitive_file_allowlist")]
+    pub allowlist: Vec<String>,
+}
 
-    // Check for justfile
-    let justfile_path = find_justfile(base_dir)?;
+impl Default for SensitiveFilePolicy {
+    fn default() -> Self {
+        Self {
+            patterns: default_sensitive_file_patterns(),
+            allowlist: default_sensitive_file_allowlist(),
+        }
+    }
+}
 
-    // Check if justfile defines 'check:' target
-    if has_check_target(&justfile_path) {
-        Some("just check".to_string())
-    } else {
-        None
+impl SensitiveFilePolicy {
+    /// Check whether a file may not be committed.
+    #[must_use]
+    pub fn is_sensitive(&self, path: &str) -> bool {
+        let matches = |pattern: &String| path_matches(pattern, path);
+        self.patterns.iter().any(matches) && !self.allowlist.iter().any(matches)
     }
 }
 
-/// Check if there's a git remote configured.
+/// Marker in the first lines of a file that says it was generated by a tool.
+const GENERATED_MARKER: &str = "@generated";
+
+/// Bytes read from the start of a file when looking for [`GENERATED_MARKER`].
+const GENERATED_MARKER_BYTES: u64 = 1024;
+
+/// Files generated by tools, and build output that must not be committed.
 ///
-/// This is used to determine whether to require pushing before exit.
-/// If there's no remote, there's nowhere to push.
-fn has_git_remote(runner: &dyn CommandRunner) -> bool {
-    // List remotes - if any exist, we have a remote
-    let output = runner.run("git", &["remote"], None);
-    match output {
-        Ok(out) if out.success() => !out.stdout.trim().is_empty(),
-        _ => false,
-    }
+/// Patterns are matched like [`SensitiveFilePolicy`] patterns. Lockfiles and
+/// vendored dependencies are generated by default, as is any file marked
+/// `@generated` near its start.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GeneratedFilePolicy {
+    /// Patterns of generated files.
+    #[serde(default = "default_generated_file_patterns")]
+    pub patterns: Vec<String>,
+
+    /// Whether a file with `@generated` in its first KB counts as generated.
+    #[serde(default = "default_true", skip_serializing_if = "is_true")]
+    pub detect_marker: bool,
+
+    /// Names of directories holding build output or installed dependencies.
+    /// Files in them are flagged when about to be committed.
+    #[serde(default = "default_build_artifact_dirs")]
+    pub build_artifact_dirs: Vec<String>,
 }
 
-/// Find the justfile in the given directory.
-fn find_justfile(base_dir: &Path) -> Option<PathBuf> {
-    for name in ["justfile", "Justfile"] {
-        let path = base_dir.join(name);
-        if path.exists() {
-            return Some(path);
+impl Default for GeneratedFilePolicy {
+    fn default() -> Self {
+        Self {
+            patterns: default_generated_file_patterns(),
+            detect_marker: true,
+            build_artifact_dirs: default_build_artifact_dirs(),
         }
     }
-    None
 }
 
-/// Check if a justfile defines the 'check:' target.
-fn has_check_target(justfile_path: &Path) -> bool {
-    let Ok(content) = std::fs::read_to_string(justfile_path) else {
-        return false;
-    };
-
-    // Look for a line starting with 'check:' (possibly with recipe attributes before it)
-    for line in content.lines() {
-        let trimmed = line.trim();
-        // Skip empty lines and comments
-        if trimmed.is_empty() || trimmed.starts_with('#') {
-            continue;
-        }
-        // Check for 'check:' at the start of a line (recipe definition)
-        if trimmed.starts_with("check:") || trimmed.starts_with("check ") {
-            return true;
+impl GeneratedFilePolicy {
+    /// Check whether a file, relative to the repository root at `base_dir`,
+    /// was generated by a tool.
+    #[must_use]
+    pub fn is_generated(&self, base_dir: &Path, path: &str) -> bool {
+        self.patterns.iter().any(|pattern| path_matches(pattern, path))
+            || self.detect_marker && has_generated_marker(&base_dir.join(path))
+    }
+
+    /// Check whether a path lies inside a build output or dependency directory.
+    #[must_use]
+    pub fn is_build_artifact(&self, path: &str) -> bool {
+        let mut components: Vec<&str> = path.split('/').collect();
+        // The last component is the file itself, unless the path names a directory
+        if !path.ends_with('/') {
+            components.pop();
         }
+        components
+            .iter()
+            .any(|component| self.build_artifact_dirs.iter().any(|dir| dir == component))
     }
-    false
 }
 
-/// Check if CLAUDE.md has a "Code Review" section.
-/// Returns the section header if found (e.g., "## Code Review").
-fn find_code_review_section(base_dir: &Path) -> Option<String> {
-    let claude_md_path = base_dir.join(CLAUDE_MD_PATH);
-    let content = std::fs::read_to_string(claude_md_path).ok()?;
-    if content.contains(CODE_REVIEW_HEADER) {
-        Some(CODE_REVIEW_HEADER.to_string())
+/// Whether a file has [`GENERATED_MARKER`] near its start.
+fn has_generated_marker(path: &Path) -> bool {
+    let Ok(file) = std::fs::File::open(path) else {
+        return false;
+    };
+    let mut start = Vec::new();
+    std::io::Read::read_to_end(&mut std::io::Read::take(file, GENERATED_MARKER_BYTES), &mut start)
+        .is_ok()
+        && String::from_utf8_lossy(&start).contains(GENERATED_MARKER)
+}
+
+/// Match a file pattern against a path, relative to the repository root.
+fn path_matches(pattern: &str, path: &str) -> bool {
+    if pattern.contains('/') {
+        glob_matches(pattern, path) || glob_matches(&format!("*/{pattern}"), path)
     } else {
-        None
+        glob_matches(pattern, path.rsplit('/').next().unwrap_or(path))
     }
 }
 
-// NOTE: We no longer auto-modify CLAUDE.md due to corruption issues (claude-9i2m).
-// The Code Review section detection still works - if the user has manually added
-// a "## Code Review" section, it will be detected and used. Otherwise, the code
-// review hook uses a default prompt.
-
-/// Ensure config exists, creating it with detected defaults if not.
-///
-/// Returns the config (either loaded or newly created).
-///
-/// # Errors
-///
-/// Returns an error if config cannot be loaded or saved.
-#[cfg(feature = "cli")]
-pub fn ensure_config(runner: &dyn CommandRunner) -> Result<ProjectConfig> {
-    ensure_config_in(runner, Path::new("."))
+/// A group of stop hook checks that run together.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StopCheckTier {
+    /// Checks that let the agent stop straight away: API errors, loops,
+    /// problem mode, emergency stops, pauses and questions.
+    FastExit,
+    /// The quality check on changed code.
+    Validation,
+    /// Work items, git state, budget, pull requests and work item reminders.
+    TaskCompletion,
+    /// Asking the agent whether its work is complete.
+    Reflection,
 }
 
-/// Ensure config exists in a specific directory.
-///
-/// If the config is created and we're in a git repo, it will be automatically
-/// added and committed. Also ensures .gitignore has the required entries.
-/// Detects (but does not modify) CLAUDE.md Code Review section.
-///
-/// # Errors
-///
-/// Returns an error if config cannot be loaded or saved.
-pub fn ensure_config_in(runner: &dyn CommandRunner, base_dir: &Path) -> Result<ProjectConfig> {
-    // Try to load existing config
-    let mut config = if let Some(mut config) = ProjectConfig::load_from(base_dir)? {
-        // Always re-detect git_repo since .git could have been added/removed
-        // This is critical for the stop hook to correctly check for uncommitted changes
-        let git_repo = base_dir.join(".git").exists();
-        if config.git_repo != git_repo {
-            config.git_repo = git_repo;
-            // Update require_push based on new git_repo status
-            config.require_push = git_repo && has_git_remote(runner);
-            config.save_to(base_dir)?;
-        }
-        config
-    } else {
-        // Detect and create new config
-        let config = ProjectConfig::detect_in(runner, base_dir);
-        config.save_to(base_dir)?;
-
-        // Auto-commit config if in a git repo
-        if config.git_repo {
-            auto_commit_config(base_dir);
-        }
-
-        config
-    };
-
-    // Always ensure gitignore is up to date (if in a git repo)
-    if config.git_repo {
-        if let Ok(modified) = ensure_gitignore(base_dir) {
-            if modified {
-                auto_commit_gitignore(base_dir);
-            }
+impl StopCheckTier {
+    /// Every tier, in the default order.
+    pub const ALL: [Self; 4] =
+        [Self::FastExit, Self::Validation, Self::TaskCompletion, Self::Reflection];
+
+    /// The tier's name in the config.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::FastExit => "fast_exit",
+            Self::Validation => "validation",
+            Self::TaskCompletion => "task_completion",
+            Self::Reflection => "reflection",
         }
     }
 
-    // Ensure CLAUDE.md has binary instructions section
-    if let Ok(modified) = ensure_claude_md_binary_section(base_dir) {
-        if modified && config.git_repo {
-            auto_commit_claude_md(base_dir);
+    /// The names of the tier's checks, in the order they run.
+    #[must_use]
+    pub const fn checks(self) -> &'static [&'static str] {
+        match self {
+            Self::FastExit => &[
+                "api_error_loop",
+                "repeated_output",
+                "problem_mode_exit",
+                "emergency_stop_exit",
+                "pause_exit",
+                "simple_qa_fast_path",
+                "commit_push_auto_confirm",
+                "interactive_question",
+            ],
+            Self::Validation => &["validation_required"],
+            Self::TaskCompletion => &[
+                "requested_tasks",
+                "auto_work_tasks",
+                "commit_policy",
+                "uncommitted_changes",
+                "agent_stashes",
+                "session_budget",
+                "pull_request",
+                "work_item_reminder",
+            ],
+            Self::Reflection => {
+                &["reflection_marker", "question_skip_reflection", "reflection_prompt"]
+            }
         }
     }
 
-    // Detect (but don't modify) Code Review section in CLAUDE.md
-    // We no longer auto-add sections due to corruption issues (claude-9i2m)
-    if config.code_review_section.is_none() {
-        if let Some(section_title) = find_code_review_section(base_dir) {
-            config.code_review_section = Some(section_title);
-            config.save_to(base_dir)?;
-            if config.git_repo {
-                auto_commit_config(base_dir);
-            }
+    /// The tiers that must run before this one.
+    ///
+    /// The fast exits come first so a stuck agent can always stop, and
+    /// reflection comes last because it allows the stop once the agent has
+    /// reflected, which would skip any blocking checks after it.
+    const fn runs_after(self) -> &'static [Self] {
+        match self {
+            Self::FastExit => &[],
+            Self::Validation | Self::TaskCompletion => &[Self::FastExit],
+            Self::Reflection => &[Self::FastExit, Self::Validation, Self::TaskCompletion],
         }
     }
-
-    Ok(config)
 }
 
-/// Check if a path is gitignored in the given directory.
-fn is_gitignored(base_dir: &Path, path: &str) -> bool {
-    use std::process::Command;
-    let result =
-        Command::new("git").args(["check-ignore", "-q", path]).current_dir(base_dir).output();
-    matches!(result, Ok(output) if output.status.success())
-}
+/// Stop hook checks that cannot be switched off, because they are how an
+/// agent that is stuck, looping or waiting on the user gets to stop.
+pub const REQUIRED_STOP_CHECKS: &[&str] = &[
+    "api_error_loop",
+    "repeated_output",
+    "problem_mode_exit",
+    "emergency_stop_exit",
+    "pause_exit",
+];
 
-/// Automatically add and commit the config file.
-/// Silently ignores any errors (best effort).
-fn auto_commit_config(base_dir: &Path) {
-    use std::process::Command;
+/// Stop hook checks that only work with another check, as `(check, needs)`.
+const STOP_CHECK_DEPENDENCIES: &[(&str, &str)] = &[
+    // Without the marker check, the reflection prompt blocks every stop
+    ("reflection_prompt", "reflection_marker"),
+];
 
-    let config_path = ProjectConfig::config_path(base_dir);
-    let config_path_str = config_path.to_string_lossy();
+/// Which stop hook checks run, and in what order.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct StopChecksPolicy {
+    /// Names of checks that are skipped, as shown in the stop hook's checks log.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub disable: Vec<String>,
+
+    /// The order the tiers run in, naming every tier once. The default order
+    /// when empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tier_order: Vec<StopCheckTier>,
+}
 
-    // Skip if the file is gitignored
-    if is_gitignored(base_dir, &config_path_str) {
-        return;
+impl StopChecksPolicy {
+    /// Check whether a check is switched off.
+    #[must_use]
+    pub fn is_disabled(&self, check: &str) -> bool {
+        self.disable.iter().any(|name| name == check)
     }
 
-    // Try to add the file
-    let add_result =
-        Command::new("git").args(["add", &config_path_str]).current_dir(base_dir).output();
-
-    if add_result.is_err() || !add_result.unwrap().status.success() {
-        return;
+    /// The tiers in the order they run.
+    #[must_use]
+    pub fn tiers(&self) -> Vec<StopCheckTier> {
+        if self.tier_order.is_empty() {
+            StopCheckTier::ALL.to_vec()
+        } else {
+            self.tier_order.clone()
+        }
     }
 
-    // Check if there's anything to commit (file might already be committed)
-    let diff_result = Command::new("git")
-        .args(["diff", "--cached", "--quiet", "--", &config_path_str])
-        .current_dir(base_dir)
-        .output();
+    /// Check the policy against the known checks and what they rely on.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming the first problem: an unknown or required
+    /// check, a disabled check that another enabled check needs, or a tier
+    /// order that leaves out a tier or runs one before a tier it relies on.
+    pub fn validate(&self) -> Result<()> {
+        for name in &self.disable {
+            if !StopCheckTier::ALL.iter().any(|tier| tier.checks().contains(&name.as_str())) {
+                return Err(Error::Config(format!("stop_checks.disable: unknown check '{name}'")));
+            }
+            if REQUIRED_STOP_CHECKS.contains(&name.as_str()) {
+                return Err(Error::Config(format!(
+                    "stop_checks.disable: '{name}' cannot be disabled; it lets a stuck agent stop"
+                )));
+            }
+        }
+        for (check, needs) in STOP_CHECK_DEPENDENCIES {
+            if !self.is_disabled(check) && self.is_disabled(needs) {
+                return Err(Error::Config(format!(
+                    "stop_checks.disable: '{check}' needs '{needs}'; disable both or neither"
+                )));
+            }
+        }
 
-    if let Ok(output) = diff_result {
-        if output.status.success() {
-            // No changes staged, nothing to commit
-            return;
+        if self.tier_order.is_empty() {
+            return Ok(());
+        }
+        for tier in StopCheckTier::ALL {
+            if self.tier_order.iter().filter(|t| **t == tier).count() != 1 {
+                return Err(Error::Config(format!(
+                    "stop_checks.tier_order must name every tier once, including '{}'",
+                    tier.name()
+                )));
+            }
+        }
+        for (position, tier) in self.tier_order.iter().enumerate() {
+            if let Some(before) =
+                tier.runs_after().iter().find(|t| !self.tier_order[..position].contains(t))
+            {
+                return Err(Error::Config(format!(
+                    "stop_checks.tier_order: '{}' must run after '{}'",
+                    tier.name(),
+                    before.name()
+                )));
+            }
         }
+        Ok(())
     }
+}
 
-    // Commit the config file
-    let _ = Command::new("git")
-        .args([
-            "commit",
-            "-m",
-            "Add claude-reliability config\n\nAuto-generated by claude-reliability plugin.",
-        ])
-        .current_dir(base_dir)
-        .output();
+/// Which changes need a work item in progress.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RequireTaskScope {
+    /// Never require one.
+    Off,
+    /// Before the Write and Edit tools.
+    #[default]
+    Edits,
+    /// Before any change to a file: Write, Edit, `NotebookEdit`, and Bash
+    /// commands that write, move or delete files.
+    All,
 }
 
-/// Ensure the gitignore contains the required entries for claude-reliability.
-///
-/// This function:
-/// 1. Reads the existing .gitignore (or creates one if it doesn't exist)
-/// 2. Finds or creates a managed section headed by `# claude-reliability managed`
-/// 3. Ensures all required entries are present in that section
-/// 4. Preserves all other content in the file
-///
-/// Returns true if the gitignore was modified, false if it was already correct.
+/// Settings for requiring a work item in progress before changing files.
 ///
-/// # Errors
-///
-/// Returns an error if the .gitignore file cannot be read or written.
-pub fn ensure_gitignore(base_dir: &Path) -> std::io::Result<bool> {
-    let gitignore_path = base_dir.join(".gitignore");
-
-    // Read existing content or start fresh
-    let existing_content = if gitignore_path.exists() {
-        std::fs::read_to_string(&gitignore_path)?
-    } else {
-        String::new()
-    };
+/// Files outside the project are never covered. An exempt pattern without a
+/// `/` matches a file's name, and a pattern with one matches the end of its
+/// path. `*` matches any characters, so `scratch/*` exempts a whole directory.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RequireTaskPolicy {
+    /// Which changes need a work item in progress.
+    #[serde(default)]
+    pub scope: RequireTaskScope,
 
-    let new_content = update_gitignore_content(&existing_content);
+    /// Patterns of files that can be changed without one, such as scratch directories.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exempt: Vec<String>,
+}
 
-    if new_content == existing_content {
-        return Ok(false);
+impl RequireTaskPolicy {
+    /// Check whether a file, relative to the project root, can be changed without a work item.
+    #[must_use]
+    pub fn is_exempt(&self, path: &str) -> bool {
+        self.exempt.iter().any(|pattern| path_matches(pattern, path))
     }
-
-    std::fs::write(&gitignore_path, &new_content)?;
-    Ok(true)
 }
 
-/// Update gitignore content to include the managed section.
+/// Settings for the spelling and terminology check.
 ///
-/// This is additive-only: it never removes existing entries from the managed section.
-/// If a user adds entries within the managed section, they are preserved.
-/// If entries from `GITIGNORE_ENTRIES` are already present, they are not duplicated.
-#[allow(clippy::option_if_let_else)]
-fn update_gitignore_content(existing: &str) -> String {
-    // Check if the managed section already exists
-    if let Some(start_idx) = existing.find(GITIGNORE_SECTION_HEADER) {
-        // Find the end of the managed section (next comment or end of file)
-        let after_header = &existing[start_idx + GITIGNORE_SECTION_HEADER.len()..];
-        let section_end = find_section_end(after_header);
+/// Added lines in Markdown files, doc comments and string literals are checked
+/// for common misspellings and banned terms, reported as warnings before a commit.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SpellingPolicy {
+    /// Whether to run the check.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub enabled: bool,
 
-        // Extract existing entries in the managed section
-        let section_content = &after_header[..section_end];
-        let existing_entries: Vec<&str> =
-            section_content.lines().map(str::trim).filter(|l| !l.is_empty()).collect();
+    /// Banned words or phrases, mapped to the preferred term (empty for none).
+    /// Also used to add misspellings missing from the built-in list.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub terminology: BTreeMap<String, String>,
+}
 
-        // Determine which entries from GITIGNORE_ENTRIES are missing
-        let missing: Vec<&&str> = GITIGNORE_ENTRIES
-            .iter()
-            .filter(|entry| !existing_entries.contains(&entry.trim()))
-            .collect();
+/// Channels that new questions are delivered on, and replies read from.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct QuestionDelivery {
+    /// GitHub issue that questions are posted to as comments: `owner/repo#123`,
+    /// or `123` for an issue of the current repository. Uses the `gh` CLI.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub github_issue: Option<String>,
 
-        if missing.is_empty() {
-            // All entries present, no modification needed
-            return existing.to_string();
-        }
+    /// Address that questions are emailed to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email_to: Option<String>,
 
-        // Append missing entries to the end of the existing managed section content.
-        // We insert them just before `section_end` (before any trailing content).
-        let insert_pos = start_idx + GITIGNORE_SECTION_HEADER.len() + section_end;
+    /// Sender address of question emails. The mailer's default when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email_from: Option<String>,
 
-        let mut result = existing[..insert_pos].to_string();
-        // Ensure we're on a new line before appending
-        if !result.ends_with('\n') {
-            result.push('\n');
-        }
-        for entry in &missing {
-            result.push_str(entry);
-            result.push('\n');
-        }
-        // Append everything after the managed section
-        let after = &existing[insert_pos..];
-        if !after.is_empty() {
-            result.push_str(after.trim_start_matches('\n'));
-        }
-        result
-    } else {
-        // No managed section exists, append it
-        let managed_section = build_managed_section();
-        let mut result = existing.to_string();
-        if !result.is_empty() && !result.ends_with('\n') {
-            result.push('\n');
-        }
-        if !result.is_empty() {
-            result.push('\n');
+    /// Command that sends an email read from standard input. Use an SMTP
+    /// client such as `msmtp -t` to send through an SMTP server.
+    #[serde(
+        default = "default_sendmail_command",
+        skip_serializing_if = "is_default_sendmail_command"
+    )]
+    pub sendmail_command: String,
+
+    /// Maildir, relative to the project root, that replies to question emails
+    /// are delivered to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reply_maildir: Option<String>,
+
+    /// GitHub logins and email addresses whose replies may also answer
+    /// questions. Comments by the repository's owners, members and
+    /// collaborators, and emails from `email_to`, are always accepted.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub answer_from: Vec<String>,
+}
+
+impl Default for QuestionDelivery {
+    fn default() -> Self {
+        Self {
+            github_issue: None,
+            email_to: None,
+            email_from: None,
+            sendmail_command: default_sendmail_command(),
+            reply_maildir: None,
+            answer_from: Vec::new(),
         }
-        result.push_str(&managed_section);
-        result
     }
 }
 
-/// Build the managed section content.
-fn build_managed_section() -> String {
-    let mut section = String::from(GITIGNORE_SECTION_HEADER);
-    section.push('\n');
-    for entry in GITIGNORE_ENTRIES {
-        section.push_str(entry);
-        section.push('\n');
+impl QuestionDelivery {
+    /// Check whether any delivery channel is configured.
+    #[must_use]
+    pub const fn is_enabled(&self) -> bool {
+        self.github_issue.is_some() || self.email_to.is_some()
     }
-    section
 }
 
-/// Find the end of the managed section (next comment line or end of string).
-fn find_section_end(content: &str) -> usize {
-    let mut pos = 0;
-    for line in content.lines() {
-        // Skip the newline after the header
-        pos += line.len() + 1; // +1 for newline
+/// Where blockers the agent cannot get past are reported.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct EscalationPolicy {
+    /// Whether to open a GitHub issue for each blocker. Uses the `gh` CLI.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub github_issues: bool,
 
-        // If we hit another comment line (not empty), that's the end of our section
-        let trimmed = line.trim();
-        if trimmed.starts_with('#') && !trimmed.is_empty() && pos > 1 {
-            // Go back to before this line
-            return pos - line.len() - 1;
-        }
+    /// Repository issues are opened in (`owner/repo`). The current
+    /// repository when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub github_repo: Option<String>,
+
+    /// Labels added to the issues. They must already exist in the repository.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub github_labels: Vec<String>,
+
+    /// Markdown file, relative to the project root, that blockers are
+    /// appended to (such as `BLOCKERS.md`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blockers_file: Option<String>,
+}
+
+impl EscalationPolicy {
+    /// Check whether blockers are reported anywhere.
+    #[must_use]
+    pub const fn is_enabled(&self) -> bool {
+        self.github_issues || self.blockers_file.is_some()
     }
-    // Reached end of file
-    content.len()
 }
 
-/// Automatically add and commit the gitignore changes.
-/// Silently ignores any errors (best effort).
-fn auto_commit_gitignore(base_dir: &Path) {
-    use std::process::Command;
+/// Who commits must be made by, and whether they must be signed.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CommitPolicy {
+    /// The committer name commits must have (git's `user.name`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub committer_name: Option<String>,
 
-    let gitignore_path = base_dir.join(".gitignore");
-    let gitignore_str = gitignore_path.to_string_lossy();
+    /// The committer email commits must have (git's `user.email`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub committer_email: Option<String>,
 
-    // Skip if the file is gitignored
-    if is_gitignored(base_dir, &gitignore_str) {
-        return;
+    /// Whether commits must have a good GPG or SSH signature.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub require_signature: bool,
+}
+
+impl CommitPolicy {
+    /// Check whether anything is required of commits.
+    #[must_use]
+    pub const fn is_enabled(&self) -> bool {
+        self.committer_name.is_some() || self.committer_email.is_some() || self.require_signature
     }
+}
 
-    // Try to add the file
-    let add_result =
-        Command::new("git").args(["add", &gitignore_str]).current_dir(base_dir).output();
+/// How to check for uncommitted changes in a large repository.
+///
+/// Any setting here switches the check from several `git diff` and
+/// `git ls-files` calls to a single `git status --no-renames
+/// --untracked-files=normal`, which skips rename detection and lists an
+/// untracked directory once rather than every file in it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GitStatusPolicy {
+    /// Subtrees to check, relative to the project root. Changes elsewhere are
+    /// not looked for. Empty checks the whole tree.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub paths: Vec<String>,
+
+    /// Whether to use the single `git status` call without restricting the paths.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub fast: bool,
 
-    if add_result.is_err() || !add_result.unwrap().status.success() {
-        return;
+    /// Whether to keep the untracked file list between stop hook runs, reusing
+    /// it until a directory, ignore file or the index changes.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub cache_untracked: bool,
+}
+
+impl GitStatusPolicy {
+    /// Check whether the single `git status` call is used.
+    #[must_use]
+    pub fn is_fast(&self) -> bool {
+        self.fast || self.cache_untracked || !self.paths.is_empty()
     }
+}
 
-    // Check if there's anything to commit
-    let diff_result = Command::new("git")
-        .args(["diff", "--cached", "--quiet", "--", &gitignore_str])
-        .current_dir(base_dir)
-        .output();
+/// Quality checks and protected paths for one package of a workspace.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PackageConfig {
+    /// Name of the package, used for task scoping and in messages.
+    pub name: String,
 
-    if let Ok(output) = diff_result {
-        if output.status.success() {
-            return;
-        }
-    }
+    /// Directory of the package relative to the project root. `*` matches any
+    /// sequence of characters (e.g. `crates/*`).
+    pub path: String,
 
-    // Commit the gitignore
-    let _ = Command::new("git")
-        .args(["commit", "-m", "Update .gitignore for claude-reliability\n\nAuto-generated by claude-reliability plugin."])
-        .current_dir(base_dir)
-        .output();
-}
+    /// Commands run from the project root when files in this package change.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub check_commands: Vec<String>,
 
-/// Ensure CLAUDE.md contains the managed binary instructions section.
-///
-/// This function:
-/// 1. Reads the existing CLAUDE.md (returns `Ok(false)` if it doesn't exist)
-/// 2. Finds the managed section by start delimiter
-/// 3. If present, replaces content between delimiters with current template
-/// 4. If absent, appends the section at the end of the file
-/// 5. Returns `Ok(true)` if the file was modified
-///
-/// # Errors
-///
-/// Returns an error if the CLAUDE.md file cannot be read or written.
-pub fn ensure_claude_md_binary_section(base_dir: &Path) -> std::io::Result<bool> {
-    let claude_md_path = base_dir.join(CLAUDE_MD_PATH);
+    /// Patterns for files in this package that may not be edited, relative to the package.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub protected_paths: Vec<String>,
+}
 
-    if !claude_md_path.exists() {
-        return Ok(false);
+impl PackageConfig {
+    /// Check whether a path relative to the project root is inside this package.
+    #[must_use]
+    pub fn contains(&self, path: &str) -> bool {
+        self.relative_path(path).is_some()
     }
 
-    let existing_content = std::fs::read_to_string(&claude_md_path)?;
-    let new_content = update_claude_md_content(&existing_content);
+    /// Check whether a path relative to the project root is a protected file of this package.
+    #[must_use]
+    pub fn is_protected(&self, path: &str) -> bool {
+        self.relative_path(path).is_some_and(|relative| {
+            self.protected_paths.iter().any(|pattern| glob_matches(pattern, relative))
+        })
+    }
 
-    if new_content == existing_content {
-        return Ok(false);
+    /// The part of `path` below the package directory, if it is inside the package.
+    fn relative_path<'a>(&self, path: &'a str) -> Option<&'a str> {
+        let root = self.path.trim_end_matches('/');
+        let path = path.trim_start_matches("./");
+        path.char_indices()
+            .filter(|&(_, c)| c == '/')
+            .map(|(idx, _)| idx)
+            .find(|&idx| glob_matches(root, &path[..idx]))
+            .map(|idx| &path[idx + 1..])
     }
+}
 
-    std::fs::write(&claude_md_path, &new_content)?;
-    Ok(true)
+/// Find the package containing `path`, relative to the project root.
+#[must_use]
+pub fn find_package<'a>(packages: &'a [PackageConfig], path: &str) -> Option<&'a PackageConfig> {
+    packages.iter().find(|package| package.contains(path))
 }
 
-/// Update CLAUDE.md content to include or refresh the managed binary instructions section.
+/// Express a file path from a tool call relative to the project root.
 ///
-/// This is a pure function for easy testing.
-fn update_claude_md_content(existing: &str) -> String {
-    let section = build_claude_md_section();
+/// Returns `None` for absolute paths outside the project.
+#[must_use]
+pub fn project_relative_path(base_dir: &Path, file_path: &str) -> Option<String> {
+    let path = Path::new(file_path);
+    if path.is_relative() {
+        return Some(file_path.trim_start_matches("./").to_string());
+    }
+    let base =
+        std::env::current_dir().map_or_else(|_| base_dir.to_path_buf(), |cwd| cwd.join(base_dir));
+    let relative = path.strip_prefix(&base).ok().map(Path::to_path_buf).or_else(|| {
+        let canonical = base.canonicalize().ok()?;
+        path.strip_prefix(canonical).ok().map(Path::to_path_buf)
+    })?;
+    Some(relative.to_string_lossy().into_owned())
+}
 
-    if let Some(start_idx) = existing.find(CLAUDE_MD_SECTION_START) {
-        // Managed section exists - replace it
-        if let Some(end_offset) = existing[start_idx..].find(CLAUDE_MD_SECTION_END) {
-            let end_idx = start_idx + end_offset + CLAUDE_MD_SECTION_END.len();
-            let mut result = existing[..start_idx].to_string();
-            result.push_str(&section);
-            result.push_str(&existing[end_idx..]);
-            return result;
+/// Find the first rule in `rules` matching `branch`.
+#[must_use]
+pub fn find_branch_rule<'a>(rules: &'a [BranchRule], branch: &str) -> Option<&'a BranchRule> {
+    rules.iter().find(|rule| rule.matches(branch))
+}
+
+/// Find the first rule in `rules` matching `model`.
+#[must_use]
+pub fn find_model_rule<'a>(rules: &'a [ModelRule], model: &str) -> Option<&'a ModelRule> {
+    rules.iter().find(|rule| rule.matches(model))
+}
+
+/// Match `text` against a pattern where `*` matches any sequence of characters.
+fn glob_matches(pattern: &str, text: &str) -> bool {
+    let Some((prefix, rest)) = pattern.split_once('*') else {
+        return pattern == text;
+    };
+    let Some(mut remaining) = text.strip_prefix(prefix) else {
+        return false;
+    };
+
+    let mut parts = rest.split('*').peekable();
+    while let Some(part) = parts.next() {
+        if parts.peek().is_none() {
+            return remaining.ends_with(part);
+        }
+        match remaining.find(part) {
+            Some(idx) => remaining = &remaining[idx + part.len()..],
+            None => return false,
         }
-        // Start marker found but no end marker - append end after content
-        // This handles a corrupted section gracefully
     }
+    true
+}
 
-    // No managed section exists, append it
-    let mut result = existing.to_string();
-    if !result.is_empty() && !result.ends_with('\n') {
-        result.push('\n');
-    }
-    if !result.is_empty() {
-        result.push('\n');
-    }
-    result.push_str(&section);
-    result.push('\n');
-    result
+/// Default value for `check_output_limit` - 1 MiB.
+const fn default_check_output_limit() -> usize {
+    1024 * 1024
 }
 
-/// Build the full managed CLAUDE.md section with delimiters.
-fn build_claude_md_section() -> String {
-    format!(
-        "{CLAUDE_MD_SECTION_START}\n{CLAUDE_MD_BINARY_SECTION_CONTENT}\n{CLAUDE_MD_SECTION_END}"
-    )
+/// Check if the output limit is the default value (for `skip_serializing_if`).
+#[allow(clippy::trivially_copy_pass_by_ref)] // Required signature for serde's skip_serializing_if
+const fn is_default_check_output_limit(val: &usize) -> bool {
+    *val == default_check_output_limit()
 }
 
-/// Automatically add and commit CLAUDE.md changes.
-/// Silently ignores any errors (best effort).
-fn auto_commit_claude_md(base_dir: &Path) {
-    use std::process::Command;
+/// Default value for `require_push` - true by default.
+const fn default_require_push() -> bool {
+    true
+}
 
-    let claude_md_path = base_dir.join(CLAUDE_MD_PATH);
-    let claude_md_str = claude_md_path.to_string_lossy();
+/// Default value for `auto_work_on_tasks` - true by default.
+const fn default_auto_work_on_tasks() -> bool {
+    true
+}
 
-    // Skip if the file is gitignored
-    if is_gitignored(base_dir, &claude_md_str) {
-        return;
-    }
+/// Default value for `auto_work_idle_minutes` - 15 minutes.
+const fn default_auto_work_idle_minutes() -> u32 {
+    15
+}
 
-    // Try to add the file
-    let add_result =
-        Command::new("git").args(["add", &claude_md_str]).current_dir(base_dir).output();
+/// Check if idle minutes is the default value (for `skip_serializing_if`).
+#[allow(clippy::trivially_copy_pass_by_ref)] // Required signature for serde's skip_serializing_if
+const fn is_default_idle_minutes(val: &u32) -> bool {
+    *val == default_auto_work_idle_minutes()
+}
 
-    if add_result.is_err() || !add_result.unwrap().status.success() {
-        return;
-    }
+/// Default value for `auto_work_decline_limit`.
+const fn default_auto_work_decline_limit() -> u32 {
+    crate::hooks::AUTO_WORK_DECLINE_LIMIT
+}
 
-    // Check if there's anything to commit
-    let diff_result = Command::new("git")
-        .args(["diff", "--cached", "--quiet", "--", &claude_md_str])
-        .current_dir(base_dir)
-        .output();
+/// Check if `auto_work_decline_limit` is the default value (for `skip_serializing_if`).
+#[allow(clippy::trivially_copy_pass_by_ref)] // Required signature for serde's skip_serializing_if
+const fn is_default_auto_work_decline_limit(val: &u32) -> bool {
+    *val == default_auto_work_decline_limit()
+}
 
-    if let Ok(output) = diff_result {
-        if output.status.success() {
-            return;
-        }
-    }
+/// Default value for `auto_work_cooldown_minutes`.
+const fn default_auto_work_cooldown_minutes() -> u32 {
+    crate::hooks::AUTO_WORK_COOLDOWN_MINUTES
+}
 
-    // Commit CLAUDE.md
-    let _ = Command::new("git")
-        .args([
-            "commit",
-            "-m",
-            "Update CLAUDE.md binary instructions\n\nAuto-generated by claude-reliability plugin.",
-        ])
-        .current_dir(base_dir)
-        .output();
+/// Check if `auto_work_cooldown_minutes` is the default value (for `skip_serializing_if`).
+#[allow(clippy::trivially_copy_pass_by_ref)] // Required signature for serde's skip_serializing_if
+const fn is_default_auto_work_cooldown_minutes(val: &u32) -> bool {
+    *val == default_auto_work_cooldown_minutes()
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::testing::MockCommandRunner;
-    use tempfile::TempDir;
+/// Default value for `commit_split_files` - 25 files.
+const fn default_commit_split_files() -> usize {
+    25
+}
 
-    #[test]
-    fn test_project_config_default() {
-        let config = ProjectConfig::default();
-        assert!(!config.git_repo);
-        assert!(config.check_command.is_none());
-    }
+/// Check if `commit_split_files` is the default value (for `skip_serializing_if`).
+#[allow(clippy::trivially_copy_pass_by_ref)] // Required signature for serde's skip_serializing_if
+const fn is_default_commit_split_files(val: &usize) -> bool {
+    *val == default_commit_split_files()
+}
 
-    #[test]
-    fn test_project_config_load_not_found() {
-        let dir = TempDir::new().unwrap();
-        let result = ProjectConfig::load_from(dir.path()).unwrap();
-        assert!(result.is_none());
-    }
+/// Default value for `commit_split_lines` - 1000 lines.
+const fn default_commit_split_lines() -> usize {
+    1000
+}
 
-    #[test]
-    fn test_project_config_save_and_load() {
-        let dir = TempDir::new().unwrap();
+/// Check if `commit_split_lines` is the default value (for `skip_serializing_if`).
+#[allow(clippy::trivially_copy_pass_by_ref)] // Required signature for serde's skip_serializing_if
+const fn is_default_commit_split_lines(val: &usize) -> bool {
+    *val == default_commit_split_lines()
+}
 
-        let config = ProjectConfig {
-            git_repo: true,
-            check_command: Some("just check".to_string()),
-            code_review_section: None,
-            require_push: true,
-            explain_stops: false,
-            ..Default::default()
-        };
+/// Default value for `binary_files.max_kb` - 100 KB.
+const fn default_binary_max_kb() -> u64 {
+    100
+}
 
-        config.save_to(dir.path()).unwrap();
+/// Default value for `binary_files.allowed_extensions` - common image formats.
+fn default_binary_allowed_extensions() -> Vec<String> {
+    ["png", "jpg", "jpeg", "gif", "webp", "ico", "svg"].iter().map(ToString::to_string).collect()
+}
 
-        let loaded = ProjectConfig::load_from(dir.path()).unwrap().unwrap();
-        assert_eq!(config, loaded);
-    }
+/// Default value for `binary_files.allowed_max_kb` - 512 KB.
+const fn default_binary_allowed_max_kb() -> u64 {
+    512
+}
 
-    #[test]
-    fn test_project_config_yaml_format() {
-        let dir = TempDir::new().unwrap();
+/// Default value for `sensitive_files.patterns` - environment files, private
+/// keys and the credential files of common tools and cloud providers.
+fn default_sensitive_file_patterns() -> Vec<String> {
+    [
+        ".env",
+        ".env.*",
+        "*.pem",
+        "*.p12",
+        "*.pfx",
+        "id_rsa",
+        "id_dsa",
+        "id_ecdsa",
+        "id_ed25519",
+        "credentials.json",
+        "service-account*.json",
+        "application_default_credentials.json",
+        ".aws/credentials",
+        ".aws/config",
+        ".azure/accessTokens.json",
+        ".docker/config.json",
+        ".kube/config",
+        ".git-credentials",
+        ".netrc",
+        ".pypirc",
+    ]
+    .iter()
+    .map(ToString::to_string)
+    .collect()
+}
 
-        let config = ProjectConfig {
-            git_repo: true,
-            check_command: Some("make test".to_string()),
-            code_review_section: None,
-            require_push: true,
-            explain_stops: false,
-            ..Default::default()
-        };
+/// Default value for `generated_files.patterns` - lockfiles and vendored
+/// dependencies.
+fn default_generated_file_patterns() -> Vec<String> {
+    [
+        "Cargo.lock",
+        "package-lock.json",
+        "npm-shrinkwrap.json",
+        "yarn.lock",
+        "pnpm-lock.yaml",
+        "bun.lockb",
+        "poetry.lock",
+        "Pipfile.lock",
+        "uv.lock",
+        "Gemfile.lock",
+        "composer.lock",
+        "go.sum",
+        "flake.lock",
+        "vendor/*",
+        "third_party/*",
+    ]
+    .iter()
+    .map(ToString::to_string)
+    .collect()
+}
 
-        config.save_to(dir.path()).unwrap();
+/// Default value for `generated_files.build_artifact_dirs`.
+fn default_build_artifact_dirs() -> Vec<String> {
+    ["target", "node_modules", "__pycache__", ".pytest_cache", ".mypy_cache", ".venv"]
+        .iter()
+        .map(ToString::to_string)
+        .collect()
+}
 
-        let content = std::fs::read_to_string(dir.path().join(CONFIG_FILE_PATH)).unwrap();
-        assert!(content.contains("git_repo: true"));
-        assert!(content.contains("check_command: make test"));
-        assert!(content.contains("require_push: true"));
-    }
+/// Default value for `sensitive_files.allowlist` - environment file templates.
+fn default_sensitive_file_allowlist() -> Vec<String> {
+    [".env.example", ".env.sample", ".env.template"].iter().map(ToString::to_string).collect()
+}
 
-    #[test]
-    fn test_project_config_detect_no_git() {
-        let dir = TempDir::new().unwrap();
-        let runner = MockCommandRunner::new();
+/// Default value for `context_budget`.
+const fn default_context_budget() -> usize {
+    crate::context_budget::DEFAULT_BUDGET
+}
 
-        let config = ProjectConfig::detect_in(&runner, dir.path());
-        assert!(!config.git_repo);
-    }
+/// Check if `context_budget` is the default value (for `skip_serializing_if`).
+#[allow(clippy::trivially_copy_pass_by_ref)] // Required signature for serde's skip_serializing_if
+const fn is_default_context_budget(val: &usize) -> bool {
+    *val == default_context_budget()
+}
 
-    #[test]
-    fn test_project_config_detect_with_git() {
-        use crate::traits::CommandOutput;
+/// Default value for the auto-confirm settings - true by default.
+const fn default_true() -> bool {
+    true
+}
 
-        let dir = TempDir::new().unwrap();
-        std::fs::create_dir(dir.path().join(".git")).unwrap();
-        let mut runner = MockCommandRunner::new();
-        // Mock git remote returning empty (no remote configured)
-        runner.expect(
-            "git",
-            &["remote"],
-            CommandOutput { exit_code: 0, stdout: String::new(), stderr: String::new() },
-        );
+/// Check if a setting that defaults to true is true (for `skip_serializing_if`).
+#[allow(clippy::trivially_copy_pass_by_ref)] // Required signature for serde's skip_serializing_if
+const fn is_true(val: &bool) -> bool {
+    *val
+}
 
-        let config = ProjectConfig::detect_in(&runner, dir.path());
-        assert!(config.git_repo);
-        // No remote, so require_push should be false
-        assert!(!config.require_push);
-        runner.verify();
-    }
-
-    #[test]
-    fn test_project_config_detect_check_command() {
-        let dir = TempDir::new().unwrap();
+/// Check if a setting that defaults to 0 is 0 (for `skip_serializing_if`).
+#[allow(clippy::trivially_copy_pass_by_ref)] // Required signature for serde's skip_serializing_if
+const fn is_zero(val: &u32) -> bool {
+    *val == 0
+}
 
-        // Create justfile with check target
-        std::fs::write(dir.path().join("justfile"), "check:\n    cargo test\n").unwrap();
+/// Default value for `user_recency_minutes`.
+const fn default_user_recency_minutes() -> u32 {
+    crate::hooks::USER_RECENCY_MINUTES
+}
 
-        let mut runner = MockCommandRunner::new();
-        runner.set_available("just");
+/// Check if `user_recency_minutes` is the default value (for `skip_serializing_if`).
+#[allow(clippy::trivially_copy_pass_by_ref)] // Required signature for serde's skip_serializing_if
+const fn is_default_user_recency_minutes(val: &u32) -> bool {
+    *val == default_user_recency_minutes()
+}
 
-        let config = ProjectConfig::detect_in(&runner, dir.path());
-        assert_eq!(config.check_command, Some("just check".to_string()));
-    }
+/// Default value for `api_error_threshold`.
+const fn default_api_error_threshold() -> u32 {
+    crate::hooks::API_ERROR_THRESHOLD
+}
 
-    #[test]
-    fn test_project_config_detect_no_check_command_no_just() {
-        let dir = TempDir::new().unwrap();
+/// Check if `api_error_threshold` is the default value (for `skip_serializing_if`).
+#[allow(clippy::trivially_copy_pass_by_ref)] // Required signature for serde's skip_serializing_if
+const fn is_default_api_error_threshold(val: &u32) -> bool {
+    *val == default_api_error_threshold()
+}
 
-        // Create justfile but just is not available
-        std::fs::write(dir.path().join("justfile"), "check:\n    cargo test\n").unwrap();
+/// Default value for `max_files_to_show`.
+const fn default_max_files_to_show() -> usize {
+    crate::hooks::MAX_FILES_TO_SHOW
+}
 
-        let runner = MockCommandRunner::new();
+/// Check if `max_files_to_show` is the default value (for `skip_serializing_if`).
+#[allow(clippy::trivially_copy_pass_by_ref)] // Required signature for serde's skip_serializing_if
+const fn is_default_max_files_to_show(val: &usize) -> bool {
+    *val == default_max_files_to_show()
+}
 
-        let config = ProjectConfig::detect_in(&runner, dir.path());
-        assert!(config.check_command.is_none());
-    }
+/// Default value for `emergency_stop_max_per_hour`.
+const fn default_emergency_stop_max_per_hour() -> u32 {
+    crate::emergency_stop::MAX_EMERGENCY_STOPS_PER_HOUR
+}
 
-    #[test]
-    fn test_project_config_detect_no_check_command_no_justfile() {
-        let dir = TempDir::new().unwrap();
+/// Check if `emergency_stop_max_per_hour` is the default value (for `skip_serializing_if`).
+#[allow(clippy::trivially_copy_pass_by_ref)] // Required signature for serde's skip_serializing_if
+const fn is_default_emergency_stop_max_per_hour(val: &u32) -> bool {
+    *val == default_emergency_stop_max_per_hour()
+}
 
-        let mut runner = MockCommandRunner::new();
-        runner.set_available("just");
+/// Default value for `emergency_stop_rejections_before_question`.
+const fn default_emergency_stop_rejections_before_question() -> u32 {
+    crate::emergency_stop::REJECTIONS_BEFORE_QUESTION
+}
 
-        let config = ProjectConfig::detect_in(&runner, dir.path());
-        assert!(config.check_command.is_none());
-    }
+/// Check if `emergency_stop_rejections_before_question` is the default value (for `skip_serializing_if`).
+#[allow(clippy::trivially_copy_pass_by_ref)] // Required signature for serde's skip_serializing_if
+const fn is_default_emergency_stop_rejections_before_question(val: &u32) -> bool {
+    *val == default_emergency_stop_rejections_before_question()
+}
 
-    #[test]
-    fn test_project_config_detect_no_check_target() {
-        let dir = TempDir::new().unwrap();
+/// Check if the message verbosity is the default (for `skip_serializing_if`).
+#[allow(clippy::trivially_copy_pass_by_ref)] // Required signature for serde's skip_serializing_if
+fn is_default_message_verbosity(val: &MessageVerbosity) -> bool {
+    *val == MessageVerbosity::default()
+}
 
-        // Create justfile without check target
-        std::fs::write(dir.path().join("justfile"), "build:\n    cargo build\n").unwrap();
+/// Check if `failure_mode` is the default value (for `skip_serializing_if`).
+#[allow(clippy::trivially_copy_pass_by_ref)] // Required signature for serde's skip_serializing_if
+fn is_default_failure_mode(val: &FailureMode) -> bool {
+    *val == FailureMode::default()
+}
 
-        let mut runner = MockCommandRunner::new();
-        runner.set_available("just");
+/// Default value for `backup_interval_hours`.
+const fn default_backup_interval_hours() -> u32 {
+    crate::backup::BACKUP_INTERVAL_HOURS
+}
 
-        let config = ProjectConfig::detect_in(&runner, dir.path());
-        assert!(config.check_command.is_none());
-    }
+/// Check if `backup_interval_hours` is the default value (for `skip_serializing_if`).
+#[allow(clippy::trivially_copy_pass_by_ref)] // Required signature for serde's skip_serializing_if
+const fn is_default_backup_interval_hours(val: &u32) -> bool {
+    *val == default_backup_interval_hours()
+}
 
-    #[test]
-    fn test_find_justfile_lowercase() {
-        let dir = TempDir::new().unwrap();
-        std::fs::write(dir.path().join("justfile"), "").unwrap();
+/// Default value for `backup_keep`.
+const fn default_backup_keep() -> u32 {
+    crate::backup::BACKUP_KEEP
+}
 
-        let result = find_justfile(dir.path());
-        assert!(result.is_some());
-        assert!(result.unwrap().ends_with("justfile"));
-    }
+/// Check if `backup_keep` is the default value (for `skip_serializing_if`).
+#[allow(clippy::trivially_copy_pass_by_ref)] // Required signature for serde's skip_serializing_if
+const fn is_default_backup_keep(val: &u32) -> bool {
+    *val == default_backup_keep()
+}
 
-    #[test]
-    fn test_find_justfile_capitalized() {
-        let dir = TempDir::new().unwrap();
-        std::fs::write(dir.path().join("Justfile"), "").unwrap();
+/// Check if `vcs` is the default value (for `skip_serializing_if`).
+#[allow(clippy::trivially_copy_pass_by_ref)] // Required signature for serde's skip_serializing_if
+fn is_default_vcs(val: &VcsKind) -> bool {
+    *val == VcsKind::default()
+}
 
-        let result = find_justfile(dir.path());
-        assert!(result.is_some());
-        // On case-insensitive filesystems (macOS), the result may be "justfile" even
-        // when we wrote "Justfile", because find_justfile checks "justfile" first.
-        let path = result.unwrap();
-        let filename = path.file_name().unwrap().to_str().unwrap();
-        assert!(filename.eq_ignore_ascii_case("justfile"));
-    }
+/// Check if `idle_source` is the default value (for `skip_serializing_if`).
+#[allow(clippy::trivially_copy_pass_by_ref)] // Required signature for serde's skip_serializing_if
+fn is_default_idle_source(val: &IdleSource) -> bool {
+    *val == IdleSource::default()
+}
 
-    #[test]
-    fn test_find_justfile_not_found() {
-        let dir = TempDir::new().unwrap();
+/// Check if the spelling policy is the default (for `skip_serializing_if`).
+fn is_default_spelling(policy: &SpellingPolicy) -> bool {
+    *policy == SpellingPolicy::default()
+}
 
-        let result = find_justfile(dir.path());
-        assert!(result.is_none());
-    }
+/// Check if the require-task policy is the default (for `skip_serializing_if`).
+fn is_default_require_task(policy: &RequireTaskPolicy) -> bool {
+    *policy == RequireTaskPolicy::default()
+}
 
-    #[test]
-    fn test_has_check_target_simple() {
-        let dir = TempDir::new().unwrap();
-        let path = dir.path().join("justfile");
-        std::fs::write(&path, "check:\n    echo test\n").unwrap();
+/// Check if question delivery is the default (for `skip_serializing_if`).
+fn is_default_question_delivery(delivery: &QuestionDelivery) -> bool {
+    *delivery == QuestionDelivery::default()
+}
 
-        assert!(has_check_target(&path));
-    }
+/// Check if the git status policy is the default (for `skip_serializing_if`).
+fn is_default_git_status_policy(policy: &GitStatusPolicy) -> bool {
+    *policy == GitStatusPolicy::default()
+}
 
-    #[test]
-    fn test_has_check_target_with_deps() {
-        let dir = TempDir::new().unwrap();
-        let path = dir.path().join("justfile");
-        std::fs::write(&path, "check: lint test\n    echo done\n").unwrap();
+/// Check if the commit policy is the default (for `skip_serializing_if`).
+fn is_default_commit_policy(policy: &CommitPolicy) -> bool {
+    *policy == CommitPolicy::default()
+}
 
-        assert!(has_check_target(&path));
-    }
+/// Check if the stop checks policy is the default (for `skip_serializing_if`).
+fn is_default_stop_checks(policy: &StopChecksPolicy) -> bool {
+    *policy == StopChecksPolicy::default()
+}
 
-    #[test]
-    fn test_has_check_target_not_found() {
-        let dir = TempDir::new().unwrap();
-        let path = dir.path().join("justfile");
-        std::fs::write(&path, "build:\n    cargo build\n").unwrap();
+/// Check if the escalation policy is the default (for `skip_serializing_if`).
+fn is_default_escalation(policy: &EscalationPolicy) -> bool {
+    *policy == EscalationPolicy::default()
+}
 
-        assert!(!has_check_target(&path));
-    }
+/// Default value for `question_delivery.sendmail_command`.
+fn default_sendmail_command() -> String {
+    "sendmail -t".to_string()
+}
 
-    #[test]
-    fn test_has_check_target_in_comment() {
-        let dir = TempDir::new().unwrap();
-        let path = dir.path().join("justfile");
-        std::fs::write(&path, "# check: this is a comment\nbuild:\n    cargo build\n").unwrap();
+/// Check if a sendmail command is the default (for `skip_serializing_if`).
+fn is_default_sendmail_command(command: &str) -> bool {
+    command == default_sendmail_command()
+}
 
-        assert!(!has_check_target(&path));
-    }
+/// Check if the binary file policy is the default (for `skip_serializing_if`).
+fn is_default_binary_files(policy: &BinaryFilePolicy) -> bool {
+    *policy == BinaryFilePolicy::default()
+}
 
-    #[test]
-    fn test_ensure_config_creates_new() {
-        use crate::traits::CommandOutput;
+/// Check if the sensitive file policy is the default (for `skip_serializing_if`).
+fn is_default_sensitive_files(policy: &SensitiveFilePolicy) -> bool {
+    *policy == SensitiveFilePolicy::default()
+}
 
-        let dir = TempDir::new().unwrap();
-        std::fs::create_dir(dir.path().join(".git")).unwrap();
+/// Check if the generated file policy is the default (for `skip_serializing_if`).
+fn is_default_generated_files(policy: &GeneratedFilePolicy) -> bool {
+    *policy == GeneratedFilePolicy::default()
+}
 
-        let mut runner = MockCommandRunner::new();
-        runner.set_available("bd");
-        // Mock git remote returning empty (no remote configured)
-        runner.expect(
-            "git",
-            &["remote"],
-            CommandOutput { exit_code: 0, stdout: String::new(), stderr: String::new() },
-        );
+/// Default value for `redact_secrets` - true by default.
+const fn default_redact_secrets() -> bool {
+    true
+}
 
-        let config = ensure_config_in(&runner, dir.path()).unwrap();
+/// Check if `redact_secrets` is the default value (for `skip_serializing_if`).
+#[allow(clippy::trivially_copy_pass_by_ref)] // Required signature for serde's skip_serializing_if
+const fn is_default_redact_secrets(val: &bool) -> bool {
+    *val == default_redact_secrets()
+}
 
-        assert!(config.git_repo);
+/// Default value for `block_escalation_threshold` - 3 identical blocks.
+const fn default_block_escalation_threshold() -> u32 {
+    3
+}
 
-        // Verify file was created
-        assert!(dir.path().join(CONFIG_FILE_PATH).exists());
-        runner.verify();
-    }
+/// Check if the escalation threshold is the default value (for `skip_serializing_if`).
+#[allow(clippy::trivially_copy_pass_by_ref)] // Required signature for serde's skip_serializing_if
+const fn is_default_block_escalation_threshold(val: &u32) -> bool {
+    *val == default_block_escalation_threshold()
+}
 
-    #[test]
-    fn test_ensure_config_loads_existing_preserves_most_fields() {
-        let dir = TempDir::new().unwrap();
+/// Default value for `injection_loop_limit` - 20 blocks.
+const fn default_injection_loop_limit() -> u32 {
+    20
+}
 
-        // Create existing config without .git
-        let existing = ProjectConfig {
-            git_repo: false,
-            check_command: Some("make test".to_string()),
-            code_review_section: None,
-            require_push: false,
-            explain_stops: false,
-            ..Default::default()
-        };
-        existing.save_to(dir.path()).unwrap();
+/// Check if the injection loop limit is the default value (for `skip_serializing_if`).
+#[allow(clippy::trivially_copy_pass_by_ref)] // Required signature for serde's skip_serializing_if
+const fn is_default_injection_loop_limit(val: &u32) -> bool {
+    *val == default_injection_loop_limit()
+}
 
-        let runner = MockCommandRunner::new();
-        let config = ensure_config_in(&runner, dir.path()).unwrap();
+/// Default value for `repeated_output_limit` - 5 messages.
+const fn default_repeated_output_limit() -> u32 {
+    5
+}
 
-        // Should preserve non-git fields from saved config
-        assert!(!config.git_repo); // Still false, no .git exists
-        assert_eq!(config.check_command, Some("make test".to_string()));
-    }
+/// Check if the repeated output limit is the default value (for `skip_serializing_if`).
+#[allow(clippy::trivially_copy_pass_by_ref)] // Required signature for serde's skip_serializing_if
+const fn is_default_repeated_output_limit(val: &u32) -> bool {
+    *val == default_repeated_output_limit()
+}
 
-    #[test]
-    fn test_ensure_config_redetects_git_repo() {
-        use crate::traits::CommandOutput;
+/// Default branch rules: `main` requires push and quality checks,
+/// `wip/*` branches only need WIP commits.
+///
+/// Default rules only relax the project settings, so they never ask for a
+/// push the project has turned off or cannot make.
+fn default_branch_rules() -> Vec<BranchRule> {
+    vec![BranchRule {
+        pattern: "wip/*".to_string(),
+        require_push: Some(false),
+        quality_checks: Some(false),
+        wip_commits: true,
+    }]
+}
 
-        let dir = TempDir::new().unwrap();
+/// Check if branch rules are the default value (for `skip_serializing_if`).
+fn is_default_branch_rules(rules: &Vec<BranchRule>) -> bool {
+    *rules == default_branch_rules()
+}
 
-        // Create existing config without .git
-        let existing = ProjectConfig {
+impl Default for ProjectConfig {
+    fn default() -> Self {
+        Self {
             git_repo: false,
-            check_command: Some("make test".to_string()),
+            check_command: None,
+            check_env_allowlist: None,
+            check_niceness: None,
+            check_output_limit: default_check_output_limit(),
+            check_progress: false,
             code_review_section: None,
-            require_push: false,
+            require_push: true,
             explain_stops: false,
-            ..Default::default()
-        };
-        existing.save_to(dir.path()).unwrap();
-
-        // Now create .git - git_repo should be re-detected
-        std::fs::create_dir(dir.path().join(".git")).unwrap();
-
-        // Mock the git remote check (called when git_repo becomes true)
-        let mut runner = MockCommandRunner::new();
-        runner.expect(
-            "git",
-            &["remote"],
-            CommandOutput { exit_code: 0, stdout: String::new(), stderr: String::new() },
-        );
-
-        let config = ensure_config_in(&runner, dir.path()).unwrap();
-
-        // git_repo should now be true (re-detected)
-        assert!(config.git_repo);
-        // Other fields should be preserved
-        assert_eq!(config.check_command, Some("make test".to_string()));
-        // require_push should be updated (false because no remote)
-        assert!(!config.require_push);
+            auto_work_on_tasks: true,
+            auto_work_idle_minutes: 15,
+            auto_work_decline_limit: default_auto_work_decline_limit(),
+            auto_work_cooldown_minutes: default_auto_work_cooldown_minutes(),
+            auto_work_hours: None,
+            auto_work_max_prompts: 0,
+            auto_work_check_in_every: 0,
+            idle_source: IdleSource::default(),
+            idle_heartbeat_file: None,
+            hook_timeout_seconds: None,
+            failure_mode: FailureMode::default(),
+            backup_interval_hours: default_backup_interval_hours(),
+            backup_keep: default_backup_keep(),
+            marker_ttl_minutes: BTreeMap::new(),
+            debug_logging: false,
+            block_escalation_threshold: default_block_escalation_threshold(),
+            injection_loop_limit: default_injection_loop_limit(),
+            repeated_output_limit: default_repeated_output_limit(),
+            max_session_cost_usd: 0,
+            branch_rules: default_branch_rules(),
+            model_rules: Vec::new(),
+            auto_wip_commit: false,
+            auto_push: false,
+            commit_policy: CommitPolicy::default(),
+            require_pr: false,
+            handoff_summaries: false,
+            commit_split_files: default_commit_split_files(),
+            commit_split_lines: default_commit_split_lines(),
+            binary_files: BinaryFilePolicy::default(),
+            sensitive_files: SensitiveFilePolicy::default(),
+            generated_files: GeneratedFilePolicy::default(),
+            spelling: SpellingPolicy::default(),
+            require_task: RequireTaskPolicy::default(),
+            vcs: VcsKind::default(),
+            git_status: GitStatusPolicy::default(),
+            stop_checks: StopChecksPolicy::default(),
+            message_verbosity: MessageVerbosity::default(),
+            context_budget: default_context_budget(),
+            tag_blocks: false,
+            suggest_answers: false,
+            assume_after_hours: 0,
+            question_delivery: QuestionDelivery::default(),
+            escalation: EscalationPolicy::default(),
+            auto_confirm_commit: true,
+            auto_confirm_push: true,
+            auto_answer_continue: true,
+            user_recency_minutes: default_user_recency_minutes(),
+            api_error_threshold: default_api_error_threshold(),
+            max_files_to_show: default_max_files_to_show(),
+            packages: Vec::new(),
+            emergency_stop_max_per_hour: default_emergency_stop_max_per_hour(),
+            emergency_stop_rejections_before_question:
+                default_emergency_stop_rejections_before_question(),
+            db_path: None,
+            redact_secrets: true,
+            deep_secrets_scan: false,
+            log_filter: None,
+            definition_of_done: Vec::new(),
+            metrics_file: None,
+            profile: None,
+        }
     }
+}
 
-    #[test]
-    fn test_config_path() {
-        let path = ProjectConfig::config_path(Path::new("/foo/bar"));
-        assert_eq!(path, PathBuf::from("/foo/bar/.claude/reliability-config.yaml"));
+impl ProjectConfig {
+    /// Load config from the default location, returning None if not found.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file exists but cannot be parsed.
+    pub fn load() -> Result<Option<Self>> {
+        Self::load_from(Path::new("."))
     }
 
-    #[test]
-    fn test_ensure_config_auto_commits_in_git_repo() {
-        use crate::traits::CommandOutput;
-        use std::process::Command;
+    /// Load config from a specific base directory, reusing the config this
+    /// process last read there if the file has not changed since.
+    ///
+    /// For settings read on every call, such as the metrics file and the
+    /// database path. Returns
+    /// `None` if the file is missing or cannot be parsed.
+    #[must_use]
+    pub fn load_cached(base_dir: &Path) -> Option<Arc<Self>> {
+        let mut config_path = Self::config_path(base_dir);
+        if config_path.is_relative() {
+            config_path = std::env::current_dir().ok()?.join(config_path);
+        }
+        let stamp =
+            std::fs::metadata(&config_path).ok().and_then(|m| Some((m.modified().ok()?, m.len())));
+        let mut loaded = LOADED.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some((cached_stamp, config)) = loaded.get(&config_path) {
+            if *cached_stamp == stamp {
+                return config.clone();
+            }
+        }
+        let config = Self::load_from(base_dir).ok().flatten().map(Arc::new);
+        loaded.insert(config_path, (stamp, config.clone()));
+        drop(loaded);
+        config
+    }
 
-        let dir = TempDir::new().unwrap();
+    /// Load config from a specific base directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file exists but cannot be parsed, or its
+    /// `stop_checks` are invalid.
+    pub fn load_from(base_dir: &Path) -> Result<Option<Self>> {
+        let config_path = base_dir.join(CONFIG_FILE_PATH);
+        if !config_path.exists() {
+            return Ok(None);
+        }
+
+        let content = std::fs::read_to_string(&config_path)?;
+        let config: Self = serde_yaml::from_str(&content)?;
+        config.stop_checks.validate()?;
+        Ok(Some(config))
+    }
+
+    /// Save config to the default location.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    pub fn save(&self) -> Result<()> {
+        self.save_to(Path::new("."))
+    }
+
+    /// Save config to a specific base directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    pub fn save_to(&self, base_dir: &Path) -> Result<()> {
+        let config_path = base_dir.join(CONFIG_FILE_PATH);
+
+        // Ensure .claude directory exists
+        if let Some(parent) = config_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let content = serde_yaml::to_string(self)?;
+        std::fs::write(&config_path, content)?;
+        Ok(())
+    }
+
+    /// Detect project configuration by checking the environment.
+    ///
+    /// This checks:
+    /// - Whether `.git` directory exists (`git_repo`)
+    /// - Whether `just check` is viable (`check_command`)
+    pub fn detect(runner: &dyn CommandRunner) -> Self {
+        Self::detect_in(runner, Path::new("."))
+    }
+
+    /// Detect project configuration in a specific directory.
+    pub fn detect_in(runner: &dyn CommandRunner, base_dir: &Path) -> Self {
+        let git_repo = base_dir.join(".git").exists();
+        let check_command = detect_check_command(runner, base_dir);
+        let code_review_section = find_code_review_section(base_dir);
+
+        // Only require push if there's a remote configured
+        let require_push = git_repo && has_git_remote(runner);
+
+        Self {
+            git_repo,
+            check_command,
+            check_env_allowlist: None,
+            check_niceness: None,
+            check_output_limit: default_check_output_limit(),
+            check_progress: false,
+            code_review_section,
+            require_push,
+            explain_stops: false,
+            auto_work_on_tasks: default_auto_work_on_tasks(),
+            auto_work_idle_minutes: default_auto_work_idle_minutes(),
+            auto_work_decline_limit: default_auto_work_decline_limit(),
+            auto_work_cooldown_minutes: default_auto_work_cooldown_minutes(),
+            auto_work_hours: None,
+            auto_work_max_prompts: 0,
+            auto_work_check_in_every: 0,
+            idle_source: IdleSource::default(),
+            idle_heartbeat_file: None,
+            hook_timeout_seconds: None,
+            failure_mode: FailureMode::default(),
+            backup_interval_hours: default_backup_interval_hours(),
+            backup_keep: default_backup_keep(),
+            marker_ttl_minutes: BTreeMap::new(),
+            debug_logging: false,
+            block_escalation_threshold: default_block_escalation_threshold(),
+            injection_loop_limit: default_injection_loop_limit(),
+            repeated_output_limit: default_repeated_output_limit(),
+            max_session_cost_usd: 0,
+            branch_rules: default_branch_rules(),
+            model_rules: Vec::new(),
+            auto_wip_commit: false,
+            auto_push: false,
+            commit_policy: CommitPolicy::default(),
+            require_pr: false,
+            handoff_summaries: false,
+            commit_split_files: default_commit_split_files(),
+            commit_split_lines: default_commit_split_lines(),
+            binary_files: BinaryFilePolicy::default(),
+            sensitive_files: SensitiveFilePolicy::default(),
+            generated_files: GeneratedFilePolicy::default(),
+            spelling: SpellingPolicy::default(),
+            require_task: RequireTaskPolicy::default(),
+            vcs: VcsKind::default(),
+            git_status: GitStatusPolicy::default(),
+            stop_checks: StopChecksPolicy::default(),
+            message_verbosity: MessageVerbosity::default(),
+            context_budget: default_context_budget(),
+            tag_blocks: false,
+            suggest_answers: false,
+            assume_after_hours: 0,
+            question_delivery: QuestionDelivery::default(),
+            escalation: EscalationPolicy::default(),
+            auto_confirm_commit: true,
+            auto_confirm_push: true,
+            auto_answer_continue: true,
+            user_recency_minutes: default_user_recency_minutes(),
+            api_error_threshold: default_api_error_threshold(),
+            max_files_to_show: default_max_files_to_show(),
+            packages: Vec::new(),
+            emergency_stop_max_per_hour: default_emergency_stop_max_per_hour(),
+            emergency_stop_rejections_before_question:
+                default_emergency_stop_rejections_before_question(),
+            db_path: None,
+            redact_secrets: true,
+            deep_secrets_scan: false,
+            log_filter: None,
+            definition_of_done: Vec::new(),
+            metrics_file: None,
+            profile: None,
+        }
+    }
+
+    /// Get the options to run the quality check command with.
+    #[must_use]
+    pub fn check_run_options(&self) -> RunOptions {
+        RunOptions {
+            env_allowlist: self.check_env_allowlist.clone(),
+            niceness: self.check_niceness,
+            max_output_bytes: Some(self.check_output_limit),
+            ..RunOptions::default()
+        }
+    }
+
+    /// Get the config file path for a base directory.
+    pub fn config_path(base_dir: &Path) -> PathBuf {
+        base_dir.join(CONFIG_FILE_PATH)
+    }
+}
+
+/// Detect an appropriate quality check command for the project.
+///
+/// Returns `Some("just check")` if:
+/// - `just` is installed
+/// - A `justfile` or `Justfile` exists
+/// - The justfile defines a `check:` target
+///
+/// Otherwise returns `None`.
+fn detect_check_command(runner: &dyn CommandRunner, base_dir: &Path) -> Option<String> {
+    // Check if just is installed
+    if !runner.is_available("just") {
+        return None;
+    }
+
+    // Check for justfile
+    let justfile_path = find_justfile(base_dir)?;
+
+    // Check if justfile defines 'check:' target
+    if has_check_target(&justfile_path) {
+        Some("just check".to_string())
+    } else {
+        None
+    }
+}
+
+/// Check if there's a git remote configured.
+///
+/// This is used to determine whether to require pushing before exit.
+/// If there's no remote, there's nowhere to push.
+fn has_git_remote(runner: &dyn CommandRunner) -> bool {
+    // List remotes - if any exist, we have a remote
+    let output = runner.run("git", &["remote"], None);
+    match output {
+        Ok(out) if out.success() => !out.stdout.trim().is_empty(),
+        _ => false,
+    }
+}
+
+/// Find the justfile in the given directory.
+fn find_justfile(base_dir: &Path) -> Option<PathBuf> {
+    for name in ["justfile", "Justfile"] {
+        let path = base_dir.join(name);
+        if path.exists() {
+            return Some(path);
+        }
+    }
+    None
+}
+
+/// Check if a justfile defines the 'check:' target.
+fn has_check_target(justfile_path: &Path) -> bool {
+    let Ok(content) = std::fs::read_to_string(justfile_path) else {
+        return false;
+    };
+
+    // Look for a line starting with 'check:' (possibly with recipe attributes before it)
+    for line in content.lines() {
+        let trimmed = line.trim();
+        // Skip empty lines and comments
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        // Check for 'check:' at the start of a line (recipe definition)
+        if trimmed.starts_with("check:") || trimmed.starts_with("check ") {
+            return true;
+        }
+    }
+    false
+}
+
+/// Check if CLAUDE.md has a "Code Review" section.
+/// Returns the section header if found (e.g., "## Code Review").
+fn find_code_review_section(base_dir: &Path) -> Option<String> {
+    let claude_md_path = base_dir.join(CLAUDE_MD_PATH);
+    let content = std::fs::read_to_string(claude_md_path).ok()?;
+    if content.contains(CODE_REVIEW_HEADER) {
+        Some(CODE_REVIEW_HEADER.to_string())
+    } else {
+        None
+    }
+}
+
+// NOTE: We no longer auto-modify CLAUDE.md due to corruption issues (claude-9i2m).
+// The Code Review section detection still works - if the user has manually added
+// a "## Code Review" section, it will be detected and used. Otherwise, the code
+// review hook uses a default prompt.
+
+/// Ensure config exists, creating it with detected defaults if not.
+///
+/// Returns the config (either loaded or newly created).
+///
+/// # Errors
+///
+/// Returns an error if config cannot be loaded or saved.
+#[cfg(feature = "cli")]
+pub fn ensure_config(runner: &dyn CommandRunner) -> Result<ProjectConfig> {
+    ensure_config_in(runner, Path::new("."))
+}
+
+/// Ensure config exists in a specific directory.
+///
+/// If the config is created and we're in a git repo, it will be automatically
+/// added and committed. Also ensures .gitignore has the required entries.
+/// Detects (but does not modify) CLAUDE.md Code Review section.
+///
+/// # Errors
+///
+/// Returns an error if config cannot be loaded or saved.
+pub fn ensure_config_in(runner: &dyn CommandRunner, base_dir: &Path) -> Result<ProjectConfig> {
+    // Try to load existing config
+    let mut config = if let Some(mut config) = ProjectConfig::load_from(base_dir)? {
+        // Always re-detect git_repo since .git could have been added/removed
+        // This is critical for the stop hook to correctly check for uncommitted changes
+        let git_repo = base_dir.join(".git").exists();
+        if config.git_repo != git_repo {
+            config.git_repo = git_repo;
+            // Update require_push based on new git_repo status
+            config.require_push = git_repo && has_git_remote(runner);
+            config.save_to(base_dir)?;
+        }
+        config
+    } else {
+        // Detect and create new config
+        let config = ProjectConfig::detect_in(runner, base_dir);
+        config.save_to(base_dir)?;
+
+        // Auto-commit config if in a git repo
+        if config.git_repo {
+            auto_commit_config(base_dir);
+        }
+
+        config
+    };
+
+    // Always ensure gitignore is up to date (if in a git repo)
+    if config.git_repo {
+        if let Ok(added) = ensure_gitignore(base_dir) {
+            if !added.is_empty() {
+                auto_commit_gitignore(base_dir);
+            }
+        }
+    }
+
+    // Ensure CLAUDE.md has binary instructions section
+    if let Ok(modified) = ensure_claude_md_binary_section(base_dir) {
+        if modified && config.git_repo {
+            auto_commit_claude_md(base_dir);
+        }
+    }
+
+    // Detect (but don't modify) Code Review section in CLAUDE.md
+    // We no longer auto-add sections due to corruption issues (claude-9i2m)
+    if config.code_review_section.is_none() {
+        if let Some(section_title) = find_code_review_section(base_dir) {
+            config.code_review_section = Some(section_title);
+            config.save_to(base_dir)?;
+            if config.git_repo {
+                auto_commit_config(base_dir);
+            }
+        }
+    }
+
+    Ok(config)
+}
+
+/// Ensure config exists in a specific directory and apply a profile to it.
+///
+/// The profile's settings replace the current values of the keys it covers;
+/// all other keys are kept. Like a newly created config, the result is
+/// committed when in a git repo.
+///
+/// # Errors
+///
+/// Returns an error if config cannot be loaded or saved.
+pub fn apply_profile_in(
+    runner: &dyn CommandRunner,
+    base_dir: &Path,
+    profile: Profile,
+) -> Result<ProjectConfig> {
+    let mut config = ensure_config_in(runner, base_dir)?;
+    profile.apply(&mut config);
+    config.save_to(base_dir)?;
+    if config.git_repo {
+        auto_commit_config(base_dir);
+    }
+    Ok(config)
+}
+
+/// Check if a path is gitignored in the given directory.
+fn is_gitignored(base_dir: &Path, path: &str) -> bool {
+    use std::process::Command;
+    let result =
+        Command::new("git").args(["check-ignore", "-q", path]).current_dir(base_dir).output();
+    matches!(result, Ok(output) if output.status.success())
+}
+
+/// Automatically add and commit the config file.
+/// Silently ignores any errors (best effort).
+fn auto_commit_config(base_dir: &Path) {
+    use std::process::Command;
+
+    let config_path = ProjectConfig::config_path(base_dir);
+    let config_path_str = config_path.to_string_lossy();
+
+    // Skip if the file is gitignored
+    if is_gitignored(base_dir, &config_path_str) {
+        return;
+    }
+
+    // Try to add the file
+    let add_result =
+        Command::new("git").args(["add", &config_path_str]).current_dir(base_dir).output();
+
+    if add_result.is_err() || !add_result.unwrap().status.success() {
+        return;
+    }
+
+    // Check if there's anything to commit (file might already be committed)
+    let diff_result = Command::new("git")
+        .args(["diff", "--cached", "--quiet", "--", &config_path_str])
+        .current_dir(base_dir)
+        .output();
+
+    if let Ok(output) = diff_result {
+        if output.status.success() {
+            // No changes staged, nothing to commit
+            return;
+        }
+    }
+
+    // Commit the config file
+    let _ = Command::new("git")
+        .args([
+            "commit",
+            "-m",
+            "Add claude-reliability config\n\nAuto-generated by claude-reliability plugin.",
+        ])
+        .current_dir(base_dir)
+        .output();
+}
+
+/// Kind of project, recognised by its manifest, whose build artifacts are gitignored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProjectType {
+    /// A Cargo project.
+    Rust,
+    /// An npm, yarn or pnpm project.
+    Node,
+    /// A Python project.
+    Python,
+}
+
+impl ProjectType {
+    /// Every project type, in the order their entries appear in the gitignore.
+    const ALL: [Self; 3] = [Self::Rust, Self::Node, Self::Python];
+
+    /// Files whose presence in the project root marks a project of this type.
+    const fn manifests(self) -> &'static [&'static str] {
+        match self {
+            Self::Rust => &["Cargo.toml"],
+            Self::Node => &["package.json"],
+            Self::Python => &["pyproject.toml", "setup.py", "setup.cfg", "requirements.txt"],
+        }
+    }
+
+    /// Gitignore patterns for the project type's build artifacts.
+    #[must_use]
+    pub const fn gitignore_entries(self) -> &'static [&'static str] {
+        match self {
+            Self::Rust => &["target/"],
+            Self::Node => &["node_modules/"],
+            Self::Python => {
+                &["__pycache__/", "*.py[cod]", ".pytest_cache/", ".venv/", "*.egg-info/"]
+            }
+        }
+    }
+
+    /// Detect the types of the project in `base_dir`.
+    #[must_use]
+    pub fn detect(base_dir: &Path) -> Vec<Self> {
+        Self::ALL
+            .into_iter()
+            .filter(|project_type| {
+                project_type.manifests().iter().any(|manifest| base_dir.join(manifest).exists())
+            })
+            .collect()
+    }
+}
+
+/// Ensure the gitignore contains the required entries for claude-reliability.
+///
+/// This function:
+/// 1. Reads the existing .gitignore (or creates one if it doesn't exist)
+/// 2. Finds or creates a managed block between `# claude-reliability managed`
+///    and `# end claude-reliability managed`
+/// 3. Ensures the block holds the required entries, plus the build artifacts
+///    of each [`ProjectType`] detected in `base_dir`
+/// 4. Preserves all other content in the file
+///
+/// Entries the file already ignores outside the block are not added again.
+/// Returns the lines added to the gitignore, empty if it was already correct.
+///
+/// # Errors
+///
+/// Returns an error if the .gitignore file cannot be read or written.
+pub fn ensure_gitignore(base_dir: &Path) -> std::io::Result<Vec<String>> {
+    let gitignore_path = base_dir.join(".gitignore");
+
+    // Read existing content or start fresh
+    let existing_content = if gitignore_path.exists() {
+        std::fs::read_to_string(&gitignore_path)?
+    } else {
+        String::new()
+    };
+
+    let mut entries = GITIGNORE_ENTRIES.to_vec();
+    for project_type in ProjectType::detect(base_dir) {
+        entries.extend(project_type.gitignore_entries());
+    }
+    let new_content = update_gitignore_content(&existing_content, &entries);
+
+    if new_content == existing_content {
+        return Ok(Vec::new());
+    }
+
+    std::fs::write(&gitignore_path, &new_content)?;
+    Ok(added_lines(&existing_content, &new_content))
+}
+
+/// Update gitignore content so that its managed block holds `entries`.
+///
+/// This is additive-only: it never removes existing entries from the managed block.
+/// If a user adds entries within the managed block, they are preserved.
+/// Entries already present, in the block or elsewhere in the file, are not duplicated.
+/// A block written before the end marker existed ends at the next comment line,
+/// and gets the end marker added.
+#[allow(clippy::option_if_let_else)]
+fn update_gitignore_content(existing: &str, entries: &[&str]) -> String {
+    // Check if the managed section already exists
+    if let Some(start_idx) = existing.find(GITIGNORE_SECTION_HEADER) {
+        let content_start = start_idx + GITIGNORE_SECTION_HEADER.len();
+        let after_header = &existing[content_start..];
+        let end_marker = find_section_end_marker(after_header);
+        let section_end = end_marker.unwrap_or_else(|| find_section_end(after_header));
+
+        // Extract existing entries in the managed section, and the rest of the file
+        let section_content = &after_header[..section_end];
+        let existing_entries: Vec<&str> =
+            section_content.lines().map(str::trim).filter(|l| !l.is_empty()).collect();
+        let outside = format!("{}{}", &existing[..start_idx], &after_header[section_end..]);
+
+        let missing = missing_gitignore_entries(entries, &existing_entries, &outside);
+        if missing.is_empty() && end_marker.is_some() {
+            // All entries present, no modification needed
+            return existing.to_string();
+        }
+
+        // Insert missing entries after the last line of the managed block's content,
+        // so that the file only gains lines
+        let insert_pos = content_start + section_content.trim_end().len();
+        let mut result = existing[..insert_pos].to_string();
+        result.push('\n');
+        for entry in &missing {
+            result.push_str(entry);
+            result.push('\n');
+        }
+        if end_marker.is_none() {
+            result.push_str(GITIGNORE_SECTION_END);
+            result.push('\n');
+        }
+        let after = &existing[insert_pos..];
+        result.push_str(
+            after.strip_prefix("\r\n").or_else(|| after.strip_prefix('\n')).unwrap_or(after),
+        );
+        result
+    } else {
+        // No managed section exists, append it
+        let missing = missing_gitignore_entries(entries, &[], existing);
+        let managed_section = build_managed_section(&missing);
+        let mut result = existing.to_string();
+        if !result.is_empty() && !result.ends_with('\n') {
+            result.push('\n');
+        }
+        if !result.is_empty() {
+            result.push('\n');
+        }
+        result.push_str(&managed_section);
+        result
+    }
+}
+
+/// Entries that are neither in the managed block nor ignored elsewhere in the file.
+fn missing_gitignore_entries<'a>(
+    entries: &[&'a str],
+    section_entries: &[&str],
+    outside: &str,
+) -> Vec<&'a str> {
+    // `/target`, `target` and `target/` all ignore the build directory
+    let normalize = |pattern: &str| pattern.trim().trim_matches('/').to_string();
+    let elsewhere: HashSet<String> = outside.lines().map(normalize).collect();
+    let mut missing: Vec<&str> = Vec::new();
+    for entry in entries {
+        if !section_entries.contains(&entry.trim())
+            && !elsewhere.contains(&normalize(entry))
+            && !missing.contains(entry)
+        {
+            missing.push(entry);
+        }
+    }
+    missing
+}
+
+/// Build the managed section content.
+fn build_managed_section(entries: &[&str]) -> String {
+    let mut section = String::from(GITIGNORE_SECTION_HEADER);
+    section.push('\n');
+    for entry in entries {
+        section.push_str(entry);
+        section.push('\n');
+    }
+    section.push_str(GITIGNORE_SECTION_END);
+    section.push('\n');
+    section
+}
+
+/// Find the line that ends the managed block, if it has one.
+fn find_section_end_marker(content: &str) -> Option<usize> {
+    let mut pos = 0;
+    for line in content.split_inclusive('\n') {
+        if line.trim() == GITIGNORE_SECTION_END {
+            return Some(pos);
+        }
+        pos += line.len();
+    }
+    None
+}
+
+/// Lines of `new` that are not in `old`, when `new` only adds lines to `old`.
+fn added_lines(old: &str, new: &str) -> Vec<String> {
+    let mut old_lines = old.lines().peekable();
+    let mut added = Vec::new();
+    for line in new.lines() {
+        if old_lines.peek() == Some(&line) {
+            old_lines.next();
+        } else {
+            added.push(line.to_string());
+        }
+    }
+    added
+}
+
+/// Find the end of the managed section (next comment line or end of string).
+fn find_section_end(content: &str) -> usize {
+    let mut pos = 0;
+    for line in content.lines() {
+        // Skip the newline after the header
+        pos += line.len() + 1; // +1 for newline
+
+        // If we hit another comment line (not empty), that's the end of our section
+        let trimmed = line.trim();
+        if trimmed.starts_with('#') && !trimmed.is_empty() && pos > 1 {
+            // Go back to before this line
+            return pos - line.len() - 1;
+        }
+    }
+    // Reached end of file
+    content.len()
+}
+
+/// Automatically add and commit the gitignore changes.
+/// Silently ignores any errors (best effort).
+fn auto_commit_gitignore(base_dir: &Path) {
+    use std::process::Command;
+
+    let gitignore_path = base_dir.join(".gitignore");
+    let gitignore_str = gitignore_path.to_string_lossy();
+
+    // Skip if the file is gitignored
+    if is_gitignored(base_dir, &gitignore_str) {
+        return;
+    }
+
+    // Try to add the file
+    let add_result =
+        Command::new("git").args(["add", &gitignore_str]).current_dir(base_dir).output();
+
+    if add_result.is_err() || !add_result.unwrap().status.success() {
+        return;
+    }
+
+    // Check if there's anything to commit
+    let diff_result = Command::new("git")
+        .args(["diff", "--cached", "--quiet", "--", &gitignore_str])
+        .current_dir(base_dir)
+        .output();
+
+    if let Ok(output) = diff_result {
+        if output.status.success() {
+            return;
+        }
+    }
+
+    // Commit the gitignore
+    let _ = Command::new("git")
+        .args(["commit", "-m", "Update .gitignore for claude-reliability\n\nAuto-generated by claude-reliability plugin."])
+        .current_dir(base_dir)
+        .output();
+}
+
+/// Ensure CLAUDE.md contains the managed binary instructions section.
+///
+/// This function:
+/// 1. Reads the existing CLAUDE.md (returns `Ok(false)` if it doesn't exist)
+/// 2. Finds the managed section by start delimiter
+/// 3. If present, replaces content between delimiters with current template
+/// 4. If absent, appends the section at the end of the file
+/// 5. Returns `Ok(true)` if the file was modified
+///
+/// # Errors
+///
+/// Returns an error if the CLAUDE.md file cannot be read or written.
+pub fn ensure_claude_md_binary_section(base_dir: &Path) -> std::io::Result<bool> {
+    let claude_md_path = base_dir.join(CLAUDE_MD_PATH);
+
+    if !claude_md_path.exists() {
+        return Ok(false);
+    }
+
+    let existing_content = std::fs::read_to_string(&claude_md_path)?;
+    let new_content = update_claude_md_content(&existing_content);
+
+    if new_content == existing_content {
+        return Ok(false);
+    }
+
+    std::fs::write(&claude_md_path, &new_content)?;
+    Ok(true)
+}
+
+/// Update CLAUDE.md content to include or refresh the managed binary instructions section.
+///
+/// This is a pure function for easy testing.
+fn update_claude_md_content(existing: &str) -> String {
+    let section = build_claude_md_section();
+
+    if let Some(start_idx) = existing.find(CLAUDE_MD_SECTION_START) {
+        // Managed section exists - replace it
+        if let Some(end_offset) = existing[start_idx..].find(CLAUDE_MD_SECTION_END) {
+            let end_idx = start_idx + end_offset + CLAUDE_MD_SECTION_END.len();
+            let mut result = existing[..start_idx].to_string();
+            result.push_str(&section);
+            result.push_str(&existing[end_idx..]);
+            return result;
+        }
+        // Start marker found but no end marker - append end after content
+        // This handles a corrupted section gracefully
+    }
+
+    // No managed section exists, append it
+    let mut result = existing.to_string();
+    if !result.is_empty() && !result.ends_with('\n') {
+        result.push('\n');
+    }
+    if !result.is_empty() {
+        result.push('\n');
+    }
+    result.push_str(&section);
+    result.push('\n');
+    result
+}
+
+/// Build the full managed CLAUDE.md section with delimiters.
+fn build_claude_md_section() -> String {
+    format!(
+        "{CLAUDE_MD_SECTION_START}\n{CLAUDE_MD_BINARY_SECTION_CONTENT}\n{CLAUDE_MD_SECTION_END}"
+    )
+}
+
+/// Automatically add and commit CLAUDE.md changes.
+/// Silently ignores any errors (best effort).
+fn auto_commit_claude_md(base_dir: &Path) {
+    use std::process::Command;
+
+    let claude_md_path = base_dir.join(CLAUDE_MD_PATH);
+    let claude_md_str = claude_md_path.to_string_lossy();
+
+    // Skip if the file is gitignored
+    if is_gitignored(base_dir, &claude_md_str) {
+        return;
+    }
+
+    // Try to add the file
+    let add_result =
+        Command::new("git").args(["add", &claude_md_str]).current_dir(base_dir).output();
+
+    if add_result.is_err() || !add_result.unwrap().status.success() {
+        return;
+    }
+
+    // Check if there's anything to commit
+    let diff_result = Command::new("git")
+        .args(["diff", "--cached", "--quiet", "--", &claude_md_str])
+        .current_dir(base_dir)
+        .output();
+
+    if let Ok(output) = diff_result {
+        if output.status.success() {
+            return;
+        }
+    }
+
+    // Commit CLAUDE.md
+    let _ = Command::new("git")
+        .args([
+            "commit",
+            "-m",
+            "Update CLAUDE.md binary instructions\n\nAuto-generated by claude-reliability plugin.",
+        ])
+        .current_dir(base_dir)
+        .output();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockCommandRunner;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_project_config_default() {
+        let config = ProjectConfig::default();
+        assert!(!config.git_repo);
+        assert!(config.check_command.is_none());
+    }
+
+    #[test]
+    fn test_project_config_load_not_found() {
+        let dir = TempDir::new().unwrap();
+        let result = ProjectConfig::load_from(dir.path()).unwrap();
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn test_project_config_save_and_load() {
+        let dir = TempDir::new().unwrap();
+
+        let config = ProjectConfig {
+            git_repo: true,
+            check_command: Some("just check".to_string()),
+            code_review_section: None,
+            require_push: true,
+            explain_stops: false,
+            ..Default::default()
+        };
+
+        config.save_to(dir.path()).unwrap();
+
+        let loaded = ProjectConfig::load_from(dir.path()).unwrap().unwrap();
+        assert_eq!(config, loaded);
+    }
+
+    #[test]
+    fn test_project_config_load_cached_follows_changes() {
+        let dir = TempDir::new().unwrap();
+        assert!(ProjectConfig::load_cached(dir.path()).is_none());
+
+        let config =
+            ProjectConfig { metrics_file: Some("m.prom".to_string()), ..Default::default() };
+        config.save_to(dir.path()).unwrap();
+        let first = ProjectConfig::load_cached(dir.path()).unwrap();
+        assert_eq!(*first, config);
+        assert!(Arc::ptr_eq(&first, &ProjectConfig::load_cached(dir.path()).unwrap()));
+
+        let config =
+            ProjectConfig { metrics_file: Some("other.prom".to_string()), ..Default::default() };
+        config.save_to(dir.path()).unwrap();
+        assert_eq!(*ProjectConfig::load_cached(dir.path()).unwrap(), config);
+    }
+
+    #[test]
+    fn test_project_config_yaml_format() {
+        let dir = TempDir::new().unwrap();
+
+        let config = ProjectConfig {
+            git_repo: true,
+            check_command: Some("make test".to_string()),
+            code_review_section: None,
+            require_push: true,
+            explain_stops: false,
+            ..Default::default()
+        };
+
+        config.save_to(dir.path()).unwrap();
+
+        let content = std::fs::read_to_string(dir.path().join(CONFIG_FILE_PATH)).unwrap();
+        assert!(content.contains("git_repo: true"));
+        assert!(content.contains("check_command: make test"));
+        assert!(content.contains("require_push: true"));
+    }
+
+    #[test]
+    fn test_project_config_detect_no_git() {
+        let dir = TempDir::new().unwrap();
+        let runner = MockCommandRunner::new();
+
+        let config = ProjectConfig::detect_in(&runner, dir.path());
+        assert!(!config.git_repo);
+    }
+
+    #[test]
+    fn test_project_config_detect_with_git() {
+        use crate::traits::CommandOutput;
+
+        let dir = TempDir::new().unwrap();
+        std::fs::create_dir(dir.path().join(".git")).unwrap();
+        let mut runner = MockCommandRunner::new();
+        // Mock git remote returning empty (no remote configured)
+        runner.expect(
+            "git",
+            &["remote"],
+            CommandOutput { exit_code: 0, stdout: String::new(), stderr: String::new() },
+        );
+
+        let config = ProjectConfig::detect_in(&runner, dir.path());
+        assert!(config.git_repo);
+        // No remote, so require_push should be false
+        assert!(!config.require_push);
+        runner.verify();
+    }
+
+    #[test]
+    fn test_project_config_detect_check_command() {
+        let dir = TempDir::new().unwrap();
+
+        // Create justfile with check target
+        std::fs::write(dir.path().join("justfile"), "check:\n    cargo test\n").unwrap();
+
+        let mut runner = MockCommandRunner::new();
+        runner.set_available("just");
+
+        let config = ProjectConfig::detect_in(&runner, dir.path());
+        assert_eq!(config.check_command, Some("just check".to_string()));
+    }
+
+    #[test]
+    fn test_project_config_detect_no_check_command_no_just() {
+        let dir = TempDir::new().unwrap();
+
+        // Create justfile but just is not available
+        std::fs::write(dir.path().join("justfile"), "check:\n    cargo test\n").unwrap();
+
+        let runner = MockCommandRunner::new();
+
+        let config = ProjectConfig::detect_in(&runner, dir.path());
+        assert!(config.check_command.is_none());
+    }
+
+    #[test]
+    fn test_project_config_detect_no_check_command_no_justfile() {
+        let dir = TempDir::new().unwrap();
+
+        let mut runner = MockCommandRunner::new();
+        runner.set_available("just");
+
+        let config = ProjectConfig::detect_in(&runner, dir.path());
+        assert!(config.check_command.is_none());
+    }
+
+    #[test]
+    fn test_project_config_detect_no_check_target() {
+        let dir = TempDir::new().unwrap();
+
+        // Create justfile without check target
+        std::fs::write(dir.path().join("justfile"), "build:\n    cargo build\n").unwrap();
+
+        let mut runner = MockCommandRunner::new();
+        runner.set_available("just");
+
+        let config = ProjectConfig::detect_in(&runner, dir.path());
+        assert!(config.check_command.is_none());
+    }
+
+    #[test]
+    fn test_find_justfile_lowercase() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("justfile"), "").unwrap();
+
+        let result = find_justfile(dir.path());
+        assert!(result.is_some());
+        assert!(result.unwrap().ends_with("justfile"));
+    }
+
+    #[test]
+    fn test_find_justfile_capitalized() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("Justfile"), "").unwrap();
+
+        let result = find_justfile(dir.path());
+        assert!(result.is_some());
+        // On case-insensitive filesystems (macOS), the result may be "justfile" even
+        // when we wrote "Justfile", because find_justfile checks "justfile" first.
+        let path = result.unwrap();
+        let filename = path.file_name().unwrap().to_str().unwrap();
+        assert!(filename.eq_ignore_ascii_case("justfile"));
+    }
+
+    #[test]
+    fn test_find_justfile_not_found() {
+        let dir = TempDir::new().unwrap();
+
+        let result = find_justfile(dir.path());
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn test_has_check_target_simple() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("justfile");
+        std::fs::write(&path, "check:\n    echo test\n").unwrap();
+
+        assert!(has_check_target(&path));
+    }
+
+    #[test]
+    fn test_has_check_target_with_deps() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("justfile");
+        std::fs::write(&path, "check: lint test\n    echo done\n").unwrap();
+
+        assert!(has_check_target(&path));
+    }
+
+    #[test]
+    fn test_has_check_target_not_found() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("justfile");
+        std::fs::write(&path, "build:\n    cargo build\n").unwrap();
+
+        assert!(!has_check_target(&path));
+    }
+
+    #[test]
+    fn test_has_check_target_in_comment() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("justfile");
+        std::fs::write(&path, "# check: this is a comment\nbuild:\n    cargo build\n").unwrap();
+
+        assert!(!has_check_target(&path));
+    }
+
+    #[test]
+    fn test_ensure_config_creates_new() {
+        use crate::traits::CommandOutput;
+
+        let dir = TempDir::new().unwrap();
+        std::fs::create_dir(dir.path().join(".git")).unwrap();
+
+        let mut runner = MockCommandRunner::new();
+        runner.set_available("bd");
+        // Mock git remote returning empty (no remote configured)
+        runner.expect(
+            "git",
+            &["remote"],
+            CommandOutput { exit_code: 0, stdout: String::new(), stderr: String::new() },
+        );
+
+        let config = ensure_config_in(&runner, dir.path()).unwrap();
+
+        assert!(config.git_repo);
+
+        // Verify file was created
+        assert!(dir.path().join(CONFIG_FILE_PATH).exists());
+        runner.verify();
+    }
+
+    #[test]
+    fn test_ensure_config_loads_existing_preserves_most_fields() {
+        let dir = TempDir::new().unwrap();
+
+        // Create existing config without .git
+        let existing = ProjectConfig {
+            git_repo: false,
+            check_command: Some("make test".to_string()),
+            code_review_section: None,
+            require_push: false,
+            explain_stops: false,
+            ..Default::default()
+        };
+        existing.save_to(dir.path()).unwrap();
+
+        let runner = MockCommandRunner::new();
+        let config = ensure_config_in(&runner, dir.path()).unwrap();
+
+        // Should preserve non-git fields from saved config
+        assert!(!config.git_repo); // Still false, no .git exists
+        assert_eq!(config.check_command, Some("make test".to_string()));
+    }
+
+    #[test]
+    fn test_ensure_config_redetects_git_repo() {
+        use crate::traits::CommandOutput;
+
+        let dir = TempDir::new().unwrap();
+
+        // Create existing config without .git
+        let existing = ProjectConfig {
+            git_repo: false,
+            check_command: Some("make test".to_string()),
+            code_review_section: None,
+            require_push: false,
+            explain_stops: false,
+            ..Default::default()
+        };
+        existing.save_to(dir.path()).unwrap();
+
+        // Now create .git - git_repo should be re-detected
+        std::fs::create_dir(dir.path().join(".git")).unwrap();
+
+        // Mock the git remote check (called when git_repo becomes true)
+        let mut runner = MockCommandRunner::new();
+        runner.expect(
+            "git",
+            &["remote"],
+            CommandOutput { exit_code: 0, stdout: String::new(), stderr: String::new() },
+        );
+
+        let config = ensure_config_in(&runner, dir.path()).unwrap();
+
+        // git_repo should now be true (re-detected)
+        assert!(config.git_repo);
+        // Other fields should be preserved
+        assert_eq!(config.check_command, Some("make test".to_string()));
+        // require_push should be updated (false because no remote)
+        assert!(!config.require_push);
+    }
+
+    #[test]
+    fn test_config_path() {
+        let path = ProjectConfig::config_path(Path::new("/foo/bar"));
+        assert_eq!(path, PathBuf::from("/foo/bar/.claude/reliability-config.yaml"));
+    }
+
+    #[test]
+    fn test_ensure_config_auto_commits_in_git_repo() {
+        use crate::traits::CommandOutput;
+        use std::process::Command;
+
+        let dir = TempDir::new().unwrap();
         let base = dir.path();
 
-        // Initialize a real git repo
-        Command::new("git").args(["init"]).current_dir(base).output().unwrap();
-        Command::new("git")
-            .args(["config", "user.email", "test@test.com"])
-            .current_dir(base)
-            .output()
-            .unwrap();
-        Command::new("git")
-            .args(["config", "user.name", "Test"])
-            .current_dir(base)
-            .output()
-            .unwrap();
+        // Initialize a real git repo
+        Command::new("git").args(["init"]).current_dir(base).output().unwrap();
+        Command::new("git")
+            .args(["config", "user.email", "test@test.com"])
+            .current_dir(base)
+            .output()
+            .unwrap();
+        Command::new("git")
+            .args(["config", "user.name", "Test"])
+            .current_dir(base)
+            .output()
+            .unwrap();
+
+        // Create initial commit
+        std::fs::write(base.join("README.md"), "test").unwrap();
+        Command::new("git").args(["add", "."]).current_dir(base).output().unwrap();
+        Command::new("git").args(["commit", "-m", "initial"]).current_dir(base).output().unwrap();
+
+        let mut runner = MockCommandRunner::new();
+        // Mock git remote returning empty (no remote configured in fresh repo)
+        runner.expect(
+            "git",
+            &["remote"],
+            CommandOutput { exit_code: 0, stdout: String::new(), stderr: String::new() },
+        );
+
+        // This should create the config AND auto-commit it
+        let config = ensure_config_in(&runner, base).unwrap();
+        assert!(config.git_repo);
+
+        // Verify the config file exists
+        assert!(base.join(CONFIG_FILE_PATH).exists());
+
+        // Verify it was committed (git status should show clean working tree)
+        let status =
+            Command::new("git").args(["status", "--porcelain"]).current_dir(base).output().unwrap();
+        let status_output = String::from_utf8_lossy(&status.stdout);
+        assert!(
+            status_output.trim().is_empty(),
+            "Expected clean working tree, got: {status_output}"
+        );
+
+        // Verify commit messages (there may be two: config and gitignore)
+        let log = Command::new("git")
+            .args(["log", "--oneline", "-2"])
+            .current_dir(base)
+            .output()
+            .unwrap();
+        let log_output = String::from_utf8_lossy(&log.stdout);
+        assert!(
+            log_output.contains("claude-reliability"),
+            "Expected commit messages to contain 'claude-reliability', got: {log_output}"
+        );
+        runner.verify();
+    }
+
+    #[test]
+    fn test_ensure_gitignore_creates_new() {
+        let dir = TempDir::new().unwrap();
+
+        let modified = ensure_gitignore(dir.path()).unwrap();
+        assert!(!modified.is_empty());
+
+        let content = std::fs::read_to_string(dir.path().join(".gitignore")).unwrap();
+        assert!(content.contains(GITIGNORE_SECTION_HEADER));
+        assert!(content.contains(".claude/bin/"));
+        assert!(content.contains(".claude/*.local.md"));
+    }
+
+    #[test]
+    fn test_ensure_gitignore_appends_to_existing() {
+        let dir = TempDir::new().unwrap();
+
+        // Create existing gitignore
+        std::fs::write(dir.path().join(".gitignore"), "node_modules/\ntarget/\n").unwrap();
+
+        let modified = ensure_gitignore(dir.path()).unwrap();
+        assert!(!modified.is_empty());
+
+        let content = std::fs::read_to_string(dir.path().join(".gitignore")).unwrap();
+        // Original content preserved
+        assert!(content.contains("node_modules/"));
+        assert!(content.contains("target/"));
+        // New section added
+        assert!(content.contains(GITIGNORE_SECTION_HEADER));
+        assert!(content.contains(".claude/bin/"));
+    }
+
+    #[test]
+    fn test_ensure_gitignore_updates_existing_section() {
+        let dir = TempDir::new().unwrap();
+
+        // Create gitignore with old managed section
+        let old_content = "node_modules/\n\n# claude-reliability managed\n.claude/old-entry/\n\n# Other section\nfoo/\n";
+        std::fs::write(dir.path().join(".gitignore"), old_content).unwrap();
 
-        // Create initial commit
-        std::fs::write(base.join("README.md"), "test").unwrap();
-        Command::new("git").args(["add", "."]).current_dir(base).output().unwrap();
-        Command::new("git").args(["commit", "-m", "initial"]).current_dir(base).output().unwrap();
+        let modified = ensure_gitignore(dir.path()).unwrap();
+        assert!(!modified.is_empty());
 
-        let mut runner = MockCommandRunner::new();
-        // Mock git remote returning empty (no remote configured in fresh repo)
-        runner.expect(
-            "git",
-            &["remote"],
-            CommandOutput { exit_code: 0, stdout: String::new(), stderr: String::new() },
-        );
+        let content = std::fs::read_to_string(dir.path().join(".gitignore")).unwrap();
+        // Original content preserved
+        assert!(content.contains("node_modules/"));
+        // Other section preserved
+        assert!(content.contains("# Other section"));
+        assert!(content.contains("foo/"));
+        // Old entry PRESERVED (additive-only), new entries also present
+        assert!(content.contains(".claude/old-entry/"));
+        assert!(content.contains(".claude/bin/"));
+    }
+
+    #[test]
+    fn test_ensure_gitignore_no_change_when_up_to_date() {
+        let dir = TempDir::new().unwrap();
+
+        // First call creates
+        ensure_gitignore(dir.path()).unwrap();
+
+        // Second call should not modify
+        let modified = ensure_gitignore(dir.path()).unwrap();
+        assert!(modified.is_empty());
+    }
+
+    #[test]
+    fn test_update_gitignore_content_empty() {
+        let result = update_gitignore_content("", GITIGNORE_ENTRIES);
+        assert!(result.contains(GITIGNORE_SECTION_HEADER));
+        assert!(result.contains(".claude/bin/"));
+    }
+
+    #[test]
+    fn test_update_gitignore_content_preserves_existing() {
+        let existing = "node_modules/\n*.log\n";
+        let result = update_gitignore_content(existing, GITIGNORE_ENTRIES);
+
+        assert!(result.contains("node_modules/"));
+        assert!(result.contains("*.log"));
+        assert!(result.contains(GITIGNORE_SECTION_HEADER));
+    }
+
+    #[test]
+    fn test_build_managed_section() {
+        let section = build_managed_section(GITIGNORE_ENTRIES);
+        assert!(section.starts_with(GITIGNORE_SECTION_HEADER));
+        for entry in GITIGNORE_ENTRIES {
+            assert!(section.contains(entry));
+        }
+    }
+
+    // Pedantic gitignore preservation tests
+
+    #[test]
+    fn test_gitignore_preserves_whitespace_only_file() {
+        let existing = "   \n\n   \n";
+        let result = update_gitignore_content(existing, GITIGNORE_ENTRIES);
+        // Should add our section after the whitespace
+        assert!(result.contains(GITIGNORE_SECTION_HEADER));
+        assert!(result.contains(".claude/bin/"));
+    }
+
+    #[test]
+    fn test_gitignore_preserves_no_trailing_newline() {
+        let existing = "node_modules/";
+        let result = update_gitignore_content(existing, GITIGNORE_ENTRIES);
+        // Should still have node_modules
+        assert!(result.contains("node_modules/"));
+        // And our section
+        assert!(result.contains(GITIGNORE_SECTION_HEADER));
+    }
+
+    #[test]
+    fn test_gitignore_preserves_trailing_whitespace_on_lines() {
+        let existing = "node_modules/   \ntarget/  \n";
+        let result = update_gitignore_content(existing, GITIGNORE_ENTRIES);
+        // Should preserve the original lines exactly (including trailing whitespace)
+        assert!(result.contains("node_modules/   \n") || result.contains("node_modules/"));
+        assert!(result.contains("target/"));
+    }
+
+    #[test]
+    fn test_gitignore_preserves_blank_lines_between_sections() {
+        let existing = "# Section 1\nfoo/\n\n\n# Section 2\nbar/\n";
+        let result = update_gitignore_content(existing, GITIGNORE_ENTRIES);
+        // Both sections preserved
+        assert!(result.contains("# Section 1"));
+        assert!(result.contains("foo/"));
+        assert!(result.contains("# Section 2"));
+        assert!(result.contains("bar/"));
+    }
+
+    #[test]
+    fn test_gitignore_handles_section_at_beginning() {
+        let existing = "# claude-reliability managed\n.claude/old/\n\n# Other stuff\nfoo/\n";
+        let result = update_gitignore_content(existing, GITIGNORE_ENTRIES);
+        // Our section should have new entries appended
+        assert!(result.contains(".claude/bin/"));
+        // Old entry preserved (additive-only)
+        assert!(result.contains(".claude/old/"));
+        // Other section preserved
+        assert!(result.contains("# Other stuff"));
+        assert!(result.contains("foo/"));
+    }
+
+    #[test]
+    fn test_gitignore_handles_section_at_end() {
+        let existing = "# Other stuff\nfoo/\n\n# claude-reliability managed\n.claude/old/\n";
+        let result = update_gitignore_content(existing, GITIGNORE_ENTRIES);
+        // Other section preserved
+        assert!(result.contains("# Other stuff"));
+        assert!(result.contains("foo/"));
+        // Our section updated with new entries, old entry preserved
+        assert!(result.contains(".claude/bin/"));
+        assert!(result.contains(".claude/old/"));
+    }
+
+    #[test]
+    fn test_gitignore_handles_section_in_middle() {
+        let existing =
+            "# Start\nfoo/\n\n# claude-reliability managed\n.claude/old/\n\n# End\nbar/\n";
+        let result = update_gitignore_content(existing, GITIGNORE_ENTRIES);
+        // All sections preserved in order
+        assert!(result.contains("# Start"));
+        assert!(result.contains("foo/"));
+        assert!(result.contains(GITIGNORE_SECTION_HEADER));
+        assert!(result.contains(".claude/bin/"));
+        // Old entry preserved (additive-only)
+        assert!(result.contains(".claude/old/"));
+        assert!(result.contains("# End"));
+        assert!(result.contains("bar/"));
+        // Verify order
+        let start_pos = result.find("# Start").unwrap();
+        let our_pos = result.find(GITIGNORE_SECTION_HEADER).unwrap();
+        let end_pos = result.find("# End").unwrap();
+        assert!(start_pos < our_pos);
+        assert!(our_pos < end_pos);
+    }
+
+    #[test]
+    fn test_gitignore_does_not_match_similar_headers() {
+        // Headers that look similar but aren't our exact header
+        let existing = "# claude-reliability\nold/\n\n# claude-reliability-extra\nmore/\n";
+        let result = update_gitignore_content(existing, GITIGNORE_ENTRIES);
+        // Neither of these should be treated as our section
+        assert!(result.contains("# claude-reliability\n"));
+        assert!(result.contains("old/"));
+        assert!(result.contains("# claude-reliability-extra"));
+        assert!(result.contains("more/"));
+        // Our section should be appended
+        assert!(result.contains(GITIGNORE_SECTION_HEADER));
+    }
+
+    #[test]
+    fn test_gitignore_preserves_unicode_content() {
+        let existing = "# 日本語コメント\nテスト/\n\n# Emoji section 🎉\n*.emoji\n";
+        let result = update_gitignore_content(existing, GITIGNORE_ENTRIES);
+        assert!(result.contains("# 日本語コメント"));
+        assert!(result.contains("テスト/"));
+        assert!(result.contains("# Emoji section 🎉"));
+        assert!(result.contains("*.emoji"));
+    }
+
+    #[test]
+    fn test_gitignore_preserves_comment_only_lines() {
+        let existing = "# This is a comment\n# Another comment\n# Third comment\n";
+        let result = update_gitignore_content(existing, GITIGNORE_ENTRIES);
+        assert!(result.contains("# This is a comment"));
+        assert!(result.contains("# Another comment"));
+        assert!(result.contains("# Third comment"));
+    }
+
+    #[test]
+    fn test_gitignore_handles_negation_patterns() {
+        let existing = "*.log\n!important.log\n";
+        let result = update_gitignore_content(existing, GITIGNORE_ENTRIES);
+        assert!(result.contains("*.log"));
+        assert!(result.contains("!important.log"));
+    }
+
+    #[test]
+    fn test_gitignore_handles_complex_patterns() {
+        let existing = "[Bb]uild/\n**/node_modules/\n*.py[cod]\ntest?.txt\n";
+        let result = update_gitignore_content(existing, GITIGNORE_ENTRIES);
+        assert!(result.contains("[Bb]uild/"));
+        assert!(result.contains("**/node_modules/"));
+        assert!(result.contains("*.py[cod]"));
+        assert!(result.contains("test?.txt"));
+    }
+
+    #[test]
+    fn test_gitignore_handles_escaped_patterns() {
+        let existing = "\\#not-a-comment\n\\!not-negation\n";
+        let result = update_gitignore_content(existing, GITIGNORE_ENTRIES);
+        assert!(result.contains("\\#not-a-comment"));
+        assert!(result.contains("\\!not-negation"));
+    }
+
+    #[test]
+    fn test_gitignore_preserves_inline_comments_in_patterns() {
+        // Note: gitignore doesn't actually support inline comments,
+        // but we should preserve whatever the user wrote
+        let existing = "*.log # these are logs\n";
+        let result = update_gitignore_content(existing, GITIGNORE_ENTRIES);
+        assert!(result.contains("*.log # these are logs"));
+    }
+
+    #[test]
+    fn test_gitignore_idempotent_multiple_calls() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join(".gitignore"), "existing/\n").unwrap();
+
+        // First call
+        ensure_gitignore(dir.path()).unwrap();
+        let first = std::fs::read_to_string(dir.path().join(".gitignore")).unwrap();
+
+        // Second call
+        ensure_gitignore(dir.path()).unwrap();
+        let second = std::fs::read_to_string(dir.path().join(".gitignore")).unwrap();
+
+        // Third call
+        ensure_gitignore(dir.path()).unwrap();
+        let third = std::fs::read_to_string(dir.path().join(".gitignore")).unwrap();
+
+        // All should be identical
+        assert_eq!(first, second);
+        assert_eq!(second, third);
+    }
+
+    #[test]
+    fn test_gitignore_handles_crlf_line_endings() {
+        let existing = "node_modules/\r\ntarget/\r\n";
+        let result = update_gitignore_content(existing, GITIGNORE_ENTRIES);
+        // Should handle CRLF without breaking
+        assert!(result.contains("node_modules/"));
+        assert!(result.contains("target/"));
+        assert!(result.contains(GITIGNORE_SECTION_HEADER));
+    }
+
+    #[test]
+    fn test_gitignore_preserves_paths_with_spaces() {
+        let existing = "My Documents/\nProgram\\ Files/\n";
+        let result = update_gitignore_content(existing, GITIGNORE_ENTRIES);
+        assert!(result.contains("My Documents/"));
+        assert!(result.contains("Program\\ Files/"));
+    }
+
+    #[test]
+    fn test_gitignore_exact_content_verification() {
+        let existing = "# My project\nnode_modules/\n\n";
+        let result = update_gitignore_content(existing, GITIGNORE_ENTRIES);
 
-        // This should create the config AND auto-commit it
-        let config = ensure_config_in(&runner, base).unwrap();
-        assert!(config.git_repo);
+        // The result should have exactly:
+        // 1. Original content (with proper newlines)
+        // 2. Our managed section
 
-        // Verify the config file exists
-        assert!(base.join(CONFIG_FILE_PATH).exists());
+        // Check it starts with the original comment
+        assert!(result.starts_with("# My project\n"));
 
-        // Verify it was committed (git status should show clean working tree)
-        let status =
-            Command::new("git").args(["status", "--porcelain"]).current_dir(base).output().unwrap();
-        let status_output = String::from_utf8_lossy(&status.stdout);
-        assert!(
-            status_output.trim().is_empty(),
-            "Expected clean working tree, got: {status_output}"
-        );
+        // Check our section is present and formatted correctly
+        let expected_section =
+            format!("{}\n{}\n", GITIGNORE_SECTION_HEADER, GITIGNORE_ENTRIES.join("\n"));
+        assert!(result.contains(&expected_section) || result.contains(GITIGNORE_SECTION_HEADER));
 
-        // Verify commit messages (there may be two: config and gitignore)
-        let log = Command::new("git")
-            .args(["log", "--oneline", "-2"])
-            .current_dir(base)
-            .output()
-            .unwrap();
-        let log_output = String::from_utf8_lossy(&log.stdout);
-        assert!(
-            log_output.contains("claude-reliability"),
-            "Expected commit messages to contain 'claude-reliability', got: {log_output}"
-        );
-        runner.verify();
+        // Make sure we haven't duplicated content
+        let header_count = result.matches(GITIGNORE_SECTION_HEADER).count();
+        assert_eq!(header_count, 1, "Should have exactly one managed section header");
     }
 
     #[test]
-    fn test_ensure_gitignore_creates_new() {
+    fn test_gitignore_file_not_found_creates_new() {
         let dir = TempDir::new().unwrap();
+        let gitignore_path = dir.path().join(".gitignore");
 
-        let modified = ensure_gitignore(dir.path()).unwrap();
-        assert!(modified);
+        // Ensure it doesn't exist
+        assert!(!gitignore_path.exists());
 
-        let content = std::fs::read_to_string(dir.path().join(".gitignore")).unwrap();
+        // Call ensure_gitignore
+        let result = ensure_gitignore(dir.path()).unwrap();
+        assert!(!result.is_empty()); // Should report modified
+
+        // File should now exist
+        assert!(gitignore_path.exists());
+
+        // And contain our entries
+        let content = std::fs::read_to_string(&gitignore_path).unwrap();
         assert!(content.contains(GITIGNORE_SECTION_HEADER));
         assert!(content.contains(".claude/bin/"));
-        assert!(content.contains(".claude/*.local.md"));
     }
 
     #[test]
-    fn test_ensure_gitignore_appends_to_existing() {
+    fn test_find_code_review_section_found() {
         let dir = TempDir::new().unwrap();
+        let claude_md = dir.path().join(CLAUDE_MD_PATH);
 
-        // Create existing gitignore
-        std::fs::write(dir.path().join(".gitignore"), "node_modules/\ntarget/\n").unwrap();
-
-        let modified = ensure_gitignore(dir.path()).unwrap();
-        assert!(modified);
+        // Create CLAUDE.md with Code Review section
+        std::fs::write(&claude_md, "# Project\n\n## Code Review\n\nReview guidelines.").unwrap();
 
-        let content = std::fs::read_to_string(dir.path().join(".gitignore")).unwrap();
-        // Original content preserved
-        assert!(content.contains("node_modules/"));
-        assert!(content.contains("target/"));
-        // New section added
-        assert!(content.contains(GITIGNORE_SECTION_HEADER));
-        assert!(content.contains(".claude/bin/"));
+        let result = find_code_review_section(dir.path());
+        assert!(result.is_some());
+        assert_eq!(result.unwrap(), CODE_REVIEW_HEADER);
     }
 
     #[test]
-    fn test_ensure_gitignore_updates_existing_section() {
+    fn test_find_code_review_section_not_found() {
         let dir = TempDir::new().unwrap();
+        let claude_md = dir.path().join(CLAUDE_MD_PATH);
 
-        // Create gitignore with old managed section
-        let old_content = "node_modules/\n\n# claude-reliability managed\n.claude/old-entry/\n\n# Other section\nfoo/\n";
-        std::fs::write(dir.path().join(".gitignore"), old_content).unwrap();
+        // Create CLAUDE.md without Code Review section
+        std::fs::write(&claude_md, "# Project\n\n## Development\n\nDev guidelines.").unwrap();
 
-        let modified = ensure_gitignore(dir.path()).unwrap();
-        assert!(modified);
+        let result = find_code_review_section(dir.path());
+        assert!(result.is_none());
+    }
 
-        let content = std::fs::read_to_string(dir.path().join(".gitignore")).unwrap();
-        // Original content preserved
-        assert!(content.contains("node_modules/"));
-        // Other section preserved
-        assert!(content.contains("# Other section"));
-        assert!(content.contains("foo/"));
-        // Old entry PRESERVED (additive-only), new entries also present
-        assert!(content.contains(".claude/old-entry/"));
-        assert!(content.contains(".claude/bin/"));
+    #[test]
+    fn test_find_code_review_section_no_file() {
+        let dir = TempDir::new().unwrap();
+        // No CLAUDE.md file
+        let result = find_code_review_section(dir.path());
+        assert!(result.is_none());
     }
 
+    // Tests for ensure_code_review_section removed - function no longer exists (claude-9i2m)
+
     #[test]
-    fn test_ensure_gitignore_no_change_when_up_to_date() {
+    fn test_default_require_push_value() {
+        // Test that require_push defaults to true when loading config without it
         let dir = TempDir::new().unwrap();
+        let config_path = dir.path().join(CONFIG_FILE_PATH);
 
-        // First call creates
-        ensure_gitignore(dir.path()).unwrap();
+        // Create .claude directory
+        std::fs::create_dir_all(config_path.parent().unwrap()).unwrap();
 
-        // Second call should not modify
-        let modified = ensure_gitignore(dir.path()).unwrap();
-        assert!(!modified);
+        // Create config without require_push field
+        std::fs::write(&config_path, "git_repo: true\n").unwrap();
+
+        let config = ProjectConfig::load_from(dir.path()).unwrap().unwrap();
+        // require_push should default to true
+        assert!(config.require_push);
     }
 
     #[test]
-    fn test_update_gitignore_content_empty() {
-        let result = update_gitignore_content("");
-        assert!(result.contains(GITIGNORE_SECTION_HEADER));
-        assert!(result.contains(".claude/bin/"));
+    fn test_project_config_load_wrapper() {
+        // Test the load() wrapper that uses current directory
+        // This test exercises the code path but doesn't verify behavior
+        // since we can't safely change current directory in parallel tests
+        let _ = ProjectConfig::load();
     }
 
     #[test]
-    fn test_update_gitignore_content_preserves_existing() {
-        let existing = "node_modules/\n*.log\n";
-        let result = update_gitignore_content(existing);
+    fn test_project_config_detect_wrapper() {
+        use crate::testing::MockCommandRunner;
+        use crate::traits::CommandOutput;
+
+        // Test the detect() wrapper
+        // Need to mock git remote since current dir might have .git
+        let mut runner = MockCommandRunner::new();
+        runner.expect(
+            "git",
+            &["remote"],
+            CommandOutput { exit_code: 0, stdout: String::new(), stderr: String::new() },
+        );
+        let _config = ProjectConfig::detect(&runner);
+        // Just verify it doesn't panic - we can't predict the result
+        // since it depends on the actual current directory
+    }
+
+    #[test]
+    fn test_has_git_remote_error() {
+        use crate::testing::FailingCommandRunner;
+
+        let runner = FailingCommandRunner::new("simulated error");
+        let has_remote = has_git_remote(&runner);
+        // Should return false when git command fails
+        assert!(!has_remote);
+    }
+
+    #[test]
+    fn test_ensure_config_with_code_review_section_discovery() {
+        use crate::testing::MockCommandRunner;
+        use crate::traits::CommandOutput;
+
+        let dir = TempDir::new().unwrap();
+        let claude_md = dir.path().join(CLAUDE_MD_PATH);
+        let config_path = dir.path().join(CONFIG_FILE_PATH);
+
+        // Create .git directory
+        std::fs::create_dir(dir.path().join(".git")).unwrap();
+
+        // Create .claude directory and config without code_review_section
+        std::fs::create_dir_all(config_path.parent().unwrap()).unwrap();
+        std::fs::write(&config_path, "git_repo: true\nrequire_push: false\n").unwrap();
+
+        // Create CLAUDE.md with Code Review section (to be discovered)
+        std::fs::write(&claude_md, "# Project\n\n## Code Review\n\nGuidelines.").unwrap();
+
+        let mut runner = MockCommandRunner::new();
+        // No git remote
+        runner.expect(
+            "git",
+            &["remote"],
+            CommandOutput { exit_code: 0, stdout: String::new(), stderr: String::new() },
+        );
+
+        let config = ensure_config_in(&runner, dir.path()).unwrap();
+
+        // Should have discovered the code review section
+        assert!(config.code_review_section.is_some());
+        assert_eq!(config.code_review_section.as_deref(), Some(CODE_REVIEW_HEADER));
+    }
 
+    #[test]
+    fn test_update_gitignore_content_no_trailing_newline() {
+        // Test that update_gitignore_content handles files without trailing newlines
+        let existing = "node_modules/";
+        let result = update_gitignore_content(existing, GITIGNORE_ENTRIES);
         assert!(result.contains("node_modules/"));
-        assert!(result.contains("*.log"));
         assert!(result.contains(GITIGNORE_SECTION_HEADER));
     }
 
     #[test]
-    fn test_build_managed_section() {
-        let section = build_managed_section();
-        assert!(section.starts_with(GITIGNORE_SECTION_HEADER));
-        for entry in GITIGNORE_ENTRIES {
-            assert!(section.contains(entry));
-        }
+    fn test_update_gitignore_content_empty_adds_section() {
+        let result = update_gitignore_content("", GITIGNORE_ENTRIES);
+        assert!(result.contains(GITIGNORE_SECTION_HEADER));
+        // Should not start with extra newlines
+        assert!(!result.starts_with("\n\n"));
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_ensure_config_detects_code_review_section() {
+        // Test that ensure_config DETECTS an existing code review section
+        // but does NOT add one (we stopped auto-modifying CLAUDE.md due to claude-9i2m)
+        use crate::testing::MockCommandRunner;
+
+        let dir = TempDir::new().unwrap();
+        let base = dir.path();
+
+        // Create CLAUDE.md WITH Code Review section (user-added)
+        let claude_md = base.join(CLAUDE_MD_PATH);
+        std::fs::write(&claude_md, "# Project\n\n## Code Review\n\nUser-added section.\n").unwrap();
+
+        // Create config WITHOUT code_review_section (not yet detected)
+        let config_path = base.join(CONFIG_FILE_PATH);
+        std::fs::create_dir_all(config_path.parent().unwrap()).unwrap();
+        std::fs::write(&config_path, "git_repo: false\n").unwrap();
+
+        let runner = MockCommandRunner::new();
+
+        let config = ensure_config_in(&runner, base).unwrap();
+
+        // Should have detected the existing code review section
+        assert!(config.code_review_section.is_some());
+        assert_eq!(config.code_review_section.as_deref(), Some(CODE_REVIEW_HEADER));
+    }
+
+    #[test]
+    fn test_ensure_config_does_not_add_code_review_section() {
+        // Test that ensure_config does NOT add a code review section to CLAUDE.md
+        // (we stopped auto-modifying CLAUDE.md due to corruption issues - claude-9i2m)
+        use crate::testing::MockCommandRunner;
+
+        let dir = TempDir::new().unwrap();
+        let base = dir.path();
+
+        // Create CLAUDE.md WITHOUT Code Review section
+        let claude_md = base.join(CLAUDE_MD_PATH);
+        std::fs::write(&claude_md, "# Project\n\n## Development\n\nSome content.\n").unwrap();
+
+        // Create config WITHOUT code_review_section
+        let config_path = base.join(CONFIG_FILE_PATH);
+        std::fs::create_dir_all(config_path.parent().unwrap()).unwrap();
+        std::fs::write(&config_path, "git_repo: false\n").unwrap();
+
+        let runner = MockCommandRunner::new();
+
+        let config = ensure_config_in(&runner, base).unwrap();
+
+        // Should NOT have added a code review section
+        assert!(config.code_review_section.is_none());
+
+        // CLAUDE.md should NOT have been modified
+        let claude_content = std::fs::read_to_string(&claude_md).unwrap();
+        assert!(!claude_content.contains(CODE_REVIEW_HEADER));
     }
 
-    // Pedantic gitignore preservation tests
+    // Tests for auto_commit_claude_md removed - function no longer exists (claude-9i2m)
 
     #[test]
-    fn test_gitignore_preserves_whitespace_only_file() {
-        let existing = "   \n\n   \n";
-        let result = update_gitignore_content(existing);
-        // Should add our section after the whitespace
+    fn test_update_gitignore_content_appends_missing_entries() {
+        // Test updating an existing managed section that only has some entries
+        let existing = "node_modules/\n# claude-reliability managed\n.claude/bin/\n";
+        let result = update_gitignore_content(existing, GITIGNORE_ENTRIES);
+        // Should preserve existing entry and add missing ones
         assert!(result.contains(GITIGNORE_SECTION_HEADER));
         assert!(result.contains(".claude/bin/"));
+        assert!(result.contains(".claude/*.local.md"));
+        assert!(result.contains(".claude-reliability/"));
     }
 
     #[test]
-    fn test_gitignore_preserves_no_trailing_newline() {
-        let existing = "node_modules/";
-        let result = update_gitignore_content(existing);
-        // Should still have node_modules
-        assert!(result.contains("node_modules/"));
-        // And our section
+    fn test_update_gitignore_content_with_content_after_section() {
+        // Test updating managed section with content after it
+        let existing =
+            "node_modules/\n# claude-reliability managed\n.claude/bin/\n# User section\n.env\n";
+        let result = update_gitignore_content(existing, GITIGNORE_ENTRIES);
+        // Should preserve content after the section
+        assert!(result.contains(".env"));
         assert!(result.contains(GITIGNORE_SECTION_HEADER));
     }
 
     #[test]
-    fn test_gitignore_preserves_trailing_whitespace_on_lines() {
-        let existing = "node_modules/   \ntarget/  \n";
-        let result = update_gitignore_content(existing);
-        // Should preserve the original lines exactly (including trailing whitespace)
-        assert!(result.contains("node_modules/   \n") || result.contains("node_modules/"));
-        assert!(result.contains("target/"));
+    fn test_has_check_target_file_not_found() {
+        // Test when justfile doesn't exist
+        let result = has_check_target(Path::new("/nonexistent/justfile"));
+        assert!(!result);
     }
 
     #[test]
-    fn test_gitignore_preserves_blank_lines_between_sections() {
-        let existing = "# Section 1\nfoo/\n\n\n# Section 2\nbar/\n";
-        let result = update_gitignore_content(existing);
-        // Both sections preserved
-        assert!(result.contains("# Section 1"));
-        assert!(result.contains("foo/"));
-        assert!(result.contains("# Section 2"));
-        assert!(result.contains("bar/"));
-    }
+    #[serial_test::serial]
+    fn test_project_config_save_wrapper() {
+        use tempfile::TempDir;
 
-    #[test]
-    fn test_gitignore_handles_section_at_beginning() {
-        let existing = "# claude-reliability managed\n.claude/old/\n\n# Other stuff\nfoo/\n";
-        let result = update_gitignore_content(existing);
-        // Our section should have new entries appended
-        assert!(result.contains(".claude/bin/"));
-        // Old entry preserved (additive-only)
-        assert!(result.contains(".claude/old/"));
-        // Other section preserved
-        assert!(result.contains("# Other stuff"));
-        assert!(result.contains("foo/"));
-    }
+        let dir = TempDir::new().unwrap();
+        let config_path = dir.path().join(CONFIG_FILE_PATH);
+        std::fs::create_dir_all(config_path.parent().unwrap()).unwrap();
 
-    #[test]
-    fn test_gitignore_handles_section_at_end() {
-        let existing = "# Other stuff\nfoo/\n\n# claude-reliability managed\n.claude/old/\n";
-        let result = update_gitignore_content(existing);
-        // Other section preserved
-        assert!(result.contains("# Other stuff"));
-        assert!(result.contains("foo/"));
-        // Our section updated with new entries, old entry preserved
-        assert!(result.contains(".claude/bin/"));
-        assert!(result.contains(".claude/old/"));
-    }
+        // Create a config and save it
+        let config = ProjectConfig {
+            git_repo: true,
+            check_command: Some("just check".to_string()),
+            code_review_section: None,
+            require_push: true,
+            explain_stops: false,
+            ..Default::default()
+        };
 
-    #[test]
-    fn test_gitignore_handles_section_in_middle() {
-        let existing =
-            "# Start\nfoo/\n\n# claude-reliability managed\n.claude/old/\n\n# End\nbar/\n";
-        let result = update_gitignore_content(existing);
-        // All sections preserved in order
-        assert!(result.contains("# Start"));
-        assert!(result.contains("foo/"));
-        assert!(result.contains(GITIGNORE_SECTION_HEADER));
-        assert!(result.contains(".claude/bin/"));
-        // Old entry preserved (additive-only)
-        assert!(result.contains(".claude/old/"));
-        assert!(result.contains("# End"));
-        assert!(result.contains("bar/"));
-        // Verify order
-        let start_pos = result.find("# Start").unwrap();
-        let our_pos = result.find(GITIGNORE_SECTION_HEADER).unwrap();
-        let end_pos = result.find("# End").unwrap();
-        assert!(start_pos < our_pos);
-        assert!(our_pos < end_pos);
-    }
+        // Change to temp dir to test save() wrapper
+        let original_dir = std::env::current_dir().unwrap();
+        std::env::set_current_dir(dir.path()).unwrap();
 
-    #[test]
-    fn test_gitignore_does_not_match_similar_headers() {
-        // Headers that look similar but aren't our exact header
-        let existing = "# claude-reliability\nold/\n\n# claude-reliability-extra\nmore/\n";
-        let result = update_gitignore_content(existing);
-        // Neither of these should be treated as our section
-        assert!(result.contains("# claude-reliability\n"));
-        assert!(result.contains("old/"));
-        assert!(result.contains("# claude-reliability-extra"));
-        assert!(result.contains("more/"));
-        // Our section should be appended
-        assert!(result.contains(GITIGNORE_SECTION_HEADER));
-    }
+        let result = config.save();
 
-    #[test]
-    fn test_gitignore_preserves_unicode_content() {
-        let existing = "# 日本語コメント\nテスト/\n\n# Emoji section 🎉\n*.emoji\n";
-        let result = update_gitignore_content(existing);
-        assert!(result.contains("# 日本語コメント"));
-        assert!(result.contains("テスト/"));
-        assert!(result.contains("# Emoji section 🎉"));
-        assert!(result.contains("*.emoji"));
-    }
+        std::env::set_current_dir(original_dir).unwrap();
 
-    #[test]
-    fn test_gitignore_preserves_comment_only_lines() {
-        let existing = "# This is a comment\n# Another comment\n# Third comment\n";
-        let result = update_gitignore_content(existing);
-        assert!(result.contains("# This is a comment"));
-        assert!(result.contains("# Another comment"));
-        assert!(result.contains("# Third comment"));
+        assert!(result.is_ok());
+        assert!(config_path.exists());
     }
 
+    // More tests for ensure_code_review_section/auto_commit_claude_md removed (claude-9i2m)
+
     #[test]
-    fn test_gitignore_handles_negation_patterns() {
-        let existing = "*.log\n!important.log\n";
-        let result = update_gitignore_content(existing);
-        assert!(result.contains("*.log"));
-        assert!(result.contains("!important.log"));
+    fn test_auto_commit_config_already_committed() {
+        // Test that auto_commit_config returns early when file is already committed
+        use std::process::Command;
+        use tempfile::TempDir;
+
+        let dir = TempDir::new().unwrap();
+        let base = dir.path();
+
+        // Set up git repo
+        Command::new("git").args(["init"]).current_dir(base).output().unwrap();
+        Command::new("git")
+            .args(["config", "user.email", "test@test.com"])
+            .current_dir(base)
+            .output()
+            .unwrap();
+        Command::new("git")
+            .args(["config", "user.name", "Test"])
+            .current_dir(base)
+            .output()
+            .unwrap();
+
+        // Create and commit config file
+        let config_path = ProjectConfig::config_path(base);
+        std::fs::create_dir_all(config_path.parent().unwrap()).unwrap();
+        std::fs::write(&config_path, "git_repo: true\n").unwrap();
+        Command::new("git").args(["add", "."]).current_dir(base).output().unwrap();
+        Command::new("git").args(["commit", "-m", "initial"]).current_dir(base).output().unwrap();
+
+        // Count commits before
+        let count_before = Command::new("git")
+            .args(["rev-list", "--count", "HEAD"])
+            .current_dir(base)
+            .output()
+            .unwrap();
+        let before: u32 = String::from_utf8_lossy(&count_before.stdout).trim().parse().unwrap();
+
+        // Call auto_commit_config - should return early since already committed
+        auto_commit_config(base);
+
+        // Count commits after - should be same (no new commit)
+        let count_after = Command::new("git")
+            .args(["rev-list", "--count", "HEAD"])
+            .current_dir(base)
+            .output()
+            .unwrap();
+        let after: u32 = String::from_utf8_lossy(&count_after.stdout).trim().parse().unwrap();
+
+        assert_eq!(before, after);
     }
 
     #[test]
-    fn test_gitignore_handles_complex_patterns() {
-        let existing = "[Bb]uild/\n**/node_modules/\n*.py[cod]\ntest?.txt\n";
-        let result = update_gitignore_content(existing);
-        assert!(result.contains("[Bb]uild/"));
-        assert!(result.contains("**/node_modules/"));
-        assert!(result.contains("*.py[cod]"));
-        assert!(result.contains("test?.txt"));
+    fn test_auto_commit_gitignore_already_committed() {
+        // Test that auto_commit_gitignore returns early when file is already committed
+        use std::process::Command;
+        use tempfile::TempDir;
+
+        let dir = TempDir::new().unwrap();
+        let base = dir.path();
+
+        // Set up git repo
+        Command::new("git").args(["init"]).current_dir(base).output().unwrap();
+        Command::new("git")
+            .args(["config", "user.email", "test@test.com"])
+            .current_dir(base)
+            .output()
+            .unwrap();
+        Command::new("git")
+            .args(["config", "user.name", "Test"])
+            .current_dir(base)
+            .output()
+            .unwrap();
+
+        // Create and commit .gitignore
+        std::fs::write(base.join(".gitignore"), "# test\n").unwrap();
+        Command::new("git").args(["add", "."]).current_dir(base).output().unwrap();
+        Command::new("git").args(["commit", "-m", "initial"]).current_dir(base).output().unwrap();
+
+        // Count commits before
+        let count_before = Command::new("git")
+            .args(["rev-list", "--count", "HEAD"])
+            .current_dir(base)
+            .output()
+            .unwrap();
+        let before: u32 = String::from_utf8_lossy(&count_before.stdout).trim().parse().unwrap();
+
+        // Call auto_commit_gitignore - should return early since already committed
+        auto_commit_gitignore(base);
+
+        // Count commits after - should be same (no new commit)
+        let count_after = Command::new("git")
+            .args(["rev-list", "--count", "HEAD"])
+            .current_dir(base)
+            .output()
+            .unwrap();
+        let after: u32 = String::from_utf8_lossy(&count_after.stdout).trim().parse().unwrap();
+
+        assert_eq!(before, after);
     }
 
     #[test]
-    fn test_gitignore_handles_escaped_patterns() {
-        let existing = "\\#not-a-comment\n\\!not-negation\n";
-        let result = update_gitignore_content(existing);
-        assert!(result.contains("\\#not-a-comment"));
-        assert!(result.contains("\\!not-negation"));
+    fn test_explain_stops_default_false() {
+        let config = ProjectConfig::default();
+        assert!(!config.explain_stops);
     }
 
     #[test]
-    fn test_gitignore_preserves_inline_comments_in_patterns() {
-        // Note: gitignore doesn't actually support inline comments,
-        // but we should preserve whatever the user wrote
-        let existing = "*.log # these are logs\n";
-        let result = update_gitignore_content(existing);
-        assert!(result.contains("*.log # these are logs"));
+    fn test_explain_stops_not_serialized_when_false() {
+        let dir = TempDir::new().unwrap();
+
+        let config = ProjectConfig { explain_stops: false, ..Default::default() };
+        config.save_to(dir.path()).unwrap();
+
+        let content = std::fs::read_to_string(dir.path().join(CONFIG_FILE_PATH)).unwrap();
+        // explain_stops should not appear in YAML when false
+        assert!(!content.contains("explain_stops"));
     }
 
     #[test]
-    fn test_gitignore_idempotent_multiple_calls() {
+    fn test_explain_stops_serialized_when_true() {
         let dir = TempDir::new().unwrap();
-        std::fs::write(dir.path().join(".gitignore"), "existing/\n").unwrap();
-
-        // First call
-        ensure_gitignore(dir.path()).unwrap();
-        let first = std::fs::read_to_string(dir.path().join(".gitignore")).unwrap();
-
-        // Second call
-        ensure_gitignore(dir.path()).unwrap();
-        let second = std::fs::read_to_string(dir.path().join(".gitignore")).unwrap();
 
-        // Third call
-        ensure_gitignore(dir.path()).unwrap();
-        let third = std::fs::read_to_string(dir.path().join(".gitignore")).unwrap();
+        let config = ProjectConfig { explain_stops: true, ..Default::default() };
+        config.save_to(dir.path()).unwrap();
 
-        // All should be identical
-        assert_eq!(first, second);
-        assert_eq!(second, third);
+        let content = std::fs::read_to_string(dir.path().join(CONFIG_FILE_PATH)).unwrap();
+        // explain_stops should appear in YAML when true
+        assert!(content.contains("explain_stops: true"));
     }
 
     #[test]
-    fn test_gitignore_handles_crlf_line_endings() {
-        let existing = "node_modules/\r\ntarget/\r\n";
-        let result = update_gitignore_content(existing);
-        // Should handle CRLF without breaking
-        assert!(result.contains("node_modules/"));
-        assert!(result.contains("target/"));
-        assert!(result.contains(GITIGNORE_SECTION_HEADER));
-    }
+    fn test_explain_stops_loaded_from_yaml() {
+        let dir = TempDir::new().unwrap();
+        let config_path = dir.path().join(CONFIG_FILE_PATH);
+        std::fs::create_dir_all(config_path.parent().unwrap()).unwrap();
 
-    #[test]
-    fn test_gitignore_preserves_paths_with_spaces() {
-        let existing = "My Documents/\nProgram\\ Files/\n";
-        let result = update_gitignore_content(existing);
-        assert!(result.contains("My Documents/"));
-        assert!(result.contains("Program\\ Files/"));
+        // Create config with explain_stops: true
+        std::fs::write(&config_path, "git_repo: false\nexplain_stops: true\n").unwrap();
+
+        let config = ProjectConfig::load_from(dir.path()).unwrap().unwrap();
+        assert!(config.explain_stops);
     }
 
     #[test]
-    fn test_gitignore_exact_content_verification() {
-        let existing = "# My project\nnode_modules/\n\n";
-        let result = update_gitignore_content(existing);
-
-        // The result should have exactly:
-        // 1. Original content (with proper newlines)
-        // 2. Our managed section
-
-        // Check it starts with the original comment
-        assert!(result.starts_with("# My project\n"));
+    fn test_explain_stops_defaults_to_false_when_missing() {
+        let dir = TempDir::new().unwrap();
+        let config_path = dir.path().join(CONFIG_FILE_PATH);
+        std::fs::create_dir_all(config_path.parent().unwrap()).unwrap();
 
-        // Check our section is present and formatted correctly
-        let expected_section =
-            format!("{}\n{}\n", GITIGNORE_SECTION_HEADER, GITIGNORE_ENTRIES.join("\n"));
-        assert!(result.contains(&expected_section) || result.contains(GITIGNORE_SECTION_HEADER));
+        // Create config without explain_stops
+        std::fs::write(&config_path, "git_repo: true\n").unwrap();
 
-        // Make sure we haven't duplicated content
-        let header_count = result.matches(GITIGNORE_SECTION_HEADER).count();
-        assert_eq!(header_count, 1, "Should have exactly one managed section header");
+        let config = ProjectConfig::load_from(dir.path()).unwrap().unwrap();
+        assert!(!config.explain_stops);
     }
 
     #[test]
-    fn test_gitignore_file_not_found_creates_new() {
+    fn test_ensure_config_preserves_all_fields() {
+        // Test that ensure_config doesn't corrupt an existing fully-populated config
+        // This is a regression test for bug claude-g2q7
+        use crate::testing::MockCommandRunner;
+
         let dir = TempDir::new().unwrap();
-        let gitignore_path = dir.path().join(".gitignore");
+        let config_path = dir.path().join(CONFIG_FILE_PATH);
+        std::fs::create_dir_all(config_path.parent().unwrap()).unwrap();
 
-        // Ensure it doesn't exist
-        assert!(!gitignore_path.exists());
+        // Create .git directory so git_repo matches
+        std::fs::create_dir(dir.path().join(".git")).unwrap();
 
-        // Call ensure_gitignore
-        let result = ensure_gitignore(dir.path()).unwrap();
-        assert!(result); // Should report modified
+        // Create a fully populated config file
+        let original_content = "git_repo: true
+check_command: just check
+code_review_section: '## Code Review'
+require_push: true
+explain_stops: true
+";
+        std::fs::write(&config_path, original_content).unwrap();
 
-        // File should now exist
-        assert!(gitignore_path.exists());
+        // No git remote check needed because git_repo already matches
+        let runner = MockCommandRunner::new();
 
-        // And contain our entries
-        let content = std::fs::read_to_string(&gitignore_path).unwrap();
-        assert!(content.contains(GITIGNORE_SECTION_HEADER));
-        assert!(content.contains(".claude/bin/"));
+        // Call ensure_config - this should NOT modify the file
+        let config = ensure_config_in(&runner, dir.path()).unwrap();
+
+        // Verify all fields are preserved
+        assert!(config.git_repo);
+        assert_eq!(config.check_command, Some("just check".to_string()));
+        assert_eq!(config.code_review_section, Some("## Code Review".to_string()));
+        assert!(config.require_push);
+        assert!(config.explain_stops, "explain_stops should be preserved as true");
+
+        // Verify the file wasn't modified
+        let final_content = std::fs::read_to_string(&config_path).unwrap();
+        assert_eq!(original_content, final_content, "Config file should not have been modified");
     }
 
     #[test]
-    fn test_find_code_review_section_found() {
+    fn test_save_preserves_explain_stops_true() {
+        // Test that saving a config with explain_stops=true preserves it
         let dir = TempDir::new().unwrap();
-        let claude_md = dir.path().join(CLAUDE_MD_PATH);
 
-        // Create CLAUDE.md with Code Review section
-        std::fs::write(&claude_md, "# Project\n\n## Code Review\n\nReview guidelines.").unwrap();
+        let config = ProjectConfig {
+            git_repo: true,
+            check_command: Some("just check".to_string()),
+            code_review_section: Some("## Code Review".to_string()),
+            require_push: true,
+            explain_stops: true,
+            ..Default::default()
+        };
 
-        let result = find_code_review_section(dir.path());
-        assert!(result.is_some());
-        assert_eq!(result.unwrap(), CODE_REVIEW_HEADER);
+        config.save_to(dir.path()).unwrap();
+
+        // Load it back and verify
+        let loaded = ProjectConfig::load_from(dir.path()).unwrap().unwrap();
+        assert!(loaded.explain_stops, "explain_stops should be preserved as true after save/load");
+        assert_eq!(
+            loaded.check_command,
+            Some("just check".to_string()),
+            "Expected check_command to be preserved after save/load"
+        );
     }
 
     #[test]
-    fn test_find_code_review_section_not_found() {
-        let dir = TempDir::new().unwrap();
-        let claude_md = dir.path().join(CLAUDE_MD_PATH);
+    fn test_auto_work_on_tasks_default_true() {
+        let config = ProjectConfig::default();
+        assert!(config.auto_work_on_tasks);
+    }
 
-        // Create CLAUDE.md without Code Review section
-        std::fs::write(&claude_md, "# Project\n\n## Development\n\nDev guidelines.").unwrap();
+    #[test]
+    fn test_auto_work_idle_minutes_default_15() {
+        let config = ProjectConfig::default();
+        assert_eq!(config.auto_work_idle_minutes, 15);
+    }
 
-        let result = find_code_review_section(dir.path());
-        assert!(result.is_none());
+    #[test]
+    fn test_idle_source_serialization() {
+        let yaml = serde_yaml::to_string(&ProjectConfig::default()).unwrap();
+        assert!(!yaml.contains("idle_source"));
+        let config: ProjectConfig = serde_yaml::from_str("idle_source: heartbeat\n").unwrap();
+        assert_eq!(config.idle_source, IdleSource::Heartbeat);
+        let yaml = serde_yaml::to_string(&config).unwrap();
+        assert!(yaml.contains("idle_source: heartbeat"));
     }
 
     #[test]
-    fn test_find_code_review_section_no_file() {
-        let dir = TempDir::new().unwrap();
-        // No CLAUDE.md file
-        let result = find_code_review_section(dir.path());
-        assert!(result.is_none());
+    fn test_marker_ttl_minutes_serialization() {
+        let yaml = serde_yaml::to_string(&ProjectConfig::default()).unwrap();
+        assert!(!yaml.contains("marker_ttl_minutes"));
+        let config: ProjectConfig =
+            serde_yaml::from_str("marker_ttl_minutes:\n  problem_mode: 30\n").unwrap();
+        assert_eq!(config.marker_ttl_minutes.get("problem_mode"), Some(&30));
     }
 
-    // Tests for ensure_code_review_section removed - function no longer exists (claude-9i2m)
+    #[test]
+    fn test_auto_work_hours_roundtrip() {
+        let config: ProjectConfig =
+            serde_yaml::from_str("auto_work_hours: \"09:00-18:00 Mon-Fri\"\n").unwrap();
+        assert_eq!(config.auto_work_hours.as_deref(), Some("09:00-18:00 Mon-Fri"));
+        assert!(serde_yaml::to_string(&config).unwrap().contains("auto_work_hours:"));
+        assert!(!serde_yaml::to_string(&ProjectConfig::default())
+            .unwrap()
+            .contains("auto_work_hours"));
+    }
 
     #[test]
-    fn test_default_require_push_value() {
-        // Test that require_push defaults to true when loading config without it
+    fn test_auto_work_on_tasks_not_serialized_when_false() {
         let dir = TempDir::new().unwrap();
-        let config_path = dir.path().join(CONFIG_FILE_PATH);
-
-        // Create .claude directory
-        std::fs::create_dir_all(config_path.parent().unwrap()).unwrap();
 
-        // Create config without require_push field
-        std::fs::write(&config_path, "git_repo: true\n").unwrap();
+        let config = ProjectConfig { auto_work_on_tasks: false, ..Default::default() };
+        config.save_to(dir.path()).unwrap();
 
-        let config = ProjectConfig::load_from(dir.path()).unwrap().unwrap();
-        // require_push should default to true
-        assert!(config.require_push);
+        let content = std::fs::read_to_string(dir.path().join(CONFIG_FILE_PATH)).unwrap();
+        // auto_work_on_tasks should not appear in YAML when false
+        assert!(!content.contains("auto_work_on_tasks"));
     }
 
     #[test]
-    fn test_project_config_load_wrapper() {
-        // Test the load() wrapper that uses current directory
-        // This test exercises the code path but doesn't verify behavior
-        // since we can't safely change current directory in parallel tests
-        let _ = ProjectConfig::load();
-    }
+    fn test_auto_work_on_tasks_serialized_when_true() {
+        let dir = TempDir::new().unwrap();
 
-    #[test]
-    fn test_project_config_detect_wrapper() {
-        use crate::testing::MockCommandRunner;
-        use crate::traits::CommandOutput;
+        let config = ProjectConfig { auto_work_on_tasks: true, ..Default::default() };
+        config.save_to(dir.path()).unwrap();
 
-        // Test the detect() wrapper
-        // Need to mock git remote since current dir might have .git
-        let mut runner = MockCommandRunner::new();
-        runner.expect(
-            "git",
-            &["remote"],
-            CommandOutput { exit_code: 0, stdout: String::new(), stderr: String::new() },
-        );
-        let _config = ProjectConfig::detect(&runner);
-        // Just verify it doesn't panic - we can't predict the result
-        // since it depends on the actual current directory
+        let content = std::fs::read_to_string(dir.path().join(CONFIG_FILE_PATH)).unwrap();
+        // auto_work_on_tasks should appear in YAML when true
+        assert!(content.contains("auto_work_on_tasks: true"));
     }
 
     #[test]
-    fn test_has_git_remote_error() {
-        use crate::testing::FailingCommandRunner;
+    fn test_auto_work_idle_minutes_not_serialized_when_default() {
+        let dir = TempDir::new().unwrap();
 
-        let runner = FailingCommandRunner::new("simulated error");
-        let has_remote = has_git_remote(&runner);
-        // Should return false when git command fails
-        assert!(!has_remote);
+        let config = ProjectConfig { auto_work_idle_minutes: 15, ..Default::default() };
+        config.save_to(dir.path()).unwrap();
+
+        let content = std::fs::read_to_string(dir.path().join(CONFIG_FILE_PATH)).unwrap();
+        // auto_work_idle_minutes should not appear when at default value
+        assert!(!content.contains("auto_work_idle_minutes"));
     }
 
     #[test]
-    fn test_ensure_config_with_code_review_section_discovery() {
-        use crate::testing::MockCommandRunner;
-        use crate::traits::CommandOutput;
-
+    fn test_auto_work_idle_minutes_serialized_when_non_default() {
         let dir = TempDir::new().unwrap();
-        let claude_md = dir.path().join(CLAUDE_MD_PATH);
-        let config_path = dir.path().join(CONFIG_FILE_PATH);
 
-        // Create .git directory
-        std::fs::create_dir(dir.path().join(".git")).unwrap();
-
-        // Create .claude directory and config without code_review_section
-        std::fs::create_dir_all(config_path.parent().unwrap()).unwrap();
-        std::fs::write(&config_path, "git_repo: true\nrequire_push: false\n").unwrap();
+        let config = ProjectConfig { auto_work_idle_minutes: 30, ..Default::default() };
+        config.save_to(dir.path()).unwrap();
 
-        // Create CLAUDE.md with Code Review section (to be discovered)
-        std::fs::write(&claude_md, "# Project\n\n## Code Review\n\nGuidelines.").unwrap();
+        let content = std::fs::read_to_string(dir.path().join(CONFIG_FILE_PATH)).unwrap();
+        // auto_work_idle_minutes should appear when not at default
+        assert!(content.contains("auto_work_idle_minutes: 30"));
+    }
 
-        let mut runner = MockCommandRunner::new();
-        // No git remote
-        runner.expect(
-            "git",
-            &["remote"],
-            CommandOutput { exit_code: 0, stdout: String::new(), stderr: String::new() },
-        );
+    #[test]
+    fn test_auto_work_fields_loaded_from_yaml() {
+        let dir = TempDir::new().unwrap();
+        let config_path = dir.path().join(CONFIG_FILE_PATH);
+        std::fs::create_dir_all(config_path.parent().unwrap()).unwrap();
 
-        let config = ensure_config_in(&runner, dir.path()).unwrap();
+        // Create config with custom auto_work values
+        std::fs::write(
+            &config_path,
+            "git_repo: false\nauto_work_on_tasks: true\nauto_work_idle_minutes: 30\n",
+        )
+        .unwrap();
 
-        // Should have discovered the code review section
-        assert!(config.code_review_section.is_some());
-        assert_eq!(config.code_review_section.as_deref(), Some(CODE_REVIEW_HEADER));
+        let config = ProjectConfig::load_from(dir.path()).unwrap().unwrap();
+        assert!(config.auto_work_on_tasks);
+        assert_eq!(config.auto_work_idle_minutes, 30);
     }
 
     #[test]
-    fn test_update_gitignore_content_no_trailing_newline() {
-        // Test that update_gitignore_content handles files without trailing newlines
-        let existing = "node_modules/";
-        let result = update_gitignore_content(existing);
-        assert!(result.contains("node_modules/"));
-        assert!(result.contains(GITIGNORE_SECTION_HEADER));
+    fn test_auto_work_fields_default_when_missing() {
+        let dir = TempDir::new().unwrap();
+        let config_path = dir.path().join(CONFIG_FILE_PATH);
+        std::fs::create_dir_all(config_path.parent().unwrap()).unwrap();
+
+        // Create config without auto_work fields
+        std::fs::write(&config_path, "git_repo: true\n").unwrap();
+
+        let config = ProjectConfig::load_from(dir.path()).unwrap().unwrap();
+        assert!(config.auto_work_on_tasks); // Defaults to true
+        assert_eq!(config.auto_work_idle_minutes, 15); // Defaults to 15
     }
 
     #[test]
-    fn test_update_gitignore_content_empty_adds_section() {
-        let result = update_gitignore_content("");
-        assert!(result.contains(GITIGNORE_SECTION_HEADER));
-        // Should not start with extra newlines
-        assert!(!result.starts_with("\n\n"));
+    fn test_debug_logging_default_false() {
+        let config = ProjectConfig::default();
+        assert!(!config.debug_logging);
     }
 
     #[test]
-    #[serial_test::serial]
-    fn test_ensure_config_detects_code_review_section() {
-        // Test that ensure_config DETECTS an existing code review section
-        // but does NOT add one (we stopped auto-modifying CLAUDE.md due to claude-9i2m)
-        use crate::testing::MockCommandRunner;
-
+    fn test_debug_logging_not_serialized_when_false() {
         let dir = TempDir::new().unwrap();
-        let base = dir.path();
 
-        // Create CLAUDE.md WITH Code Review section (user-added)
-        let claude_md = base.join(CLAUDE_MD_PATH);
-        std::fs::write(&claude_md, "# Project\n\n## Code Review\n\nUser-added section.\n").unwrap();
+        let config = ProjectConfig { debug_logging: false, ..Default::default() };
+        config.save_to(dir.path()).unwrap();
 
-        // Create config WITHOUT code_review_section (not yet detected)
-        let config_path = base.join(CONFIG_FILE_PATH);
-        std::fs::create_dir_all(config_path.parent().unwrap()).unwrap();
-        std::fs::write(&config_path, "git_repo: false\n").unwrap();
+        let content = std::fs::read_to_string(dir.path().join(CONFIG_FILE_PATH)).unwrap();
+        assert!(!content.contains("debug_logging"));
+    }
 
-        let runner = MockCommandRunner::new();
+    #[test]
+    fn test_debug_logging_serialized_when_true() {
+        let dir = TempDir::new().unwrap();
 
-        let config = ensure_config_in(&runner, base).unwrap();
+        let config = ProjectConfig { debug_logging: true, ..Default::default() };
+        config.save_to(dir.path()).unwrap();
 
-        // Should have detected the existing code review section
-        assert!(config.code_review_section.is_some());
-        assert_eq!(config.code_review_section.as_deref(), Some(CODE_REVIEW_HEADER));
+        let content = std::fs::read_to_string(dir.path().join(CONFIG_FILE_PATH)).unwrap();
+        assert!(content.contains("debug_logging: true"));
     }
 
     #[test]
-    fn test_ensure_config_does_not_add_code_review_section() {
-        // Test that ensure_config does NOT add a code review section to CLAUDE.md
-        // (we stopped auto-modifying CLAUDE.md due to corruption issues - claude-9i2m)
-        use crate::testing::MockCommandRunner;
-
+    fn test_debug_logging_loaded_from_yaml() {
         let dir = TempDir::new().unwrap();
-        let base = dir.path();
-
-        // Create CLAUDE.md WITHOUT Code Review section
-        let claude_md = base.join(CLAUDE_MD_PATH);
-        std::fs::write(&claude_md, "# Project\n\n## Development\n\nSome content.\n").unwrap();
-
-        // Create config WITHOUT code_review_section
-        let config_path = base.join(CONFIG_FILE_PATH);
+        let config_path = dir.path().join(CONFIG_FILE_PATH);
         std::fs::create_dir_all(config_path.parent().unwrap()).unwrap();
-        std::fs::write(&config_path, "git_repo: false\n").unwrap();
 
-        let runner = MockCommandRunner::new();
+        std::fs::write(&config_path, "git_repo: false\ndebug_logging: true\n").unwrap();
 
-        let config = ensure_config_in(&runner, base).unwrap();
+        let config = ProjectConfig::load_from(dir.path()).unwrap().unwrap();
+        assert!(config.debug_logging);
+    }
 
-        // Should NOT have added a code review section
-        assert!(config.code_review_section.is_none());
+    #[test]
+    fn test_debug_logging_defaults_to_false_when_missing() {
+        let dir = TempDir::new().unwrap();
+        let config_path = dir.path().join(CONFIG_FILE_PATH);
+        std::fs::create_dir_all(config_path.parent().unwrap()).unwrap();
 
-        // CLAUDE.md should NOT have been modified
-        let claude_content = std::fs::read_to_string(&claude_md).unwrap();
-        assert!(!claude_content.contains(CODE_REVIEW_HEADER));
-    }
+        std::fs::write(&config_path, "git_repo: true\n").unwrap();
 
-    // Tests for auto_commit_claude_md removed - function no longer exists (claude-9i2m)
+        let config = ProjectConfig::load_from(dir.path()).unwrap().unwrap();
+        assert!(!config.debug_logging);
+    }
 
     #[test]
-    fn test_update_gitignore_content_appends_missing_entries() {
-        // Test updating an existing managed section that only has some entries
-        let existing = "node_modules/\n# claude-reliability managed\n.claude/bin/\n";
-        let result = update_gitignore_content(existing);
-        // Should preserve existing entry and add missing ones
-        assert!(result.contains(GITIGNORE_SECTION_HEADER));
-        assert!(result.contains(".claude/bin/"));
-        assert!(result.contains(".claude/*.local.md"));
-        assert!(result.contains(".claude-reliability/"));
+    fn test_block_escalation_threshold_default_and_serialization() {
+        let config = ProjectConfig::default();
+        assert_eq!(config.block_escalation_threshold, 3);
+        let yaml = serde_yaml::to_string(&config).unwrap();
+        assert!(!yaml.contains("block_escalation_threshold"));
+
+        let config = ProjectConfig { block_escalation_threshold: 0, ..Default::default() };
+        let yaml = serde_yaml::to_string(&config).unwrap();
+        assert!(yaml.contains("block_escalation_threshold: 0"));
     }
 
     #[test]
-    fn test_update_gitignore_content_with_content_after_section() {
-        // Test updating managed section with content after it
-        let existing =
-            "node_modules/\n# claude-reliability managed\n.claude/bin/\n# User section\n.env\n";
-        let result = update_gitignore_content(existing);
-        // Should preserve content after the section
-        assert!(result.contains(".env"));
-        assert!(result.contains(GITIGNORE_SECTION_HEADER));
+    fn test_injection_loop_limit_default_and_serialization() {
+        let config = ProjectConfig::default();
+        assert_eq!(config.injection_loop_limit, 20);
+        let yaml = serde_yaml::to_string(&config).unwrap();
+        assert!(!yaml.contains("injection_loop_limit"));
+
+        let config = ProjectConfig { injection_loop_limit: 0, ..Default::default() };
+        let yaml = serde_yaml::to_string(&config).unwrap();
+        assert!(yaml.contains("injection_loop_limit: 0"));
     }
 
     #[test]
-    fn test_has_check_target_file_not_found() {
-        // Test when justfile doesn't exist
-        let result = has_check_target(Path::new("/nonexistent/justfile"));
-        assert!(!result);
+    fn test_repeated_output_limit_default_and_serialization() {
+        let config = ProjectConfig::default();
+        assert_eq!(config.repeated_output_limit, 5);
+        let yaml = serde_yaml::to_string(&config).unwrap();
+        assert!(!yaml.contains("repeated_output_limit"));
+
+        let config = ProjectConfig { repeated_output_limit: 0, ..Default::default() };
+        let yaml = serde_yaml::to_string(&config).unwrap();
+        assert!(yaml.contains("repeated_output_limit: 0"));
     }
 
     #[test]
-    #[serial_test::serial]
-    fn test_project_config_save_wrapper() {
-        use tempfile::TempDir;
-
+    fn test_max_session_cost_loaded_from_yaml() {
         let dir = TempDir::new().unwrap();
         let config_path = dir.path().join(CONFIG_FILE_PATH);
         std::fs::create_dir_all(config_path.parent().unwrap()).unwrap();
 
-        // Create a config and save it
-        let config = ProjectConfig {
-            git_repo: true,
-            check_command: Some("just check".to_string()),
-            code_review_section: None,
-            require_push: true,
-            explain_stops: false,
-            ..Default::default()
-        };
-
-        // Change to temp dir to test save() wrapper
-        let original_dir = std::env::current_dir().unwrap();
-        std::env::set_current_dir(dir.path()).unwrap();
-
-        let result = config.save();
-
-        std::env::set_current_dir(original_dir).unwrap();
+        std::fs::write(&config_path, "git_repo: true\nmax_session_cost_usd: 25\n").unwrap();
 
-        assert!(result.is_ok());
-        assert!(config_path.exists());
+        let config = ProjectConfig::load_from(dir.path()).unwrap().unwrap();
+        assert_eq!(config.max_session_cost_usd, 25);
+        let yaml = serde_yaml::to_string(&ProjectConfig::default()).unwrap();
+        assert!(!yaml.contains("max_session_cost_usd"));
     }
 
-    // More tests for ensure_code_review_section/auto_commit_claude_md removed (claude-9i2m)
-
     #[test]
-    fn test_auto_commit_config_already_committed() {
-        // Test that auto_commit_config returns early when file is already committed
-        use std::process::Command;
-        use tempfile::TempDir;
-
+    fn test_block_escalation_threshold_loaded_from_yaml() {
         let dir = TempDir::new().unwrap();
-        let base = dir.path();
-
-        // Set up git repo
-        Command::new("git").args(["init"]).current_dir(base).output().unwrap();
-        Command::new("git")
-            .args(["config", "user.email", "test@test.com"])
-            .current_dir(base)
-            .output()
-            .unwrap();
-        Command::new("git")
-            .args(["config", "user.name", "Test"])
-            .current_dir(base)
-            .output()
-            .unwrap();
-
-        // Create and commit config file
-        let config_path = ProjectConfig::config_path(base);
+        let config_path = dir.path().join(CONFIG_FILE_PATH);
         std::fs::create_dir_all(config_path.parent().unwrap()).unwrap();
-        std::fs::write(&config_path, "git_repo: true\n").unwrap();
-        Command::new("git").args(["add", "."]).current_dir(base).output().unwrap();
-        Command::new("git").args(["commit", "-m", "initial"]).current_dir(base).output().unwrap();
 
-        // Count commits before
-        let count_before = Command::new("git")
-            .args(["rev-list", "--count", "HEAD"])
-            .current_dir(base)
-            .output()
-            .unwrap();
-        let before: u32 = String::from_utf8_lossy(&count_before.stdout).trim().parse().unwrap();
+        std::fs::write(&config_path, "git_repo: true\nblock_escalation_threshold: 5\n").unwrap();
 
-        // Call auto_commit_config - should return early since already committed
-        auto_commit_config(base);
+        let config = ProjectConfig::load_from(dir.path()).unwrap().unwrap();
+        assert_eq!(config.block_escalation_threshold, 5);
+    }
 
-        // Count commits after - should be same (no new commit)
-        let count_after = Command::new("git")
-            .args(["rev-list", "--count", "HEAD"])
-            .current_dir(base)
-            .output()
-            .unwrap();
-        let after: u32 = String::from_utf8_lossy(&count_after.stdout).trim().parse().unwrap();
+    #[test]
+    fn test_auto_wip_commit_default_and_serialization() {
+        let config = ProjectConfig::default();
+        assert!(!config.auto_wip_commit);
+        let yaml = serde_yaml::to_string(&config).unwrap();
+        assert!(!yaml.contains("auto_wip_commit"));
 
-        assert_eq!(before, after);
+        let config = ProjectConfig { auto_wip_commit: true, ..Default::default() };
+        let yaml = serde_yaml::to_string(&config).unwrap();
+        assert!(yaml.contains("auto_wip_commit: true"));
     }
 
     #[test]
-    fn test_auto_commit_gitignore_already_committed() {
-        // Test that auto_commit_gitignore returns early when file is already committed
-        use std::process::Command;
-        use tempfile::TempDir;
+    fn test_require_pr_default_and_serialization() {
+        let config = ProjectConfig::default();
+        assert!(!config.require_pr);
+        let yaml = serde_yaml::to_string(&config).unwrap();
+        assert!(!yaml.contains("require_pr"));
 
-        let dir = TempDir::new().unwrap();
-        let base = dir.path();
+        let config: ProjectConfig = serde_yaml::from_str("require_pr: true\n").unwrap();
+        assert!(config.require_pr);
+    }
 
-        // Set up git repo
-        Command::new("git").args(["init"]).current_dir(base).output().unwrap();
-        Command::new("git")
-            .args(["config", "user.email", "test@test.com"])
-            .current_dir(base)
-            .output()
-            .unwrap();
-        Command::new("git")
-            .args(["config", "user.name", "Test"])
-            .current_dir(base)
-            .output()
-            .unwrap();
+    #[test]
+    fn test_commit_split_thresholds_default_and_serialization() {
+        let config = ProjectConfig::default();
+        assert_eq!(config.commit_split_files, 25);
+        assert_eq!(config.commit_split_lines, 1000);
+        let yaml = serde_yaml::to_string(&config).unwrap();
+        assert!(!yaml.contains("commit_split"));
 
-        // Create and commit .gitignore
-        std::fs::write(base.join(".gitignore"), "# test\n").unwrap();
-        Command::new("git").args(["add", "."]).current_dir(base).output().unwrap();
-        Command::new("git").args(["commit", "-m", "initial"]).current_dir(base).output().unwrap();
+        let config: ProjectConfig =
+            serde_yaml::from_str("commit_split_files: 0\ncommit_split_lines: 400\n").unwrap();
+        assert_eq!(config.commit_split_files, 0);
+        assert_eq!(config.commit_split_lines, 400);
+    }
 
-        // Count commits before
-        let count_before = Command::new("git")
-            .args(["rev-list", "--count", "HEAD"])
-            .current_dir(base)
-            .output()
-            .unwrap();
-        let before: u32 = String::from_utf8_lossy(&count_before.stdout).trim().parse().unwrap();
+    #[test]
+    fn test_binary_file_policy() {
+        let policy = BinaryFilePolicy::default();
+        assert!(policy.allows("model.bin", 100));
+        assert!(!policy.allows("model.bin", 101));
+        assert!(policy.allows("docs/Logo.PNG", 400));
+        assert!(!policy.allows("docs/logo.png", 513));
+        assert!(BinaryFilePolicy { max_kb: 0, ..policy }.allows("model.bin", 50_000));
+
+        let yaml = serde_yaml::to_string(&ProjectConfig::default()).unwrap();
+        assert!(!yaml.contains("binary_files"));
+        let config: ProjectConfig =
+            serde_yaml::from_str("binary_files:\n  max_kb: 10\n  suggest_lfs: true\n").unwrap();
+        assert_eq!(config.binary_files.max_kb, 10);
+        assert!(config.binary_files.suggest_lfs);
+        assert_eq!(config.binary_files.allowed_max_kb, 512);
+    }
 
-        // Call auto_commit_gitignore - should return early since already committed
-        auto_commit_gitignore(base);
+    #[test]
+    fn test_sensitive_file_policy() {
+        let policy = SensitiveFilePolicy::default();
+        assert!(policy.is_sensitive(".env"));
+        assert!(policy.is_sensitive("services/api/.env.production"));
+        assert!(policy.is_sensitive("deploy/certs/server.pem"));
+        assert!(policy.is_sensitive("home/.ssh/id_rsa"));
+        assert!(policy.is_sensitive(".aws/credentials"));
+        assert!(policy.is_sensitive("ci/.kube/config"));
+        assert!(!policy.is_sensitive(".env.example"));
+        assert!(!policy.is_sensitive("home/.ssh/id_rsa.pub"));
+        assert!(!policy.is_sensitive("src/credentials.rs"));
+        assert!(!policy.is_sensitive("config"));
+        assert!(!SensitiveFilePolicy { patterns: Vec::new(), ..policy }.is_sensitive(".env"));
+
+        let yaml = serde_yaml::to_string(&ProjectConfig::default()).unwrap();
+        assert!(!yaml.contains("sensitive_files"));
+        let config: ProjectConfig =
+            serde_yaml::from_str("sensitive_files:\n  allowlist:\n    - tests/fixtures/*.pem\n")
+                .unwrap();
+        assert!(!config.sensitive_files.is_sensitive("tests/fixtures/test.pem"));
+        assert!(config.sensitive_files.is_sensitive("server.pem"));
+        assert!(config.sensitive_files.is_sensitive(".env.example"));
+    }
 
-        // Count commits after - should be same (no new commit)
-        let count_after = Command::new("git")
-            .args(["rev-list", "--count", "HEAD"])
-            .current_dir(base)
-            .output()
+    #[test]
+    fn test_generated_file_policy() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("schema.rs"), "// @generated by schemagen\nstruct A;\n")
             .unwrap();
-        let after: u32 = String::from_utf8_lossy(&count_after.stdout).trim().parse().unwrap();
+        std::fs::write(dir.path().join("lib.rs"), "struct B;\n").unwrap();
+        let policy = GeneratedFilePolicy::default();
+        assert!(policy.is_generated(dir.path(), "Cargo.lock"));
+        assert!(policy.is_generated(dir.path(), "web/package-lock.json"));
+        assert!(policy.is_generated(dir.path(), "vendor/github.com/pkg/errors/errors.go"));
+        assert!(policy.is_generated(dir.path(), "schema.rs"));
+        assert!(!policy.is_generated(dir.path(), "lib.rs"));
+        assert!(!policy.is_generated(dir.path(), "missing.rs"));
+        let no_marker = GeneratedFilePolicy { detect_marker: false, ..policy.clone() };
+        assert!(!no_marker.is_generated(dir.path(), "schema.rs"));
+
+        assert!(policy.is_build_artifact("target/debug/app"));
+        assert!(policy.is_build_artifact("web/node_modules/"));
+        assert!(policy.is_build_artifact("pkg/__pycache__/mod.cpython-312.pyc"));
+        assert!(!policy.is_build_artifact("src/target.rs"));
+        assert!(!policy.is_build_artifact("target"));
+        assert!(!policy.is_build_artifact("docs/build-notes.md"));
+
+        assert!(!serde_yaml::to_string(&ProjectConfig::default())
+            .unwrap()
+            .contains("generated_files"));
+        let config: ProjectConfig = serde_yaml::from_str(
+            "generated_files:\n  patterns:\n    - '*.pb.go'\n  build_artifact_dirs:\n    - dist\n",
+        )
+        .unwrap();
+        assert!(config.generated_files.is_generated(dir.path(), "api/v1/api.pb.go"));
+        assert!(!config.generated_files.is_generated(dir.path(), "Cargo.lock"));
+        assert!(config.generated_files.is_build_artifact("dist/app.js"));
+        assert!(!config.generated_files.is_build_artifact("target/debug/app"));
+        assert!(config.generated_files.detect_marker);
+    }
 
-        assert_eq!(before, after);
+    #[test]
+    fn test_context_budget_default_and_serialization() {
+        let config: ProjectConfig = serde_yaml::from_str("git_repo: true\n").unwrap();
+        assert_eq!(config.context_budget, crate::context_budget::DEFAULT_BUDGET);
+        assert!(!serde_yaml::to_string(&config).unwrap().contains("context_budget"));
+        let config: ProjectConfig = serde_yaml::from_str("context_budget: 0\n").unwrap();
+        assert_eq!(config.context_budget, 0);
     }
 
     #[test]
-    fn test_explain_stops_default_false() {
+    fn test_auto_confirm_settings_serialization() {
         let config = ProjectConfig::default();
-        assert!(!config.explain_stops);
+        assert!(config.auto_confirm_commit && config.auto_confirm_push);
+        assert!(config.auto_answer_continue);
+        let yaml = serde_yaml::to_string(&config).unwrap();
+        assert!(!yaml.contains("auto_confirm") && !yaml.contains("auto_answer"));
+        let config: ProjectConfig = serde_yaml::from_str("auto_confirm_push: false\n").unwrap();
+        assert!(config.auto_confirm_commit);
+        assert!(!config.auto_confirm_push);
+        let yaml = serde_yaml::to_string(&config).unwrap();
+        assert!(yaml.contains("auto_confirm_push: false"));
+        assert!(!yaml.contains("auto_confirm_commit"));
     }
 
     #[test]
-    fn test_explain_stops_not_serialized_when_false() {
-        let dir = TempDir::new().unwrap();
-
-        let config = ProjectConfig { explain_stops: false, ..Default::default() };
-        config.save_to(dir.path()).unwrap();
+    fn test_stop_hook_thresholds_serialization() {
+        let yaml = serde_yaml::to_string(&ProjectConfig::default()).unwrap();
+        assert!(!yaml.contains("user_recency_minutes"));
+        assert!(!yaml.contains("api_error_threshold"));
+        assert!(!yaml.contains("max_files_to_show"));
+        let config: ProjectConfig =
+            serde_yaml::from_str("user_recency_minutes: 30\nmax_files_to_show: 25\n").unwrap();
+        assert_eq!(config.user_recency_minutes, 30);
+        assert_eq!(config.api_error_threshold, crate::hooks::API_ERROR_THRESHOLD);
+        assert_eq!(config.max_files_to_show, 25);
+        let yaml = serde_yaml::to_string(&config).unwrap();
+        assert!(yaml.contains("user_recency_minutes: 30"));
+    }
 
-        let content = std::fs::read_to_string(dir.path().join(CONFIG_FILE_PATH)).unwrap();
-        // explain_stops should not appear in YAML when false
-        assert!(!content.contains("explain_stops"));
+    #[test]
+    fn test_backup_settings_serialization() {
+        let yaml = serde_yaml::to_string(&ProjectConfig::default()).unwrap();
+        assert!(!yaml.contains("backup_"));
+        let config: ProjectConfig =
+            serde_yaml::from_str("backup_interval_hours: 6\nbackup_keep: 3\n").unwrap();
+        assert_eq!(config.backup_interval_hours, 6);
+        assert_eq!(config.backup_keep, 3);
+        assert_eq!(ProjectConfig::default().backup_keep, crate::backup::BACKUP_KEEP);
     }
 
     #[test]
-    fn test_explain_stops_serialized_when_true() {
-        let dir = TempDir::new().unwrap();
+    fn test_failure_mode_serialization() {
+        let yaml = serde_yaml::to_string(&ProjectConfig::default()).unwrap();
+        assert!(!yaml.contains("failure_mode"));
+        assert!(FailureMode::default().allows());
+        let config: ProjectConfig = serde_yaml::from_str("failure_mode: closed\n").unwrap();
+        assert_eq!(config.failure_mode, FailureMode::Closed);
+        assert!(!config.failure_mode.allows());
+        let yaml = serde_yaml::to_string(&config).unwrap();
+        assert!(yaml.contains("failure_mode: closed"));
+    }
 
-        let config = ProjectConfig { explain_stops: true, ..Default::default() };
-        config.save_to(dir.path()).unwrap();
+    #[test]
+    fn test_message_verbosity_serialization() {
+        let yaml = serde_yaml::to_string(&ProjectConfig::default()).unwrap();
+        assert!(!yaml.contains("message_verbosity"));
+        let config: ProjectConfig = serde_yaml::from_str("message_verbosity: quiet\n").unwrap();
+        assert_eq!(config.message_verbosity, MessageVerbosity::Quiet);
+        let yaml = serde_yaml::to_string(&config).unwrap();
+        assert!(yaml.contains("message_verbosity: quiet"));
+    }
 
-        let content = std::fs::read_to_string(dir.path().join(CONFIG_FILE_PATH)).unwrap();
-        // explain_stops should appear in YAML when true
-        assert!(content.contains("explain_stops: true"));
+    #[test]
+    fn test_tag_blocks_serialization() {
+        let yaml = serde_yaml::to_string(&ProjectConfig::default()).unwrap();
+        assert!(!yaml.contains("tag_blocks"));
+        let config: ProjectConfig = serde_yaml::from_str("tag_blocks: true\n").unwrap();
+        assert!(config.tag_blocks);
+        let yaml = serde_yaml::to_string(&config).unwrap();
+        assert!(yaml.contains("tag_blocks: true"));
     }
 
     #[test]
-    fn test_explain_stops_loaded_from_yaml() {
-        let dir = TempDir::new().unwrap();
-        let config_path = dir.path().join(CONFIG_FILE_PATH);
-        std::fs::create_dir_all(config_path.parent().unwrap()).unwrap();
+    fn test_suggest_answers_serialization() {
+        let yaml = serde_yaml::to_string(&ProjectConfig::default()).unwrap();
+        assert!(!yaml.contains("suggest_answers"));
+        let config: ProjectConfig = serde_yaml::from_str("suggest_answers: true\n").unwrap();
+        assert!(config.suggest_answers);
+        let yaml = serde_yaml::to_string(&config).unwrap();
+        assert!(yaml.contains("suggest_answers: true"));
+    }
 
-        // Create config with explain_stops: true
-        std::fs::write(&config_path, "git_repo: false\nexplain_stops: true\n").unwrap();
+    #[test]
+    fn test_assume_after_hours_serialization() {
+        let yaml = serde_yaml::to_string(&ProjectConfig::default()).unwrap();
+        assert!(!yaml.contains("assume_after_hours"));
+        let config: ProjectConfig = serde_yaml::from_str("assume_after_hours: 48\n").unwrap();
+        assert_eq!(config.assume_after_hours, 48);
+        let yaml = serde_yaml::to_string(&config).unwrap();
+        assert!(yaml.contains("assume_after_hours: 48"));
+    }
 
-        let config = ProjectConfig::load_from(dir.path()).unwrap().unwrap();
-        assert!(config.explain_stops);
+    #[test]
+    fn test_question_delivery_serialization() {
+        let yaml = serde_yaml::to_string(&ProjectConfig::default()).unwrap();
+        assert!(!yaml.contains("question_delivery"));
+        assert!(!ProjectConfig::default().question_delivery.is_enabled());
+        let config: ProjectConfig =
+            serde_yaml::from_str("question_delivery:\n  github_issue: acme/app#12\n").unwrap();
+        assert!(config.question_delivery.is_enabled());
+        assert_eq!(config.question_delivery.github_issue.as_deref(), Some("acme/app#12"));
+        assert_eq!(config.question_delivery.sendmail_command, "sendmail -t");
+        let yaml = serde_yaml::to_string(&config).unwrap();
+        assert!(yaml.contains("github_issue: acme/app#12"));
+        assert!(!yaml.contains("sendmail_command"));
     }
 
     #[test]
-    fn test_explain_stops_defaults_to_false_when_missing() {
-        let dir = TempDir::new().unwrap();
-        let config_path = dir.path().join(CONFIG_FILE_PATH);
-        std::fs::create_dir_all(config_path.parent().unwrap()).unwrap();
+    fn test_escalation_policy_serialization() {
+        let yaml = serde_yaml::to_string(&ProjectConfig::default()).unwrap();
+        assert!(!yaml.contains("escalation"));
+        assert!(!ProjectConfig::default().escalation.is_enabled());
+        let config: ProjectConfig =
+            serde_yaml::from_str("escalation:\n  blockers_file: BLOCKERS.md\n").unwrap();
+        assert!(config.escalation.is_enabled());
+        assert!(!config.escalation.github_issues);
+        let config: ProjectConfig = serde_yaml::from_str(
+            "escalation:\n  github_issues: true\n  github_labels: [blocker]\n",
+        )
+        .unwrap();
+        assert!(config.escalation.is_enabled());
+        assert_eq!(config.escalation.github_labels, vec!["blocker"]);
+    }
 
-        // Create config without explain_stops
-        std::fs::write(&config_path, "git_repo: true\n").unwrap();
+    #[test]
+    fn test_spelling_policy_serialization() {
+        let yaml = serde_yaml::to_string(&ProjectConfig::default()).unwrap();
+        assert!(!yaml.contains("spelling"));
+        let config: ProjectConfig = serde_yaml::from_str(
+            "spelling:\n  enabled: true\n  terminology:\n    whitelist: allowlist\n",
+        )
+        .unwrap();
+        assert!(config.spelling.enabled);
+        assert_eq!(config.spelling.terminology["whitelist"], "allowlist");
+    }
 
-        let config = ProjectConfig::load_from(dir.path()).unwrap().unwrap();
-        assert!(!config.explain_stops);
+    #[test]
+    fn test_require_task_policy() {
+        let yaml = serde_yaml::to_string(&ProjectConfig::default()).unwrap();
+        assert!(!yaml.contains("require_task"));
+        let config: ProjectConfig = serde_yaml::from_str(
+            "require_task:\n  scope: all\n  exempt: [\"scratch/*\", \"*.md\"]\n",
+        )
+        .unwrap();
+        assert_eq!(config.require_task.scope, RequireTaskScope::All);
+        assert!(config.require_task.is_exempt("scratch/notes/plan.txt"));
+        assert!(config.require_task.is_exempt("docs/README.md"));
+        assert!(!config.require_task.is_exempt("src/main.rs"));
     }
 
     #[test]
-    fn test_ensure_config_preserves_all_fields() {
-        // Test that ensure_config doesn't corrupt an existing fully-populated config
-        // This is a regression test for bug claude-g2q7
-        use crate::testing::MockCommandRunner;
+    fn test_auto_push_default_and_serialization() {
+        let config = ProjectConfig::default();
+        assert!(!config.auto_push);
+        let yaml = serde_yaml::to_string(&config).unwrap();
+        assert!(!yaml.contains("auto_push"));
 
-        let dir = TempDir::new().unwrap();
-        let config_path = dir.path().join(CONFIG_FILE_PATH);
-        std::fs::create_dir_all(config_path.parent().unwrap()).unwrap();
+        let config: ProjectConfig = serde_yaml::from_str("auto_push: true\n").unwrap();
+        assert!(config.auto_push);
+    }
 
-        // Create .git directory so git_repo matches
-        std::fs::create_dir(dir.path().join(".git")).unwrap();
+    #[test]
+    fn test_commit_policy_default_and_serialization() {
+        let config = ProjectConfig::default();
+        assert!(!config.commit_policy.is_enabled());
+        let yaml = serde_yaml::to_string(&config).unwrap();
+        assert!(!yaml.contains("commit_policy"));
 
-        // Create a fully populated config file
-        let original_content = "git_repo: true
-check_command: just check
-code_review_section: '## Code Review'
-require_push: true
-explain_stops: true
-";
-        std::fs::write(&config_path, original_content).unwrap();
+        let config: ProjectConfig = serde_yaml::from_str(
+            "commit_policy:\n  committer_email: ada@example.com\n  require_signature: true\n",
+        )
+        .unwrap();
+        assert!(config.commit_policy.is_enabled());
+        assert_eq!(config.commit_policy.committer_email.as_deref(), Some("ada@example.com"));
+        assert!(config.commit_policy.committer_name.is_none());
+        assert!(config.commit_policy.require_signature);
+    }
 
-        // No git remote check needed because git_repo already matches
-        let runner = MockCommandRunner::new();
+    #[test]
+    fn test_git_status_policy() {
+        let yaml = serde_yaml::to_string(&ProjectConfig::default()).unwrap();
+        assert!(!yaml.contains("git_status"));
+        assert!(!GitStatusPolicy::default().is_fast());
 
-        // Call ensure_config - this should NOT modify the file
-        let config = ensure_config_in(&runner, dir.path()).unwrap();
+        let config: ProjectConfig =
+            serde_yaml::from_str("git_status:\n  paths: [services/api, libs]\n").unwrap();
+        assert_eq!(config.git_status.paths, vec!["services/api", "libs"]);
+        assert!(config.git_status.is_fast());
+        assert!(GitStatusPolicy { cache_untracked: true, ..Default::default() }.is_fast());
+    }
 
-        // Verify all fields are preserved
-        assert!(config.git_repo);
-        assert_eq!(config.check_command, Some("just check".to_string()));
-        assert_eq!(config.code_review_section, Some("## Code Review".to_string()));
-        assert!(config.require_push);
-        assert!(config.explain_stops, "explain_stops should be preserved as true");
+    #[test]
+    fn test_vcs_default_and_serialization() {
+        let yaml = serde_yaml::to_string(&ProjectConfig::default()).unwrap();
+        assert!(!yaml.contains("vcs"));
+        let config: ProjectConfig = serde_yaml::from_str("vcs: jj\n").unwrap();
+        assert_eq!(config.vcs, VcsKind::Jj);
+        let config: ProjectConfig = serde_yaml::from_str("vcs: none\n").unwrap();
+        assert_eq!(config.vcs, VcsKind::None);
+    }
 
-        // Verify the file wasn't modified
-        let final_content = std::fs::read_to_string(&config_path).unwrap();
-        assert_eq!(original_content, final_content, "Config file should not have been modified");
+    #[test]
+    fn test_stop_checks_policy_validation() {
+        let yaml = serde_yaml::to_string(&ProjectConfig::default()).unwrap();
+        assert!(!yaml.contains("stop_checks"));
+        assert_eq!(StopChecksPolicy::default().tiers(), StopCheckTier::ALL.to_vec());
+
+        let policy = |yaml: &str| serde_yaml::from_str::<StopChecksPolicy>(yaml).unwrap();
+        let config = policy(
+            "disable: [work_item_reminder, auto_work_tasks]\n\
+             tier_order: [fast_exit, task_completion, validation, reflection]\n",
+        );
+        config.validate().unwrap();
+        assert!(config.is_disabled("auto_work_tasks"));
+        assert!(!config.is_disabled("pull_request"));
+        assert_eq!(config.tiers()[1], StopCheckTier::TaskCompletion);
+        policy("disable: [reflection_marker, reflection_prompt]\n").validate().unwrap();
+
+        let error = |yaml: &str| policy(yaml).validate().unwrap_err().to_string();
+        assert!(error("disable: [spellcheck]\n").contains("unknown check 'spellcheck'"));
+        assert!(error("disable: [pause_exit]\n").contains("'pause_exit' cannot be disabled"));
+        assert!(error("disable: [reflection_marker]\n")
+            .contains("'reflection_prompt' needs 'reflection_marker'"));
+        assert!(error("tier_order: [fast_exit, validation, task_completion]\n")
+            .contains("including 'reflection'"));
+        assert!(error(
+            "tier_order: [fast_exit, validation, validation, task_completion, reflection]\n"
+        )
+        .contains("including 'validation'"));
+        assert!(error("tier_order: [validation, fast_exit, task_completion, reflection]\n")
+            .contains("'validation' must run after 'fast_exit'"));
+        assert!(error("tier_order: [fast_exit, reflection, validation, task_completion]\n")
+            .contains("'reflection' must run after 'validation'"));
     }
 
     #[test]
-    fn test_save_preserves_explain_stops_true() {
-        // Test that saving a config with explain_stops=true preserves it
+    fn test_invalid_stop_checks_fail_to_load() {
         let dir = TempDir::new().unwrap();
+        let config_path = dir.path().join(CONFIG_FILE_PATH);
+        std::fs::create_dir_all(config_path.parent().unwrap()).unwrap();
 
-        let config = ProjectConfig {
-            git_repo: true,
-            check_command: Some("just check".to_string()),
-            code_review_section: Some("## Code Review".to_string()),
-            require_push: true,
-            explain_stops: true,
-            ..Default::default()
-        };
-
-        config.save_to(dir.path()).unwrap();
+        std::fs::write(&config_path, "stop_checks:\n  disable: [api_error_loop]\n").unwrap();
+        assert!(matches!(ProjectConfig::load_from(dir.path()), Err(Error::Config(_))));
 
-        // Load it back and verify
-        let loaded = ProjectConfig::load_from(dir.path()).unwrap().unwrap();
-        assert!(loaded.explain_stops, "explain_stops should be preserved as true after save/load");
-        assert_eq!(
-            loaded.check_command,
-            Some("just check".to_string()),
-            "Expected check_command to be preserved after save/load"
-        );
+        std::fs::write(&config_path, "stop_checks:\n  disable: [agent_stashes]\n").unwrap();
+        let config = ProjectConfig::load_from(dir.path()).unwrap().unwrap();
+        assert_eq!(config.stop_checks.disable, vec!["agent_stashes"]);
     }
 
     #[test]
-    fn test_auto_work_on_tasks_default_true() {
-        let config = ProjectConfig::default();
-        assert!(config.auto_work_on_tasks);
+    fn test_handoff_summaries_default_and_serialization() {
+        let config: ProjectConfig = serde_yaml::from_str("git_repo: true\n").unwrap();
+        assert!(!config.handoff_summaries);
+        let yaml = serde_yaml::to_string(&config).unwrap();
+        assert!(!yaml.contains("handoff_summaries"));
+
+        let config = ProjectConfig { handoff_summaries: true, ..Default::default() };
+        let yaml = serde_yaml::to_string(&config).unwrap();
+        assert!(yaml.contains("handoff_summaries: true"));
     }
 
     #[test]
-    fn test_auto_work_idle_minutes_default_15() {
+    fn test_db_path_default_and_serialization() {
         let config = ProjectConfig::default();
-        assert_eq!(config.auto_work_idle_minutes, 15);
+        assert!(config.db_path.is_none());
+        let yaml = serde_yaml::to_string(&config).unwrap();
+        assert!(!yaml.contains("db_path"));
+
+        let config: ProjectConfig = serde_yaml::from_str("db_path: user\n").unwrap();
+        assert_eq!(config.db_path.as_deref(), Some("user"));
     }
 
     #[test]
-    fn test_auto_work_on_tasks_not_serialized_when_false() {
-        let dir = TempDir::new().unwrap();
+    fn test_redact_secrets_default_and_serialization() {
+        let config: ProjectConfig = serde_yaml::from_str("git_repo: true\n").unwrap();
+        assert!(config.redact_secrets);
+        let yaml = serde_yaml::to_string(&config).unwrap();
+        assert!(!yaml.contains("redact_secrets"));
 
-        let config = ProjectConfig { auto_work_on_tasks: false, ..Default::default() };
-        config.save_to(dir.path()).unwrap();
-
-        let content = std::fs::read_to_string(dir.path().join(CONFIG_FILE_PATH)).unwrap();
-        // auto_work_on_tasks should not appear in YAML when false
-        assert!(!content.contains("auto_work_on_tasks"));
+        let config = ProjectConfig { redact_secrets: false, ..Default::default() };
+        let yaml = serde_yaml::to_string(&config).unwrap();
+        assert!(yaml.contains("redact_secrets: false"));
     }
 
     #[test]
-    fn test_auto_work_on_tasks_serialized_when_true() {
-        let dir = TempDir::new().unwrap();
+    fn test_deep_secrets_scan_default_and_serialization() {
+        let config: ProjectConfig = serde_yaml::from_str("git_repo: true\n").unwrap();
+        assert!(!config.deep_secrets_scan);
+        assert!(!serde_yaml::to_string(&config).unwrap().contains("deep_secrets_scan"));
 
-        let config = ProjectConfig { auto_work_on_tasks: true, ..Default::default() };
-        config.save_to(dir.path()).unwrap();
+        let config: ProjectConfig =
+            serde_yaml::from_str("git_repo: true\ndeep_secrets_scan: true\n").unwrap();
+        assert!(config.deep_secrets_scan);
+    }
 
-        let content = std::fs::read_to_string(dir.path().join(CONFIG_FILE_PATH)).unwrap();
-        // auto_work_on_tasks should appear in YAML when true
-        assert!(content.contains("auto_work_on_tasks: true"));
+    #[test]
+    fn test_auto_work_loop_limits() {
+        let config: ProjectConfig = serde_yaml::from_str("git_repo: true\n").unwrap();
+        assert_eq!((config.auto_work_max_prompts, config.auto_work_check_in_every), (0, 0));
+        let yaml = serde_yaml::to_string(&config).unwrap();
+        assert!(!yaml.contains("auto_work_max_prompts") && !yaml.contains("check_in"));
+
+        let yaml = "auto_work_max_prompts: 20\nauto_work_check_in_every: 5\n";
+        let config: ProjectConfig = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!((config.auto_work_max_prompts, config.auto_work_check_in_every), (20, 5));
     }
 
     #[test]
-    fn test_auto_work_idle_minutes_not_serialized_when_default() {
-        let dir = TempDir::new().unwrap();
+    fn test_profiles() {
+        assert_eq!("Strict".parse::<Profile>().unwrap(), Profile::Strict);
+        assert!("lenient".parse::<Profile>().is_err());
+
+        let mut config =
+            ProjectConfig { check_command: Some("just check".to_string()), ..Default::default() };
+        Profile::Strict.apply(&mut config);
+        assert_eq!(config.failure_mode, FailureMode::Closed);
+        assert!(config.deep_secrets_scan && config.spelling.enabled && !config.auto_confirm_push);
+        assert_eq!(config.require_task.scope, RequireTaskScope::All);
+        // Keys the profile does not cover are kept
+        assert_eq!(config.check_command.as_deref(), Some("just check"));
+
+        let yaml = serde_yaml::to_string(&config).unwrap();
+        assert!(yaml.contains("profile: strict"), "yaml: {yaml}");
+        assert_eq!(serde_yaml::from_str::<ProjectConfig>(&yaml).unwrap(), config);
+
+        Profile::Permissive.apply(&mut config);
+        assert_eq!(config.commit_split_files, 0);
+        assert!(!config.auto_answer_continue && config.auto_confirm_push);
+        assert_eq!(config.require_task.scope, RequireTaskScope::Off);
+
+        // The standard profile restores the defaults of the keys it covers
+        Profile::Standard.apply(&mut config);
+        assert_eq!(
+            config,
+            ProjectConfig {
+                check_command: Some("just check".to_string()),
+                profile: Some(Profile::Standard),
+                ..Default::default()
+            }
+        );
+    }
 
-        let config = ProjectConfig { auto_work_idle_minutes: 15, ..Default::default() };
-        config.save_to(dir.path()).unwrap();
+    #[test]
+    fn test_check_run_options() {
+        let config: ProjectConfig = serde_yaml::from_str("git_repo: true\n").unwrap();
+        assert_eq!(config.check_run_options(), RunOptions::new().max_output_bytes(1024 * 1024));
+        let yaml = serde_yaml::to_string(&config).unwrap();
+        assert!(!yaml.contains("check_"));
+
+        let yaml =
+            "check_env_allowlist: [PATH, HOME]\ncheck_niceness: 10\ncheck_output_limit: 4096\n";
+        let config: ProjectConfig = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(
+            config.check_run_options(),
+            RunOptions::new().env_allowlist(["PATH", "HOME"]).niceness(10).max_output_bytes(4096)
+        );
+    }
 
-        let content = std::fs::read_to_string(dir.path().join(CONFIG_FILE_PATH)).unwrap();
-        // auto_work_idle_minutes should not appear when at default value
-        assert!(!content.contains("auto_work_idle_minutes"));
+    #[test]
+    fn test_glob_matches() {
+        assert!(glob_matches("main", "main"));
+        assert!(!glob_matches("main", "main2"));
+        assert!(glob_matches("wip/*", "wip/feature"));
+        assert!(glob_matches("wip/*", "wip/"));
+        assert!(!glob_matches("wip/*", "feature/wip"));
+        assert!(glob_matches("*", "anything"));
+        assert!(glob_matches("release-*-rc", "release-1.2-rc"));
+        assert!(!glob_matches("release-*-rc", "release-1.2"));
+        assert!(glob_matches("*/*", "user/topic"));
+        assert!(!glob_matches("*/*", "topic"));
     }
 
     #[test]
-    fn test_auto_work_idle_minutes_serialized_when_non_default() {
-        let dir = TempDir::new().unwrap();
+    fn test_find_branch_rule_first_match_wins() {
+        let rules = vec![
+            BranchRule { pattern: "wip/special".to_string(), ..Default::default() },
+            BranchRule { pattern: "wip/*".to_string(), wip_commits: true, ..Default::default() },
+        ];
+        assert!(!find_branch_rule(&rules, "wip/special").unwrap().wip_commits);
+        assert!(find_branch_rule(&rules, "wip/other").unwrap().wip_commits);
+        assert!(find_branch_rule(&rules, "main").is_none());
+    }
 
-        let config = ProjectConfig { auto_work_idle_minutes: 30, ..Default::default() };
-        config.save_to(dir.path()).unwrap();
+    #[test]
+    fn test_default_branch_rules() {
+        let config = ProjectConfig::default();
+        assert!(find_branch_rule(&config.branch_rules, "main").is_none());
+        assert!(find_branch_rule(&config.branch_rules, "master").is_none());
+        let wip = find_branch_rule(&config.branch_rules, "wip/refactor").unwrap();
+        assert!(wip.wip_commits);
+        assert_eq!(wip.require_push, Some(false));
 
-        let content = std::fs::read_to_string(dir.path().join(CONFIG_FILE_PATH)).unwrap();
-        // auto_work_idle_minutes should appear when not at default
-        assert!(content.contains("auto_work_idle_minutes: 30"));
+        let yaml = serde_yaml::to_string(&config).unwrap();
+        assert!(!yaml.contains("branch_rules"));
     }
 
     #[test]
-    fn test_auto_work_fields_loaded_from_yaml() {
+    fn test_branch_rules_loaded_from_yaml() {
         let dir = TempDir::new().unwrap();
         let config_path = dir.path().join(CONFIG_FILE_PATH);
         std::fs::create_dir_all(config_path.parent().unwrap()).unwrap();
 
-        // Create config with custom auto_work values
         std::fs::write(
             &config_path,
-            "git_repo: false\nauto_work_on_tasks: true\nauto_work_idle_minutes: 30\n",
+            "git_repo: true\nbranch_rules:\n  - pattern: scratch/*\n    wip_commits: true\n",
         )
         .unwrap();
 
         let config = ProjectConfig::load_from(dir.path()).unwrap().unwrap();
-        assert!(config.auto_work_on_tasks);
-        assert_eq!(config.auto_work_idle_minutes, 30);
+        assert_eq!(config.branch_rules.len(), 1);
+        assert_eq!(config.branch_rules[0].pattern, "scratch/*");
+        assert!(config.branch_rules[0].wip_commits);
+        assert_eq!(config.branch_rules[0].require_push, None);
     }
 
     #[test]
-    fn test_auto_work_fields_default_when_missing() {
+    fn test_model_rules_loaded_from_yaml() {
         let dir = TempDir::new().unwrap();
         let config_path = dir.path().join(CONFIG_FILE_PATH);
         std::fs::create_dir_all(config_path.parent().unwrap()).unwrap();
 
-        // Create config without auto_work fields
-        std::fs::write(&config_path, "git_repo: true\n").unwrap();
+        std::fs::write(
+            &config_path,
+            "git_repo: true\nmodel_rules:\n  - pattern: \"*haiku*\"\n    quality_checks: true\n    auto_work_on_tasks: false\n",
+        )
+        .unwrap();
 
         let config = ProjectConfig::load_from(dir.path()).unwrap().unwrap();
-        assert!(config.auto_work_on_tasks); // Defaults to true
-        assert_eq!(config.auto_work_idle_minutes, 15); // Defaults to 15
-    }
-
-    #[test]
-    fn test_debug_logging_default_false() {
-        let config = ProjectConfig::default();
-        assert!(!config.debug_logging);
+        let rule = find_model_rule(&config.model_rules, "claude-haiku-4-5").unwrap();
+        assert_eq!(rule.quality_checks, Some(true));
+        assert_eq!(rule.auto_work_on_tasks, Some(false));
+        assert_eq!(rule.require_push, None);
+        assert!(find_model_rule(&config.model_rules, "claude-opus-4").is_none());
+        assert!(!serde_yaml::to_string(&ProjectConfig::default()).unwrap().contains("model_rules"));
     }
 
     #[test]
-    fn test_debug_logging_not_serialized_when_false() {
-        let dir = TempDir::new().unwrap();
+    fn test_packages_loaded_from_yaml() {
+        let yaml = "git_repo: true\npackage:\n  - name: api\n    path: crates/api\n    check_commands: [cargo test -p api]\n    protected_paths: [migrations/*]\n";
+        let config: ProjectConfig = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(config.packages.len(), 1);
+        assert_eq!(config.packages[0].check_commands, vec!["cargo test -p api"]);
 
-        let config = ProjectConfig { debug_logging: false, ..Default::default() };
-        config.save_to(dir.path()).unwrap();
+        let package = find_package(&config.packages, "crates/api/src/lib.rs").unwrap();
+        assert_eq!(package.name, "api");
+        assert!(find_package(&config.packages, "crates/api-client/src/lib.rs").is_none());
+        assert!(find_package(&config.packages, "crates/api").is_none());
+        assert!(package.is_protected("crates/api/migrations/001.sql"));
+        assert!(!package.is_protected("crates/api/src/migrations.rs"));
 
-        let content = std::fs::read_to_string(dir.path().join(CONFIG_FILE_PATH)).unwrap();
-        assert!(!content.contains("debug_logging"));
+        let reserialized = serde_yaml::to_string(&config).unwrap();
+        assert!(reserialized.contains("package:"));
+        assert!(!serde_yaml::to_string(&ProjectConfig::default()).unwrap().contains("package"));
     }
 
     #[test]
-    fn test_debug_logging_serialized_when_true() {
+    fn test_project_relative_path() {
         let dir = TempDir::new().unwrap();
-
-        let config = ProjectConfig { debug_logging: true, ..Default::default() };
-        config.save_to(dir.path()).unwrap();
-
-        let content = std::fs::read_to_string(dir.path().join(CONFIG_FILE_PATH)).unwrap();
-        assert!(content.contains("debug_logging: true"));
+        let inside = dir.path().join("crates/api/src/lib.rs");
+        assert_eq!(
+            project_relative_path(dir.path(), inside.to_str().unwrap()).as_deref(),
+            Some("crates/api/src/lib.rs")
+        );
+        assert_eq!(
+            project_relative_path(dir.path(), "./src/lib.rs").as_deref(),
+            Some("src/lib.rs")
+        );
+        assert_eq!(project_relative_path(dir.path(), "/elsewhere/lib.rs"), None);
     }
 
     #[test]
-    fn test_debug_logging_loaded_from_yaml() {
-        let dir = TempDir::new().unwrap();
-        let config_path = dir.path().join(CONFIG_FILE_PATH);
-        std::fs::create_dir_all(config_path.parent().unwrap()).unwrap();
-
-        std::fs::write(&config_path, "git_repo: false\ndebug_logging: true\n").unwrap();
+    fn test_emergency_stop_limits_serialization() {
+        let yaml = serde_yaml::to_string(&ProjectConfig::default()).unwrap();
+        assert!(!yaml.contains("emergency_stop"));
 
-        let config = ProjectConfig::load_from(dir.path()).unwrap().unwrap();
-        assert!(config.debug_logging);
+        let config: ProjectConfig = serde_yaml::from_str(
+            "emergency_stop_max_per_hour: 0\nemergency_stop_rejections_before_question: 5\n",
+        )
+        .unwrap();
+        assert_eq!(config.emergency_stop_max_per_hour, 0);
+        assert_eq!(config.emergency_stop_rejections_before_question, 5);
     }
 
     #[test]
-    fn test_debug_logging_defaults_to_false_when_missing() {
-        let dir = TempDir::new().unwrap();
-        let config_path = dir.path().join(CONFIG_FILE_PATH);
-        std::fs::create_dir_all(config_path.parent().unwrap()).unwrap();
+    fn test_auto_work_cooldown_serialization() {
+        let yaml = serde_yaml::to_string(&ProjectConfig::default()).unwrap();
+        assert!(!yaml.contains("auto_work_decline_limit"));
+        assert!(!yaml.contains("auto_work_cooldown_minutes"));
 
-        std::fs::write(&config_path, "git_repo: true\n").unwrap();
+        let config: ProjectConfig =
+            serde_yaml::from_str("auto_work_decline_limit: 0\nauto_work_cooldown_minutes: 240\n")
+                .unwrap();
+        assert_eq!(config.auto_work_decline_limit, 0);
+        assert_eq!(config.auto_work_cooldown_minutes, 240);
+    }
 
-        let config = ProjectConfig::load_from(dir.path()).unwrap().unwrap();
-        assert!(!config.debug_logging);
+    #[test]
+    fn test_package_path_glob() {
+        let package = PackageConfig {
+            name: "crates".to_string(),
+            path: "crates/*/".to_string(),
+            ..Default::default()
+        };
+        assert!(package.contains("crates/a/src/lib.rs"));
+        assert!(package.contains("./crates/b/Cargo.toml"));
+        assert!(!package.contains("docs/crates/a/README.md"));
+        assert!(!package.is_protected("crates/a/src/lib.rs"));
     }
 
     #[test]
     fn test_gitignore_user_entries_in_managed_section_preserved() {
         // User-added entries within the managed section must be preserved
         let existing =
-            "# claude-reliability managed\n.claude/bin/\n.claude/*.local.md\n.claude/*.local.json\n.claude/*.local\n.claude-reliability/\n.claude/my-custom-entry\n";
-        let result = update_gitignore_content(existing);
+            "# claude-reliability managed\n.claude/bin/\n.claude/*.local.md\n.claude/*.local.json\n.claude/*.local\n.claude-reliability/\n.claude/my-custom-entry\n# end claude-reliability managed\n";
+        let result = update_gitignore_content(existing, GITIGNORE_ENTRIES);
         // All original entries preserved including user-added one
         assert!(result.contains(".claude/my-custom-entry"));
         assert!(result.contains(".claude/bin/"));
@@ -2067,10 +4727,65 @@ explain_stops: true
         assert_eq!(result, existing);
     }
 
+    #[test]
+    fn test_gitignore_legacy_section_gets_end_marker() {
+        let existing =
+            "node_modules/\n\n# claude-reliability managed\n.claude/bin/\n\n# Other\nfoo/\n";
+        let result = update_gitignore_content(existing, &[".claude/bin/", ".claude-reliability/"]);
+        assert_eq!(
+            result,
+            "node_modules/\n\n# claude-reliability managed\n.claude/bin/\n.claude-reliability/\n\
+             # end claude-reliability managed\n\n# Other\nfoo/\n"
+        );
+        assert_eq!(
+            added_lines(existing, &result),
+            vec![".claude-reliability/", "# end claude-reliability managed"]
+        );
+    }
+
+    #[test]
+    fn test_gitignore_block_keeps_user_comments() {
+        // Comments inside a delimited block belong to it
+        let existing = "# claude-reliability managed\n.claude/bin/\n# mine\n.cache/\n\n# end claude-reliability managed\nfoo/\n";
+        let result = update_gitignore_content(existing, &[".claude/bin/", "target/"]);
+        assert_eq!(
+            result,
+            "# claude-reliability managed\n.claude/bin/\n# mine\n.cache/\ntarget/\n\n# end claude-reliability managed\nfoo/\n"
+        );
+        assert_eq!(update_gitignore_content(&result, &[".claude/bin/", "target/"]), result);
+    }
+
+    #[test]
+    fn test_gitignore_skips_entries_ignored_elsewhere() {
+        let existing = "/target\nnode_modules\n";
+        let result = update_gitignore_content(existing, &["target/", "node_modules/", ".venv/"]);
+        assert_eq!(
+            result,
+            "/target\nnode_modules\n\n# claude-reliability managed\n.venv/\n# end claude-reliability managed\n"
+        );
+    }
+
+    #[test]
+    fn test_project_type_detection() {
+        let dir = TempDir::new().unwrap();
+        assert!(ProjectType::detect(dir.path()).is_empty());
+
+        std::fs::write(dir.path().join("Cargo.toml"), "[package]\n").unwrap();
+        std::fs::write(dir.path().join("requirements.txt"), "requests\n").unwrap();
+        assert_eq!(ProjectType::detect(dir.path()), vec![ProjectType::Rust, ProjectType::Python]);
+
+        let added = ensure_gitignore(dir.path()).unwrap();
+        assert_eq!(added.first().map(String::as_str), Some(GITIGNORE_SECTION_HEADER));
+        assert!(added.contains(&"target/".to_string()));
+        assert!(added.contains(&"__pycache__/".to_string()));
+        assert!(!added.contains(&"node_modules/".to_string()));
+        assert!(ensure_gitignore(dir.path()).unwrap().is_empty());
+    }
+
     #[test]
     fn test_gitignore_contains_claude_reliability_dir() {
         // .claude-reliability/ should be in the managed section
-        let result = update_gitignore_content("");
+        let result = update_gitignore_content("", GITIGNORE_ENTRIES);
         assert!(result.contains(".claude-reliability/"));
     }
 
@@ -2175,7 +4890,7 @@ explain_stops: true
         // When the managed section has no trailing newline, new entries should
         // still be appended correctly with proper newline separation
         let existing = "# claude-reliability managed\n.claude/bin/";
-        let result = update_gitignore_content(existing);
+        let result = update_gitignore_content(existing, GITIGNORE_ENTRIES);
         // Should have all entries
         assert!(result.contains(".claude/bin/"));
         assert!(result.contains(".claude/*.local.md"));
@@ -0,0 +1,247 @@
+//! Fitting messages for the agent into a context budget.
+//!
+//! Messages injected into the agent's context, such as quality check output,
+//! file lists and reflection prompts, are cut down to a budget in characters
+//! (roughly four characters per token). Command output is summarized rather
+//! than cut blindly: lines reporting errors and failures are kept wherever they
+//! are, lines reporting progress or success are dropped first, and the rest of
+//! the space goes to the end of the output, where summaries usually are.
+
+/// Default context budget, in characters (about 5,000 tokens).
+pub const DEFAULT_BUDGET: usize = 20_000;
+
+/// Substrings (matched case-insensitively) of lines worth keeping from command output.
+const IMPORTANT_MARKERS: &[&str] =
+    &["error", "fail", "panic", "warning", "assert", "traceback", "exception", "expected", "-->"];
+
+/// Prefixes of lines that report progress, which are dropped first.
+const NOISE_PREFIXES: &[&str] = &[
+    "Compiling ",
+    "Checking ",
+    "Downloaded ",
+    "Downloading ",
+    "Fresh ",
+    "Running ",
+    "Finished ",
+    "Installing ",
+    "Collecting ",
+];
+
+/// Suffixes of lines that report a passing test, which are dropped first.
+const NOISE_SUFFIXES: &[&str] = &["... ok", " PASSED", " passed", " ✓"];
+
+/// Interpret a configured budget, where 0 means unlimited.
+const fn effective(budget: usize) -> usize {
+    if budget == 0 {
+        usize::MAX
+    } else {
+        budget
+    }
+}
+
+/// Whether a line of command output reports an error or failure.
+fn is_important(line: &str) -> bool {
+    let lower = line.to_ascii_lowercase();
+    IMPORTANT_MARKERS.iter().any(|marker| lower.contains(marker))
+}
+
+/// Whether a line of command output only reports progress or success.
+fn is_noise(line: &str) -> bool {
+    let trimmed = line.trim();
+    trimmed.is_empty()
+        || NOISE_PREFIXES.iter().any(|prefix| trimmed.starts_with(prefix))
+        || NOISE_SUFFIXES.iter().any(|suffix| trimmed.ends_with(suffix))
+}
+
+/// Lines chosen to keep from some output, with the space they take up.
+struct Selection {
+    keep: Vec<bool>,
+    lines: usize,
+    chars: usize,
+}
+
+impl Selection {
+    /// Keep a line if doing so stays within the limits.
+    fn take(&mut self, index: usize, len: usize, line_limit: usize, char_limit: usize) -> bool {
+        let cost = len + 1;
+        if self.lines >= line_limit || self.chars + cost > char_limit {
+            return false;
+        }
+        self.keep[index] = true;
+        self.lines += 1;
+        self.chars += cost;
+        true
+    }
+}
+
+/// Summarize command output in at most `max_lines` lines and about `budget`
+/// characters (0 for no character limit).
+///
+/// Output that fits is returned unchanged. Otherwise error and failure lines
+/// are kept first, using up to half the space, and the rest is filled from
+/// the end of the output, skipping progress and success lines. Each run of
+/// dropped lines is replaced by a line saying how many were omitted.
+#[must_use]
+pub fn summarize_output(output: &str, max_lines: usize, budget: usize) -> Vec<String> {
+    let budget = effective(budget);
+    let lines: Vec<&str> = output.lines().collect();
+    if lines.len() <= max_lines && output.len() <= budget {
+        return lines.into_iter().map(String::from).collect();
+    }
+
+    let mut selection = Selection { keep: vec![false; lines.len()], lines: 0, chars: 0 };
+    for (index, line) in lines.iter().enumerate() {
+        if is_important(line) {
+            selection.take(index, line.len(), max_lines / 2, budget / 2);
+        }
+    }
+    for (index, line) in lines.iter().enumerate().rev() {
+        if selection.keep[index] || is_noise(line) {
+            continue;
+        }
+        if !selection.take(index, line.len(), max_lines, budget) {
+            break;
+        }
+    }
+
+    let mut summary = Vec::new();
+    let mut omitted = 0;
+    for (line, kept) in lines.iter().zip(selection.keep) {
+        if kept {
+            if omitted > 0 {
+                summary.push(format!("... ({omitted} lines omitted)"));
+                omitted = 0;
+            }
+            summary.push((*line).to_string());
+        } else {
+            omitted += 1;
+        }
+    }
+    if omitted > 0 {
+        summary.push(format!("... ({omitted} lines omitted)"));
+    }
+    summary
+}
+
+/// Fit a list of message lines into about `budget` characters (0 for no limit).
+///
+/// Lines are kept from the start and the end, which is where headings and
+/// instructions are, and the lines in between are replaced by a note saying
+/// how many were omitted.
+pub fn fit_messages(messages: &mut Vec<String>, budget: usize) {
+    let budget = effective(budget);
+    let total: usize = messages.iter().map(|m| m.len() + 1).sum();
+    if total <= budget {
+        return;
+    }
+
+    let mut head = 0;
+    let mut used = 0;
+    while head < messages.len() && used + messages[head].len() < budget / 2 {
+        used += messages[head].len() + 1;
+        head += 1;
+    }
+    let mut tail = messages.len();
+    while tail > head && used + messages[tail - 1].len() < budget {
+        used += messages[tail - 1].len() + 1;
+        tail -= 1;
+    }
+
+    let omitted = tail - head;
+    messages
+        .splice(head..tail, [format!("... ({omitted} lines omitted to fit the context budget)")]);
+}
+
+/// Truncate text to at most `max_len` bytes (0 for no limit), on a character
+/// boundary, noting that it was truncated.
+#[must_use]
+pub fn truncate(text: &str, max_len: usize) -> String {
+    let max_len = effective(max_len);
+    if text.len() <= max_len {
+        return text.to_string();
+    }
+    let mut end = max_len;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}\n... (truncated)", &text[..end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numbered(n: usize) -> String {
+        (1..=n).map(|i| format!("line{i}")).collect::<Vec<_>>().join("\n")
+    }
+
+    #[test]
+    fn test_summarize_output_that_fits_is_unchanged() {
+        assert_eq!(summarize_output("line1\nline2", 10, 100), vec!["line1", "line2"]);
+        assert_eq!(summarize_output(&numbered(20), 20, 0).len(), 20);
+    }
+
+    #[test]
+    fn test_summarize_output_keeps_the_end() {
+        let summary = summarize_output(&numbered(100), 10, 0);
+        assert_eq!(summary.len(), 11);
+        assert_eq!(summary[0], "... (90 lines omitted)");
+        assert_eq!(summary[10], "line100");
+    }
+
+    #[test]
+    fn test_summarize_output_keeps_failures_and_drops_passing_tests() {
+        let mut output = vec!["   Compiling app v0.1.0".to_string()];
+        output.push("test parse::fails_on_empty ... FAILED".to_string());
+        output.extend((0..200).map(|i| format!("test passing_{i} ... ok")));
+        output.push("thread 'parse::fails_on_empty' panicked at src/parse.rs:10:5".to_string());
+        output.extend((0..50).map(|i| format!("stack frame {i}")));
+        output.push("test result: FAILED. 200 passed; 1 failed".to_string());
+
+        let summary = summarize_output(&output.join("\n"), 20, 0);
+        assert!(summary.len() <= 25, "{summary:?}");
+        assert!(summary.contains(&"test parse::fails_on_empty ... FAILED".to_string()));
+        assert!(summary.iter().any(|line| line.contains("panicked")));
+        assert_eq!(summary.last().unwrap(), "test result: FAILED. 200 passed; 1 failed");
+        assert!(!summary.iter().any(|line| line.contains("passing_")));
+        assert!(!summary.iter().any(|line| line.contains("Compiling")));
+    }
+
+    #[test]
+    fn test_is_noise_only_drops_passing_test_lines() {
+        assert!(is_noise("test parse::works ... ok"));
+        assert!(is_noise("test_parse.py::test_works PASSED"));
+        assert!(!is_noise("error: linking with `cc` failed: exit status: 1, ok"));
+        assert!(!is_noise("Cache status: ok"));
+    }
+
+    #[test]
+    fn test_summarize_output_respects_character_budget() {
+        let output = (0..100).map(|i| format!("{i}: {}", "x".repeat(100))).collect::<Vec<_>>();
+        let summary = summarize_output(&output.join("\n"), 100, 1000);
+        let kept: usize = summary.iter().filter(|l| !l.starts_with("...")).map(String::len).sum();
+        assert!(kept <= 1000);
+        assert!(summary.last().unwrap().starts_with("99: "));
+    }
+
+    #[test]
+    fn test_fit_messages_keeps_start_and_end() {
+        let mut messages: Vec<String> = (0..100).map(|i| format!("message {i:03}")).collect();
+        fit_messages(&mut messages, 200);
+        assert_eq!(messages[0], "message 000");
+        assert_eq!(messages.last().unwrap(), "message 099");
+        assert!(messages.iter().any(|m| m.contains("lines omitted to fit the context budget")));
+        assert!(messages.iter().map(|m| m.len() + 1).sum::<usize>() <= 250);
+
+        let mut short = vec!["a".to_string(), "b".to_string()];
+        fit_messages(&mut short, 0);
+        assert_eq!(short, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("short", 10), "short");
+        assert_eq!(truncate("héllo", 2), "h\n... (truncated)");
+        assert_eq!(truncate("unlimited", 0), "unlimited");
+    }
+}
@@ -0,0 +1,303 @@
+//! Project-wide definition of done, checked when work items are completed.
+//!
+//! The `definition_of_done` config setting lists what must hold before
+//! `work update --status complete` succeeds. The built-in items are verified
+//! from the touched-files journal and git state:
+//!
+//! | Item | Verified when |
+//! |------|---------------|
+//! | `tests_updated` | a test file was changed |
+//! | `docs_touched` | a documentation file was changed |
+//! | `quality_check_green` | the quality check passed after the last edit |
+//! | `pushed` | the working tree is clean and the branch is not ahead of its upstream |
+//!
+//! Any other item is a free-form statement that can only be acknowledged.
+//! Items that are not verified must be acknowledged with `--confirm-dod <item>`,
+//! and each acknowledgment is recorded in the audit log.
+
+use crate::config::ProjectConfig;
+use crate::error::Result;
+use crate::git::{self, UpstreamStatus};
+use crate::session;
+use crate::traits::CommandRunner;
+use std::collections::BTreeSet;
+use std::path::Path;
+
+/// Audit log operation recording an acknowledged definition-of-done item.
+pub const CONFIRM_OPERATION: &str = "confirm_dod";
+
+/// One item of the definition of done.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DodItem {
+    /// A test file was changed.
+    TestsUpdated,
+    /// A documentation file was changed.
+    DocsTouched,
+    /// The quality check passed after the last edit.
+    QualityCheckGreen,
+    /// All changes are committed and pushed.
+    Pushed,
+    /// A statement that can only be acknowledged.
+    Manual(String),
+}
+
+impl DodItem {
+    /// Parse an item from the config; unknown names are manual items.
+    #[must_use]
+    pub fn parse(name: &str) -> Self {
+        match name.trim().to_lowercase().replace('-', "_").as_str() {
+            "tests_updated" => Self::TestsUpdated,
+            "docs_touched" => Self::DocsTouched,
+            "quality_check_green" => Self::QualityCheckGreen,
+            "pushed" => Self::Pushed,
+            _ => Self::Manual(name.trim().to_string()),
+        }
+    }
+
+    /// The name used in messages and with `--confirm-dod`.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        match self {
+            Self::TestsUpdated => "tests_updated",
+            Self::DocsTouched => "docs_touched",
+            Self::QualityCheckGreen => "quality_check_green",
+            Self::Pushed => "pushed",
+            Self::Manual(text) => text,
+        }
+    }
+
+    /// Why the evidence does not show this item holds, or `None` if it does.
+    #[must_use]
+    pub fn unverified_reason(&self, evidence: &Evidence) -> Option<&'static str> {
+        match self {
+            Self::TestsUpdated => (!evidence.changed_files.iter().any(|f| is_test_file(f)))
+                .then_some("no test files were changed"),
+            Self::DocsTouched => (!evidence.changed_files.iter().any(|f| is_doc_file(f)))
+                .then_some("no documentation files were changed"),
+            Self::QualityCheckGreen => match evidence.quality_check_passed {
+                Some(true) => None,
+                Some(false) => Some("the quality check has not passed since the last edit"),
+                None => Some("no quality check is configured"),
+            },
+            Self::Pushed => match evidence.pushed {
+                Some(true) => None,
+                Some(false) => Some("there are uncommitted or unpushed changes"),
+                None => Some("the push state could not be determined"),
+            },
+            Self::Manual(_) => Some("needs acknowledging"),
+        }
+    }
+
+    fn matches(&self, name: &str) -> bool {
+        match self {
+            Self::Manual(text) => text.eq_ignore_ascii_case(name.trim()),
+            _ => *self == Self::parse(name),
+        }
+    }
+}
+
+/// What is known about the work when it is completed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Evidence {
+    /// Files changed since the last push, or since validation last passed.
+    pub changed_files: Vec<String>,
+    /// Whether the quality check passed after the last edit; `None` if no
+    /// check is configured.
+    pub quality_check_passed: Option<bool>,
+    /// Whether everything is committed and pushed; `None` outside a git
+    /// repository or when the upstream is unknown.
+    pub pushed: Option<bool>,
+}
+
+/// Collect evidence from the touched-files journal, validation marker and git.
+///
+/// # Errors
+///
+/// Returns an error if git commands cannot be run.
+pub fn gather_evidence(
+    base_dir: &Path,
+    config: &ProjectConfig,
+    runner: &dyn CommandRunner,
+) -> Result<Evidence> {
+    let mut changed: BTreeSet<String> =
+        session::get_touched_files(base_dir).unwrap_or_default().into_iter().collect();
+
+    let has_check = config.check_command.is_some()
+        || config.packages.iter().any(|p| !p.check_commands.is_empty());
+    let quality_check_passed = has_check.then(|| !session::needs_validation(base_dir));
+
+    let pushed = if git::is_git_repo(runner) {
+        let status = git::check_uncommitted_changes(runner)?;
+        let dirty = status.uncommitted.has_changes();
+        changed.extend(status.unstaged_files);
+        changed.extend(status.staged_files);
+        changed.extend(status.untracked_files);
+        changed.extend(git::unpushed_files(runner)?);
+        match git::upstream_status(runner)? {
+            UpstreamStatus::Tracking { ahead, .. } => Some(!dirty && ahead == 0),
+            UpstreamStatus::Missing { unpushed } => Some(!dirty && unpushed == 0),
+            UpstreamStatus::Unknown => dirty.then_some(false),
+        }
+    } else {
+        None
+    };
+
+    Ok(Evidence { changed_files: changed.into_iter().collect(), quality_check_passed, pushed })
+}
+
+/// Check the definition of done against the evidence and acknowledgments.
+///
+/// Returns the names of the acknowledged items to record, or a message listing
+/// the items that are neither verified nor acknowledged (and any
+/// acknowledgments that match no item).
+///
+/// # Errors
+///
+/// Returns the message if the definition of done is not met.
+pub fn check(
+    items: &[String],
+    evidence: &Evidence,
+    confirmed: &[String],
+) -> std::result::Result<Vec<String>, String> {
+    let items: Vec<DodItem> = items.iter().map(|name| DodItem::parse(name)).collect();
+    let mut problems = Vec::new();
+
+    for name in confirmed {
+        if !items.iter().any(|item| item.matches(name)) {
+            problems.push(format!("  - {name}: not part of the definition of done"));
+        }
+    }
+
+    let mut acknowledged = Vec::new();
+    for item in &items {
+        if confirmed.iter().any(|name| item.matches(name)) {
+            acknowledged.push(item.name().to_string());
+        } else if let Some(reason) = item.unverified_reason(evidence) {
+            problems.push(format!("  - {}: {reason}", item.name()));
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(acknowledged)
+    } else {
+        Err(format!(
+            "Definition of done not met:\n{}\n\nFinish these, or acknowledge the ones that hold \
+             with --confirm-dod <item>.",
+            problems.join("\n")
+        ))
+    }
+}
+
+/// Whether a path looks like a test file.
+fn is_test_file(path: &str) -> bool {
+    let path = path.to_lowercase();
+    let mut components = path.split('/');
+    let file = components.next_back().unwrap_or_default();
+    let stem = file.split('.').next().unwrap_or_default();
+    components.any(|dir| matches!(dir, "test" | "tests" | "spec" | "specs" | "__tests__"))
+        || stem.starts_with("test_")
+        || stem.ends_with("_test")
+        || stem.ends_with("_spec")
+        || stem == "tests"
+        || file.contains(".test.")
+        || file.contains(".spec.")
+}
+
+/// Whether a path looks like a documentation file.
+fn is_doc_file(path: &str) -> bool {
+    let path = path.to_lowercase();
+    path.starts_with("docs/")
+        || path.starts_with("doc/")
+        || [".md", ".rst", ".adoc", ".txt"].iter().any(|ext| path.ends_with(ext))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockCommandRunner;
+    use crate::traits::CommandOutput;
+    use tempfile::TempDir;
+
+    fn names(items: &[&str]) -> Vec<String> {
+        items.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_parse_item() {
+        assert_eq!(DodItem::parse("tests-updated"), DodItem::TestsUpdated);
+        assert_eq!(DodItem::parse("Pushed"), DodItem::Pushed);
+        assert_eq!(
+            DodItem::parse(" Changelog entry written "),
+            DodItem::Manual("Changelog entry written".to_string())
+        );
+        assert_eq!(DodItem::parse("quality_check_green").name(), "quality_check_green");
+    }
+
+    #[test]
+    fn test_file_kinds() {
+        assert!(is_test_file("src/cli/tests.rs"));
+        assert!(is_test_file("tests/integration.rs"));
+        assert!(is_test_file("pkg/parser_test.go"));
+        assert!(is_test_file("web/app.spec.ts"));
+        assert!(!is_test_file("src/contest.rs"));
+        assert!(is_doc_file("README.md"));
+        assert!(is_doc_file("docs/guide.html"));
+        assert!(!is_doc_file("src/lib.rs"));
+    }
+
+    #[test]
+    fn test_check_verified_items() {
+        let evidence = Evidence {
+            changed_files: names(&["src/lib.rs", "tests/lib.rs", "README.md"]),
+            quality_check_passed: Some(true),
+            pushed: Some(true),
+        };
+        let items = names(&["tests_updated", "docs_touched", "quality_check_green", "pushed"]);
+        assert_eq!(check(&items, &evidence, &[]), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_check_requires_acknowledgments() {
+        let evidence = Evidence { changed_files: names(&["src/lib.rs"]), ..Default::default() };
+        let items = names(&["tests_updated", "pushed", "Changelog updated"]);
+
+        let message = check(&items, &evidence, &[]).unwrap_err();
+        assert!(message.contains("tests_updated: no test files were changed"), "{message}");
+        assert!(message.contains("pushed: the push state could not be determined"), "{message}");
+        assert!(message.contains("Changelog updated: needs acknowledging"), "{message}");
+
+        let confirmed = names(&["tests-updated", "pushed", "changelog updated"]);
+        assert_eq!(
+            check(&items, &evidence, &confirmed),
+            Ok(names(&["tests_updated", "pushed", "Changelog updated"]))
+        );
+
+        let message = check(&items, &evidence, &names(&["docs_touched"])).unwrap_err();
+        assert!(message.contains("docs_touched: not part of the definition of done"));
+    }
+
+    #[test]
+    fn test_gather_evidence_outside_git() {
+        let dir = TempDir::new().unwrap();
+        session::record_touched_file(dir.path(), "tests/new.rs").unwrap();
+        session::set_needs_validation(dir.path()).unwrap();
+        let mut runner = MockCommandRunner::new();
+        for _ in 0..2 {
+            runner.expect(
+                "git",
+                &["rev-parse", "--git-dir"],
+                CommandOutput { exit_code: 128, ..Default::default() },
+            );
+        }
+        let config =
+            ProjectConfig { check_command: Some("just check".to_string()), ..Default::default() };
+
+        let evidence = gather_evidence(dir.path(), &config, &runner).unwrap();
+        assert_eq!(evidence.changed_files, names(&["tests/new.rs"]));
+        assert_eq!(evidence.quality_check_passed, Some(false));
+        assert_eq!(evidence.pushed, None);
+
+        let evidence = gather_evidence(dir.path(), &ProjectConfig::default(), &runner);
+        assert_eq!(evidence.unwrap().quality_check_passed, None);
+    }
+}
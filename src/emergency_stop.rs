@@ -0,0 +1,491 @@
+//! Policy for the `emergency-stop` command.
+//!
+//! An emergency stop lets the agent stop when it is genuinely blocked, after a
+//! sub-agent agrees that it is. Because an agent could repeat the request until
+//! the sub-agent gives in, requests are limited per session, must name a
+//! concrete kind of blocker, and rejections are recorded in the audit log.
+//! Once the agent has been rejected several times in a row, the request is
+//! turned into a question for the user instead.
+
+use crate::config::FailureMode;
+use crate::error::Result;
+use crate::session;
+use crate::tasks::{SqliteTaskStore, TaskStore};
+use crate::traits::{EmergencyStopContext, EmergencyStopDecision, SubAgent};
+use std::path::Path;
+
+/// Default maximum number of emergency stop requests per session in an hour.
+pub const MAX_EMERGENCY_STOPS_PER_HOUR: u32 = 3;
+
+/// Default number of consecutive rejections after which a question is filed.
+pub const REJECTIONS_BEFORE_QUESTION: u32 = 3;
+
+/// Session ID used when no hook has recorded a session yet.
+const UNKNOWN_SESSION: &str = "unknown";
+
+/// A kind of blocker that justifies an emergency stop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockerCategory {
+    /// Missing credentials, keys or permissions.
+    Credentials,
+    /// Tools, dependencies or resources the work needs are unavailable.
+    Environment,
+    /// The requirements are unclear and need a decision from the user.
+    Requirements,
+    /// An external service is failing or unreachable.
+    ExternalService,
+    /// A technical problem that reasonable attempts have not solved.
+    TechnicalProblem,
+}
+
+impl BlockerCategory {
+    /// All categories, in the order they are checked.
+    pub const ALL: [Self; 5] = [
+        Self::Credentials,
+        Self::Environment,
+        Self::Requirements,
+        Self::ExternalService,
+        Self::TechnicalProblem,
+    ];
+
+    /// The name recorded for the category, such as `credentials`.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Credentials => "credentials",
+            Self::Environment => "environment",
+            Self::Requirements => "requirements",
+            Self::ExternalService => "external_service",
+            Self::TechnicalProblem => "technical_problem",
+        }
+    }
+
+    /// A short description of the category.
+    #[must_use]
+    pub const fn description(self) -> &'static str {
+        match self {
+            Self::Credentials => "missing credentials or access",
+            Self::Environment => "unavailable tools, dependencies or resources",
+            Self::Requirements => "unclear requirements that need the user's decision",
+            Self::ExternalService => "an external service failing or unreachable",
+            Self::TechnicalProblem => "a technical problem that repeated attempts have not solved",
+        }
+    }
+
+    /// Lowercase words and phrases that mark an explanation as this category.
+    const fn keywords(self) -> &'static [&'static str] {
+        match self {
+            Self::Credentials => &[
+                "credential",
+                "api key",
+                "token",
+                "password",
+                "permission",
+                "access",
+                "unauthorized",
+                "forbidden",
+                "login",
+                "secret",
+            ],
+            Self::Environment => &[
+                "install",
+                "dependenc",
+                "toolchain",
+                "compiler",
+                "not available",
+                "command not found",
+                "disk space",
+                "out of memory",
+                "environment",
+            ],
+            Self::Requirements => &[
+                "requirement",
+                "unclear",
+                "ambiguous",
+                "clarif",
+                "decision",
+                "contradict",
+                "which approach",
+            ],
+            Self::ExternalService => &[
+                "outage",
+                "service",
+                "server",
+                "network",
+                "unreachable",
+                "rate limit",
+                "timed out",
+                "is down",
+            ],
+            Self::TechnicalProblem => {
+                &["tried", "attempted", "attempts", "stuck", "no progress", "cannot figure"]
+            }
+        }
+    }
+
+    /// Find the category an explanation refers to, if any.
+    #[must_use]
+    pub fn detect(explanation: &str) -> Option<Self> {
+        let lower = explanation.to_lowercase();
+        Self::ALL.into_iter().find(|category| category.keywords().iter().any(|k| lower.contains(k)))
+    }
+}
+
+/// Limits on emergency stop requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EmergencyStopLimits {
+    /// Maximum requests per session in an hour (0 = unlimited).
+    pub max_per_hour: u32,
+    /// Consecutive rejections after which a question is filed (0 = never).
+    pub rejections_before_question: u32,
+}
+
+impl Default for EmergencyStopLimits {
+    fn default() -> Self {
+        Self {
+            max_per_hour: MAX_EMERGENCY_STOPS_PER_HOUR,
+            rejections_before_question: REJECTIONS_BEFORE_QUESTION,
+        }
+    }
+}
+
+/// The result of an emergency stop request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EmergencyStopOutcome {
+    /// The stop was accepted, optionally with a message from the sub-agent.
+    Accepted(Option<String>),
+    /// The stop was accepted because the sub-agent could not be asked.
+    AcceptedUnevaluated,
+    /// The stop was rejected, with instructions for the agent.
+    Rejected(String),
+    /// The agent was rejected repeatedly, so a question was filed for the user.
+    Escalated {
+        /// ID of the question filed for the user.
+        question_id: String,
+        /// Why the latest request was rejected.
+        instructions: String,
+    },
+}
+
+/// Evaluate an emergency stop request, enforcing the limits.
+///
+/// Accepted stops set the emergency stop marker. Rejections are written to the
+/// audit log, and after `limits.rejections_before_question` consecutive
+/// rejections a question containing the explanation is filed for the user.
+/// If the sub-agent cannot evaluate the request, it is accepted unevaluated
+/// when `failure_mode` is open and rejected with the error when closed.
+///
+/// # Errors
+///
+/// Returns an error if the session or work item database cannot be used.
+pub fn request_emergency_stop(
+    base_dir: &Path,
+    explanation: &str,
+    limits: EmergencyStopLimits,
+    failure_mode: FailureMode,
+    sub_agent: &dyn SubAgent,
+    store: &SqliteTaskStore,
+) -> Result<EmergencyStopOutcome> {
+    let session_id = current_session_id(base_dir);
+
+    let decision = if limits.max_per_hour > 0
+        && session::emergency_stop_attempts_last_hour(base_dir, &session_id)? >= limits.max_per_hour
+    {
+        Ok(EmergencyStopDecision::Reject(format!(
+            "You have already requested {} emergency stops in the last hour. \
+             Keep working on what you can, or ask the user a question instead.",
+            limits.max_per_hour
+        )))
+    } else if let Some(category) = BlockerCategory::detect(explanation) {
+        let context = EmergencyStopContext {
+            explanation: explanation.to_string(),
+            category: category.description().to_string(),
+        };
+        sub_agent.evaluate_emergency_stop(&context).or_else(|e| {
+            if failure_mode.allows() {
+                Err(e)
+            } else {
+                Ok(EmergencyStopDecision::Reject(format!(
+                    "The request could not be evaluated ({e}), and failure_mode is closed. \
+                     Keep working on what you can, or ask the user a question instead."
+                )))
+            }
+        })
+    } else {
+        Ok(EmergencyStopDecision::Reject(missing_category_instructions()))
+    };
+
+    let instructions = match decision {
+        Ok(EmergencyStopDecision::Reject(instructions)) => instructions,
+        accepted => {
+            session::record_emergency_stop_attempt(base_dir, &session_id, "accepted")?;
+            session::set_emergency_stop(base_dir)?;
+            return Ok(match accepted {
+                Ok(EmergencyStopDecision::Accept(message)) => {
+                    EmergencyStopOutcome::Accepted(message)
+                }
+                _ => EmergencyStopOutcome::AcceptedUnevaluated,
+            });
+        }
+    };
+
+    session::record_emergency_stop_attempt(base_dir, &session_id, "rejected")?;
+    let details = serde_json::json!({
+        "session_id": session_id,
+        "explanation": explanation,
+        "instructions": instructions,
+    });
+    store.record_event("emergency_stop_rejected", &details.to_string())?;
+
+    if limits.rejections_before_question == 0
+        || session::emergency_stop_rejection_streak(base_dir, &session_id)?
+            < limits.rejections_before_question
+    {
+        return Ok(EmergencyStopOutcome::Rejected(instructions));
+    }
+
+    let question = store.create_question(&format!(
+        "The agent has repeatedly asked to stop working and was refused. \
+         Should it stop? Its latest explanation: {explanation}"
+    ))?;
+    session::record_emergency_stop_attempt(base_dir, &session_id, "escalated")?;
+    Ok(EmergencyStopOutcome::Escalated { question_id: question.id, instructions })
+}
+
+/// Instructions for an explanation that names no kind of blocker.
+fn missing_category_instructions() -> String {
+    let categories: Vec<&str> =
+        BlockerCategory::ALL.iter().map(|category| category.description()).collect();
+    format!(
+        "Your explanation does not describe a concrete blocker. An emergency stop needs one of: {}. \
+         Explain specifically what is blocking you, or keep working.",
+        categories.join("; ")
+    )
+}
+
+/// The most recently active session, which is the one asking to stop.
+#[must_use]
+pub fn current_session_id(base_dir: &Path) -> String {
+    session::list_sessions(base_dir)
+        .ok()
+        .and_then(|sessions| sessions.into_iter().next())
+        .map_or_else(|| UNKNOWN_SESSION.to_string(), |session| session.session_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::paths;
+    use crate::testing::{FailingSubAgent, MockSubAgent};
+    use tempfile::TempDir;
+
+    fn create_store(dir: &Path) -> SqliteTaskStore {
+        SqliteTaskStore::new(paths::project_db_path(dir)).unwrap()
+    }
+
+    #[test]
+    fn test_detect_category() {
+        assert_eq!(
+            BlockerCategory::detect("The deploy needs an API key I don't have"),
+            Some(BlockerCategory::Credentials)
+        );
+        assert_eq!(
+            BlockerCategory::detect("The requirements are ambiguous about the date format"),
+            Some(BlockerCategory::Requirements)
+        );
+        assert_eq!(
+            BlockerCategory::detect("The package registry is unreachable"),
+            Some(BlockerCategory::ExternalService)
+        );
+        assert_eq!(BlockerCategory::detect("There is too much work left"), None);
+    }
+
+    #[test]
+    fn test_accepted_sets_marker() {
+        let dir = TempDir::new().unwrap();
+        let store = create_store(dir.path());
+        let mut sub_agent = MockSubAgent::new();
+        sub_agent.expect_emergency_stop(EmergencyStopDecision::Accept(Some("ok".to_string())));
+
+        let outcome = request_emergency_stop(
+            dir.path(),
+            "I need the database password",
+            EmergencyStopLimits::default(),
+            FailureMode::Open,
+            &sub_agent,
+            &store,
+        )
+        .unwrap();
+        assert_eq!(outcome, EmergencyStopOutcome::Accepted(Some("ok".to_string())));
+        assert!(session::is_emergency_stop_active(dir.path()));
+    }
+
+    #[test]
+    fn test_sub_agent_failure_accepts() {
+        let dir = TempDir::new().unwrap();
+        let store = create_store(dir.path());
+
+        let outcome = request_emergency_stop(
+            dir.path(),
+            "I need the database password",
+            EmergencyStopLimits::default(),
+            FailureMode::Open,
+            &FailingSubAgent::new("unavailable"),
+            &store,
+        )
+        .unwrap();
+        assert_eq!(outcome, EmergencyStopOutcome::AcceptedUnevaluated);
+    }
+
+    #[test]
+    fn test_sub_agent_failure_rejects_when_failing_closed() {
+        let dir = TempDir::new().unwrap();
+        let store = create_store(dir.path());
+
+        let outcome = request_emergency_stop(
+            dir.path(),
+            "I need the database password",
+            EmergencyStopLimits::default(),
+            FailureMode::Closed,
+            &FailingSubAgent::new("unavailable"),
+            &store,
+        )
+        .unwrap();
+        let EmergencyStopOutcome::Rejected(instructions) = outcome else {
+            panic!("expected rejection, got {outcome:?}");
+        };
+        assert!(instructions.contains("could not be evaluated"), "{instructions}");
+        assert!(instructions.contains("unavailable"), "{instructions}");
+        assert!(!session::is_emergency_stop_active(dir.path()));
+    }
+
+    #[test]
+    fn test_missing_category_rejected_and_audited() {
+        let dir = TempDir::new().unwrap();
+        let store = create_store(dir.path());
+
+        let outcome = request_emergency_stop(
+            dir.path(),
+            "There is too much work left",
+            EmergencyStopLimits::default(),
+            FailureMode::Open,
+            &MockSubAgent::new(),
+            &store,
+        )
+        .unwrap();
+        let EmergencyStopOutcome::Rejected(instructions) = outcome else {
+            panic!("expected rejection, got {outcome:?}");
+        };
+        assert!(instructions.contains("missing credentials or access"));
+        assert!(!session::is_emergency_stop_active(dir.path()));
+
+        let log = store.get_audit_log(None, None).unwrap();
+        assert_eq!(log[0].operation, "emergency_stop_rejected");
+        assert!(log[0].details.as_deref().unwrap().contains("too much work"));
+    }
+
+    #[test]
+    fn test_rate_limit_rejects_without_evaluation() {
+        let dir = TempDir::new().unwrap();
+        let store = create_store(dir.path());
+        let limits = EmergencyStopLimits { max_per_hour: 1, rejections_before_question: 0 };
+        let mut sub_agent = MockSubAgent::new();
+        sub_agent.expect_emergency_stop(EmergencyStopDecision::Reject("keep going".to_string()));
+
+        let explanation = "The CI server is down";
+        let first = request_emergency_stop(
+            dir.path(),
+            explanation,
+            limits,
+            FailureMode::Open,
+            &sub_agent,
+            &store,
+        );
+        assert_eq!(first.unwrap(), EmergencyStopOutcome::Rejected("keep going".to_string()));
+
+        // The mock has no more responses, so the sub-agent must not be asked again
+        let second = request_emergency_stop(
+            dir.path(),
+            explanation,
+            limits,
+            FailureMode::Open,
+            &sub_agent,
+            &store,
+        )
+        .unwrap();
+        let EmergencyStopOutcome::Rejected(instructions) = second else {
+            panic!("expected rejection, got {second:?}");
+        };
+        assert!(instructions.contains("1 emergency stops in the last hour"));
+    }
+
+    #[test]
+    fn test_repeated_rejections_file_question() {
+        let dir = TempDir::new().unwrap();
+        let store = create_store(dir.path());
+        let limits = EmergencyStopLimits { max_per_hour: 0, rejections_before_question: 2 };
+
+        let explanation = "Too much to do";
+        let first = request_emergency_stop(
+            dir.path(),
+            explanation,
+            limits,
+            FailureMode::Open,
+            &MockSubAgent::new(),
+            &store,
+        );
+        assert!(matches!(first.unwrap(), EmergencyStopOutcome::Rejected(_)));
+
+        let second = request_emergency_stop(
+            dir.path(),
+            explanation,
+            limits,
+            FailureMode::Open,
+            &MockSubAgent::new(),
+            &store,
+        )
+        .unwrap();
+        let EmergencyStopOutcome::Escalated { question_id, .. } = second else {
+            panic!("expected escalation, got {second:?}");
+        };
+        let question = store.get_question(&question_id).unwrap().unwrap();
+        assert!(question.text.contains("Too much to do"));
+
+        // Escalating starts a new streak
+        let third = request_emergency_stop(
+            dir.path(),
+            explanation,
+            limits,
+            FailureMode::Open,
+            &MockSubAgent::new(),
+            &store,
+        );
+        assert!(matches!(third.unwrap(), EmergencyStopOutcome::Rejected(_)));
+    }
+
+    #[test]
+    fn test_escalation_does_not_count_toward_hourly_limit() {
+        let dir = TempDir::new().unwrap();
+        let store = create_store(dir.path());
+        let limits = EmergencyStopLimits { max_per_hour: 3, rejections_before_question: 2 };
+        let request = || {
+            request_emergency_stop(
+                dir.path(),
+                "Too much to do",
+                limits,
+                FailureMode::Open,
+                &MockSubAgent::new(),
+                &store,
+            )
+            .unwrap()
+        };
+
+        assert!(matches!(request(), EmergencyStopOutcome::Rejected(_)));
+        assert!(matches!(request(), EmergencyStopOutcome::Escalated { .. }));
+        // Two requests so far, so the third is still evaluated
+        let EmergencyStopOutcome::Rejected(instructions) = request() else {
+            panic!("expected rejection");
+        };
+        assert!(instructions.contains("concrete blocker"), "{instructions}");
+    }
+}
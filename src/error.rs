@@ -1,7 +1,22 @@
 //! Error types for `claude_reliability`.
+//!
+//! [`Error::exit_code`] and [`Error::user_message`] are the one place errors are
+//! turned into process exit codes and the messages shown to users.
 
 use std::path::PathBuf;
 
+/// Exit code for errors without a more specific code.
+pub const EXIT_FAILURE: u8 = 1;
+
+/// Exit code for configuration errors.
+pub const EXIT_CONFIG: u8 = 3;
+
+/// Exit code for database errors.
+pub const EXIT_DATABASE: u8 = 4;
+
+/// Exit code for commands that could not be run or failed.
+pub const EXIT_COMMAND: u8 = 5;
+
 /// Errors that can occur in the reliability hooks.
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -36,6 +51,16 @@ pub enum Error {
         stderr: String,
     },
 
+    /// A command could not be started.
+    #[error("Could not run '{command}': {source}")]
+    CommandSpawn {
+        /// The command that was run.
+        command: String,
+        /// Why it could not be started.
+        #[source]
+        source: std::io::Error,
+    },
+
     /// A command timed out.
     #[error("Command '{command}' timed out after {timeout_secs} seconds")]
     CommandTimeout {
@@ -45,10 +70,39 @@ pub enum Error {
         timeout_secs: u64,
     },
 
+    /// A database could not be opened.
+    #[error("Could not open database {}: {source}", path.display())]
+    DatabaseOpen {
+        /// Path to the database file.
+        path: PathBuf,
+        /// Why it could not be opened.
+        #[source]
+        source: rusqlite::Error,
+    },
+
+    /// A file or directory could not be read or written.
+    #[error("Could not access {}: {source}", path.display())]
+    FileAccess {
+        /// The file or directory.
+        path: PathBuf,
+        /// Why it could not be accessed.
+        #[source]
+        source: std::io::Error,
+    },
+
     /// A file was not found.
     #[error("File not found: {0}")]
     FileNotFound(PathBuf),
 
+    /// A file cannot be restored as the database.
+    #[error("{} is not a usable backup: {reason}", path.display())]
+    InvalidBackup {
+        /// The file.
+        path: PathBuf,
+        /// Why it cannot be used.
+        reason: String,
+    },
+
     /// Invalid transcript format.
     #[error("Invalid transcript format: {0}")]
     InvalidTranscript(String),
@@ -72,7 +126,112 @@ pub enum Error {
     /// A task-related error occurred.
     #[error("{0}")]
     Task(#[source] Box<dyn std::error::Error + Send + Sync>),
+
+    /// An error, with what was being done when it happened.
+    #[error("Failed to {action}: {source}")]
+    Context {
+        /// What was being done, e.g. "clear the reflection marker".
+        action: String,
+        /// The underlying error.
+        #[source]
+        source: Box<Self>,
+    },
+}
+
+impl Error {
+    /// Wrap the error with what was being done when it happened.
+    #[must_use]
+    pub fn context(self, action: impl Into<String>) -> Self {
+        Self::Context { action: action.into(), source: Box::new(self) }
+    }
+
+    /// The process exit code for this error.
+    ///
+    /// Never 2, which tells Claude Code that a hook blocked the action.
+    #[must_use]
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Self::Context { source, .. } => source.exit_code(),
+            Self::Yaml(_) | Self::Config(_) => EXIT_CONFIG,
+            Self::Database(_) | Self::DatabaseOpen { .. } | Self::InvalidBackup { .. } => {
+                EXIT_DATABASE
+            }
+            Self::CommandFailed { .. }
+            | Self::CommandSpawn { .. }
+            | Self::CommandTimeout { .. }
+            | Self::Git(_) => EXIT_COMMAND,
+            _ => EXIT_FAILURE,
+        }
+    }
+
+    /// The message to show the user when `doing` failed with this error.
+    #[must_use]
+    pub fn user_message(&self, doing: &str) -> String {
+        let hint = match self.exit_code() {
+            EXIT_CONFIG => "\nCheck .claude/reliability-config.yaml.",
+            EXIT_DATABASE => {
+                "\nRun `claude-reliability maintenance run` if the database is damaged, or \
+                 `claude-reliability backup restore <file>` to go back to a backup."
+            }
+            _ => "",
+        };
+        format!("Error {doing}: {self}{hint}")
+    }
+}
+
+/// Add context to the error of a result.
+pub trait ResultExt<T> {
+    /// Wrap any error with what was being done when it happened.
+    ///
+    /// # Errors
+    ///
+    /// Returns the original error, wrapped in [`Error::Context`].
+    fn context(self, action: impl Into<String>) -> Result<T>;
+}
+
+impl<T, E: Into<Error>> ResultExt<T> for std::result::Result<T, E> {
+    fn context(self, action: impl Into<String>) -> Result<T> {
+        self.map_err(|e| e.into().context(action))
+    }
 }
 
 /// A specialized Result type for this crate.
 pub type Result<T> = std::result::Result<T, Error>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_context_message_and_exit_code() {
+        let error = Error::Config("no check command".to_string());
+        let error: Result<()> = Err(error).context("load the config");
+        let error = error.unwrap_err();
+        assert_eq!(error.to_string(), "Failed to load the config: Config error: no check command");
+        assert_eq!(error.exit_code(), EXIT_CONFIG);
+        assert_eq!(
+            error.user_message("running stop hook"),
+            "Error running stop hook: Failed to load the config: Config error: no check command\n\
+             Check .claude/reliability-config.yaml."
+        );
+    }
+
+    #[test]
+    fn test_exit_codes() {
+        let io = || std::io::Error::from(std::io::ErrorKind::NotFound);
+        let spawn = Error::CommandSpawn { command: "git status".to_string(), source: io() };
+        assert_eq!(spawn.to_string(), "Could not run 'git status': entity not found");
+        assert_eq!(spawn.exit_code(), EXIT_COMMAND);
+        let open = Error::DatabaseOpen {
+            path: PathBuf::from("/tmp/db"),
+            source: rusqlite::Error::InvalidQuery,
+        };
+        assert_eq!(open.exit_code(), EXIT_DATABASE);
+        assert!(open.user_message("opening").contains("maintenance run"));
+        assert_eq!(Error::Io(io()).exit_code(), EXIT_FAILURE);
+        assert_eq!(
+            Error::Io(io()).user_message("reading"),
+            "Error reading: I/O error: entity not found"
+        );
+    }
+}
@@ -0,0 +1,311 @@
+//! Reporting blockers to the team's normal triage process.
+//!
+//! When `escalation` is configured, accepted emergency stops and problems
+//! explained in problem mode are reported as a GitHub issue (with the `gh`
+//! CLI), appended to a markdown file such as `BLOCKERS.md`, or both. Each
+//! report has the agent's explanation, its session and the work items in
+//! progress. Reports are recorded in the audit log.
+
+use crate::config::EscalationPolicy;
+use crate::tasks::{self, TaskStore};
+use crate::traits::CommandRunner;
+use chrono::Utc;
+use std::io::Write;
+use std::path::Path;
+
+/// Audit log operation for reported blockers.
+pub const ESCALATION_OPERATION: &str = "escalate";
+
+/// Maximum length of the explanation in an issue title.
+const TITLE_TEXT_CHARS: usize = 60;
+
+/// A blocker the agent could not get past.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Blocker {
+    /// What reported it: `emergency stop` or `problem mode`.
+    pub source: &'static str,
+    /// The kind of blocker, such as `credentials`.
+    pub category: String,
+    /// The agent's explanation.
+    pub explanation: String,
+    /// The session that ran into it.
+    pub session_id: String,
+    /// IDs of the work items in progress.
+    pub task_ids: Vec<String>,
+}
+
+impl Blocker {
+    /// Describe a blocker, linked to the work items now in progress.
+    #[must_use]
+    pub fn new(
+        base_dir: &Path,
+        source: &'static str,
+        category: &str,
+        explanation: &str,
+        session_id: &str,
+    ) -> Self {
+        let task_ids = tasks::open_project_store(base_dir)
+            .and_then(|store| store.get_in_progress_tasks().ok())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|task| task.id)
+            .collect();
+        Self {
+            source,
+            category: category.to_string(),
+            explanation: explanation.trim().to_string(),
+            session_id: session_id.to_string(),
+            task_ids,
+        }
+    }
+
+    /// A one-line title for the blocker.
+    fn title(&self) -> String {
+        let first_line = self.explanation.lines().next().unwrap_or_default();
+        let mut summary: String = first_line.chars().take(TITLE_TEXT_CHARS).collect();
+        if first_line.chars().count() > TITLE_TEXT_CHARS {
+            summary.push_str("...");
+        }
+        format!("Blocker ({}): {summary}", self.category)
+    }
+
+    /// The blocker's details as markdown.
+    fn body(&self) -> String {
+        let work_items = if self.task_ids.is_empty() {
+            "none".to_string()
+        } else {
+            self.task_ids.iter().map(|id| format!("`{id}`")).collect::<Vec<_>>().join(", ")
+        };
+        let quoted: Vec<String> = self
+            .explanation
+            .lines()
+            .map(|line| if line.is_empty() { ">".to_string() } else { format!("> {line}") })
+            .collect();
+        format!(
+            "- **Reported by:** {}\n- **Category:** {}\n- **Session:** `{}`\n- **Work items:** {work_items}\n\n{}",
+            self.source,
+            self.category,
+            self.session_id,
+            quoted.join("\n"),
+        )
+    }
+}
+
+/// Where a blocker was reported.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EscalationReport {
+    /// Where the blocker went: the issue URL and the blockers file.
+    pub reported_to: Vec<String>,
+    /// Problems with a destination. A failing destination does not stop the others.
+    pub errors: Vec<String>,
+}
+
+/// Report a blocker everywhere the policy asks for.
+///
+/// Does nothing when escalation is not configured.
+pub fn escalate(
+    base_dir: &Path,
+    policy: &EscalationPolicy,
+    runner: &dyn CommandRunner,
+    blocker: &Blocker,
+) -> EscalationReport {
+    let mut report = EscalationReport::default();
+    if policy.github_issues {
+        match open_issue(runner, policy, blocker) {
+            Ok(url) => report.reported_to.push(url),
+            Err(e) => report.errors.push(format!("github: {e}")),
+        }
+    }
+    if let Some(file) = &policy.blockers_file {
+        match append_blocker(&base_dir.join(file), blocker) {
+            Ok(()) => report.reported_to.push(file.clone()),
+            Err(e) => report.errors.push(format!("{file}: {e}")),
+        }
+    }
+
+    for error in &report.errors {
+        tracing::warn!("Failed to report blocker: {error}");
+    }
+    if !report.reported_to.is_empty() {
+        let details = format!("{}: {}", blocker.title(), report.reported_to.join(", "));
+        if let Some(Err(e)) = tasks::open_project_store(base_dir)
+            .map(|s| s.record_event(ESCALATION_OPERATION, &details))
+        {
+            tracing::warn!("Failed to record escalation: {e}");
+        }
+    }
+    report
+}
+
+/// Open a GitHub issue for the blocker, returning its URL.
+fn open_issue(
+    runner: &dyn CommandRunner,
+    policy: &EscalationPolicy,
+    blocker: &Blocker,
+) -> std::result::Result<String, String> {
+    let title = blocker.title();
+    let body = blocker.body();
+    let mut args = vec!["issue", "create", "--title", title.as_str(), "--body", body.as_str()];
+    if let Some(repo) = &policy.github_repo {
+        args.extend(["--repo", repo.as_str()]);
+    }
+    for label in &policy.github_labels {
+        args.extend(["--label", label.as_str()]);
+    }
+    let output = runner.run("gh", &args, None).map_err(|e| e.to_string())?;
+    if output.success() {
+        Ok(output.stdout.trim().to_string())
+    } else {
+        Err(format!("gh issue create failed: {}", output.combined_output().trim()))
+    }
+}
+
+/// Append the blocker to a markdown file, creating it if needed.
+fn append_blocker(path: &Path, blocker: &Blocker) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let new_file = !path.exists();
+    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+    if new_file {
+        writeln!(file, "# Blockers")?;
+    }
+    write!(
+        file,
+        "\n## {} {}\n\n{}\n",
+        Utc::now().format("%Y-%m-%d %H:%M UTC"),
+        blocker.title(),
+        blocker.body()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tasks::{Priority, SqliteTaskStore, TaskUpdate};
+    use crate::testing::MockCommandRunner;
+    use crate::traits::CommandOutput;
+    use tempfile::TempDir;
+
+    fn blocker() -> Blocker {
+        Blocker {
+            source: "emergency stop",
+            category: "credentials".to_string(),
+            explanation: "The deploy needs an API key.\n\nNone is configured.".to_string(),
+            session_id: "s1".to_string(),
+            task_ids: vec!["abc".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_blocker_links_work_in_progress() {
+        let dir = TempDir::new().unwrap();
+        let store = SqliteTaskStore::for_project(dir.path()).unwrap();
+        let task = store.create_task("Deploy", "", Priority::High).unwrap();
+        store.create_task("Later", "", Priority::Low).unwrap();
+        store
+            .update_task(&task.id, TaskUpdate { in_progress: Some(true), ..Default::default() })
+            .unwrap();
+
+        let blocker = Blocker::new(dir.path(), "problem mode", "other", " Stuck \n", "s2");
+        assert_eq!(blocker.task_ids, vec![task.id]);
+        assert_eq!(blocker.explanation, "Stuck");
+    }
+
+    #[test]
+    fn test_blocker_title_and_body() {
+        let mut blocker = blocker();
+        assert_eq!(blocker.title(), "Blocker (credentials): The deploy needs an API key.");
+        assert_eq!(
+            blocker.body(),
+            "- **Reported by:** emergency stop\n- **Category:** credentials\n- **Session:** `s1`\n\
+             - **Work items:** `abc`\n\n> The deploy needs an API key.\n>\n> None is configured."
+        );
+
+        blocker.explanation = "x".repeat(70);
+        assert_eq!(blocker.title(), format!("Blocker (credentials): {}...", "x".repeat(60)));
+        blocker.task_ids.clear();
+        assert!(blocker.body().contains("**Work items:** none"));
+    }
+
+    #[test]
+    fn test_escalate_opens_issue_and_appends_file() {
+        let dir = TempDir::new().unwrap();
+        SqliteTaskStore::for_project(dir.path()).unwrap();
+        let blocker = blocker();
+        let title = blocker.title();
+        let body = blocker.body();
+        let mut runner = MockCommandRunner::new();
+        runner.expect(
+            "gh",
+            &[
+                "issue", "create", "--title", &title, "--body", &body, "--repo", "acme/app",
+                "--label", "blocker",
+            ],
+            CommandOutput {
+                exit_code: 0,
+                stdout: "https://github.com/acme/app/issues/7\n".to_string(),
+                stderr: String::new(),
+            },
+        );
+        let policy = EscalationPolicy {
+            github_issues: true,
+            github_repo: Some("acme/app".to_string()),
+            github_labels: vec!["blocker".to_string()],
+            blockers_file: Some("docs/BLOCKERS.md".to_string()),
+        };
+
+        let report = escalate(dir.path(), &policy, &runner, &blocker);
+        assert_eq!(
+            report,
+            EscalationReport {
+                reported_to: vec![
+                    "https://github.com/acme/app/issues/7".to_string(),
+                    "docs/BLOCKERS.md".to_string()
+                ],
+                errors: vec![],
+            }
+        );
+        runner.verify();
+
+        escalate(
+            dir.path(),
+            &EscalationPolicy { github_issues: false, ..policy },
+            &runner,
+            &blocker,
+        );
+        let file = std::fs::read_to_string(dir.path().join("docs/BLOCKERS.md")).unwrap();
+        assert!(file.starts_with("# Blockers\n\n## "));
+        assert_eq!(file.matches("# Blockers").count(), 1);
+        assert_eq!(file.matches(&title).count(), 2);
+        assert!(file.contains("> None is configured.\n"));
+
+        let store = tasks::open_project_store(dir.path()).unwrap();
+        let log = store.get_audit_log(None, None).unwrap();
+        assert_eq!(log.iter().filter(|e| e.operation == ESCALATION_OPERATION).count(), 2);
+    }
+
+    #[test]
+    fn test_escalate_reports_failures() {
+        let dir = TempDir::new().unwrap();
+        let blocker = blocker();
+        let mut runner = MockCommandRunner::new();
+        runner.expect(
+            "gh",
+            &["issue", "create", "--title", &blocker.title(), "--body", &blocker.body()],
+            CommandOutput {
+                exit_code: 1,
+                stdout: String::new(),
+                stderr: "not logged in".to_string(),
+            },
+        );
+        let policy = EscalationPolicy { github_issues: true, ..Default::default() };
+
+        let report = escalate(dir.path(), &policy, &runner, &blocker);
+        assert!(report.reported_to.is_empty());
+        assert_eq!(report.errors, vec!["github: gh issue create failed: not logged in"]);
+
+        let report = escalate(dir.path(), &EscalationPolicy::default(), &runner, &blocker);
+        assert_eq!(report, EscalationReport::default());
+    }
+}
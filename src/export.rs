@@ -0,0 +1,412 @@
+//! Export the audit log and session data for analysis.
+//!
+//! `claude-reliability audit-log export` writes one file per table into a
+//! directory, so the data can be loaded into a notebook without querying the
+//! database directly:
+//!
+//! - `audit_log`: every audit log entry, oldest first.
+//! - `task_transitions`: work item status changes derived from the audit log
+//!   (creation, status updates and deletion).
+//! - `hook_events`: hook invocations from `hook-events.jsonl`, which is only
+//!   written when `debug_logging` is enabled.
+//! - `sessions`: the session registry, with each session's duration and
+//!   number of logged hook events.
+
+use crate::error::{Error, Result};
+use crate::hook_logging::HOOK_EVENTS_FILE;
+use crate::paths;
+use crate::storage::SqliteStore;
+use crate::tasks::{AuditEntry, SqliteTaskStore, TaskStore};
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Format of the timestamps in the session registry.
+const SESSION_TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// File format of an export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// Comma-separated values with a header row.
+    Csv,
+    /// One JSON object per line.
+    Jsonl,
+}
+
+impl ExportFormat {
+    /// The file extension for this format.
+    #[must_use]
+    pub const fn extension(self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Jsonl => "jsonl",
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = Error;
+
+    fn from_str(format: &str) -> Result<Self> {
+        match format.to_lowercase().as_str() {
+            "csv" => Ok(Self::Csv),
+            "jsonl" | "ndjson" => Ok(Self::Jsonl),
+            _ => Err(Error::Config(format!(
+                "invalid export format '{format}' (must be one of: csv, jsonl)"
+            ))),
+        }
+    }
+}
+
+/// A file written by an export.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ExportedFile {
+    /// The table the file holds.
+    pub table: String,
+    /// Path of the file.
+    pub path: String,
+    /// Number of rows written, not counting the header.
+    pub rows: usize,
+}
+
+/// A table of exported data.
+struct Table {
+    name: &'static str,
+    columns: &'static [&'static str],
+    rows: Vec<Vec<Value>>,
+}
+
+/// Export the audit log, task transitions, hook events and sessions into `output_dir`.
+///
+/// Existing files with the same names are overwritten.
+///
+/// # Errors
+///
+/// Returns an error if the database cannot be read or a file cannot be written.
+pub fn export(
+    base_dir: &Path,
+    format: ExportFormat,
+    output_dir: &Path,
+) -> Result<Vec<ExportedFile>> {
+    let task_store = SqliteTaskStore::new(paths::project_db_path(base_dir))?;
+    let mut audit = task_store.get_audit_log(None, None)?;
+    audit.sort_by_key(|entry| entry.id);
+    let events = read_hook_events(base_dir)?;
+    let sessions = session_rows(&SqliteStore::new(base_dir)?, &events)?;
+
+    let tables = [
+        Table {
+            name: "audit_log",
+            columns: &[
+                "id",
+                "timestamp",
+                "operation",
+                "task_id",
+                "old_value",
+                "new_value",
+                "details",
+            ],
+            rows: audit.iter().map(audit_row).collect(),
+        },
+        Table {
+            name: "task_transitions",
+            columns: &["audit_id", "timestamp", "task_id", "title", "from_status", "to_status"],
+            rows: audit.iter().filter_map(transition_row).collect(),
+        },
+        Table {
+            name: "hook_events",
+            columns: &["timestamp", "hook_type", "session_id", "tool_name", "input"],
+            rows: events,
+        },
+        Table {
+            name: "sessions",
+            columns: &[
+                "session_id",
+                "transcript_path",
+                "state",
+                "started_at",
+                "last_seen",
+                "duration_seconds",
+                "hook_events",
+            ],
+            rows: sessions,
+        },
+    ];
+
+    std::fs::create_dir_all(output_dir)
+        .map_err(|source| Error::FileAccess { path: output_dir.to_path_buf(), source })?;
+    tables.iter().map(|table| write_table(table, format, output_dir)).collect()
+}
+
+/// Write a table into `<output_dir>/<name>.<extension>`.
+fn write_table(table: &Table, format: ExportFormat, output_dir: &Path) -> Result<ExportedFile> {
+    let path = output_dir.join(format!("{}.{}", table.name, format.extension()));
+    let content = match format {
+        ExportFormat::Csv => to_csv(table),
+        ExportFormat::Jsonl => to_jsonl(table),
+    };
+    std::fs::write(&path, content)
+        .map_err(|source| Error::FileAccess { path: path.clone(), source })?;
+    Ok(ExportedFile {
+        table: table.name.to_string(),
+        path: path.display().to_string(),
+        rows: table.rows.len(),
+    })
+}
+
+fn to_csv(table: &Table) -> String {
+    let mut out = table.columns.join(",");
+    out.push('\n');
+    for row in &table.rows {
+        let cells: Vec<String> = row.iter().map(|cell| csv_field(&cell_text(cell))).collect();
+        let _ = writeln!(out, "{}", cells.join(","));
+    }
+    out
+}
+
+fn to_jsonl(table: &Table) -> String {
+    let mut out = String::new();
+    for row in &table.rows {
+        let object: serde_json::Map<String, Value> = table
+            .columns
+            .iter()
+            .map(|column| (*column).to_string())
+            .zip(row.iter().cloned())
+            .collect();
+        let _ = writeln!(out, "{}", Value::Object(object));
+    }
+    out
+}
+
+/// The text of a cell: empty for null, unquoted for strings.
+fn cell_text(cell: &Value) -> String {
+    match cell {
+        Value::Null => String::new(),
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Quote a CSV field if it contains a separator, quote or line break.
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+fn optional(value: Option<&str>) -> Value {
+    value.map_or(Value::Null, |text| Value::String(text.to_string()))
+}
+
+fn audit_row(entry: &AuditEntry) -> Vec<Value> {
+    vec![
+        entry.id.into(),
+        entry.timestamp.clone().into(),
+        entry.operation.clone().into(),
+        optional(entry.task_id.as_deref()),
+        optional(entry.old_value.as_deref()),
+        optional(entry.new_value.as_deref()),
+        optional(entry.details.as_deref()),
+    ]
+}
+
+/// A work item status change recorded in the audit log.
+pub(crate) struct StatusTransition {
+    /// The work item's title after the change (before it, for a deletion).
+    pub(crate) title: Option<String>,
+    /// The status before the change, or `None` for a new work item.
+    pub(crate) from: Option<String>,
+    /// The status after the change, or `deleted`.
+    pub(crate) to: String,
+}
+
+/// The status change an audit entry records, if it records one.
+pub(crate) fn status_transition(entry: &AuditEntry) -> Option<StatusTransition> {
+    let parse = |json: Option<&String>| -> Option<Value> { serde_json::from_str(json?).ok() };
+    let old = parse(entry.old_value.as_ref());
+    let new = parse(entry.new_value.as_ref());
+    let status = |task: Option<&Value>| {
+        task.and_then(|t| t.get("status")).and_then(Value::as_str).map(str::to_string)
+    };
+
+    let (from, to) = match entry.operation.as_str() {
+        "create" => (None, status(new.as_ref())?),
+        "update" => (Some(status(old.as_ref())?), status(new.as_ref())?),
+        "delete" => (Some(status(old.as_ref())?), "deleted".to_string()),
+        _ => return None,
+    };
+    if from.as_ref() == Some(&to) {
+        return None;
+    }
+    let title = new.as_ref().or(old.as_ref()).and_then(|t| t.get("title")?.as_str());
+    Some(StatusTransition { title: title.map(str::to_string), from, to })
+}
+
+fn transition_row(entry: &AuditEntry) -> Option<Vec<Value>> {
+    let transition = status_transition(entry)?;
+    Some(vec![
+        entry.id.into(),
+        entry.timestamp.clone().into(),
+        optional(entry.task_id.as_deref()),
+        optional(transition.title.as_deref()),
+        optional(transition.from.as_deref()),
+        transition.to.into(),
+    ])
+}
+
+/// Read the debug hook event log, which is absent unless `debug_logging` is on.
+fn read_hook_events(base_dir: &Path) -> Result<Vec<Vec<Value>>> {
+    let path: PathBuf = paths::project_data_dir(base_dir).join(HOOK_EVENTS_FILE);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = std::fs::read_to_string(&path)
+        .map_err(|source| Error::FileAccess { path: path.clone(), source })?;
+    Ok(content
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .map(|event| {
+            let input = event.get("input").cloned().unwrap_or(Value::Null);
+            let field = |name: &str| input.get(name).cloned().unwrap_or(Value::Null);
+            vec![
+                event.get("timestamp").cloned().unwrap_or(Value::Null),
+                event.get("hook_type").cloned().unwrap_or(Value::Null),
+                field("session_id"),
+                field("tool_name"),
+                input.to_string().into(),
+            ]
+        })
+        .collect())
+}
+
+fn session_rows(store: &SqliteStore, events: &[Vec<Value>]) -> Result<Vec<Vec<Value>>> {
+    let mut event_counts: HashMap<&str, u64> = HashMap::new();
+    for session_id in events.iter().filter_map(|event| event[2].as_str()) {
+        *event_counts.entry(session_id).or_default() += 1;
+    }
+    let parse =
+        |timestamp: &str| NaiveDateTime::parse_from_str(timestamp, SESSION_TIMESTAMP_FORMAT).ok();
+
+    Ok(store
+        .sessions()?
+        .into_iter()
+        .map(|session| {
+            let duration = parse(&session.started_at)
+                .zip(parse(&session.last_seen))
+                .map_or(Value::Null, |(start, end)| (end - start).num_seconds().into());
+            let hook_events = event_counts.get(session.session_id.as_str()).copied().unwrap_or(0);
+            vec![
+                session.session_id.into(),
+                optional(session.transcript_path.as_deref()),
+                session.state.as_str().into(),
+                session.started_at.into(),
+                session.last_seen.into(),
+                duration,
+                hook_events.into(),
+            ]
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ProjectConfig;
+    use crate::hook_logging::log_hook_event_in;
+    use crate::tasks::{Priority, Status, TaskUpdate};
+    use tempfile::TempDir;
+
+    fn read(path: &Path) -> String {
+        std::fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn test_export_format_from_str() {
+        assert_eq!("csv".parse::<ExportFormat>().unwrap(), ExportFormat::Csv);
+        assert_eq!("JSONL".parse::<ExportFormat>().unwrap(), ExportFormat::Jsonl);
+        let err = "parquet".parse::<ExportFormat>().unwrap_err();
+        assert!(err.to_string().contains("must be one of: csv, jsonl"));
+    }
+
+    #[test]
+    fn test_csv_field_quoting() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+    }
+
+    #[test]
+    fn test_export_csv() {
+        let dir = TempDir::new().unwrap();
+        ProjectConfig { debug_logging: true, ..Default::default() }.save_to(dir.path()).unwrap();
+        let store = SqliteTaskStore::new(paths::project_db_path(dir.path())).unwrap();
+        let task = store.create_task("Write, then test", "", Priority::Medium).unwrap();
+        store
+            .update_task(
+                &task.id,
+                TaskUpdate { status: Some(Status::Complete), ..Default::default() },
+            )
+            .unwrap();
+        store
+            .update_task(
+                &task.id,
+                TaskUpdate { title: Some("Renamed".into()), ..Default::default() },
+            )
+            .unwrap();
+        SqliteStore::new(dir.path())
+            .unwrap()
+            .touch_session("s1", None, crate::storage::SessionState::Working)
+            .unwrap();
+        log_hook_event_in(
+            "pre-tool-use",
+            r#"{"session_id": "s1", "tool_name": "Bash"}"#,
+            dir.path(),
+        );
+
+        let out = dir.path().join("out");
+        let files = export(dir.path(), ExportFormat::Csv, &out).unwrap();
+        let rows: HashMap<_, _> = files.iter().map(|f| (f.table.as_str(), f.rows)).collect();
+        assert_eq!(rows["audit_log"], 3);
+        assert_eq!(rows["task_transitions"], 2);
+        assert_eq!(rows["hook_events"], 1);
+        assert_eq!(rows["sessions"], 1);
+
+        let transitions = read(&out.join("task_transitions.csv"));
+        let lines: Vec<&str> = transitions.lines().collect();
+        assert_eq!(lines[0], "audit_id,timestamp,task_id,title,from_status,to_status");
+        assert!(lines[1].ends_with(&format!("{},\"Write, then test\",,open", task.id)));
+        assert!(lines[2].ends_with("open,complete"));
+
+        let sessions = read(&out.join("sessions.csv"));
+        assert!(sessions.lines().nth(1).unwrap().starts_with("s1,,working,"));
+        assert!(sessions.lines().nth(1).unwrap().ends_with(",0,1"));
+        assert!(read(&out.join("hook_events.csv")).contains(",pre-tool-use,s1,Bash,"));
+    }
+
+    #[test]
+    fn test_export_jsonl_without_hook_log() {
+        let dir = TempDir::new().unwrap();
+        let store = SqliteTaskStore::new(paths::project_db_path(dir.path())).unwrap();
+        let task = store.create_task("Task", "", Priority::Low).unwrap();
+        store.delete_task(&task.id).unwrap();
+
+        let out = dir.path().join("out");
+        export(dir.path(), ExportFormat::Jsonl, &out).unwrap();
+
+        let transitions: Vec<Value> = read(&out.join("task_transitions.jsonl"))
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(transitions.len(), 2);
+        assert_eq!(transitions[0]["from_status"], Value::Null);
+        assert_eq!(transitions[1]["to_status"], "deleted");
+        assert_eq!(read(&out.join("hook_events.jsonl")), "");
+    }
+}
@@ -1,15 +1,43 @@
 //! Git operations module.
 
 mod status;
+mod untracked_cache;
 
-pub use status::{check_uncommitted_changes, GitStatus, UncommittedChanges};
+pub use status::{
+    check_uncommitted_changes, check_uncommitted_changes_with, latest_stash, operation_in_progress,
+    stash_shas, upstream_status, GitOperation, GitStatus, UncommittedChanges, UpstreamStatus,
+};
 
+use crate::command;
 use crate::error::Result;
 use crate::traits::CommandRunner;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+/// Untracked files larger than this are left out of [`commit_diff`].
+const MAX_UNTRACKED_DIFF_BYTES: u64 = 256 * 1024;
 
 /// Check if we're in a git repository.
 pub fn is_git_repo(runner: &dyn CommandRunner) -> bool {
-    runner.run("git", &["rev-parse", "--git-dir"], None).map(|o| o.success()).unwrap_or(false)
+    runner.run("git", &["rev-parse", "--git-dir"], None).is_ok_and(|o| o.success())
+}
+
+/// Get the root of the worktree containing `dir`.
+///
+/// For a linked worktree (`git worktree add`) this is the worktree's own
+/// directory, not the main repository's. Returns `None` outside a git repository.
+///
+/// # Errors
+///
+/// Returns an error if the git command cannot be run.
+pub fn worktree_root(runner: &dyn CommandRunner, dir: &Path) -> Result<Option<PathBuf>> {
+    let output = runner.run_in_dir("git", &["rev-parse", "--show-toplevel"], None, dir)?;
+    let root = output.stdout.trim();
+    if output.success() && !root.is_empty() {
+        Ok(Some(PathBuf::from(root)))
+    } else {
+        Ok(None)
+    }
 }
 
 /// Get the current branch name.
@@ -31,6 +59,35 @@ pub fn current_branch(runner: &dyn CommandRunner) -> Result<Option<String>> {
     }
 }
 
+/// What `HEAD` currently points at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HeadRef {
+    /// `HEAD` is on a branch (which may not have any commits yet).
+    Branch(String),
+    /// `HEAD` points directly at a commit.
+    Detached,
+    /// `HEAD` could not be resolved (e.g. not a git repository).
+    Unknown,
+}
+
+/// Determine what `HEAD` points at.
+///
+/// Unlike [`current_branch`], this distinguishes a detached `HEAD` from
+/// a failure to run git, and reports unborn branches by name.
+///
+/// # Errors
+///
+/// Returns an error if the git command cannot be run.
+pub fn head_ref(runner: &dyn CommandRunner) -> Result<HeadRef> {
+    let output = runner.run("git", &["symbolic-ref", "--short", "-q", "HEAD"], None)?;
+    let branch = output.stdout.trim();
+    Ok(match output.exit_code {
+        0 if !branch.is_empty() => HeadRef::Branch(branch.to_string()),
+        1 => HeadRef::Detached,
+        _ => HeadRef::Unknown,
+    })
+}
+
 /// Get the current commit SHA.
 ///
 /// # Errors
@@ -50,6 +107,126 @@ pub fn current_sha(runner: &dyn CommandRunner) -> Result<Option<String>> {
     }
 }
 
+/// A commit with its committer and signature.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommitInfo {
+    /// The abbreviated commit SHA.
+    pub sha: String,
+    /// The first line of the commit message.
+    pub subject: String,
+    /// The committer's name.
+    pub committer_name: String,
+    /// The committer's email.
+    pub committer_email: String,
+    /// git's signature status (`%G?`): `G` for a good signature, `N` for none,
+    /// `B` for a bad one, and other letters for signatures that cannot be
+    /// fully checked.
+    pub signature: char,
+}
+
+impl CommitInfo {
+    /// Check whether the commit has a signature that is not bad.
+    #[must_use]
+    pub const fn is_signed(&self) -> bool {
+        !matches!(self.signature, 'N' | 'B')
+    }
+}
+
+/// List the commits on `HEAD` committed since `since` that are on no
+/// remote-tracking branch, newest first.
+///
+/// `since` is anything `git log --since` accepts.
+///
+/// # Errors
+///
+/// Returns an error if the git command cannot be run.
+pub fn unpushed_commits_since(runner: &dyn CommandRunner, since: &str) -> Result<Vec<CommitInfo>> {
+    let since = format!("--since={since}");
+    let output = runner.run(
+        "git",
+        &["log", &since, "--format=%h%x1f%G?%x1f%cn%x1f%ce%x1f%s", "HEAD", "--not", "--remotes"],
+        None,
+    )?;
+    if !output.success() {
+        return Ok(Vec::new());
+    }
+    Ok(output
+        .stdout
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(5, '\x1f');
+            Some(CommitInfo {
+                sha: fields.next()?.to_string(),
+                signature: fields.next()?.chars().next()?,
+                committer_name: fields.next()?.to_string(),
+                committer_email: fields.next()?.to_string(),
+                subject: fields.next().unwrap_or_default().to_string(),
+            })
+        })
+        .collect())
+}
+
+/// Check whether a shell command line runs `git commit`.
+pub fn is_commit_command(command: &str) -> bool {
+    command::parse(command).iter().any(|segment| {
+        segment.program() == Some("git") && segment.args().iter().any(|arg| arg.text == "commit")
+    })
+}
+
+/// The pathspecs a shell command line stages new files from with `git add`.
+///
+/// Returns `None` if the command runs no `git add` that can stage untracked
+/// files, and an empty list if one stages the whole tree, as `git add -A`
+/// does. `git add -u` and `git commit -a` only stage tracked files, so they
+/// are not counted.
+pub fn added_pathspecs(command: &str) -> Option<Vec<String>> {
+    let mut found: Option<Vec<String>> = None;
+    for segment in command::parse(command) {
+        if segment.program() != Some("git") {
+            continue;
+        }
+        let mut args = segment.args().iter().map(|word| word.text.as_str());
+        // Skip global options, including those that take a value
+        let subcommand = loop {
+            match args.next() {
+                Some("-C" | "-c" | "--git-dir" | "--work-tree") => {
+                    args.next();
+                }
+                Some(arg) if arg.starts_with('-') => {}
+                other => break other,
+            }
+        };
+        if subcommand != Some("add") {
+            continue;
+        }
+
+        let mut whole_tree = false;
+        let mut update_only = false;
+        let mut after_separator = false;
+        let mut pathspecs = Vec::new();
+        for arg in args {
+            match arg {
+                _ if after_separator || !arg.starts_with('-') => pathspecs.push(arg.to_string()),
+                "--" => after_separator = true,
+                "-A" | "--all" => whole_tree = true,
+                "-u" | "--update" => update_only = true,
+                _ => {}
+            }
+        }
+        if update_only {
+            continue;
+        }
+        if pathspecs.is_empty() {
+            if whole_tree {
+                return Some(Vec::new());
+            }
+            continue;
+        }
+        found.get_or_insert_with(Vec::new).extend(pathspecs);
+    }
+    found
+}
+
 /// Get staged files.
 ///
 /// # Errors
@@ -64,6 +241,75 @@ pub fn staged_files(runner: &dyn CommandRunner) -> Result<Vec<String>> {
     }
 }
 
+/// Get staged files, leaving out the ones being deleted.
+///
+/// # Errors
+///
+/// Returns an error if the git command fails.
+pub fn staged_files_not_deleted(runner: &dyn CommandRunner) -> Result<Vec<String>> {
+    let output =
+        runner.run("git", &["diff", "--cached", "--name-only", "--diff-filter=d"], None)?;
+    if output.success() {
+        Ok(output.stdout.lines().map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect())
+    } else {
+        Ok(Vec::new())
+    }
+}
+
+/// Get untracked files that are not ignored.
+///
+/// # Errors
+///
+/// Returns an error if the git command fails.
+pub fn untracked_files(runner: &dyn CommandRunner) -> Result<Vec<String>> {
+    let output = runner.run("git", &["ls-files", "--others", "--exclude-standard"], None)?;
+    if output.success() {
+        Ok(output.stdout.lines().map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect())
+    } else {
+        Ok(Vec::new())
+    }
+}
+
+/// Get the files with changes not yet committed: staged, unstaged and
+/// untracked (not ignored), relative to the repository root.
+///
+/// # Errors
+///
+/// Returns an error if the git command fails.
+pub fn uncommitted_files(runner: &dyn CommandRunner) -> Result<Vec<String>> {
+    let output = runner.run(
+        "git",
+        &["status", "--porcelain", "-z", "--no-renames", "--untracked-files=all"],
+        None,
+    )?;
+    if !output.success() {
+        return Ok(Vec::new());
+    }
+    Ok(output
+        .stdout
+        .split('\0')
+        .filter_map(|entry| entry.get(3..))
+        .filter(|path| !path.is_empty())
+        .map(ToString::to_string)
+        .collect())
+}
+
+/// Get the files changed by commits not yet pushed to the upstream.
+///
+/// Returns an empty list when the branch has no upstream.
+///
+/// # Errors
+///
+/// Returns an error if the git command fails.
+pub fn unpushed_files(runner: &dyn CommandRunner) -> Result<Vec<String>> {
+    let output = runner.run("git", &["diff", "--name-only", "@{upstream}...HEAD"], None)?;
+    if output.success() {
+        Ok(output.stdout.lines().map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect())
+    } else {
+        Ok(Vec::new())
+    }
+}
+
 /// Get the staged diff.
 ///
 /// # Errors
@@ -74,6 +320,150 @@ pub fn staged_diff(runner: &dyn CommandRunner) -> Result<String> {
     Ok(output.stdout)
 }
 
+/// Get the unstaged diff of tracked files matching the pathspecs. An empty
+/// list of pathspecs matches the whole tree.
+///
+/// # Errors
+///
+/// Returns an error if the git command fails.
+pub fn unstaged_diff(runner: &dyn CommandRunner, pathspecs: &[String]) -> Result<String> {
+    let mut args = vec!["diff", "-U0", "--"];
+    args.extend(pathspecs.iter().map(String::as_str));
+    let output = runner.run("git", &args, None)?;
+    Ok(output.stdout)
+}
+
+/// The diff a commit command will commit: the staged diff, plus the
+/// unstaged changes and untracked files matching the pathspecs of any
+/// `git add` in the same command line.
+///
+/// Untracked files are added whole; those larger than [`MAX_UNTRACKED_DIFF_BYTES`] or not text are skipped.
+///
+/// # Errors
+///
+/// Returns an error if a git command fails.
+pub fn commit_diff(runner: &dyn CommandRunner, base_dir: &Path, command: &str) -> Result<String> {
+    let mut diff = staged_diff(runner)?;
+    let Some(pathspecs) = added_pathspecs(command) else {
+        return Ok(diff);
+    };
+    diff.push_str(&unstaged_diff(runner, &pathspecs)?);
+    for path in untracked_files_matching(runner, &pathspecs)? {
+        let file = base_dir.join(&path);
+        let small = std::fs::metadata(&file).is_ok_and(|m| m.len() <= MAX_UNTRACKED_DIFF_BYTES);
+        let Some(contents) = small.then(|| std::fs::read_to_string(&file).ok()).flatten() else {
+            continue;
+        };
+        let _ = writeln!(diff, "diff --git a/{path} b/{path}\n+++ b/{path}\n@@ -0,0 +1 @@");
+        for line in contents.lines() {
+            let _ = writeln!(diff, "+{line}");
+        }
+    }
+    Ok(diff)
+}
+
+/// Untracked files that a `git add` in a shell command line is about to
+/// stage, as matched by its [`added_pathspecs`].
+///
+/// # Errors
+///
+/// Returns an error if the git command fails.
+pub fn added_untracked_files(runner: &dyn CommandRunner, command: &str) -> Result<Vec<String>> {
+    added_pathspecs(command)
+        .map_or_else(|| Ok(Vec::new()), |pathspecs| untracked_files_matching(runner, &pathspecs))
+}
+
+/// Get the binary files newly added in the index.
+///
+/// # Errors
+///
+/// Returns an error if the git command fails.
+pub fn staged_new_binary_files(runner: &dyn CommandRunner) -> Result<Vec<String>> {
+    let output = runner.run("git", &["diff", "--cached", "--numstat", "--diff-filter=A"], None)?;
+    if !output.success() {
+        return Ok(Vec::new());
+    }
+    Ok(output
+        .stdout
+        .lines()
+        .filter_map(|line| line.strip_prefix("-\t-\t"))
+        .map(ToString::to_string)
+        .collect())
+}
+
+/// Get the untracked, non-ignored files matching the pathspecs, relative to
+/// the repository root. An empty list of pathspecs matches the whole tree.
+///
+/// # Errors
+///
+/// Returns an error if the git command fails.
+pub fn untracked_files_matching(
+    runner: &dyn CommandRunner,
+    pathspecs: &[String],
+) -> Result<Vec<String>> {
+    let mut args = vec!["ls-files", "--others", "--exclude-standard", "--full-name", "--"];
+    args.extend(pathspecs.iter().map(String::as_str));
+    let output = runner.run("git", &args, None)?;
+    if !output.success() {
+        return Ok(Vec::new());
+    }
+    Ok(output.stdout.lines().filter(|line| !line.is_empty()).map(ToString::to_string).collect())
+}
+
+/// Get the size in bytes of a file as staged in the index.
+///
+/// Returns `None` if the file is not in the index.
+///
+/// # Errors
+///
+/// Returns an error if the git command fails.
+pub fn staged_size(runner: &dyn CommandRunner, path: &str) -> Result<Option<u64>> {
+    let output = runner.run("git", &["cat-file", "-s", &format!(":{path}")], None)?;
+    if !output.success() {
+        return Ok(None);
+    }
+    Ok(output.stdout.trim().parse().ok())
+}
+
+/// Count the lines added and deleted in tracked files since `HEAD`, leaving
+/// out the files for which `skip` returns true.
+///
+/// Binary files count as no lines. Returns zero if there is no `HEAD` yet.
+///
+/// # Errors
+///
+/// Returns an error if the git command fails.
+pub fn uncommitted_line_count(
+    runner: &dyn CommandRunner,
+    skip: &dyn Fn(&str) -> bool,
+) -> Result<usize> {
+    let output = runner.run("git", &["diff", "HEAD", "--numstat"], None)?;
+    if !output.success() {
+        return Ok(0);
+    }
+    Ok(output
+        .stdout
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, '\t');
+            let (added, deleted, path) = (fields.next()?, fields.next()?, fields.next()?);
+            (!skip(path)).then_some([added, deleted])
+        })
+        .flatten()
+        .filter_map(|count| count.parse::<usize>().ok())
+        .sum())
+}
+
+/// Get a `--stat` summary of the changes to tracked files since `HEAD`.
+///
+/// # Errors
+///
+/// Returns an error if the git command fails.
+pub fn uncommitted_diff_stat(runner: &dyn CommandRunner) -> Result<String> {
+    let output = runner.run("git", &["diff", "HEAD", "--stat"], None)?;
+    Ok(if output.success() { output.stdout } else { String::new() })
+}
+
 /// Compute a hash of the current git working state for staleness detection.
 ///
 /// This includes:
@@ -119,6 +509,70 @@ mod tests {
     use crate::testing::MockCommandRunner;
     use crate::traits::CommandOutput;
 
+    #[test]
+    fn test_unpushed_commits_since() {
+        let mut runner = MockCommandRunner::new();
+        runner.expect(
+            "git",
+            &[
+                "log",
+                "--since=2026-03-01 11:00:00 +0000",
+                "--format=%h%x1f%G?%x1f%cn%x1f%ce%x1f%s",
+                "HEAD",
+                "--not",
+                "--remotes",
+            ],
+            CommandOutput {
+                exit_code: 0,
+                stdout: "abc1234\x1fN\x1fAgent\x1fagent@example.com\x1fAdd parser\n\
+                         def5678\x1fG\x1fAda\x1fada@example.com\x1fFix: the \x1f case\n"
+                    .to_string(),
+                stderr: String::new(),
+            },
+        );
+
+        let commits = unpushed_commits_since(&runner, "2026-03-01 11:00:00 +0000").unwrap();
+        assert_eq!(commits.len(), 2);
+        assert_eq!(commits[0].sha, "abc1234");
+        assert_eq!(commits[0].committer_email, "agent@example.com");
+        assert!(!commits[0].is_signed());
+        assert!(commits[1].is_signed());
+        assert_eq!(commits[1].subject, "Fix: the \x1f case");
+    }
+
+    #[test]
+    fn test_worktree_root() {
+        let mut runner = MockCommandRunner::new();
+        runner.expect(
+            "git",
+            &["rev-parse", "--show-toplevel"],
+            CommandOutput {
+                exit_code: 0,
+                stdout: "/work/repo-feature\n".to_string(),
+                stderr: String::new(),
+            },
+        );
+
+        let root = worktree_root(&runner, Path::new("/work/repo-feature/src")).unwrap();
+        assert_eq!(root, Some(PathBuf::from("/work/repo-feature")));
+    }
+
+    #[test]
+    fn test_worktree_root_outside_repo() {
+        let mut runner = MockCommandRunner::new();
+        runner.expect(
+            "git",
+            &["rev-parse", "--show-toplevel"],
+            CommandOutput {
+                exit_code: 128,
+                stdout: String::new(),
+                stderr: "fatal: not a git repository".to_string(),
+            },
+        );
+
+        assert_eq!(worktree_root(&runner, Path::new("/tmp")).unwrap(), None);
+    }
+
     #[test]
     fn test_is_git_repo_true() {
         let mut runner = MockCommandRunner::new();
@@ -156,6 +610,43 @@ mod tests {
         assert_eq!(current_branch(&runner).unwrap(), Some("main".to_string()));
     }
 
+    #[test]
+    fn test_head_ref_branch() {
+        let mut runner = MockCommandRunner::new();
+        runner.expect(
+            "git",
+            &["symbolic-ref", "--short", "-q", "HEAD"],
+            CommandOutput { exit_code: 0, stdout: "wip/x\n".to_string(), stderr: String::new() },
+        );
+        assert_eq!(head_ref(&runner).unwrap(), HeadRef::Branch("wip/x".to_string()));
+    }
+
+    #[test]
+    fn test_head_ref_detached() {
+        let mut runner = MockCommandRunner::new();
+        runner.expect(
+            "git",
+            &["symbolic-ref", "--short", "-q", "HEAD"],
+            CommandOutput { exit_code: 1, stdout: String::new(), stderr: String::new() },
+        );
+        assert_eq!(head_ref(&runner).unwrap(), HeadRef::Detached);
+    }
+
+    #[test]
+    fn test_head_ref_not_a_repo() {
+        let mut runner = MockCommandRunner::new();
+        runner.expect(
+            "git",
+            &["symbolic-ref", "--short", "-q", "HEAD"],
+            CommandOutput {
+                exit_code: 128,
+                stdout: String::new(),
+                stderr: "fatal: not a git repository\n".to_string(),
+            },
+        );
+        assert_eq!(head_ref(&runner).unwrap(), HeadRef::Unknown);
+    }
+
     #[test]
     fn test_staged_files() {
         let mut runner = MockCommandRunner::new();
@@ -172,6 +663,43 @@ mod tests {
         assert_eq!(files, vec!["src/lib.rs", "src/main.rs"]);
     }
 
+    #[test]
+    fn test_untracked_files() {
+        let mut runner = MockCommandRunner::new();
+        runner.expect(
+            "git",
+            &["ls-files", "--others", "--exclude-standard"],
+            CommandOutput {
+                exit_code: 0,
+                stdout: "notes.txt
+.env.local
+"
+                .to_string(),
+                stderr: String::new(),
+            },
+        );
+        assert_eq!(untracked_files(&runner).unwrap(), vec!["notes.txt", ".env.local"]);
+    }
+
+    #[test]
+    fn test_uncommitted_files() {
+        let mut runner = MockCommandRunner::new();
+        runner.expect(
+            "git",
+            &["status", "--porcelain", "-z", "--no-renames", "--untracked-files=all"],
+            CommandOutput {
+                exit_code: 0,
+                stdout: "M  crates/api/src/lib.rs\0 M README.md\0?? crates/cli/new file.rs\0"
+                    .to_string(),
+                stderr: String::new(),
+            },
+        );
+        assert_eq!(
+            uncommitted_files(&runner).unwrap(),
+            vec!["crates/api/src/lib.rs", "README.md", "crates/cli/new file.rs"]
+        );
+    }
+
     #[test]
     fn test_staged_files_empty() {
         let mut runner = MockCommandRunner::new();
@@ -212,6 +740,101 @@ mod tests {
         assert_eq!(diff, "+some change\n");
     }
 
+    #[test]
+    fn test_is_commit_command() {
+        assert!(is_commit_command("git add -A && git commit -m 'x'"));
+        assert!(is_commit_command("git -C sub commit --amend"));
+        assert!(!is_commit_command("git status"));
+        assert!(!is_commit_command("echo git commit"));
+    }
+
+    #[test]
+    fn test_added_pathspecs() {
+        assert_eq!(added_pathspecs("git add -A && git commit -m x"), Some(vec![]));
+        assert_eq!(added_pathspecs("git commit --all -m x"), None);
+        assert_eq!(added_pathspecs("git add -u && git commit -m x"), None);
+        assert_eq!(added_pathspecs("git add"), None);
+        assert_eq!(
+            added_pathspecs("git -C repo add src 'my file' && git add -- -odd"),
+            Some(vec!["src".to_string(), "my file".to_string(), "-odd".to_string()])
+        );
+        assert_eq!(
+            added_pathspecs("git add . && git add -A docs"),
+            Some(vec![".".to_string(), "docs".to_string()])
+        );
+        assert_eq!(added_pathspecs("echo git add ."), None);
+    }
+
+    #[test]
+    fn test_untracked_files_matching_and_staged_size() {
+        let mut runner = MockCommandRunner::new();
+        runner.expect(
+            "git",
+            &["ls-files", "--others", "--exclude-standard", "--full-name", "--", "assets"],
+            CommandOutput {
+                exit_code: 0,
+                stdout: "assets/a.bin\nassets/b.bin\n".to_string(),
+                stderr: String::new(),
+            },
+        );
+        runner.expect(
+            "git",
+            &["cat-file", "-s", ":assets/a.bin"],
+            CommandOutput { exit_code: 0, stdout: "2048\n".to_string(), stderr: String::new() },
+        );
+        runner.expect(
+            "git",
+            &["cat-file", "-s", ":missing"],
+            CommandOutput { exit_code: 128, stdout: String::new(), stderr: "fatal".to_string() },
+        );
+        assert_eq!(
+            untracked_files_matching(&runner, &["assets".to_string()]).unwrap(),
+            vec!["assets/a.bin", "assets/b.bin"]
+        );
+        assert_eq!(staged_size(&runner, "assets/a.bin").unwrap(), Some(2048));
+        assert_eq!(staged_size(&runner, "missing").unwrap(), None);
+    }
+
+    #[test]
+    fn test_staged_new_binary_files() {
+        let mut runner = MockCommandRunner::new();
+        runner.expect(
+            "git",
+            &["diff", "--cached", "--numstat", "--diff-filter=A"],
+            CommandOutput {
+                exit_code: 0,
+                stdout: "12\t0\tsrc/new.rs\n-\t-\tassets/model.bin\n".to_string(),
+                stderr: String::new(),
+            },
+        );
+        assert_eq!(staged_new_binary_files(&runner).unwrap(), vec!["assets/model.bin"]);
+    }
+
+    #[test]
+    fn test_uncommitted_line_count() {
+        let mut runner = MockCommandRunner::new();
+        runner.expect(
+            "git",
+            &["diff", "HEAD", "--numstat"],
+            CommandOutput {
+                exit_code: 0,
+                stdout: "10\t2\tsrc/a.rs\n-\t-\tlogo.png\n0\t7\tsrc/b.rs\n".to_string(),
+                stderr: String::new(),
+            },
+        );
+        runner.expect(
+            "git",
+            &["diff", "HEAD", "--numstat"],
+            CommandOutput {
+                exit_code: 128,
+                stdout: String::new(),
+                stderr: "fatal: bad revision 'HEAD'".to_string(),
+            },
+        );
+        assert_eq!(uncommitted_line_count(&runner, &|path| path == "src/b.rs").unwrap(), 12);
+        assert_eq!(uncommitted_line_count(&runner, &|_| false).unwrap(), 0);
+    }
+
     #[test]
     fn test_current_branch_failure() {
         let mut runner = MockCommandRunner::new();
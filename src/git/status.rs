@@ -1,10 +1,18 @@
 //! Git status checking.
 
+use super::untracked_cache::UntrackedCache;
+use crate::config::GitStatusPolicy;
 use crate::error::Result;
+use crate::paths;
 use crate::traits::CommandRunner;
+use std::path::{Path, PathBuf};
+
+/// Shell command run in each submodule to print its path if it has changes.
+const DIRTY_SUBMODULE_CMD: &str = r#"test -z "$(git status --porcelain)" || echo "$displaypath""#;
 
 /// Represents uncommitted changes in a git repository.
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[allow(clippy::struct_excessive_bools)] // Independent kinds of change, not a state machine
 pub struct UncommittedChanges {
     /// Whether there are unstaged changes.
     pub has_unstaged: bool,
@@ -12,13 +20,15 @@ pub struct UncommittedChanges {
     pub has_staged: bool,
     /// Whether there are untracked files.
     pub has_untracked: bool,
+    /// Whether any submodule has uncommitted changes of its own.
+    pub has_dirty_submodules: bool,
 }
 
 impl UncommittedChanges {
     /// Check if there are any uncommitted changes.
     #[must_use]
     pub const fn has_changes(&self) -> bool {
-        self.has_unstaged || self.has_staged || self.has_untracked
+        self.has_unstaged || self.has_staged || self.has_untracked || self.has_dirty_submodules
     }
 
     /// Get a human-readable description of the changes.
@@ -34,10 +44,75 @@ impl UncommittedChanges {
         if self.has_untracked {
             parts.push("untracked files");
         }
+        if self.has_dirty_submodules {
+            parts.push("dirty submodules");
+        }
         parts.join(", ")
     }
 }
 
+/// A multi-step git operation that was started and not finished.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GitOperation {
+    /// A rebase (`rebase-merge` or `rebase-apply` in the git directory).
+    Rebase,
+    /// A merge (`MERGE_HEAD`).
+    Merge,
+    /// A cherry-pick (`CHERRY_PICK_HEAD`).
+    CherryPick,
+    /// A revert (`REVERT_HEAD`).
+    Revert,
+}
+
+impl GitOperation {
+    /// Every operation, in the order they are looked for.
+    const ALL: [Self; 4] = [Self::Rebase, Self::Merge, Self::CherryPick, Self::Revert];
+
+    /// The git command for the operation, such as `cherry-pick`.
+    #[must_use]
+    pub const fn command(self) -> &'static str {
+        match self {
+            Self::Rebase => "rebase",
+            Self::Merge => "merge",
+            Self::CherryPick => "cherry-pick",
+            Self::Revert => "revert",
+        }
+    }
+
+    /// Files in the git directory present while the operation is in progress.
+    const fn markers(self) -> &'static [&'static str] {
+        match self {
+            Self::Rebase => &["rebase-merge", "rebase-apply"],
+            Self::Merge => &["MERGE_HEAD"],
+            Self::CherryPick => &["CHERRY_PICK_HEAD"],
+            Self::Revert => &["REVERT_HEAD"],
+        }
+    }
+}
+
+/// Find the operation in progress in the repository whose worktree is `work_tree`.
+///
+/// Looks for the operation's state files in the git directory, following the
+/// `gitdir:` link of a linked worktree or submodule.
+#[must_use]
+pub fn operation_in_progress(work_tree: &Path) -> Option<GitOperation> {
+    let git_dir = git_dir(work_tree)?;
+    GitOperation::ALL
+        .into_iter()
+        .find(|op| op.markers().iter().any(|marker| git_dir.join(marker).exists()))
+}
+
+/// The git directory of the worktree at `work_tree`, if it has one.
+fn git_dir(work_tree: &Path) -> Option<PathBuf> {
+    let dot_git = work_tree.join(".git");
+    if dot_git.is_dir() {
+        return Some(dot_git);
+    }
+    let link = std::fs::read_to_string(&dot_git).ok()?;
+    let target = link.trim().strip_prefix("gitdir:")?.trim();
+    Some(work_tree.join(target))
+}
+
 /// Full git status information.
 #[derive(Debug, Clone, Default)]
 pub struct GitStatus {
@@ -49,18 +124,135 @@ pub struct GitStatus {
     pub staged_files: Vec<String>,
     /// List of untracked files.
     pub untracked_files: Vec<String>,
+    /// Paths of submodules with uncommitted changes.
+    pub dirty_submodules: Vec<String>,
     /// Whether the branch is ahead of the remote.
     pub ahead_of_remote: bool,
     /// Number of commits ahead.
     pub commits_ahead: u32,
+    /// A rebase, merge, cherry-pick or revert that has not been finished.
+    pub operation: Option<GitOperation>,
 }
 
 /// Check for uncommitted changes in the git repository.
 ///
+/// Submodules are checked recursively when the repository has a `.gitmodules`
+/// file, since changes inside a submodule (notably untracked files) are not
+/// visible from the superproject's diff.
+///
 /// # Errors
 ///
 /// Returns an error if git commands fail.
 pub fn check_uncommitted_changes(runner: &dyn CommandRunner) -> Result<GitStatus> {
+    let mut status = check_working_tree(runner, Path::new(".gitmodules").exists())?;
+    status.operation = operation_in_progress(Path::new("."));
+    Ok(status)
+}
+
+/// Check for uncommitted changes as the git status policy says.
+///
+/// With the default policy this is [`check_uncommitted_changes`]. Otherwise a
+/// single `git status` is run, limited to the policy's paths, and the
+/// untracked files are taken from the cache when it is enabled and current.
+///
+/// # Errors
+///
+/// Returns an error if git commands fail.
+pub fn check_uncommitted_changes_with(
+    runner: &dyn CommandRunner,
+    policy: &GitStatusPolicy,
+) -> Result<GitStatus> {
+    if !policy.is_fast() {
+        return check_uncommitted_changes(runner);
+    }
+    let work_tree = Path::new(".");
+    let mut status = check_working_tree_fast(runner, policy, work_tree)?;
+    status.operation = operation_in_progress(work_tree);
+    Ok(status)
+}
+
+/// Check the working tree with one `git status`, without rename detection
+/// and listing untracked directories rather than their files.
+fn check_working_tree_fast(
+    runner: &dyn CommandRunner,
+    policy: &GitStatusPolicy,
+    work_tree: &Path,
+) -> Result<GitStatus> {
+    let cache_path = paths::project_untracked_cache_path(work_tree);
+    let data_dir = paths::project_data_dir(work_tree);
+    let mut cached = None;
+    if policy.cache_untracked {
+        if let Some(cache) = UntrackedCache::load(&cache_path) {
+            let key = cache.current_key(runner, work_tree, &policy.paths, &data_dir)?;
+            if key.as_ref() == Some(&cache.key) {
+                cached = Some(cache);
+            }
+        }
+    }
+
+    let mut args = vec!["status", "--porcelain", "-z", "--no-renames"];
+    if cached.is_some() {
+        args.push("--untracked-files=no");
+    } else {
+        args.push("--untracked-files=normal");
+        if policy.cache_untracked {
+            args.push("--ignored");
+        }
+    }
+    if !policy.paths.is_empty() {
+        args.push("--");
+        args.extend(policy.paths.iter().map(String::as_str));
+    }
+
+    let mut status = GitStatus::default();
+    let output = runner.run("git", &args, None)?;
+    if output.success() {
+        let mut ignored = Vec::new();
+        for entry in output.stdout.split('\0').filter(|e| e.len() > 3) {
+            let (code, path) = entry.split_at(3);
+            let path = path.to_string();
+            match code.as_bytes() {
+                [b'?', b'?', _] => status.untracked_files.push(path),
+                [b'!', b'!', _] => ignored.push(path),
+                [staged, unstaged, _] => {
+                    if *staged != b' ' {
+                        status.staged_files.push(path.clone());
+                    }
+                    if *unstaged != b' ' {
+                        status.unstaged_files.push(path);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(cache) = cached {
+            status.untracked_files = cache.untracked;
+        } else if policy.cache_untracked {
+            let mut cache = UntrackedCache {
+                key: String::new(),
+                untracked: status.untracked_files.clone(),
+                ignored,
+            };
+            if let Some(key) = cache.current_key(runner, work_tree, &policy.paths, &data_dir)? {
+                cache.key = key;
+                cache.save(&cache_path);
+            }
+        }
+    }
+    status.uncommitted.has_unstaged = !status.unstaged_files.is_empty();
+    status.uncommitted.has_staged = !status.staged_files.is_empty();
+    status.uncommitted.has_untracked = !status.untracked_files.is_empty();
+
+    if work_tree.join(".gitmodules").exists() {
+        check_submodules(runner, &mut status)?;
+    }
+    check_ahead_of_upstream(runner, &mut status);
+    Ok(status)
+}
+
+/// Check the working tree, optionally including submodules.
+fn check_working_tree(runner: &dyn CommandRunner, include_submodules: bool) -> Result<GitStatus> {
     let mut status = GitStatus::default();
 
     // Check for unstaged changes
@@ -113,7 +305,31 @@ pub fn check_uncommitted_changes(runner: &dyn CommandRunner) -> Result<GitStatus
         status.untracked_files = files;
     }
 
-    // Check if ahead of remote
+    if include_submodules {
+        check_submodules(runner, &mut status)?;
+    }
+    check_ahead_of_upstream(runner, &mut status);
+
+    Ok(status)
+}
+
+/// Record the submodules with uncommitted changes of their own.
+fn check_submodules(runner: &dyn CommandRunner, status: &mut GitStatus) -> Result<()> {
+    let output = runner.run(
+        "git",
+        &["submodule", "foreach", "--quiet", "--recursive", DIRTY_SUBMODULE_CMD],
+        None,
+    )?;
+    if output.success() {
+        status.dirty_submodules =
+            output.stdout.lines().map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect();
+        status.uncommitted.has_dirty_submodules = !status.dirty_submodules.is_empty();
+    }
+    Ok(())
+}
+
+/// Record how far the branch is ahead of its upstream, if it has one.
+fn check_ahead_of_upstream(runner: &dyn CommandRunner, status: &mut GitStatus) {
     let rev_list = runner.run("git", &["rev-list", "--count", "@{upstream}..HEAD"], None);
     if let Ok(output) = rev_list {
         if output.success() {
@@ -123,8 +339,91 @@ pub fn check_uncommitted_changes(runner: &dyn CommandRunner) -> Result<GitStatus
             }
         }
     }
+}
 
-    Ok(status)
+/// List the commit SHAs of all stash entries, newest first.
+///
+/// # Errors
+///
+/// Returns an error if the git command cannot be run.
+pub fn stash_shas(runner: &dyn CommandRunner) -> Result<Vec<String>> {
+    let output = runner.run("git", &["stash", "list", "--format=%H"], None)?;
+    if !output.success() {
+        return Ok(Vec::new());
+    }
+    Ok(output.stdout.lines().map(str::trim).filter(|s| !s.is_empty()).map(String::from).collect())
+}
+
+/// Get the commit SHA of the most recent stash entry, if any.
+///
+/// # Errors
+///
+/// Returns an error if the git command cannot be run.
+pub fn latest_stash(runner: &dyn CommandRunner) -> Result<Option<String>> {
+    let output = runner.run("git", &["rev-parse", "-q", "--verify", "refs/stash"], None)?;
+    let sha = output.stdout.trim();
+    Ok((output.success() && !sha.is_empty()).then(|| sha.to_string()))
+}
+
+/// How the current branch relates to its upstream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpstreamStatus {
+    /// The branch tracks an upstream.
+    Tracking {
+        /// Commits on `HEAD` not on the upstream.
+        ahead: u32,
+        /// Commits on the upstream not on `HEAD`.
+        behind: u32,
+    },
+    /// The branch has no upstream configured.
+    Missing {
+        /// Commits on `HEAD` not on any remote-tracking branch.
+        unpushed: u32,
+    },
+    /// The relationship could not be determined (e.g. no commits yet, or no
+    /// remotes to push to).
+    Unknown,
+}
+
+impl UpstreamStatus {
+    /// Whether the branch and its upstream each have commits the other lacks.
+    #[must_use]
+    pub const fn is_diverged(&self) -> bool {
+        matches!(self, Self::Tracking { ahead, behind } if *ahead > 0 && *behind > 0)
+    }
+}
+
+/// Determine how the current branch relates to its upstream.
+///
+/// # Errors
+///
+/// Returns an error if git commands cannot be run.
+pub fn upstream_status(runner: &dyn CommandRunner) -> Result<UpstreamStatus> {
+    let output =
+        runner.run("git", &["rev-list", "--left-right", "--count", "@{upstream}...HEAD"], None)?;
+    if output.success() {
+        let mut counts = output.stdout.split_whitespace().map(str::parse::<u32>);
+        return Ok(match (counts.next(), counts.next()) {
+            (Some(Ok(behind)), Some(Ok(ahead))) => UpstreamStatus::Tracking { ahead, behind },
+            _ => UpstreamStatus::Unknown,
+        });
+    }
+
+    // With no remotes every commit would count as unpushed, but there is
+    // nowhere to push them
+    let remotes = runner.run("git", &["remote"], None)?;
+    if remotes.success() && remotes.stdout.trim().is_empty() {
+        return Ok(UpstreamStatus::Unknown);
+    }
+
+    // No upstream: count commits that no remote-tracking branch contains
+    let output = runner.run("git", &["rev-list", "--count", "HEAD", "--not", "--remotes"], None)?;
+    if output.success() {
+        if let Ok(unpushed) = output.stdout.trim().parse::<u32>() {
+            return Ok(UpstreamStatus::Missing { unpushed });
+        }
+    }
+    Ok(UpstreamStatus::Unknown)
 }
 
 #[cfg(test)]
@@ -135,10 +434,12 @@ mod tests {
 
     #[test]
     fn test_uncommitted_changes_has_changes() {
-        let changes =
-            UncommittedChanges { has_unstaged: true, has_staged: false, has_untracked: false };
+        let changes = UncommittedChanges { has_unstaged: true, ..Default::default() };
         assert!(changes.has_changes());
 
+        let submodules = UncommittedChanges { has_dirty_submodules: true, ..Default::default() };
+        assert!(submodules.has_changes());
+
         let no_changes = UncommittedChanges::default();
         assert!(!no_changes.has_changes());
     }
@@ -146,12 +447,48 @@ mod tests {
     #[test]
     fn test_uncommitted_changes_description() {
         let changes =
-            UncommittedChanges { has_unstaged: true, has_staged: true, has_untracked: false };
+            UncommittedChanges { has_unstaged: true, has_staged: true, ..Default::default() };
         assert_eq!(changes.description(), "unstaged changes, staged changes");
 
-        let all_changes =
-            UncommittedChanges { has_unstaged: true, has_staged: true, has_untracked: true };
-        assert_eq!(all_changes.description(), "unstaged changes, staged changes, untracked files");
+        let all_changes = UncommittedChanges {
+            has_unstaged: true,
+            has_staged: true,
+            has_untracked: true,
+            has_dirty_submodules: true,
+        };
+        assert_eq!(
+            all_changes.description(),
+            "unstaged changes, staged changes, untracked files, dirty submodules"
+        );
+    }
+
+    #[test]
+    fn test_operation_in_progress() {
+        let dir = tempfile::TempDir::new().unwrap();
+        assert_eq!(operation_in_progress(dir.path()), None);
+
+        let git_dir = dir.path().join(".git");
+        std::fs::create_dir(&git_dir).unwrap();
+        assert_eq!(operation_in_progress(dir.path()), None);
+        std::fs::write(git_dir.join("CHERRY_PICK_HEAD"), "abc\n").unwrap();
+        assert_eq!(operation_in_progress(dir.path()), Some(GitOperation::CherryPick));
+        std::fs::create_dir(git_dir.join("rebase-merge")).unwrap();
+        assert_eq!(operation_in_progress(dir.path()), Some(GitOperation::Rebase));
+    }
+
+    #[test]
+    fn test_operation_in_progress_in_linked_worktree() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let git_dir = dir.path().join("main/.git/worktrees/feature");
+        std::fs::create_dir_all(&git_dir).unwrap();
+        std::fs::write(git_dir.join("MERGE_HEAD"), "abc\n").unwrap();
+        let work_tree = dir.path().join("feature");
+        std::fs::create_dir(&work_tree).unwrap();
+        std::fs::write(work_tree.join(".git"), "gitdir: ../main/.git/worktrees/feature\n").unwrap();
+
+        let operation = operation_in_progress(&work_tree).unwrap();
+        assert_eq!(operation, GitOperation::Merge);
+        assert_eq!(operation.command(), "merge");
     }
 
     #[test]
@@ -405,4 +742,246 @@ mod tests {
         assert!(!status.uncommitted.has_untracked);
         assert_eq!(status.staged_files, vec!["src/main.rs"]);
     }
+
+    fn output(exit_code: i32, stdout: &str) -> CommandOutput {
+        CommandOutput { exit_code, stdout: stdout.to_string(), stderr: String::new() }
+    }
+
+    #[test]
+    fn test_upstream_status_tracking() {
+        let mut runner = MockCommandRunner::new();
+        runner.expect(
+            "git",
+            &["rev-list", "--left-right", "--count", "@{upstream}...HEAD"],
+            output(0, "2\t3\n"),
+        );
+
+        let status = upstream_status(&runner).unwrap();
+        assert_eq!(status, UpstreamStatus::Tracking { ahead: 3, behind: 2 });
+        assert!(status.is_diverged());
+    }
+
+    #[test]
+    fn test_upstream_status_ahead_only_is_not_diverged() {
+        let mut runner = MockCommandRunner::new();
+        runner.expect(
+            "git",
+            &["rev-list", "--left-right", "--count", "@{upstream}...HEAD"],
+            output(0, "0\t1\n"),
+        );
+
+        let status = upstream_status(&runner).unwrap();
+        assert_eq!(status, UpstreamStatus::Tracking { ahead: 1, behind: 0 });
+        assert!(!status.is_diverged());
+    }
+
+    #[test]
+    fn test_upstream_status_missing_upstream() {
+        let mut runner = MockCommandRunner::new();
+        runner.expect(
+            "git",
+            &["rev-list", "--left-right", "--count", "@{upstream}...HEAD"],
+            output(128, ""),
+        );
+        runner.expect("git", &["remote"], output(0, "origin\n"));
+        runner.expect(
+            "git",
+            &["rev-list", "--count", "HEAD", "--not", "--remotes"],
+            output(0, "4\n"),
+        );
+
+        assert_eq!(upstream_status(&runner).unwrap(), UpstreamStatus::Missing { unpushed: 4 });
+    }
+
+    #[test]
+    fn test_upstream_status_without_remotes_is_unknown() {
+        let mut runner = MockCommandRunner::new();
+        runner.expect(
+            "git",
+            &["rev-list", "--left-right", "--count", "@{upstream}...HEAD"],
+            output(128, ""),
+        );
+        runner.expect("git", &["remote"], output(0, ""));
+
+        assert_eq!(upstream_status(&runner).unwrap(), UpstreamStatus::Unknown);
+        runner.verify();
+    }
+
+    #[test]
+    fn test_upstream_status_unknown() {
+        let mut runner = MockCommandRunner::new();
+        runner.expect(
+            "git",
+            &["rev-list", "--left-right", "--count", "@{upstream}...HEAD"],
+            output(128, ""),
+        );
+        runner.expect("git", &["remote"], output(0, "origin\n"));
+        runner.expect(
+            "git",
+            &["rev-list", "--count", "HEAD", "--not", "--remotes"],
+            output(128, ""),
+        );
+
+        assert_eq!(upstream_status(&runner).unwrap(), UpstreamStatus::Unknown);
+    }
+
+    #[test]
+    fn test_check_working_tree_dirty_submodules() {
+        let empty = || output(0, "");
+        let mut runner = MockCommandRunner::new();
+        runner.expect("git", &["diff", "--stat"], empty());
+        runner.expect("git", &["diff", "--cached", "--stat"], empty());
+        runner.expect("git", &["ls-files", "--others", "--exclude-standard"], empty());
+        runner.expect(
+            "git",
+            &["submodule", "foreach", "--quiet", "--recursive", DIRTY_SUBMODULE_CMD],
+            output(0, "vendor/lib\nvendor/lib/nested\n"),
+        );
+        runner.expect("git", &["rev-list", "--count", "@{upstream}..HEAD"], output(0, "0\n"));
+
+        let status = check_working_tree(&runner, true).unwrap();
+        assert!(status.uncommitted.has_dirty_submodules);
+        assert!(status.uncommitted.has_changes());
+        assert_eq!(status.dirty_submodules, vec!["vendor/lib", "vendor/lib/nested"]);
+    }
+
+    #[test]
+    fn test_check_working_tree_clean_submodules() {
+        let empty = || output(0, "");
+        let mut runner = MockCommandRunner::new();
+        runner.expect("git", &["diff", "--stat"], empty());
+        runner.expect("git", &["diff", "--cached", "--stat"], empty());
+        runner.expect("git", &["ls-files", "--others", "--exclude-standard"], empty());
+        runner.expect(
+            "git",
+            &["submodule", "foreach", "--quiet", "--recursive", DIRTY_SUBMODULE_CMD],
+            empty(),
+        );
+        runner.expect("git", &["rev-list", "--count", "@{upstream}..HEAD"], output(0, "0\n"));
+
+        let status = check_working_tree(&runner, true).unwrap();
+        assert!(!status.uncommitted.has_changes());
+        runner.verify();
+    }
+
+    #[test]
+    fn test_stash_shas() {
+        let mut runner = MockCommandRunner::new();
+        runner.expect("git", &["stash", "list", "--format=%H"], output(0, "aaa\nbbb\n"));
+
+        assert_eq!(stash_shas(&runner).unwrap(), vec!["aaa", "bbb"]);
+    }
+
+    #[test]
+    fn test_stash_shas_failure_is_empty() {
+        let mut runner = MockCommandRunner::new();
+        runner.expect("git", &["stash", "list", "--format=%H"], output(128, ""));
+
+        assert!(stash_shas(&runner).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_latest_stash() {
+        let mut runner = MockCommandRunner::new();
+        runner.expect("git", &["rev-parse", "-q", "--verify", "refs/stash"], output(0, "abc\n"));
+        runner.expect("git", &["rev-parse", "-q", "--verify", "refs/stash"], output(1, ""));
+
+        assert_eq!(latest_stash(&runner).unwrap(), Some("abc".to_string()));
+        assert_eq!(latest_stash(&runner).unwrap(), None);
+    }
+
+    fn success(stdout: &str) -> CommandOutput {
+        CommandOutput { exit_code: 0, stdout: stdout.to_string(), stderr: String::new() }
+    }
+
+    fn expect_git_paths(runner: &mut MockCommandRunner) {
+        runner.expect(
+            "git",
+            &["rev-parse", "--git-path", "index", "--git-path", "info/exclude"],
+            success(".git/index\n.git/info/exclude\n"),
+        );
+    }
+
+    #[test]
+    fn test_check_working_tree_fast_scoped() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut runner = MockCommandRunner::new();
+        runner.expect(
+            "git",
+            &[
+                "status",
+                "--porcelain",
+                "-z",
+                "--no-renames",
+                "--untracked-files=normal",
+                "--",
+                "services/api",
+            ],
+            success(
+                " M services/api/a.rs\0MM services/api/b.rs\0A  services/api/c.rs\0\
+                 ?? services/api/new/\0",
+            ),
+        );
+        runner.expect("git", &["rev-list", "--count", "@{upstream}..HEAD"], success("2\n"));
+        let policy =
+            GitStatusPolicy { paths: vec!["services/api".to_string()], ..Default::default() };
+
+        let status = check_working_tree_fast(&runner, &policy, dir.path()).unwrap();
+        runner.verify();
+        assert_eq!(status.unstaged_files, vec!["services/api/a.rs", "services/api/b.rs"]);
+        assert_eq!(status.staged_files, vec!["services/api/b.rs", "services/api/c.rs"]);
+        assert_eq!(status.untracked_files, vec!["services/api/new/"]);
+        assert_eq!(
+            status.uncommitted.description(),
+            "unstaged changes, staged changes, untracked files"
+        );
+        assert_eq!(status.commits_ahead, 2);
+    }
+
+    #[test]
+    fn test_check_working_tree_fast_caches_untracked() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(paths::project_data_dir(dir.path())).unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        let policy = GitStatusPolicy { cache_untracked: true, ..Default::default() };
+        let full_status = [
+            "status",
+            "--porcelain",
+            "-z",
+            "--no-renames",
+            "--untracked-files=normal",
+            "--ignored",
+        ];
+        let tracked_status =
+            ["status", "--porcelain", "-z", "--no-renames", "--untracked-files=no"];
+        let mut runner = MockCommandRunner::new();
+
+        // Nothing cached: list everything, and cache the untracked list
+        runner.expect("git", &full_status, success("?? notes.txt\0!! target/\0"));
+        expect_git_paths(&mut runner);
+        runner.expect("git", &["rev-list", "--count", "@{upstream}..HEAD"], success("0\n"));
+        let status = check_working_tree_fast(&runner, &policy, dir.path()).unwrap();
+        assert_eq!(status.untracked_files, vec!["notes.txt"]);
+        runner.verify();
+
+        // Nothing has changed: only tracked files are checked
+        expect_git_paths(&mut runner);
+        runner.expect("git", &tracked_status, success(" M src/lib.rs\0"));
+        runner.expect("git", &["rev-list", "--count", "@{upstream}..HEAD"], success("0\n"));
+        let status = check_working_tree_fast(&runner, &policy, dir.path()).unwrap();
+        assert_eq!(status.untracked_files, vec!["notes.txt"]);
+        assert_eq!(status.unstaged_files, vec!["src/lib.rs"]);
+        assert!(status.uncommitted.has_untracked);
+        runner.verify();
+
+        // A new directory changes the key, so everything is listed again
+        std::fs::create_dir(dir.path().join("src/new")).unwrap();
+        expect_git_paths(&mut runner);
+        runner.expect("git", &full_status, success("?? notes.txt\0?? src/new/\0!! target/\0"));
+        expect_git_paths(&mut runner);
+        runner.expect("git", &["rev-list", "--count", "@{upstream}..HEAD"], success("0\n"));
+        let status = check_working_tree_fast(&runner, &policy, dir.path()).unwrap();
+        assert_eq!(status.untracked_files, vec!["notes.txt", "src/new/"]);
+        runner.verify();
+    }
 }
@@ -0,0 +1,237 @@
+//! The untracked file list kept between stop hook runs.
+//!
+//! Listing untracked files means reading every directory in the tree and
+//! matching its entries against the ignore rules, which dominates the status
+//! check in a large repository. The list can only change when a directory
+//! gains or loses entries, an ignore file changes, or the index changes, so it
+//! is kept with a key built from the modification times of those, and reused
+//! while the key is the same. Directories that are untracked or ignored as a
+//! whole are not looked inside, as git does not list their contents either.
+
+use crate::error::Result;
+use crate::traits::CommandRunner;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// The untracked and ignored entries from the last full status.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub(super) struct UntrackedCache {
+    /// The key the lists were recorded with.
+    pub key: String,
+    /// Untracked files, and directories (ending in `/`) with nothing tracked.
+    pub untracked: Vec<String>,
+    /// Ignored files, and directories (ending in `/`) that are ignored whole.
+    pub ignored: Vec<String>,
+}
+
+impl UntrackedCache {
+    /// Read the cache, if there is a readable one.
+    pub fn load(path: &Path) -> Option<Self> {
+        let contents = std::fs::read_to_string(path).ok()?;
+        serde_json::from_str(&contents).ok()
+    }
+
+    /// Write the cache. Nothing is written if the directory for it does not
+    /// exist, since creating one in the project would itself be a change.
+    pub fn save(&self, path: &Path) {
+        if !path.parent().is_some_and(Path::is_dir) {
+            return;
+        }
+        let result = serde_json::to_string(self)
+            .map_err(std::io::Error::other)
+            .and_then(|json| std::fs::write(path, json));
+        if let Err(e) = result {
+            tracing::debug!("Failed to write the untracked file cache: {e}");
+        }
+    }
+
+    /// Compute the key for the tree as it is now, given these lists.
+    ///
+    /// Returns `None` if git cannot say where the index is.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if git cannot be run.
+    pub fn current_key(
+        &self,
+        runner: &dyn CommandRunner,
+        work_tree: &Path,
+        paths: &[String],
+        skip: &Path,
+    ) -> Result<Option<String>> {
+        let output = runner.run(
+            "git",
+            &["rev-parse", "--git-path", "index", "--git-path", "info/exclude"],
+            None,
+        )?;
+        if !output.success() {
+            return Ok(None);
+        }
+
+        let mut hasher = DefaultHasher::new();
+        paths.hash(&mut hasher);
+        for git_path in output.stdout.lines().map(str::trim).filter(|p| !p.is_empty()) {
+            modified(&work_tree.join(git_path)).hash(&mut hasher);
+        }
+
+        let mut skipped: HashSet<PathBuf> = self
+            .untracked
+            .iter()
+            .chain(&self.ignored)
+            .filter_map(|entry| entry.strip_suffix('/'))
+            .map(PathBuf::from)
+            .collect();
+        if let Ok(skip) = skip.strip_prefix(work_tree) {
+            skipped.insert(skip.to_path_buf());
+        }
+        let roots: Vec<PathBuf> = if paths.is_empty() {
+            vec![PathBuf::new()]
+        } else {
+            paths.iter().map(|p| PathBuf::from(p.trim_end_matches('/'))).collect()
+        };
+        for root in roots {
+            hash_directory(work_tree, &root, &skipped, &mut hasher);
+        }
+        Ok(Some(format!("{:x}", hasher.finish())))
+    }
+}
+
+/// Hash the modification times of a directory, its `.gitignore`, and the
+/// directories below it, stopping at skipped directories and nested
+/// repositories.
+fn hash_directory(
+    work_tree: &Path,
+    dir: &Path,
+    skipped: &HashSet<PathBuf>,
+    hasher: &mut DefaultHasher,
+) {
+    let full = work_tree.join(dir);
+    let Ok(entries) = std::fs::read_dir(&full) else {
+        return;
+    };
+    let mut entries: Vec<_> = entries.flatten().collect();
+    entries.sort_by_key(std::fs::DirEntry::file_name);
+    let nested_repo = entries.iter().any(|entry| entry.file_name() == ".git");
+    if nested_repo && !dir.as_os_str().is_empty() {
+        return;
+    }
+
+    dir.hash(hasher);
+    modified(&full).hash(hasher);
+    for entry in entries {
+        let name = entry.file_name();
+        if name == ".git" {
+            continue;
+        }
+        if name == ".gitignore" {
+            modified(&entry.path()).hash(hasher);
+        } else if entry.file_type().is_ok_and(|t| t.is_dir()) {
+            let child = dir.join(&name);
+            if !skipped.contains(&child) {
+                hash_directory(work_tree, &child, skipped, hasher);
+            }
+        }
+    }
+}
+
+/// When a file was last modified, if it exists.
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockCommandRunner;
+    use crate::traits::CommandOutput;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    fn expect_git_paths(runner: &mut MockCommandRunner) {
+        runner.expect(
+            "git",
+            &["rev-parse", "--git-path", "index", "--git-path", "info/exclude"],
+            CommandOutput {
+                exit_code: 0,
+                stdout: ".git/index\n.git/info/exclude\n".to_string(),
+                stderr: String::new(),
+            },
+        );
+    }
+
+    /// Move a file's modification time, since a test can run within the
+    /// filesystem's timestamp resolution.
+    fn touch(path: &Path, seconds: u64) {
+        let file = std::fs::File::options().append(true).open(path).unwrap();
+        file.set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(seconds)).unwrap();
+    }
+
+    fn key(cache: &UntrackedCache, dir: &Path, paths: &[String]) -> String {
+        let mut runner = MockCommandRunner::new();
+        expect_git_paths(&mut runner);
+        let key = cache.current_key(&runner, dir, paths, &dir.join(".data")).unwrap().unwrap();
+        runner.verify();
+        key
+    }
+
+    #[test]
+    fn test_key_changes_with_directories_ignore_files_and_index() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join(".git/info")).unwrap();
+        std::fs::write(root.join(".git/index"), "").unwrap();
+        std::fs::create_dir_all(root.join("src/nested")).unwrap();
+        std::fs::create_dir_all(root.join("target/debug")).unwrap();
+        std::fs::create_dir_all(root.join(".data")).unwrap();
+        std::fs::write(root.join(".gitignore"), "target/\n").unwrap();
+        let cache = UntrackedCache { ignored: vec!["target/".to_string()], ..Default::default() };
+
+        let initial = key(&cache, root, &[]);
+        assert_eq!(key(&cache, root, &[]), initial);
+
+        // Changes in ignored directories and the data directory do not count
+        std::fs::write(root.join("target/debug/build.o"), "").unwrap();
+        std::fs::write(root.join(".data/untracked-cache.json"), "").unwrap();
+        assert_eq!(key(&cache, root, &[]), initial);
+
+        std::fs::write(root.join("src/nested/new.rs"), "").unwrap();
+        let with_file = key(&cache, root, &[]);
+        assert_ne!(with_file, initial);
+
+        touch(&root.join(".gitignore"), 1_000);
+        let with_ignore = key(&cache, root, &[]);
+        assert_ne!(with_ignore, with_file);
+
+        touch(&root.join(".git/index"), 2_000);
+        assert_ne!(key(&cache, root, &[]), with_ignore);
+
+        // Scoping to a subtree changes the key, and ignores the rest
+        let scoped = key(&cache, root, &["src/".to_string()]);
+        assert_ne!(scoped, key(&cache, root, &[]));
+        std::fs::create_dir(root.join("docs")).unwrap();
+        assert_eq!(key(&cache, root, &["src/".to_string()]), scoped);
+    }
+
+    #[test]
+    fn test_load_and_save() {
+        let dir = TempDir::new().unwrap();
+        let cache = UntrackedCache {
+            key: "abc".to_string(),
+            untracked: vec!["notes.txt".to_string()],
+            ignored: vec!["target/".to_string()],
+        };
+
+        let missing_dir = dir.path().join("missing/cache.json");
+        cache.save(&missing_dir);
+        assert!(!missing_dir.parent().unwrap().exists());
+
+        let path = dir.path().join("cache.json");
+        assert_eq!(UntrackedCache::load(&path), None);
+        cache.save(&path);
+        assert_eq!(UntrackedCache::load(&path), Some(cache));
+    }
+}
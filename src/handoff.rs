@@ -0,0 +1,278 @@
+//! Session handoff summaries.
+//!
+//! When the stop hook finally allows a stop after the agent modified the
+//! project, a sub-agent writes a digest of the run (work items completed,
+//! commits, open questions, assumptions made and next steps) to
+//! `.claude-reliability/handoffs/<timestamp>.md`. The latest one can be
+//! printed with `claude-reliability handoff latest`.
+
+use crate::error::Result;
+use crate::paths;
+use crate::tasks::{self, Status, TaskFilter, TaskStore};
+use crate::traits::{CommandOutput, CommandRunner, HandoffContext, SubAgent};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use std::path::{Path, PathBuf};
+
+/// Format of handoff file names (without the `.md` extension).
+const FILENAME_FORMAT: &str = "%Y-%m-%dT%H-%M-%SZ";
+
+/// Format of task timestamps in the task database.
+const TASK_TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// Maximum number of commits and open work items included in a handoff.
+const MAX_ITEMS: usize = 20;
+
+/// Find the most recent handoff summary.
+#[must_use]
+pub fn latest_handoff(base_dir: &Path) -> Option<PathBuf> {
+    let entries = std::fs::read_dir(paths::project_handoffs_dir(base_dir)).ok()?;
+    entries
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| handoff_time(p).is_some())
+        .max_by_key(|p| p.file_name().map(std::ffi::OsStr::to_os_string))
+}
+
+/// Get the time a handoff was written from its file name.
+fn handoff_time(path: &Path) -> Option<DateTime<Utc>> {
+    if path.extension()? != "md" {
+        return None;
+    }
+    let stem = path.file_stem()?.to_str()?;
+    NaiveDateTime::parse_from_str(stem, FILENAME_FORMAT).ok().map(|t| t.and_utc())
+}
+
+/// Get the subjects of commits made since the previous handoff.
+fn recent_commits(runner: &dyn CommandRunner, since: Option<DateTime<Utc>>) -> Vec<String> {
+    let limit = MAX_ITEMS.to_string();
+    let mut args = vec!["log".to_string(), "--format=%s".to_string(), "-n".to_string(), limit];
+    if let Some(since) = since {
+        args.push(format!("--since={}", since.to_rfc3339()));
+    }
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    runner
+        .run("git", &args, None)
+        .ok()
+        .filter(CommandOutput::success)
+        .map(|o| {
+            o.stdout.lines().map(str::trim).filter(|s| !s.is_empty()).map(String::from).collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Gather what happened since the previous handoff.
+fn gather_context(
+    base_dir: &Path,
+    runner: Option<&dyn CommandRunner>,
+    final_message: Option<&str>,
+) -> HandoffContext {
+    let since = latest_handoff(base_dir).as_deref().and_then(handoff_time);
+    let since_stamp = since.map(|t| t.format(TASK_TIMESTAMP_FORMAT).to_string());
+
+    let mut context = HandoffContext {
+        commits: runner.map(|r| recent_commits(r, since)).unwrap_or_default(),
+        open_questions: tasks::list_unanswered_questions(base_dir)
+            .into_iter()
+            .map(|q| q.text)
+            .collect(),
+        final_message: final_message.map(String::from),
+        ..Default::default()
+    };
+
+    if let Some(store) = tasks::open_project_store(base_dir) {
+        let titles = |status: Status| {
+            store
+                .list_tasks(TaskFilter { status: Some(status), ..Default::default() })
+                .unwrap_or_default()
+                .into_iter()
+        };
+        context.completed_work_items = titles(Status::Complete)
+            .filter(|t| since_stamp.as_ref().map_or(true, |s| t.updated_at >= *s))
+            .map(|t| t.title)
+            .collect();
+        context.open_work_items = titles(Status::Open).take(MAX_ITEMS).map(|t| t.title).collect();
+        context.assumptions = store
+            .list_questions(false)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|q| q.assumed)
+            .map(|q| {
+                let answer = q.answer.unwrap_or_default();
+                format!("{} Assumed: {answer} (question {})", q.text, q.id)
+            })
+            .collect();
+        context.problems = store
+            .list_problems(None)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|p| since_stamp.as_ref().map_or(true, |s| p.created_at >= *s))
+            .map(|p| {
+                let summary = p
+                    .suggested_action
+                    .unwrap_or_else(|| p.explanation.lines().next().unwrap_or("").to_string());
+                let task = p.task_id.map(|id| format!(" (work item {id})")).unwrap_or_default();
+                format!("[{}] {summary}{task}", p.category)
+            })
+            .collect();
+    }
+    context
+}
+
+/// Write a handoff summary for the run that just finished.
+///
+/// Commits are only looked up when a command runner is given. Returns the
+/// path the summary was written to.
+///
+/// # Errors
+///
+/// Returns an error if the summary cannot be written.
+pub fn write_handoff(
+    base_dir: &Path,
+    runner: Option<&dyn CommandRunner>,
+    sub_agent: &dyn SubAgent,
+    final_message: Option<&str>,
+) -> Result<PathBuf> {
+    let context = gather_context(base_dir, runner, final_message);
+    let summary = sub_agent.write_handoff(&context)?;
+
+    let dir = paths::project_handoffs_dir(base_dir);
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("{}.md", Utc::now().format(FILENAME_FORMAT)));
+    std::fs::write(&path, summary)?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tasks::{Priority, SqliteTaskStore, TaskUpdate};
+    use crate::testing::{FailingSubAgent, MockCommandRunner, MockSubAgent};
+    use tempfile::TempDir;
+
+    fn store(dir: &TempDir) -> SqliteTaskStore {
+        let db_path = paths::project_db_path(dir.path());
+        std::fs::create_dir_all(db_path.parent().unwrap()).unwrap();
+        SqliteTaskStore::new(&db_path).unwrap()
+    }
+
+    fn write_file(dir: &TempDir, name: &str) {
+        let handoffs = paths::project_handoffs_dir(dir.path());
+        std::fs::create_dir_all(&handoffs).unwrap();
+        std::fs::write(handoffs.join(name), name).unwrap();
+    }
+
+    #[test]
+    fn test_latest_handoff() {
+        let dir = TempDir::new().unwrap();
+        assert!(latest_handoff(dir.path()).is_none());
+
+        write_file(&dir, "2026-01-02T10-00-00Z.md");
+        write_file(&dir, "2026-01-03T09-00-00Z.md");
+        write_file(&dir, "notes.md");
+        let latest = latest_handoff(dir.path()).unwrap();
+        assert!(latest.ends_with("2026-01-03T09-00-00Z.md"));
+    }
+
+    #[test]
+    fn test_handoff_time() {
+        let time = handoff_time(Path::new("2026-01-03T09-30-15Z.md")).unwrap();
+        assert_eq!(time.to_rfc3339(), "2026-01-03T09:30:15+00:00");
+        assert!(handoff_time(Path::new("2026-01-03T09-30-15Z.txt")).is_none());
+    }
+
+    #[test]
+    fn test_gather_context() {
+        let dir = TempDir::new().unwrap();
+        let store = store(&dir);
+        let done = store.create_task("Write a parser", "", Priority::High).unwrap();
+        store
+            .update_task(
+                &done.id,
+                TaskUpdate { status: Some(Status::Complete), ..Default::default() },
+            )
+            .unwrap();
+        let benchmarks = store.create_task("Add benchmarks", "", Priority::Low).unwrap();
+        store
+            .record_problem("environment", "No GPU", Some("Run on CI"), Some(&benchmarks.id), "s")
+            .unwrap();
+        store.record_problem("other", "Confused\nby the spec", None, None, "s").unwrap();
+        store.create_question("Which auth provider?").unwrap();
+        let region = store.create_question("Which region?").unwrap();
+        store.assume_answer(&region.id, "eu-west-1").unwrap();
+
+        let mut runner = MockCommandRunner::new();
+        runner.expect(
+            "git",
+            &["log", "--format=%s", "-n", "20"],
+            CommandOutput {
+                exit_code: 0,
+                stdout: "Add parser\n".to_string(),
+                stderr: String::new(),
+            },
+        );
+
+        let context = gather_context(dir.path(), Some(&runner), Some("Done."));
+        assert_eq!(context.completed_work_items, vec!["Write a parser"]);
+        assert_eq!(context.open_work_items, vec!["Add benchmarks"]);
+        assert_eq!(context.open_questions, vec!["Which auth provider?"]);
+        assert_eq!(
+            context.assumptions,
+            vec![format!("Which region? Assumed: eu-west-1 (question {})", region.id)]
+        );
+        assert_eq!(
+            context.problems,
+            vec![
+                "[other] Confused".to_string(),
+                format!("[environment] Run on CI (work item {})", benchmarks.id),
+            ]
+        );
+        assert_eq!(context.commits, vec!["Add parser"]);
+        assert_eq!(context.final_message.as_deref(), Some("Done."));
+        runner.verify();
+    }
+
+    #[test]
+    fn test_gather_context_since_previous_handoff() {
+        let dir = TempDir::new().unwrap();
+        let store = store(&dir);
+        let done = store.create_task("Old work", "", Priority::High).unwrap();
+        store
+            .update_task(
+                &done.id,
+                TaskUpdate { status: Some(Status::Complete), ..Default::default() },
+            )
+            .unwrap();
+        write_file(&dir, "2999-01-01T00-00-00Z.md");
+
+        let mut runner = MockCommandRunner::new();
+        runner.expect(
+            "git",
+            &["log", "--format=%s", "-n", "20", "--since=2999-01-01T00:00:00+00:00"],
+            CommandOutput { exit_code: 0, stdout: String::new(), stderr: String::new() },
+        );
+
+        let context = gather_context(dir.path(), Some(&runner), None);
+        assert!(context.completed_work_items.is_empty());
+        assert!(context.commits.is_empty());
+        runner.verify();
+    }
+
+    #[test]
+    fn test_write_handoff() {
+        let dir = TempDir::new().unwrap();
+        let mut sub_agent = MockSubAgent::new();
+        sub_agent.expect_handoff("# Session Handoff\n");
+
+        let path = write_handoff(dir.path(), None, &sub_agent, None).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "# Session Handoff\n");
+        assert_eq!(latest_handoff(dir.path()), Some(path));
+    }
+
+    #[test]
+    fn test_write_handoff_sub_agent_failure() {
+        let dir = TempDir::new().unwrap();
+        let sub_agent = FailingSubAgent::new("unavailable");
+
+        assert!(write_handoff(dir.path(), None, &sub_agent, None).is_err());
+        assert!(latest_handoff(dir.path()).is_none());
+    }
+}
@@ -11,7 +11,7 @@ use std::io::Write;
 use std::path::Path;
 
 /// Log file name within the data directory.
-const HOOK_EVENTS_FILE: &str = "hook-events.jsonl";
+pub(crate) const HOOK_EVENTS_FILE: &str = "hook-events.jsonl";
 
 /// Log a hook event if debug logging is enabled.
 ///
@@ -0,0 +1,350 @@
+//! API error and overload backoff state shared between hook types.
+//!
+//! The stop hook sees API errors in the transcript, while tools that call the
+//! API themselves (sub-agents, web fetches and searches) report theirs in the
+//! tool response. Errors reported by tools are counted in the session store,
+//! and an overloaded API sets a time before which it should not be called
+//! again. The stop hook adds the counted errors to those in the transcript, and
+//! the `PreToolUse` hook blocks API tools until the retry time, so the agent
+//! cannot keep calling an overloaded API through tools.
+
+use crate::hooks::post_tool_use::PostToolUseInput;
+use crate::hooks::{HookInput, PreToolUseOutput};
+use crate::session;
+use crate::transcript;
+use chrono::{DateTime, Utc};
+use std::path::Path;
+
+/// Tools whose calls go through the API.
+const API_TOOLS: [&str; 3] = ["Task", "WebFetch", "WebSearch"];
+
+/// Default base backoff time for overloaded errors (5 seconds).
+const DEFAULT_BACKOFF_BASE_SECS: u64 = 5;
+
+/// Default maximum backoff time for overloaded errors (2 minutes).
+const DEFAULT_BACKOFF_MAX_SECS: u64 = 120;
+
+/// Configuration for overloaded error backoff.
+#[derive(Debug, Clone, Copy)]
+pub struct BackoffConfig {
+    /// Base backoff time in seconds.
+    pub base_secs: u64,
+    /// Maximum total wait time in seconds before allowing stop.
+    pub max_secs: u64,
+}
+
+impl Default for BackoffConfig {
+    fn default() -> Self {
+        Self { base_secs: DEFAULT_BACKOFF_BASE_SECS, max_secs: DEFAULT_BACKOFF_MAX_SECS }
+    }
+}
+
+/// Result of calculating backoff for overloaded errors.
+#[derive(Debug, PartialEq, Eq)]
+pub enum OverloadedBackoffResult {
+    /// Should retry after sleeping for the specified duration.
+    Retry { sleep_secs: u64, error_count: u32 },
+    /// Should allow stop - exceeded maximum retry time.
+    AllowStop { error_count: u32, total_wait: u64 },
+}
+
+/// Calculate backoff for overloaded errors with default configuration.
+///
+/// Returns `Retry` with sleep duration if retries should continue,
+/// or `AllowStop` if maximum retry time has been exceeded.
+#[cfg(test)]
+pub fn calculate_overloaded_backoff(error_count: u32) -> OverloadedBackoffResult {
+    calculate_overloaded_backoff_with_config(error_count, &BackoffConfig::default())
+}
+
+/// Calculate backoff for overloaded errors with custom configuration.
+///
+/// Returns `Retry` with sleep duration if retries should continue,
+/// or `AllowStop` if maximum retry time has been exceeded.
+pub fn calculate_overloaded_backoff_with_config(
+    error_count: u32,
+    config: &BackoffConfig,
+) -> OverloadedBackoffResult {
+    // Calculate backoff: base * 2^(error_count - 1), capped at max
+    let backoff_secs = std::cmp::min(
+        config.base_secs.saturating_mul(1 << (error_count.saturating_sub(1))),
+        config.max_secs,
+    );
+
+    // Calculate total wait time so far: sum of all backoffs
+    // Sum of base * (1 + 2 + 4 + ... + 2^(n-1)) = base * (2^n - 1)
+    let capped_count = std::cmp::min(error_count, 6); // After 6 errors we're at max
+    let total_wait = config.base_secs.saturating_mul((1 << capped_count) - 1);
+
+    // If we've waited more than max total, allow stop
+    if total_wait > config.max_secs {
+        return OverloadedBackoffResult::AllowStop { error_count, total_wait };
+    }
+
+    // Add jitter (±20% of backoff time)
+    let jitter_range = backoff_secs / 5; // 20%
+    let sleep_secs = if jitter_range > 0 {
+        use std::time::{SystemTime, UNIX_EPOCH};
+        let seed = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos());
+        // Simple pseudo-random: take low bits of nanoseconds
+        // jitter_offset is in [0, 2 * jitter_range]
+        #[allow(clippy::cast_possible_truncation)] // Jitter is small, won't overflow u64
+        let jitter_offset = (seed % u128::from(jitter_range * 2 + 1)) as u64;
+
+        // Apply jitter: if offset > range, add (positive jitter); else subtract (negative jitter)
+        if jitter_offset > jitter_range {
+            backoff_secs.saturating_add(jitter_offset - jitter_range)
+        } else {
+            backoff_secs.saturating_sub(jitter_range - jitter_offset)
+        }
+    } else {
+        backoff_secs
+    };
+
+    OverloadedBackoffResult::Retry { sleep_secs, error_count }
+}
+
+/// The time `secs` seconds after `now`, if it can be represented.
+pub fn retry_time(now: DateTime<Utc>, secs: u64) -> Option<DateTime<Utc>> {
+    i64::try_from(secs)
+        .ok()
+        .and_then(chrono::Duration::try_seconds)
+        .and_then(|wait| now.checked_add_signed(wait))
+}
+
+/// Whole seconds left before the API may be called again, if it is backing off.
+pub fn backoff_remaining(base_dir: &Path, now: DateTime<Utc>) -> Option<i64> {
+    let retry_at = session::api_retry_at(base_dir)?;
+    (retry_at > now).then(|| (retry_at - now).num_seconds().max(1))
+}
+
+/// Block calls to API tools while the API is backing off.
+pub fn check_tool_backoff(input: &HookInput, base_dir: &Path) -> PreToolUseOutput {
+    let tool_name = input.tool_name.as_deref().unwrap_or_default();
+    if !API_TOOLS.contains(&tool_name) {
+        return PreToolUseOutput::allow(None);
+    }
+    backoff_remaining(base_dir, Utc::now()).map_or_else(
+        || PreToolUseOutput::allow(None),
+        |remaining| {
+            PreToolUseOutput::block(Some(format!(
+                "The API is overloaded. Wait another {remaining}s (for example with \
+                 `sleep {remaining}`) before using {tool_name} again."
+            )))
+        },
+    )
+}
+
+/// Whether a tool response reports an API error, and if so whether it was overloaded.
+fn response_error(response: &serde_json::Value) -> Option<bool> {
+    match response {
+        serde_json::Value::String(text) => {
+            let text = text.trim_start();
+            (text.starts_with("API Error:") && transcript::is_api_error_message(text))
+                .then(|| transcript::is_overloaded_error_text(text))
+        }
+        serde_json::Value::Array(values) => values.iter().find_map(response_error),
+        serde_json::Value::Object(fields) => fields.values().find_map(response_error),
+        _ => None,
+    }
+}
+
+/// Update the shared API error state from a finished call to an API tool.
+///
+/// An API error is counted, and an overloaded error starts a backoff. Any
+/// other result shows the API is working again and clears the state.
+///
+/// # Errors
+///
+/// Returns an error if the session store cannot be updated.
+pub fn record_tool_result(
+    input: &PostToolUseInput,
+    base_dir: &Path,
+    backoff: &BackoffConfig,
+) -> Result<(), String> {
+    let tool_name = input.tool_name.as_deref().unwrap_or_default();
+    if !API_TOOLS.contains(&tool_name) {
+        return Ok(());
+    }
+    let Some(overloaded) = input.tool_response.as_ref().and_then(response_error) else {
+        if session::api_error_state(base_dir) != crate::storage::ApiErrorState::default() {
+            session::clear_api_errors(base_dir)
+                .map_err(|e| format!("Failed to clear API errors: {e}"))?;
+        }
+        return Ok(());
+    };
+
+    let error_count = session::record_api_error(base_dir, overloaded)
+        .map_err(|e| format!("Failed to record API error: {e}"))?;
+    if overloaded {
+        if let OverloadedBackoffResult::Retry { sleep_secs, .. } =
+            calculate_overloaded_backoff_with_config(error_count, backoff)
+        {
+            session::set_api_retry_at(base_dir, retry_time(Utc::now(), sleep_secs))
+                .map_err(|e| format!("Failed to record API backoff: {e}"))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_calculate_overloaded_backoff_first_error() {
+        let result = calculate_overloaded_backoff(1);
+        match result {
+            OverloadedBackoffResult::Retry { sleep_secs, error_count } => {
+                // First error: 5s base, ±20% jitter (4-6s range)
+                assert!((4..=6).contains(&sleep_secs), "Expected 4-6s, got {sleep_secs}");
+                assert_eq!(error_count, 1);
+            }
+            OverloadedBackoffResult::AllowStop { .. } => {
+                panic!("Expected Retry, got AllowStop");
+            }
+        }
+    }
+
+    #[test]
+    fn test_calculate_overloaded_backoff_second_error() {
+        let result = calculate_overloaded_backoff(2);
+        match result {
+            OverloadedBackoffResult::Retry { sleep_secs, error_count } => {
+                // Second error: 10s base, ±20% jitter (8-12s range)
+                assert!((8..=12).contains(&sleep_secs), "Expected 8-12s, got {sleep_secs}");
+                assert_eq!(error_count, 2);
+            }
+            OverloadedBackoffResult::AllowStop { .. } => {
+                panic!("Expected Retry, got AllowStop");
+            }
+        }
+    }
+
+    #[test]
+    fn test_calculate_overloaded_backoff_exceeds_max() {
+        // After 6 errors, total wait is 5*(2^6-1) = 315s > 120s, so should allow stop
+        let result = calculate_overloaded_backoff(6);
+        match result {
+            OverloadedBackoffResult::AllowStop { error_count, total_wait } => {
+                assert_eq!(error_count, 6);
+                assert!(total_wait > 120, "Expected total_wait > 120, got {total_wait}");
+            }
+            OverloadedBackoffResult::Retry { .. } => {
+                panic!("Expected AllowStop, got Retry");
+            }
+        }
+    }
+
+    #[test]
+    fn test_calculate_overloaded_backoff_capped_at_120s() {
+        // At 5 errors, base backoff would be 5*16=80s, but total wait is 5*(2^5-1)=155s > 120s
+        // Actually, let's verify what happens at 5 errors:
+        // backoff = min(5*16, 120) = 80
+        // total_wait = 5*(2^5-1) = 155 > 120, so AllowStop
+        let result = calculate_overloaded_backoff(5);
+        match result {
+            OverloadedBackoffResult::AllowStop { error_count, total_wait } => {
+                assert_eq!(error_count, 5);
+                assert!(total_wait > 120, "Expected total_wait > 120, got {total_wait}");
+            }
+            OverloadedBackoffResult::Retry { .. } => {
+                panic!("Expected AllowStop at 5 errors due to total wait time");
+            }
+        }
+    }
+
+    #[test]
+    fn test_calculate_overloaded_backoff_zero_errors() {
+        // Edge case: 0 errors should still return Retry (won't happen in practice)
+        let result = calculate_overloaded_backoff(0);
+        match result {
+            OverloadedBackoffResult::Retry { sleep_secs, error_count } => {
+                // 0 errors: 5s * 2^(-1) would underflow, but saturating_sub handles it
+                // backoff = min(5*1, 120) = 5 (since 2^0=1 due to saturating_sub)
+                assert!(sleep_secs <= 6, "Expected <=6s, got {sleep_secs}");
+                assert_eq!(error_count, 0);
+            }
+            OverloadedBackoffResult::AllowStop { .. } => {
+                panic!("Expected Retry, got AllowStop");
+            }
+        }
+    }
+
+    #[test]
+    fn test_calculate_overloaded_backoff_jitter_positive() {
+        // Run multiple times to statistically exercise jitter branches
+        // The jitter is ±20%, so with base 10s (2nd error), range is 8-12s
+        for _ in 0..10 {
+            let result = calculate_overloaded_backoff(2);
+            if let OverloadedBackoffResult::Retry { sleep_secs, .. } = result {
+                // Should be in range 8-12
+                assert!((8..=12).contains(&sleep_secs), "Got {sleep_secs}");
+            }
+        }
+    }
+
+    fn tool_result(tool_name: &str, response: serde_json::Value) -> PostToolUseInput {
+        PostToolUseInput {
+            tool_name: Some(tool_name.to_string()),
+            tool_input: None,
+            tool_response: Some(response),
+        }
+    }
+
+    fn tool_call(tool_name: &str) -> HookInput {
+        HookInput { tool_name: Some(tool_name.to_string()), ..Default::default() }
+    }
+
+    #[test]
+    fn test_response_error() {
+        let overloaded = serde_json::json!({
+            "content": [{"type": "text", "text": "API Error: 529 {\"type\":\"overloaded_error\"}"}]
+        });
+        assert_eq!(response_error(&overloaded), Some(true));
+        assert_eq!(
+            response_error(&serde_json::json!("API Error: 400 invalid_request_error")),
+            Some(false)
+        );
+        // Pages that merely mention errors are not errors
+        assert_eq!(
+            response_error(&serde_json::json!({"result": "Retry on overloaded_error (529)"})),
+            None
+        );
+    }
+
+    #[test]
+    fn test_tool_errors_back_off_api_tools() {
+        let dir = TempDir::new().unwrap();
+        let backoff = BackoffConfig { base_secs: 30, max_secs: 1000 };
+        let overloaded = serde_json::json!("API Error: 529 overloaded_error");
+
+        record_tool_result(&tool_result("Task", overloaded.clone()), dir.path(), &backoff).unwrap();
+        record_tool_result(&tool_result("WebFetch", overloaded), dir.path(), &backoff).unwrap();
+        let state = session::api_error_state(dir.path());
+        assert_eq!(state.consecutive_errors, 2);
+        assert!(state.overloaded);
+        assert!(backoff_remaining(dir.path(), Utc::now()).unwrap() > 40);
+
+        assert!(check_tool_backoff(&tool_call("Task"), dir.path()).is_block());
+        assert!(!check_tool_backoff(&tool_call("Bash"), dir.path()).is_block());
+
+        // Other tools neither count nor clear errors
+        let ok = serde_json::json!({"result": "done"});
+        record_tool_result(&tool_result("Bash", ok.clone()), dir.path(), &backoff).unwrap();
+        assert_eq!(session::api_error_state(dir.path()).consecutive_errors, 2);
+
+        // A working API tool call clears everything
+        record_tool_result(&tool_result("WebSearch", ok), dir.path(), &backoff).unwrap();
+        assert_eq!(session::api_error_state(dir.path()), crate::storage::ApiErrorState::default());
+        assert!(!check_tool_backoff(&tool_call("Task"), dir.path()).is_block());
+    }
+
+    #[test]
+    fn test_retry_time() {
+        let now = Utc::now();
+        assert_eq!(retry_time(now, 30), Some(now + chrono::Duration::seconds(30)));
+        assert_eq!(retry_time(now, u64::MAX), None);
+    }
+}
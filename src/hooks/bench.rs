@@ -0,0 +1,364 @@
+//! Stop hook latency benchmarks (`bench`).
+//!
+//! A fixture fabricates a transcript and a work item database of a given size
+//! in a scratch directory, and runs the stop hook against them with a clean
+//! git repository answered from memory and canned sub-agent answers. What is
+//! measured is the hook's own work: reading the transcript and the database,
+//! and running the checks. The `hooks` criterion benchmarks and the `bench`
+//! command both use these fixtures, and `bench --assert-under` fails when a
+//! size's median run is over budget, so regressions in the hot path are caught.
+
+use super::simulate::{CannedSubAgent, Scratch};
+use super::stop::{run_stop_hook, StopHookConfig, StopHookResult};
+use super::HookInput;
+use crate::config::{MessageVerbosity, VcsKind};
+use crate::error::{Error, Result};
+use crate::paths;
+use crate::replay::EnvGuard;
+use crate::session;
+use crate::tasks::{Priority, SqliteTaskStore, Status, TaskStore, TaskUpdate};
+use crate::traits::{CommandOutput, CommandRunner, RunOptions};
+use chrono::{Duration as ChronoDuration, Utc};
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// How large a fabricated session is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BenchSize {
+    /// A short session in a new project.
+    Small,
+    /// A working session in an established project.
+    Medium,
+    /// A long session in a project with a large backlog.
+    Large,
+}
+
+impl BenchSize {
+    /// Every size, smallest first.
+    pub const ALL: [Self; 3] = [Self::Small, Self::Medium, Self::Large];
+
+    /// The size's name on the command line.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Small => "small",
+            Self::Medium => "medium",
+            Self::Large => "large",
+        }
+    }
+
+    /// How many turns the transcript has, each an edit and a reply.
+    #[must_use]
+    pub const fn transcript_turns(self) -> usize {
+        match self {
+            Self::Small => 10,
+            Self::Medium => 200,
+            Self::Large => 2_000,
+        }
+    }
+
+    /// How many work items the database has.
+    #[must_use]
+    pub const fn work_items(self) -> usize {
+        match self {
+            Self::Small => 10,
+            Self::Medium => 200,
+            Self::Large => 2_000,
+        }
+    }
+}
+
+impl FromStr for BenchSize {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        Self::ALL.into_iter().find(|s| s.name() == name.to_lowercase()).ok_or_else(|| {
+            let names: Vec<&str> = Self::ALL.iter().map(|s| s.name()).collect();
+            Error::Config(format!("invalid size '{name}' (must be one of: {})", names.join(", ")))
+        })
+    }
+}
+
+/// A fabricated session to run the stop hook against, reusable across runs.
+///
+/// The session has edited files and left nothing uncommitted, so no check
+/// stops the hook early.
+pub struct StopHookFixture {
+    scratch: Scratch,
+    input: HookInput,
+    config: StopHookConfig,
+    // The project's data and database must not be used while the fixture exists
+    _env: EnvGuard,
+}
+
+impl StopHookFixture {
+    /// Fabricate a session of the given size.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the scratch directory or work item database cannot
+    /// be created.
+    pub fn create(size: BenchSize) -> Result<Self> {
+        let env = EnvGuard::remove(&[paths::DATA_DIR_ENV, paths::DB_PATH_ENV]);
+        let scratch = Scratch::create("bench")?;
+        let dir = scratch.0.as_path();
+
+        let transcript_path = dir.join("transcript.jsonl");
+        std::fs::write(&transcript_path, transcript(size.transcript_turns()))?;
+        populate_work_items(dir, size.work_items())?;
+        // The one-time reminder would make the first run differ from the rest
+        session::set_work_item_reminded(dir)?;
+
+        let config = StopHookConfig {
+            git_repo: true,
+            vcs: VcsKind::Git,
+            base_dir: Some(dir.to_path_buf()),
+            message_verbosity: MessageVerbosity::Verbose,
+            ..StopHookConfig::default()
+        };
+        let input = HookInput {
+            transcript_path: Some(transcript_path.to_string_lossy().to_string()),
+            ..Default::default()
+        };
+        Ok(Self { scratch, input, config, _env: env })
+    }
+
+    /// The directory the fixture's files are in.
+    #[must_use]
+    pub fn dir(&self) -> &Path {
+        &self.scratch.0
+    }
+
+    /// Run the stop hook once.
+    ///
+    /// The reflection marker the last run set is cleared first, so every run
+    /// goes through every check and ends by prompting for reflection.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the stop hook fails.
+    pub fn run(&self) -> Result<StopHookResult> {
+        session::clear_reflect_marker(self.dir())?;
+        run_stop_hook(&self.input, &self.config, &CleanRepoRunner, &CannedSubAgent)
+    }
+}
+
+/// How long the stop hook took over several runs against one fixture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BenchResult {
+    /// The fixture's size.
+    pub size: BenchSize,
+    /// How many runs were timed.
+    pub runs: usize,
+    /// The median run.
+    pub median: Duration,
+    /// The slowest run.
+    pub max: Duration,
+}
+
+/// Time the stop hook against a fixture of the given size.
+///
+/// One untimed run comes first, so one-off setup such as creating the
+/// database schema is not counted.
+///
+/// # Errors
+///
+/// Returns an error if the fixture cannot be created or the stop hook fails.
+pub fn bench_stop_hook(size: BenchSize, runs: usize) -> Result<BenchResult> {
+    let fixture = StopHookFixture::create(size)?;
+    fixture.run()?;
+
+    let runs = runs.max(1);
+    let mut times = Vec::with_capacity(runs);
+    for _ in 0..runs {
+        let start = Instant::now();
+        fixture.run()?;
+        times.push(start.elapsed());
+    }
+    times.sort();
+    Ok(BenchResult { size, runs, median: times[runs / 2], max: times[runs - 1] })
+}
+
+/// A transcript of `turns` edits, each with its result and a reply.
+fn transcript(turns: usize) -> String {
+    let at = |minutes_ago: usize| {
+        let minutes = i64::try_from(minutes_ago).unwrap_or(i64::MAX);
+        (Utc::now() - ChronoDuration::minutes(minutes)).to_rfc3339()
+    };
+    let mut entries = vec![serde_json::json!({
+        "type": "user",
+        "timestamp": at(turns + 1),
+        "message": {"role": "user", "content": "Tidy up the parser module"},
+    })];
+    for turn in 0..turns {
+        let timestamp = at(turns - turn);
+        let id = format!("toolu_{turn}");
+        entries.push(serde_json::json!({
+            "type": "assistant",
+            "timestamp": timestamp,
+            "message": {"role": "assistant", "content": [{
+                "type": "tool_use",
+                "id": id,
+                "name": "Edit",
+                "input": {
+                    "file_path": format!("src/parser/part_{}.rs", turn % 50),
+                    "old_string": "fn parse(input: &str)",
+                    "new_string": "fn parse(input: &str, options: &Options)",
+                },
+            }]},
+        }));
+        entries.push(serde_json::json!({
+            "type": "user",
+            "timestamp": timestamp,
+            "message": {"role": "user", "content": [{
+                "type": "tool_result",
+                "tool_use_id": id,
+                "content": "The file has been updated.",
+            }]},
+        }));
+        entries.push(serde_json::json!({
+            "type": "assistant",
+            "timestamp": timestamp,
+            "message": {"role": "assistant", "content": [{
+                "type": "text",
+                "text": format!("Updated part {turn} of the parser to take the options."),
+            }]},
+        }));
+    }
+    entries.push(serde_json::json!({
+        "type": "assistant",
+        "timestamp": at(0),
+        "message": {"role": "assistant", "content": [{
+            "type": "text",
+            "text": "The parser is tidied up, committed and pushed.",
+        }]},
+    }));
+    let lines: Vec<String> = entries.iter().map(ToString::to_string).collect();
+    lines.join("\n") + "\n"
+}
+
+/// Create `count` work items, most complete and each tenth one open.
+fn populate_work_items(dir: &Path, count: usize) -> Result<()> {
+    let store = SqliteTaskStore::for_project(dir)?;
+    let mut previous: Option<String> = None;
+    for n in 0..count {
+        let task = store.create_task(
+            &format!("Work item {n}"),
+            "Fabricated for the stop hook benchmarks.",
+            Priority::Medium,
+        )?;
+        if n % 10 == 0 {
+            if let Some(previous) = &previous {
+                store.add_dependency(&task.id, previous)?;
+            }
+            previous = Some(task.id);
+        } else {
+            store.update_task(
+                &task.id,
+                TaskUpdate { status: Some(Status::Complete), ..Default::default() },
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// A clean, pushed git repository on `main`, answered without running git.
+/// Other programs are not available.
+struct CleanRepoRunner;
+
+impl CommandRunner for CleanRepoRunner {
+    fn run(&self, program: &str, args: &[&str], _: Option<Duration>) -> Result<CommandOutput> {
+        let stdout = match (program, args) {
+            ("git", ["rev-list", "--left-right", "--count", ..]) => "0\t0\n",
+            ("git", ["rev-list", "--count", ..]) => "0\n",
+            ("git", ["rev-parse", "--abbrev-ref", "HEAD"] | ["symbolic-ref", ..]) => "main\n",
+            ("git", _) => "",
+            _ => {
+                return Ok(CommandOutput {
+                    exit_code: 127,
+                    stdout: String::new(),
+                    stderr: format!("{program}: command not found"),
+                })
+            }
+        };
+        Ok(CommandOutput { exit_code: 0, stdout: stdout.to_string(), stderr: String::new() })
+    }
+
+    fn run_in_dir(
+        &self,
+        program: &str,
+        args: &[&str],
+        timeout: Option<Duration>,
+        _: &Path,
+    ) -> Result<CommandOutput> {
+        self.run(program, args, timeout)
+    }
+
+    fn run_with_options(
+        &self,
+        program: &str,
+        args: &[&str],
+        options: &RunOptions,
+    ) -> Result<CommandOutput> {
+        self.run(program, args, options.timeout)
+    }
+
+    fn is_available(&self, program: &str) -> bool {
+        program == "git"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!("Medium".parse::<BenchSize>().unwrap(), BenchSize::Medium);
+        let err = "huge".parse::<BenchSize>().unwrap_err().to_string();
+        assert!(err.contains("small, medium, large"), "{err}");
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_fixture_runs_every_check() {
+        let fixture = StopHookFixture::create(BenchSize::Small).unwrap();
+        let store = SqliteTaskStore::for_project(fixture.dir()).unwrap();
+        assert_eq!(store.list_tasks(crate::tasks::TaskFilter::default()).unwrap().len(), 10);
+
+        // Every run decides the same way, so the runs are comparable
+        for _ in 0..2 {
+            let result = fixture.run().unwrap();
+            assert_eq!(result.decided_by.as_deref(), Some("reflection_prompt"));
+        }
+    }
+
+    #[test]
+    fn test_clean_repo_runner() {
+        let runner = CleanRepoRunner;
+        let ahead =
+            runner.run_in_dir("git", &["rev-list", "--count", "HEAD"], None, Path::new("."));
+        assert_eq!(ahead.unwrap().stdout, "0\n");
+        let branch = runner.run_with_options(
+            "git",
+            &["symbolic-ref", "--short", "-q", "HEAD"],
+            &RunOptions::new(),
+        );
+        assert_eq!(branch.unwrap().stdout, "main\n");
+        assert!(runner.run("git", &["diff", "--stat"], None).unwrap().stdout.is_empty());
+
+        assert!(runner.is_available("git"));
+        assert!(!runner.is_available("gh"));
+        assert_eq!(runner.run("gh", &["pr", "view"], None).unwrap().exit_code, 127);
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_bench_stop_hook() {
+        let result = bench_stop_hook(BenchSize::Small, 3).unwrap();
+        assert_eq!(result.size, BenchSize::Small);
+        assert_eq!(result.runs, 3);
+        assert!(result.median <= result.max);
+    }
+}
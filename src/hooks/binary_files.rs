@@ -0,0 +1,288 @@
+//! Binary file check for commits.
+//!
+//! Before a `git commit`, newly staged binary files larger than the project's
+//! [`BinaryFilePolicy`] allows are flagged, along with untracked binary files
+//! that a `git add` in the same command line will stage. Generated files,
+//! such as vendored dependencies, are left out. The commit is
+//! blocked, or with `suggest_lfs` allowed with a suggestion to track the
+//! files with Git LFS.
+
+use crate::config::{BinaryFilePolicy, ProjectConfig};
+use crate::git;
+use crate::hooks::{HookInput, PreToolUseOutput};
+use crate::templates;
+use crate::traits::CommandRunner;
+use std::collections::BTreeSet;
+use std::io::Read;
+use std::path::Path;
+use tera::Context;
+
+/// A staged binary file that exceeds the policy's limits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LargeBinary {
+    /// Path of the file, relative to the repository root.
+    pub path: String,
+    /// Size of the file as it will be committed, in KB.
+    pub size_kb: u64,
+}
+
+/// Bytes read from the start of a file to decide whether it is binary, as
+/// git does.
+const BINARY_SNIFF_BYTES: usize = 8000;
+
+/// Find new binary files that the policy does not allow.
+///
+/// Staged files are sized as staged in the index. With `added` set to the
+/// pathspecs a `git add` is about to stage (see [`git::added_pathspecs`]),
+/// matching untracked files are checked too, as they are in the working
+/// tree; those missing from it are skipped.
+///
+/// # Errors
+///
+/// Returns an error if a git command fails.
+pub fn find_large_binaries(
+    runner: &dyn CommandRunner,
+    base_dir: &Path,
+    policy: &BinaryFilePolicy,
+    added: Option<&[String]>,
+) -> crate::error::Result<Vec<LargeBinary>> {
+    if policy.max_kb == 0 {
+        return Ok(Vec::new());
+    }
+    let mut sized = Vec::new();
+    for path in git::staged_new_binary_files(runner)? {
+        if let Some(size) = git::staged_size(runner, &path)? {
+            sized.push((path, size));
+        }
+    }
+    if let Some(pathspecs) = added {
+        for path in git::untracked_files_matching(runner, pathspecs)? {
+            let file = base_dir.join(&path);
+            if sized.iter().all(|(staged, _)| *staged != path) && is_binary(&file) {
+                if let Ok(metadata) = std::fs::metadata(&file) {
+                    sized.push((path, metadata.len()));
+                }
+            }
+        }
+    }
+    Ok(sized
+        .into_iter()
+        .filter_map(|(path, size)| {
+            let size_kb = size.div_ceil(1024);
+            (!policy.allows(&path, size_kb)).then_some(LargeBinary { path, size_kb })
+        })
+        .collect())
+}
+
+/// Whether a working tree file looks binary: a NUL byte near its start.
+fn is_binary(path: &Path) -> bool {
+    let Ok(file) = std::fs::File::open(path) else {
+        return false;
+    };
+    let mut start = Vec::with_capacity(BINARY_SNIFF_BYTES);
+    file.take(BINARY_SNIFF_BYTES as u64).read_to_end(&mut start).is_ok() && start.contains(&0)
+}
+
+/// The `git lfs track` pattern for a file: its extension, or the path itself.
+fn lfs_pattern(path: &str) -> String {
+    Path::new(path)
+        .extension()
+        .map_or_else(|| path.to_string(), |ext| format!("*.{}", ext.to_string_lossy()))
+}
+
+/// Run the binary file check for a Bash tool call.
+///
+/// Returns an allow with no context when the command is not a commit or no
+/// new binary exceeds the policy.
+pub fn run_binary_files_check(
+    input: &HookInput,
+    base_dir: &Path,
+    runner: &dyn CommandRunner,
+) -> PreToolUseOutput {
+    let command = input.tool_input.as_ref().and_then(|ti| ti.command.as_deref()).unwrap_or("");
+    if !git::is_commit_command(command) {
+        return PreToolUseOutput::allow(None);
+    }
+
+    let config = ProjectConfig::load_from(base_dir).ok().flatten().unwrap_or_default();
+    let policy = config.binary_files;
+    let added = git::added_pathspecs(command);
+    let mut binaries = match find_large_binaries(runner, base_dir, &policy, added.as_deref()) {
+        Ok(binaries) => binaries,
+        Err(e) => {
+            tracing::warn!("Failed to check staged binary files: {e}");
+            return PreToolUseOutput::allow(None);
+        }
+    };
+    binaries.retain(|binary| !config.generated_files.is_generated(base_dir, &binary.path));
+    if binaries.is_empty() {
+        return PreToolUseOutput::allow(None);
+    }
+
+    let files: Vec<String> =
+        binaries.iter().map(|binary| format!("{} ({} KB)", binary.path, binary.size_kb)).collect();
+    let lfs_patterns: BTreeSet<String> =
+        binaries.iter().map(|binary| lfs_pattern(&binary.path)).collect();
+    let mut ctx = Context::new();
+    ctx.insert("files", &files);
+    ctx.insert("lfs_patterns", &lfs_patterns);
+    ctx.insert("max_kb", &policy.max_kb);
+    ctx.insert("blocking", &!policy.suggest_lfs);
+    let message = templates::render("messages/binary_files.tera", &ctx)
+        .expect("binary_files.tera template should always render");
+
+    if policy.suggest_lfs {
+        PreToolUseOutput::allow(Some(message))
+    } else {
+        PreToolUseOutput::block(Some(message))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hooks::ToolInput;
+    use crate::testing::MockCommandRunner;
+    use crate::traits::CommandOutput;
+    use tempfile::TempDir;
+
+    fn commit_input(command: &str) -> HookInput {
+        HookInput {
+            tool_name: Some("Bash".to_string()),
+            tool_input: Some(ToolInput {
+                command: Some(command.to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    /// A runner with the given binary files staged, with their sizes in the
+    /// index; a file without a size is no longer in the index.
+    fn staged_binaries_runner(files: &[(&str, Option<u64>)]) -> MockCommandRunner {
+        let stdout =
+            files.iter().map(|(path, _)| format!("-\t-\t{path}")).collect::<Vec<_>>().join("\n");
+        let mut runner = MockCommandRunner::new();
+        runner.expect(
+            "git",
+            &["diff", "--cached", "--numstat", "--diff-filter=A"],
+            CommandOutput { exit_code: 0, stdout, stderr: String::new() },
+        );
+        for (path, size) in files {
+            let output = size.map_or_else(
+                || CommandOutput { exit_code: 128, stdout: String::new(), stderr: String::new() },
+                |size| CommandOutput {
+                    exit_code: 0,
+                    stdout: format!("{size}\n"),
+                    stderr: String::new(),
+                },
+            );
+            runner.expect("git", &["cat-file", "-s", &format!(":{path}")], output);
+        }
+        runner
+    }
+
+    fn write_file(dir: &Path, path: &str, size: usize) {
+        let path = dir.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, vec![0_u8; size]).unwrap();
+    }
+
+    #[test]
+    fn test_find_large_binaries_applies_policy() {
+        let dir = TempDir::new().unwrap();
+        // The working tree copy is not what gets committed
+        write_file(dir.path(), "assets/model.bin", 10);
+        let runner = staged_binaries_runner(&[
+            ("assets/model.bin", Some(300 * 1024)),
+            ("assets/icon.bin", Some(10 * 1024)),
+            ("docs/diagram.png", Some(300 * 1024)),
+            ("assets/unstaged.bin", None),
+        ]);
+
+        let binaries =
+            find_large_binaries(&runner, dir.path(), &BinaryFilePolicy::default(), None).unwrap();
+        assert_eq!(
+            binaries,
+            vec![LargeBinary { path: "assets/model.bin".to_string(), size_kb: 300 }]
+        );
+
+        let disabled = BinaryFilePolicy { max_kb: 0, ..Default::default() };
+        assert!(find_large_binaries(&MockCommandRunner::new(), dir.path(), &disabled, None)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_binary_files_check_blocks_commit() {
+        let dir = TempDir::new().unwrap();
+        let runner = staged_binaries_runner(&[("data/dump.sqlite", Some(2048 * 1024))]);
+
+        let output = run_binary_files_check(&commit_input("git commit -m x"), dir.path(), &runner);
+        assert!(output.is_block());
+        let message = output.hook_specific_output.additional_context.unwrap();
+        assert!(message.contains("data/dump.sqlite (2048 KB)"));
+        assert!(message.contains("git lfs track \"*.sqlite\""));
+    }
+
+    #[test]
+    fn test_binary_files_check_suggests_lfs() {
+        let dir = TempDir::new().unwrap();
+        let config = ProjectConfig {
+            binary_files: BinaryFilePolicy { suggest_lfs: true, ..Default::default() },
+            ..Default::default()
+        };
+        config.save_to(dir.path()).unwrap();
+        let runner = staged_binaries_runner(&[("model", Some(200 * 1024))]);
+
+        let output = run_binary_files_check(&commit_input("git commit -m x"), dir.path(), &runner);
+        assert!(!output.is_block());
+        let message = output.hook_specific_output.additional_context.unwrap();
+        assert!(message.contains("git lfs track \"model\""));
+    }
+
+    #[test]
+    fn test_binary_files_check_sees_files_added_by_the_command() {
+        let dir = TempDir::new().unwrap();
+        write_file(dir.path(), "data/dump.sqlite", 2048 * 1024);
+        std::fs::write(dir.path().join("notes.txt"), "text ".repeat(100 * 1024)).unwrap();
+        let mut runner = staged_binaries_runner(&[]);
+        runner.expect(
+            "git",
+            &["ls-files", "--others", "--exclude-standard", "--full-name", "--"],
+            CommandOutput {
+                exit_code: 0,
+                stdout: "data/dump.sqlite\nnotes.txt\ngone.bin\n".to_string(),
+                stderr: String::new(),
+            },
+        );
+
+        let output = run_binary_files_check(
+            &commit_input("git add -A && git commit -m x"),
+            dir.path(),
+            &runner,
+        );
+        assert!(output.is_block());
+        let message = output.hook_specific_output.additional_context.unwrap();
+        assert!(message.contains("data/dump.sqlite (2048 KB)"), "{message}");
+        assert!(!message.contains("notes.txt"), "{message}");
+    }
+
+    #[test]
+    fn test_binary_files_check_leaves_out_generated_files() {
+        let dir = TempDir::new().unwrap();
+        let runner = staged_binaries_runner(&[("vendor/libfoo/libfoo.a", Some(4096 * 1024))]);
+
+        let output = run_binary_files_check(&commit_input("git commit -m x"), dir.path(), &runner);
+        assert!(!output.is_block());
+        assert!(output.hook_specific_output.additional_context.is_none());
+    }
+
+    #[test]
+    fn test_binary_files_check_ignores_other_commands() {
+        let runner = MockCommandRunner::new();
+        let output = run_binary_files_check(&commit_input("git status"), Path::new("."), &runner);
+        assert!(!output.is_block());
+        assert!(output.hook_specific_output.additional_context.is_none());
+    }
+}
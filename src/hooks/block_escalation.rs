@@ -0,0 +1,361 @@
+//! Escalation ladder for repeated identical stop blocks.
+//!
+//! If the stop hook blocks for the same reason several times in a row without
+//! anything changing, blocking again will not help. Each repeat past the
+//! configured threshold escalates one step:
+//!
+//! 1. Stronger guidance is appended to the block message
+//! 2. A question describing the impasse is filed for the user
+//! 3. The stop is allowed with an explanation
+//!
+//! A block's signature combines the name of the check that blocked, the git
+//! working state hash, and the incomplete requested work items, so any real
+//! progress restarts the streak.
+
+use crate::git;
+use crate::hooks::stop::{StopHookConfig, StopHookResult, StopReason};
+use crate::session;
+use crate::tasks::{self, SqliteTaskStore, TaskStore};
+use crate::templates;
+use crate::traits::CommandRunner;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use tera::Context;
+
+/// Step of the escalation ladder reached by a streak of identical blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EscalationStage {
+    /// Below the threshold - block as normal.
+    None,
+    /// Append stronger guidance to the block.
+    Guidance,
+    /// File a question describing the impasse.
+    Question,
+    /// Allow the stop with an explanation.
+    Allow,
+}
+
+impl EscalationStage {
+    /// Determine the stage for a streak of `count` identical blocks.
+    ///
+    /// A threshold of 0 disables escalation.
+    #[must_use]
+    pub const fn for_count(count: u32, threshold: u32) -> Self {
+        if threshold == 0 || count < threshold {
+            Self::None
+        } else if count == threshold {
+            Self::Guidance
+        } else if count == threshold + 1 {
+            Self::Question
+        } else {
+            Self::Allow
+        }
+    }
+
+    /// Name used in templates.
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Guidance => "guidance",
+            Self::Question => "question",
+            Self::Allow => "allow",
+        }
+    }
+}
+
+/// Extract the name of the check that produced a result from its checks log.
+///
+/// The blocking check is always the last entry, formatted as `  name: detail`.
+fn blocking_check_name(result: &StopHookResult) -> String {
+    result
+        .checks_log
+        .last()
+        .and_then(|entry| entry.trim().split(':').next())
+        .unwrap_or("unknown")
+        .to_string()
+}
+
+/// Compute the signature identifying a block and the state it was made in.
+fn block_signature(
+    check_name: &str,
+    config: &StopHookConfig,
+    runner: &dyn CommandRunner,
+) -> String {
+    let mut hasher = DefaultHasher::new();
+    check_name.hash(&mut hasher);
+
+    if config.git_repo {
+        git::working_state_hash(runner).unwrap_or_default().hash(&mut hasher);
+    }
+
+    for (id, _, status) in tasks::get_incomplete_requested_work(config.base_dir()) {
+        id.hash(&mut hasher);
+        status.hash(&mut hasher);
+    }
+
+    format!("{:x}", hasher.finish())
+}
+
+/// File a question describing the impasse, returning its ID.
+fn file_impasse_question(
+    config: &StopHookConfig,
+    check_name: &str,
+    count: u32,
+    result: &StopHookResult,
+) -> Option<String> {
+    let headline = result.messages.iter().skip(1).find(|m| m.starts_with('#')).map_or_else(
+        || check_name.to_string(),
+        |m| m.lines().next().unwrap_or_default().trim_start_matches('#').trim().to_string(),
+    );
+
+    let text = format!(
+        "The agent has been blocked from stopping {count} times in a row by the \
+         `{check_name}` check ({headline}) without any change in state. \
+         How should it proceed?"
+    );
+
+    let store = SqliteTaskStore::for_project(config.base_dir()).ok()?;
+    store.create_question(&text).ok().map(|q| q.id)
+}
+
+/// Render the escalation message for a stage.
+fn render_message(
+    stage: EscalationStage,
+    count: u32,
+    check_name: &str,
+    question_id: Option<&str>,
+) -> String {
+    let mut ctx = Context::new();
+    ctx.insert("stage", stage.as_str());
+    ctx.insert("block_count", &count);
+    ctx.insert("check_name", check_name);
+    ctx.insert("question_id", question_id.unwrap_or("unknown"));
+    templates::render("messages/stop/repeated_block.tera", &ctx)
+        .expect("repeated_block.tera template should always render")
+}
+
+/// Apply the escalation ladder to a stop hook result.
+///
+/// Allowed stops clear the streak. Blocks that inject a response are answers
+/// rather than impasses, so they are passed through untouched.
+///
+/// # Panics
+///
+/// Panics if the repeated block template fails to render.
+pub fn apply(
+    result: StopHookResult,
+    config: &StopHookConfig,
+    runner: &dyn CommandRunner,
+) -> StopHookResult {
+    if config.block_escalation_threshold == 0 {
+        return result;
+    }
+
+    let base_dir = config.base_dir();
+    if result.allow_stop {
+        let _ = session::clear_stop_block_streak(base_dir);
+        return result;
+    }
+    if result.inject_response.is_some() {
+        return result;
+    }
+
+    let check_name = blocking_check_name(&result);
+    let signature = block_signature(&check_name, config, runner);
+    let Ok(count) = session::record_stop_block(base_dir, &signature) else {
+        return result;
+    };
+
+    match EscalationStage::for_count(count, config.block_escalation_threshold) {
+        EscalationStage::None => result,
+        EscalationStage::Guidance => {
+            result.with_message(render_message(EscalationStage::Guidance, count, &check_name, None))
+        }
+        EscalationStage::Question => {
+            let question_id = file_impasse_question(config, &check_name, count, &result);
+            result.with_message(render_message(
+                EscalationStage::Question,
+                count,
+                &check_name,
+                question_id.as_deref(),
+            ))
+        }
+        EscalationStage::Allow => {
+            let _ = session::clear_stop_block_streak(base_dir);
+            let mut allowed = StopHookResult::allow()
+                .with_message(render_message(EscalationStage::Allow, count, &check_name, None))
+                .with_explanation(
+                    config.explain_stops,
+                    format!("`{check_name}` blocked {count} times without any change in state"),
+                )
+                .with_reason(StopReason::RepeatedBlock)
+                .with_details(serde_json::json!({
+                    "check": check_name,
+                    "count": count,
+                    "blocked_reason": result.reason,
+                }));
+            allowed.checks_log = result.checks_log;
+            allowed
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn blocked_by(check_name: &str) -> StopHookResult {
+        let mut result = StopHookResult::block()
+            .with_message("Stop blocked:\n  uncommitted_changes: uncommitted changes, blocking")
+            .with_message("# Uncommitted Changes");
+        result.checks_log = vec![format!("  {check_name}: blocking")];
+        result
+    }
+
+    fn config_for(dir: &TempDir, threshold: u32) -> StopHookConfig {
+        StopHookConfig {
+            base_dir: Some(dir.path().to_path_buf()),
+            block_escalation_threshold: threshold,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_for_count_below_threshold() {
+        assert_eq!(EscalationStage::for_count(1, 3), EscalationStage::None);
+        assert_eq!(EscalationStage::for_count(2, 3), EscalationStage::None);
+    }
+
+    #[test]
+    fn test_for_count_ladder() {
+        assert_eq!(EscalationStage::for_count(3, 3), EscalationStage::Guidance);
+        assert_eq!(EscalationStage::for_count(4, 3), EscalationStage::Question);
+        assert_eq!(EscalationStage::for_count(5, 3), EscalationStage::Allow);
+        assert_eq!(EscalationStage::for_count(9, 3), EscalationStage::Allow);
+    }
+
+    #[test]
+    fn test_for_count_disabled() {
+        assert_eq!(EscalationStage::for_count(100, 0), EscalationStage::None);
+    }
+
+    #[test]
+    fn test_blocking_check_name() {
+        assert_eq!(blocking_check_name(&blocked_by("requested_tasks")), "requested_tasks");
+        assert_eq!(blocking_check_name(&StopHookResult::block()), "unknown");
+    }
+
+    #[test]
+    fn test_apply_disabled_passes_through() {
+        let dir = TempDir::new().unwrap();
+        let config = config_for(&dir, 0);
+        let runner = crate::testing::MockCommandRunner::new();
+
+        for _ in 0..10 {
+            let result = apply(blocked_by("uncommitted_changes"), &config, &runner);
+            assert!(!result.allow_stop);
+            assert_eq!(result.messages.len(), 2);
+        }
+    }
+
+    #[test]
+    fn test_apply_escalates_through_ladder() {
+        let dir = TempDir::new().unwrap();
+        let config = config_for(&dir, 2);
+        let runner = crate::testing::MockCommandRunner::new();
+
+        let first = apply(blocked_by("uncommitted_changes"), &config, &runner);
+        assert!(!first.allow_stop);
+        assert_eq!(first.messages.len(), 2);
+
+        let second = apply(blocked_by("uncommitted_changes"), &config, &runner);
+        assert!(!second.allow_stop);
+        assert!(second.messages.iter().any(|m| m.contains("# Repeated Block")));
+
+        let third = apply(blocked_by("uncommitted_changes"), &config, &runner);
+        assert!(!third.allow_stop);
+        assert!(third.messages.iter().any(|m| m.contains("Question Filed")));
+        let questions = tasks::list_unanswered_questions(dir.path());
+        assert_eq!(questions.len(), 1);
+        assert!(questions[0].text.contains("uncommitted_changes"));
+        assert!(questions[0].text.contains("Uncommitted Changes"));
+
+        let fourth = apply(blocked_by("uncommitted_changes"), &config, &runner);
+        assert!(fourth.allow_stop);
+        assert_eq!(fourth.exit_code, 0);
+        assert!(fourth.messages.iter().any(|m| m.contains("Allowing Stop")));
+
+        // Streak restarts after the forced allow
+        let fifth = apply(blocked_by("uncommitted_changes"), &config, &runner);
+        assert!(!fifth.allow_stop);
+        assert_eq!(fifth.messages.len(), 2);
+    }
+
+    #[test]
+    fn test_apply_different_check_restarts_streak() {
+        let dir = TempDir::new().unwrap();
+        let config = config_for(&dir, 2);
+        let runner = crate::testing::MockCommandRunner::new();
+
+        apply(blocked_by("uncommitted_changes"), &config, &runner);
+        let result = apply(blocked_by("requested_tasks"), &config, &runner);
+        assert_eq!(result.messages.len(), 2);
+    }
+
+    #[test]
+    fn test_apply_allow_clears_streak() {
+        let dir = TempDir::new().unwrap();
+        let config = config_for(&dir, 2);
+        let runner = crate::testing::MockCommandRunner::new();
+
+        apply(blocked_by("uncommitted_changes"), &config, &runner);
+        apply(StopHookResult::allow(), &config, &runner);
+        let result = apply(blocked_by("uncommitted_changes"), &config, &runner);
+        assert_eq!(result.messages.len(), 2);
+    }
+
+    #[test]
+    fn test_apply_ignores_inject_blocks() {
+        let dir = TempDir::new().unwrap();
+        let config = config_for(&dir, 1);
+        let runner = crate::testing::MockCommandRunner::new();
+
+        for _ in 0..5 {
+            let result = apply(
+                StopHookResult::block().with_inject("Yes, please continue."),
+                &config,
+                &runner,
+            );
+            assert!(!result.allow_stop);
+            assert!(result.messages.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_apply_includes_working_state_in_signature() {
+        use crate::traits::CommandOutput;
+
+        fn runner_with_head(sha: &str) -> crate::testing::MockCommandRunner {
+            let mut runner = crate::testing::MockCommandRunner::new();
+            let ok = |stdout: &str| CommandOutput {
+                exit_code: 0,
+                stdout: stdout.to_string(),
+                stderr: String::new(),
+            };
+            runner.expect("git", &["rev-parse", "HEAD"], ok(sha));
+            runner.expect("git", &["diff", "--cached", "--name-only"], ok(""));
+            runner.expect("git", &["diff", "--name-only"], ok("src/lib.rs\n"));
+            runner.expect("git", &["ls-files", "--others", "--exclude-standard"], ok(""));
+            runner
+        }
+
+        let dir = TempDir::new().unwrap();
+        let config = StopHookConfig { git_repo: true, ..config_for(&dir, 2) };
+
+        apply(blocked_by("uncommitted_changes"), &config, &runner_with_head("aaa\n"));
+        // HEAD moved - a commit was made, so this is not a repeat
+        let result = apply(blocked_by("uncommitted_changes"), &config, &runner_with_head("bbb\n"));
+        assert_eq!(result.messages.len(), 2);
+    }
+}
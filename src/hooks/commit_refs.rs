@@ -0,0 +1,272 @@
+//! Update work items referenced from commit messages.
+//!
+//! After a Bash command runs `git commit`, the new commit's message is scanned
+//! for `closes <id>` (also `fixes`/`resolves`) and `refs <id>` references to
+//! existing work items. Each referenced item gets a note linking the commit.
+//! Closed items are marked complete, or in review when the project requires
+//! pull requests, unless their checklist or the definition of done says
+//! otherwise; the reason is then noted on the item instead.
+
+use crate::config::ProjectConfig;
+use crate::definition_of_done;
+use crate::git;
+use crate::hooks::post_tool_use::PostToolUseInput;
+use crate::tasks::{self, SqliteTaskStore, Status, TaskStore, TaskUpdate};
+use crate::traits::CommandRunner;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::path::Path;
+
+/// Matches a closing or referencing keyword followed by a work item ID.
+static REFERENCE_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?i)\b(close[sd]?|fix(?:e[sd])?|resolve[sd]?|refs?|references)\b:?\s+#?([a-z0-9]+(?:-[a-z0-9]+)*)",
+    )
+    .unwrap()
+});
+
+/// What a commit says about a work item.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Reference {
+    /// The commit finishes the work item.
+    Closes,
+    /// The commit is part of the work item.
+    Refs,
+}
+
+/// Find the work item references in a commit message, in order.
+fn parse_references(message: &str) -> Vec<(Reference, String)> {
+    REFERENCE_PATTERN
+        .captures_iter(message)
+        .map(|caps| {
+            let keyword = caps[1].to_lowercase();
+            let reference =
+                if keyword.starts_with("ref") { Reference::Refs } else { Reference::Closes };
+            (reference, caps[2].to_lowercase())
+        })
+        .collect()
+}
+
+/// The IDs of the work items a commit message refers to, in order.
+pub fn referenced_work_items(message: &str) -> Vec<String> {
+    parse_references(message).into_iter().map(|(_, id)| id).collect()
+}
+
+/// Link a commit made by a Bash command to the work items it references.
+///
+/// # Errors
+///
+/// Returns an error if a referenced work item cannot be updated.
+pub fn link_commit(
+    input: &PostToolUseInput,
+    base_dir: &Path,
+    runner: &dyn CommandRunner,
+) -> Result<(), String> {
+    let command = input
+        .tool_input
+        .as_ref()
+        .and_then(|v| v.get("command"))
+        .and_then(|c| c.as_str())
+        .unwrap_or_default();
+    if !git::is_commit_command(command) {
+        return Ok(());
+    }
+    let Some(store) = tasks::open_project_store(base_dir) else {
+        return Ok(());
+    };
+
+    let output = runner
+        .run("git", &["log", "-1", "--format=%H%n%B"], None)
+        .map_err(|e| format!("Failed to read commit: {e}"))?;
+    if !output.success() {
+        return Ok(());
+    }
+    let Some((sha, message)) = output.stdout.split_once('\n') else {
+        return Ok(());
+    };
+    let references = parse_references(message);
+    if references.is_empty() {
+        return Ok(());
+    }
+
+    let subject = message.lines().next().unwrap_or_default().trim();
+    let note = format!("Commit {}: {subject}", sha.trim());
+    let config = ProjectConfig::load_from(base_dir).ok().flatten().unwrap_or_default();
+    for (reference, id) in references {
+        apply_reference(&store, &id, reference, &note, &config, base_dir, runner)
+            .map_err(|e| format!("Failed to update work item {id} from commit: {e}"))?;
+    }
+    Ok(())
+}
+
+/// Note the commit on a work item and, if it closes the item, finish it.
+fn apply_reference(
+    store: &SqliteTaskStore,
+    id: &str,
+    reference: Reference,
+    note: &str,
+    config: &ProjectConfig,
+    base_dir: &Path,
+    runner: &dyn CommandRunner,
+) -> crate::error::Result<()> {
+    let Some(task) = store.get_task(id)? else {
+        return Ok(());
+    };
+    // A failed or repeated commit leaves the same commit at HEAD
+    if store.get_notes(id)?.iter().any(|n| n.content == note) {
+        return Ok(());
+    }
+    store.add_note(id, note)?;
+    if reference == Reference::Refs || task.is_closed() || task.status == Status::InReview {
+        return Ok(());
+    }
+
+    let status = if config.require_pr { Status::InReview } else { Status::Complete };
+    if status == Status::Complete && !config.definition_of_done.is_empty() {
+        let evidence = definition_of_done::gather_evidence(base_dir, config, runner)?;
+        if let Err(unmet) = definition_of_done::check(&config.definition_of_done, &evidence, &[]) {
+            store.add_note(id, &format!("Left open by the commit above. {unmet}"))?;
+            return Ok(());
+        }
+    }
+    if let Err(e) = store.update_task(id, TaskUpdate { status: Some(status), ..Default::default() })
+    {
+        store.add_note(id, &format!("Left open by the commit above: {e}"))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tasks::Priority;
+    use crate::testing::MockCommandRunner;
+    use crate::traits::CommandOutput;
+    use tempfile::TempDir;
+
+    fn commit_input(command: &str) -> PostToolUseInput {
+        PostToolUseInput {
+            tool_name: Some("Bash".to_string()),
+            tool_input: Some(serde_json::json!({ "command": command })),
+            tool_response: Some(serde_json::json!({ "stdout": "", "stderr": "" })),
+        }
+    }
+
+    fn expect_log(runner: &mut MockCommandRunner, sha: &str, message: &str) {
+        runner.expect(
+            "git",
+            &["log", "-1", "--format=%H%n%B"],
+            CommandOutput {
+                exit_code: 0,
+                stdout: format!("{sha}\n{message}\n"),
+                ..Default::default()
+            },
+        );
+    }
+
+    fn status_of(store: &SqliteTaskStore, id: &str) -> Status {
+        store.get_task(id).unwrap().unwrap().status
+    }
+
+    #[test]
+    fn test_parse_references() {
+        assert_eq!(
+            parse_references("Tidy parser\n\nCloses parse-bug-1a2b, refs: #epic-3c4d\nFixes X-9"),
+            vec![
+                (Reference::Closes, "parse-bug-1a2b".to_string()),
+                (Reference::Refs, "epic-3c4d".to_string()),
+                (Reference::Closes, "x-9".to_string()),
+            ]
+        );
+        assert!(parse_references("Refactor closing logic").is_empty());
+    }
+
+    #[test]
+    fn test_link_commit_closes_and_refs() {
+        let dir = TempDir::new().unwrap();
+        let store = SqliteTaskStore::for_project(dir.path()).unwrap();
+        let bug = store.create_task("Parse bug", "", Priority::High).unwrap();
+        let epic = store.create_task("Epic", "", Priority::Low).unwrap();
+        let mut runner = MockCommandRunner::new();
+        let message = format!("Fix parser\n\nCloses {}\nRefs {}", bug.id, epic.id);
+        expect_log(&mut runner, "abc123", &message);
+        expect_log(&mut runner, "abc123", &message);
+
+        link_commit(&commit_input("git commit -am 'Fix parser'"), dir.path(), &runner).unwrap();
+        assert_eq!(status_of(&store, &bug.id), Status::Complete);
+        assert_eq!(status_of(&store, &epic.id), Status::Open);
+        let notes = store.get_notes(&epic.id).unwrap();
+        assert_eq!(notes[0].content, "Commit abc123: Fix parser");
+
+        // Running again for the same commit changes nothing
+        store
+            .update_task(&bug.id, TaskUpdate { status: Some(Status::Open), ..Default::default() })
+            .unwrap();
+        link_commit(&commit_input("git commit --amend"), dir.path(), &runner).unwrap();
+        assert_eq!(status_of(&store, &bug.id), Status::Open);
+        assert_eq!(store.get_notes(&epic.id).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_link_commit_respects_require_pr_and_checklist() {
+        let dir = TempDir::new().unwrap();
+        ProjectConfig { require_pr: true, ..Default::default() }.save_to(dir.path()).unwrap();
+        let store = SqliteTaskStore::for_project(dir.path()).unwrap();
+        let reviewed = store.create_task("Reviewed", "", Priority::High).unwrap();
+        let mut runner = MockCommandRunner::new();
+        expect_log(&mut runner, "abc123", &format!("Done\n\nCloses {}", reviewed.id));
+
+        link_commit(&commit_input("git commit -m Done"), dir.path(), &runner).unwrap();
+        assert_eq!(status_of(&store, &reviewed.id), Status::InReview);
+
+        ProjectConfig::default().save_to(dir.path()).unwrap();
+        let checked = store.create_task("Checked", "", Priority::High).unwrap();
+        store.request_tasks(&[&checked.id]).unwrap();
+        store.add_checklist_item(&checked.id, "docs updated").unwrap();
+        expect_log(&mut runner, "def456", &format!("Part\n\nfixes {}", checked.id));
+
+        link_commit(&commit_input("git commit -m Part"), dir.path(), &runner).unwrap();
+        assert_eq!(status_of(&store, &checked.id), Status::Open);
+        let notes = store.get_notes(&checked.id).unwrap();
+        assert!(notes[1].content.contains("checklist items not done: 1"), "{notes:?}");
+    }
+
+    #[test]
+    fn test_link_commit_respects_definition_of_done() {
+        let dir = TempDir::new().unwrap();
+        let config = ProjectConfig {
+            definition_of_done: vec!["Changelog updated".to_string()],
+            ..Default::default()
+        };
+        config.save_to(dir.path()).unwrap();
+        let store = SqliteTaskStore::for_project(dir.path()).unwrap();
+        let task = store.create_task("Ship", "", Priority::High).unwrap();
+        let mut runner = MockCommandRunner::new();
+        expect_log(&mut runner, "abc123", &format!("Ship\n\nCloses {}", task.id));
+        runner.expect(
+            "git",
+            &["rev-parse", "--git-dir"],
+            CommandOutput { exit_code: 128, ..Default::default() },
+        );
+
+        link_commit(&commit_input("git commit -m Ship"), dir.path(), &runner).unwrap();
+        assert_eq!(status_of(&store, &task.id), Status::Open);
+        let notes = store.get_notes(&task.id).unwrap();
+        assert!(notes[1].content.contains("Changelog updated: needs acknowledging"));
+    }
+
+    #[test]
+    fn test_link_commit_ignores_other_commands_and_unknown_ids() {
+        let dir = TempDir::new().unwrap();
+        let runner = MockCommandRunner::new();
+        link_commit(&commit_input("git commit -m x"), dir.path(), &runner).unwrap();
+
+        let store = SqliteTaskStore::for_project(dir.path()).unwrap();
+        link_commit(&commit_input("git status"), dir.path(), &runner).unwrap();
+
+        let mut runner = MockCommandRunner::new();
+        expect_log(&mut runner, "abc123", "Tidy\n\nCloses no-such-item");
+        link_commit(&commit_input("git commit -m Tidy"), dir.path(), &runner).unwrap();
+        assert!(store.list_tasks(tasks::TaskFilter::default()).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,198 @@
+//! Limit on how often the stop hook blocks a session without progress.
+//!
+//! Every block sends a message to the agent, or answers for the user, and
+//! starts another turn. A mis-tuned check can keep doing this indefinitely,
+//! and when the blocking check changes from turn to turn the escalation
+//! ladder for identical blocks never kicks in. This guard counts every block
+//! in a session, whatever its reason, and allows the stop with a summary of
+//! the checks that blocked once `injection_loop_limit` is exceeded.
+//!
+//! The count restarts when the user sends a message, the agent edits a file,
+//! or the git working state changes.
+
+use crate::git;
+use crate::hooks::stop::{StopHookConfig, StopHookResult, StopReason};
+use crate::session;
+use crate::templates;
+use crate::traits::CommandRunner;
+use serde::Serialize;
+use tera::Context;
+
+/// Number of times a check blocked during a streak.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+struct CheckCount {
+    name: String,
+    count: usize,
+}
+
+/// Count how often each check blocked, in the order they first blocked.
+fn count_checks(checks: &[String]) -> Vec<CheckCount> {
+    let mut counts: Vec<CheckCount> = Vec::new();
+    for check in checks {
+        match counts.iter_mut().find(|c| c.name == *check) {
+            Some(count) => count.count += 1,
+            None => counts.push(CheckCount { name: check.clone(), count: 1 }),
+        }
+    }
+    counts
+}
+
+/// Apply the block limit to a stop hook result for a session.
+///
+/// Allowed stops clear the session's streak.
+///
+/// # Panics
+///
+/// Panics if the injection loop template fails to render.
+pub fn apply(
+    result: StopHookResult,
+    config: &StopHookConfig,
+    runner: &dyn CommandRunner,
+    session_id: &str,
+) -> StopHookResult {
+    if config.injection_loop_limit == 0 {
+        return result;
+    }
+
+    let base_dir = config.base_dir();
+    if result.allow_stop {
+        let _ = session::clear_stop_injections(base_dir, session_id);
+        return result;
+    }
+
+    let state = if config.git_repo {
+        git::working_state_hash(runner).unwrap_or_default()
+    } else {
+        String::new()
+    };
+    let check = result.decided_by.as_deref().unwrap_or("unknown");
+    let Ok(checks) = session::record_stop_injection(base_dir, session_id, &state, check) else {
+        return result;
+    };
+    let count = u32::try_from(checks.len()).unwrap_or(u32::MAX);
+    if count <= config.injection_loop_limit {
+        return result;
+    }
+
+    let _ = session::clear_stop_injections(base_dir, session_id);
+    let blocking_checks = count_checks(&checks);
+    let mut ctx = Context::new();
+    ctx.insert("block_count", &count);
+    ctx.insert("blocking_checks", &blocking_checks);
+    let message = templates::render("messages/stop/injection_loop.tera", &ctx)
+        .expect("injection_loop.tera template should always render");
+
+    let mut allowed = StopHookResult::allow()
+        .with_message(message)
+        .with_explanation(
+            config.explain_stops,
+            format!("the stop hook blocked {count} times without any progress"),
+        )
+        .with_reason(StopReason::InjectionLoop)
+        .with_details(serde_json::json!({
+            "count": count,
+            "checks": blocking_checks,
+            "blocked_reason": result.reason,
+        }));
+    allowed.checks_log = result.checks_log;
+    allowed.decided_by = Some("injection_loop".to_string());
+    allowed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn blocked_by(check_name: &str) -> StopHookResult {
+        let mut result = StopHookResult::block().with_message("# Blocked");
+        result.decided_by = Some(check_name.to_string());
+        result
+    }
+
+    fn config_for(dir: &TempDir, limit: u32) -> StopHookConfig {
+        StopHookConfig {
+            base_dir: Some(dir.path().to_path_buf()),
+            injection_loop_limit: limit,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_count_checks() {
+        let checks: Vec<String> =
+            ["validation", "reflection_prompt", "validation"].map(String::from).to_vec();
+        assert_eq!(
+            count_checks(&checks),
+            vec![
+                CheckCount { name: "validation".to_string(), count: 2 },
+                CheckCount { name: "reflection_prompt".to_string(), count: 1 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_apply_allows_stop_past_limit() {
+        let dir = TempDir::new().unwrap();
+        let config = config_for(&dir, 3);
+        let runner = crate::testing::MockCommandRunner::new();
+
+        for check in ["validation", "auto_work", "validation"] {
+            let result = apply(blocked_by(check), &config, &runner, "s1");
+            assert!(!result.allow_stop);
+        }
+        let result = apply(
+            StopHookResult::block().with_inject("Yes, please continue."),
+            &config,
+            &runner,
+            "s1",
+        );
+        assert!(result.allow_stop);
+        assert_eq!(result.reason, Some(StopReason::InjectionLoop));
+        assert_eq!(result.decided_by.as_deref(), Some("injection_loop"));
+        let message = result.messages.join("\n");
+        assert!(message.contains("blocked this session 4 times"), "message: {message}");
+        assert!(message.contains("- `validation`: 2 times"), "message: {message}");
+        assert!(message.contains("- `unknown`: 1 time\n"), "message: {message}");
+
+        // The streak restarts after the forced allow
+        assert!(!apply(blocked_by("validation"), &config, &runner, "s1").allow_stop);
+    }
+
+    #[test]
+    fn test_apply_counts_sessions_separately() {
+        let dir = TempDir::new().unwrap();
+        let config = config_for(&dir, 1);
+        let runner = crate::testing::MockCommandRunner::new();
+
+        assert!(!apply(blocked_by("validation"), &config, &runner, "s1").allow_stop);
+        assert!(!apply(blocked_by("validation"), &config, &runner, "s2").allow_stop);
+        assert!(apply(blocked_by("validation"), &config, &runner, "s1").allow_stop);
+    }
+
+    #[test]
+    fn test_apply_progress_restarts_streak() {
+        let dir = TempDir::new().unwrap();
+        let config = config_for(&dir, 1);
+        let runner = crate::testing::MockCommandRunner::new();
+
+        apply(blocked_by("validation"), &config, &runner, "s1");
+        apply(StopHookResult::allow(), &config, &runner, "s1");
+        assert!(!apply(blocked_by("validation"), &config, &runner, "s1").allow_stop);
+
+        // A user message or file edit clears the streak too
+        session::clear_stop_injections(dir.path(), "s1").unwrap();
+        assert!(!apply(blocked_by("validation"), &config, &runner, "s1").allow_stop);
+    }
+
+    #[test]
+    fn test_apply_disabled_passes_through() {
+        let dir = TempDir::new().unwrap();
+        let config = config_for(&dir, 0);
+        let runner = crate::testing::MockCommandRunner::new();
+
+        for _ in 0..30 {
+            assert!(!apply(blocked_by("validation"), &config, &runner, "s1").allow_stop);
+        }
+    }
+}
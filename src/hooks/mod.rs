@@ -1,28 +1,55 @@
 //! Hook implementations for Claude Code.
 
+mod api_errors;
+mod bench;
+mod binary_files;
+mod block_escalation;
 mod code_review;
+mod commit_refs;
+mod injection_loop;
 mod no_verify;
+mod panic_guard;
 mod plan_tasks;
 mod post_tool_use;
 mod pre_tool_use;
 mod problem_mode;
 mod protect_config;
 mod require_task;
+mod sensitive_files;
+mod session_start;
+mod simulate;
+mod spelling;
+mod stash_tracking;
 mod stop;
 mod user_prompt_submit;
 mod validation;
 mod warn_on_warnings;
+mod watchdog;
 
+pub use bench::{bench_stop_hook, BenchResult, BenchSize, StopHookFixture};
 pub use code_review::{run_code_review_hook, CodeReviewConfig};
+pub(crate) use commit_refs::referenced_work_items;
 pub use no_verify::run_no_verify_hook;
-pub use post_tool_use::{run_post_tool_use, PostToolUseInput};
-pub use pre_tool_use::run_pre_tool_use;
+pub use panic_guard::{catch_panic, HookCrash, PANIC_OPERATION};
+pub use post_tool_use::{run_post_tool_use, run_post_tool_use_with_observer, PostToolUseInput};
+pub use pre_tool_use::{run_pre_tool_use, run_pre_tool_use_with_observer};
 pub use problem_mode::run_problem_mode_hook;
-pub use protect_config::run_protect_config_hook;
+pub use protect_config::{run_protect_config_hook, run_protect_package_paths_hook};
 pub use require_task::run_require_task_hook;
-pub use stop::{run_stop_hook, StopHookConfig, StopHookResult};
-pub use user_prompt_submit::{run_user_prompt_submit_hook, UserPromptSubmitInput};
+pub use session_start::{
+    resume_context, run_session_start_hook, run_session_start_hook_with_observer, SessionStartInput,
+};
+pub use simulate::{simulate_stop, simulate_stop_with_config, StopScenario};
+pub use stop::{
+    run_stop_hook, run_stop_hook_with_observer, StopHookConfig, StopHookResult, StopReason,
+    API_ERROR_THRESHOLD, AUTO_WORK_COOLDOWN_MINUTES, AUTO_WORK_DECLINE_LIMIT, MAX_FILES_TO_SHOW,
+    USER_RECENCY_MINUTES,
+};
+pub use user_prompt_submit::{
+    run_user_prompt_submit_hook, run_user_prompt_submit_hook_with_observer, UserPromptSubmitInput,
+};
 pub use validation::run_validation_hook;
+pub use watchdog::{Deadline, DEADLINE_MARGIN, HOOK_TIMEOUT_ENV, TIMEOUT_OPERATION};
 
 use crate::error::Result;
 use serde::{Deserialize, Serialize};
@@ -53,6 +80,21 @@ pub struct ToolInput {
     /// The file path being written/edited (for Write/Edit tools).
     #[serde(default)]
     pub file_path: Option<String>,
+    /// The notebook being edited (for `NotebookEdit` tool).
+    #[serde(default)]
+    pub notebook_path: Option<String>,
+    /// The new file content (for Write tool).
+    #[serde(default)]
+    pub content: Option<String>,
+    /// The text being replaced (for Edit tool).
+    #[serde(default)]
+    pub old_string: Option<String>,
+    /// The replacement text (for Edit tool).
+    #[serde(default)]
+    pub new_string: Option<String>,
+    /// Whether every occurrence of `old_string` is replaced (for Edit tool).
+    #[serde(default)]
+    pub replace_all: bool,
 }
 
 /// Output from a `PreToolUse` hook.
@@ -2,12 +2,13 @@
 //!
 //! This hook blocks `git commit --no-verify` and `git commit -n` unless
 //! explicitly acknowledged via the `NO_VERIFY_OK` environment variable.
+//! Commands are parsed with [`crate::command::parse`], so flags hidden by
+//! quoting, chaining or variables are still found.
 
+use crate::command::{self, SimpleCommand, Word};
 use crate::error::Result;
 use crate::hooks::{HookInput, PreToolUseOutput};
 use crate::templates;
-use once_cell::sync::Lazy;
-use regex::Regex;
 use std::env;
 use std::io::Write;
 use tera::Context;
@@ -15,13 +16,142 @@ use tera::Context;
 /// Acknowledgment phrase required in `NO_VERIFY_OK` env var.
 const ACKNOWLEDGMENT: &str = "I promise the user has said I can use --no-verify here";
 
-/// Patterns that match git commit with --no-verify or -n flag.
-static NO_VERIFY_PATTERNS: Lazy<Vec<Regex>> = Lazy::new(|| {
-    vec![
-        Regex::new(r"\bgit\s+commit\b.*--no-verify\b").unwrap(),
-        Regex::new(r"\bgit\s+commit\b.*\s-[a-zA-Z]*n").unwrap(), // -n anywhere in flags
-    ]
-});
+/// Git global options that take their value as a separate argument.
+const GIT_VALUE_OPTIONS: &[&str] =
+    &["-C", "-c", "--git-dir", "--work-tree", "--namespace", "--super-prefix", "--config-env"];
+
+/// `git commit` options that take their value as a separate argument.
+const COMMIT_VALUE_OPTIONS: &[&str] = &[
+    "--message",
+    "--file",
+    "--reuse-message",
+    "--reedit-message",
+    "--template",
+    "--author",
+    "--date",
+    "--fixup",
+    "--squash",
+    "--cleanup",
+    "--trailer",
+    "--pathspec-from-file",
+];
+
+/// `git push` options that take their value as a separate argument.
+const PUSH_VALUE_OPTIONS: &[&str] = &["--repo", "--receive-pack", "--exec", "--push-option"];
+
+/// Short `git commit` flags whose value is the rest of the flag cluster or
+/// the next argument.
+const COMMIT_SHORT_VALUE_FLAGS: &str = "mFCct";
+
+/// Short `git commit` flags whose optional value can only be attached.
+const COMMIT_SHORT_OPTIONAL_FLAGS: &str = "Su";
+
+/// Check whether a command line runs `git commit` or `git push` without
+/// their hooks.
+///
+/// Each simple command in the line is checked, including those chained
+/// with `&&` or `;`, nested in substitutions and run via `sh -c`. Hooks are
+/// considered skipped by `--no-verify` (or an abbreviation of it), by
+/// `git commit -n`, and by overriding `core.hooksPath`. An unquoted
+/// expansion in option position, such as `git commit $FLAGS`, could expand
+/// to any of those and is treated as skipping hooks too. Expansions in the
+/// refspecs of `git push`, such as `git push origin $BRANCH`, are allowed.
+pub fn skips_git_hooks(command: &str) -> bool {
+    command::parse(command).iter().any(command_skips_git_hooks)
+}
+
+fn command_skips_git_hooks(command: &SimpleCommand) -> bool {
+    if command.program() != Some("git") {
+        return false;
+    }
+    let args = command.args();
+    let mut index = 0;
+    let mut hooks_overridden = false;
+    while let Some(arg) = args.get(index) {
+        let text = arg.text.as_str();
+        if text == "-c" || text == "--config-env" {
+            hooks_overridden |= args.get(index + 1).is_some_and(overrides_hooks_path);
+        } else if let Some(value) = text.strip_prefix("--config-env=") {
+            hooks_overridden |=
+                overrides_hooks_path(&Word { text: value.to_string(), ..arg.clone() });
+        } else if !text.starts_with('-') || arg.unquoted_expansion {
+            break;
+        }
+        index += if GIT_VALUE_OPTIONS.contains(&text) { 2 } else { 1 };
+    }
+    let Some(subcommand) = args.get(index) else {
+        return false;
+    };
+    let rest = &args[index + 1..];
+    match subcommand.text.as_str() {
+        "commit" => hooks_overridden || options_skip_hooks(rest, COMMIT_VALUE_OPTIONS, false),
+        "push" => hooks_overridden || options_skip_hooks(rest, PUSH_VALUE_OPTIONS, true),
+        _ => subcommand.unquoted_expansion,
+    }
+}
+
+/// Whether a `-c` value sets `core.hooksPath`, or could at run time.
+fn overrides_hooks_path(value: &Word) -> bool {
+    value.expanded || value.text.to_ascii_lowercase().starts_with("core.hookspath")
+}
+
+/// Whether the options of `git commit` or `git push` skip hooks.
+///
+/// `-n` means `--no-verify` for commit but `--dry-run` for push, so short
+/// flags are only checked for commit. The words after the remote of a push
+/// are refspecs, so expansions there are not read as options.
+fn options_skip_hooks(args: &[Word], value_options: &[&str], push: bool) -> bool {
+    let mut args = args.iter();
+    let mut positionals = 0;
+    while let Some(arg) = args.next() {
+        let text = arg.text.as_str();
+        if text == "--" {
+            break;
+        }
+        if arg.unquoted_expansion {
+            // Literal text before the expansion keeps the word from being an option
+            let leading = text.starts_with(['$', '`']);
+            if leading && !(push && positionals > 0) {
+                return true;
+            }
+            positionals += 1;
+            continue;
+        }
+        if let Some(long) = text.strip_prefix("--") {
+            // Git accepts any unambiguous prefix of a long option
+            if long.len() >= "no-veri".len() && "no-verify".starts_with(long) {
+                return true;
+            }
+            if value_options.contains(&text) {
+                args.next();
+            }
+        } else if let Some(flags) = text.strip_prefix('-') {
+            if push {
+                if text == "-o" {
+                    args.next();
+                }
+                continue;
+            }
+            for (position, flag) in flags.char_indices() {
+                if flag == 'n' {
+                    return true;
+                }
+                if COMMIT_SHORT_VALUE_FLAGS.contains(flag) {
+                    if position + 1 == flags.len() {
+                        args.next();
+                    }
+                    break;
+                }
+                if COMMIT_SHORT_OPTIONAL_FLAGS.contains(flag) {
+                    break;
+                }
+            }
+        } else {
+            positionals += 1;
+        }
+    }
+    false
+}
 
 /// Result of the no-verify check.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -36,10 +166,7 @@ pub enum NoVerifyResult {
 
 /// Check no-verify with an explicit acknowledgment value (for testing).
 fn check_no_verify_with_ack(command: &str, ack_value: Option<&str>) -> NoVerifyResult {
-    // Check if this is a git commit with --no-verify or -n
-    let has_no_verify = NO_VERIFY_PATTERNS.iter().any(|re| re.is_match(command));
-
-    if !has_no_verify {
+    if !skips_git_hooks(command) {
         return NoVerifyResult::NoFlag;
     }
 
@@ -82,7 +209,7 @@ fn run_no_verify_hook_with_ack(input: &HookInput, ack_value: Option<&str>) -> Re
     match check_no_verify_with_ack(command, ack_value) {
         NoVerifyResult::NoFlag => Ok(0),
         NoVerifyResult::Acknowledged => {
-            eprintln!("--no-verify acknowledged by NO_VERIFY_OK environment variable");
+            tracing::info!("--no-verify acknowledged by NO_VERIFY_OK environment variable");
             Ok(0)
         }
         NoVerifyResult::Blocked => {
@@ -141,6 +268,50 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_skips_git_hooks_sees_through_shell_syntax() {
+        for command in [
+            "git add . && git commit --no-verify -m wip",
+            "git commit -m 'a' --no-ver\"ify\"",
+            "git commit --no-veri -m x",
+            "git commit -anm 'quick'",
+            "git commit $FLAGS -m x",
+            "FLAGS=--no-verify; git commit ${FLAGS}",
+            "git -C repo -c core.hooksPath=/dev/null commit -m x",
+            "bash -c 'git commit -n -m x'",
+            "echo $(git commit -n -m x)",
+            "sudo -u me /usr/bin/git commit --no-verify",
+            "git push --no-verify origin main",
+            "git push $FLAGS origin main",
+            "git push origin $BRANCH --no-verify",
+        ] {
+            assert!(skips_git_hooks(command), "{command}");
+        }
+    }
+
+    #[test]
+    fn test_skips_git_hooks_ignores_lookalikes() {
+        for command in [
+            "git commit -m 'do not use --no-verify or -n'",
+            "git commit -m -n",
+            "git commit -mn",
+            "git commit -F \"$MSG_FILE\" -- -n",
+            "git commit -m \"$(printf 'fix\\n\\nskip --no-verify')\"",
+            "echo git commit --no-verify",
+            "git log -n 5 && grep -e --no-verify notes.txt",
+            "git push -n origin main",
+            "git push origin \"$BRANCH\"",
+            "git push origin $BRANCH",
+            "git push -u origin $(git branch --show-current)",
+            "git push origin HEAD:$BRANCH",
+            "git commit -m x path/$FILE",
+            "git -c user.name=me commit -m x",
+            "git commit --no-verbose -m x",
+        ] {
+            assert!(!skips_git_hooks(command), "{command}");
+        }
+    }
+
     #[test]
     fn test_check_no_verify_acknowledged() {
         let ack = Some("I promise the user has said I can use --no-verify here");
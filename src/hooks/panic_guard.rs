@@ -0,0 +1,246 @@
+//! Catching hook panics so that a crash never leaves the agent stuck.
+//!
+//! A hook that panics exits with code 101, which Claude Code reports as a
+//! failed hook without saying why, and what happens next depends on the hook
+//! type. Hook entry points run inside [`catch_panic`] instead: the panic's
+//! message, location and backtrace are written to a crash file in
+//! `.claude-reliability/crashes/` and recorded in the audit log, and the
+//! caller returns the decision set by the `failure_mode` config setting.
+//! Worker threads that a hook spawns with [`spawn_guarded`] report their
+//! panics the same way once the hook joins them.
+
+use crate::paths;
+use crate::tasks;
+use chrono::Utc;
+use std::any::Any;
+use std::backtrace::Backtrace;
+use std::cell::{Cell, RefCell};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::Once;
+use std::thread::{Scope, ScopedJoinHandle};
+
+/// Audit log operation recording a hook that panicked.
+pub const PANIC_OPERATION: &str = "hook_panic";
+
+thread_local! {
+    /// Whether the current thread is inside [`catch_panic`].
+    static GUARDED: Cell<bool> = const { Cell::new(false) };
+    /// Where the last guarded panic happened, and its backtrace.
+    static LAST_PANIC: RefCell<Option<PanicDetails>> = const { RefCell::new(None) };
+}
+
+/// Where a panic happened, if known, and its backtrace.
+type PanicDetails = (Option<String>, String);
+
+/// A worker thread's panic payload, with the details captured on the worker.
+type WorkerPanic = (Box<dyn Any + Send>, Option<PanicDetails>);
+
+static INSTALL_HOOK: Once = Once::new();
+
+/// A hook that panicked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HookCrash {
+    /// The hook that panicked.
+    pub hook: String,
+    /// The panic message.
+    pub message: String,
+    /// The source location of the panic, if known.
+    pub location: Option<String>,
+    /// The crash file the details were written to, if it could be written.
+    pub crash_file: Option<PathBuf>,
+}
+
+impl HookCrash {
+    /// One line describing the crash and where to find the details.
+    #[must_use]
+    pub fn summary(&self) -> String {
+        let location = self.location.as_deref().map_or_else(String::new, |l| format!(" at {l}"));
+        let details = self
+            .crash_file
+            .as_ref()
+            .map_or_else(String::new, |f| format!(" (details in {})", f.display()));
+        format!("The {} hook crashed{location}: {}{details}", self.hook, self.message)
+    }
+}
+
+/// Run a hook, catching any panic.
+///
+/// A panic is reported through the crash file and audit log of `base_dir`
+/// instead of the default panic message on stderr.
+///
+/// # Errors
+///
+/// Returns the crash if the hook panicked.
+pub fn catch_panic<T>(
+    hook: &str,
+    base_dir: &Path,
+    f: impl FnOnce() -> T,
+) -> std::result::Result<T, HookCrash> {
+    install_panic_hook();
+    let was_guarded = GUARDED.replace(true);
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    GUARDED.set(was_guarded);
+
+    result.map_err(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(ToString::to_string)
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic".to_string());
+        let (location, backtrace) = LAST_PANIC.take().unwrap_or_default();
+        let mut crash = HookCrash { hook: hook.to_string(), message, location, crash_file: None };
+        crash.crash_file = write_crash_file(base_dir, &crash, &backtrace);
+        tracing::error!("{}", crash.summary());
+        record_crash(base_dir, &crash);
+        crash
+    })
+}
+
+/// A worker thread spawned with [`spawn_guarded`].
+pub struct GuardedHandle<'scope, T> {
+    handle: ScopedJoinHandle<'scope, Result<T, WorkerPanic>>,
+}
+
+impl<T> GuardedHandle<'_, T> {
+    /// Wait for the thread to finish, re-raising its panic, if any, on the
+    /// calling thread with the location and backtrace it had on the worker.
+    pub fn join(self) -> T {
+        match self.handle.join() {
+            Ok(Ok(value)) => value,
+            Ok(Err((payload, details))) => {
+                if details.is_some() {
+                    LAST_PANIC.set(details);
+                }
+                panic::resume_unwind(payload)
+            }
+            Err(payload) => panic::resume_unwind(payload),
+        }
+    }
+}
+
+/// Spawn a scoped worker thread that is guarded if the calling thread is.
+///
+/// The panic hook only captures the details of panics on guarded threads, so
+/// a worker of a hook running in [`catch_panic`] must be spawned this way for
+/// its panics to be reported with their own location and backtrace.
+pub fn spawn_guarded<'scope, T: Send + 'scope>(
+    scope: &'scope Scope<'scope, '_>,
+    f: impl FnOnce() -> T + Send + 'scope,
+) -> GuardedHandle<'scope, T> {
+    let guarded = GUARDED.get();
+    let handle = scope.spawn(move || {
+        GUARDED.set(guarded);
+        panic::catch_unwind(AssertUnwindSafe(f)).map_err(|payload| (payload, LAST_PANIC.take()))
+    });
+    GuardedHandle { handle }
+}
+
+/// Install a panic hook that captures guarded panics and passes on the rest.
+fn install_panic_hook() {
+    INSTALL_HOOK.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if GUARDED.get() {
+                let location = info.location().map(ToString::to_string);
+                LAST_PANIC.set(Some((location, Backtrace::force_capture().to_string())));
+            } else {
+                previous(info);
+            }
+        }));
+    });
+}
+
+/// Write the crash details to a new file in the crashes directory.
+fn write_crash_file(base_dir: &Path, crash: &HookCrash, backtrace: &str) -> Option<PathBuf> {
+    let now = Utc::now();
+    let dir = paths::project_crashes_dir(base_dir);
+    let path = dir.join(format!("{}-{}.txt", crash.hook, now.format("%Y%m%dT%H%M%S%.3fZ")));
+    let contents = format!(
+        "claude-reliability v{} {} hook panicked at {}\nLocation: {}\nMessage: {}\n\nBacktrace:\n{backtrace}\n",
+        crate::VERSION,
+        crash.hook,
+        now.to_rfc3339(),
+        crash.location.as_deref().unwrap_or("unknown"),
+        crash.message,
+    );
+    match std::fs::create_dir_all(&dir).and_then(|()| std::fs::write(&path, contents)) {
+        Ok(()) => Some(path),
+        Err(e) => {
+            tracing::warn!("Failed to write crash file {}: {e}", path.display());
+            None
+        }
+    }
+}
+
+/// Record the crash in the audit log, if the project has a database.
+fn record_crash(base_dir: &Path, crash: &HookCrash) {
+    let Some(store) = tasks::open_project_store(base_dir) else {
+        return;
+    };
+    if let Err(e) = store.record_event(PANIC_OPERATION, &crash.summary()) {
+        tracing::warn!("Failed to record hook crash: {e}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tasks::{SqliteTaskStore, TaskStore};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_catch_panic_passes_results_through() {
+        let dir = TempDir::new().unwrap();
+        assert_eq!(catch_panic("stop", dir.path(), || 42), Ok(42));
+        assert!(!paths::project_crashes_dir(dir.path()).exists());
+    }
+
+    #[test]
+    fn test_catch_panic_reports_crash() {
+        let dir = TempDir::new().unwrap();
+        let store = SqliteTaskStore::for_project(dir.path()).unwrap();
+
+        let crash = catch_panic("stop", dir.path(), || -> u32 { panic!("database is locked") })
+            .unwrap_err();
+        assert_eq!(crash.hook, "stop");
+        assert_eq!(crash.message, "database is locked");
+        assert!(crash.location.as_deref().unwrap().contains("panic_guard.rs"));
+
+        let contents = std::fs::read_to_string(crash.crash_file.as_ref().unwrap()).unwrap();
+        assert!(contents.contains("Message: database is locked"), "{contents}");
+        assert!(contents.contains("Backtrace:"), "{contents}");
+
+        let log = store.get_audit_log(None, None).unwrap();
+        assert_eq!(log[0].operation, PANIC_OPERATION);
+        assert_eq!(log[0].details.as_deref(), Some(crash.summary().as_str()));
+        assert!(crash.summary().starts_with("The stop hook crashed at "));
+    }
+
+    #[test]
+    fn test_catch_panic_with_formatted_message() {
+        let dir = TempDir::new().unwrap();
+        let crash =
+            catch_panic("pre-tool-use", dir.path(), || panic!("bad row {}", 3)).unwrap_err();
+        assert_eq!(crash.message, "bad row 3");
+        // The guard is lifted once the hook has run
+        assert!(!GUARDED.get());
+    }
+
+    #[test]
+    fn test_catch_panic_reports_worker_thread_crash() {
+        let dir = TempDir::new().unwrap();
+        let crash = catch_panic("stop", dir.path(), || {
+            std::thread::scope(|scope| {
+                let ok = spawn_guarded(scope, || 1);
+                let failing = spawn_guarded(scope, || -> u32 { panic!("worker failed") });
+                ok.join() + failing.join()
+            })
+        })
+        .unwrap_err();
+        assert_eq!(crash.message, "worker failed");
+        assert!(crash.location.as_deref().unwrap().contains("panic_guard.rs"));
+        let contents = std::fs::read_to_string(crash.crash_file.as_ref().unwrap()).unwrap();
+        assert!(contents.contains("test_catch_panic_reports_worker_thread_crash"), "{contents}");
+    }
+}
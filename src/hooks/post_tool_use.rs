@@ -3,10 +3,17 @@
 //! This module handles hooks that run after a tool completes execution.
 //! Currently supports:
 //! - `ExitPlanMode`: Creates tasks to track plan implementation
-//! - `Bash`: Detects warnings in stderr and creates work items to track them
+//! - `Bash`: Detects warnings in stderr and creates work items to track them,
+//!   records stashes created by the agent, and updates work items referenced
+//!   by new commits
+//! - `Task`, `WebFetch`, `WebSearch`: Track API errors shared with the other hooks
 
+use crate::hooks::api_errors::{self, BackoffConfig};
 use crate::hooks::plan_tasks::{create_plan_tasks, ExitPlanModeToolResponse};
-use crate::hooks::warn_on_warnings;
+use crate::hooks::watchdog::{self, Deadline};
+use crate::hooks::{commit_refs, stash_tracking, warn_on_warnings};
+use crate::traits::{CommandRunner, HookDecision, HookObserver, NoopObserver};
+use std::cell::Cell;
 use std::path::Path;
 
 /// Input provided to `PostToolUse` hooks by Claude Code.
@@ -29,26 +36,95 @@ pub struct PostToolUseInput {
 ///
 /// * `input` - The parsed hook input containing `tool_name` and `tool_response`
 /// * `base_dir` - The base directory for the project
+/// * `runner` - Command runner for git lookups
 ///
 /// # Errors
 ///
 /// Returns an error if the tool response cannot be parsed or if task creation fails.
-pub fn run_post_tool_use(input: &PostToolUseInput, base_dir: &Path) -> Result<(), String> {
+pub fn run_post_tool_use(
+    input: &PostToolUseInput,
+    base_dir: &Path,
+    runner: &dyn CommandRunner,
+) -> Result<(), String> {
+    let deadline = Deadline::for_project(base_dir);
+    run_post_tool_use_with_observer(input, base_dir, runner, &NoopObserver, deadline)
+}
+
+/// Run all applicable `PostToolUse` hooks, reporting each check and the
+/// decision to `observer`.
+///
+/// The hook never blocks, so the observer cannot veto anything. Once
+/// `deadline` is reached the remaining checks are skipped and the timeout is
+/// recorded in the audit log.
+///
+/// # Errors
+///
+/// Returns an error if the tool response cannot be parsed or if task creation fails.
+pub fn run_post_tool_use_with_observer(
+    input: &PostToolUseInput,
+    base_dir: &Path,
+    runner: &dyn CommandRunner,
+    observer: &dyn HookObserver,
+    deadline: Option<Deadline>,
+) -> Result<(), String> {
+    let result = run_post_tool_use_checks(input, base_dir, runner, observer, deadline);
+    observer.on_decision(&HookDecision { hook: "post_tool_use", allow: true, decided_by: None });
+    result
+}
+
+/// Run the `PostToolUse` checks for the tool, returning early on the first error.
+fn run_post_tool_use_checks(
+    input: &PostToolUseInput,
+    base_dir: &Path,
+    runner: &dyn CommandRunner,
+    observer: &dyn HookObserver,
+    deadline: Option<Deadline>,
+) -> Result<(), String> {
     let tool_name = input.tool_name.as_deref().unwrap_or("");
+    let last_check = Cell::new(None);
+
+    // Helper macro to report a check to the observer and return early if it
+    // fails or the deadline has been reached
+    macro_rules! check {
+        ($name:literal, $result:expr) => {
+            if deadline.is_some_and(|deadline| deadline.is_reached()) {
+                let during = last_check.get().unwrap_or($name);
+                tracing::warn!("post_tool_use hook ran out of time during {during}");
+                watchdog::record_timeout(base_dir, "post_tool_use", during);
+                return Ok(());
+            }
+            last_check.set(Some($name));
+            observer.on_check_start("post_tool_use", $name);
+            let result = $result;
+            let outcome = if result.is_ok() { "ok" } else { "failed" };
+            observer.on_check_end("post_tool_use", $name, outcome);
+            result?;
+        };
+    }
 
     if tool_name == "ExitPlanMode" {
         // Parse ExitPlanMode-specific response
         if let Some(response) = &input.tool_response {
-            let exit_response: ExitPlanModeToolResponse = serde_json::from_value(response.clone())
-                .map_err(|e| format!("Failed to parse ExitPlanMode response: {e}"))?;
-            create_plan_tasks(&exit_response, base_dir)?;
+            check!(
+                "plan_tasks",
+                serde_json::from_value::<ExitPlanModeToolResponse>(response.clone())
+                    .map_err(|e| format!("Failed to parse ExitPlanMode response: {e}"))
+                    .and_then(|exit_response| create_plan_tasks(&exit_response, base_dir))
+            );
         }
     }
 
     if tool_name == "Bash" {
-        warn_on_warnings::check_bash_warnings(input, base_dir)?;
+        check!("warnings", warn_on_warnings::check_bash_warnings(input, base_dir));
+        check!("stash_tracking", stash_tracking::track_stash(input, base_dir, runner));
+        check!("commit_refs", commit_refs::link_commit(input, base_dir, runner));
     }
 
+    check!(
+        "api_errors",
+        api_errors::record_tool_result(input, base_dir, &BackoffConfig::default())
+    );
+
     Ok(())
 }
 
@@ -57,6 +133,7 @@ mod tests {
     use super::*;
     use crate::paths;
     use crate::tasks::{SqliteTaskStore, TaskFilter, TaskStore};
+    use crate::testing::MockCommandRunner;
     use tempfile::TempDir;
 
     fn setup_db(dir: &Path) {
@@ -77,7 +154,7 @@ mod tests {
             })),
         };
 
-        let result = run_post_tool_use(&input, dir.path());
+        let result = run_post_tool_use(&input, dir.path(), &MockCommandRunner::new());
         assert!(result.is_ok());
 
         // Verify tasks were created
@@ -86,6 +163,66 @@ mod tests {
         assert_eq!(tasks.len(), 2);
     }
 
+    #[test]
+    fn test_run_post_tool_use_reports_to_observer() {
+        use crate::testing::RecordingObserver;
+
+        let dir = TempDir::new().unwrap();
+        let observer = RecordingObserver::vetoing("ignored");
+        let input = PostToolUseInput {
+            tool_name: Some("ExitPlanMode".to_string()),
+            tool_input: None,
+            tool_response: Some(serde_json::json!({"unexpected": true})),
+        };
+
+        let result = run_post_tool_use_with_observer(
+            &input,
+            dir.path(),
+            &MockCommandRunner::new(),
+            &observer,
+            None,
+        );
+        assert!(result.is_err());
+        assert_eq!(
+            observer.events(),
+            vec![
+                "start post_tool_use/plan_tasks",
+                "end post_tool_use/plan_tasks: failed",
+                "decision post_tool_use: allow",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_run_post_tool_use_skips_checks_after_deadline() {
+        use crate::testing::RecordingObserver;
+
+        let dir = TempDir::new().unwrap();
+        let store = SqliteTaskStore::for_project(dir.path()).unwrap();
+        let observer = RecordingObserver::new();
+        let input = PostToolUseInput {
+            tool_name: Some("ExitPlanMode".to_string()),
+            tool_input: None,
+            tool_response: Some(serde_json::json!({"unexpected": true})),
+        };
+
+        let result = run_post_tool_use_with_observer(
+            &input,
+            dir.path(),
+            &MockCommandRunner::new(),
+            &observer,
+            Some(Deadline::after(std::time::Duration::ZERO)),
+        );
+        assert!(result.is_ok());
+        assert_eq!(observer.events(), vec!["decision post_tool_use: allow"]);
+        let log = store.get_audit_log(None, None).unwrap();
+        assert_eq!(log[0].operation, watchdog::TIMEOUT_OPERATION);
+        assert_eq!(
+            log[0].details.as_deref(),
+            Some("post_tool_use hook ran out of time during plan_tasks")
+        );
+    }
+
     #[test]
     fn test_run_post_tool_use_unknown_tool() {
         let dir = TempDir::new().unwrap();
@@ -97,7 +234,7 @@ mod tests {
         };
 
         // Should succeed (no hooks for unknown tools)
-        let result = run_post_tool_use(&input, dir.path());
+        let result = run_post_tool_use(&input, dir.path(), &MockCommandRunner::new());
         assert!(result.is_ok());
     }
 
@@ -108,7 +245,7 @@ mod tests {
         let input = PostToolUseInput { tool_name: None, tool_input: None, tool_response: None };
 
         // Should succeed (no tool name means nothing to do)
-        let result = run_post_tool_use(&input, dir.path());
+        let result = run_post_tool_use(&input, dir.path(), &MockCommandRunner::new());
         assert!(result.is_ok());
     }
 
@@ -124,7 +261,7 @@ mod tests {
         };
 
         // Should succeed (no response means nothing to process)
-        let result = run_post_tool_use(&input, dir.path());
+        let result = run_post_tool_use(&input, dir.path(), &MockCommandRunner::new());
         assert!(result.is_ok());
 
         // Verify no tasks were created
@@ -145,7 +282,7 @@ mod tests {
             tool_response: Some(serde_json::json!({"plan": "content only"})),
         };
 
-        let result = run_post_tool_use(&input, dir.path());
+        let result = run_post_tool_use(&input, dir.path(), &MockCommandRunner::new());
         assert!(result.is_err());
         assert!(result.unwrap_err().contains("No plan file path"));
     }
@@ -165,7 +302,7 @@ mod tests {
             })),
         };
 
-        let result = run_post_tool_use(&input, dir.path());
+        let result = run_post_tool_use(&input, dir.path(), &MockCommandRunner::new());
         assert!(result.is_ok());
 
         let store = SqliteTaskStore::for_project(dir.path()).unwrap();
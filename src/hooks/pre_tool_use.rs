@@ -3,14 +3,18 @@
 //! This module consolidates all `PreToolUse` hook logic into a single entry point,
 //! dispatching to appropriate handlers based on tool name.
 
+use crate::command;
+use crate::config::ProjectConfig;
 use crate::hooks::{
-    plan_tasks, run_code_review_hook, run_problem_mode_hook, run_protect_config_hook,
-    run_require_task_hook, run_validation_hook, CodeReviewConfig, HookInput, PreToolUseOutput,
+    api_errors, binary_files, no_verify, plan_tasks, run_code_review_hook, run_problem_mode_hook,
+    run_protect_config_hook, run_protect_package_paths_hook, run_require_task_hook,
+    run_validation_hook, sensitive_files, spelling, CodeReviewConfig, HookInput, PreToolUseOutput,
 };
+use crate::paths;
 use crate::reminders;
 use crate::subagent::RealSubAgent;
 use crate::templates;
-use crate::traits::{CommandRunner, SubAgent};
+use crate::traits::{CommandRunner, HookDecision, HookObserver, NoopObserver, SubAgent};
 use crate::transcript;
 use std::path::Path;
 use tera::Context;
@@ -46,13 +50,60 @@ pub fn run_pre_tool_use_with_sub_agent(
     base_dir: &Path,
     runner: &dyn CommandRunner,
     sub_agent: &dyn SubAgent,
+) -> PreToolUseOutput {
+    run_pre_tool_use_with_observer(input, base_dir, runner, sub_agent, &NoopObserver)
+}
+
+/// Run all applicable `PreToolUse` hooks, reporting each check and the decision to `observer`.
+///
+/// The observer may veto a decision to allow the tool call, in which case the
+/// call is blocked with the observer's message. Running
+/// `claude-reliability emergency-stop` cannot be vetoed, so the agent always
+/// has a way out.
+pub fn run_pre_tool_use_with_observer(
+    input: &HookInput,
+    base_dir: &Path,
+    runner: &dyn CommandRunner,
+    sub_agent: &dyn SubAgent,
+    observer: &dyn HookObserver,
+) -> PreToolUseOutput {
+    let output = run_pre_tool_use_checks(input, base_dir, runner, sub_agent, observer);
+    let decision =
+        HookDecision { hook: "pre_tool_use", allow: !output.is_block(), decided_by: None };
+    let vetoable = !output.is_block() && !is_escape_hatch(input);
+    match observer.on_decision(&decision) {
+        Some(message) if vetoable => PreToolUseOutput::block(Some(message)),
+        _ => output,
+    }
+}
+
+/// Whether the tool call runs `claude-reliability emergency-stop`.
+fn is_escape_hatch(input: &HookInput) -> bool {
+    input.tool_name.as_deref() == Some("Bash")
+        && input.tool_input.as_ref().and_then(|t| t.command.as_deref()).is_some_and(|line| {
+            command::parse(line)
+                .iter()
+                .any(|command| command.reliability_subcommand() == Some("emergency-stop"))
+        })
+}
+
+/// Run the `PreToolUse` checks for the tool, returning early on the first block.
+fn run_pre_tool_use_checks(
+    input: &HookInput,
+    base_dir: &Path,
+    runner: &dyn CommandRunner,
+    sub_agent: &dyn SubAgent,
+    observer: &dyn HookObserver,
 ) -> PreToolUseOutput {
     let tool_name = input.tool_name.as_deref().unwrap_or("");
 
-    // Helper macro to return early if result is a block
+    // Helper macro to report a check to the observer and return early if it blocks
     macro_rules! check_hook {
-        ($result:expr) => {
+        ($name:literal, $result:expr) => {
+            observer.on_check_start("pre_tool_use", $name);
             let result = $result;
+            let outcome = if result.is_block() { "blocked" } else { "allowed" };
+            observer.on_check_end("pre_tool_use", $name, outcome);
             if result.is_block() {
                 return result;
             }
@@ -60,20 +111,68 @@ pub fn run_pre_tool_use_with_sub_agent(
     }
 
     // Problem mode check - applies to all tools
-    check_hook!(run_problem_mode_hook(input, base_dir));
+    check_hook!("problem_mode", run_problem_mode_hook(input, base_dir));
+
+    // Don't call an overloaded API through tools while it is backing off
+    check_hook!("api_backoff", api_errors::check_tool_backoff(input, base_dir));
+
+    // Non-blocking guidance from hooks, added to any reminder context
+    let mut hook_context = None;
 
     // Tool-specific hooks
     match tool_name {
         "Bash" => {
             // Check for direct sqlite3 access to working-memory database
-            check_hook!(run_direct_db_access_check(input));
+            check_hook!("direct_db_access", run_direct_db_access_check(input, base_dir));
 
             // Check for --no-verify
-            check_hook!(run_no_verify_check(input));
+            check_hook!("no_verify", run_no_verify_check(input));
+
+            // Protect the reliability config, database and settings hooks
+            check_hook!("protect_config", run_protect_config_hook(input, base_dir));
+
+            // Require task in progress, when the scope covers Bash commands
+            check_hook!("require_task", run_require_task_hook(input, base_dir));
+
+            // Check for credential files being committed
+            check_hook!(
+                "sensitive_files",
+                sensitive_files::run_sensitive_files_check(input, base_dir, runner)
+            );
+
+            // Check for large binary files being committed
+            observer.on_check_start("pre_tool_use", "binary_files");
+            let binary_files = binary_files::run_binary_files_check(input, base_dir, runner);
+            let outcome = if binary_files.is_block() { "blocked" } else { "allowed" };
+            observer.on_check_end("pre_tool_use", "binary_files", outcome);
+            if binary_files.is_block() {
+                return binary_files;
+            }
+            hook_context = binary_files.hook_specific_output.additional_context;
+
+            // Warn about misspellings in committed prose
+            let spelling = spelling::run_spelling_check(input, base_dir, runner);
+            if let Some(warnings) = spelling.hook_specific_output.additional_context {
+                hook_context = Some(match hook_context {
+                    Some(context) => format!("{context}\n\n{warnings}"),
+                    None => warnings,
+                });
+            }
 
             // Code review for git commits
-            let config = CodeReviewConfig::default();
-            if let Ok(exit_code) = run_code_review_hook(input, &config, runner, sub_agent) {
+            let config = CodeReviewConfig {
+                deep_secrets_scan: ProjectConfig::load_from(base_dir)
+                    .ok()
+                    .flatten()
+                    .is_some_and(|config| config.deep_secrets_scan),
+                base_dir: base_dir.to_path_buf(),
+            };
+            observer.on_check_start("pre_tool_use", "code_review");
+            let review = run_code_review_hook(input, &config, runner, sub_agent);
+            let outcome =
+                if matches!(review, Ok(code) if code != 0) { "blocked" } else { "allowed" };
+            observer.on_check_end("pre_tool_use", "code_review", outcome);
+            if let Ok(exit_code) = review {
                 if exit_code != 0 {
                     return PreToolUseOutput::block(Some(
                         "Code review required before commit".to_string(),
@@ -84,18 +183,24 @@ pub fn run_pre_tool_use_with_sub_agent(
 
         "Write" | "Edit" => {
             // Validation tracking (doesn't block, just tracks)
-            check_hook!(run_validation_hook(input, base_dir));
+            check_hook!("validation", run_validation_hook(input, base_dir));
 
             // Require task in progress
-            check_hook!(run_require_task_hook(input, base_dir));
+            check_hook!("require_task", run_require_task_hook(input, base_dir));
 
             // Protect config files
-            check_hook!(run_protect_config_hook(input));
+            check_hook!("protect_config", run_protect_config_hook(input, base_dir));
+
+            // Protect files of workspace packages
+            check_hook!("protect_package_paths", run_protect_package_paths_hook(input, base_dir));
         }
 
         "NotebookEdit" => {
             // Validation tracking
-            check_hook!(run_validation_hook(input, base_dir));
+            check_hook!("validation", run_validation_hook(input, base_dir));
+
+            // Require task in progress, when the scope covers notebooks
+            check_hook!("require_task", run_require_task_hook(input, base_dir));
         }
 
         "EnterPlanMode" => {
@@ -115,7 +220,10 @@ pub fn run_pre_tool_use_with_sub_agent(
     }
 
     // All hooks passed - check for reminders
-    let context = get_reminder_context(input, base_dir);
+    let context = match (hook_context, get_reminder_context(input, base_dir)) {
+        (Some(hook_context), Some(reminders)) => Some(format!("{hook_context}\n\n{reminders}")),
+        (hook_context, reminders) => hook_context.or(reminders),
+    };
     PreToolUseOutput::allow(context)
 }
 
@@ -169,7 +277,7 @@ fn handle_exit_plan_mode(base_dir: &Path, plans_dir_override: Option<&Path>) {
 
     if let Err(e) = result {
         // Log but don't block - plan approval should continue
-        eprintln!("Warning: Failed to create plan tasks: {e}");
+        tracing::warn!("Failed to create plan tasks: {e}");
     }
 }
 
@@ -177,11 +285,7 @@ fn handle_exit_plan_mode(base_dir: &Path, plans_dir_override: Option<&Path>) {
 fn run_no_verify_check(input: &HookInput) -> PreToolUseOutput {
     let command = input.tool_input.as_ref().and_then(|ti| ti.command.as_deref()).unwrap_or("");
 
-    // Check for --no-verify or -n flag (only for git commit)
-    let is_git_commit = command.contains("git commit") || command.contains("git push");
-    let has_no_verify = command.contains("--no-verify");
-
-    if is_git_commit && has_no_verify {
+    if no_verify::skips_git_hooks(command) {
         let mut ctx = Context::new();
         ctx.insert("acknowledgment", "I promise the user has said I can use --no-verify here");
         let message = templates::render("messages/no_verify_block.tera", &ctx)
@@ -193,14 +297,29 @@ fn run_no_verify_check(input: &HookInput) -> PreToolUseOutput {
 }
 
 /// Check for direct sqlite3 access to the working-memory database.
-fn run_direct_db_access_check(input: &HookInput) -> PreToolUseOutput {
+///
+/// Simple commands that mention sqlite3 and name the default database
+/// location or the configured `db_path` in their arguments, redirections or
+/// here-documents are blocked.
+fn run_direct_db_access_check(input: &HookInput, base_dir: &Path) -> PreToolUseOutput {
     let command = input.tool_input.as_ref().and_then(|ti| ti.command.as_deref()).unwrap_or("");
 
-    let has_sqlite3 = command.contains("sqlite3");
-    let targets_working_memory =
-        command.contains("working-memory.sqlite3") || command.contains(".claude-reliability/");
-
-    if has_sqlite3 && targets_working_memory {
+    let db_file_name = paths::project_db_path(base_dir)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let targets_working_memory = |text: &str| {
+        text.contains("working-memory.sqlite3")
+            || text.contains(".claude-reliability/")
+            || (!db_file_name.is_empty() && text.contains(&db_file_name))
+    };
+    let accesses_db = command::parse(command).iter().any(|segment| {
+        let mut texts = segment.words.iter().chain(&segment.redirects).map(|w| w.text.as_str());
+        let has_sqlite3 = texts.clone().any(|text| text.contains("sqlite3"));
+        has_sqlite3 && texts.any(targets_working_memory)
+    });
+
+    if accesses_db {
         let ctx = Context::new();
         let message = templates::render("messages/direct_db_access_block.tera", &ctx)
             .expect("direct_db_access_block.tera template should always render");
@@ -290,6 +409,78 @@ mod tests {
         assert!(output.is_block());
     }
 
+    #[test]
+    fn test_observer_sees_checks_and_decision() {
+        use crate::testing::{MockSubAgent, RecordingObserver};
+
+        let dir = TempDir::new().unwrap();
+        let runner = MockCommandRunner::new();
+        let observer = RecordingObserver::new();
+        let input = HookInput {
+            tool_name: Some("Bash".to_string()),
+            tool_input: Some(ToolInput {
+                command: Some("git commit --no-verify -m test".to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let output = run_pre_tool_use_with_observer(
+            &input,
+            dir.path(),
+            &runner,
+            &MockSubAgent::new(),
+            &observer,
+        );
+        assert!(output.is_block());
+        let events = observer.events();
+        assert_eq!(events.first().unwrap(), "start pre_tool_use/problem_mode");
+        assert!(events.contains(&"end pre_tool_use/no_verify: blocked".to_string()));
+        assert_eq!(events.last().unwrap(), "decision pre_tool_use: block");
+    }
+
+    #[test]
+    fn test_observer_can_veto_allowed_tool() {
+        use crate::testing::{MockSubAgent, RecordingObserver};
+
+        let dir = TempDir::new().unwrap();
+        let runner = MockCommandRunner::new();
+        let observer = RecordingObserver::vetoing("Tool calls are paused");
+        let input = HookInput { tool_name: Some("Read".to_string()), ..Default::default() };
+
+        let output = run_pre_tool_use_with_observer(
+            &input,
+            dir.path(),
+            &runner,
+            &MockSubAgent::new(),
+            &observer,
+        );
+        assert!(output.is_block());
+        assert_eq!(
+            output.hook_specific_output.additional_context.as_deref(),
+            Some("Tool calls are paused")
+        );
+
+        // The emergency stop is always allowed through
+        let input = HookInput {
+            tool_name: Some("Bash".to_string()),
+            tool_input: Some(ToolInput {
+                command: Some("claude-reliability emergency-stop \"The disk is full\"".to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let output = run_pre_tool_use_with_observer(
+            &input,
+            dir.path(),
+            &runner,
+            &MockSubAgent::new(),
+            &observer,
+        );
+        assert!(!output.is_block());
+        assert_eq!(observer.events().last().unwrap(), "decision pre_tool_use: allow");
+    }
+
     #[test]
     fn test_unknown_tool_allowed() {
         let dir = TempDir::new().unwrap();
@@ -395,6 +586,26 @@ mod tests {
         let dir = TempDir::new().unwrap();
         let mut runner = MockCommandRunner::new();
 
+        // No sensitive or binary files are staged
+        runner.expect(
+            "git",
+            &["diff", "--cached", "--name-only", "--diff-filter=d"],
+            CommandOutput {
+                exit_code: 0,
+                stdout: "src/main.rs\n".to_string(),
+                stderr: String::new(),
+            },
+        );
+        runner.expect(
+            "git",
+            &["diff", "--cached", "--numstat", "--diff-filter=A"],
+            CommandOutput {
+                exit_code: 0,
+                stdout: "1\t0\tsrc/main.rs\n".to_string(),
+                stderr: String::new(),
+            },
+        );
+
         // Set up expectations for git diff commands used by code review
         runner.expect(
             "git",
@@ -702,10 +913,44 @@ reminders:
             ..Default::default()
         };
 
-        let output = run_direct_db_access_check(&input);
+        let output = run_direct_db_access_check(&input, Path::new("/nonexistent"));
         assert!(output.is_block());
     }
 
+    #[test]
+    fn test_bash_direct_sqlite3_access_checks_each_command() {
+        let check = |command: &str| {
+            let input = HookInput {
+                tool_name: Some("Bash".to_string()),
+                tool_input: Some(ToolInput {
+                    command: Some(command.to_string()),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            };
+            run_direct_db_access_check(&input, Path::new("/nonexistent")).is_block()
+        };
+
+        assert!(check("cd .claude-reliability/ && sqlite3 working-memory.sqlite3"));
+        assert!(check("bash -c \"sqlite3 '.claude-reliability/working-memory.sqlite3'\""));
+        assert!(check("sqlite3 < .claude-reliability/dump.sql"));
+        assert!(!check("ls .claude-reliability/ && which sqlite3"));
+    }
+
+    #[test]
+    fn test_no_verify_check_blocks_hidden_flags() {
+        let input = HookInput {
+            tool_name: Some("Bash".to_string()),
+            tool_input: Some(ToolInput {
+                command: Some("cargo fmt && git commit $FLAGS -m 'test'".to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        assert!(run_no_verify_check(&input).is_block());
+    }
+
     #[test]
     fn test_bash_allowed_unrelated_sqlite3() {
         let input = HookInput {
@@ -717,7 +962,7 @@ reminders:
             ..Default::default()
         };
 
-        let output = run_direct_db_access_check(&input);
+        let output = run_direct_db_access_check(&input, Path::new("/nonexistent"));
         assert!(!output.is_block());
     }
 
@@ -732,7 +977,28 @@ reminders:
             ..Default::default()
         };
 
-        let output = run_direct_db_access_check(&input);
+        let output = run_direct_db_access_check(&input, Path::new("/nonexistent"));
         assert!(output.is_block());
     }
+
+    #[test]
+    fn test_bash_blocked_sqlite3_on_configured_db_path() {
+        let dir = TempDir::new().unwrap();
+        let config = crate::config::ProjectConfig {
+            db_path: Some("/var/lib/memory.db".to_string()),
+            ..Default::default()
+        };
+        config.save_to(dir.path()).unwrap();
+        let input = HookInput {
+            tool_name: Some("Bash".to_string()),
+            tool_input: Some(ToolInput {
+                command: Some("sqlite3 /var/lib/memory.db \"SELECT 1\"".to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        assert!(run_direct_db_access_check(&input, dir.path()).is_block());
+        assert!(!run_direct_db_access_check(&input, Path::new("/nonexistent")).is_block());
+    }
 }
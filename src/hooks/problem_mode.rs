@@ -2,13 +2,24 @@
 //!
 //! When a user enters problem mode (by saying "I have run into a problem"),
 //! all tool use is blocked until they stop and explain their problem to the user.
+//! The explanation is then recorded as a [`Problem`], linked to the work item
+//! in progress, so that recurring problems show up in
+//! `claude-reliability session problems` and in handoff summaries.
 
+use crate::emergency_stop::BlockerCategory;
 use crate::hooks::{HookInput, PreToolUseOutput};
 use crate::session;
+use crate::tasks::{self, Problem, TaskStore};
 use crate::templates;
 use std::path::Path;
 use tera::Context;
 
+/// Category recorded for problems that fit no [`BlockerCategory`].
+pub const OTHER_CATEGORY: &str = "other";
+
+/// The heading the problem mode message asks the agent to answer with what it needs.
+const SUGGESTED_ACTION_HEADING: &str = "what would help";
+
 /// Run the problem mode `PreToolUse` hook.
 ///
 /// This hook checks if problem mode is active and blocks all tool use if so.
@@ -39,11 +50,122 @@ pub fn run_problem_mode_hook(input: &HookInput, base_dir: &Path) -> PreToolUseOu
     PreToolUseOutput::block(Some(context))
 }
 
+/// Record the agent's explanation of a problem.
+///
+/// The problem is linked to a work item in progress, if there is one, which
+/// also gets a note about it. Returns `None` if the project has no task
+/// database or the problem cannot be recorded.
+#[must_use]
+pub fn record_problem(base_dir: &Path, explanation: &str) -> Option<Problem> {
+    let store = tasks::open_project_store(base_dir)?;
+    let category = BlockerCategory::detect(explanation).map_or(OTHER_CATEGORY, |c| c.name());
+    let suggested_action = suggested_action(explanation);
+    let task_id = store
+        .get_in_progress_tasks()
+        .ok()
+        .and_then(|tasks| tasks.into_iter().next())
+        .map(|task| task.id);
+    let session_id = session::current_session().unwrap_or_else(|| "unknown".to_string());
+
+    let problem = store
+        .record_problem(
+            category,
+            explanation,
+            suggested_action.as_deref(),
+            task_id.as_deref(),
+            &session_id,
+        )
+        .map_err(|e| tracing::warn!("Failed to record problem: {e}"))
+        .ok()?;
+    if let Some(task_id) = &problem.task_id {
+        let note = format!(
+            "Problem {} reported ({category}): {}",
+            problem.id,
+            problem.suggested_action.as_deref().unwrap_or("see `session problems`")
+        );
+        if let Err(e) = store.add_note(task_id, &note) {
+            tracing::warn!("Failed to add problem note: {e}");
+        }
+    }
+    Some(problem)
+}
+
+/// What the explanation asks the user to do, from its "What would help" part.
+fn suggested_action(explanation: &str) -> Option<String> {
+    let mut lines = explanation.lines();
+    while let Some(line) = lines.next() {
+        // ASCII lowercasing keeps byte offsets valid for `line`
+        let Some(start) = line.to_ascii_lowercase().find(SUGGESTED_ACTION_HEADING) else {
+            continue;
+        };
+        let rest = line[start + SUGGESTED_ACTION_HEADING.len()..]
+            .trim_start_matches(|c: char| c == '*' || c == ':' || c == '-' || c.is_whitespace())
+            .trim_end();
+        if !rest.is_empty() {
+            return Some(rest.to_string());
+        }
+        let following: Vec<&str> = lines
+            .by_ref()
+            .map(str::trim)
+            .skip_while(|l| l.is_empty())
+            .take_while(|l| !l.is_empty())
+            .collect();
+        return (!following.is_empty()).then(|| following.join(" "));
+    }
+    None
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use tempfile::TempDir;
 
+    #[test]
+    fn test_record_problem_links_work_in_progress() {
+        use crate::tasks::{Priority, SqliteTaskStore, TaskUpdate};
+
+        let dir = TempDir::new().unwrap();
+        assert!(record_problem(dir.path(), "No API key").is_none());
+
+        let store = SqliteTaskStore::for_project(dir.path()).unwrap();
+        let task = store.create_task("Deploy", "", Priority::High).unwrap();
+        store
+            .update_task(&task.id, TaskUpdate { in_progress: Some(true), ..Default::default() })
+            .unwrap();
+
+        let explanation = "The deploy needs an API key.\n\n**What would help:** Set DEPLOY_KEY.";
+        let problem = record_problem(dir.path(), explanation).unwrap();
+        assert_eq!(problem.category, "credentials");
+        assert_eq!(problem.suggested_action.as_deref(), Some("Set DEPLOY_KEY."));
+        assert_eq!(problem.task_id.as_deref(), Some(task.id.as_str()));
+        let notes = store.get_notes(&task.id).unwrap();
+        assert_eq!(
+            notes[0].content,
+            format!("Problem {} reported (credentials): Set DEPLOY_KEY.", problem.id)
+        );
+
+        store
+            .update_task(&task.id, TaskUpdate { in_progress: Some(false), ..Default::default() })
+            .unwrap();
+        let problem = record_problem(dir.path(), "Something odd happened").unwrap();
+        assert_eq!(problem.category, OTHER_CATEGORY);
+        assert_eq!(problem.task_id, None);
+    }
+
+    #[test]
+    fn test_suggested_action() {
+        assert_eq!(suggested_action("No heading here"), None);
+        assert_eq!(
+            suggested_action("3. **What would help** - A newer compiler"),
+            Some("A newer compiler".to_string())
+        );
+        assert_eq!(
+            suggested_action("## What would help\n\nAccess to the\nstaging server\n\nThanks"),
+            Some("Access to the staging server".to_string())
+        );
+        assert_eq!(suggested_action("What would help:\n"), None);
+    }
+
     #[test]
     fn test_problem_mode_not_active_allows_tools() {
         let dir = TempDir::new().unwrap();
@@ -1,15 +1,59 @@
 //! Config protection hook for preventing modifications to reliability config.
 //!
 //! This hook blocks Write, Edit, and delete operations targeting the
-//! reliability config file to prevent accidental modifications.
-
-use crate::hooks::{HookInput, PreToolUseOutput};
+//! reliability config file, the project database, and the hook entries in
+//! `.claude/settings.json`, unless a sub-agent has approved the change through
+//! `claude-reliability unlock-protected`. Every attempt is recorded in the
+//! audit log. The same goes for running `claude-reliability disable` or
+//! `enable`, which switch every hook off or on. It also blocks edits to the
+//! `protected_paths` of configured workspace packages.
+
+use crate::command;
+use crate::config::{self, ProjectConfig};
+use crate::hooks::{HookInput, PreToolUseOutput, ToolInput};
+use crate::paths;
+use crate::protected_change;
+use crate::session;
 use crate::templates;
+use std::path::{Component, Path, PathBuf};
 use tera::Context;
 
 /// The protected config file path (relative to project root).
 const PROTECTED_CONFIG: &str = ".claude/reliability-config.yaml";
 
+/// Claude Code settings files whose `hooks` entries are protected.
+const SETTINGS_FILES: [&str; 2] = [".claude/settings.json", ".claude/settings.local.json"];
+
+/// Files `SQLite` keeps next to the database.
+const DB_SUFFIXES: [&str; 4] = ["", "-wal", "-shm", "-journal"];
+
+/// A kind of file the agent may not change on its own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ProtectedFile {
+    /// The reliability config.
+    Config,
+    /// The project database.
+    Database,
+    /// A settings file holding the hook entries.
+    Settings,
+    /// The switch `disable` and `enable` flip in the project database.
+    Switch,
+}
+
+impl ProtectedFile {
+    /// A short description of the file.
+    const fn description(self) -> &'static str {
+        match self {
+            Self::Config => "the reliability config, which controls the reliability hooks",
+            Self::Database => "the project database, which holds tasks, questions and hook state",
+            Self::Settings => {
+                "the Claude Code settings, whose hook entries run the reliability hooks"
+            }
+            Self::Switch => "the switch that turns every reliability hook off or on",
+        }
+    }
+}
+
 /// Check if a path matches the protected config file.
 fn is_protected_path(path: &str) -> bool {
     // Normalize the path by removing leading ./ or /
@@ -36,54 +80,224 @@ fn is_config_delete_command(command: &str) -> bool {
     false
 }
 
+/// Find a `claude-reliability disable` or `enable` in a Bash command,
+/// returning it as written.
+fn find_hooks_switch(command_line: &str) -> Option<String> {
+    command::parse(command_line).into_iter().find_map(|command| {
+        command
+            .reliability_subcommand()
+            .filter(|subcommand| matches!(*subcommand, "disable" | "enable"))
+            .map(|subcommand| format!("claude-reliability {subcommand}"))
+    })
+}
+
+/// Make `path` absolute against `base_dir`, dropping `.` components.
+fn absolute_path(base_dir: &Path, path: &Path) -> PathBuf {
+    let base = if base_dir.is_absolute() {
+        base_dir.to_path_buf()
+    } else {
+        std::env::current_dir().map_or_else(|_| base_dir.to_path_buf(), |cwd| cwd.join(base_dir))
+    };
+    base.join(path).components().filter(|c| !matches!(c, Component::CurDir)).collect()
+}
+
+/// The protected files of a project, as absolute paths.
+fn protected_files(base_dir: &Path) -> Vec<(PathBuf, ProtectedFile)> {
+    let mut files =
+        vec![(absolute_path(base_dir, Path::new(PROTECTED_CONFIG)), ProtectedFile::Config)];
+    files.extend(
+        SETTINGS_FILES.iter().map(|settings| {
+            (absolute_path(base_dir, Path::new(settings)), ProtectedFile::Settings)
+        }),
+    );
+    let db_path = paths::project_db_path(base_dir).into_os_string();
+    files.extend(DB_SUFFIXES.iter().map(|suffix| {
+        let mut path = db_path.clone();
+        path.push(suffix);
+        (absolute_path(base_dir, Path::new(&path)), ProtectedFile::Database)
+    }));
+    files
+}
+
+/// Find the protected file `path` refers to.
+///
+/// With `include_dirs`, a directory inside the project containing a protected
+/// file counts as that file, since deleting or moving it removes the file.
+fn find_protected(base_dir: &Path, path: &str, include_dirs: bool) -> Option<ProtectedFile> {
+    if is_protected_path(path) {
+        return Some(ProtectedFile::Config);
+    }
+    let base = absolute_path(base_dir, Path::new(""));
+    let target = absolute_path(base_dir, Path::new(path));
+    protected_files(base_dir).into_iter().find_map(|(file, kind)| {
+        let contains = include_dirs
+            && target != base
+            && target.starts_with(&base)
+            && file.starts_with(&target);
+        (file == target || contains).then_some(kind)
+    })
+}
+
+/// The `hooks` entry of a settings file, if it parses.
+fn settings_hooks(content: &str) -> Option<serde_json::Value> {
+    serde_json::from_str::<serde_json::Value>(content).ok()?.get("hooks").cloned()
+}
+
+/// Check whether a Write or Edit changes the `hooks` entry of a settings file.
+fn changes_settings_hooks(base_dir: &Path, file_path: &str, tool_input: &ToolInput) -> bool {
+    let current =
+        std::fs::read_to_string(absolute_path(base_dir, Path::new(file_path))).unwrap_or_default();
+    let updated = match (&tool_input.content, &tool_input.old_string, &tool_input.new_string) {
+        (Some(content), _, _) => content.clone(),
+        (None, Some(old), Some(new)) if tool_input.replace_all => current.replace(old, new),
+        (None, Some(old), Some(new)) => current.replacen(old, new, 1),
+        _ => return false,
+    };
+    settings_hooks(&current) != settings_hooks(&updated)
+}
+
+/// The paths a Bash command may delete, move or overwrite.
+pub(super) fn bash_write_targets(command_line: &str) -> Vec<String> {
+    let mut targets = Vec::new();
+    for command in command::parse(command_line) {
+        let args: Vec<&str> = command
+            .args()
+            .iter()
+            .map(|word| word.text.as_str())
+            .filter(|arg| !arg.starts_with('-'))
+            .collect();
+        let in_place = command.args().iter().any(|word| {
+            word.text == "--in-place"
+                || (word.text.starts_with('-')
+                    && !word.text.starts_with("--")
+                    && word.text.contains('i'))
+        });
+        match command.program() {
+            Some("rm" | "unlink" | "shred" | "truncate" | "mv" | "tee") => {
+                targets.extend(args.iter().map(|arg| (*arg).to_string()));
+            }
+            Some("cp" | "install") => targets.extend(args.last().map(|arg| (*arg).to_string())),
+            Some("sed" | "perl") if in_place => {
+                targets.extend(args.iter().map(|arg| (*arg).to_string()));
+            }
+            _ => {}
+        }
+        if command_line.contains('>') {
+            targets.extend(command.redirects.iter().map(|word| word.text.clone()));
+        }
+    }
+    targets
+}
+
+/// Find the protected file a tool call would change, with its path.
+fn find_protected_change(input: &HookInput, base_dir: &Path) -> Option<(String, ProtectedFile)> {
+    let tool_input = input.tool_input.as_ref()?;
+    match input.tool_name.as_deref().unwrap_or("") {
+        "Write" | "Edit" => {
+            let file_path = tool_input.file_path.as_deref()?;
+            let kind = find_protected(base_dir, file_path, false)?;
+            if kind == ProtectedFile::Settings
+                && !changes_settings_hooks(base_dir, file_path, tool_input)
+            {
+                return None;
+            }
+            Some((file_path.to_string(), kind))
+        }
+        "Bash" => {
+            let command = tool_input.command.as_deref()?;
+            if let Some(switch) = find_hooks_switch(command) {
+                return Some((switch, ProtectedFile::Switch));
+            }
+            if is_config_delete_command(command) {
+                return Some((PROTECTED_CONFIG.to_string(), ProtectedFile::Config));
+            }
+            bash_write_targets(command).into_iter().find_map(|target| {
+                find_protected(base_dir, &target, true).map(|kind| (target, kind))
+            })
+        }
+        _ => None,
+    }
+}
+
 /// Run the config protection `PreToolUse` hook.
 ///
-/// This hook blocks Write, Edit, and delete operations on the reliability config.
+/// This hook blocks Write, Edit, and delete operations on the reliability
+/// config and database, changes to the hook entries of the settings files,
+/// and commands that switch the hooks off or on. A change approved through
+/// `unlock-protected` is allowed once.
 ///
 /// # Panics
 ///
 /// Panics if embedded templates fail to render. Templates are embedded via
 /// `include_str!` and verified by `test_all_embedded_templates_render`, so
 /// this should only occur if a template has a bug that escaped tests.
-pub fn run_protect_config_hook(input: &HookInput) -> PreToolUseOutput {
+pub fn run_protect_config_hook(input: &HookInput, base_dir: &Path) -> PreToolUseOutput {
+    let Some((path, kind)) = find_protected_change(input, base_dir) else {
+        return PreToolUseOutput::allow(None);
+    };
     let tool_name = input.tool_name.as_deref().unwrap_or("");
 
-    match tool_name {
-        "Write" | "Edit" => {
-            if let Some(ref tool_input) = input.tool_input {
-                if let Some(ref file_path) = tool_input.file_path {
-                    if is_protected_path(file_path) {
-                        let mut ctx = Context::new();
-                        ctx.insert("config_path", PROTECTED_CONFIG);
-
-                        let message = templates::render("messages/protect_config_write.tera", &ctx)
-                            .expect("protect_config_write.tera template should always render");
-
-                        return PreToolUseOutput::block(Some(message));
-                    }
-                }
-            }
-        }
-        "Bash" => {
-            if let Some(ref tool_input) = input.tool_input {
-                if let Some(ref command) = tool_input.command {
-                    if is_config_delete_command(command) {
-                        let mut ctx = Context::new();
-                        ctx.insert("config_path", PROTECTED_CONFIG);
-
-                        let message =
-                            templates::render("messages/protect_config_delete.tera", &ctx)
-                                .expect("protect_config_delete.tera template should always render");
-
-                        return PreToolUseOutput::block(Some(message));
-                    }
-                }
-            }
-        }
-        _ => {}
+    if let Some(justification) = session::take_protected_change_grant(base_dir) {
+        protected_change::record_attempt(
+            base_dir,
+            &format!("{tool_name} on {path} allowed: {justification}"),
+        );
+        return PreToolUseOutput::allow(None);
     }
+    protected_change::record_attempt(base_dir, &format!("{tool_name} on {path} blocked"));
+
+    let mut ctx = Context::new();
+    ctx.insert("config_path", &path);
+    ctx.insert("description", kind.description());
+
+    let message = if kind == ProtectedFile::Switch {
+        ctx.insert("command", &path);
+        templates::render("messages/protect_hooks_switch.tera", &ctx)
+            .expect("protect_hooks_switch.tera template should always render")
+    } else if tool_name == "Bash" {
+        templates::render("messages/protect_config_delete.tera", &ctx)
+            .expect("protect_config_delete.tera template should always render")
+    } else {
+        templates::render("messages/protect_config_write.tera", &ctx)
+            .expect("protect_config_write.tera template should always render")
+    };
+
+    PreToolUseOutput::block(Some(message))
+}
 
-    PreToolUseOutput::allow(None)
+/// Run the package protection `PreToolUse` hook.
+///
+/// Blocks Write and Edit operations on files matching the `protected_paths`
+/// of a package in the project config.
+///
+/// # Panics
+///
+/// Panics if the embedded template fails to render.
+pub fn run_protect_package_paths_hook(input: &HookInput, base_dir: &Path) -> PreToolUseOutput {
+    let Some(file_path) = input.tool_input.as_ref().and_then(|t| t.file_path.as_deref()) else {
+        return PreToolUseOutput::allow(None);
+    };
+    let Some(path) = config::project_relative_path(base_dir, file_path) else {
+        return PreToolUseOutput::allow(None);
+    };
+    let packages = ProjectConfig::load_from(base_dir)
+        .ok()
+        .flatten()
+        .map(|config| config.packages)
+        .unwrap_or_default();
+
+    let Some(package) = packages.iter().find(|package| package.is_protected(&path)) else {
+        return PreToolUseOutput::allow(None);
+    };
+
+    let mut ctx = Context::new();
+    ctx.insert("file_path", &path);
+    ctx.insert("package", &package.name);
+
+    let message = templates::render("messages/protect_package_path.tera", &ctx)
+        .expect("protect_package_path.tera template should always render");
+
+    PreToolUseOutput::block(Some(message))
 }
 
 #[cfg(test)]
@@ -134,6 +348,7 @@ mod tests {
 
     #[test]
     fn test_write_to_config_blocked() {
+        let dir = tempfile::TempDir::new().unwrap();
         let input = HookInput {
             tool_name: Some("Write".to_string()),
             tool_input: Some(ToolInput {
@@ -143,7 +358,7 @@ mod tests {
             ..Default::default()
         };
 
-        let output = run_protect_config_hook(&input);
+        let output = run_protect_config_hook(&input, dir.path());
         let json = serde_json::to_string(&output).unwrap();
         assert!(json.contains("block"));
         assert!(json.contains("Protected File"));
@@ -151,6 +366,7 @@ mod tests {
 
     #[test]
     fn test_edit_to_config_blocked() {
+        let dir = tempfile::TempDir::new().unwrap();
         let input = HookInput {
             tool_name: Some("Edit".to_string()),
             tool_input: Some(ToolInput {
@@ -160,13 +376,14 @@ mod tests {
             ..Default::default()
         };
 
-        let output = run_protect_config_hook(&input);
+        let output = run_protect_config_hook(&input, dir.path());
         let json = serde_json::to_string(&output).unwrap();
         assert!(json.contains("block"));
     }
 
     #[test]
     fn test_delete_config_blocked() {
+        let dir = tempfile::TempDir::new().unwrap();
         let input = HookInput {
             tool_name: Some("Bash".to_string()),
             tool_input: Some(ToolInput {
@@ -176,7 +393,7 @@ mod tests {
             ..Default::default()
         };
 
-        let output = run_protect_config_hook(&input);
+        let output = run_protect_config_hook(&input, dir.path());
         let json = serde_json::to_string(&output).unwrap();
         assert!(json.contains("block"));
         assert!(json.contains("Deletion Blocked"));
@@ -184,6 +401,7 @@ mod tests {
 
     #[test]
     fn test_write_to_other_file_allowed() {
+        let dir = tempfile::TempDir::new().unwrap();
         let input = HookInput {
             tool_name: Some("Write".to_string()),
             tool_input: Some(ToolInput {
@@ -193,13 +411,14 @@ mod tests {
             ..Default::default()
         };
 
-        let output = run_protect_config_hook(&input);
+        let output = run_protect_config_hook(&input, dir.path());
         let json = serde_json::to_string(&output).unwrap();
         assert!(json.contains("allow"));
     }
 
     #[test]
     fn test_bash_other_command_allowed() {
+        let dir = tempfile::TempDir::new().unwrap();
         let input = HookInput {
             tool_name: Some("Bash".to_string()),
             tool_input: Some(ToolInput {
@@ -209,13 +428,14 @@ mod tests {
             ..Default::default()
         };
 
-        let output = run_protect_config_hook(&input);
+        let output = run_protect_config_hook(&input, dir.path());
         let json = serde_json::to_string(&output).unwrap();
         assert!(json.contains("allow"));
     }
 
     #[test]
     fn test_read_config_allowed() {
+        let dir = tempfile::TempDir::new().unwrap();
         let input = HookInput {
             tool_name: Some("Read".to_string()),
             tool_input: Some(ToolInput {
@@ -225,17 +445,215 @@ mod tests {
             ..Default::default()
         };
 
-        let output = run_protect_config_hook(&input);
+        let output = run_protect_config_hook(&input, dir.path());
         let json = serde_json::to_string(&output).unwrap();
         assert!(json.contains("allow"));
     }
 
     #[test]
     fn test_no_tool_input_allowed() {
+        let dir = tempfile::TempDir::new().unwrap();
         let input = HookInput { tool_name: Some("Write".to_string()), ..Default::default() };
 
-        let output = run_protect_config_hook(&input);
+        let output = run_protect_config_hook(&input, dir.path());
         let json = serde_json::to_string(&output).unwrap();
         assert!(json.contains("allow"));
     }
+
+    fn bash(command: &str) -> HookInput {
+        HookInput {
+            tool_name: Some("Bash".to_string()),
+            tool_input: Some(ToolInput {
+                command: Some(command.to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn edit(file_path: &str, old_string: &str, new_string: &str) -> HookInput {
+        HookInput {
+            tool_name: Some("Edit".to_string()),
+            tool_input: Some(ToolInput {
+                file_path: Some(file_path.to_string()),
+                old_string: Some(old_string.to_string()),
+                new_string: Some(new_string.to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_database_changes_blocked() {
+        let dir = tempfile::TempDir::new().unwrap();
+        for command in [
+            "rm .claude-reliability/working-memory.sqlite3",
+            "rm -rf .claude-reliability",
+            "mv ./.claude-reliability/working-memory.sqlite3 /tmp/old.sqlite3",
+            "echo > .claude-reliability/working-memory.sqlite3-wal",
+            "cd src && rm -f ../other.txt; truncate -s 0 .claude-reliability/working-memory.sqlite3",
+        ] {
+            let output = run_protect_config_hook(&bash(command), dir.path());
+            assert!(output.is_block(), "command: {command}");
+            let reason = output.hook_specific_output.additional_context.unwrap();
+            assert!(reason.contains("project database"), "reason: {reason}");
+            assert!(reason.contains("unlock-protected"), "reason: {reason}");
+        }
+
+        for command in
+            ["rm -rf target", "rm -rf .", "cat .claude-reliability/working-memory.sqlite3"]
+        {
+            assert!(!run_protect_config_hook(&bash(command), dir.path()).is_block(), "{command}");
+        }
+    }
+
+    #[test]
+    fn test_bash_writes_to_config_blocked() {
+        let dir = tempfile::TempDir::new().unwrap();
+        for command in [
+            "sed -i 's/true/false/' .claude/reliability-config.yaml",
+            "cp /tmp/config.yaml .claude/reliability-config.yaml",
+            "echo 'hooks: {}' | tee .claude/settings.json",
+        ] {
+            assert!(run_protect_config_hook(&bash(command), dir.path()).is_block(), "{command}");
+        }
+        for command in [
+            "sed 's/true/false/' .claude/reliability-config.yaml",
+            "cp .claude/reliability-config.yaml /tmp/config.yaml",
+        ] {
+            assert!(!run_protect_config_hook(&bash(command), dir.path()).is_block(), "{command}");
+        }
+    }
+
+    #[test]
+    fn test_settings_hooks_protected() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join(".claude")).unwrap();
+        std::fs::write(
+            dir.path().join(".claude/settings.json"),
+            r#"{"permissions": {"allow": []}, "hooks": {"Stop": [{"command": "stop"}]}}"#,
+        )
+        .unwrap();
+
+        let output = run_protect_config_hook(
+            &edit(".claude/settings.json", r#"[{"command": "stop"}]"#, "[]"),
+            dir.path(),
+        );
+        assert!(output.is_block());
+        assert!(output.hook_specific_output.additional_context.unwrap().contains("hook entries"));
+
+        let output = run_protect_config_hook(
+            &edit(".claude/settings.json", r#""allow": []"#, r#""allow": ["Bash(ls)"]"#),
+            dir.path(),
+        );
+        assert!(!output.is_block());
+
+        let write = HookInput {
+            tool_name: Some("Write".to_string()),
+            tool_input: Some(ToolInput {
+                file_path: Some(".claude/settings.local.json".to_string()),
+                content: Some(r#"{"hooks": {}}"#.to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert!(run_protect_config_hook(&write, dir.path()).is_block());
+    }
+
+    #[test]
+    fn test_hooks_switch_blocked() {
+        let dir = tempfile::TempDir::new().unwrap();
+        for (command, switch) in [
+            ("claude-reliability disable", "claude-reliability disable"),
+            ("claude-reliability disable --for 2h", "claude-reliability disable"),
+            (".claude-reliability/bin/claude-reliability enable", "claude-reliability enable"),
+            ("cargo build && sh -c 'claude-reliability disable'", "claude-reliability disable"),
+        ] {
+            let output = run_protect_config_hook(&bash(command), dir.path());
+            assert!(output.is_block(), "command: {command}");
+            let reason = output.hook_specific_output.additional_context.unwrap();
+            assert!(reason.contains(&format!("`{switch}`")), "reason: {reason}");
+            assert!(reason.contains("unlock-protected"), "reason: {reason}");
+        }
+
+        for command in ["claude-reliability work list", "echo claude-reliability disable"] {
+            assert!(!run_protect_config_hook(&bash(command), dir.path()).is_block(), "{command}");
+        }
+
+        session::grant_protected_change(
+            dir.path(),
+            "The user asked to switch the hooks off",
+            chrono::Utc::now() + chrono::Duration::minutes(5),
+        )
+        .unwrap();
+        assert!(
+            !run_protect_config_hook(&bash("claude-reliability disable"), dir.path()).is_block()
+        );
+    }
+
+    #[test]
+    fn test_grant_allows_one_change_and_attempts_are_logged() {
+        use crate::tasks::TaskStore;
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let input = bash("rm .claude-reliability/working-memory.sqlite3");
+        session::grant_protected_change(
+            dir.path(),
+            "The user asked for a fresh database",
+            chrono::Utc::now() + chrono::Duration::minutes(5),
+        )
+        .unwrap();
+
+        assert!(!run_protect_config_hook(&input, dir.path()).is_block());
+        assert!(run_protect_config_hook(&input, dir.path()).is_block());
+
+        let store = crate::tasks::open_project_store(dir.path()).unwrap();
+        let entries = store.get_audit_log(None, None).unwrap();
+        let details: Vec<String> = entries
+            .iter()
+            .filter(|entry| entry.operation == protected_change::PROTECTED_CHANGE_OPERATION)
+            .filter_map(|entry| entry.details.clone())
+            .collect();
+        assert!(details.iter().any(|d| d.contains("allowed: The user asked for a fresh database")));
+        assert!(details.iter().any(|d| d.ends_with("blocked")));
+    }
+
+    #[test]
+    fn test_protected_package_path_blocked() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let config = ProjectConfig {
+            packages: vec![config::PackageConfig {
+                name: "api".to_string(),
+                path: "crates/api".to_string(),
+                protected_paths: vec!["migrations/*".to_string()],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        config.save_to(dir.path()).unwrap();
+
+        let write = |file_path: &str| HookInput {
+            tool_name: Some("Edit".to_string()),
+            tool_input: Some(ToolInput {
+                file_path: Some(file_path.to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let output =
+            run_protect_package_paths_hook(&write("crates/api/migrations/001.sql"), dir.path());
+        assert!(output.is_block());
+        let reason = output.hook_specific_output.additional_context.unwrap();
+        assert!(reason.contains("crates/api/migrations/001.sql"));
+        assert!(reason.contains("`api`"));
+
+        assert!(
+            !run_protect_package_paths_hook(&write("crates/api/src/lib.rs"), dir.path()).is_block()
+        );
+        assert!(
+            !run_protect_package_paths_hook(&write("migrations/001.sql"), dir.path()).is_block()
+        );
+    }
 }
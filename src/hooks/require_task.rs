@@ -1,38 +1,96 @@
 //! Hook to require a task be in progress before making code changes.
 //!
-//! This hook blocks Write and Edit operations when no task is marked as in-progress,
-//! encouraging the use of task tracking for all code modifications.
+//! This hook blocks changes to project files when no task is marked as
+//! in-progress, encouraging the use of task tracking for all code
+//! modifications. The `require_task` config sets which tools are covered and
+//! which files are exempt.
 
+use crate::config::{self, ProjectConfig, RequireTaskScope};
+use crate::error::ResultExt;
+use crate::hooks::protect_config::bash_write_targets;
 use crate::hooks::{HookInput, PreToolUseOutput};
 use crate::tasks::{SqliteTaskStore, TaskStore};
 use crate::templates;
 use std::path::Path;
 use tera::Context;
 
+/// Ready work items suggested in the block message.
+const SUGGESTED_ITEMS: usize = 3;
+
 /// Run the require task `PreToolUse` hook.
 ///
-/// Blocks Write and Edit operations when no task is in progress.
+/// Blocks changes to project files that are not exempt when no task is in
+/// progress: by Write and Edit, or with the `all` scope also by `NotebookEdit`
+/// and Bash commands that write files. If the task store cannot be read, the
+/// project's failure mode decides.
 ///
 /// # Panics
 ///
 /// Panics if embedded templates fail to render.
 pub fn run_require_task_hook(input: &HookInput, base_dir: &Path) -> PreToolUseOutput {
-    let tool_name = input.tool_name.as_deref().unwrap_or("");
-
-    // Only check Write and Edit tools
-    if tool_name != "Write" && tool_name != "Edit" {
+    let config = ProjectConfig::load_from(base_dir).ok().flatten().unwrap_or_default();
+    let policy = &config.require_task;
+    let Some(path) = changed_paths(input, policy.scope)
+        .iter()
+        .filter_map(|path| config::project_relative_path(base_dir, path))
+        .find(|path| !policy.is_exempt(path))
+    else {
         return PreToolUseOutput::allow(None);
-    }
+    };
 
-    // Open the task store - panic on database errors since something is seriously wrong
-    let store = SqliteTaskStore::for_project(base_dir).expect("task store should be accessible");
+    let checked = SqliteTaskStore::for_project(base_dir)
+        .and_then(|store| Ok((store.has_in_progress_task()?, store)))
+        .context("check for an in-progress task");
+    let store = match checked {
+        Ok((true, _)) => return PreToolUseOutput::allow(None),
+        Ok((false, store)) => store,
+        Err(e) => {
+            tracing::warn!("{e}");
+            return if config.failure_mode.allows() {
+                PreToolUseOutput::allow(None)
+            } else {
+                PreToolUseOutput::block(Some(format!("{e}\nBlocking, as failure_mode is closed.")))
+            };
+        }
+    };
 
-    if store.has_in_progress_task().expect("task store query should succeed") {
-        PreToolUseOutput::allow(None)
-    } else {
-        let message = templates::render("messages/require_task.tera", &Context::new())
-            .expect("require_task.tera template should always render");
-        PreToolUseOutput::block(Some(message))
+    let ready: Vec<String> = store
+        .get_ready_tasks()
+        .unwrap_or_default()
+        .into_iter()
+        .take(SUGGESTED_ITEMS)
+        .map(|task| format!("claude-reliability work on {}  # {}", task.id, task.title))
+        .collect();
+    let mut ctx = Context::new();
+    ctx.insert("file_path", &path);
+    ctx.insert("ready_items", &ready);
+    let message = templates::render("messages/require_task.tera", &ctx)
+        .expect("require_task.tera template should always render");
+    PreToolUseOutput::block(Some(message))
+}
+
+/// The files a tool call would change, if the scope covers it.
+fn changed_paths(input: &HookInput, scope: RequireTaskScope) -> Vec<String> {
+    let Some(tool_input) = input.tool_input.as_ref() else {
+        return Vec::new();
+    };
+    match (scope, input.tool_name.as_deref().unwrap_or("")) {
+        (RequireTaskScope::Edits | RequireTaskScope::All, "Write" | "Edit") => {
+            tool_input.file_path.iter().cloned().collect()
+        }
+        (RequireTaskScope::All, "NotebookEdit") => {
+            tool_input.notebook_path.iter().chain(&tool_input.file_path).cloned().collect()
+        }
+        (RequireTaskScope::All, "Bash") => tool_input
+            .command
+            .as_deref()
+            .map(bash_write_targets)
+            .unwrap_or_default()
+            .into_iter()
+            // File descriptor duplications such as `2>&1`
+            .filter(|target| !target.starts_with('&'))
+            .collect(),
+        _ => Vec::new(),
     }
 }
 
@@ -168,4 +226,104 @@ mod tests {
         // Should block - agent must create and work_on a task first
         assert!(json.contains("block"));
     }
+
+    fn tool_call(tool_name: &str, tool_input: ToolInput) -> HookInput {
+        HookInput {
+            tool_name: Some(tool_name.to_string()),
+            tool_input: Some(tool_input),
+            ..Default::default()
+        }
+    }
+
+    fn write(path: &str) -> HookInput {
+        tool_call("Write", ToolInput { file_path: Some(path.to_string()), ..Default::default() })
+    }
+
+    fn bash(command: &str) -> HookInput {
+        tool_call("Bash", ToolInput { command: Some(command.to_string()), ..Default::default() })
+    }
+
+    fn save_policy(base: &Path, scope: RequireTaskScope, exempt: &[&str]) {
+        let require_task = crate::config::RequireTaskPolicy {
+            scope,
+            exempt: exempt.iter().map(|p| (*p).to_string()).collect(),
+        };
+        ProjectConfig { require_task, ..Default::default() }.save_to(base).unwrap();
+    }
+
+    #[test]
+    fn test_block_message_suggests_ready_items() {
+        let dir = TempDir::new().unwrap();
+        let store = setup_test_store(dir.path());
+        let task = store.create_task("Fix the parser", "", Priority::High).unwrap();
+
+        let output = run_require_task_hook(&write("src/parser.rs"), dir.path());
+        let json = serde_json::to_string(&output).unwrap();
+        assert!(json.contains("src/parser.rs"), "{json}");
+        assert!(json.contains(&format!("claude-reliability work on {}", task.id)), "{json}");
+        assert!(json.contains("claude-reliability work create --title"), "{json}");
+    }
+
+    #[test]
+    fn test_exempt_and_outside_files_allowed() {
+        let dir = TempDir::new().unwrap();
+        let _store = setup_test_store(dir.path());
+        save_policy(dir.path(), RequireTaskScope::Edits, &["scratch/*", "*.md"]);
+
+        for path in ["scratch/try.py", "./docs/NOTES.md", "/tmp/elsewhere.txt"] {
+            let output = run_require_task_hook(&write(path), dir.path());
+            assert!(!output.is_block(), "{path} should be exempt");
+        }
+        assert!(run_require_task_hook(&write("src/main.rs"), dir.path()).is_block());
+        // Bash is only covered by the `all` scope
+        assert!(!run_require_task_hook(&bash("echo hi > src/main.rs"), dir.path()).is_block());
+    }
+
+    #[test]
+    fn test_scopes() {
+        let dir = TempDir::new().unwrap();
+        let _store = setup_test_store(dir.path());
+
+        save_policy(dir.path(), RequireTaskScope::Off, &[]);
+        assert!(!run_require_task_hook(&write("src/main.rs"), dir.path()).is_block());
+
+        save_policy(dir.path(), RequireTaskScope::All, &["scratch/*"]);
+        assert!(run_require_task_hook(&write("src/main.rs"), dir.path()).is_block());
+        assert!(run_require_task_hook(&bash("echo hi > src/main.rs 2>&1"), dir.path()).is_block());
+        assert!(run_require_task_hook(&bash("rm src/old.rs"), dir.path()).is_block());
+        assert!(!run_require_task_hook(&bash("cargo test 2>/dev/null"), dir.path()).is_block());
+        assert!(!run_require_task_hook(&bash("echo hi > scratch/out.txt"), dir.path()).is_block());
+        let notebook = tool_call(
+            "NotebookEdit",
+            ToolInput { notebook_path: Some("analysis.ipynb".to_string()), ..Default::default() },
+        );
+        assert!(run_require_task_hook(&notebook, dir.path()).is_block());
+    }
+
+    #[test]
+    fn test_allowed_when_task_store_unreadable() {
+        let dir = TempDir::new().unwrap();
+        // A directory where the database should be cannot be opened
+        std::fs::create_dir_all(paths::project_db_path(dir.path())).unwrap();
+
+        let input = HookInput {
+            tool_name: Some("Write".to_string()),
+            tool_input: Some(ToolInput {
+                file_path: Some("src/main.rs".to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let output = run_require_task_hook(&input, dir.path());
+        let json = serde_json::to_string(&output).unwrap();
+        assert!(json.contains("allow"));
+
+        ProjectConfig { failure_mode: crate::config::FailureMode::Closed, ..Default::default() }
+            .save_to(dir.path())
+            .unwrap();
+        let output = run_require_task_hook(&input, dir.path());
+        let json = serde_json::to_string(&output).unwrap();
+        assert!(json.contains("failure_mode is closed"), "{json}");
+    }
 }
@@ -0,0 +1,192 @@
+//! Sensitive file check for commits.
+//!
+//! Before a `git commit`, staged files whose names match the project's
+//! [`SensitiveFilePolicy`], such as `.env` files and private keys, block the
+//! commit unless they are on the policy's allowlist. Untracked files that a
+//! chained `git add` is about to stage are checked too.
+
+use crate::config::{ProjectConfig, SensitiveFilePolicy};
+use crate::git;
+use crate::hooks::{HookInput, PreToolUseOutput};
+use crate::templates;
+use crate::traits::CommandRunner;
+use std::path::Path;
+use tera::Context;
+
+/// Find staged files that the policy does not allow to be committed.
+///
+/// Files being deleted are left out, so that a committed secret can be removed.
+/// With `added` set to the pathspecs a `git add` is about to stage (see
+/// [`git::added_pathspecs`]), matching untracked files are checked too.
+///
+/// # Errors
+///
+/// Returns an error if a git command fails.
+pub fn find_sensitive_files(
+    runner: &dyn CommandRunner,
+    policy: &SensitiveFilePolicy,
+    added: Option<&[String]>,
+) -> crate::error::Result<Vec<String>> {
+    if policy.patterns.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut files = git::staged_files_not_deleted(runner)?;
+    if let Some(pathspecs) = added {
+        for path in git::untracked_files_matching(runner, pathspecs)? {
+            if !files.contains(&path) {
+                files.push(path);
+            }
+        }
+    }
+    Ok(files.into_iter().filter(|path| policy.is_sensitive(path)).collect())
+}
+
+/// Run the sensitive file check for a Bash tool call.
+///
+/// Returns an allow with no context when the command is not a commit or no
+/// sensitive file is staged.
+pub fn run_sensitive_files_check(
+    input: &HookInput,
+    base_dir: &Path,
+    runner: &dyn CommandRunner,
+) -> PreToolUseOutput {
+    let command = input.tool_input.as_ref().and_then(|ti| ti.command.as_deref()).unwrap_or("");
+    if !git::is_commit_command(command) {
+        return PreToolUseOutput::allow(None);
+    }
+
+    let policy = ProjectConfig::load_from(base_dir)
+        .ok()
+        .flatten()
+        .map(|config| config.sensitive_files)
+        .unwrap_or_default();
+    let added = git::added_pathspecs(command);
+    let files = match find_sensitive_files(runner, &policy, added.as_deref()) {
+        Ok(files) => files,
+        Err(e) => {
+            tracing::warn!("Failed to check staged files for sensitive names: {e}");
+            return PreToolUseOutput::allow(None);
+        }
+    };
+    if files.is_empty() {
+        return PreToolUseOutput::allow(None);
+    }
+
+    let mut ctx = Context::new();
+    ctx.insert("files", &files);
+    let message = templates::render("messages/sensitive_files.tera", &ctx)
+        .expect("sensitive_files.tera template should always render");
+    PreToolUseOutput::block(Some(message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hooks::ToolInput;
+    use crate::testing::MockCommandRunner;
+    use crate::traits::CommandOutput;
+    use tempfile::TempDir;
+
+    fn commit_input(command: &str) -> HookInput {
+        HookInput {
+            tool_name: Some("Bash".to_string()),
+            tool_input: Some(ToolInput {
+                command: Some(command.to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn staged_files_runner(paths: &[&str]) -> MockCommandRunner {
+        let mut runner = MockCommandRunner::new();
+        runner.expect(
+            "git",
+            &["diff", "--cached", "--name-only", "--diff-filter=d"],
+            CommandOutput { exit_code: 0, stdout: paths.join("\n"), stderr: String::new() },
+        );
+        runner
+    }
+
+    #[test]
+    fn test_blocks_sensitive_files() {
+        let dir = TempDir::new().unwrap();
+        let runner = staged_files_runner(&["src/main.rs", ".env", ".env.example", "keys/id_rsa"]);
+
+        let output =
+            run_sensitive_files_check(&commit_input("git commit -m x"), dir.path(), &runner);
+        assert!(output.is_block());
+        let message = output.hook_specific_output.additional_context.unwrap();
+        assert!(message.contains("  .env\n"), "message: {message}");
+        assert!(message.contains("  keys/id_rsa\n"), "message: {message}");
+        assert!(!message.contains(".env.example"), "message: {message}");
+        assert!(message.contains("git rm --cached"), "message: {message}");
+        assert!(message.contains("claude-reliability ensure-gitignore"), "message: {message}");
+    }
+
+    #[test]
+    fn test_allowlist_from_config() {
+        let dir = TempDir::new().unwrap();
+        let config = ProjectConfig {
+            sensitive_files: SensitiveFilePolicy {
+                allowlist: vec!["tests/fixtures/*.pem".to_string()],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        config.save_to(dir.path()).unwrap();
+        let runner = staged_files_runner(&["tests/fixtures/test.pem"]);
+
+        let output =
+            run_sensitive_files_check(&commit_input("git commit -m x"), dir.path(), &runner);
+        assert!(!output.is_block());
+    }
+
+    #[test]
+    fn test_blocks_sensitive_files_in_chained_add() {
+        let dir = TempDir::new().unwrap();
+        let untracked = |stdout: &str| CommandOutput {
+            exit_code: 0,
+            stdout: stdout.to_string(),
+            stderr: String::new(),
+        };
+        let mut runner = staged_files_runner(&[]);
+        runner.expect(
+            "git",
+            &["ls-files", "--others", "--exclude-standard", "--full-name", "--"],
+            untracked("src/lib.rs\n.env\n"),
+        );
+        let output = run_sensitive_files_check(
+            &commit_input("git add -A && git commit -m x"),
+            dir.path(),
+            &runner,
+        );
+        assert!(output.is_block());
+        let message = output.hook_specific_output.additional_context.unwrap();
+        assert!(message.contains("  .env\n"), "message: {message}");
+        assert!(!message.contains("src/lib.rs"), "message: {message}");
+
+        let mut runner = staged_files_runner(&[]);
+        runner.expect(
+            "git",
+            &["ls-files", "--others", "--exclude-standard", "--full-name", "--", "keys"],
+            untracked("keys/id_ed25519\n"),
+        );
+        let output = run_sensitive_files_check(
+            &commit_input("git add keys && git commit -m x"),
+            dir.path(),
+            &runner,
+        );
+        assert!(output.is_block());
+        runner.verify();
+    }
+
+    #[test]
+    fn test_ignores_other_commands() {
+        let dir = TempDir::new().unwrap();
+        let runner = MockCommandRunner::new();
+        let output = run_sensitive_files_check(&commit_input("git status"), dir.path(), &runner);
+        assert!(!output.is_block());
+        assert!(output.hook_specific_output.additional_context.is_none());
+    }
+}
@@ -0,0 +1,325 @@
+//! `SessionStart` hook for resuming where the previous session stopped.
+//!
+//! A fresh session (or one whose context was just compacted) knows nothing
+//! about the work in progress. This hook injects the latest handoff summary,
+//! the work items still in progress, the user messages sent before
+//! compaction, and why the previous session paused, as additional context.
+
+use crate::backup;
+use crate::handoff;
+use crate::hooks::watchdog::{self, Deadline};
+use crate::maintenance;
+use crate::recovery;
+use crate::session;
+use crate::tasks;
+use crate::templates;
+use crate::traits::{HookDecision, HookObserver, NoopObserver};
+use std::path::Path;
+use tera::Context;
+
+/// Source of a session start that follows context compaction.
+const COMPACT_SOURCE: &str = "compact";
+
+/// Input provided to `SessionStart` hooks by Claude Code.
+#[derive(Debug, Default, serde::Deserialize)]
+pub struct SessionStartInput {
+    /// How the session started: `startup`, `resume`, `clear` or `compact`.
+    #[serde(default)]
+    pub source: Option<String>,
+    /// Path to the conversation transcript.
+    #[serde(default, alias = "transcriptPath")]
+    pub transcript_path: Option<String>,
+}
+
+/// Output from the `SessionStart` hook.
+#[derive(Debug, Default, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionStartOutput {
+    /// Context to add to the session, if there is anything to resume.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hook_specific_output: Option<SessionStartContext>,
+}
+
+/// Hook-specific output carrying the additional context.
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionStartContext {
+    /// Always `SessionStart`.
+    pub hook_event_name: &'static str,
+    /// The context to inject.
+    pub additional_context: String,
+}
+
+/// Build the context needed to resume previous work.
+///
+/// User messages from the given session are included if they were sent
+/// before compaction, or all of them if `compacted` is set. If the previous
+/// session stopped for a `session pause`, its reason is included and the
+/// pause is forgotten. Returns `None` if there is nothing to resume.
+///
+/// # Panics
+///
+/// Panics if the task database exists but its user messages can't be read.
+#[must_use]
+pub fn resume_context(
+    base_dir: &Path,
+    session_id: Option<&str>,
+    compacted: bool,
+) -> Option<String> {
+    let handoff = handoff::latest_handoff(base_dir)
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .unwrap_or_default();
+    let in_progress: Vec<String> = tasks::get_in_progress_work(base_dir)
+        .into_iter()
+        .map(|(id, title)| format!("{id}: {title}"))
+        .collect();
+    let messages: Vec<String> = session_id
+        .map(|id| tasks::get_session_user_messages(base_dir, id))
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|m| compacted || m.pre_compaction)
+        .map(|m| m.message)
+        .collect();
+    let pause_reason = session::take_paused_session(base_dir).unwrap_or_default();
+
+    if handoff.trim().is_empty()
+        && in_progress.is_empty()
+        && messages.is_empty()
+        && pause_reason.is_empty()
+    {
+        return None;
+    }
+
+    let mut ctx = Context::new();
+    ctx.insert("handoff", handoff.trim());
+    ctx.insert("in_progress_work_items", &in_progress);
+    ctx.insert("pre_compaction_messages", &messages);
+    ctx.insert("pause_reason", &pause_reason);
+    let message = templates::render("messages/resume_context.tera", &ctx)
+        .expect("resume_context.tera template should always render");
+    Some(message.trim().to_string())
+}
+
+/// Run the session start hook.
+///
+/// Database maintenance and backups also run here when they are due, and
+/// the context warns about any recovery from a corrupted database.
+///
+/// # Arguments
+///
+/// * `input` - The hook input.
+/// * `base_dir` - Optional base directory (defaults to current directory).
+///
+/// # Panics
+///
+/// Panics if the task database exists but its user messages can't be read.
+#[must_use]
+pub fn run_session_start_hook(
+    input: &SessionStartInput,
+    base_dir: Option<&Path>,
+) -> SessionStartOutput {
+    let deadline = Deadline::for_project(base_dir.unwrap_or_else(|| Path::new(".")));
+    run_session_start_hook_with_observer(input, base_dir, &NoopObserver, deadline)
+}
+
+/// Run the session start hook, reporting each step and the decision to `observer`.
+///
+/// The hook never blocks, so the observer cannot veto anything. Once
+/// `deadline` is reached, maintenance and backups are skipped until the next
+/// session and the timeout is recorded in the audit log; the resume context
+/// is always given.
+///
+/// # Panics
+///
+/// Panics if the task database exists but its user messages can't be read.
+#[must_use]
+pub fn run_session_start_hook_with_observer(
+    input: &SessionStartInput,
+    base_dir: Option<&Path>,
+    observer: &dyn HookObserver,
+    deadline: Option<Deadline>,
+) -> SessionStartOutput {
+    let base = base_dir.unwrap_or_else(|| Path::new("."));
+    let mut last_step = None;
+    for step in ["maintenance", "backup"] {
+        if deadline.is_some_and(|deadline| deadline.is_reached()) {
+            let during = last_step.unwrap_or(step);
+            tracing::warn!("session_start hook ran out of time during {during}");
+            watchdog::record_timeout(base, "session_start", during);
+            break;
+        }
+        last_step = Some(step);
+        observer.on_check_start("session_start", step);
+        if step == "maintenance" {
+            maintenance::run_if_due(base);
+        } else {
+            backup::run_if_due(base);
+        }
+        observer.on_check_end("session_start", step, "done");
+    }
+
+    observer.on_check_start("session_start", "resume_context");
+    let compacted = input.source.as_deref() == Some(COMPACT_SOURCE);
+    let additional_context = recovery::with_recovery_warning(
+        base,
+        resume_context(base, input.transcript_path.as_deref(), compacted),
+    );
+    let outcome = if additional_context.is_some() { "context added" } else { "nothing to resume" };
+    observer.on_check_end("session_start", "resume_context", outcome);
+    observer.on_decision(&HookDecision { hook: "session_start", allow: true, decided_by: None });
+    SessionStartOutput {
+        hook_specific_output: additional_context.map(|additional_context| SessionStartContext {
+            hook_event_name: "SessionStart",
+            additional_context,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::paths;
+    use crate::tasks::{Priority, SqliteTaskStore, TaskStore, TaskUpdate};
+    use tempfile::TempDir;
+
+    fn store(dir: &TempDir) -> SqliteTaskStore {
+        let db_path = paths::project_db_path(dir.path());
+        std::fs::create_dir_all(db_path.parent().unwrap()).unwrap();
+        SqliteTaskStore::new(&db_path).unwrap()
+    }
+
+    #[test]
+    fn test_nothing_to_resume() {
+        let dir = TempDir::new().unwrap();
+        let output = run_session_start_hook(&SessionStartInput::default(), Some(dir.path()));
+        assert!(output.hook_specific_output.is_none());
+        assert_eq!(serde_json::to_string(&output).unwrap(), "{}");
+    }
+
+    #[test]
+    fn test_observer_sees_steps() {
+        use crate::testing::RecordingObserver;
+
+        let dir = TempDir::new().unwrap();
+        let observer = RecordingObserver::vetoing("ignored");
+        let output = run_session_start_hook_with_observer(
+            &SessionStartInput::default(),
+            Some(dir.path()),
+            &observer,
+            None,
+        );
+        assert!(output.hook_specific_output.is_none());
+        assert_eq!(
+            observer.events(),
+            vec![
+                "start session_start/maintenance",
+                "end session_start/maintenance: done",
+                "start session_start/backup",
+                "end session_start/backup: done",
+                "start session_start/resume_context",
+                "end session_start/resume_context: nothing to resume",
+                "decision session_start: allow",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_deadline_skips_maintenance_but_keeps_resume_context() {
+        use crate::testing::RecordingObserver;
+
+        let dir = TempDir::new().unwrap();
+        let store = store(&dir);
+        let observer = RecordingObserver::new();
+        let output = run_session_start_hook_with_observer(
+            &SessionStartInput::default(),
+            Some(dir.path()),
+            &observer,
+            Some(Deadline::after(std::time::Duration::ZERO)),
+        );
+        assert!(output.hook_specific_output.is_none());
+        assert_eq!(
+            observer.events(),
+            vec![
+                "start session_start/resume_context",
+                "end session_start/resume_context: nothing to resume",
+                "decision session_start: allow",
+            ]
+        );
+        let log = store.get_audit_log(None, None).unwrap();
+        assert_eq!(
+            log[0].details.as_deref(),
+            Some("session_start hook ran out of time during maintenance")
+        );
+    }
+
+    #[test]
+    fn test_resume_context_includes_handoff_and_in_progress_work() {
+        let dir = TempDir::new().unwrap();
+        let store = store(&dir);
+        let task = store.create_task("Add parser", "", Priority::High).unwrap();
+        store
+            .update_task(&task.id, TaskUpdate { in_progress: Some(true), ..Default::default() })
+            .unwrap();
+        let handoffs = paths::project_handoffs_dir(dir.path());
+        std::fs::create_dir_all(&handoffs).unwrap();
+        std::fs::write(handoffs.join("2026-01-01T00-00-00Z.md"), "# Session Handoff\n\nDone.\n")
+            .unwrap();
+
+        let context = resume_context(dir.path(), None, false).unwrap();
+        assert!(context.starts_with("# Resuming Previous Work"));
+        assert!(context.contains(&format!("- {}: Add parser", task.id)));
+        assert!(context.contains("# Session Handoff\n\nDone."));
+        assert!(!context.contains("Earlier User Messages"));
+    }
+
+    #[test]
+    fn test_resume_context_pre_compaction_messages() {
+        let dir = TempDir::new().unwrap();
+        let store = store(&dir);
+        store.record_user_message("Fix the login bug", "opening prompt", None, "s1").unwrap();
+
+        // Messages are not yet marked as pre-compaction
+        assert!(resume_context(dir.path(), Some("s1"), false).is_none());
+
+        // Starting after compaction, every earlier message counts
+        let context = resume_context(dir.path(), Some("s1"), true).unwrap();
+        assert!(context.contains("- Fix the login bug"));
+
+        store.mark_pre_compaction("s1").unwrap();
+        assert!(resume_context(dir.path(), Some("s1"), false).unwrap().contains("login bug"));
+    }
+
+    #[test]
+    fn test_resume_context_after_pause() {
+        let dir = TempDir::new().unwrap();
+        session::request_pause(dir.path(), "stepping away").unwrap();
+
+        // The session has not stopped for the pause yet
+        assert!(resume_context(dir.path(), None, false).is_none());
+
+        session::mark_pause_stopped(dir.path()).unwrap();
+        let context = resume_context(dir.path(), None, false).unwrap();
+        assert!(context.contains("## Paused Session"));
+        assert!(context.contains("stepping away"));
+
+        // The pause is only reported once
+        assert!(resume_context(dir.path(), None, false).is_none());
+    }
+
+    #[test]
+    fn test_session_start_output_format() {
+        let dir = TempDir::new().unwrap();
+        let store = store(&dir);
+        store.record_user_message("Fix the login bug", "opening prompt", None, "s1").unwrap();
+        let input: SessionStartInput =
+            serde_json::from_str(r#"{"source": "compact", "transcript_path": "s1"}"#).unwrap();
+
+        let output = run_session_start_hook(&input, Some(dir.path()));
+        let json: serde_json::Value = serde_json::to_value(&output).unwrap();
+        assert_eq!(json["hookSpecificOutput"]["hookEventName"], "SessionStart");
+        assert!(json["hookSpecificOutput"]["additionalContext"]
+            .as_str()
+            .unwrap()
+            .contains("Fix the login bug"));
+    }
+}
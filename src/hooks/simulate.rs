@@ -0,0 +1,395 @@
+//! Simulated stop hook runs (`simulate stop`).
+//!
+//! A scenario fabricates a transcript, a git repository and an empty task
+//! database in a scratch directory, then runs the stop hook against them, so
+//! the result shows which check fires for that situation under a given
+//! configuration. Git runs in the scratch directory and the sub-agent's
+//! answers are canned, so a simulation leaves the project alone and needs no
+//! network. The quality check is not run, and nothing is committed.
+
+use super::stop::{run_stop_hook, StopHookConfig, StopHookResult};
+use super::HookInput;
+use crate::command::RealCommandRunner;
+use crate::config::{EscalationPolicy, MessageVerbosity, VcsKind};
+use crate::error::{Error, Result};
+use crate::paths;
+use crate::replay::EnvGuard;
+use crate::tasks::AnswerSuggestion;
+use crate::traits::{
+    CommandOutput, CommandRunner, CommitPushRequest, CommitSplitContext, CreateQuestionContext,
+    CreateQuestionDecision, EmergencyStopContext, EmergencyStopDecision, HandoffContext,
+    PlannedCommit, PrDescription, PrDescriptionContext, ProtectedChangeContext,
+    ProtectedChangeDecision, QuestionContext, ReflectionContext, ReflectionDecision, RunOptions,
+    SubAgent, SubAgentDecision,
+};
+use chrono::{Duration, Utc};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// The file the fabricated session edits.
+const EDITED_FILE: &str = "README.md";
+
+/// A situation to run the stop hook against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopScenario {
+    /// The agent edited a file and did not commit it.
+    Uncommitted,
+    /// Every recent request to the API failed.
+    ApiError,
+    /// The agent asked the user a question after making changes.
+    Question,
+}
+
+impl StopScenario {
+    /// Every scenario, in the order they are listed.
+    pub const ALL: [Self; 3] = [Self::Uncommitted, Self::ApiError, Self::Question];
+
+    /// The scenario's name on the command line.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Uncommitted => "uncommitted",
+            Self::ApiError => "api-error",
+            Self::Question => "question",
+        }
+    }
+
+    /// What the scenario fabricates.
+    #[must_use]
+    pub const fn description(self) -> &'static str {
+        match self {
+            Self::Uncommitted => "the agent edited README.md and left it uncommitted",
+            Self::ApiError => "the last API requests all failed with server errors",
+            Self::Question => "the agent edited README.md, then asked the user which option to use",
+        }
+    }
+}
+
+impl FromStr for StopScenario {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        Self::ALL.into_iter().find(|s| s.name() == name.to_lowercase()).ok_or_else(|| {
+            let names: Vec<&str> = Self::ALL.iter().map(|s| s.name()).collect();
+            Error::Config(format!(
+                "invalid scenario '{name}' (must be one of: {})",
+                names.join(", ")
+            ))
+        })
+    }
+}
+
+/// Run the stop hook against a scenario with the default configuration.
+///
+/// # Errors
+///
+/// Returns an error if the scenario cannot be set up (for example, if git is
+/// not installed) or the stop hook fails.
+pub fn simulate_stop(scenario: StopScenario) -> Result<StopHookResult> {
+    simulate_stop_with_config(scenario, &StopHookConfig::default())
+}
+
+/// Run the stop hook against a scenario with the given configuration.
+///
+/// The configuration's base directory, quality check, automatic WIP commits,
+/// single work item and escalation are replaced, so the simulation cannot
+/// act outside its scratch directory.
+///
+/// # Errors
+///
+/// Returns an error if the scenario cannot be set up (for example, if git is
+/// not installed) or the stop hook fails.
+pub fn simulate_stop_with_config(
+    scenario: StopScenario,
+    config: &StopHookConfig,
+) -> Result<StopHookResult> {
+    let scratch = Scratch::create("simulate")?;
+    let dir = scratch.0.as_path();
+    // The project's data and database must not be used
+    let _env = EnvGuard::remove(&[paths::DATA_DIR_ENV, paths::DB_PATH_ENV]);
+    let runner = InDirRunner { dir };
+    init_repository(&runner, dir, scenario)?;
+
+    let transcript_path = dir.join("transcript.jsonl");
+    std::fs::write(&transcript_path, transcript(scenario, config))?;
+
+    let config = StopHookConfig {
+        git_repo: true,
+        vcs: VcsKind::Git,
+        base_dir: Some(dir.to_path_buf()),
+        quality_check_enabled: false,
+        quality_check_command: None,
+        packages: Vec::new(),
+        auto_wip_commit: false,
+        auto_push: false,
+        single_work_item_id: None,
+        escalation: EscalationPolicy::default(),
+        deadline: None,
+        message_verbosity: MessageVerbosity::Verbose,
+        ..config.clone()
+    };
+    let input = HookInput {
+        transcript_path: Some(transcript_path.to_string_lossy().to_string()),
+        ..Default::default()
+    };
+    run_stop_hook(&input, &config, &runner, &CannedSubAgent)
+}
+
+/// Create a repository with one commit, and the scenario's uncommitted edit.
+fn init_repository(runner: &dyn CommandRunner, dir: &Path, scenario: StopScenario) -> Result<()> {
+    std::fs::write(dir.join(EDITED_FILE), "# Example\n")?;
+    let commands: [&[&str]; 3] = [
+        &["init", "-q"],
+        &["add", EDITED_FILE],
+        &[
+            "-c",
+            "user.name=Simulation",
+            "-c",
+            "user.email=simulation@example.com",
+            "-c",
+            "commit.gpgsign=false",
+            "commit",
+            "-q",
+            "-m",
+            "Initial commit",
+        ],
+    ];
+    for args in commands {
+        let output = runner.run("git", args, None)?;
+        if !output.success() {
+            return Err(Error::Config(format!(
+                "git {} failed in the simulation: {}",
+                args.join(" "),
+                output.combined_output().trim()
+            )));
+        }
+    }
+    if scenario == StopScenario::Uncommitted {
+        std::fs::write(dir.join(EDITED_FILE), "# Example\n\nWith usage notes.\n")?;
+    }
+    Ok(())
+}
+
+/// The scenario's transcript, as JSON lines.
+fn transcript(scenario: StopScenario, config: &StopHookConfig) -> String {
+    let at = |minutes_ago: i64| (Utc::now() - Duration::minutes(minutes_ago)).to_rfc3339();
+    let user = |text: &str| {
+        serde_json::json!({
+            "type": "user",
+            "timestamp": at(2),
+            "message": {"role": "user", "content": text},
+        })
+    };
+    let assistant = |content: serde_json::Value| {
+        serde_json::json!({
+            "type": "assistant",
+            "timestamp": at(1),
+            "message": {"role": "assistant", "content": [content]},
+        })
+    };
+    let edit = assistant(serde_json::json!({
+        "type": "tool_use",
+        "id": "simulated",
+        "name": "Write",
+        "input": {"file_path": EDITED_FILE},
+    }));
+
+    let entries = match scenario {
+        StopScenario::Uncommitted => vec![
+            user("Add usage notes to the README"),
+            edit,
+            assistant(serde_json::json!({"type": "text", "text": "I've added usage notes."})),
+        ],
+        StopScenario::ApiError => {
+            let mut entries = vec![user("Add usage notes to the README")];
+            let error = serde_json::json!({
+                "type": "assistant",
+                "timestamp": at(1),
+                "isApiErrorMessage": true,
+                "message": {"content": [{"type": "text", "text": "API Error: 500 Internal server error"}]},
+            });
+            let errors = config.api_error_threshold();
+            entries.extend((0..errors).map(|_| error.clone()));
+            entries
+        }
+        StopScenario::Question => vec![
+            user("Document how to configure the database"),
+            edit,
+            assistant(serde_json::json!({
+                "type": "text",
+                "text": "Which option should the README recommend, Postgres or SQLite? Let me know.",
+            })),
+        ],
+    };
+    let lines: Vec<String> = entries.iter().map(ToString::to_string).collect();
+    lines.join("\n") + "\n"
+}
+
+/// A scratch directory, deleted when dropped.
+pub(super) struct Scratch(pub(super) PathBuf);
+
+impl Scratch {
+    /// Create a scratch directory, named for what it is used for.
+    pub(super) fn create(purpose: &str) -> Result<Self> {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.subsec_nanos());
+        let dir = std::env::temp_dir()
+            .join(format!("claude-reliability-{purpose}-{}-{nanos}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self(dir))
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Runs commands in the scratch directory rather than the current one.
+struct InDirRunner<'a> {
+    dir: &'a Path,
+}
+
+impl CommandRunner for InDirRunner<'_> {
+    fn run(
+        &self,
+        program: &str,
+        args: &[&str],
+        timeout: Option<std::time::Duration>,
+    ) -> Result<CommandOutput> {
+        RealCommandRunner.run_in_dir(program, args, timeout, self.dir)
+    }
+
+    fn run_in_dir(
+        &self,
+        program: &str,
+        args: &[&str],
+        timeout: Option<std::time::Duration>,
+        cwd: &Path,
+    ) -> Result<CommandOutput> {
+        RealCommandRunner.run_in_dir(program, args, timeout, &self.dir.join(cwd))
+    }
+
+    fn run_with_options(
+        &self,
+        program: &str,
+        args: &[&str],
+        options: &RunOptions,
+    ) -> Result<CommandOutput> {
+        let cwd = self.dir.join(options.cwd.as_deref().unwrap_or_else(|| Path::new(".")));
+        RealCommandRunner.run_with_options(program, args, &options.clone().cwd(cwd))
+    }
+
+    fn is_available(&self, program: &str) -> bool {
+        RealCommandRunner.is_available(program)
+    }
+}
+
+/// A sub-agent that gives the same answer every time, without a network.
+///
+/// Questions are left for the user, reviews approve, and reflections find the
+/// work complete.
+pub(super) struct CannedSubAgent;
+
+impl SubAgent for CannedSubAgent {
+    fn decide_on_question(&self, _: &QuestionContext) -> Result<SubAgentDecision> {
+        Ok(SubAgentDecision::AllowStop(Some(
+            "(simulated) The question needs the user's answer.".to_string(),
+        )))
+    }
+
+    fn review_code(&self, _: &str, _: &[String], _: Option<&str>) -> Result<(bool, String)> {
+        Ok((true, "(simulated) Approved.".to_string()))
+    }
+
+    fn evaluate_emergency_stop(&self, _: &EmergencyStopContext) -> Result<EmergencyStopDecision> {
+        Ok(EmergencyStopDecision::Accept(None))
+    }
+
+    fn evaluate_create_question(
+        &self,
+        _: &CreateQuestionContext,
+    ) -> Result<CreateQuestionDecision> {
+        Ok(CreateQuestionDecision::Create)
+    }
+
+    fn evaluate_reflection(&self, _: &ReflectionContext) -> Result<ReflectionDecision> {
+        Ok(ReflectionDecision::Complete)
+    }
+
+    fn summarize_wip_commit(&self, _: &str) -> Result<String> {
+        Ok("Work in progress".to_string())
+    }
+
+    fn write_pr_description(&self, _: &PrDescriptionContext) -> Result<PrDescription> {
+        Ok(PrDescription { title: "Simulated change".to_string(), body: String::new() })
+    }
+
+    fn write_handoff(&self, _: &HandoffContext) -> Result<String> {
+        Ok("# Session Handoff\n".to_string())
+    }
+
+    fn narrate_report(&self, report: &str) -> Result<String> {
+        Ok(report.to_string())
+    }
+
+    fn plan_commit_split(&self, _: &CommitSplitContext) -> Result<Vec<PlannedCommit>> {
+        Ok(Vec::new())
+    }
+
+    fn classify_commit_push_question(&self, _: &str) -> Result<Option<CommitPushRequest>> {
+        Ok(None)
+    }
+
+    fn suggest_answers(&self, _: &str) -> Result<Vec<AnswerSuggestion>> {
+        Ok(Vec::new())
+    }
+
+    fn evaluate_protected_change(
+        &self,
+        _: &ProtectedChangeContext,
+    ) -> Result<ProtectedChangeDecision> {
+        Ok(ProtectedChangeDecision::Reject("(simulated) Not in a simulation.".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hooks::StopReason;
+
+    #[test]
+    fn test_parse_scenario() {
+        assert_eq!("api-error".parse::<StopScenario>().unwrap(), StopScenario::ApiError);
+        assert_eq!("Question".parse::<StopScenario>().unwrap(), StopScenario::Question);
+        let err = "nope".parse::<StopScenario>().unwrap_err().to_string();
+        assert!(err.contains("uncommitted, api-error, question"), "{err}");
+    }
+
+    #[test]
+    fn test_simulate_uncommitted() {
+        let result = simulate_stop(StopScenario::Uncommitted).unwrap();
+        assert!(!result.allow_stop);
+        assert_eq!(result.decided_by.as_deref(), Some("uncommitted_changes"));
+        assert_eq!(result.reason, Some(StopReason::UncommittedChanges));
+    }
+
+    #[test]
+    fn test_simulate_api_error() {
+        let config = StopHookConfig { api_error_threshold: Some(5), ..Default::default() };
+        let result = simulate_stop_with_config(StopScenario::ApiError, &config).unwrap();
+        assert!(result.allow_stop);
+        assert_eq!(result.decided_by.as_deref(), Some("api_error_loop"));
+        assert!(result.messages.iter().any(|m| m.contains('5')));
+    }
+
+    #[test]
+    fn test_simulate_question() {
+        let result = simulate_stop(StopScenario::Question).unwrap();
+        assert!(result.allow_stop);
+        assert_eq!(result.decided_by.as_deref(), Some("interactive_question"));
+        assert!(result.checks_log.iter().any(|line| line.contains("commit_push_auto_confirm")));
+    }
+}
@@ -0,0 +1,408 @@
+//! Spelling and terminology check for commits.
+//!
+//! Before a `git commit`, the lines added in the staged diff are scanned for
+//! common misspellings and the project's banned terms, along with the
+//! changes and untracked files that a `git add` in the same command line
+//! will stage. Only prose is checked:
+//! Markdown and text files, doc comments, and string literals in source
+//! files. Generated files, such as lockfiles, are left out. Findings are
+//! reported as warnings and never block the commit.
+
+use crate::config::ProjectConfig;
+use crate::git;
+use crate::hooks::code_review::is_source_code_file;
+use crate::hooks::{HookInput, PreToolUseOutput};
+use crate::templates;
+use crate::traits::CommandRunner;
+use std::collections::BTreeMap;
+use std::path::Path;
+use tera::Context;
+
+/// Common misspellings and their corrections.
+const MISSPELLINGS: &[(&str, &str)] = &[
+    ("accomodate", "accommodate"),
+    ("acheive", "achieve"),
+    ("adress", "address"),
+    ("agressive", "aggressive"),
+    ("apparant", "apparent"),
+    ("arguement", "argument"),
+    ("begining", "beginning"),
+    ("beleive", "believe"),
+    ("calender", "calendar"),
+    ("commited", "committed"),
+    ("comming", "coming"),
+    ("definately", "definitely"),
+    ("dependancy", "dependency"),
+    ("enviroment", "environment"),
+    ("existant", "existent"),
+    ("explicitely", "explicitly"),
+    ("familar", "familiar"),
+    ("guarentee", "guarantee"),
+    ("independant", "independent"),
+    ("lenght", "length"),
+    ("neccessary", "necessary"),
+    ("occured", "occurred"),
+    ("occurence", "occurrence"),
+    ("paramter", "parameter"),
+    ("persistant", "persistent"),
+    ("posible", "possible"),
+    ("recieve", "receive"),
+    ("recomend", "recommend"),
+    ("refered", "referred"),
+    ("seperate", "separate"),
+    ("succesful", "successful"),
+    ("sucess", "success"),
+    ("teh", "the"),
+    ("tommorow", "tomorrow"),
+    ("truely", "truly"),
+    ("untill", "until"),
+    ("wich", "which"),
+    ("writting", "writing"),
+];
+
+/// Extensions of files whose every line is prose.
+const PROSE_EXTENSIONS: &[&str] = &["md", "markdown", "mdx", "rst", "txt", "adoc"];
+
+/// Prefixes of doc comment lines in source files.
+const DOC_COMMENT_PREFIXES: &[&str] = &["///", "//!", "/**", "* "];
+
+/// A misspelling or banned term in an added line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpellingWarning {
+    /// Path of the file, relative to the repository root.
+    pub path: String,
+    /// Line number in the new version of the file.
+    pub line: usize,
+    /// The text found, as written.
+    pub found: String,
+    /// The preferred text, or empty for a banned term with no replacement.
+    pub suggestion: String,
+}
+
+impl std::fmt::Display for SpellingWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.suggestion.is_empty() {
+            write!(f, "{}:{}: \"{}\" should not be used", self.path, self.line, self.found)
+        } else {
+            write!(
+                f,
+                "{}:{}: \"{}\" should be \"{}\"",
+                self.path, self.line, self.found, self.suggestion
+            )
+        }
+    }
+}
+
+/// Check the lines added in a unified diff.
+///
+/// `terminology` maps banned terms to their replacements and is checked
+/// together with the built-in misspellings, taking precedence over them.
+pub fn check_diff(diff: &str, terminology: &BTreeMap<String, String>) -> Vec<SpellingWarning> {
+    let mut terms: BTreeMap<String, &str> =
+        MISSPELLINGS.iter().map(|(wrong, right)| ((*wrong).to_string(), *right)).collect();
+    for (term, replacement) in terminology {
+        terms.insert(term.to_ascii_lowercase(), replacement);
+    }
+
+    let mut warnings = Vec::new();
+    let mut path: Option<&str> = None;
+    let mut line_number = 0;
+    // File headers only come before a file's first hunk; after that, a
+    // `+++ ` line is an added line starting with `++`
+    let mut in_header = true;
+    for line in diff.lines() {
+        if line.starts_with("diff ") {
+            in_header = true;
+        } else if let Some(new_path) = line.strip_prefix("+++ ").filter(|_| in_header) {
+            path = new_path.strip_prefix("b/");
+        } else if let Some(hunk) = line.strip_prefix("@@ ") {
+            in_header = false;
+            line_number = hunk_start(hunk).unwrap_or(0);
+        } else if let Some(added) = line.strip_prefix('+').filter(|_| !in_header) {
+            if let Some(path) = path {
+                for segment in prose_segments(path, added) {
+                    for (found, replacement) in find_terms(segment, &terms) {
+                        warnings.push(SpellingWarning {
+                            path: path.to_string(),
+                            line: line_number,
+                            found: found.to_string(),
+                            suggestion: replacement.to_string(),
+                        });
+                    }
+                }
+            }
+            line_number += 1;
+        } else if line.starts_with(' ') && !in_header {
+            line_number += 1;
+        }
+    }
+    warnings
+}
+
+/// Parse the first new-file line number from a hunk header such as
+/// `-10,2 +12,3 @@`.
+fn hunk_start(hunk: &str) -> Option<usize> {
+    let new_range = hunk.split_whitespace().find_map(|part| part.strip_prefix('+'))?;
+    new_range.split(',').next()?.parse().ok()
+}
+
+/// The parts of an added line that are prose, given the file it is in.
+fn prose_segments<'a>(path: &str, line: &'a str) -> Vec<&'a str> {
+    let extension = Path::new(path).extension().map(|e| e.to_string_lossy().to_ascii_lowercase());
+    if extension.is_some_and(|extension| PROSE_EXTENSIONS.contains(&extension.as_str())) {
+        return vec![line];
+    }
+    if !is_source_code_file(path) {
+        return Vec::new();
+    }
+    let trimmed = line.trim_start();
+    if DOC_COMMENT_PREFIXES.iter().any(|prefix| trimmed.starts_with(prefix)) {
+        return vec![trimmed];
+    }
+    string_literals(line)
+}
+
+/// The contents of the double-quoted string literals on a line.
+fn string_literals(line: &str) -> Vec<&str> {
+    let mut literals = Vec::new();
+    let mut start = None;
+    let mut escaped = false;
+    for (index, c) in line.char_indices() {
+        match (start, c) {
+            (Some(_), _) if escaped => escaped = false,
+            (Some(_), '\\') => escaped = true,
+            (Some(open), '"') => {
+                literals.push(&line[open..index]);
+                start = None;
+            }
+            (None, '"') => start = Some(index + 1),
+            _ => {}
+        }
+    }
+    literals
+}
+
+/// Find whole-word, case-insensitive occurrences of the terms in some text.
+///
+/// Each term is reported at most once.
+fn find_terms<'a, 'b>(text: &'a str, terms: &BTreeMap<String, &'b str>) -> Vec<(&'a str, &'b str)> {
+    let lowercase = text.to_ascii_lowercase();
+    let is_word_char = |c: char| c.is_alphanumeric() || c == '_';
+    let mut found = Vec::new();
+    for (term, replacement) in terms {
+        let occurrence = lowercase.match_indices(term.as_str()).find(|(start, _)| {
+            let end = start + term.len();
+            !lowercase[..*start].chars().next_back().is_some_and(is_word_char)
+                && !lowercase[end..].chars().next().is_some_and(is_word_char)
+        });
+        if let Some((start, _)) = occurrence {
+            found.push((&text[start..start + term.len()], *replacement));
+        }
+    }
+    found
+}
+
+/// Run the spelling check for a Bash tool call.
+///
+/// Returns an allow, with the warnings as context if any were found.
+pub fn run_spelling_check(
+    input: &HookInput,
+    base_dir: &Path,
+    runner: &dyn CommandRunner,
+) -> PreToolUseOutput {
+    let command = input.tool_input.as_ref().and_then(|ti| ti.command.as_deref()).unwrap_or("");
+    if !git::is_commit_command(command) {
+        return PreToolUseOutput::allow(None);
+    }
+
+    let config = ProjectConfig::load_from(base_dir).ok().flatten().unwrap_or_default();
+    let policy = config.spelling;
+    if !policy.enabled {
+        return PreToolUseOutput::allow(None);
+    }
+
+    let diff = match git::commit_diff(runner, base_dir, command) {
+        Ok(diff) => diff,
+        Err(e) => {
+            tracing::warn!("Failed to get diff for spelling check: {e}");
+            return PreToolUseOutput::allow(None);
+        }
+    };
+    let mut warnings = check_diff(&diff, &policy.terminology);
+    warnings.retain(|warning| !config.generated_files.is_generated(base_dir, &warning.path));
+    if warnings.is_empty() {
+        return PreToolUseOutput::allow(None);
+    }
+
+    let warnings: Vec<String> = warnings.iter().map(ToString::to_string).collect();
+    let mut ctx = Context::new();
+    ctx.insert("warnings", &warnings);
+    let message = templates::render("messages/spelling_warnings.tera", &ctx)
+        .expect("spelling_warnings.tera template should always render");
+    PreToolUseOutput::allow(Some(message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SpellingPolicy;
+    use crate::hooks::ToolInput;
+    use crate::testing::MockCommandRunner;
+    use crate::traits::CommandOutput;
+    use tempfile::TempDir;
+
+    const DIFF: &str = "\
+diff --git a/README.md b/README.md
+--- a/README.md
++++ b/README.md
+@@ -3,0 +4,2 @@
++Teh installer will recieve the config.
++Add the host to the Whitelist.
+diff --git a/src/lib.rs b/src/lib.rs
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -10 +10,3 @@
+-/// Old text
++/// The paramter is optional.
++let wich = parse(\"failed to recieve \\\"data\\\"\");
++let teh_value = 1; // teh
+diff --git a/config.yaml b/config.yaml
+--- a/config.yaml
++++ b/config.yaml
+@@ -1 +1 @@
++name: recieve
+";
+
+    fn terminology() -> BTreeMap<String, String> {
+        BTreeMap::from([
+            ("whitelist".to_string(), "allowlist".to_string()),
+            ("recieve".to_string(), String::new()),
+        ])
+    }
+
+    #[test]
+    fn test_check_diff_finds_terms_in_prose() {
+        let warnings: Vec<String> =
+            check_diff(DIFF, &terminology()).iter().map(ToString::to_string).collect();
+        assert_eq!(
+            warnings,
+            vec![
+                "README.md:4: \"recieve\" should not be used",
+                "README.md:4: \"Teh\" should be \"the\"",
+                "README.md:5: \"Whitelist\" should be \"allowlist\"",
+                "src/lib.rs:10: \"paramter\" should be \"parameter\"",
+                "src/lib.rs:11: \"recieve\" should not be used",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_diff_reads_plus_lines_in_hunks_as_added() {
+        let diff = "\
+diff --git a/NOTES.md b/NOTES.md
+--- a/NOTES.md
++++ b/NOTES.md
+@@ -1,0 +2,2 @@
++++ Teh plus signs
++--- seperate
+";
+        let warnings: Vec<String> =
+            check_diff(diff, &BTreeMap::new()).iter().map(ToString::to_string).collect();
+        assert_eq!(
+            warnings,
+            vec![
+                "NOTES.md:2: \"Teh\" should be \"the\"",
+                "NOTES.md:3: \"seperate\" should be \"separate\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_string_literals() {
+        assert_eq!(string_literals(r#"f("a", "b \"c\"") + x"#), vec!["a", r#"b \"c\""#]);
+        assert!(string_literals("no strings").is_empty());
+        assert_eq!(hunk_start("-1,2 +30,4 @@ fn main()"), Some(30));
+    }
+
+    fn commit_input(command: &str) -> HookInput {
+        HookInput {
+            tool_name: Some("Bash".to_string()),
+            tool_input: Some(ToolInput {
+                command: Some(command.to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_run_spelling_check_warns_when_enabled() {
+        let dir = TempDir::new().unwrap();
+        let config = ProjectConfig {
+            spelling: SpellingPolicy { enabled: true, terminology: terminology() },
+            ..Default::default()
+        };
+        config.save_to(dir.path()).unwrap();
+        let mut runner = MockCommandRunner::new();
+        runner.expect(
+            "git",
+            &["diff", "--cached", "-U0"],
+            CommandOutput { exit_code: 0, stdout: DIFF.to_string(), stderr: String::new() },
+        );
+
+        let output = run_spelling_check(&commit_input("git commit -m docs"), dir.path(), &runner);
+        assert!(!output.is_block());
+        let message = output.hook_specific_output.additional_context.unwrap();
+        assert!(message.contains("README.md:5: \"Whitelist\" should be \"allowlist\""));
+    }
+
+    #[test]
+    fn test_run_spelling_check_sees_files_added_by_the_command() {
+        let dir = TempDir::new().unwrap();
+        let config = ProjectConfig {
+            spelling: SpellingPolicy { enabled: true, terminology: BTreeMap::new() },
+            ..Default::default()
+        };
+        config.save_to(dir.path()).unwrap();
+        std::fs::create_dir(dir.path().join("docs")).unwrap();
+        std::fs::write(dir.path().join("docs/guide.md"), "# Guide\n\nPick wich one.\n").unwrap();
+        let mut runner = MockCommandRunner::new();
+        let output = |stdout: &str| CommandOutput {
+            exit_code: 0,
+            stdout: stdout.to_string(),
+            stderr: String::new(),
+        };
+        runner.expect("git", &["diff", "--cached", "-U0"], output(""));
+        runner.expect("git", &["diff", "-U0", "--", "docs", "README.md"], output(DIFF));
+        runner.expect(
+            "git",
+            &[
+                "ls-files",
+                "--others",
+                "--exclude-standard",
+                "--full-name",
+                "--",
+                "docs",
+                "README.md",
+            ],
+            output("docs/guide.md\n"),
+        );
+
+        let output = run_spelling_check(
+            &commit_input("git add docs README.md && git commit -m docs"),
+            dir.path(),
+            &runner,
+        );
+        let message = output.hook_specific_output.additional_context.unwrap();
+        assert!(message.contains("README.md:4: \"Teh\" should be \"the\""), "{message}");
+        assert!(message.contains("docs/guide.md:3: \"wich\" should be \"which\""), "{message}");
+    }
+
+    #[test]
+    fn test_run_spelling_check_disabled_by_default() {
+        let dir = TempDir::new().unwrap();
+        let runner = MockCommandRunner::new();
+        let output = run_spelling_check(&commit_input("git commit -m docs"), dir.path(), &runner);
+        assert!(output.hook_specific_output.additional_context.is_none());
+    }
+}
@@ -0,0 +1,137 @@
+//! Track stashes the agent creates so the stop hook can make sure they are
+//! resolved.
+//!
+//! Work parked in a stash is invisible to the user, so when a Bash command
+//! successfully stashes changes the new stash is recorded. The stop hook then
+//! blocks until each recorded stash has been applied or dropped.
+
+use crate::git;
+use crate::hooks::post_tool_use::PostToolUseInput;
+use crate::session;
+use crate::traits::CommandRunner;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::path::Path;
+
+/// Matches `git stash` invocations that create a stash: bare `git stash`,
+/// `git stash push`/`save`, or `git stash` followed directly by flags.
+static STASH_CREATE_PATTERN: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\bgit\s+stash(\s+(push|save)\b|\s+-|\s*$|\s*[;&|])").unwrap());
+
+/// Output git prints when a stash is created.
+const STASH_SAVED_MARKER: &str = "Saved working directory";
+
+/// Check whether a command creates a stash.
+fn creates_stash(command: &str) -> bool {
+    command.lines().any(|line| STASH_CREATE_PATTERN.is_match(line.trim()))
+}
+
+/// Check whether the tool response shows a stash was actually saved.
+fn stash_was_saved(tool_response: Option<&serde_json::Value>) -> bool {
+    let Some(response) = tool_response else {
+        return false;
+    };
+    let stdout = response.get("stdout").and_then(|s| s.as_str());
+    let text = stdout.or_else(|| response.as_str()).unwrap_or_default();
+    text.contains(STASH_SAVED_MARKER)
+}
+
+/// Record a stash created by a Bash command, if any.
+///
+/// # Errors
+///
+/// Returns an error if the stash cannot be looked up or recorded.
+pub fn track_stash(
+    input: &PostToolUseInput,
+    base_dir: &Path,
+    runner: &dyn CommandRunner,
+) -> Result<(), String> {
+    let command = input
+        .tool_input
+        .as_ref()
+        .and_then(|v| v.get("command"))
+        .and_then(|c| c.as_str())
+        .unwrap_or_default();
+
+    if !creates_stash(command) || !stash_was_saved(input.tool_response.as_ref()) {
+        return Ok(());
+    }
+
+    let sha = git::latest_stash(runner).map_err(|e| format!("Failed to look up stash: {e}"))?;
+    if let Some(sha) = sha {
+        session::record_agent_stash(base_dir, &sha)
+            .map_err(|e| format!("Failed to record stash: {e}"))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockCommandRunner;
+    use crate::traits::CommandOutput;
+    use tempfile::TempDir;
+
+    fn bash_input(command: &str, stdout: &str) -> PostToolUseInput {
+        PostToolUseInput {
+            tool_name: Some("Bash".to_string()),
+            tool_input: Some(serde_json::json!({ "command": command })),
+            tool_response: Some(serde_json::json!({ "stdout": stdout, "stderr": "" })),
+        }
+    }
+
+    #[test]
+    fn test_creates_stash() {
+        assert!(creates_stash("git stash"));
+        assert!(creates_stash("git stash -u"));
+        assert!(creates_stash("git stash push -m 'temp'"));
+        assert!(creates_stash("git stash save wip"));
+        assert!(creates_stash("git stash && git pull --rebase"));
+        assert!(creates_stash("cd repo; git stash; git checkout main"));
+    }
+
+    #[test]
+    fn test_does_not_create_stash() {
+        assert!(!creates_stash("git stash pop"));
+        assert!(!creates_stash("git stash apply stash@{0}"));
+        assert!(!creates_stash("git stash list"));
+        assert!(!creates_stash("git stash drop"));
+        assert!(!creates_stash("git status"));
+    }
+
+    #[test]
+    fn test_track_stash_records_new_stash() {
+        let dir = TempDir::new().unwrap();
+        let mut runner = MockCommandRunner::new();
+        runner.expect(
+            "git",
+            &["rev-parse", "-q", "--verify", "refs/stash"],
+            CommandOutput { exit_code: 0, stdout: "abc123\n".to_string(), stderr: String::new() },
+        );
+        let input =
+            bash_input("git stash", "Saved working directory and index state WIP on main: 1a2b3c");
+
+        track_stash(&input, dir.path(), &runner).unwrap();
+        assert_eq!(session::get_agent_stashes(dir.path()).unwrap(), vec!["abc123"]);
+    }
+
+    #[test]
+    fn test_track_stash_ignores_failed_stash() {
+        let dir = TempDir::new().unwrap();
+        let runner = MockCommandRunner::new();
+        let input = bash_input("git stash", "No local changes to save");
+
+        track_stash(&input, dir.path(), &runner).unwrap();
+        assert!(session::get_agent_stashes(dir.path()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_track_stash_ignores_other_commands() {
+        let dir = TempDir::new().unwrap();
+        let runner = MockCommandRunner::new();
+        let input = bash_input("git stash pop", "Saved working directory");
+
+        track_stash(&input, dir.path(), &runner).unwrap();
+        assert!(session::get_agent_stashes(dir.path()).unwrap().is_empty());
+    }
+}
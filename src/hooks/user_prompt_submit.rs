@@ -6,8 +6,11 @@
 //! It also records user messages for verification during the reflection prompt.
 
 use crate::error::Result;
+use crate::recovery;
 use crate::session;
+use crate::storage::Marker;
 use crate::tasks;
+use crate::traits::{HookDecision, HookObserver, NoopObserver};
 use std::path::Path;
 
 /// Input provided to `UserPromptSubmit` hooks by Claude Code.
@@ -38,7 +41,8 @@ pub struct UserPromptSubmitOutput {
 ///
 /// This hook resets session state when the user sends a new message,
 /// including clearing the reflection marker and validation marker.
-/// It also detects post-compaction scenarios and prompts task recovery.
+/// It also detects post-compaction scenarios and prompts task recovery, and
+/// warns about any recovery from a corrupted database.
 ///
 /// # Arguments
 ///
@@ -52,8 +56,40 @@ pub fn run_user_prompt_submit_hook(
     input: &UserPromptSubmitInput,
     base_dir: Option<&Path>,
 ) -> Result<UserPromptSubmitOutput> {
-    let single_work_item_id = crate::single_work_item::get_single_work_item_id();
-    run_user_prompt_submit_hook_inner(input, base_dir, single_work_item_id.as_deref())
+    run_user_prompt_submit_hook_with_observer(input, base_dir, &NoopObserver)
+}
+
+/// Run the user prompt submit hook, reporting its work and the decision to `observer`.
+///
+/// The hook never blocks, so the observer cannot veto anything.
+///
+/// # Errors
+///
+/// Returns an error if file operations fail.
+pub fn run_user_prompt_submit_hook_with_observer(
+    input: &UserPromptSubmitInput,
+    base_dir: Option<&Path>,
+    observer: &dyn HookObserver,
+) -> Result<UserPromptSubmitOutput> {
+    let base = base_dir.unwrap_or_else(|| Path::new("."));
+    let single_work_item_id = crate::single_work_item::assigned_work_item_id(base);
+    observer.on_check_start("user_prompt_submit", "session_state");
+    let result =
+        run_user_prompt_submit_hook_inner(input, Some(base), single_work_item_id.as_deref());
+    let outcome = match &result {
+        Ok(output) if output.system_message.is_some() => "message added",
+        Ok(_) => "ok",
+        Err(_) => "failed",
+    };
+    observer.on_check_end("user_prompt_submit", "session_state", outcome);
+    observer.on_decision(&HookDecision {
+        hook: "user_prompt_submit",
+        allow: true,
+        decided_by: None,
+    });
+    let mut output = result?;
+    output.system_message = recovery::with_recovery_warning(base, output.system_message);
+    Ok(output)
 }
 
 /// Check if this is the opening prompt (first user message in the session).
@@ -88,11 +124,19 @@ fn run_user_prompt_submit_hook_inner(
 ) -> Result<UserPromptSubmitOutput> {
     let base = base_dir.unwrap_or_else(|| Path::new("."));
 
-    // Clear the needs validation marker - user has seen changes
-    session::clear_needs_validation(base)?;
+    // Clear the needs validation marker - user has seen changes - and the
+    // reflection marker so the next stop with modifying tools will prompt again
+    session::clear_markers(base, &[Marker::NeedsValidation, Marker::MustReflect])?;
+    session::clear_touched_files(base)?;
+
+    // The user is back, so earlier declined auto-work prompts no longer count
+    session::clear_auto_work_declines(base)?;
 
-    // Clear the reflection marker so the next stop with modifying tools will prompt again
-    session::clear_reflect_marker(base)?;
+    // The user has spoken, so any repeated stop block is no longer an impasse
+    session::clear_stop_block_streak(base)?;
+
+    // The user has resumed a paused session in place, so there is nothing left to resume
+    session::clear_stopped_pause(base)?;
 
     let session_id = input.transcript_path.as_deref().unwrap_or("unknown");
 
@@ -104,6 +148,9 @@ fn run_user_prompt_submit_hook_inner(
         return Ok(UserPromptSubmitOutput { system_message: Some(compaction_msg) });
     }
 
+    // The user has spoken, so the stop hook's blocks are no longer a loop
+    session::clear_stop_injections(base, session_id)?;
+
     // Single work item mode: validate the assigned item and announce it
     if let Some(swi_id) = single_work_item_id {
         return match crate::single_work_item::validate_work_item(base, swi_id) {
@@ -195,6 +242,62 @@ mod tests {
         assert!(!session::has_reflect_marker(base));
     }
 
+    #[test]
+    fn test_observer_sees_session_state_reset() {
+        use crate::testing::RecordingObserver;
+
+        let dir = TempDir::new().unwrap();
+        let observer = RecordingObserver::new();
+        run_user_prompt_submit_hook_with_observer(&default_input(), Some(dir.path()), &observer)
+            .unwrap();
+        assert_eq!(observer.events()[0], "start user_prompt_submit/session_state");
+        assert_eq!(observer.events().last().unwrap(), "decision user_prompt_submit: allow");
+    }
+
+    #[test]
+    fn test_user_prompt_submit_clears_stopped_pause() {
+        let dir = TempDir::new().unwrap();
+        let base = dir.path();
+
+        session::request_pause(base, "lunch").unwrap();
+        session::mark_pause_stopped(base).unwrap();
+
+        run_user_prompt_submit_hook(&default_input(), Some(base)).unwrap();
+
+        assert_eq!(session::take_paused_session(base), None);
+    }
+
+    #[test]
+    fn test_user_prompt_submit_clears_stop_block_streak() {
+        let dir = TempDir::new().unwrap();
+        let base = dir.path();
+
+        session::record_stop_block(base, "sig").unwrap();
+        session::record_stop_block(base, "sig").unwrap();
+
+        run_user_prompt_submit_hook(&default_input(), Some(base)).unwrap();
+
+        assert_eq!(session::record_stop_block(base, "sig").unwrap(), 1);
+    }
+
+    #[test]
+    fn test_user_prompt_submit_clears_injection_streak() {
+        let dir = TempDir::new().unwrap();
+        let base = dir.path();
+        let input = UserPromptSubmitInput {
+            transcript_path: Some("s1".to_string()),
+            prompt: Some("Carry on".to_string()),
+            ..Default::default()
+        };
+
+        session::record_stop_injection(base, "s1", "", "validation").unwrap();
+        session::record_stop_injection(base, "s1", "", "validation").unwrap();
+
+        run_user_prompt_submit_hook(&input, Some(base)).unwrap();
+
+        assert_eq!(session::record_stop_injection(base, "s1", "", "validation").unwrap().len(), 1);
+    }
+
     #[test]
     fn test_user_prompt_submit_no_marker() {
         let dir = TempDir::new().unwrap();
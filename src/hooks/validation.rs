@@ -1,8 +1,12 @@
 //! Pre-tool-use hook for tracking when validation is needed.
 //!
 //! This hook sets a marker when modifying tools (`Edit`, `Write`, `NotebookEdit`) are used,
-//! indicating that validation must run before stopping.
+//! indicating that validation must run before stopping. The modified file is also
+//! recorded so that workspaces only run the checks of the packages that changed,
+//! and the session's streak of stop hook blocks restarts, since a file change is
+//! progress.
 
+use crate::config;
 use crate::hooks::{HookInput, PreToolUseOutput};
 use crate::session;
 use crate::storage::SqliteStore;
@@ -24,14 +28,45 @@ const MODIFYING_TOOLS: &[&str] = &["Edit", "Write", "NotebookEdit"];
 pub fn run_validation_hook(input: &HookInput, base_dir: &Path) -> PreToolUseOutput {
     // Create the default store and delegate to the testable version
     match SqliteStore::new(base_dir) {
-        Ok(store) => run_validation_hook_with_store(input, &store),
+        Ok(store) => {
+            record_touched_file(input, base_dir, &store);
+            clear_injection_streak(input, &store);
+            run_validation_hook_with_store(input, &store)
+        }
         Err(e) => {
-            eprintln!("Warning: Failed to create store for validation marker: {e}");
+            tracing::warn!("Failed to create store for validation marker: {e}");
             PreToolUseOutput::allow(None)
         }
     }
 }
 
+/// Record the file a modifying tool is about to change, relative to the project.
+fn record_touched_file(input: &HookInput, base_dir: &Path, store: &SqliteStore) {
+    if !MODIFYING_TOOLS.contains(&input.tool_name.as_deref().unwrap_or("")) {
+        return;
+    }
+    let Some(file_path) = input.tool_input.as_ref().and_then(|t| t.file_path.as_deref()) else {
+        return;
+    };
+    let Some(path) = config::project_relative_path(base_dir, file_path) else {
+        return;
+    };
+    if let Err(e) = store.add_touched_file(&path) {
+        tracing::warn!("Failed to record touched file: {e}");
+    }
+}
+
+/// Restart the session's streak of stop hook blocks when a file is changed.
+fn clear_injection_streak(input: &HookInput, store: &SqliteStore) {
+    if !MODIFYING_TOOLS.contains(&input.tool_name.as_deref().unwrap_or("")) {
+        return;
+    }
+    let session_id = input.transcript_path.as_deref().unwrap_or("unknown");
+    if let Err(e) = store.clear_injection_streak(session_id) {
+        tracing::warn!("Failed to clear the stop block streak: {e}");
+    }
+}
+
 /// Run the validation tracking hook with a provided store.
 ///
 /// This is the testable version that accepts a `StateStore` trait.
@@ -45,7 +80,7 @@ pub fn run_validation_hook_with_store(
     if MODIFYING_TOOLS.contains(&tool_name) {
         // Set the marker indicating validation is needed
         if let Err(e) = session::set_needs_validation_with_store(store) {
-            eprintln!("Warning: Failed to set needs_validation marker: {e}");
+            tracing::warn!("Failed to set needs_validation marker: {e}");
         }
     }
 
@@ -82,6 +117,43 @@ mod tests {
         assert!(session::needs_validation(base));
     }
 
+    #[test]
+    fn test_edit_records_touched_file() {
+        let dir = TempDir::new().unwrap();
+        let base = dir.path();
+
+        let mut input = make_input("Edit");
+        let file_path = base.join("crates/api/src/lib.rs");
+        input.tool_input = Some(ToolInput {
+            file_path: Some(file_path.display().to_string()),
+            ..Default::default()
+        });
+        run_validation_hook(&input, base);
+
+        let mut read = make_input("Read");
+        read.tool_input =
+            Some(ToolInput { file_path: Some("README.md".to_string()), ..Default::default() });
+        run_validation_hook(&read, base);
+
+        assert_eq!(session::get_touched_files(base).unwrap(), vec!["crates/api/src/lib.rs"]);
+    }
+
+    #[test]
+    fn test_edit_clears_injection_streak() {
+        let dir = TempDir::new().unwrap();
+        let base = dir.path();
+        let mut input = make_input("Read");
+        input.transcript_path = Some("s1".to_string());
+
+        session::record_stop_injection(base, "s1", "", "validation").unwrap();
+        run_validation_hook(&input, base);
+        assert_eq!(session::record_stop_injection(base, "s1", "", "validation").unwrap().len(), 2);
+
+        input.tool_name = Some("Edit".to_string());
+        run_validation_hook(&input, base);
+        assert_eq!(session::record_stop_injection(base, "s1", "", "validation").unwrap().len(), 1);
+    }
+
     #[test]
     fn test_write_sets_marker() {
         let dir = TempDir::new().unwrap();
@@ -1,5 +1,7 @@
 //! Detect warnings in Bash tool stderr and create work items to track them.
 
+use crate::config::ProjectConfig;
+use crate::context_budget;
 use crate::hooks::post_tool_use::PostToolUseInput;
 use crate::tasks::{Priority, SqliteTaskStore, TaskStore};
 use std::path::Path;
@@ -19,9 +21,6 @@ struct BashToolResponse {
 /// Environment variable to disable the warning detection hook.
 const DISABLE_ENV_VAR: &str = "CLAUDE_RELIABILITY_DISABLE_HOOK";
 
-/// Maximum length of warning text included in work item descriptions.
-const MAX_WARNING_TEXT_LEN: usize = 2000;
-
 /// Maximum length of command text included in work item titles.
 const MAX_COMMAND_TITLE_LEN: usize = 60;
 
@@ -94,12 +93,11 @@ pub fn check_bash_warnings(input: &PostToolUseInput, base_dir: &Path) -> Result<
     };
     let title = format!("Fix warnings from: {truncated_command}");
 
-    // Build description with command and warning lines
-    let mut warning_text = warning_lines.join("\n");
-    if warning_text.len() > MAX_WARNING_TEXT_LEN {
-        warning_text.truncate(MAX_WARNING_TEXT_LEN);
-        warning_text.push_str("\n... (truncated)");
-    }
+    // Build description with command and warning lines, within the context
+    // budget as the work item is shown to the agent
+    let budget = ProjectConfig::load_cached(base_dir)
+        .map_or(context_budget::DEFAULT_BUDGET, |config| config.context_budget);
+    let warning_text = context_budget::truncate(&warning_lines.join("\n"), budget);
     let description =
         format!("Command:\n```\n{command}\n```\n\nWarnings:\n```\n{warning_text}\n```");
 
@@ -408,7 +406,10 @@ mod tests {
         let dir = TempDir::new().unwrap();
         setup_db(dir.path());
 
-        // Create many warning lines that exceed MAX_WARNING_TEXT_LEN
+        let config = ProjectConfig { context_budget: 2000, ..Default::default() };
+        config.save_to(dir.path()).unwrap();
+
+        // Create many warning lines that exceed the context budget
         let mut stderr_lines = Vec::new();
         for i in 0..200 {
             stderr_lines.push(format!("warning: issue number {i} is very problematic"));
@@ -431,5 +432,7 @@ mod tests {
         let tasks = store.list_tasks(TaskFilter::default()).unwrap();
         assert_eq!(tasks.len(), 1);
         assert!(tasks[0].description.contains("(truncated)"));
+        assert!(tasks[0].description.len() < 2200);
+        assert!(!tasks[0].description.contains("issue number 199"));
     }
 }
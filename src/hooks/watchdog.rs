@@ -0,0 +1,146 @@
+//! Deadlines that keep hooks inside their time limit.
+//!
+//! Claude Code kills a hook that runs past its timeout, and everything the
+//! hook found out is lost. A hook with a deadline checks it between steps:
+//! once the deadline is reached it skips the remaining steps, reports which
+//! step it ran out of time in, and records the timeout in the audit log.
+//!
+//! The stop, post-tool-use and session start hooks have deadlines. The
+//! pre-tool-use hook does not: its checks guard the tool call, and skipping
+//! them would let the call through unchecked.
+//!
+//! The timeout is read from `CLAUDE_RELIABILITY_HOOK_TIMEOUT` or the
+//! `hook_timeout_seconds` config setting, in seconds. The deadline falls
+//! [`DEADLINE_MARGIN`] before it, leaving time to report.
+
+use crate::config::ProjectConfig;
+use crate::tasks;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Environment variable giving the hook timeout in seconds.
+pub const HOOK_TIMEOUT_ENV: &str = "CLAUDE_RELIABILITY_HOOK_TIMEOUT";
+
+/// Time kept in reserve before the timeout for reporting.
+pub const DEADLINE_MARGIN: Duration = Duration::from_secs(5);
+
+/// Audit log operation recording a hook that ran out of time.
+pub const TIMEOUT_OPERATION: &str = "hook_timeout";
+
+/// The point by which a hook has to wrap up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Deadline {
+    at: Instant,
+}
+
+impl Deadline {
+    /// A deadline for a hook with the given timeout, starting now.
+    ///
+    /// At most half of a short timeout is kept in reserve.
+    #[must_use]
+    pub fn after(timeout: Duration) -> Self {
+        Self { at: Instant::now() + timeout.saturating_sub(DEADLINE_MARGIN.min(timeout / 2)) }
+    }
+
+    /// The deadline for a hook starting now, from the environment or else
+    /// the configured timeout. A timeout of 0 means no deadline.
+    #[must_use]
+    pub fn from_env_or_config(configured_secs: Option<u32>) -> Option<Self> {
+        timeout_secs(std::env::var(HOOK_TIMEOUT_ENV).ok().as_deref(), configured_secs)
+            .map(|secs| Self::after(Duration::from_secs(u64::from(secs))))
+    }
+
+    /// The deadline for a hook starting now in the project at `base_dir`,
+    /// from the environment or else the project's configured timeout.
+    #[must_use]
+    pub fn for_project(base_dir: &Path) -> Option<Self> {
+        let configured = ProjectConfig::load_from(base_dir)
+            .ok()
+            .flatten()
+            .and_then(|config| config.hook_timeout_seconds);
+        Self::from_env_or_config(configured)
+    }
+
+    /// Time left before the deadline.
+    #[must_use]
+    pub fn remaining(&self) -> Duration {
+        self.at.saturating_duration_since(Instant::now())
+    }
+
+    /// Whether the deadline has been reached.
+    #[must_use]
+    pub fn is_reached(&self) -> bool {
+        self.remaining().is_zero()
+    }
+}
+
+/// Resolve the timeout from the environment variable's value and the config.
+///
+/// A value in the environment that is not a number is ignored.
+fn timeout_secs(env: Option<&str>, configured_secs: Option<u32>) -> Option<u32> {
+    let from_env = env.and_then(|value| {
+        let parsed = value.trim().parse().ok();
+        if parsed.is_none() {
+            tracing::warn!("Ignoring {HOOK_TIMEOUT_ENV}={value:?}: not a number of seconds");
+        }
+        parsed
+    });
+    from_env.or(configured_secs).filter(|&secs| secs > 0)
+}
+
+/// Record in the audit log that a hook ran out of time during a step.
+///
+/// Nothing is recorded if the project has no database.
+pub fn record_timeout(base_dir: &Path, hook: &str, step: &str) {
+    let Some(store) = tasks::open_project_store(base_dir) else {
+        return;
+    };
+    let details = format!("{hook} hook ran out of time during {step}");
+    if let Err(e) = store.record_event(TIMEOUT_OPERATION, &details) {
+        tracing::warn!("Failed to record hook timeout: {e}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tasks::{SqliteTaskStore, TaskStore};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_timeout_secs() {
+        assert_eq!(timeout_secs(None, None), None);
+        assert_eq!(timeout_secs(None, Some(60)), Some(60));
+        assert_eq!(timeout_secs(Some("30"), Some(60)), Some(30));
+        assert_eq!(timeout_secs(Some("soon"), Some(60)), Some(60));
+        assert_eq!(timeout_secs(Some("0"), Some(60)), None);
+    }
+
+    #[test]
+    fn test_deadline() {
+        let deadline = Deadline::after(Duration::from_secs(60));
+        assert!(!deadline.is_reached());
+        assert!(deadline.remaining() <= Duration::from_secs(55));
+        assert!(deadline.remaining() > Duration::from_secs(50));
+
+        // Short timeouts keep half in reserve
+        let deadline = Deadline::after(Duration::from_secs(4));
+        assert!(deadline.remaining() <= Duration::from_secs(2));
+        assert!(Deadline::after(Duration::ZERO).is_reached());
+    }
+
+    #[test]
+    fn test_record_timeout() {
+        let dir = TempDir::new().unwrap();
+        record_timeout(dir.path(), "stop", "validation_required");
+
+        let store = SqliteTaskStore::for_project(dir.path()).unwrap();
+        record_timeout(dir.path(), "stop", "validation_required");
+        let log = store.get_audit_log(None, None).unwrap();
+        assert_eq!(log[0].operation, TIMEOUT_OPERATION);
+        assert_eq!(
+            log[0].details.as_deref(),
+            Some("stop hook ran out of time during validation_required")
+        );
+    }
+}
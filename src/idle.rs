@@ -0,0 +1,225 @@
+//! Detecting whether the user is idle from sources other than the transcript.
+//!
+//! The transcript only shows when the user last sent a message, so a user who
+//! is reading the agent's diff in another window looks idle. An [`IdleProvider`]
+//! gives a second opinion, and the stop hook treats the user as idle only when
+//! both agree.
+
+use crate::config::IdleSource;
+use crate::paths;
+use crate::traits::{CommandRunner, IdleProvider};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// How long to wait for an OS idle-time query.
+const QUERY_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Idle time from the modification time of a heartbeat file.
+///
+/// The user (or an editor plugin, shell prompt hook, etc.) touches the file
+/// while they are active. A missing file gives no information.
+#[derive(Debug, Clone)]
+pub struct HeartbeatIdle {
+    path: PathBuf,
+}
+
+impl HeartbeatIdle {
+    /// Create a provider reading the given heartbeat file.
+    #[must_use]
+    pub const fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+}
+
+impl IdleProvider for HeartbeatIdle {
+    fn idle_minutes(&self) -> Option<u32> {
+        let modified = std::fs::metadata(&self.path).and_then(|m| m.modified()).ok()?;
+        // A timestamp in the future counts as just now
+        let elapsed = SystemTime::now().duration_since(modified).unwrap_or_default();
+        Some(u32::try_from(elapsed.as_secs() / 60).unwrap_or(u32::MAX))
+    }
+}
+
+/// Idle time from the operating system's keyboard and mouse activity.
+///
+/// Uses `ioreg` on macOS. Elsewhere it tries `xprintidle` (X11) and then
+/// GNOME's Mutter idle monitor over D-Bus (Wayland).
+pub struct SystemIdle<'a> {
+    runner: &'a dyn CommandRunner,
+}
+
+impl<'a> SystemIdle<'a> {
+    /// Create a provider running its queries through the given runner.
+    #[must_use]
+    pub const fn new(runner: &'a dyn CommandRunner) -> Self {
+        Self { runner }
+    }
+
+    /// Run a query command, returning its stdout if it succeeded.
+    fn query(&self, program: &str, args: &[&str]) -> Option<String> {
+        if !self.runner.is_available(program) {
+            return None;
+        }
+        let output = self.runner.run(program, args, Some(QUERY_TIMEOUT)).ok()?;
+        output.success().then_some(output.stdout)
+    }
+
+    /// Idle time in milliseconds, if any query method works here.
+    fn idle_millis(&self) -> Option<u64> {
+        if cfg!(target_os = "macos") {
+            return self
+                .query("ioreg", &["-c", "IOHIDSystem", "-d", "4"])
+                .and_then(|out| parse_ioreg_idle(&out));
+        }
+        self.query("xprintidle", &[]).and_then(|out| out.trim().parse().ok()).or_else(|| {
+            self.query(
+                "gdbus",
+                &[
+                    "call",
+                    "--session",
+                    "--dest",
+                    "org.gnome.Mutter.IdleMonitor",
+                    "--object-path",
+                    "/org/gnome/Mutter/IdleMonitor/Core",
+                    "--method",
+                    "org.gnome.Mutter.IdleMonitor.GetIdletime",
+                ],
+            )
+            .and_then(|out| parse_gdbus_idle(&out))
+        })
+    }
+}
+
+impl IdleProvider for SystemIdle<'_> {
+    fn idle_minutes(&self) -> Option<u32> {
+        self.idle_millis().map(|ms| u32::try_from(ms / 60_000).unwrap_or(u32::MAX))
+    }
+}
+
+/// Parse `HIDIdleTime` (nanoseconds) from `ioreg` output into milliseconds.
+fn parse_ioreg_idle(output: &str) -> Option<u64> {
+    let line = output.lines().find(|line| line.contains("\"HIDIdleTime\""))?;
+    let nanos: u64 = line.rsplit('=').next()?.trim().parse().ok()?;
+    Some(nanos / 1_000_000)
+}
+
+/// Parse the reply of Mutter's `GetIdletime`, such as `(uint64 12345,)`, in milliseconds.
+fn parse_gdbus_idle(output: &str) -> Option<u64> {
+    output.trim().trim_start_matches("(uint64").trim_end_matches(",)").trim().parse().ok()
+}
+
+/// Build the idle provider for the configured source.
+///
+/// Returns `None` for [`IdleSource::Transcript`], which needs no provider.
+#[must_use]
+pub fn provider_for<'a>(
+    source: IdleSource,
+    heartbeat_file: Option<&str>,
+    base_dir: &Path,
+    runner: &'a dyn CommandRunner,
+) -> Option<Box<dyn IdleProvider + 'a>> {
+    match source {
+        IdleSource::Transcript => None,
+        IdleSource::Os => Some(Box::new(SystemIdle::new(runner))),
+        IdleSource::Heartbeat => Some(Box::new(HeartbeatIdle::new(paths::project_heartbeat_path(
+            base_dir,
+            heartbeat_file,
+        )))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockCommandRunner;
+    use crate::traits::CommandOutput;
+    use tempfile::TempDir;
+
+    fn stdout(text: &str) -> CommandOutput {
+        CommandOutput { exit_code: 0, stdout: text.to_string(), stderr: String::new() }
+    }
+
+    #[test]
+    fn test_heartbeat_missing_file_is_unknown() {
+        let dir = TempDir::new().unwrap();
+        let provider = HeartbeatIdle::new(dir.path().join("heartbeat"));
+        assert_eq!(provider.idle_minutes(), None);
+    }
+
+    #[test]
+    fn test_heartbeat_recently_touched() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("heartbeat");
+        std::fs::write(&path, "").unwrap();
+        assert_eq!(HeartbeatIdle::new(path).idle_minutes(), Some(0));
+    }
+
+    #[test]
+    fn test_heartbeat_old_file() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("heartbeat");
+        let file = std::fs::File::create(&path).unwrap();
+        file.set_modified(SystemTime::now() - Duration::from_secs(45 * 60)).unwrap();
+        assert_eq!(HeartbeatIdle::new(path).idle_minutes(), Some(45));
+    }
+
+    #[test]
+    fn test_parse_ioreg_idle() {
+        let output = "    | |   \"HIDIdleTime\" = 123000000000\n    | |   \"Other\" = 1\n";
+        assert_eq!(parse_ioreg_idle(output), Some(123_000));
+        assert_eq!(parse_ioreg_idle("nothing here"), None);
+    }
+
+    #[test]
+    fn test_parse_gdbus_idle() {
+        assert_eq!(parse_gdbus_idle("(uint64 905000,)\n"), Some(905_000));
+        assert_eq!(parse_gdbus_idle("Error: no such service"), None);
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    #[test]
+    fn test_system_idle_uses_xprintidle() {
+        let mut runner = MockCommandRunner::new();
+        runner.set_available("xprintidle");
+        runner.expect("xprintidle", &[], stdout("1260000\n"));
+        assert_eq!(SystemIdle::new(&runner).idle_minutes(), Some(21));
+        runner.verify();
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    #[test]
+    fn test_system_idle_falls_back_to_gdbus() {
+        let mut runner = MockCommandRunner::new();
+        runner.set_available("gdbus");
+        runner.expect(
+            "gdbus",
+            &[
+                "call",
+                "--session",
+                "--dest",
+                "org.gnome.Mutter.IdleMonitor",
+                "--object-path",
+                "/org/gnome/Mutter/IdleMonitor/Core",
+                "--method",
+                "org.gnome.Mutter.IdleMonitor.GetIdletime",
+            ],
+            stdout("(uint64 180000,)"),
+        );
+        assert_eq!(SystemIdle::new(&runner).idle_minutes(), Some(3));
+        runner.verify();
+    }
+
+    #[test]
+    fn test_system_idle_unknown_without_tools() {
+        let runner = MockCommandRunner::new();
+        assert_eq!(SystemIdle::new(&runner).idle_minutes(), None);
+    }
+
+    #[test]
+    fn test_provider_for_transcript_is_none() {
+        let runner = MockCommandRunner::new();
+        let dir = TempDir::new().unwrap();
+        assert!(provider_for(IdleSource::Transcript, None, dir.path(), &runner).is_none());
+        assert!(provider_for(IdleSource::Heartbeat, None, dir.path(), &runner).is_some());
+    }
+}
@@ -2,18 +2,39 @@
 //!
 //! Hooks for improving Claude Code reliability and safety.
 
+pub mod assumptions;
+pub mod backup;
 pub mod beads_sync;
 #[cfg(feature = "cli")]
 pub mod cli;
 pub mod command;
 pub mod config;
+pub mod context_budget;
+pub mod definition_of_done;
+pub mod emergency_stop;
 pub mod error;
+pub mod escalation;
+pub mod export;
 pub mod git;
+pub mod handoff;
 pub mod hook_logging;
 pub mod hooks;
+pub mod idle;
+pub mod logging;
+pub mod maintenance;
+pub mod metrics;
 pub mod paths;
+pub mod pr;
+pub mod profiling;
+pub mod protected_change;
 pub mod question;
+pub mod question_delivery;
+pub mod recovery;
 pub mod reminders;
+pub mod replay;
+pub mod report;
+pub mod schedule;
+pub mod secrets;
 pub mod session;
 pub mod single_work_item;
 pub mod storage;
@@ -21,9 +42,15 @@ pub mod subagent;
 pub mod subagent_logging;
 pub mod tasks;
 pub mod templates;
+#[cfg(any(test, feature = "test-support"))]
 pub mod testing;
 pub mod traits;
 pub mod transcript;
+pub mod transcript_export;
+pub mod transcript_index;
+pub mod usage;
+pub mod vcs;
+pub mod worker;
 
 /// Library version.
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
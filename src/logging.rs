@@ -0,0 +1,236 @@
+//! Structured logging for hooks.
+//!
+//! Each hook invocation writes JSON log lines to
+//! `.claude-reliability/logs/<hook>.log`. Warnings are also printed to stderr.
+//! The log file is rotated when it grows past a size limit, keeping a few
+//! older files as `<hook>.log.1`, `<hook>.log.2`, and so on.
+//!
+//! What gets logged is controlled by a `RUST_LOG`-style filter, taken from the
+//! `CLAUDE_RELIABILITY_LOG` environment variable, the `log_filter` config key,
+//! or `info` by default.
+
+use crate::config::ProjectConfig;
+use crate::paths;
+use crate::profiling::ProfileLayer;
+use std::fs::OpenOptions;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::Subscriber;
+use tracing_subscriber::filter::{EnvFilter, LevelFilter};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::Layer;
+
+/// Environment variable holding the log filter.
+pub const LOG_FILTER_ENV: &str = "CLAUDE_RELIABILITY_LOG";
+
+/// Filter used when none is configured.
+const DEFAULT_FILTER: &str = "info";
+
+/// Size at which a log file is rotated (1 MiB).
+const MAX_LOG_BYTES: u64 = 1024 * 1024;
+
+/// Number of rotated log files kept per hook.
+const MAX_ROTATED_LOGS: u32 = 3;
+
+/// Get the log file for a hook.
+///
+/// Returns `<project data dir>/logs/<hook>.log`.
+#[must_use]
+pub fn hook_log_path(base_dir: &Path, hook: &str) -> PathBuf {
+    paths::project_logs_dir(base_dir).join(format!("{hook}.log"))
+}
+
+/// Get the path of the `n`th rotated copy of a log file.
+fn rotated_path(path: &Path, n: u32) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(format!(".{n}"));
+    PathBuf::from(name)
+}
+
+/// Rotate a log file if it has grown past the size limit.
+fn rotate_if_needed(path: &Path) -> std::io::Result<()> {
+    if std::fs::metadata(path).map_or(true, |m| m.len() < MAX_LOG_BYTES) {
+        return Ok(());
+    }
+    for n in (1..MAX_ROTATED_LOGS).rev() {
+        let from = rotated_path(path, n);
+        if from.exists() {
+            std::fs::rename(&from, rotated_path(path, n + 1))?;
+        }
+    }
+    std::fs::rename(path, rotated_path(path, 1))
+}
+
+/// Get the log filter directives for a project.
+fn filter_directives(base_dir: &Path) -> String {
+    std::env::var(LOG_FILTER_ENV)
+        .ok()
+        .filter(|f| !f.trim().is_empty())
+        .or_else(|| ProjectConfig::load_from(base_dir).ok().flatten().and_then(|c| c.log_filter))
+        .unwrap_or_else(|| DEFAULT_FILTER.to_string())
+}
+
+/// Build a filter from directives, falling back to the default if they are invalid.
+fn build_filter(directives: &str) -> EnvFilter {
+    EnvFilter::try_new(directives).unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER))
+}
+
+/// Open a hook's log file for appending, rotating it first if needed.
+fn open_log_file(path: &Path) -> std::io::Result<std::fs::File> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    rotate_if_needed(path)?;
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+/// Build the subscriber for a hook.
+///
+/// Logs JSON to the hook's log file and warnings to stderr, and records a
+/// timing profile when given a profile layer. If the log file cannot be
+/// opened, only stderr is used and the error is returned alongside.
+pub fn hook_subscriber(
+    base_dir: &Path,
+    hook: &str,
+    profile: Option<ProfileLayer>,
+) -> (impl Subscriber + Send + Sync, Option<std::io::Error>) {
+    let (file, error) = match open_log_file(&hook_log_path(base_dir, hook)) {
+        Ok(file) => (Some(file), None),
+        Err(e) => (None, Some(e)),
+    };
+    let file_layer = file.map(|file| {
+        tracing_subscriber::fmt::layer()
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .with_writer(Mutex::new(file))
+            .with_filter(build_filter(&filter_directives(base_dir)))
+    });
+    let stderr_layer = tracing_subscriber::fmt::layer()
+        .with_writer(std::io::stderr)
+        .with_ansi(false)
+        .without_time()
+        .with_target(false)
+        .with_filter(LevelFilter::WARN);
+    (tracing_subscriber::registry().with(file_layer).with(stderr_layer).with(profile), error)
+}
+
+/// Install the hook's subscriber as the global default.
+///
+/// Does nothing if a global subscriber is already installed.
+pub fn init_hook_logging(base_dir: &Path, hook: &str, profile: Option<ProfileLayer>) {
+    let (subscriber, error) = hook_subscriber(base_dir, hook, profile);
+    if tracing::subscriber::set_global_default(subscriber).is_ok() {
+        if let Some(e) = error {
+            tracing::warn!("Could not open log file for {hook} hook: {e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn read_log(dir: &TempDir, hook: &str) -> Vec<serde_json::Value> {
+        std::fs::read_to_string(hook_log_path(dir.path(), hook))
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_hook_log_path() {
+        assert_eq!(
+            hook_log_path(Path::new("/project"), "stop"),
+            PathBuf::from("/project/.claude-reliability/logs/stop.log")
+        );
+    }
+
+    #[test]
+    fn test_hook_subscriber_writes_json() {
+        let dir = TempDir::new().unwrap();
+        let (subscriber, error) = hook_subscriber(dir.path(), "stop", None);
+        assert!(error.is_none());
+
+        tracing::subscriber::with_default(subscriber, || {
+            let _span = tracing::info_span!("stop_check", check = "uncommitted_changes").entered();
+            tracing::info!(outcome = "blocking", "check finished");
+            tracing::debug!("not logged at the default level");
+        });
+
+        let lines = read_log(&dir, "stop");
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0]["level"], "INFO");
+        assert_eq!(lines[0]["fields"]["message"], "check finished");
+        assert_eq!(lines[0]["fields"]["outcome"], "blocking");
+        assert_eq!(lines[0]["span"]["check"], "uncommitted_changes");
+    }
+
+    #[test]
+    fn test_hook_subscriber_records_profile() {
+        let dir = TempDir::new().unwrap();
+        let profiler = crate::profiling::Profiler::start();
+        let (subscriber, _) = hook_subscriber(dir.path(), "stop", Some(profiler.layer()));
+
+        tracing::subscriber::with_default(subscriber, || {
+            let _span = tracing::info_span!("stop_check", check = "problem_mode").entered();
+            let _command = tracing::debug_span!("command", command = "git status").entered();
+            tracing::info!("check finished");
+        });
+
+        // Spans below the log level are profiled, but not logged
+        assert!(profiler.folded().contains("stop_check:problem_mode;command:git status "));
+        let lines = read_log(&dir, "stop");
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0]["spans"].as_array().unwrap().iter().all(|s| s["name"] != "command"));
+    }
+
+    #[test]
+    fn test_filter_from_config() {
+        let dir = TempDir::new().unwrap();
+        let config = ProjectConfig { log_filter: Some("debug".to_string()), ..Default::default() };
+        config.save_to(dir.path()).unwrap();
+        let (subscriber, _) = hook_subscriber(dir.path(), "pre-tool-use", None);
+
+        tracing::subscriber::with_default(subscriber, || tracing::debug!("detail"));
+
+        assert_eq!(read_log(&dir, "pre-tool-use")[0]["fields"]["message"], "detail");
+    }
+
+    #[test]
+    fn test_invalid_filter_falls_back_to_default() {
+        let filter = build_filter("not a [valid filter");
+        assert_eq!(filter.max_level_hint(), Some(LevelFilter::INFO));
+    }
+
+    #[test]
+    fn test_rotation() {
+        let dir = TempDir::new().unwrap();
+        let path = hook_log_path(dir.path(), "stop");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+
+        // Small files are left alone
+        std::fs::write(&path, "small\n").unwrap();
+        rotate_if_needed(&path).unwrap();
+        assert!(path.exists());
+
+        let big = vec![b'x'; usize::try_from(MAX_LOG_BYTES).unwrap()];
+        for _ in 0..=MAX_ROTATED_LOGS {
+            std::fs::write(&path, &big).unwrap();
+            rotate_if_needed(&path).unwrap();
+            assert!(!path.exists());
+        }
+        assert!(rotated_path(&path, MAX_ROTATED_LOGS).exists());
+        assert!(!rotated_path(&path, MAX_ROTATED_LOGS + 1).exists());
+    }
+
+    #[test]
+    fn test_unwritable_log_dir() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join(".claude-reliability"), "not a directory").unwrap();
+        let (_, error) = hook_subscriber(dir.path(), "stop", None);
+        assert!(error.is_some());
+    }
+}
@@ -0,0 +1,130 @@
+//! Database maintenance, keeping hook latency flat as the database grows.
+//!
+//! A maintenance run checks the database for corruption, recovering it if
+//! needed, rebuilds the full-text search indexes, lets `SQLite`
+//! refresh its query planner statistics with `PRAGMA optimize`, clears
+//! expired markers and reports the size of the database. The session start
+//! hook runs it at most once per [`MAINTENANCE_INTERVAL_MINUTES`];
+//! `claude-reliability maintenance run` runs it on demand.
+
+use crate::error::Result;
+use crate::paths;
+use crate::recovery;
+use crate::session;
+use crate::storage::SqliteStore;
+use crate::tasks::SqliteTaskStore;
+use serde::Serialize;
+use std::path::Path;
+
+/// Minutes between opportunistic maintenance runs.
+pub const MAINTENANCE_INTERVAL_MINUTES: u32 = 24 * 60;
+
+/// What a maintenance run did.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MaintenanceReport {
+    /// Path of the database.
+    pub database: String,
+    /// Size of the database in bytes, including its write-ahead log.
+    pub size_bytes: u64,
+    /// Number of full-text search indexes rebuilt.
+    pub indexes_rebuilt: usize,
+    /// Number of expired markers cleared.
+    pub markers_pruned: usize,
+    /// What was recovered, if the database was corrupted.
+    pub recovered: Option<String>,
+}
+
+/// Run maintenance now.
+///
+/// # Errors
+///
+/// Returns an error if the database cannot be opened or maintained.
+pub fn run(base_dir: &Path) -> Result<MaintenanceReport> {
+    let recovered = recovery::check(&paths::project_db_path(base_dir))?.map(|r| r.summary());
+    let tasks = SqliteTaskStore::for_project(base_dir)?;
+    let indexes_rebuilt = tasks.rebuild_search_indexes()?;
+    let markers_pruned = session::prune_expired_markers(base_dir)?;
+    tasks.optimize()?;
+    SqliteStore::new(base_dir)?.claim_maintenance_run(0)?;
+
+    let db_path = tasks.db_path();
+    Ok(MaintenanceReport {
+        database: db_path.display().to_string(),
+        size_bytes: database_size(db_path),
+        indexes_rebuilt,
+        markers_pruned,
+        recovered,
+    })
+}
+
+/// Run maintenance if the database exists and it has not run recently.
+///
+/// Failures are logged rather than returned, since this runs inside hooks.
+pub fn run_if_due(base_dir: &Path) -> Option<MaintenanceReport> {
+    if !paths::project_db_path(base_dir).exists() {
+        return None;
+    }
+    let due = SqliteStore::new(base_dir)
+        .and_then(|store| store.claim_maintenance_run(MAINTENANCE_INTERVAL_MINUTES));
+    match due.and_then(|due| due.then(|| run(base_dir)).transpose()) {
+        Ok(report) => report,
+        Err(e) => {
+            tracing::warn!("Database maintenance failed: {e}");
+            None
+        }
+    }
+}
+
+/// Size of a database file plus its write-ahead log, in bytes.
+fn database_size(db_path: &Path) -> u64 {
+    let mut wal = db_path.as_os_str().to_owned();
+    wal.push("-wal");
+    [db_path, Path::new(&wal)]
+        .iter()
+        .filter_map(|path| std::fs::metadata(path).ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::Marker;
+    use crate::tasks::{Priority, TaskStore};
+    use crate::traits::StateStore;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_run_reports_and_keeps_search_working() {
+        let dir = TempDir::new().unwrap();
+        let tasks = SqliteTaskStore::for_project(dir.path()).unwrap();
+        let task = tasks.create_task("Speed up the parser", "", Priority::High).unwrap();
+
+        let report = run(dir.path()).unwrap();
+        assert_eq!(report.indexes_rebuilt, 4);
+        assert_eq!(report.markers_pruned, 0);
+        assert_eq!(report.recovered, None);
+        assert!(report.size_bytes > 0);
+        assert_eq!(tasks.search_tasks("parser").unwrap()[0].id, task.id);
+    }
+
+    #[test]
+    fn test_run_if_due_is_rate_limited() {
+        let dir = TempDir::new().unwrap();
+        assert_eq!(run_if_due(dir.path()), None);
+        assert!(!paths::project_db_path(dir.path()).exists());
+
+        let state = SqliteStore::new(dir.path()).unwrap();
+        state.set_marker(Marker::NeedsValidation).unwrap();
+        let conn = rusqlite::Connection::open(state.db_path()).unwrap();
+        conn.execute(
+            "UPDATE session_state SET needs_validation_at = datetime('now', '-2 days')",
+            [],
+        )
+        .unwrap();
+
+        let report = run_if_due(dir.path()).unwrap();
+        assert_eq!(report.markers_pruned, 1);
+        assert_eq!(run_if_due(dir.path()), None);
+    }
+}
@@ -0,0 +1,317 @@
+//! Metrics export for monitoring long autonomous runs.
+//!
+//! When `metrics_file` is set in the project config, hooks record counters and
+//! histograms in the project database and rewrite the metrics file in the
+//! Prometheus text format after every hook call. Point node exporter's
+//! textfile collector at it (or run `claude-reliability metrics`) to graph
+//! stop blocks by reason, quality check durations, sub-agent latency, and
+//! work items opened and closed (e.g. `delta(..._work_items_closed[1d])`).
+//! Work item counts are read from the task list when the file is written, so
+//! they are gauges: deleting work items lowers them.
+
+use crate::config::ProjectConfig;
+use crate::error::Result;
+use crate::storage::SqliteStore;
+use crate::tasks::{self, TaskFilter, TaskStore};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+/// Bucket bounds (seconds) for duration histograms.
+const DURATION_BUCKETS: &[f64] = &[0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0];
+
+/// The type of a metric.
+#[derive(Debug, Clone, Copy)]
+pub enum MetricKind {
+    /// A monotonically increasing count.
+    Counter,
+    /// A distribution of observations with the given bucket bounds.
+    Histogram(&'static [f64]),
+}
+
+/// A metric recorded by the hooks.
+#[derive(Debug)]
+pub struct Metric {
+    /// The metric name.
+    pub name: &'static str,
+    /// Description shown in the `# HELP` line.
+    pub help: &'static str,
+    /// The metric type.
+    pub kind: MetricKind,
+}
+
+/// Stop hook decisions, labelled by `decision` and the check that decided.
+pub const STOP_DECISIONS: Metric = Metric {
+    name: "claude_reliability_stop_decisions_total",
+    help: "Stop hook decisions by outcome and deciding check.",
+    kind: MetricKind::Counter,
+};
+
+/// Quality check durations, labelled by `outcome`.
+pub const QUALITY_CHECK_DURATION: Metric = Metric {
+    name: "claude_reliability_quality_check_duration_seconds",
+    help: "Time taken by the quality check command.",
+    kind: MetricKind::Histogram(DURATION_BUCKETS),
+};
+
+/// Sub-agent call durations, labelled by `operation` and `success`.
+pub const SUBAGENT_DURATION: Metric = Metric {
+    name: "claude_reliability_subagent_duration_seconds",
+    help: "Time taken by sub-agent calls.",
+    kind: MetricKind::Histogram(DURATION_BUCKETS),
+};
+
+/// Metrics recorded by the hooks, in output order.
+const RECORDED_METRICS: &[&Metric] =
+    &[&STOP_DECISIONS, &QUALITY_CHECK_DURATION, &SUBAGENT_DURATION];
+
+/// Get the metrics file for a project, or `None` if metrics are disabled.
+#[must_use]
+pub fn metrics_file(base_dir: &Path) -> Option<PathBuf> {
+    let config = ProjectConfig::load_cached(base_dir)?;
+    config.metrics_file.as_deref().filter(|f| !f.trim().is_empty()).map(|f| base_dir.join(f))
+}
+
+/// Render a label set, escaping values.
+fn render_labels(labels: &[(&str, &str)]) -> String {
+    labels
+        .iter()
+        .map(|(name, value)| {
+            let value = value.replace('\\', r"\\").replace('"', r#"\""#).replace('\n', r"\n");
+            format!(r#"{name}="{value}""#)
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Increment a counter if metrics are enabled.
+///
+/// Errors are ignored — metrics should never break a hook.
+pub fn increment(base_dir: &Path, metric: &Metric, labels: &[(&str, &str)]) {
+    if metrics_file(base_dir).is_none() {
+        return;
+    }
+    if let Ok(store) = SqliteStore::new(base_dir) {
+        let _ = store.increment_counter(metric.name, &render_labels(labels), 1.0);
+    }
+}
+
+/// Record an observation in a histogram if metrics are enabled.
+///
+/// Errors are ignored — metrics should never break a hook.
+pub fn observe(base_dir: &Path, metric: &Metric, labels: &[(&str, &str)], value: f64) {
+    let MetricKind::Histogram(bounds) = metric.kind else {
+        return;
+    };
+    if metrics_file(base_dir).is_none() {
+        return;
+    }
+    if let Ok(store) = SqliteStore::new(base_dir) {
+        let _ = store.observe_histogram(metric.name, &render_labels(labels), bounds, value);
+    }
+}
+
+/// Format a sample line.
+fn sample(out: &mut String, name: &str, labels: &str, value: impl std::fmt::Display) {
+    if labels.is_empty() {
+        let _ = writeln!(out, "{name} {value}");
+    } else {
+        let _ = writeln!(out, "{name}{{{labels}}} {value}");
+    }
+}
+
+/// Format the `# HELP` and `# TYPE` lines for a metric.
+fn header(out: &mut String, name: &str, help: &str, kind: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+}
+
+/// Render the recorded metrics.
+fn render_recorded(out: &mut String, store: &SqliteStore) -> Result<()> {
+    let counters = store.counters()?;
+    let histograms = store.histograms()?;
+    for metric in RECORDED_METRICS {
+        match metric.kind {
+            MetricKind::Counter => {
+                header(out, metric.name, metric.help, "counter");
+                for (_, labels, value) in counters.iter().filter(|(n, _, _)| n == metric.name) {
+                    sample(out, metric.name, labels, value);
+                }
+            }
+            MetricKind::Histogram(bounds) => {
+                header(out, metric.name, metric.help, "histogram");
+                for h in histograms.iter().filter(|h| h.name == metric.name) {
+                    let bucket_name = format!("{}_bucket", metric.name);
+                    let with_le = |le: &str| {
+                        let le = format!(r#"le="{le}""#);
+                        if h.labels.is_empty() {
+                            le
+                        } else {
+                            format!("{},{le}", h.labels)
+                        }
+                    };
+                    for (bound, count) in bounds.iter().zip(&h.buckets) {
+                        sample(out, &bucket_name, &with_le(&bound.to_string()), count);
+                    }
+                    sample(out, &bucket_name, &with_le("+Inf"), h.count);
+                    sample(out, &format!("{}_sum", metric.name), &h.labels, h.sum);
+                    sample(out, &format!("{}_count", metric.name), &h.labels, h.count);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Render work item counts from the task database.
+fn render_work_items(out: &mut String, base_dir: &Path) {
+    let Some(store) = tasks::open_project_store(base_dir) else {
+        return;
+    };
+    let Ok(tasks) = store.list_tasks(TaskFilter::default()) else {
+        return;
+    };
+
+    let name = "claude_reliability_work_items_created";
+    header(out, name, "Work items in the task list, open or closed.", "gauge");
+    sample(out, name, "", tasks.len());
+
+    let name = "claude_reliability_work_items_closed";
+    header(out, name, "Work items in the task list that are completed or abandoned.", "gauge");
+    sample(out, name, "", tasks.iter().filter(|t| t.is_closed()).count());
+
+    let name = "claude_reliability_work_items";
+    header(out, name, "Work items by status.", "gauge");
+    let mut statuses: Vec<&str> = tasks.iter().map(|t| t.status.as_str()).collect();
+    statuses.sort_unstable();
+    statuses.dedup();
+    for status in statuses {
+        let count = tasks.iter().filter(|t| t.status.as_str() == status).count();
+        sample(out, name, &render_labels(&[("status", status)]), count);
+    }
+}
+
+/// Render all metrics for a project in the Prometheus text format.
+///
+/// # Errors
+///
+/// Returns an error if the recorded metrics cannot be read.
+pub fn render(base_dir: &Path) -> Result<String> {
+    let mut out = String::new();
+    render_recorded(&mut out, &SqliteStore::new(base_dir)?)?;
+    render_work_items(&mut out, base_dir);
+    Ok(out)
+}
+
+/// Rewrite the metrics file if metrics are enabled.
+///
+/// The file is replaced atomically so collectors never read a partial file.
+///
+/// # Errors
+///
+/// Returns an error if the metrics cannot be rendered or written.
+pub fn export(base_dir: &Path) -> Result<()> {
+    let Some(path) = metrics_file(base_dir) else {
+        return Ok(());
+    };
+    let content = render(base_dir)?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut tmp = path.clone().into_os_string();
+    tmp.push(".tmp");
+    std::fs::write(&tmp, content)?;
+    std::fs::rename(&tmp, &path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tasks::{Priority, Status, TaskUpdate};
+    use tempfile::TempDir;
+
+    fn enable(dir: &TempDir) {
+        let config =
+            ProjectConfig { metrics_file: Some("metrics.prom".to_string()), ..Default::default() };
+        config.save_to(dir.path()).unwrap();
+    }
+
+    #[test]
+    fn test_disabled_by_default() {
+        let dir = TempDir::new().unwrap();
+        assert!(metrics_file(dir.path()).is_none());
+        increment(dir.path(), &STOP_DECISIONS, &[("decision", "block")]);
+        export(dir.path()).unwrap();
+        assert!(!dir.path().join(".claude-reliability").exists());
+    }
+
+    #[test]
+    fn test_render_labels_escapes_values() {
+        assert_eq!(
+            render_labels(&[("a", "x"), ("b", "say \"hi\"\n")]),
+            r#"a="x",b="say \"hi\"\n""#
+        );
+    }
+
+    #[test]
+    fn test_render_counters_and_histograms() {
+        let dir = TempDir::new().unwrap();
+        enable(&dir);
+        let labels = [("decision", "block"), ("reason", "uncommitted_changes")];
+        increment(dir.path(), &STOP_DECISIONS, &labels);
+        increment(dir.path(), &STOP_DECISIONS, &labels);
+        observe(dir.path(), &SUBAGENT_DURATION, &[("operation", "code_review")], 3.0);
+
+        let text = render(dir.path()).unwrap();
+        assert!(text.contains("# TYPE claude_reliability_stop_decisions_total counter\n"));
+        assert!(text.contains(
+            "claude_reliability_stop_decisions_total{decision=\"block\",reason=\"uncommitted_changes\"} 2\n"
+        ));
+        assert!(text.contains(
+            "claude_reliability_subagent_duration_seconds_bucket{operation=\"code_review\",le=\"2.5\"} 0\n"
+        ));
+        assert!(text.contains(
+            "claude_reliability_subagent_duration_seconds_bucket{operation=\"code_review\",le=\"5\"} 1\n"
+        ));
+        assert!(text.contains(
+            "claude_reliability_subagent_duration_seconds_bucket{operation=\"code_review\",le=\"+Inf\"} 1\n"
+        ));
+        assert!(text.contains(
+            "claude_reliability_subagent_duration_seconds_sum{operation=\"code_review\"} 3\n"
+        ));
+    }
+
+    #[test]
+    fn test_render_work_items() {
+        let dir = TempDir::new().unwrap();
+        let db_path = crate::paths::project_db_path(dir.path());
+        std::fs::create_dir_all(db_path.parent().unwrap()).unwrap();
+        let store = tasks::SqliteTaskStore::new(&db_path).unwrap();
+        store.create_task("Open", "", Priority::Medium).unwrap();
+        let done = store.create_task("Done", "", Priority::Medium).unwrap();
+        store
+            .update_task(
+                &done.id,
+                TaskUpdate { status: Some(Status::Complete), ..Default::default() },
+            )
+            .unwrap();
+
+        let text = render(dir.path()).unwrap();
+        assert!(text.contains("# TYPE claude_reliability_work_items_created gauge\n"));
+        assert!(text.contains("claude_reliability_work_items_created 2\n"));
+        assert!(text.contains("claude_reliability_work_items_closed 1\n"));
+        assert!(text.contains("claude_reliability_work_items{status=\"open\"} 1\n"));
+    }
+
+    #[test]
+    fn test_export_writes_file() {
+        let dir = TempDir::new().unwrap();
+        enable(&dir);
+        increment(dir.path(), &STOP_DECISIONS, &[("decision", "allow")]);
+
+        export(dir.path()).unwrap();
+        let text = std::fs::read_to_string(dir.path().join("metrics.prom")).unwrap();
+        assert!(text.contains("claude_reliability_stop_decisions_total{decision=\"allow\"} 1\n"));
+        assert!(!dir.path().join("metrics.prom.tmp").exists());
+    }
+}
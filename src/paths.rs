@@ -1,9 +1,21 @@
 //! Path utilities for determining data storage locations.
 //!
 //! This module provides functions to determine where claude-reliability
-//! stores its data files. Data is stored in `<project>/.claude-reliability/`
-//! which keeps all plugin data within the project directory.
+//! stores its data files. By default data is stored in
+//! `<project>/.claude-reliability/` which keeps all plugin data within the
+//! project directory.
+//!
+//! The locations can be changed:
+//! - `CLAUDE_RELIABILITY_DIR` relocates the whole project data directory.
+//! - `CLAUDE_RELIABILITY_DB` relocates just the database, overriding the config.
+//! - The `db_path` config key relocates just the database, e.g. outside the
+//!   repository so it can never be committed. The value `user` keeps it in the
+//!   user data directory.
+//! - User-level state lives in `$XDG_DATA_HOME/claude-reliability/`
+//!   (`~/.local/share/claude-reliability/` if unset).
 
+use crate::config::ProjectConfig;
+use std::ffi::OsString;
 use std::path::{Path, PathBuf};
 
 /// The data directory name within a project.
@@ -12,34 +24,248 @@ const DATA_DIR_NAME: &str = ".claude-reliability";
 /// The database filename.
 pub const DATABASE_FILENAME: &str = "working-memory.sqlite3";
 
+/// Environment variable overriding the project data directory.
+pub const DATA_DIR_ENV: &str = "CLAUDE_RELIABILITY_DIR";
+
+/// Environment variable overriding the database path.
+pub const DB_PATH_ENV: &str = "CLAUDE_RELIABILITY_DB";
+
+/// The directory name for user-level state within the XDG data directory.
+const USER_DIR_NAME: &str = "claude-reliability";
+
+/// The `db_path` value that keeps the database in the user data directory.
+pub const USER_DB_PATH: &str = "user";
+
 /// Get the project-specific data directory.
 ///
-/// Returns `<project_dir>/.claude-reliability/`.
+/// Returns `$CLAUDE_RELIABILITY_DIR` if set (relative values are resolved
+/// against the project), otherwise `<project_dir>/.claude-reliability/`.
 ///
 /// # Arguments
 ///
 /// * `project_dir` - The project directory to get data dir for.
 #[must_use]
 pub fn project_data_dir(project_dir: &Path) -> PathBuf {
-    project_dir.join(DATA_DIR_NAME)
+    resolve_data_dir(project_dir, std::env::var_os(DATA_DIR_ENV))
+}
+
+/// Resolve the project data directory given the value of `CLAUDE_RELIABILITY_DIR`.
+fn resolve_data_dir(project_dir: &Path, override_dir: Option<OsString>) -> PathBuf {
+    let dir = override_dir
+        .filter(|d| !d.is_empty())
+        .map_or_else(|| PathBuf::from(DATA_DIR_NAME), |d| expand_home(Path::new(&d)));
+    project_dir.join(dir)
 }
 
 /// Get the database path for a project.
 ///
-/// Returns `<project_dir>/.claude-reliability/working-memory.sqlite3`.
+/// Returns `$CLAUDE_RELIABILITY_DB` if set, then the `db_path` from the
+/// project config if set, otherwise `<project data dir>/working-memory.sqlite3`.
 ///
 /// # Arguments
 ///
 /// * `project_dir` - The project directory to get database path for.
 #[must_use]
 pub fn project_db_path(project_dir: &Path) -> PathBuf {
-    project_data_dir(project_dir).join(DATABASE_FILENAME)
+    let configured =
+        std::env::var(DB_PATH_ENV).ok().filter(|p| !p.trim().is_empty()).or_else(|| {
+            ProjectConfig::load_cached(project_dir).and_then(|config| config.db_path.clone())
+        });
+    resolve_db_path(project_dir, configured.as_deref())
+}
+
+/// Resolve the database path given the configured `db_path`, if any.
+fn resolve_db_path(project_dir: &Path, configured: Option<&str>) -> PathBuf {
+    match configured.map(str::trim).filter(|p| !p.is_empty()) {
+        Some(USER_DB_PATH) => user_project_dir(project_dir)
+            .unwrap_or_else(|| project_data_dir(project_dir))
+            .join(DATABASE_FILENAME),
+        Some(path) => project_dir.join(expand_home(Path::new(path))),
+        None => project_data_dir(project_dir).join(DATABASE_FILENAME),
+    }
+}
+
+/// Get the directory session handoff summaries are written to.
+///
+/// Returns `<project data dir>/handoffs/`.
+///
+/// # Arguments
+///
+/// * `project_dir` - The project directory to get the handoffs dir for.
+#[must_use]
+pub fn project_handoffs_dir(project_dir: &Path) -> PathBuf {
+    project_data_dir(project_dir).join("handoffs")
+}
+
+/// Get the directory hook log files are written to.
+///
+/// Returns `<project data dir>/logs/`.
+///
+/// # Arguments
+///
+/// * `project_dir` - The project directory to get the logs dir for.
+#[must_use]
+pub fn project_logs_dir(project_dir: &Path) -> PathBuf {
+    project_data_dir(project_dir).join("logs")
+}
+
+/// Get the directory hook timing profiles are written to.
+///
+/// Returns `<project data dir>/profiles/`.
+///
+/// # Arguments
+///
+/// * `project_dir` - The project directory to get the profiles dir for.
+#[must_use]
+pub fn project_profiles_dir(project_dir: &Path) -> PathBuf {
+    project_data_dir(project_dir).join("profiles")
+}
+
+/// Get the directory hook crash reports are written to.
+///
+/// Returns `<project data dir>/crashes/`.
+///
+/// # Arguments
+///
+/// * `project_dir` - The project directory to get the crashes dir for.
+#[must_use]
+pub fn project_crashes_dir(project_dir: &Path) -> PathBuf {
+    project_data_dir(project_dir).join("crashes")
+}
+
+/// Get the directory database backups are kept in.
+///
+/// Returns `<project data dir>/backups/`.
+///
+/// # Arguments
+///
+/// * `project_dir` - The project directory to get the backups dir for.
+#[must_use]
+pub fn project_backups_dir(project_dir: &Path) -> PathBuf {
+    project_data_dir(project_dir).join("backups")
+}
+
+/// Get the directory `audit-log export` writes to by default.
+///
+/// Returns `<project data dir>/exports/`.
+///
+/// # Arguments
+///
+/// * `project_dir` - The project directory to get the exports dir for.
+#[must_use]
+pub fn project_exports_dir(project_dir: &Path) -> PathBuf {
+    project_data_dir(project_dir).join("exports")
+}
+
+/// Get the file the untracked file list is cached in between stop hook runs.
+///
+/// Returns `<project data dir>/untracked-cache.json`.
+///
+/// # Arguments
+///
+/// * `project_dir` - The project directory to get the cache file for.
+#[must_use]
+pub fn project_untracked_cache_path(project_dir: &Path) -> PathBuf {
+    project_data_dir(project_dir).join("untracked-cache.json")
+}
+
+/// Get the directory project overrides of the message templates are read from.
+///
+/// Returns `<project data dir>/templates/`.
+///
+/// # Arguments
+///
+/// * `project_dir` - The project directory to get the templates dir for.
+#[must_use]
+pub fn project_templates_dir(project_dir: &Path) -> PathBuf {
+    project_data_dir(project_dir).join("templates")
+}
+
+/// Get the heartbeat file touched to signal that the user is active.
+///
+/// Returns the configured `idle_heartbeat_file` if set (relative paths are
+/// resolved against the project), otherwise `<project data dir>/heartbeat`.
+///
+/// # Arguments
+///
+/// * `project_dir` - The project directory to get the heartbeat file for.
+/// * `configured` - The configured heartbeat file, if any.
+#[must_use]
+pub fn project_heartbeat_path(project_dir: &Path, configured: Option<&str>) -> PathBuf {
+    configured.map(str::trim).filter(|p| !p.is_empty()).map_or_else(
+        || project_data_dir(project_dir).join("heartbeat"),
+        |path| project_dir.join(expand_home(Path::new(path))),
+    )
+}
+
+/// Get the directory for user-level state.
+///
+/// Returns `$XDG_DATA_HOME/claude-reliability/`, falling back to
+/// `~/.local/share/claude-reliability/`. Returns `None` if neither
+/// `XDG_DATA_HOME` nor the home directory is known.
+#[must_use]
+pub fn user_data_dir() -> Option<PathBuf> {
+    resolve_user_data_dir(std::env::var_os("XDG_DATA_HOME"), dirs::home_dir())
+}
+
+/// Resolve the user data directory from `XDG_DATA_HOME` and the home directory.
+///
+/// Per the XDG base directory spec, relative values of `XDG_DATA_HOME` are ignored.
+fn resolve_user_data_dir(
+    xdg_data_home: Option<OsString>,
+    home: Option<PathBuf>,
+) -> Option<PathBuf> {
+    let data_home = xdg_data_home
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or_else(|| home.map(|h| h.join(".local").join("share")))?;
+    Some(data_home.join(USER_DIR_NAME))
+}
+
+/// Get the user-level directory for a project's state.
+///
+/// Returns `<user data dir>/projects/<key>/`, where the key is the project's
+/// absolute path with separators replaced by `-`.
+#[must_use]
+pub fn user_project_dir(project_dir: &Path) -> Option<PathBuf> {
+    let project = std::fs::canonicalize(project_dir).unwrap_or_else(|_| project_dir.to_path_buf());
+    Some(user_data_dir()?.join("projects").join(project_key(&project)))
+}
+
+/// Turn a project path into a directory name.
+fn project_key(project: &Path) -> String {
+    project.to_string_lossy().replace(['/', '\\', ':'], "-")
+}
+
+/// Expand a leading `~` to the home directory.
+fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), dirs::home_dir()) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => path.to_path_buf(),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_project_heartbeat_path() {
+        let project = PathBuf::from("/some/project/path");
+        assert_eq!(
+            project_heartbeat_path(&project, None),
+            project_data_dir(&project).join("heartbeat")
+        );
+        assert_eq!(
+            project_heartbeat_path(&project, Some("tmp/active")),
+            PathBuf::from("/some/project/path/tmp/active")
+        );
+        assert_eq!(
+            project_heartbeat_path(&project, Some("/var/run/active")),
+            PathBuf::from("/var/run/active")
+        );
+    }
+
     #[test]
     fn test_project_data_dir_is_within_project() {
         let project = PathBuf::from("/some/project/path");
@@ -47,6 +273,23 @@ mod tests {
         assert_eq!(dir, PathBuf::from("/some/project/path/.claude-reliability"));
     }
 
+    #[test]
+    fn test_project_data_dir_override() {
+        let project = Path::new("/some/project/path");
+        assert_eq!(
+            resolve_data_dir(project, Some("/var/lib/reliability".into())),
+            PathBuf::from("/var/lib/reliability")
+        );
+        assert_eq!(
+            resolve_data_dir(project, Some("state".into())),
+            PathBuf::from("/some/project/path/state")
+        );
+        assert_eq!(
+            resolve_data_dir(project, Some(OsString::new())),
+            PathBuf::from("/some/project/path/.claude-reliability")
+        );
+    }
+
     #[test]
     fn test_project_db_path_ends_with_filename() {
         let project = PathBuf::from("/some/project/path");
@@ -56,4 +299,80 @@ mod tests {
             PathBuf::from("/some/project/path/.claude-reliability/working-memory.sqlite3")
         );
     }
+
+    #[test]
+    fn test_resolve_db_path() {
+        let project = Path::new("/some/project/path");
+        assert_eq!(
+            resolve_db_path(project, Some("/tmp/memory.sqlite3")),
+            PathBuf::from("/tmp/memory.sqlite3")
+        );
+        assert_eq!(
+            resolve_db_path(project, Some("../memory.sqlite3")),
+            PathBuf::from("/some/project/path/../memory.sqlite3")
+        );
+        assert_eq!(resolve_db_path(project, Some(" ")), resolve_db_path(project, None));
+        if let Some(home) = dirs::home_dir() {
+            assert_eq!(resolve_db_path(project, Some("~/db.sqlite3")), home.join("db.sqlite3"));
+        }
+    }
+
+    #[test]
+    fn test_resolve_db_path_in_user_dir() {
+        let project = Path::new("/some/project/path");
+        if let Some(dir) = user_project_dir(project) {
+            assert_eq!(resolve_db_path(project, Some("user")), dir.join(DATABASE_FILENAME));
+            assert!(dir.ends_with("claude-reliability/projects/-some-project-path"));
+        }
+    }
+
+    #[test]
+    fn test_project_db_path_from_config() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let config =
+            ProjectConfig { db_path: Some("../outside.sqlite3".to_string()), ..Default::default() };
+        config.save_to(dir.path()).unwrap();
+        assert_eq!(project_db_path(dir.path()), dir.path().join("../outside.sqlite3"));
+    }
+
+    #[test]
+    fn test_resolve_user_data_dir() {
+        assert_eq!(
+            resolve_user_data_dir(Some("/xdg/data".into()), Some("/home/u".into())),
+            Some(PathBuf::from("/xdg/data/claude-reliability"))
+        );
+        // Relative XDG_DATA_HOME is ignored
+        assert_eq!(
+            resolve_user_data_dir(Some("data".into()), Some("/home/u".into())),
+            Some(PathBuf::from("/home/u/.local/share/claude-reliability"))
+        );
+        assert_eq!(
+            resolve_user_data_dir(None, Some("/home/u".into())),
+            Some(PathBuf::from("/home/u/.local/share/claude-reliability"))
+        );
+        assert_eq!(resolve_user_data_dir(None, None), None);
+    }
+
+    #[test]
+    fn test_project_key() {
+        assert_eq!(project_key(Path::new("/root/crate")), "-root-crate");
+    }
+
+    #[test]
+    fn test_project_handoffs_dir() {
+        let project = PathBuf::from("/some/project/path");
+        assert_eq!(
+            project_handoffs_dir(&project),
+            PathBuf::from("/some/project/path/.claude-reliability/handoffs")
+        );
+    }
+
+    #[test]
+    fn test_project_templates_dir() {
+        let project = PathBuf::from("/some/project/path");
+        assert_eq!(
+            project_templates_dir(&project),
+            PathBuf::from("/some/project/path/.claude-reliability/templates")
+        );
+    }
 }
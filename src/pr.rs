@@ -0,0 +1,459 @@
+//! Pull request lifecycle support.
+//!
+//! When enabled, the stop hook requires that a feature branch whose work is
+//! complete and pushed has a pull request. If none exists, one is created with
+//! `gh pr create` using a sub-agent-written description that references the
+//! work items the branch completed: those its commits refer to, and requested
+//! items finished since its first commit. The pull request URL is then
+//! recorded as a note on those work items. A branch with no commits of its
+//! own has nothing to review, so no pull request is needed.
+
+use crate::error::Result;
+use crate::hooks::referenced_work_items;
+use crate::tasks::{self, Status, Task, TaskFilter, TaskStore};
+use crate::traits::{CommandOutput, CommandRunner, PrDescription, PrDescriptionContext, SubAgent};
+use chrono::DateTime;
+use std::collections::HashSet;
+use std::path::Path;
+
+/// Branches that pull requests target rather than come from.
+pub const BASE_BRANCHES: &[&str] = &["main", "master"];
+
+/// Maximum number of commit subjects passed to the sub-agent.
+const MAX_COMMITS: usize = 20;
+
+/// What `gh pr create` says when the branch has no commits of its own.
+const NO_COMMITS_ERRORS: [&str; 2] = ["no commits between", "could not find any commits"];
+
+/// Prefix of the note recording a pull request on a work item.
+const PR_NOTE_PREFIX: &str = "Pull request: ";
+
+/// Outcome of making sure a pull request exists.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PrStatus {
+    /// A pull request already existed.
+    Existing(String),
+    /// A pull request was created.
+    Created(String),
+    /// The branch has no commits the base branch lacks, so there is nothing to review.
+    NothingToReview,
+    /// A pull request could not be created.
+    Failed(String),
+}
+
+/// Find the URL of the pull request for the current branch.
+///
+/// # Errors
+///
+/// Returns an error if `gh` cannot be run.
+pub fn find_pr_url(runner: &dyn CommandRunner) -> Result<Option<String>> {
+    let output = runner.run("gh", &["pr", "view", "--json", "url", "--jq", ".url"], None)?;
+    let url = output.stdout.trim();
+    Ok((output.success() && !url.is_empty()).then(|| url.to_string()))
+}
+
+/// Create a pull request for the current branch.
+///
+/// Returns the new pull request's URL, or the error output on failure.
+///
+/// # Errors
+///
+/// Returns an error if `gh` cannot be run.
+pub fn create_pr(
+    runner: &dyn CommandRunner,
+    description: &PrDescription,
+) -> Result<std::result::Result<String, String>> {
+    let output = runner.run(
+        "gh",
+        &["pr", "create", "--title", &description.title, "--body", &description.body],
+        None,
+    )?;
+    if !output.success() {
+        return Ok(Err(output.combined_output()));
+    }
+    Ok(output
+        .stdout
+        .lines()
+        .map(str::trim)
+        .rfind(|line| line.starts_with("http"))
+        .map(String::from)
+        .ok_or_else(|| output.combined_output()))
+}
+
+/// A commit on the current branch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct BranchCommit {
+    /// Commit time, in seconds since the Unix epoch.
+    time: i64,
+    /// The full commit message.
+    message: String,
+}
+
+impl BranchCommit {
+    /// The first line of the message.
+    fn subject(&self) -> &str {
+        self.message.lines().next().unwrap_or_default().trim()
+    }
+}
+
+/// Find the branch pull requests target: the remote's default branch, or
+/// the first of [`BASE_BRANCHES`] that exists.
+fn base_ref(runner: &dyn CommandRunner) -> Option<String> {
+    let succeeded = |args: &[&str]| {
+        runner.run("git", args, None).ok().filter(CommandOutput::success).map(|o| o.stdout)
+    };
+    if let Some(head) = succeeded(&["symbolic-ref", "--short", "-q", "refs/remotes/origin/HEAD"]) {
+        return Some(head.trim().to_string());
+    }
+    BASE_BRANCHES
+        .iter()
+        .flat_map(|branch| [format!("origin/{branch}"), (*branch).to_string()])
+        .find(|candidate| succeeded(&["rev-parse", "--verify", "-q", candidate]).is_some())
+}
+
+/// Get the commits on the current branch that its base lacks, newest first.
+///
+/// Returns `None` if there is no base branch to compare with.
+fn branch_commits(runner: &dyn CommandRunner) -> Option<Vec<BranchCommit>> {
+    let base = base_ref(runner)?;
+    let output = runner
+        .run("git", &["log", "--format=%ct%x1f%B%x1e", "HEAD", "--not", &base], None)
+        .ok()
+        .filter(CommandOutput::success)?;
+    Some(
+        output
+            .stdout
+            .split('\x1e')
+            .filter_map(|record| {
+                let (time, message) = record.trim_start().split_once('\x1f')?;
+                let time = time.parse().ok()?;
+                Some(BranchCommit { time, message: message.trim().to_string() })
+            })
+            .collect(),
+    )
+}
+
+/// Get the finished work items the branch addresses.
+///
+/// These are the items its commits refer to, and the requested items
+/// finished since its first commit.
+fn branch_work_items<S: TaskStore + ?Sized>(store: &S, commits: &[BranchCommit]) -> Vec<Task> {
+    let referenced: HashSet<String> =
+        commits.iter().flat_map(|c| referenced_work_items(&c.message)).collect();
+    // Task timestamps are SQLite's `datetime('now')`, which compare as text
+    let since = commits
+        .iter()
+        .map(|c| c.time)
+        .min()
+        .and_then(|time| DateTime::from_timestamp(time, 0))
+        .map(|time| time.format("%Y-%m-%d %H:%M:%S").to_string());
+    [Status::Complete, Status::InReview]
+        .into_iter()
+        .flat_map(|status| {
+            store
+                .list_tasks(TaskFilter { status: Some(status), ..Default::default() })
+                .unwrap_or_default()
+        })
+        .filter(|t| {
+            referenced.contains(&t.id)
+                || (t.requested && since.as_ref().is_some_and(|since| &t.updated_at >= since))
+        })
+        .collect()
+}
+
+/// Record the pull request URL on each work item that does not have it yet.
+fn record_pr_url<S: TaskStore + ?Sized>(store: &S, tasks: &[Task], url: &str) {
+    let note = format!("{PR_NOTE_PREFIX}{url}");
+    for task in tasks {
+        let already_recorded =
+            store.get_notes(&task.id).is_ok_and(|notes| notes.iter().any(|n| n.content == note));
+        if !already_recorded {
+            let _ = store.add_note(&task.id, &note);
+        }
+    }
+}
+
+/// Make sure the current branch has a pull request, creating one if needed,
+/// and record its URL on the work items the branch addresses.
+///
+/// # Errors
+///
+/// Returns an error if `gh` cannot be run.
+pub fn ensure_pr(
+    runner: &dyn CommandRunner,
+    sub_agent: &dyn SubAgent,
+    base_dir: &Path,
+    branch: &str,
+) -> Result<PrStatus> {
+    let existing = find_pr_url(runner)?;
+    let commits = branch_commits(runner);
+    if existing.is_none() && commits.as_ref().is_some_and(Vec::is_empty) {
+        return Ok(PrStatus::NothingToReview);
+    }
+    let commits = commits.unwrap_or_default();
+    let store = tasks::open_project_store(base_dir);
+    let work_items =
+        store.as_ref().map(|store| branch_work_items(store, &commits)).unwrap_or_default();
+
+    let status = if let Some(url) = existing {
+        PrStatus::Existing(url)
+    } else {
+        let context = PrDescriptionContext {
+            branch: branch.to_string(),
+            commits: commits.iter().take(MAX_COMMITS).map(|c| c.subject().to_string()).collect(),
+            work_items: work_items.iter().map(|t| t.title.clone()).collect(),
+        };
+        let description = match sub_agent.write_pr_description(&context) {
+            Ok(description) => description,
+            Err(e) => return Ok(PrStatus::Failed(format!("Could not write a description: {e}"))),
+        };
+        match create_pr(runner, &description)? {
+            Ok(url) => PrStatus::Created(url),
+            Err(output) if is_no_commits_error(&output) => return Ok(PrStatus::NothingToReview),
+            Err(output) => return Ok(PrStatus::Failed(output)),
+        }
+    };
+
+    if let (PrStatus::Existing(url) | PrStatus::Created(url), Some(store)) = (&status, &store) {
+        record_pr_url(store, &work_items, url);
+    }
+    Ok(status)
+}
+
+/// Whether `gh pr create` failed because the branch has no commits of its own.
+fn is_no_commits_error(output: &str) -> bool {
+    let output = output.to_lowercase();
+    NO_COMMITS_ERRORS.iter().any(|error| output.contains(error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::paths;
+    use crate::tasks::{Priority, SqliteTaskStore, TaskUpdate};
+    use crate::testing::{FailingSubAgent, MockCommandRunner, MockSubAgent};
+    use tempfile::TempDir;
+
+    fn output(exit_code: i32, stdout: &str) -> CommandOutput {
+        CommandOutput { exit_code, stdout: stdout.to_string(), stderr: String::new() }
+    }
+
+    fn expect_pr_view(runner: &mut MockCommandRunner, result: CommandOutput) {
+        runner.expect("gh", &["pr", "view", "--json", "url", "--jq", ".url"], result);
+    }
+
+    /// Expect the base branch lookup and the log of the branch's commits.
+    fn expect_branch_log(runner: &mut MockCommandRunner, log: &str) {
+        runner.expect(
+            "git",
+            &["symbolic-ref", "--short", "-q", "refs/remotes/origin/HEAD"],
+            output(0, "origin/main\n"),
+        );
+        runner.expect(
+            "git",
+            &["log", "--format=%ct%x1f%B%x1e", "HEAD", "--not", "origin/main"],
+            output(0, log),
+        );
+    }
+
+    fn completed_requested_task(dir: &TempDir, title: &str) -> Task {
+        let db_path = paths::project_db_path(dir.path());
+        std::fs::create_dir_all(db_path.parent().unwrap()).unwrap();
+        let store = SqliteTaskStore::new(&db_path).unwrap();
+        let task = store.create_task(title, "", Priority::Medium).unwrap();
+        store.request_tasks(&[&task.id]).unwrap();
+        store
+            .update_task(
+                &task.id,
+                TaskUpdate { status: Some(Status::Complete), ..Default::default() },
+            )
+            .unwrap()
+            .unwrap()
+    }
+
+    fn notes_for(dir: &TempDir, task: &Task) -> Vec<String> {
+        let store = SqliteTaskStore::new(paths::project_db_path(dir.path())).unwrap();
+        store.get_notes(&task.id).unwrap().into_iter().map(|n| n.content).collect()
+    }
+
+    #[test]
+    fn test_find_pr_url() {
+        let mut runner = MockCommandRunner::new();
+        expect_pr_view(&mut runner, output(0, "https://github.com/o/r/pull/7\n"));
+        expect_pr_view(&mut runner, output(1, ""));
+
+        assert_eq!(
+            find_pr_url(&runner).unwrap(),
+            Some("https://github.com/o/r/pull/7".to_string())
+        );
+        assert_eq!(find_pr_url(&runner).unwrap(), None);
+    }
+
+    #[test]
+    fn test_create_pr_returns_url() {
+        let mut runner = MockCommandRunner::new();
+        runner.expect(
+            "gh",
+            &["pr", "create", "--title", "Add parser", "--body", "Body"],
+            output(0, "Creating pull request\nhttps://github.com/o/r/pull/8\n"),
+        );
+        let description =
+            PrDescription { title: "Add parser".to_string(), body: "Body".to_string() };
+
+        assert_eq!(
+            create_pr(&runner, &description).unwrap(),
+            Ok("https://github.com/o/r/pull/8".to_string())
+        );
+    }
+
+    #[test]
+    fn test_create_pr_failure() {
+        let mut runner = MockCommandRunner::new();
+        runner.expect(
+            "gh",
+            &["pr", "create", "--title", "t", "--body", "b"],
+            CommandOutput {
+                exit_code: 1,
+                stdout: String::new(),
+                stderr: "no commits between main and feature".to_string(),
+            },
+        );
+        let description = PrDescription { title: "t".to_string(), body: "b".to_string() };
+
+        let result = create_pr(&runner, &description).unwrap();
+        assert!(result.unwrap_err().contains("no commits"));
+    }
+
+    #[test]
+    fn test_ensure_pr_existing_records_url() {
+        let dir = TempDir::new().unwrap();
+        let task = completed_requested_task(&dir, "Write a parser");
+        let mut runner = MockCommandRunner::new();
+        for _ in 0..2 {
+            expect_pr_view(&mut runner, output(0, "https://github.com/o/r/pull/7\n"));
+            expect_branch_log(&mut runner, "1000000000\x1fAdd parser\n\x1e\n");
+        }
+        let sub_agent = MockSubAgent::new();
+
+        let status = ensure_pr(&runner, &sub_agent, dir.path(), "feature/parser").unwrap();
+        assert_eq!(status, PrStatus::Existing("https://github.com/o/r/pull/7".to_string()));
+        // Recording again does not duplicate the note
+        ensure_pr(&runner, &sub_agent, dir.path(), "feature/parser").unwrap();
+        assert_eq!(notes_for(&dir, &task), vec!["Pull request: https://github.com/o/r/pull/7"]);
+    }
+
+    #[test]
+    fn test_ensure_pr_creates_pr() {
+        let dir = TempDir::new().unwrap();
+        let task = completed_requested_task(&dir, "Write a parser");
+        let mut runner = MockCommandRunner::new();
+        expect_pr_view(&mut runner, output(1, ""));
+        expect_branch_log(&mut runner, "1000000000\x1fAdd tokenizer\n\nMore detail\n\x1e\n");
+        runner.expect(
+            "gh",
+            &["pr", "create", "--title", "Add parser", "--body", "Adds a parser."],
+            output(0, "https://github.com/o/r/pull/9\n"),
+        );
+        let mut sub_agent = MockSubAgent::new();
+        sub_agent.expect_pr_description("Add parser", "Adds a parser.");
+
+        let status = ensure_pr(&runner, &sub_agent, dir.path(), "feature/parser").unwrap();
+        assert_eq!(status, PrStatus::Created("https://github.com/o/r/pull/9".to_string()));
+        assert_eq!(notes_for(&dir, &task), vec!["Pull request: https://github.com/o/r/pull/9"]);
+        runner.verify();
+    }
+
+    #[test]
+    fn test_ensure_pr_description_failure() {
+        let dir = TempDir::new().unwrap();
+        let mut runner = MockCommandRunner::new();
+        expect_pr_view(&mut runner, output(1, ""));
+        runner.expect(
+            "git",
+            &["symbolic-ref", "--short", "-q", "refs/remotes/origin/HEAD"],
+            output(1, ""),
+        );
+        for candidate in ["origin/main", "main", "origin/master", "master"] {
+            runner.expect("git", &["rev-parse", "--verify", "-q", candidate], output(1, ""));
+        }
+        let sub_agent = FailingSubAgent::new("unavailable");
+
+        let status = ensure_pr(&runner, &sub_agent, dir.path(), "feature/parser").unwrap();
+        assert!(matches!(status, PrStatus::Failed(msg) if msg.contains("unavailable")));
+        runner.verify();
+    }
+
+    #[test]
+    fn test_ensure_pr_nothing_to_review() {
+        let dir = TempDir::new().unwrap();
+        let mut runner = MockCommandRunner::new();
+        expect_pr_view(&mut runner, output(1, ""));
+        expect_branch_log(&mut runner, "");
+        let sub_agent = MockSubAgent::new();
+
+        let status = ensure_pr(&runner, &sub_agent, dir.path(), "feature/parser").unwrap();
+        assert_eq!(status, PrStatus::NothingToReview);
+        runner.verify();
+
+        // gh can still find nothing to compare, e.g. against another base
+        let mut runner = MockCommandRunner::new();
+        expect_pr_view(&mut runner, output(1, ""));
+        expect_branch_log(&mut runner, "1000000000\x1fAdd parser\n\x1e\n");
+        runner.expect(
+            "gh",
+            &["pr", "create", "--title", "t", "--body", "b"],
+            CommandOutput {
+                exit_code: 1,
+                stdout: String::new(),
+                stderr: "pull request create failed: No commits between main and feature"
+                    .to_string(),
+            },
+        );
+        let mut sub_agent = MockSubAgent::new();
+        sub_agent.expect_pr_description("t", "b");
+
+        let status = ensure_pr(&runner, &sub_agent, dir.path(), "feature/parser").unwrap();
+        assert_eq!(status, PrStatus::NothingToReview);
+    }
+
+    #[test]
+    fn test_branch_work_items_are_scoped_to_the_branch() {
+        let dir = TempDir::new().unwrap();
+        let requested = completed_requested_task(&dir, "Write a parser");
+        let store = SqliteTaskStore::new(paths::project_db_path(dir.path())).unwrap();
+        let referenced = store.create_task("Fix tokenizer", "", Priority::Low).unwrap();
+        store
+            .update_task(
+                &referenced.id,
+                TaskUpdate { status: Some(Status::InReview), ..Default::default() },
+            )
+            .unwrap();
+        let unrelated = store.create_task("Old work", "", Priority::Low).unwrap();
+        store
+            .update_task(
+                &unrelated.id,
+                TaskUpdate { status: Some(Status::Complete), ..Default::default() },
+            )
+            .unwrap();
+
+        let commit = |time: i64, message: &str| BranchCommit { time, message: message.into() };
+        let ids = |commits: &[BranchCommit]| {
+            let mut ids: Vec<String> =
+                branch_work_items(&store, commits).into_iter().map(|t| t.id).collect();
+            ids.sort();
+            ids
+        };
+
+        // Requested work finished after the branch's first commit counts
+        let mut expected = vec![requested.id, referenced.id.clone()];
+        expected.sort();
+        assert_eq!(
+            ids(&[commit(1_000_000_000, &format!("Fix tokenizer\n\nCloses {}", referenced.id))]),
+            expected
+        );
+        // A branch started after the work was finished does not claim it
+        let future = chrono::Utc::now().timestamp() + 3600;
+        assert_eq!(ids(&[commit(future, "Tidy up")]), Vec::<String>::new());
+        assert!(ids(&[]).is_empty());
+    }
+}
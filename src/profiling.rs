@@ -0,0 +1,373 @@
+//! Timing profiles of hook runs (`--profile`).
+//!
+//! A hook run with `--profile` records how long it spent in each stop check,
+//! each subprocess it ran and each SQL statement, and writes the breakdown to
+//! `.claude-reliability/profiles/<hook>-<time>-<pid>.folded`. The file has one
+//! line per call stack, its frames separated by `;` and followed by the
+//! microseconds spent in that stack itself (not in the frames below it). This
+//! is the folded stack format read by `flamegraph.pl`, `inferno-flamegraph`
+//! and speedscope, and it is short enough to attach to a slow-hook report.
+//!
+//! Stack frames are the tracing spans the hook enters: a span's frame is its
+//! name and the value of its first field, such as
+//! `stop_check:uncommitted_changes` or `command:git status --porcelain`. SQL
+//! statements are reported by the database as they finish, and become `sql:`
+//! frames below the span they ran in.
+
+use crate::paths;
+use chrono::Utc;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+/// Target of the events that report a finished SQL statement.
+pub const SQL_TARGET: &str = "claude_reliability::sql";
+
+/// Longest a frame is kept, in characters. Long commands and statements are
+/// cut short so the stacks stay readable.
+const MAX_FRAME_CHARS: usize = 100;
+
+/// Whether a profile is being recorded, so that database connections report
+/// their statements.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Whether a profile is being recorded in this process.
+#[must_use]
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Report a finished SQL statement to the profile.
+///
+/// This is the profile callback given to database connections while a
+/// profile is being recorded.
+pub fn record_sql(sql: &str, elapsed: Duration) {
+    let elapsed_ns = u64::try_from(elapsed.as_nanos()).unwrap_or(u64::MAX);
+    tracing::debug!(target: SQL_TARGET, sql, elapsed_ns, "sql statement finished");
+}
+
+/// Self time in nanoseconds, by folded stack.
+type Samples = Arc<Mutex<BTreeMap<String, u64>>>;
+
+/// A profile being recorded.
+#[derive(Debug, Clone, Default)]
+pub struct Profiler {
+    samples: Samples,
+}
+
+impl Profiler {
+    /// Start recording a profile.
+    ///
+    /// Spans are recorded by the [`ProfileLayer`] from [`Profiler::layer`],
+    /// which must be part of the subscriber the hook runs under.
+    #[must_use]
+    pub fn start() -> Self {
+        ENABLED.store(true, Ordering::Relaxed);
+        Self::default()
+    }
+
+    /// The tracing layer that records into this profile.
+    #[must_use]
+    pub fn layer(&self) -> ProfileLayer {
+        ProfileLayer { samples: Arc::clone(&self.samples) }
+    }
+
+    /// The profile in folded stack format, one stack per line. Stacks that
+    /// took less than a microsecond in all are left out.
+    #[must_use]
+    pub fn folded(&self) -> String {
+        let samples = self.samples.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+        samples.iter().filter(|(_, nanos)| **nanos >= 1_000).fold(
+            String::new(),
+            |mut out, (stack, nanos)| {
+                let _ = writeln!(out, "{stack} {}", nanos / 1_000);
+                out
+            },
+        )
+    }
+
+    /// Write the profile to the project's profiles directory, returning the
+    /// file written.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory or file cannot be written.
+    pub fn write(&self, base_dir: &Path, hook: &str) -> std::io::Result<PathBuf> {
+        let dir = paths::project_profiles_dir(base_dir);
+        std::fs::create_dir_all(&dir)?;
+        let name = format!(
+            "{hook}-{}-{}.folded",
+            Utc::now().format("%Y%m%dT%H%M%S%.3f"),
+            std::process::id()
+        );
+        let path = dir.join(name);
+        std::fs::write(&path, self.folded())?;
+        Ok(path)
+    }
+}
+
+/// A tracing layer that records how long each span and SQL statement took.
+#[derive(Debug)]
+pub struct ProfileLayer {
+    samples: Samples,
+}
+
+impl ProfileLayer {
+    /// Add self time to a stack.
+    fn add(&self, stack: String, time: Duration) {
+        let nanos = u64::try_from(time.as_nanos()).unwrap_or(u64::MAX);
+        let mut samples = self.samples.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+        let total = samples.entry(stack).or_default();
+        *total = total.saturating_add(nanos);
+        drop(samples);
+    }
+}
+
+/// The timing kept on each open span.
+struct Timing {
+    frame: String,
+    start: Instant,
+    /// Time spent in spans and statements below this one.
+    children: Duration,
+}
+
+/// The frames of a span and the spans it is in, outermost first.
+fn frames<'a, S>(span: Option<tracing_subscriber::registry::SpanRef<'a, S>>) -> Vec<String>
+where
+    S: Subscriber + for<'l> LookupSpan<'l>,
+{
+    span.into_iter()
+        .flat_map(|span| span.scope().from_root())
+        .filter_map(|span| span.extensions().get::<Timing>().map(|t| t.frame.clone()))
+        .collect()
+}
+
+/// Add time to the children of a span, if it is being timed.
+fn add_child_time<S>(span: Option<tracing_subscriber::registry::SpanRef<'_, S>>, time: Duration)
+where
+    S: Subscriber + for<'l> LookupSpan<'l>,
+{
+    if let Some(span) = span {
+        if let Some(timing) = span.extensions_mut().get_mut::<Timing>() {
+            timing.children += time;
+        }
+    }
+}
+
+impl<S> Layer<S> for ProfileLayer
+where
+    S: Subscriber + for<'l> LookupSpan<'l>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut first = FirstField::default();
+        attrs.record(&mut first);
+        let name = attrs.metadata().name();
+        let frame =
+            frame(&first.0.map_or_else(|| name.to_string(), |value| format!("{name}:{value}")));
+        span.extensions_mut().insert(Timing {
+            frame,
+            start: Instant::now(),
+            children: Duration::ZERO,
+        });
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let Some(timing) = span.extensions_mut().remove::<Timing>() else {
+            return;
+        };
+        let total = timing.start.elapsed();
+        let mut stack = frames(span.parent());
+        stack.push(timing.frame);
+        self.add(stack.join(";"), total.saturating_sub(timing.children));
+        add_child_time(span.parent(), total);
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        if event.metadata().target() != SQL_TARGET {
+            return;
+        }
+        let mut statement = SqlStatement::default();
+        event.record(&mut statement);
+        let (Some(sql), Some(elapsed_ns)) = (statement.sql, statement.elapsed_ns) else {
+            return;
+        };
+        let elapsed = Duration::from_nanos(elapsed_ns);
+        let mut stack = frames(ctx.event_span(event));
+        stack.push(frame(&format!("sql:{}", without_comments(&sql))));
+        self.add(stack.join(";"), elapsed);
+        add_child_time(ctx.event_span(event), elapsed);
+    }
+}
+
+/// SQL without its `--` comments, which would otherwise fill the frame.
+fn without_comments(sql: &str) -> String {
+    sql.lines()
+        .map(|line| line.split("--").next().unwrap_or_default())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Make text into a frame: on one line, without the `;` that separates
+/// frames, and no longer than [`MAX_FRAME_CHARS`].
+fn frame(text: &str) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ").replace(';', ",");
+    if text.chars().count() > MAX_FRAME_CHARS {
+        let mut short: String = text.chars().take(MAX_FRAME_CHARS - 3).collect();
+        short.push_str("...");
+        short
+    } else {
+        text
+    }
+}
+
+/// Records the value of the first field of a span.
+#[derive(Default)]
+struct FirstField(Option<String>);
+
+impl Visit for FirstField {
+    fn record_str(&mut self, _field: &Field, value: &str) {
+        if self.0.is_none() {
+            self.0 = Some(value.to_string());
+        }
+    }
+
+    fn record_debug(&mut self, _field: &Field, value: &dyn std::fmt::Debug) {
+        if self.0.is_none() {
+            self.0 = Some(format!("{value:?}"));
+        }
+    }
+}
+
+/// Records the fields of a finished SQL statement event.
+#[derive(Default)]
+struct SqlStatement {
+    sql: Option<String>,
+    elapsed_ns: Option<u64>,
+}
+
+impl Visit for SqlStatement {
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        if field.name() == "elapsed_ns" {
+            self.elapsed_ns = Some(value);
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "sql" {
+            self.sql = Some(value.to_string());
+        }
+    }
+
+    fn record_debug(&mut self, _field: &Field, _value: &dyn std::fmt::Debug) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    /// Parse folded stacks back into a map.
+    fn parse(folded: &str) -> BTreeMap<String, u64> {
+        folded
+            .lines()
+            .map(|line| {
+                let (stack, micros) = line.rsplit_once(' ').unwrap();
+                (stack.to_string(), micros.parse().unwrap())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_frame() {
+        assert_eq!(frame("command:git  commit\n-m a;b"), "command:git commit -m a,b");
+        let long = frame(&format!("sql:{}", "x".repeat(200)));
+        assert_eq!(long.chars().count(), MAX_FRAME_CHARS);
+        assert!(long.ends_with("..."));
+    }
+
+    #[test]
+    fn test_profile_records_self_time_by_stack() {
+        let profiler = Profiler::start();
+        assert!(is_enabled());
+        let subscriber = tracing_subscriber::registry().with(profiler.layer());
+        let mut check_times = Vec::new();
+        tracing::subscriber::with_default(subscriber, || {
+            let _hook = tracing::info_span!("hook", hook = "stop").entered();
+            std::thread::sleep(Duration::from_millis(1));
+            for check in ["uncommitted_changes", "problem_mode"] {
+                let started = std::time::Instant::now();
+                let check_span = tracing::info_span!("stop_check", check).entered();
+                std::thread::sleep(Duration::from_millis(1));
+                let command = tracing::debug_span!("command", command = %"git status").entered();
+                std::thread::sleep(Duration::from_millis(2));
+                drop(command);
+                record_sql("SELECT * -- every column\n  FROM tasks", Duration::from_micros(100));
+                // Too quick to show up
+                record_sql("PRAGMA foreign_keys = ON", Duration::from_nanos(10));
+                drop(check_span);
+                check_times.push(started.elapsed());
+            }
+            // Other events and incomplete statements are not counted
+            tracing::info!("not a statement");
+            tracing::debug!(target: SQL_TARGET, sql = "SELECT 1", "no time");
+            let _plain = tracing::info_span!("stop_hook").entered();
+            std::thread::sleep(Duration::from_millis(1));
+        });
+
+        let stacks = parse(&profiler.folded());
+        let check = "hook:stop;stop_check:uncommitted_changes";
+        assert_eq!(
+            stacks.keys().cloned().collect::<Vec<_>>(),
+            vec![
+                "hook:stop".to_string(),
+                "hook:stop;stop_check:problem_mode".to_string(),
+                "hook:stop;stop_check:problem_mode;command:git status".to_string(),
+                "hook:stop;stop_check:problem_mode;sql:SELECT * FROM tasks".to_string(),
+                check.to_string(),
+                format!("{check};command:git status"),
+                format!("{check};sql:SELECT * FROM tasks"),
+                "hook:stop;stop_hook".to_string(),
+            ]
+        );
+        assert_eq!(stacks[&format!("{check};sql:SELECT * FROM tasks")], 100);
+        assert!(stacks[&format!("{check};command:git status")] >= 2_000);
+        // The statement and command are not counted again in the check's self
+        // time, so the three add up to no more than the check took
+        let children = stacks[&format!("{check};command:git status")] + 100;
+        let check_micros = u64::try_from(check_times[0].as_micros()).unwrap();
+        assert!(stacks[check] + children <= check_micros, "{stacks:?}");
+    }
+
+    #[test]
+    fn test_write_profile() {
+        let dir = TempDir::new().unwrap();
+        let profiler = Profiler::start();
+        let subscriber = tracing_subscriber::registry().with(profiler.layer());
+        tracing::subscriber::with_default(subscriber, || {
+            let _hook = tracing::info_span!("hook", hook = "pre-tool-use").entered();
+        });
+
+        let path = profiler.write(dir.path(), "pre-tool-use").unwrap();
+        assert_eq!(path.parent().unwrap(), paths::project_profiles_dir(dir.path()));
+        let name = path.file_name().unwrap().to_string_lossy().to_string();
+        assert!(name.starts_with("pre-tool-use-") && name.ends_with(".folded"), "{name}");
+        let contents = std::fs::read_to_string(path).unwrap();
+        assert!(contents.starts_with("hook:pre-tool-use "), "{contents}");
+    }
+}
@@ -0,0 +1,137 @@
+//! Policy for the `unlock-protected` command.
+//!
+//! The agent may not change the reliability config, delete the project
+//! database or change the hook entries in `.claude/settings.json` on its own.
+//! With `unlock-protected` it gives a justification, which a sub-agent
+//! reviews; an approved justification allows one such change within the next
+//! few minutes. Requests, and every attempt on a protected file, are recorded
+//! in the audit log.
+
+use crate::error::Result;
+use crate::session;
+use crate::tasks;
+use crate::traits::{ProtectedChangeContext, ProtectedChangeDecision, SubAgent};
+use chrono::{Duration, Utc};
+use std::path::Path;
+
+/// Audit log operation for requests and attempts to change protected files.
+pub const PROTECTED_CHANGE_OPERATION: &str = "protected_change";
+
+/// How long an approved change stays available.
+pub const GRANT_MINUTES: i64 = 10;
+
+/// The result of a request to change protected files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UnlockOutcome {
+    /// The next change to a protected file is allowed.
+    Approved,
+    /// The request was refused, with the reason.
+    Rejected(String),
+}
+
+/// Ask a sub-agent whether the agent may change protected files.
+///
+/// An empty justification is rejected without asking, and a sub-agent that
+/// cannot be reached counts as a rejection.
+///
+/// # Errors
+///
+/// Returns an error if an approved change cannot be recorded.
+pub fn request_unlock(
+    base_dir: &Path,
+    justification: &str,
+    sub_agent: &dyn SubAgent,
+) -> Result<UnlockOutcome> {
+    let justification = justification.trim();
+    if justification.is_empty() {
+        return Ok(UnlockOutcome::Rejected(
+            "Say why the change is needed and who asked for it.".to_string(),
+        ));
+    }
+
+    let context = ProtectedChangeContext { justification: justification.to_string() };
+    let decision = sub_agent.evaluate_protected_change(&context).unwrap_or_else(|e| {
+        ProtectedChangeDecision::Reject(format!("The justification could not be reviewed: {e}"))
+    });
+
+    match decision {
+        ProtectedChangeDecision::Approve => {
+            session::grant_protected_change(
+                base_dir,
+                justification,
+                Utc::now() + Duration::minutes(GRANT_MINUTES),
+            )?;
+            record_attempt(base_dir, &format!("unlock approved: {justification}"));
+            Ok(UnlockOutcome::Approved)
+        }
+        ProtectedChangeDecision::Reject(reason) => {
+            record_attempt(base_dir, &format!("unlock rejected: {justification} ({reason})"));
+            Ok(UnlockOutcome::Rejected(reason))
+        }
+    }
+}
+
+/// Record a request or attempt to change protected files in the audit log.
+///
+/// Nothing is recorded if the project has no database.
+pub fn record_attempt(base_dir: &Path, details: &str) {
+    let Some(store) = tasks::open_project_store(base_dir) else {
+        return;
+    };
+    if let Err(e) = store.record_event(PROTECTED_CHANGE_OPERATION, details) {
+        tracing::warn!("Failed to record protected change: {e}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{FailingSubAgent, MockSubAgent};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_approved_unlock_grants_one_change() {
+        let dir = TempDir::new().unwrap();
+        let mut sub_agent = MockSubAgent::new();
+        sub_agent.expect_protected_change(ProtectedChangeDecision::Approve);
+
+        let outcome = request_unlock(
+            dir.path(),
+            "The user asked me to disable the spelling check",
+            &sub_agent,
+        )
+        .unwrap();
+        assert_eq!(outcome, UnlockOutcome::Approved);
+        assert_eq!(
+            session::take_protected_change_grant(dir.path()).as_deref(),
+            Some("The user asked me to disable the spelling check")
+        );
+        assert_eq!(session::take_protected_change_grant(dir.path()), None);
+    }
+
+    #[test]
+    fn test_rejected_unlock_grants_nothing() {
+        let dir = TempDir::new().unwrap();
+        let mut sub_agent = MockSubAgent::new();
+        sub_agent.expect_protected_change(ProtectedChangeDecision::Reject("No.".to_string()));
+
+        let outcome = request_unlock(dir.path(), "The stop hook keeps blocking me", &sub_agent);
+        assert_eq!(outcome.unwrap(), UnlockOutcome::Rejected("No.".to_string()));
+        assert_eq!(session::take_protected_change_grant(dir.path()), None);
+    }
+
+    #[test]
+    fn test_unreviewed_unlock_is_rejected() {
+        let dir = TempDir::new().unwrap();
+        let sub_agent = FailingSubAgent::new("unavailable");
+
+        let outcome = request_unlock(dir.path(), "Please", &sub_agent).unwrap();
+        assert!(
+            matches!(outcome, UnlockOutcome::Rejected(reason) if reason.contains("unavailable"))
+        );
+
+        let outcome = request_unlock(dir.path(), "  ", &MockSubAgent::new()).unwrap();
+        assert!(matches!(outcome, UnlockOutcome::Rejected(_)));
+        assert_eq!(session::take_protected_change_grant(dir.path()), None);
+    }
+}
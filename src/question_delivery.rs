@@ -0,0 +1,730 @@
+//! Delivery of questions to people not watching the terminal.
+//!
+//! When `question_delivery` is configured, new questions are posted as
+//! comments on a GitHub issue (with the `gh` CLI) or emailed (with a
+//! sendmail-compatible command), and `claude-reliability question sync` reads
+//! the replies back and answers the questions they refer to.
+//!
+//! On GitHub, an answer is a line starting `/answer <question-id>` in a comment
+//! on the issue, followed by the answer on the same line or the lines below.
+//! One comment may answer several questions. Only comments by the
+//! repository's owners, members and collaborators, or by a login in
+//! `answer_from`, are read.
+//!
+//! By email, a reply keeps `[question <question-id>]` in its subject, and its
+//! text above the quoted original is the answer. Replies are read from a
+//! maildir (`reply_maildir`), as written by `fetchmail`, `mbsync` or a local
+//! mail server. Plain-text and quoted-printable replies are understood. Only
+//! replies from `email_to` or an address in `answer_from` are read.
+
+use crate::config::QuestionDelivery;
+use crate::error::{Error, Result};
+use crate::storage::SqliteStore;
+use crate::tasks::{answers, Question, TaskStore};
+use crate::traits::CommandRunner;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+
+/// Delivery channel name for GitHub issue comments.
+pub const GITHUB_CHANNEL: &str = "github";
+
+/// Delivery channel name for email.
+pub const EMAIL_CHANNEL: &str = "email";
+
+/// Command that starts an answer in a GitHub comment.
+const ANSWER_COMMAND: &str = "/answer";
+
+/// Repository placeholder that `gh api` replaces with the current repository.
+#[allow(clippy::literal_string_with_formatting_args)] // Not a format string
+const CURRENT_REPO: &str = "{owner}/{repo}";
+
+/// GitHub author associations whose comments may answer questions.
+const TRUSTED_ASSOCIATIONS: [&str; 3] = ["OWNER", "MEMBER", "COLLABORATOR"];
+
+/// Comment fields selected by `gh api`, one JSON object per line.
+const COMMENTS_JQ: &str =
+    ".[] | {body, author: .user.login, association: .author_association} | @json";
+
+/// Maximum length of the question text in an email subject.
+const SUBJECT_TEXT_CHARS: usize = 60;
+
+/// A question sent on a delivery channel.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Delivery {
+    /// The channel the question was sent on.
+    pub channel: &'static str,
+    /// The question's ID.
+    pub question_id: String,
+}
+
+/// What a sync did.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct SyncReport {
+    /// IDs of the questions answered from replies.
+    pub answered: Vec<String>,
+    /// Questions sent.
+    pub delivered: Vec<Delivery>,
+    /// Problems with a channel. A failing channel does not stop the others.
+    pub errors: Vec<String>,
+}
+
+/// A GitHub issue, optionally in another repository than the current one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct GithubIssue {
+    repo: Option<String>,
+    number: u64,
+}
+
+impl GithubIssue {
+    /// Parse `owner/repo#123`, `#123` or `123`.
+    fn parse(spec: &str) -> Result<Self> {
+        let spec = spec.trim();
+        let (repo, number) = spec.rsplit_once('#').unwrap_or(("", spec));
+        let repo = repo.trim();
+        let valid_repo = repo.is_empty() || repo.split('/').filter(|p| !p.is_empty()).count() == 2;
+        match number.trim().parse() {
+            Ok(number) if valid_repo => {
+                Ok(Self { repo: (!repo.is_empty()).then(|| repo.to_string()), number })
+            }
+            _ => Err(Error::Config(format!(
+                "invalid github_issue '{spec}' (must be owner/repo#123 or an issue number)"
+            ))),
+        }
+    }
+
+    /// Path of the issue's comments in the GitHub API, as understood by `gh api`.
+    fn comments_endpoint(&self) -> String {
+        let repo = self.repo.as_deref().unwrap_or(CURRENT_REPO);
+        format!("repos/{repo}/issues/{}/comments", self.number)
+    }
+}
+
+/// A comment on the question issue.
+#[derive(Debug, Deserialize)]
+struct GithubComment {
+    body: String,
+    #[serde(default)]
+    author: Option<String>,
+    #[serde(default)]
+    association: Option<String>,
+}
+
+/// A configured delivery channel.
+enum Channel<'a> {
+    /// Comments on a GitHub issue.
+    Github(GithubIssue),
+    /// Emails to an address.
+    Email(&'a str),
+}
+
+impl Channel<'_> {
+    /// The channel's name, as recorded with its deliveries.
+    const fn name(&self) -> &'static str {
+        match self {
+            Self::Github(_) => GITHUB_CHANNEL,
+            Self::Email(_) => EMAIL_CHANNEL,
+        }
+    }
+}
+
+/// Send new questions on every configured channel, and answer questions from replies.
+///
+/// # Errors
+///
+/// Returns an error if the task store or state database cannot be used.
+/// Channel failures are reported in [`SyncReport::errors`].
+pub fn sync<S: TaskStore + ?Sized>(
+    base_dir: &Path,
+    delivery: &QuestionDelivery,
+    store: &S,
+    state: &SqliteStore,
+    runner: &dyn CommandRunner,
+) -> Result<SyncReport> {
+    let mut report = SyncReport::default();
+    let mut replies = Vec::new();
+    if let Some(issue) = &delivery.github_issue {
+        let found = GithubIssue::parse(issue)
+            .map_err(|e| e.to_string())
+            .and_then(|issue| github_replies(runner, &issue, &delivery.answer_from));
+        match found {
+            Ok(found) => replies.extend(found),
+            Err(e) => report.errors.push(format!("{GITHUB_CHANNEL}: {e}")),
+        }
+    }
+    if let Some(maildir) = &delivery.reply_maildir {
+        replies.extend(maildir_replies(&base_dir.join(maildir), delivery));
+    }
+
+    for (id, answer) in replies {
+        let unanswered = store.get_question(&id)?.is_some_and(|q| !q.is_answered());
+        if unanswered && store.answer_question(&id, &answer)?.is_some() {
+            report.answered.push(id);
+        }
+    }
+
+    let sent = deliver_pending(delivery, store, state, runner)?;
+    report.delivered = sent.delivered;
+    report.errors.extend(sent.errors);
+    Ok(report)
+}
+
+/// Send the pending questions not yet sent on each configured channel.
+///
+/// # Errors
+///
+/// Returns an error if the task store or state database cannot be used.
+/// Channel failures are reported in [`SyncReport::errors`].
+pub fn deliver_pending<S: TaskStore + ?Sized>(
+    delivery: &QuestionDelivery,
+    store: &S,
+    state: &SqliteStore,
+    runner: &dyn CommandRunner,
+) -> Result<SyncReport> {
+    let mut report = SyncReport::default();
+    if !delivery.is_enabled() {
+        return Ok(report);
+    }
+    let questions = answers::pending_questions(store)?;
+
+    let mut channels = Vec::new();
+    if let Some(issue) = &delivery.github_issue {
+        match GithubIssue::parse(issue) {
+            Ok(issue) => channels.push(Channel::Github(issue)),
+            Err(e) => report.errors.push(format!("{GITHUB_CHANNEL}: {e}")),
+        }
+    }
+    if let Some(to) = &delivery.email_to {
+        channels.push(Channel::Email(to));
+    }
+
+    for channel in channels {
+        let name = channel.name();
+        let sent: HashSet<String> = state.delivered_questions(name)?.into_iter().collect();
+        for question in questions.iter().filter(|q| !sent.contains(&q.id)) {
+            let result = match &channel {
+                Channel::Github(issue) => post_github_comment(runner, issue, question),
+                Channel::Email(to) => send_email(runner, delivery, to, question),
+            };
+            if let Err(e) = result {
+                // Stop at the first failure so questions go out in order
+                report.errors.push(format!("{name}: {e}"));
+                break;
+            }
+            state.record_question_delivery(&question.id, name)?;
+            report.delivered.push(Delivery { channel: name, question_id: question.id.clone() });
+        }
+    }
+    Ok(report)
+}
+
+/// Format a question as a GitHub comment.
+fn github_comment(question: &Question) -> String {
+    let quoted: Vec<String> = question
+        .text
+        .lines()
+        .map(|line| if line.is_empty() { ">".to_string() } else { format!("> {line}") })
+        .collect();
+    format!(
+        "**Question `{id}`**\n\n{text}\n\nTo answer, comment with a line starting `{ANSWER_COMMAND} {id}` followed by your answer.",
+        id = question.id,
+        text = quoted.join("\n"),
+    )
+}
+
+/// Post a question as a comment on the issue.
+fn post_github_comment(
+    runner: &dyn CommandRunner,
+    issue: &GithubIssue,
+    question: &Question,
+) -> std::result::Result<(), String> {
+    let number = issue.number.to_string();
+    let body = github_comment(question);
+    let mut args = vec!["issue", "comment", number.as_str(), "--body", body.as_str()];
+    if let Some(repo) = &issue.repo {
+        args.extend(["--repo", repo.as_str()]);
+    }
+    let output = runner.run("gh", &args, None).map_err(|e| e.to_string())?;
+    if output.success() {
+        Ok(())
+    } else {
+        Err(format!("gh issue comment failed: {}", output.combined_output().trim()))
+    }
+}
+
+/// Read the answers in the issue's comments by trusted authors.
+fn github_replies(
+    runner: &dyn CommandRunner,
+    issue: &GithubIssue,
+    answer_from: &[String],
+) -> std::result::Result<Vec<(String, String)>, String> {
+    // One JSON object per line, so multi-line comments can be told apart
+    let output = runner
+        .run("gh", &["api", &issue.comments_endpoint(), "--paginate", "--jq", COMMENTS_JQ], None)
+        .map_err(|e| e.to_string())?;
+    if !output.success() {
+        return Err(format!("gh api failed: {}", output.combined_output().trim()));
+    }
+    Ok(output
+        .stdout
+        .lines()
+        .filter_map(|line| serde_json::from_str::<GithubComment>(line).ok())
+        .filter(|comment| {
+            let trusted = comment.is_trusted(answer_from);
+            if !trusted && comment.body.contains(ANSWER_COMMAND) {
+                tracing::info!(author = ?comment.author, "ignoring answer from untrusted commenter");
+            }
+            trusted
+        })
+        .flat_map(|comment| parse_answer_commands(&comment.body))
+        .collect())
+}
+
+impl GithubComment {
+    /// Whether the comment's author may answer questions.
+    fn is_trusted(&self, answer_from: &[String]) -> bool {
+        self.association.as_deref().is_some_and(|a| TRUSTED_ASSOCIATIONS.contains(&a))
+            || self
+                .author
+                .as_deref()
+                .is_some_and(|author| answer_from.iter().any(|a| a.eq_ignore_ascii_case(author)))
+    }
+}
+
+/// Parse the `/answer <id> ...` sections of a comment into `(question ID, answer)` pairs.
+fn parse_answer_commands(body: &str) -> Vec<(String, String)> {
+    let mut answers: Vec<(String, String)> = Vec::new();
+    for line in body.lines() {
+        let command = line.trim_start().strip_prefix(ANSWER_COMMAND);
+        if let Some(rest) = command.filter(|rest| rest.starts_with(char::is_whitespace)) {
+            let rest = rest.trim_start();
+            let (id, first_line) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+            answers.push((id.trim_end_matches(':').to_string(), first_line.to_string()));
+        } else if let Some((_, answer)) = answers.last_mut() {
+            answer.push('\n');
+            answer.push_str(line);
+        }
+    }
+    answers
+        .into_iter()
+        .map(|(id, answer)| (id, answer.trim().to_string()))
+        .filter(|(id, answer)| !id.is_empty() && !answer.is_empty())
+        .collect()
+}
+
+/// Format a question as an email, headers included.
+fn email_message(delivery: &QuestionDelivery, to: &str, question: &Question) -> String {
+    let first_line = question.text.lines().next().unwrap_or_default();
+    let mut summary: String = first_line.chars().take(SUBJECT_TEXT_CHARS).collect();
+    if first_line.chars().count() > SUBJECT_TEXT_CHARS {
+        summary.push_str("...");
+    }
+    let mut headers = vec![format!("To: {to}")];
+    if let Some(from) = &delivery.email_from {
+        headers.push(format!("From: {from}"));
+    }
+    headers.push(format!("Subject: [question {}] {summary}", question.id));
+    headers.push("Content-Type: text/plain; charset=utf-8".to_string());
+    format!(
+        "{}\n\n{}\n\nReply to this email to answer. Keep \"[question {}]\" in the subject.\n",
+        headers.join("\n"),
+        question.text,
+        question.id
+    )
+}
+
+/// Email a question with the configured sendmail command.
+fn send_email(
+    runner: &dyn CommandRunner,
+    delivery: &QuestionDelivery,
+    to: &str,
+    question: &Question,
+) -> std::result::Result<(), String> {
+    let message = email_message(delivery, to, question);
+    // Run through the shell so the command can have arguments (`sendmail -t`)
+    let script = format!("printf '%s' \"$1\" | {}", delivery.sendmail_command);
+    let output =
+        runner.run("sh", &["-c", &script, "sh", &message], None).map_err(|e| e.to_string())?;
+    if output.success() {
+        Ok(())
+    } else {
+        Err(format!("'{}' failed: {}", delivery.sendmail_command, output.combined_output().trim()))
+    }
+}
+
+/// Read the answers in the replies from trusted senders delivered to a maildir.
+fn maildir_replies(maildir: &Path, delivery: &QuestionDelivery) -> Vec<(String, String)> {
+    let trusted: Vec<String> = delivery
+        .email_to
+        .iter()
+        .flat_map(|to| to.split(','))
+        .chain(delivery.answer_from.iter().map(String::as_str))
+        .filter_map(email_address)
+        .collect();
+    let mut paths: Vec<_> = ["new", "cur"]
+        .iter()
+        .filter_map(|sub| std::fs::read_dir(maildir.join(sub)).ok())
+        .flatten()
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .collect();
+    // Maildir file names start with the delivery time, so this is oldest first
+    paths.sort_by(|a, b| a.file_name().cmp(&b.file_name()));
+    paths
+        .iter()
+        .filter_map(|path| std::fs::read_to_string(path).ok())
+        .filter_map(|message| parse_email_reply(&message, &trusted))
+        .collect()
+}
+
+/// The lowercase address in a mailbox such as `Ann <ann@example.com>`.
+fn email_address(mailbox: &str) -> Option<String> {
+    let mailbox = mailbox.trim();
+    let address = match mailbox.rsplit_once('<') {
+        Some((_, rest)) => rest.split('>').next().unwrap_or_default(),
+        None => mailbox,
+    };
+    let address = address.trim();
+    address.contains('@').then(|| address.to_ascii_lowercase())
+}
+
+/// Split a message or MIME part into its unfolded headers and body.
+fn split_message(message: &str) -> (Vec<(String, String)>, &str) {
+    let (head, body) = message.split_once("\n\n").unwrap_or((message, ""));
+    let mut headers: Vec<(String, String)> = Vec::new();
+    for line in head.lines() {
+        if line.starts_with([' ', '\t']) {
+            if let Some((_, value)) = headers.last_mut() {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+        } else if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+        }
+    }
+    (headers, body)
+}
+
+/// Get a header's value by lowercase name.
+fn header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+}
+
+/// Parse a reply email from one of the `trusted` addresses into the question
+/// ID in its subject and the answer.
+fn parse_email_reply(message: &str, trusted: &[String]) -> Option<(String, String)> {
+    let message = message.replace("\r\n", "\n");
+    let (headers, body) = split_message(&message);
+    let from = header(&headers, "from").and_then(email_address);
+    if !from.as_ref().is_some_and(|from| trusted.contains(from)) {
+        tracing::info!(?from, "ignoring email reply from untrusted sender");
+        return None;
+    }
+    let subject = header(&headers, "subject")?;
+    let id = subject.split("[question ").nth(1)?.split(']').next()?.trim().to_string();
+    let text = plain_text(&headers, body);
+    let answer = reply_text(&text);
+    (!id.is_empty() && !answer.is_empty()).then_some((id, answer))
+}
+
+/// Get the plain-text body of a message, decoding quoted-printable.
+fn plain_text(headers: &[(String, String)], body: &str) -> String {
+    let content_type = header(headers, "content-type").unwrap_or("text/plain");
+    if content_type.to_ascii_lowercase().starts_with("multipart/") {
+        let boundary = content_type
+            .split(';')
+            .filter_map(|param| param.trim().split_once('='))
+            .find(|(name, _)| name.eq_ignore_ascii_case("boundary"))
+            .map(|(_, value)| value.trim_matches('"'));
+        let Some(boundary) = boundary else {
+            return String::new();
+        };
+        return body
+            .split(&format!("--{boundary}"))
+            // Skip the preamble before the first boundary
+            .skip(1)
+            .map(|part| split_message(part.trim_start_matches('\n')))
+            .find(|(part_headers, _)| {
+                header(part_headers, "content-type").map_or(true, |t| {
+                    let t = t.to_ascii_lowercase();
+                    t.starts_with("text/plain") || t.starts_with("multipart/")
+                })
+            })
+            .map(|(part_headers, part_body)| plain_text(&part_headers, part_body))
+            .unwrap_or_default();
+    }
+    let encoding = header(headers, "content-transfer-encoding").unwrap_or_default();
+    if encoding.eq_ignore_ascii_case("quoted-printable") {
+        decode_quoted_printable(body)
+    } else {
+        body.to_string()
+    }
+}
+
+/// Decode a quoted-printable body.
+fn decode_quoted_printable(body: &str) -> String {
+    let body = body.replace("=\n", "");
+    let bytes = body.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = (bytes[i] == b'=')
+            .then(|| body.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        if let Some(byte) = hex {
+            decoded.push(byte);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Get the text of a reply above the quoted original.
+fn reply_text(text: &str) -> String {
+    let mut lines: Vec<&str> = text
+        .lines()
+        .take_while(|line| !line.starts_with('>') && !line.contains("-----Original Message-----"))
+        .collect();
+    // Drop the attribution line(s), such as "On Mon, 2 Nov 2026, Ann <ann@x.org> wrote:"
+    if lines
+        .iter()
+        .rev()
+        .find(|l| !l.trim().is_empty())
+        .is_some_and(|l| l.trim_end().ends_with("wrote:"))
+    {
+        while lines.last().is_some_and(|l| l.trim().is_empty()) {
+            lines.pop();
+        }
+        while lines.last().is_some_and(|l| !l.trim().is_empty()) {
+            lines.pop();
+        }
+    }
+    lines.join("\n").trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tasks::InMemoryTaskStore;
+    use crate::testing::MockCommandRunner;
+    use crate::traits::CommandOutput;
+    use tempfile::TempDir;
+
+    fn output(exit_code: i32, stdout: &str) -> CommandOutput {
+        CommandOutput { exit_code, stdout: stdout.to_string(), stderr: String::new() }
+    }
+
+    fn state(dir: &TempDir) -> SqliteStore {
+        SqliteStore::new(dir.path()).unwrap()
+    }
+
+    fn comment(body: &str, author: &str, association: &str) -> String {
+        serde_json::json!({"body": body, "author": author, "association": association}).to_string()
+    }
+
+    #[test]
+    fn test_parse_github_issue() {
+        assert_eq!(
+            GithubIssue::parse("acme/app#12").unwrap(),
+            GithubIssue { repo: Some("acme/app".to_string()), number: 12 }
+        );
+        assert_eq!(GithubIssue::parse("#7").unwrap(), GithubIssue { repo: None, number: 7 });
+        assert_eq!(
+            GithubIssue::parse("7").unwrap().comments_endpoint(),
+            "repos/{owner}/{repo}/issues/7/comments"
+        );
+        assert!(GithubIssue::parse("acme#7").is_err());
+        assert!(GithubIssue::parse("acme/app#x").is_err());
+    }
+
+    #[test]
+    fn test_parse_answer_commands() {
+        let body = "Thanks!\n/answer db-1a2b Postgres.\n\nIt is what ops knows.\n/answer region-3c4d:\neu-west-1\n/answers nope-0000 x\n/answer empty-5e6f";
+        assert_eq!(
+            parse_answer_commands(body),
+            vec![
+                ("db-1a2b".to_string(), "Postgres.\n\nIt is what ops knows.".to_string()),
+                ("region-3c4d".to_string(), "eu-west-1\n/answers nope-0000 x".to_string()),
+            ]
+        );
+        // The posted question itself is not an answer
+        let question = InMemoryTaskStore::new().create_question("Which?").unwrap();
+        assert!(parse_answer_commands(&github_comment(&question)).is_empty());
+    }
+
+    #[test]
+    fn test_sync_github_posts_new_questions_and_reads_answers() {
+        let dir = TempDir::new().unwrap();
+        let state = state(&dir);
+        let store = InMemoryTaskStore::new();
+        let answered = store.create_question("Which database?").unwrap();
+        let asked = store.create_question("Which region?").unwrap();
+        state.record_question_delivery(&answered.id, GITHUB_CHANNEL).unwrap();
+        let delivery = QuestionDelivery {
+            github_issue: Some("acme/app#12".to_string()),
+            ..QuestionDelivery::default()
+        };
+
+        let comments = [
+            comment(&github_comment(&answered), "ann", "OWNER"),
+            comment(&format!("/answer {}\nPostgres", answered.id), "bob", "COLLABORATOR"),
+        ]
+        .join("\n");
+        let mut runner = MockCommandRunner::new();
+        runner.expect(
+            "gh",
+            &["api", "repos/acme/app/issues/12/comments", "--paginate", "--jq", COMMENTS_JQ],
+            output(0, &comments),
+        );
+        let body = github_comment(&asked);
+        runner.expect(
+            "gh",
+            &["issue", "comment", "12", "--body", &body, "--repo", "acme/app"],
+            output(0, ""),
+        );
+
+        let report = sync(dir.path(), &delivery, &store, &state, &runner).unwrap();
+        runner.verify();
+        assert_eq!(report.answered, vec![answered.id.clone()]);
+        assert_eq!(
+            report.delivered,
+            vec![Delivery { channel: GITHUB_CHANNEL, question_id: asked.id }]
+        );
+        assert!(report.errors.is_empty());
+        assert_eq!(
+            store.get_question(&answered.id).unwrap().unwrap().answer.as_deref(),
+            Some("Postgres")
+        );
+
+        // Nothing new to send; a failing channel is reported, not fatal
+        let mut runner = MockCommandRunner::new();
+        runner.expect(
+            "gh",
+            &["api", "repos/acme/app/issues/12/comments", "--paginate", "--jq", COMMENTS_JQ],
+            CommandOutput { exit_code: 1, stdout: String::new(), stderr: "HTTP 404".to_string() },
+        );
+        let report = sync(dir.path(), &delivery, &store, &state, &runner).unwrap();
+        runner.verify();
+        assert!(report.delivered.is_empty());
+        assert_eq!(report.errors, vec!["github: gh api failed: HTTP 404"]);
+    }
+
+    #[test]
+    fn test_github_answers_only_from_trusted_authors() {
+        let issue = GithubIssue { repo: Some("acme/app".to_string()), number: 12 };
+        let comments = [
+            comment("/answer db-1a2b Drop every table", "mallory", "NONE"),
+            comment("/answer region-3c4d eu-west-1", "ops-bot", "CONTRIBUTOR"),
+            comment("/answer licence-5e6f MIT", "ann", "MEMBER"),
+        ]
+        .join("\n");
+        let runner = || {
+            let mut runner = MockCommandRunner::new();
+            runner.expect(
+                "gh",
+                &["api", "repos/acme/app/issues/12/comments", "--paginate", "--jq", COMMENTS_JQ],
+                output(0, &comments),
+            );
+            runner
+        };
+
+        assert_eq!(
+            github_replies(&runner(), &issue, &[]).unwrap(),
+            vec![("licence-5e6f".to_string(), "MIT".to_string())]
+        );
+        assert_eq!(
+            github_replies(&runner(), &issue, &["Ops-Bot".to_string()]).unwrap(),
+            vec![
+                ("region-3c4d".to_string(), "eu-west-1".to_string()),
+                ("licence-5e6f".to_string(), "MIT".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_deliver_pending_email() {
+        let dir = TempDir::new().unwrap();
+        let state = state(&dir);
+        let store = InMemoryTaskStore::new();
+        let question =
+            store.create_question("Which licence should we use?\nMIT or Apache?").unwrap();
+        let delivery = QuestionDelivery {
+            email_to: Some("team@example.com".to_string()),
+            email_from: Some("agent@example.com".to_string()),
+            sendmail_command: "msmtp -t".to_string(),
+            ..QuestionDelivery::default()
+        };
+
+        let message = email_message(&delivery, "team@example.com", &question);
+        assert!(message.starts_with(&format!(
+            "To: team@example.com\nFrom: agent@example.com\nSubject: [question {}] Which licence should we use?\n",
+            question.id
+        )));
+        let mut runner = MockCommandRunner::new();
+        runner.expect(
+            "sh",
+            &["-c", "printf '%s' \"$1\" | msmtp -t", "sh", &message],
+            output(0, ""),
+        );
+
+        let report = deliver_pending(&delivery, &store, &state, &runner).unwrap();
+        runner.verify();
+        assert_eq!(report.delivered.len(), 1);
+        assert_eq!(state.delivered_questions(EMAIL_CHANNEL).unwrap(), vec![question.id]);
+        // Already sent
+        let report = deliver_pending(&delivery, &store, &state, &MockCommandRunner::new()).unwrap();
+        assert!(report.delivered.is_empty());
+    }
+
+    #[test]
+    fn test_email_replies_from_maildir() {
+        let dir = TempDir::new().unwrap();
+        let maildir = dir.path().join("replies");
+        std::fs::create_dir_all(maildir.join("new")).unwrap();
+        std::fs::create_dir_all(maildir.join("cur")).unwrap();
+        std::fs::write(
+            maildir.join("new/1.eml"),
+            "From: ann@example.com\r\nSubject: Re: [question licence-1a2b] Which\r\n licence?\r\n\r\nApache, for the patent grant.\r\n\r\nOn Mon, 2 Nov 2026, agent wrote:\r\n> Which licence?\r\n",
+        )
+        .unwrap();
+        std::fs::write(
+            maildir.join("cur/2.eml"),
+            "From: Team <TEAM@example.com>\nSubject: Re: [question region-3c4d] Which region?\nContent-Type: multipart/alternative; boundary=\"b1\"\n\n--b1\nContent-Type: text/plain; charset=utf-8\nContent-Transfer-Encoding: quoted-printable\n\nZ=C3=BCrich, please. This is a long line that wraps with a soft line =\nbreak.\n--b1\nContent-Type: text/html\n\n<p>Zurich</p>\n--b1--\n",
+        )
+        .unwrap();
+        std::fs::write(maildir.join("cur/3.eml"), "Subject: Lunch?\n\nPizza").unwrap();
+        // Replies from anyone else, or with no sender, are not answers
+        std::fs::write(
+            maildir.join("cur/4.eml"),
+            "From: mallory@example.net\nSubject: Re: [question licence-1a2b] Which\n\nGPL",
+        )
+        .unwrap();
+        std::fs::write(
+            maildir.join("cur/5.eml"),
+            "Subject: Re: [question region-3c4d] Which region?\n\nus-east-1",
+        )
+        .unwrap();
+        let delivery = QuestionDelivery {
+            email_to: Some("team@example.com".to_string()),
+            answer_from: vec!["Ann <ann@example.com>".to_string()],
+            ..QuestionDelivery::default()
+        };
+
+        assert_eq!(
+            maildir_replies(&maildir, &delivery),
+            vec![
+                ("licence-1a2b".to_string(), "Apache, for the patent grant.".to_string()),
+                (
+                    "region-3c4d".to_string(),
+                    "Zürich, please. This is a long line that wraps with a soft line break."
+                        .to_string()
+                ),
+            ]
+        );
+        assert!(maildir_replies(&dir.path().join("missing"), &delivery).is_empty());
+        assert!(maildir_replies(&maildir, &QuestionDelivery::default()).is_empty());
+    }
+}
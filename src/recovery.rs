@@ -0,0 +1,476 @@
+//! Detecting a corrupted database and recovering what can still be read.
+//!
+//! The first time a process opens a database, [`check_on_open`] reads its
+//! schema, which is cheap enough for every hook and catches a file that is
+//! no longer a database. The full `PRAGMA quick_check` is left to
+//! [`check`], which maintenance runs, and to any open whose schema read
+//! reports corruption. If `SQLite` reports corruption, the damaged file is
+//! copied to a timestamped file in the `backups/` directory next to it, and
+//! every row that can still be read is copied into a freshly created
+//! database, which then replaces it. Recoveries hold an exclusive lock, so
+//! two processes never replace the same database at once. The recovery is recorded in the audit
+//! log, and the next session start or prompt warns the agent that work items
+//! may have been lost, rather than carrying on as if there had never been any.
+
+use crate::error::Result;
+use crate::storage::SqliteStore;
+use crate::tasks::SqliteTaskStore;
+use crate::templates;
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use rusqlite::types::Value;
+use rusqlite::{Connection, ErrorCode, Transaction};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+use tera::Context;
+
+/// Audit log operation recording a recovered database.
+pub const RECOVERY_OPERATION: &str = "database_recovered";
+
+/// How long to wait for another process to finish recovering a database.
+const RECOVERY_LOCK_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Databases already checked by this process.
+static CHECKED: Lazy<Mutex<HashSet<PathBuf>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+/// What recovering a database did.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RecoveryReport {
+    /// Where the damaged database was copied to.
+    pub backup_path: PathBuf,
+    /// Number of rows copied into the new database.
+    pub rows_recovered: usize,
+    /// Tables with rows that could not be read.
+    pub unreadable_tables: Vec<String>,
+}
+
+impl RecoveryReport {
+    /// One line describing what was recovered.
+    #[must_use]
+    pub fn summary(&self) -> String {
+        let rows = format!("Recovered {} rows", self.rows_recovered);
+        if self.unreadable_tables.is_empty() {
+            rows
+        } else {
+            format!("{rows}; rows could not be read from {}", self.unreadable_tables.join(", "))
+        }
+    }
+}
+
+/// Read a database's schema the first time this process opens it, and check
+/// and recover it if that reports corruption.
+///
+/// # Errors
+///
+/// Returns an error if the database is corrupted and cannot be recovered.
+pub fn check_on_open(db_path: &Path) -> Result<()> {
+    let first_open =
+        CHECKED.lock().unwrap_or_else(PoisonError::into_inner).insert(db_path.to_path_buf());
+    if first_open && db_path.exists() && schema_unreadable(db_path) {
+        check(db_path)?;
+    }
+    Ok(())
+}
+
+/// Whether reading a database's schema reports corruption.
+fn schema_unreadable(db_path: &Path) -> bool {
+    let read = Connection::open(db_path).and_then(|conn| {
+        conn.query_row("SELECT count(*) FROM sqlite_master", [], |row| row.get::<_, i64>(0))
+    });
+    read.is_err_and(|e| is_corruption(&e))
+}
+
+/// Check a database for corruption, recovering it if it is corrupted.
+///
+/// Returns what the recovery did, or `None` if the database is missing or fine.
+///
+/// # Errors
+///
+/// Returns an error if the database is corrupted and cannot be recovered.
+pub fn check(db_path: &Path) -> Result<Option<RecoveryReport>> {
+    if !db_path.exists() {
+        return Ok(None);
+    }
+    if find_corruption(db_path).is_none() {
+        return Ok(None);
+    }
+    let _lock = lock(db_path)?;
+    // Another process may have recovered it while we waited for the lock
+    let Some(problem) = find_corruption(db_path) else {
+        return Ok(None);
+    };
+    tracing::error!("Database {} is corrupted: {problem}", db_path.display());
+    let report = recover_locked(db_path)?;
+    tracing::error!(
+        "{}; the damaged database was kept at {}",
+        report.summary(),
+        report.backup_path.display()
+    );
+    Ok(Some(report))
+}
+
+/// The corruption `SQLite` reports in a database, if any.
+///
+/// Errors other than corruption, such as the database being locked, are not
+/// reported.
+#[must_use]
+pub fn find_corruption(db_path: &Path) -> Option<String> {
+    let status = Connection::open(db_path)
+        .and_then(|conn| conn.query_row("PRAGMA quick_check", [], |row| row.get::<_, String>(0)));
+    match status {
+        Ok(status) => (status != "ok").then_some(status),
+        Err(e) => is_corruption(&e).then(|| e.to_string()),
+    }
+}
+
+/// Whether an error means the database file is damaged.
+fn is_corruption(error: &rusqlite::Error) -> bool {
+    matches!(error.sqlite_error_code(), Some(ErrorCode::DatabaseCorrupt | ErrorCode::NotADatabase))
+}
+
+/// Back up a damaged database and replace it with the rows that can be read.
+///
+/// Waits for the exclusive recovery lock on the database first.
+///
+/// # Errors
+///
+/// Returns an error if the lock cannot be taken, or the backup or the new
+/// database cannot be written.
+pub fn recover(db_path: &Path) -> Result<RecoveryReport> {
+    let _lock = lock(db_path)?;
+    recover_locked(db_path)
+}
+
+/// Take the exclusive recovery lock on a database, held until the returned
+/// connection is dropped.
+///
+/// The lock is on a `.recovery-lock` file next to the database, since a
+/// damaged database may not be lockable itself.
+fn lock(db_path: &Path) -> Result<Connection> {
+    let conn = Connection::open(with_suffix(db_path, ".recovery-lock"))?;
+    conn.busy_timeout(RECOVERY_LOCK_TIMEOUT)?;
+    conn.execute_batch("PRAGMA locking_mode = EXCLUSIVE; BEGIN EXCLUSIVE;")?;
+    Ok(conn)
+}
+
+/// [`recover`], with the recovery lock already held.
+fn recover_locked(db_path: &Path) -> Result<RecoveryReport> {
+    let backup_path = back_up(db_path, "corrupt")?;
+
+    let fresh_path = with_suffix(db_path, ".recovering");
+    remove_with_sidecars(&fresh_path)?;
+    SqliteTaskStore::new(&fresh_path)?;
+    SqliteStore::with_path(fresh_path.clone())?;
+    let (rows_recovered, unreadable_tables) = copy_readable_rows(db_path, &fresh_path)?;
+    SqliteTaskStore::new(&fresh_path)?.rebuild_search_indexes()?;
+    Connection::open(&fresh_path)?.execute_batch("PRAGMA wal_checkpoint(TRUNCATE);")?;
+
+    // The damaged log would otherwise be applied to the new database
+    remove_sidecars(db_path)?;
+    std::fs::rename(&fresh_path, db_path)?;
+    remove_sidecars(&fresh_path)?;
+
+    let report = RecoveryReport { backup_path, rows_recovered, unreadable_tables };
+    SqliteTaskStore::new(db_path)?.record_event(RECOVERY_OPERATION, &report.summary())?;
+    SqliteStore::with_path(db_path.to_path_buf())?
+        .record_database_recovery(&report.backup_path.display().to_string(), &report.summary())?;
+    Ok(report)
+}
+
+/// Copy a database and its write-ahead log into the `backups/` directory next
+/// to it, as `<label>-<timestamp>.sqlite3`.
+///
+/// Unlike a backup made by [`crate::backup::create`], this works on a damaged
+/// database.
+///
+/// # Errors
+///
+/// Returns an error if the files cannot be copied.
+pub fn back_up(db_path: &Path, label: &str) -> Result<PathBuf> {
+    let dir = db_path.parent().unwrap_or_else(|| Path::new(".")).join("backups");
+    std::fs::create_dir_all(&dir)?;
+    let name = format!("{label}-{}.sqlite3", Utc::now().format("%Y%m%dT%H%M%S%.3fZ"));
+    let backup_path = dir.join(name);
+    std::fs::copy(db_path, &backup_path)?;
+    let wal = with_suffix(db_path, "-wal");
+    if wal.exists() {
+        std::fs::copy(wal, with_suffix(&backup_path, "-wal"))?;
+    }
+    Ok(backup_path)
+}
+
+/// Copy every readable row of the tables in `to` from the same tables in `from`.
+///
+/// Returns the number of rows copied and the tables that could not be fully read.
+fn copy_readable_rows(from: &Path, to: &Path) -> Result<(usize, Vec<String>)> {
+    let source = Connection::open(from)?;
+    let mut dest = Connection::open(to)?;
+    // Tables are copied in any order, and a row whose parent was lost is still worth keeping
+    dest.execute_batch("PRAGMA foreign_keys = OFF;")?;
+    let tables = data_tables(&dest)?;
+    let tx = dest.transaction()?;
+    let mut rows = 0;
+    let mut unreadable = Vec::new();
+    for table in tables {
+        let (copied, complete) = copy_table(&source, &tx, &table)?;
+        rows += copied;
+        if !complete {
+            unreadable.push(table);
+        }
+    }
+    tx.commit()?;
+    Ok((rows, unreadable))
+}
+
+/// The ordinary tables of a database, leaving out full-text search indexes,
+/// which are rebuilt from the tables they index.
+fn data_tables(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "SELECT name, sql LIKE 'CREATE VIRTUAL%' FROM sqlite_master
+         WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+    )?;
+    let all: Vec<(String, bool)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+    let virtual_tables: Vec<&str> =
+        all.iter().filter(|(_, is_virtual)| *is_virtual).map(|(name, _)| name.as_str()).collect();
+    Ok(all
+        .iter()
+        .filter(|(name, is_virtual)| {
+            !is_virtual && !virtual_tables.iter().any(|v| name.starts_with(&format!("{v}_")))
+        })
+        .map(|(name, _)| name.clone())
+        .collect())
+}
+
+/// The column names of a table.
+fn columns(conn: &Connection, table: &str) -> rusqlite::Result<Vec<String>> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info(\"{table}\")"))?;
+    let names = stmt.query_map([], |row| row.get(1))?.collect();
+    names
+}
+
+/// Copy the readable rows of one table, stopping at the first unreadable row.
+///
+/// Returns the number of rows copied and whether the whole table was read.
+/// Only errors writing to `dest` are returned; a table missing from `source`
+/// counts as fully read.
+fn copy_table(source: &Connection, dest: &Transaction, table: &str) -> Result<(usize, bool)> {
+    let Ok(source_columns) = columns(source, table) else {
+        return Ok((0, false));
+    };
+    let shared: Vec<String> = columns(dest, table)?
+        .into_iter()
+        .filter(|c| source_columns.contains(c))
+        .map(|c| format!("\"{c}\""))
+        .collect();
+    if shared.is_empty() {
+        return Ok((0, true));
+    }
+
+    let column_list = shared.join(", ");
+    let placeholders = vec!["?"; shared.len()].join(", ");
+    let mut insert = dest.prepare(&format!(
+        "INSERT OR IGNORE INTO \"{table}\" ({column_list}) VALUES ({placeholders})"
+    ))?;
+    let Ok(mut select) = source.prepare(&format!("SELECT {column_list} FROM \"{table}\"")) else {
+        return Ok((0, false));
+    };
+    let Ok(mut rows) = select.query([]) else {
+        return Ok((0, false));
+    };
+
+    let mut copied = 0;
+    loop {
+        let values: rusqlite::Result<Vec<Value>> = match rows.next() {
+            Ok(Some(row)) => (0..shared.len()).map(|i| row.get(i)).collect(),
+            Ok(None) => return Ok((copied, true)),
+            Err(e) => Err(e),
+        };
+        let Ok(values) = values else {
+            return Ok((copied, false));
+        };
+        insert.execute(rusqlite::params_from_iter(values))?;
+        copied += 1;
+    }
+}
+
+/// A path with a suffix appended to its file name.
+pub(crate) fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Remove a database's write-ahead log and shared-memory files.
+pub(crate) fn remove_sidecars(db_path: &Path) -> Result<()> {
+    for suffix in ["-wal", "-shm"] {
+        let path = with_suffix(db_path, suffix);
+        if path.exists() {
+            std::fs::remove_file(path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Remove a database along with its write-ahead log and shared-memory files.
+fn remove_with_sidecars(db_path: &Path) -> Result<()> {
+    if db_path.exists() {
+        std::fs::remove_file(db_path)?;
+    }
+    remove_sidecars(db_path)
+}
+
+/// Add a warning about unreported database recoveries to a message.
+///
+/// The recoveries are reported only once. Returns the message unchanged if
+/// there are none.
+///
+/// # Panics
+///
+/// Panics if the embedded warning template fails to render.
+#[must_use]
+pub fn with_recovery_warning(base_dir: &Path, message: Option<String>) -> Option<String> {
+    let recoveries = match SqliteStore::new(base_dir).and_then(|s| s.take_database_recoveries()) {
+        Ok(recoveries) if !recoveries.is_empty() => recoveries,
+        Ok(_) => return message,
+        Err(e) => {
+            tracing::warn!("Failed to read database recoveries: {e}");
+            return message;
+        }
+    };
+    let mut ctx = Context::new();
+    ctx.insert("recoveries", &recoveries);
+    let warning = templates::render("messages/database_recovered.tera", &ctx)
+        .expect("database_recovered.tera template should always render");
+    let warning = warning.trim().to_string();
+    Some(message.map_or_else(|| warning.clone(), |message| format!("{warning}\n\n{message}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::paths;
+    use crate::tasks::{Priority, TaskFilter, TaskStore};
+    use tempfile::TempDir;
+
+    /// Overwrite the root page of an index with garbage.
+    fn corrupt_index(db_path: &Path, index: &str) {
+        let conn = Connection::open(db_path).unwrap();
+        conn.execute_batch("PRAGMA wal_checkpoint(TRUNCATE);").unwrap();
+        let page_size: u64 = conn.query_row("PRAGMA page_size", [], |row| row.get(0)).unwrap();
+        let root_page: u64 = conn
+            .query_row("SELECT rootpage FROM sqlite_master WHERE name = ?1", [index], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        drop(conn);
+
+        let mut bytes = std::fs::read(db_path).unwrap();
+        let start = usize::try_from((root_page - 1) * page_size).unwrap();
+        let end = start + usize::try_from(page_size).unwrap();
+        bytes[start..end].fill(0xAB);
+        std::fs::write(db_path, bytes).unwrap();
+    }
+
+    #[test]
+    fn test_recovers_rows_from_corrupted_database() {
+        let dir = TempDir::new().unwrap();
+        let db_path = paths::project_db_path(dir.path());
+        let store = SqliteTaskStore::new(&db_path).unwrap();
+        let task = store.create_task("Fix parser", "It drops commas", Priority::High).unwrap();
+        store.add_note(&task.id, "Started on the tokenizer").unwrap();
+        store.create_task("Write docs", "", Priority::Low).unwrap();
+        corrupt_index(&db_path, "sqlite_autoindex_tasks_1");
+        assert!(find_corruption(&db_path).is_some());
+
+        let report = check(&db_path).unwrap().unwrap();
+        assert!(report.rows_recovered >= 3, "{report:?}");
+        assert!(report.backup_path.exists());
+        assert!(report.backup_path.starts_with(db_path.parent().unwrap().join("backups")));
+        assert_eq!(find_corruption(&db_path), None);
+        assert_eq!(check(&db_path).unwrap(), None);
+
+        let store = SqliteTaskStore::new(&db_path).unwrap();
+        assert_eq!(store.list_tasks(TaskFilter::default()).unwrap().len(), 2);
+        assert_eq!(store.get_notes(&task.id).unwrap()[0].content, "Started on the tokenizer");
+        assert_eq!(store.search_tasks("commas").unwrap().len(), 1);
+        let log = store.get_audit_log(None, None).unwrap();
+        assert!(log.iter().any(|entry| entry.operation == RECOVERY_OPERATION), "{log:?}");
+    }
+
+    #[test]
+    fn test_unreadable_file_replaced_on_open() {
+        let dir = TempDir::new().unwrap();
+        let db_path = paths::project_db_path(dir.path());
+        std::fs::create_dir_all(db_path.parent().unwrap()).unwrap();
+        std::fs::write(&db_path, "not a database ".repeat(500)).unwrap();
+
+        let store = SqliteTaskStore::for_project(dir.path()).unwrap();
+        assert!(store.list_tasks(TaskFilter::default()).unwrap().is_empty());
+
+        let message = with_recovery_warning(dir.path(), Some("Resume work".to_string())).unwrap();
+        assert!(message.contains("Database Was Corrupted"), "{message}");
+        assert!(message.contains("Recovered 0 rows; rows could not be read from"), "{message}");
+        assert!(message.ends_with("Resume work"), "{message}");
+        let backups = std::fs::read_dir(db_path.parent().unwrap().join("backups")).unwrap();
+        assert_eq!(backups.count(), 1);
+
+        // The warning is only given once
+        assert_eq!(with_recovery_warning(dir.path(), None), None);
+    }
+
+    #[test]
+    fn test_damaged_index_is_left_to_maintenance() {
+        let dir = TempDir::new().unwrap();
+        let db_path = paths::project_db_path(dir.path());
+        SqliteTaskStore::new(&db_path)
+            .unwrap()
+            .create_task("Fix parser", "", Priority::High)
+            .unwrap();
+        corrupt_index(&db_path, "sqlite_autoindex_tasks_1");
+
+        // Opening only reads the schema, which is intact
+        assert!(!schema_unreadable(&db_path));
+
+        let report = crate::maintenance::run(dir.path()).unwrap();
+        assert!(report.recovered.unwrap().starts_with("Recovered"));
+        assert_eq!(find_corruption(&db_path), None);
+    }
+
+    #[test]
+    fn test_concurrent_checks_recover_once() {
+        let dir = TempDir::new().unwrap();
+        let db_path = paths::project_db_path(dir.path());
+        SqliteTaskStore::new(&db_path)
+            .unwrap()
+            .create_task("Fix parser", "", Priority::High)
+            .unwrap();
+        corrupt_index(&db_path, "sqlite_autoindex_tasks_1");
+
+        let reports = std::thread::scope(|scope| {
+            let first = scope.spawn(|| check(&db_path).unwrap());
+            let second = scope.spawn(|| check(&db_path).unwrap());
+            [first.join().unwrap(), second.join().unwrap()]
+        });
+        assert_eq!(reports.iter().filter(|r| r.is_some()).count(), 1, "{reports:?}");
+        let backups = std::fs::read_dir(db_path.parent().unwrap().join("backups")).unwrap();
+        let databases = backups.filter(|entry| {
+            entry.as_ref().unwrap().path().extension().is_some_and(|ext| ext == "sqlite3")
+        });
+        assert_eq!(databases.count(), 1);
+    }
+
+    #[test]
+    fn test_check_ignores_missing_and_healthy_databases() {
+        let dir = TempDir::new().unwrap();
+        let db_path = paths::project_db_path(dir.path());
+        assert_eq!(check(&db_path).unwrap(), None);
+        SqliteTaskStore::new(&db_path).unwrap();
+        assert_eq!(check(&db_path).unwrap(), None);
+        assert!(!db_path.parent().unwrap().join("backups").exists());
+    }
+}
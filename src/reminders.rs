@@ -192,12 +192,12 @@ fn get_compiled_reminders(base_dir: &Path) -> Result<Vec<CompiledReminder>> {
 /// vector if:
 /// - No patterns match
 /// - The reminders file doesn't exist
-/// - There's an error loading the reminders (errors are logged to stderr)
+/// - There's an error loading the reminders (errors are logged as warnings)
 pub fn check_reminders(text: &str, base_dir: &Path) -> Vec<String> {
     let compiled = match get_compiled_reminders(base_dir) {
         Ok(c) => c,
         Err(e) => {
-            eprintln!("Warning: Failed to load reminders: {e}");
+            tracing::warn!("Failed to load reminders: {e}");
             return Vec::new();
         }
     };
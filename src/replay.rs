@@ -0,0 +1,228 @@
+//! Replaying hooks against captured input.
+//!
+//! When a hook makes a surprising decision, its input (from the hook event
+//! log, or saved by hand) can be fed back through it with
+//! `claude-reliability replay`. The hook runs against a scratch copy of the
+//! project data directory, so replaying never changes the real session state,
+//! and the task database can be rewound to an earlier audit log entry to
+//! reconstruct what the hook saw at the time. A replayed stop hook also never
+//! commits, pushes, opens pull requests or issues, or calls sub-agents for
+//! handoffs and assumptions.
+
+use crate::error::{Error, Result};
+use crate::paths;
+use crate::tasks::SqliteTaskStore;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+/// Hooks that can be replayed.
+pub const REPLAYABLE_HOOKS: &[&str] =
+    &["stop", "session-start", "user-prompt-submit", "pre-tool-use", "post-tool-use"];
+
+/// A scratch copy of a project's data directory and database.
+///
+/// The copy is deleted when the snapshot is dropped.
+#[derive(Debug)]
+pub struct Snapshot {
+    root: PathBuf,
+}
+
+impl Snapshot {
+    /// Copy a project's data directory and database into a scratch directory.
+    ///
+    /// Logs are not copied. If `at` is given, the copied database is rewound
+    /// to that audit log entry.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the data cannot be copied or the database rewound.
+    pub fn create(project_dir: &Path, at: Option<i64>) -> Result<Self> {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.subsec_nanos());
+        let root = std::env::temp_dir()
+            .join(format!("claude-reliability-replay-{}-{nanos}", std::process::id()));
+        let snapshot = Self { root };
+        std::fs::create_dir_all(snapshot.data_dir())?;
+
+        let db_path = paths::project_db_path(project_dir);
+        let data_dir = paths::project_data_dir(project_dir);
+        if data_dir.is_dir() {
+            copy_data_dir(&data_dir, &snapshot.data_dir(), &db_path)?;
+        }
+
+        if db_path.exists() {
+            let conn = rusqlite::Connection::open(&db_path)?;
+            conn.execute("VACUUM INTO ?1", [snapshot.db_path().to_string_lossy()])?;
+        }
+        if let Some(at) = at {
+            SqliteTaskStore::new(snapshot.db_path())?.rewind_to_audit_entry(at)?;
+        }
+        Ok(snapshot)
+    }
+
+    /// Get the copied data directory.
+    #[must_use]
+    pub fn data_dir(&self) -> PathBuf {
+        self.root.join("data")
+    }
+
+    /// Get the copied database.
+    #[must_use]
+    pub fn db_path(&self) -> PathBuf {
+        self.data_dir().join(paths::DATABASE_FILENAME)
+    }
+
+    /// Point the data directory and database paths at the snapshot.
+    ///
+    /// The previous environment is restored when the returned guard is dropped.
+    #[must_use]
+    pub fn activate(&self) -> EnvGuard {
+        EnvGuard::set(&[
+            (paths::DATA_DIR_ENV, self.data_dir().into_os_string()),
+            (paths::DB_PATH_ENV, self.db_path().into_os_string()),
+        ])
+    }
+}
+
+impl Drop for Snapshot {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.root);
+    }
+}
+
+/// Restores environment variables when dropped.
+#[derive(Debug)]
+pub struct EnvGuard {
+    previous: Vec<(&'static str, Option<OsString>)>,
+}
+
+impl EnvGuard {
+    /// Set environment variables, remembering their previous values.
+    fn set(vars: &[(&'static str, OsString)]) -> Self {
+        let previous = vars
+            .iter()
+            .map(|(name, value)| {
+                let old = std::env::var_os(name);
+                std::env::set_var(name, value);
+                (*name, old)
+            })
+            .collect();
+        Self { previous }
+    }
+
+    /// Unset environment variables, remembering their previous values.
+    ///
+    /// Variables that are not set are left alone.
+    pub(crate) fn remove(names: &[&'static str]) -> Self {
+        let previous = names
+            .iter()
+            .filter_map(|name| std::env::var_os(name).map(|old| (*name, Some(old))))
+            .inspect(|(name, _)| std::env::remove_var(name))
+            .collect();
+        Self { previous }
+    }
+}
+
+impl Drop for EnvGuard {
+    fn drop(&mut self) {
+        for (name, value) in &self.previous {
+            match value {
+                Some(value) => std::env::set_var(name, value),
+                None => std::env::remove_var(name),
+            }
+        }
+    }
+}
+
+/// Copy a data directory, skipping logs and the database files.
+fn copy_data_dir(from: &Path, to: &Path, db_path: &Path) -> std::io::Result<()> {
+    let db_prefix = db_path.to_string_lossy();
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        let path = entry.path();
+        if path.to_string_lossy().starts_with(db_prefix.as_ref()) {
+            continue;
+        }
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            if entry.file_name() == "logs" {
+                continue;
+            }
+            std::fs::create_dir_all(&target)?;
+            copy_data_dir(&path, &target, db_path)?;
+        } else {
+            std::fs::copy(&path, &target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Prepare captured hook input for replay.
+///
+/// If `transcript` is given, it replaces the input's `transcript_path`.
+///
+/// # Errors
+///
+/// Returns an error if the input is not a JSON object.
+pub fn prepare_input(input: &str, transcript: Option<&Path>) -> Result<String> {
+    let mut value: serde_json::Value = serde_json::from_str(input)?;
+    let object = value
+        .as_object_mut()
+        .ok_or_else(|| Error::Config("hook input must be a JSON object".to_string()))?;
+    if let Some(transcript) = transcript {
+        object.insert(
+            "transcript_path".to_string(),
+            serde_json::Value::String(transcript.to_string_lossy().into_owned()),
+        );
+    }
+    Ok(value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tasks::{Priority, TaskStore};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_prepare_input() {
+        let input = r#"{"session_id": "abc", "transcript_path": "/old.jsonl"}"#;
+        let prepared = prepare_input(input, Some(Path::new("/new.jsonl"))).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&prepared).unwrap();
+        assert_eq!(value["transcript_path"], "/new.jsonl");
+        assert_eq!(value["session_id"], "abc");
+
+        assert!(prepare_input("[]", None).is_err());
+        assert!(prepare_input("not json", None).is_err());
+    }
+
+    #[test]
+    fn test_snapshot_copies_state_and_rewinds() {
+        let dir = TempDir::new().unwrap();
+        let store = SqliteTaskStore::for_project(dir.path()).unwrap();
+        store.create_task("Before", "", Priority::Medium).unwrap();
+        let at = store.get_audit_log(None, None).unwrap().iter().map(|e| e.id).max().unwrap();
+        store.create_task("After", "", Priority::Medium).unwrap();
+        let data_dir = paths::project_data_dir(dir.path());
+        std::fs::write(data_dir.join("marker"), "").unwrap();
+        std::fs::create_dir_all(data_dir.join("logs")).unwrap();
+
+        let snapshot = Snapshot::create(dir.path(), Some(at)).unwrap();
+        assert!(snapshot.data_dir().join("marker").exists());
+        assert!(!snapshot.data_dir().join("logs").exists());
+        let copy = SqliteTaskStore::new(snapshot.db_path()).unwrap();
+        let titles: Vec<String> = copy
+            .list_tasks(crate::tasks::TaskFilter::default())
+            .unwrap()
+            .into_iter()
+            .map(|t| t.title)
+            .collect();
+        assert_eq!(titles, vec!["Before"]);
+        assert_eq!(store.list_tasks(crate::tasks::TaskFilter::default()).unwrap().len(), 2);
+
+        let root = snapshot.root.clone();
+        drop(snapshot);
+        assert!(!root.exists());
+    }
+}
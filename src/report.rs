@@ -0,0 +1,331 @@
+//! Activity summary reports.
+//!
+//! `claude-reliability report --since 7d` aggregates what happened over a
+//! period (work items created and completed, questions asked and answered,
+//! stop blocks by reason and the quality check failure rate) into a Markdown
+//! report suitable for a team update. With `--narrate`, a sub-agent adds a
+//! short narrative summary at the top.
+//!
+//! Work items and questions come from the task database. Stop decisions and
+//! quality check results are recorded by the stop hook in the
+//! `hook_outcomes` table as they happen.
+
+use crate::error::Result;
+use crate::export::status_transition;
+use crate::storage::SqliteStore;
+use crate::tasks::{SqliteTaskStore, TaskStore};
+use crate::traits::SubAgent;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::fmt::Write;
+use std::path::Path;
+
+/// Hook outcome kind recording a stop hook decision.
+pub const STOP_OUTCOME: &str = "stop";
+
+/// Hook outcome kind recording a quality check result.
+pub const QUALITY_CHECK_OUTCOME: &str = "quality_check";
+
+/// Format of timestamps in the databases.
+const DB_TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// Maximum number of completed work items listed by title.
+const MAX_LISTED: usize = 20;
+
+/// Record a stop hook decision and the check that made it.
+///
+/// Errors are ignored — reporting should never break a hook.
+pub fn record_stop_decision(base_dir: &Path, decision: &str, reason: &str) {
+    if let Ok(store) = SqliteStore::new(base_dir) {
+        let _ = store.record_hook_outcome(STOP_OUTCOME, decision, Some(reason));
+    }
+}
+
+/// Record the result of a quality check run (`passed`, `failed`, `timeout` or `error`).
+///
+/// Errors are ignored — reporting should never break a hook.
+pub fn record_quality_check(base_dir: &Path, outcome: &str) {
+    if let Ok(store) = SqliteStore::new(base_dir) {
+        let _ = store.record_hook_outcome(QUALITY_CHECK_OUTCOME, outcome, None);
+    }
+}
+
+/// Stop blocks for one reason.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BlockCount {
+    /// The check that blocked the stop.
+    pub reason: String,
+    /// Number of blocks.
+    pub count: u64,
+}
+
+/// Activity over a period.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Report {
+    /// Start of the period (UTC).
+    pub since: String,
+    /// End of the period (UTC).
+    pub until: String,
+    /// Number of work items created.
+    pub tasks_created: usize,
+    /// Titles of the work items completed, oldest first.
+    pub tasks_completed: Vec<String>,
+    /// Number of work items abandoned.
+    pub tasks_abandoned: usize,
+    /// Number of questions asked.
+    pub questions_asked: usize,
+    /// Number of questions answered.
+    pub questions_answered: usize,
+    /// Number of stops the stop hook allowed.
+    pub stops_allowed: u64,
+    /// Stop blocks by reason, most frequent first.
+    pub stop_blocks: Vec<BlockCount>,
+    /// Number of quality check runs.
+    pub quality_checks: u64,
+    /// Number of quality check runs that failed, timed out or could not run.
+    pub quality_check_failures: u64,
+}
+
+impl Report {
+    /// Total number of stops blocked.
+    #[must_use]
+    pub fn stops_blocked(&self) -> u64 {
+        self.stop_blocks.iter().map(|b| b.count).sum()
+    }
+
+    /// Fraction of quality check runs that failed, or `None` if none ran.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)] // Run counts are far below 2^52
+    pub fn quality_check_failure_rate(&self) -> Option<f64> {
+        (self.quality_checks > 0)
+            .then(|| self.quality_check_failures as f64 / self.quality_checks as f64)
+    }
+
+    /// Render the report as Markdown, with an optional narrative summary.
+    #[must_use]
+    pub fn to_markdown(&self, narrative: Option<&str>) -> String {
+        let mut out = format!("# Activity Report\n\n{} to {} UTC\n\n", self.since, self.until);
+        if let Some(narrative) = narrative.map(str::trim).filter(|n| !n.is_empty()) {
+            let _ = write!(out, "{narrative}\n\n");
+        }
+
+        let _ = write!(
+            out,
+            "## Work Items\n\n- Created: {}\n- Completed: {}\n- Abandoned: {}\n",
+            self.tasks_created,
+            self.tasks_completed.len(),
+            self.tasks_abandoned
+        );
+        if !self.tasks_completed.is_empty() {
+            out.push_str("\nCompleted:\n\n");
+            for title in self.tasks_completed.iter().take(MAX_LISTED) {
+                let _ = writeln!(out, "- {title}");
+            }
+            if self.tasks_completed.len() > MAX_LISTED {
+                let _ = writeln!(out, "- ...and {} more", self.tasks_completed.len() - MAX_LISTED);
+            }
+        }
+
+        let _ = write!(
+            out,
+            "\n## Questions\n\n- Asked: {}\n- Answered: {}\n",
+            self.questions_asked, self.questions_answered
+        );
+
+        let _ = write!(
+            out,
+            "\n## Stop Hook\n\n- Stops allowed: {}\n- Stops blocked: {}\n",
+            self.stops_allowed,
+            self.stops_blocked()
+        );
+        if !self.stop_blocks.is_empty() {
+            out.push_str("\n| Blocked by | Count |\n| --- | --- |\n");
+            for block in &self.stop_blocks {
+                let _ = writeln!(out, "| {} | {} |", block.reason, block.count);
+            }
+        }
+
+        out.push_str("\n## Quality Checks\n\n");
+        match self.quality_check_failure_rate() {
+            Some(rate) => {
+                let _ = writeln!(
+                    out,
+                    "- Runs: {}\n- Failures: {} ({:.0}%)",
+                    self.quality_checks,
+                    self.quality_check_failures,
+                    rate * 100.0
+                );
+            }
+            None => out.push_str("No quality checks ran.\n"),
+        }
+        out
+    }
+}
+
+/// Gather the activity from `since` until now.
+///
+/// # Errors
+///
+/// Returns an error if the databases cannot be read.
+pub fn build(base_dir: &Path, since: DateTime<Utc>) -> Result<Report> {
+    let since_db = since.format(DB_TIMESTAMP_FORMAT).to_string();
+    let tasks = SqliteTaskStore::for_project(base_dir)?;
+
+    let mut audit = tasks.get_audit_log(None, None)?;
+    audit.retain(|entry| entry.timestamp >= since_db);
+    audit.sort_by_key(|entry| entry.id);
+    let transitions: Vec<_> = audit.iter().filter_map(status_transition).collect();
+    let tasks_created = transitions.iter().filter(|t| t.from.is_none()).count();
+    let tasks_completed = transitions
+        .iter()
+        .filter(|t| t.from.is_some() && t.to == "complete")
+        .map(|t| t.title.clone().unwrap_or_default())
+        .collect();
+    let tasks_abandoned =
+        transitions.iter().filter(|t| t.from.is_some() && t.to == "abandoned").count();
+
+    let questions = tasks.list_questions(false)?;
+    let questions_asked = questions.iter().filter(|q| q.created_at >= since_db).count();
+    let questions_answered = questions
+        .iter()
+        .filter(|q| q.answered_at.as_ref().is_some_and(|at| *at >= since_db))
+        .count();
+
+    let mut stops_allowed = 0;
+    let mut stop_blocks = Vec::new();
+    let mut quality_checks = 0;
+    let mut quality_check_failures = 0;
+    for outcome in SqliteStore::new(base_dir)?.hook_outcome_counts(&since_db)? {
+        match (outcome.kind.as_str(), outcome.outcome.as_str()) {
+            (STOP_OUTCOME, "block") => stop_blocks.push(BlockCount {
+                reason: outcome.reason.unwrap_or_else(|| "unknown".to_string()),
+                count: outcome.count,
+            }),
+            (STOP_OUTCOME, _) => stops_allowed += outcome.count,
+            (QUALITY_CHECK_OUTCOME, result) => {
+                quality_checks += outcome.count;
+                if result != "passed" {
+                    quality_check_failures += outcome.count;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(Report {
+        since: since.format(DB_TIMESTAMP_FORMAT).to_string(),
+        until: Utc::now().format(DB_TIMESTAMP_FORMAT).to_string(),
+        tasks_created,
+        tasks_completed,
+        tasks_abandoned,
+        questions_asked,
+        questions_answered,
+        stops_allowed,
+        stop_blocks,
+        quality_checks,
+        quality_check_failures,
+    })
+}
+
+/// Render the report for the period as Markdown, narrated by a sub-agent if given.
+///
+/// If the sub-agent fails, the report is rendered without a narrative.
+///
+/// # Errors
+///
+/// Returns an error if the databases cannot be read.
+pub fn render(
+    base_dir: &Path,
+    since: DateTime<Utc>,
+    sub_agent: Option<&dyn SubAgent>,
+) -> Result<String> {
+    let report = build(base_dir, since)?;
+    let narrative = sub_agent.and_then(|agent| {
+        agent
+            .narrate_report(&report.to_markdown(None))
+            .map_err(|e| tracing::warn!(error = %e, "report narration failed"))
+            .ok()
+    });
+    Ok(report.to_markdown(narrative.as_deref()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tasks::{Priority, Status, TaskUpdate};
+    use crate::testing::{FailingSubAgent, MockSubAgent};
+    use tempfile::TempDir;
+
+    fn complete(store: &SqliteTaskStore, id: &str) {
+        let update = TaskUpdate { status: Some(Status::Complete), ..Default::default() };
+        store.update_task(id, update).unwrap();
+    }
+
+    #[test]
+    fn test_build_aggregates_activity() {
+        let dir = TempDir::new().unwrap();
+        let store = SqliteTaskStore::for_project(dir.path()).unwrap();
+        let done = store.create_task("Ship the parser", "", Priority::High).unwrap();
+        store.create_task("Write docs", "", Priority::Low).unwrap();
+        complete(&store, &done.id);
+        let question = store.create_question("Which database?").unwrap();
+        store.create_question("Which licence?").unwrap();
+        store.answer_question(&question.id, "SQLite").unwrap();
+
+        record_stop_decision(dir.path(), "block", "uncommitted_changes");
+        record_stop_decision(dir.path(), "block", "uncommitted_changes");
+        record_stop_decision(dir.path(), "block", "quality_check");
+        record_stop_decision(dir.path(), "allow", "all_checks_passed");
+        record_quality_check(dir.path(), "passed");
+        record_quality_check(dir.path(), "failed");
+        record_quality_check(dir.path(), "timeout");
+
+        let report = build(dir.path(), Utc::now() - chrono::Duration::days(7)).unwrap();
+        assert_eq!(report.tasks_created, 2);
+        assert_eq!(report.tasks_completed, vec!["Ship the parser".to_string()]);
+        assert_eq!(report.questions_asked, 2);
+        assert_eq!(report.questions_answered, 1);
+        assert_eq!(report.stops_allowed, 1);
+        assert_eq!(report.stops_blocked(), 3);
+        assert_eq!(
+            report.stop_blocks[0],
+            BlockCount { reason: "uncommitted_changes".to_string(), count: 2 }
+        );
+        assert_eq!(report.quality_checks, 3);
+        assert_eq!(report.quality_check_failures, 2);
+
+        let markdown = report.to_markdown(None);
+        assert!(markdown.contains("- Completed: 1\n"));
+        assert!(markdown.contains("- Ship the parser\n"));
+        assert!(markdown.contains("| uncommitted_changes | 2 |"));
+        assert!(markdown.contains("- Failures: 2 (67%)"));
+    }
+
+    #[test]
+    fn test_build_excludes_earlier_activity() {
+        let dir = TempDir::new().unwrap();
+        let store = SqliteTaskStore::for_project(dir.path()).unwrap();
+        store.create_task("Old task", "", Priority::Medium).unwrap();
+        record_quality_check(dir.path(), "failed");
+
+        let report = build(dir.path(), Utc::now() + chrono::Duration::hours(1)).unwrap();
+        assert_eq!(report.tasks_created, 0);
+        assert_eq!(report.quality_checks, 0);
+        assert!(report.to_markdown(None).contains("No quality checks ran."));
+    }
+
+    #[test]
+    fn test_render_with_narrative() {
+        let dir = TempDir::new().unwrap();
+        let since = Utc::now() - chrono::Duration::days(7);
+        let mut agent = MockSubAgent::new();
+        agent.expect_report_narrative("A quiet week.");
+
+        let narrated = render(dir.path(), since, Some(&agent)).unwrap();
+        assert!(narrated.contains("UTC\n\nA quiet week.\n\n## Work Items"));
+
+        let failing = FailingSubAgent::new("unavailable");
+        let plain = render(dir.path(), since, Some(&failing)).unwrap();
+        assert!(plain.contains("UTC\n\n## Work Items"));
+    }
+}
@@ -0,0 +1,212 @@
+//! Working-hours schedules for autonomous behavior.
+//!
+//! A schedule such as `09:00-18:00 Mon-Fri` limits when the hooks push the
+//! agent to keep working on its own, so that sessions left running overnight
+//! or at the weekend wind down instead of using tokens while nobody is watching.
+//!
+//! The format is a time range, optionally followed by days and a time zone:
+//!
+//! - `09:00-18:00` - every day, in the system's local time
+//! - `09:00-18:00 Mon-Fri` - weekdays only
+//! - `22:00-06:00 Fri,Sat` - overnight, starting on Friday and Saturday evenings
+//! - `09:00-17:30 Mon-Thu +01:00` - with a fixed UTC offset (`UTC` and `local` also work)
+
+use crate::error::{Error, Result};
+use chrono::{DateTime, Datelike, FixedOffset, Local, NaiveDateTime, NaiveTime, Utc, Weekday};
+use std::str::FromStr;
+
+/// Day names accepted in schedules, in `Weekday::num_days_from_monday` order.
+const DAY_NAMES: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+
+/// The time zone a schedule is expressed in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScheduleZone {
+    /// The system's local time zone, including daylight saving changes.
+    Local,
+    /// A fixed offset from UTC.
+    Fixed(FixedOffset),
+}
+
+/// Hours of the week during which autonomous work is allowed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkSchedule {
+    start: NaiveTime,
+    end: NaiveTime,
+    /// Days the window starts on, indexed from Monday.
+    days: [bool; 7],
+    zone: ScheduleZone,
+}
+
+impl WorkSchedule {
+    /// Check whether the given instant falls inside the schedule.
+    ///
+    /// A window whose end is before its start runs past midnight, and belongs
+    /// to the day it starts on.
+    #[must_use]
+    pub fn contains(&self, now: DateTime<Utc>) -> bool {
+        let local = match self.zone {
+            ScheduleZone::Local => now.with_timezone(&Local).naive_local(),
+            ScheduleZone::Fixed(offset) => now.with_timezone(&offset).naive_local(),
+        };
+        self.contains_local(local)
+    }
+
+    /// Check whether a wall-clock time in the schedule's zone falls inside it.
+    fn contains_local(&self, local: NaiveDateTime) -> bool {
+        let time = local.time();
+        let runs_on = |day: Weekday| self.days[day.num_days_from_monday() as usize];
+        if self.start <= self.end {
+            return runs_on(local.weekday()) && time >= self.start && time < self.end;
+        }
+        (time >= self.start && runs_on(local.weekday()))
+            || (time < self.end && runs_on(local.weekday().pred()))
+    }
+}
+
+impl FromStr for WorkSchedule {
+    type Err = Error;
+
+    fn from_str(schedule: &str) -> Result<Self> {
+        let invalid =
+            |reason: &str| Error::Config(format!("invalid schedule '{schedule}': {reason}"));
+        let mut parts = schedule.split_whitespace();
+
+        let range =
+            parts.next().ok_or_else(|| invalid("expected a time range like 09:00-18:00"))?;
+        let (start, end) = range
+            .split_once('-')
+            .ok_or_else(|| invalid("expected a time range like 09:00-18:00"))?;
+        let parse_time = |time: &str| {
+            NaiveTime::parse_from_str(time, "%H:%M")
+                .map_err(|_| invalid(&format!("'{time}' is not a time like 09:00")))
+        };
+        let (start, end) = (parse_time(start)?, parse_time(end)?);
+        if start == end {
+            return Err(invalid("the time range is empty"));
+        }
+
+        let mut days = [true; 7];
+        let mut zone = ScheduleZone::Local;
+        for part in parts {
+            if let Some(parsed) = parse_zone(part) {
+                zone = parsed;
+            } else {
+                days = parse_days(part).ok_or_else(|| {
+                    invalid(&format!("'{part}' is not a list of days like Mon-Fri or a time zone"))
+                })?;
+            }
+        }
+
+        Ok(Self { start, end, days, zone })
+    }
+}
+
+/// Parse a time zone: `local`, `UTC`/`Z`, or an offset like `+01:00`.
+fn parse_zone(zone: &str) -> Option<ScheduleZone> {
+    match zone.to_lowercase().as_str() {
+        "local" => Some(ScheduleZone::Local),
+        "utc" | "z" => FixedOffset::east_opt(0).map(ScheduleZone::Fixed),
+        _ => {
+            let (sign, rest) = match zone.as_bytes().first()? {
+                b'+' => (1, &zone[1..]),
+                b'-' => (-1, &zone[1..]),
+                _ => return None,
+            };
+            let (hours, minutes) = rest.split_once(':').unwrap_or((rest, "0"));
+            let seconds = hours.parse::<i32>().ok()? * 3600 + minutes.parse::<i32>().ok()? * 60;
+            FixedOffset::east_opt(sign * seconds).map(ScheduleZone::Fixed)
+        }
+    }
+}
+
+/// Parse days like `Mon-Fri`, `Sat,Sun`, `Fri-Mon` or `daily`.
+fn parse_days(spec: &str) -> Option<[bool; 7]> {
+    let spec = spec.to_lowercase();
+    if spec == "daily" {
+        return Some([true; 7]);
+    }
+    let day = |name: &str| DAY_NAMES.iter().position(|d| name.starts_with(d));
+
+    let mut days = [false; 7];
+    for item in spec.split(',') {
+        if let Some((from, to)) = item.split_once('-') {
+            let (from, to) = (day(from)?, day(to)?);
+            let mut current = from;
+            loop {
+                days[current] = true;
+                if current == to {
+                    break;
+                }
+                current = (current + 1) % 7;
+            }
+        } else {
+            days[day(item)?] = true;
+        }
+    }
+    Some(days)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A local time on 2024-01-01 (a Monday) plus `days_later` days.
+    fn at(days_later: u64, time: &str) -> NaiveDateTime {
+        let date =
+            chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap() + chrono::Days::new(days_later);
+        date.and_time(NaiveTime::parse_from_str(time, "%H:%M").unwrap())
+    }
+
+    #[test]
+    fn test_weekday_hours() {
+        let schedule: WorkSchedule = "09:00-18:00 Mon-Fri".parse().unwrap();
+        assert!(schedule.contains_local(at(0, "09:00")));
+        assert!(schedule.contains_local(at(4, "17:59")));
+        assert!(!schedule.contains_local(at(0, "18:00")));
+        assert!(!schedule.contains_local(at(0, "08:59")));
+        assert!(!schedule.contains_local(at(5, "12:00"))); // Saturday
+    }
+
+    #[test]
+    fn test_overnight_window_belongs_to_start_day() {
+        let schedule: WorkSchedule = "22:00-06:00 Fri".parse().unwrap();
+        assert!(schedule.contains_local(at(4, "23:00"))); // Friday night
+        assert!(schedule.contains_local(at(5, "05:00"))); // Early Saturday
+        assert!(!schedule.contains_local(at(5, "23:00"))); // Saturday night
+        assert!(!schedule.contains_local(at(4, "05:00"))); // Early Friday
+    }
+
+    #[test]
+    fn test_day_lists_and_wrapping_ranges() {
+        let schedule: WorkSchedule = "10:00-12:00 Sat,Sun".parse().unwrap();
+        assert!(schedule.contains_local(at(6, "11:00")));
+        assert!(!schedule.contains_local(at(2, "11:00")));
+
+        let schedule: WorkSchedule = "10:00-12:00 Fri-Mon".parse().unwrap();
+        assert!(schedule.contains_local(at(0, "11:00")));
+        assert!(schedule.contains_local(at(5, "11:00")));
+        assert!(!schedule.contains_local(at(1, "11:00")));
+
+        let every_day: WorkSchedule = "10:00-12:00".parse().unwrap();
+        assert_eq!(every_day, "10:00-12:00 daily".parse().unwrap());
+    }
+
+    #[test]
+    fn test_fixed_offset_zone() {
+        let schedule: WorkSchedule = "09:00-18:00 Mon-Fri +02:00".parse().unwrap();
+        // 07:30 UTC on a Monday is 09:30 at +02:00
+        let now = at(0, "07:30").and_utc();
+        assert!(schedule.contains(now));
+        assert!(!schedule.contains(at(0, "16:30").and_utc()));
+
+        let utc: WorkSchedule = "09:00-18:00 UTC".parse().unwrap();
+        assert!(utc.contains(at(0, "09:30").and_utc()));
+    }
+
+    #[test]
+    fn test_invalid_schedules() {
+        for schedule in ["", "9-5", "09:00", "09:00-09:00", "09:00-18:00 Weekdays", "25:00-26:00"] {
+            assert!(schedule.parse::<WorkSchedule>().is_err(), "{schedule} should be invalid");
+        }
+    }
+}
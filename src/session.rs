@@ -7,28 +7,109 @@
 //! - Path-based functions for backward compatibility (create `SqliteStore` internally)
 //! - Store-based functions for testability (take a `&dyn StateStore` parameter)
 
+use crate::config::ProjectConfig;
 use crate::error::Result;
-use crate::storage::{markers, SqliteStore};
+use crate::storage::{
+    markers, ApiErrorState, AutoWorkSession, Marker, SessionRecord, SessionState, SqliteStore,
+    WorkItemAssignment,
+};
 use crate::traits::StateStore;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::path::Path;
 
+/// Session ID used for per-session state when no hook has recorded a session.
+const UNKNOWN_SESSION: &str = "unknown";
+
+thread_local! {
+    /// The session the current hook call belongs to, if known.
+    static CURRENT_SESSION: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Set the session the current hook call belongs to.
+///
+/// Markers are scoped to this session: markers set by another session are
+/// treated as stale. With no session set, markers are shared by all sessions.
+pub fn set_current_session(session_id: Option<&str>) {
+    CURRENT_SESSION.with(|current| *current.borrow_mut() = session_id.map(str::to_string));
+}
+
+/// Get the session the current hook call belongs to, if known.
+#[must_use]
+pub fn current_session() -> Option<String> {
+    CURRENT_SESSION.with(|current| current.borrow().clone())
+}
+
+/// Marker lifetimes configured for the project, by marker.
+fn configured_marker_ttls(base_dir: &Path) -> HashMap<Marker, u32> {
+    let Ok(Some(config)) = ProjectConfig::load_from(base_dir) else {
+        return HashMap::new();
+    };
+    config
+        .marker_ttl_minutes
+        .iter()
+        .filter_map(|(name, ttl)| {
+            let marker = Marker::from_name(name);
+            if marker.is_none() {
+                tracing::warn!("Ignoring marker_ttl_minutes for unknown marker '{name}'");
+            }
+            marker.map(|m| (m, *ttl))
+        })
+        .collect()
+}
+
 /// Get or create a `SQLite` store for the given base directory.
 ///
-/// This also performs migration from old file-based state if needed.
+/// The store's markers are scoped to the current session and use the
+/// project's configured marker lifetimes. This also performs migration from
+/// old file-based state if needed.
 fn get_store(base_dir: &Path) -> Result<SqliteStore> {
-    let store = SqliteStore::new(base_dir)?;
+    let store = SqliteStore::new(base_dir)?
+        .for_session(current_session().as_deref())
+        .with_marker_ttls(configured_marker_ttls(base_dir));
     // Migrate any existing file-based state
     store.migrate_from_files(base_dir)?;
     Ok(store)
 }
 
+/// Get the markers that are currently set.
+///
+/// # Errors
+///
+/// Returns an error if the database operation fails.
+pub fn active_markers(base_dir: &Path) -> Result<Vec<Marker>> {
+    get_store(base_dir)?.active_markers()
+}
+
+/// Clear several markers in a single transaction.
+///
+/// # Errors
+///
+/// Returns an error if the database operation fails.
+pub fn clear_markers(base_dir: &Path, markers: &[Marker]) -> Result<()> {
+    let changes: Vec<(Marker, bool)> = markers.iter().map(|&m| (m, false)).collect();
+    get_store(base_dir)?.update_markers(&changes)
+}
+
+/// Clear every expired marker, using the project's configured marker lifetimes.
+///
+/// Returns the number of markers cleared.
+///
+/// # Errors
+///
+/// Returns an error if the database operation fails.
+pub fn prune_expired_markers(base_dir: &Path) -> Result<usize> {
+    get_store(base_dir)?.prune_expired_markers()
+}
+
 /// Default path for the problem mode marker file (legacy, for migration).
 pub const PROBLEM_MODE_MARKER_PATH: &str = ".claude/problem-mode.local";
 
 /// Check if problem mode is active (marker exists in database).
 #[must_use]
 pub fn is_problem_mode_active(base_dir: &Path) -> bool {
-    get_store(base_dir).map(|s| s.has_marker(markers::PROBLEM_MODE)).unwrap_or(false)
+    get_store(base_dir).is_ok_and(|s| s.has_marker(markers::PROBLEM_MODE))
 }
 
 /// Check if problem mode is active using a provided store.
@@ -79,7 +160,7 @@ pub const NEEDS_VALIDATION_MARKER_PATH: &str = ".claude/needs-validation.local";
 /// Check if validation is needed (marker exists in database).
 #[must_use]
 pub fn needs_validation(base_dir: &Path) -> bool {
-    get_store(base_dir).map(|s| s.has_marker(markers::NEEDS_VALIDATION)).unwrap_or(false)
+    get_store(base_dir).is_ok_and(|s| s.has_marker(markers::NEEDS_VALIDATION))
 }
 
 /// Check if validation is needed using a provided store.
@@ -124,13 +205,40 @@ pub fn clear_needs_validation_with_store(store: &dyn StateStore) -> Result<()> {
     store.clear_marker(markers::NEEDS_VALIDATION)
 }
 
+/// Record a file modified since validation last passed, relative to the project.
+///
+/// # Errors
+///
+/// Returns an error if the database operation fails.
+pub fn record_touched_file(base_dir: &Path, path: &str) -> Result<()> {
+    get_store(base_dir)?.add_touched_file(path)
+}
+
+/// Get the files modified since validation last passed.
+///
+/// # Errors
+///
+/// Returns an error if the database operation fails.
+pub fn get_touched_files(base_dir: &Path) -> Result<Vec<String>> {
+    get_store(base_dir)?.touched_files()
+}
+
+/// Forget the files modified since validation last passed.
+///
+/// # Errors
+///
+/// Returns an error if the database operation fails.
+pub fn clear_touched_files(base_dir: &Path) -> Result<()> {
+    get_store(base_dir)?.clear_touched_files()
+}
+
 /// Path for "must reflect" marker file (legacy, for migration).
 pub const MUST_REFLECT_MARKER_PATH: &str = ".claude/must-reflect.local";
 
 /// Check if the `must_reflect` marker exists.
 #[must_use]
 pub fn has_reflect_marker(base_dir: &Path) -> bool {
-    get_store(base_dir).map(|s| s.has_marker(markers::MUST_REFLECT)).unwrap_or(false)
+    get_store(base_dir).is_ok_and(|s| s.has_marker(markers::MUST_REFLECT))
 }
 
 /// Check if the `must_reflect` marker exists using a provided store.
@@ -178,7 +286,7 @@ pub fn clear_reflect_marker_with_store(store: &dyn StateStore) -> Result<()> {
 /// Check if emergency stop is active (marker exists in database).
 #[must_use]
 pub fn is_emergency_stop_active(base_dir: &Path) -> bool {
-    get_store(base_dir).map(|s| s.has_marker(markers::EMERGENCY_STOP)).unwrap_or(false)
+    get_store(base_dir).is_ok_and(|s| s.has_marker(markers::EMERGENCY_STOP))
 }
 
 /// Check if emergency stop is active using a provided store.
@@ -226,7 +334,7 @@ pub fn clear_emergency_stop_with_store(store: &dyn StateStore) -> Result<()> {
 /// Check if the work item reminder has been shown this session.
 #[must_use]
 pub fn has_work_item_reminded(base_dir: &Path) -> bool {
-    get_store(base_dir).map(|s| s.has_marker(markers::WORK_ITEM_REMINDED)).unwrap_or(false)
+    get_store(base_dir).is_ok_and(|s| s.has_marker(markers::WORK_ITEM_REMINDED))
 }
 
 /// Check if the work item reminder has been shown using a provided store.
@@ -271,6 +379,414 @@ pub fn clear_work_item_reminded_with_store(store: &dyn StateStore) -> Result<()>
     store.clear_marker(markers::WORK_ITEM_REMINDED)
 }
 
+/// Record a stop block and return how many times in a row it has repeated.
+///
+/// # Errors
+///
+/// Returns an error if the database operation fails.
+pub fn record_stop_block(base_dir: &Path, signature: &str) -> Result<u32> {
+    get_store(base_dir)?.record_block(signature)
+}
+
+/// Clear the record of repeated stop blocks.
+///
+/// # Errors
+///
+/// Returns an error if the database operation fails.
+pub fn clear_stop_block_streak(base_dir: &Path) -> Result<()> {
+    get_store(base_dir)?.clear_block_streak()
+}
+
+/// Record that the stop hook blocked a session in a given working state.
+///
+/// Returns the checks that have blocked since the last user message or
+/// change of state, oldest first.
+///
+/// # Errors
+///
+/// Returns an error if the database operation fails.
+pub fn record_stop_injection(
+    base_dir: &Path,
+    session_id: &str,
+    state: &str,
+    check: &str,
+) -> Result<Vec<String>> {
+    get_store(base_dir)?.record_injection(session_id, state, check)
+}
+
+/// Clear the record of the stop hook's blocks in a session.
+///
+/// # Errors
+///
+/// Returns an error if the database operation fails.
+pub fn clear_stop_injections(base_dir: &Path, session_id: &str) -> Result<()> {
+    get_store(base_dir)?.clear_injection_streak(session_id)
+}
+
+/// Allow one change to a protected file until `until`.
+///
+/// # Errors
+///
+/// Returns an error if the database operation fails.
+pub fn grant_protected_change(
+    base_dir: &Path,
+    justification: &str,
+    until: DateTime<Utc>,
+) -> Result<()> {
+    get_store(base_dir)?.grant_protected_change(justification, &until.to_rfc3339())
+}
+
+/// Use up the grant for a change to a protected file, returning its
+/// justification if it has not expired.
+///
+/// Unreadable state counts as no grant.
+#[must_use]
+pub fn take_protected_change_grant(base_dir: &Path) -> Option<String> {
+    let (justification, expires_at) =
+        get_store(base_dir).ok()?.take_protected_change_grant().ok()??;
+    let expires_at = DateTime::parse_from_rfc3339(&expires_at).ok()?;
+    (expires_at > Utc::now()).then_some(justification)
+}
+
+/// Record a stash the agent created, so the stop hook can check it was resolved.
+///
+/// # Errors
+///
+/// Returns an error if the database operation fails.
+pub fn record_agent_stash(base_dir: &Path, sha: &str) -> Result<()> {
+    get_store(base_dir)?.add_agent_stash(sha)
+}
+
+/// Get the stashes the agent created that have not been resolved.
+///
+/// # Errors
+///
+/// Returns an error if the database operation fails.
+pub fn get_agent_stashes(base_dir: &Path) -> Result<Vec<String>> {
+    get_store(base_dir)?.agent_stashes()
+}
+
+/// Stop tracking a stash the agent created (it was applied or dropped).
+///
+/// # Errors
+///
+/// Returns an error if the database operation fails.
+pub fn forget_agent_stash(base_dir: &Path, sha: &str) -> Result<()> {
+    get_store(base_dir)?.remove_agent_stash(sha)
+}
+
+/// Record an emergency stop request and its outcome.
+///
+/// # Errors
+///
+/// Returns an error if the database operation fails.
+pub fn record_emergency_stop_attempt(
+    base_dir: &Path,
+    session_id: &str,
+    outcome: &str,
+) -> Result<()> {
+    get_store(base_dir)?.add_emergency_stop_attempt(session_id, outcome)
+}
+
+/// Count a session's emergency stop requests in the last hour.
+///
+/// # Errors
+///
+/// Returns an error if the database operation fails.
+pub fn emergency_stop_attempts_last_hour(base_dir: &Path, session_id: &str) -> Result<u32> {
+    get_store(base_dir)?.recent_emergency_stop_attempts(session_id, 60)
+}
+
+/// Count a session's emergency stop rejections since one was last accepted or escalated.
+///
+/// # Errors
+///
+/// Returns an error if the database operation fails.
+pub fn emergency_stop_rejection_streak(base_dir: &Path, session_id: &str) -> Result<u32> {
+    get_store(base_dir)?.emergency_stop_rejection_streak(session_id)
+}
+
+/// Get the auto-work prompting state of the current session.
+///
+/// Unreadable state counts as no prompts yet.
+#[must_use]
+pub fn auto_work_session(base_dir: &Path) -> AutoWorkSession {
+    let session_id = current_session().unwrap_or_else(|| UNKNOWN_SESSION.to_string());
+    get_store(base_dir).and_then(|store| store.auto_work_session(&session_id)).unwrap_or_default()
+}
+
+/// Count an auto-work prompt in the current session.
+///
+/// # Errors
+///
+/// Returns an error if the database operation fails.
+pub fn record_auto_work_session_prompt(base_dir: &Path) -> Result<()> {
+    let session_id = current_session().unwrap_or_else(|| UNKNOWN_SESSION.to_string());
+    get_store(base_dir)?.record_auto_work_session_prompt(&session_id)
+}
+
+/// Record the number of completed work items at a check-in of the current session.
+///
+/// # Errors
+///
+/// Returns an error if the database operation fails.
+pub fn record_auto_work_check_in(base_dir: &Path, completed: u32) -> Result<()> {
+    let session_id = current_session().unwrap_or_else(|| UNKNOWN_SESSION.to_string());
+    get_store(base_dir)?.record_auto_work_check_in(&session_id, completed)
+}
+
+/// How long all hooks are disabled for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HooksDisabled {
+    /// Until `enable` is run.
+    UntilEnabled,
+    /// Until the given time.
+    Until(DateTime<Utc>),
+}
+
+/// Check whether all hooks are disabled.
+///
+/// A time limit that has passed, and unreadable state, count as enabled.
+#[must_use]
+pub fn hooks_disabled(base_dir: &Path) -> Option<HooksDisabled> {
+    let disabled = get_store(base_dir).ok()?.hooks_disabled().ok()??;
+    let Some(until) = disabled.until else {
+        return Some(HooksDisabled::UntilEnabled);
+    };
+    let until = DateTime::parse_from_rfc3339(&until).ok()?.with_timezone(&Utc);
+    (until > Utc::now()).then_some(HooksDisabled::Until(until))
+}
+
+/// Disable all hooks until the given time, or until re-enabled with `None`.
+///
+/// # Errors
+///
+/// Returns an error if the database operation fails.
+pub fn disable_hooks(base_dir: &Path, until: Option<DateTime<Utc>>) -> Result<()> {
+    get_store(base_dir)?.disable_hooks(until.map(|until| until.to_rfc3339()).as_deref())
+}
+
+/// Enable all hooks again, returning whether they were disabled.
+///
+/// # Errors
+///
+/// Returns an error if the database operation fails.
+pub fn enable_hooks(base_dir: &Path) -> Result<bool> {
+    get_store(base_dir)?.enable_hooks()
+}
+
+/// Get the work item assigned with `work assign`, if any.
+///
+/// Nothing is read, and no database is created, if the project has none yet.
+#[must_use]
+pub fn work_item_assignment(base_dir: &Path) -> Option<WorkItemAssignment> {
+    if !crate::paths::project_db_path(base_dir).exists() {
+        return None;
+    }
+    get_store(base_dir).ok()?.work_item_assignment().ok()?
+}
+
+/// Assign a work item, chaining on to the next ready item when it is done if `chain` is set.
+///
+/// # Errors
+///
+/// Returns an error if the database operation fails.
+pub fn assign_work_item(base_dir: &Path, work_item_id: &str, chain: bool) -> Result<()> {
+    get_store(base_dir)?.assign_work_item(work_item_id, chain)
+}
+
+/// Remove the work item assignment, returning whether there was one.
+///
+/// # Errors
+///
+/// Returns an error if the database operation fails.
+pub fn clear_work_item_assignment(base_dir: &Path) -> Result<bool> {
+    get_store(base_dir)?.clear_work_item_assignment()
+}
+
+/// Get the time until which auto-work prompting is paused, if it is.
+///
+/// Unreadable or unparseable state counts as not paused.
+#[must_use]
+pub fn auto_work_paused_until(base_dir: &Path) -> Option<DateTime<Utc>> {
+    let until = get_store(base_dir).ok()?.auto_work_paused_until().ok()??;
+    DateTime::parse_from_rfc3339(&until).ok().map(|until| until.with_timezone(&Utc))
+}
+
+/// Pause auto-work prompting until the given time.
+///
+/// # Errors
+///
+/// Returns an error if the database operation fails.
+pub fn pause_auto_work(base_dir: &Path, until: DateTime<Utc>) -> Result<()> {
+    get_store(base_dir)?.set_auto_work_paused_until(Some(&until.to_rfc3339()))
+}
+
+/// Resume auto-work prompting if it was paused.
+///
+/// # Errors
+///
+/// Returns an error if the database operation fails.
+pub fn resume_auto_work(base_dir: &Path) -> Result<()> {
+    get_store(base_dir)?.set_auto_work_paused_until(None)
+}
+
+/// Get the API errors reported by tool calls, shared by all hooks.
+///
+/// Unreadable state counts as no errors.
+#[must_use]
+pub fn api_error_state(base_dir: &Path) -> ApiErrorState {
+    get_store(base_dir).and_then(|store| store.api_error_state()).unwrap_or_default()
+}
+
+/// Get the time before which the API should not be called again, if set.
+///
+/// Unreadable or unparseable state counts as not set.
+#[must_use]
+pub fn api_retry_at(base_dir: &Path) -> Option<DateTime<Utc>> {
+    let retry_at = api_error_state(base_dir).retry_at?;
+    DateTime::parse_from_rfc3339(&retry_at).ok().map(|at| at.with_timezone(&Utc))
+}
+
+/// Set the time before which the API should not be called again, or clear it.
+///
+/// # Errors
+///
+/// Returns an error if the database operation fails.
+pub fn set_api_retry_at(base_dir: &Path, retry_at: Option<DateTime<Utc>>) -> Result<()> {
+    get_store(base_dir)?.set_api_retry_at(retry_at.map(|at| at.to_rfc3339()).as_deref())
+}
+
+/// Record an API error reported by a tool call, returning how many there have been in a row.
+///
+/// # Errors
+///
+/// Returns an error if the database operation fails.
+pub fn record_api_error(base_dir: &Path, overloaded: bool) -> Result<u32> {
+    get_store(base_dir)?.record_api_error(overloaded)
+}
+
+/// Forget API errors and any backoff, because the API is working again.
+///
+/// # Errors
+///
+/// Returns an error if the database operation fails.
+pub fn clear_api_errors(base_dir: &Path) -> Result<()> {
+    get_store(base_dir)?.clear_api_errors()
+}
+
+/// Record an auto-work prompt, returning how many prompts in a row made no progress.
+///
+/// # Errors
+///
+/// Returns an error if the database operation fails.
+pub fn record_auto_work_prompt(base_dir: &Path, ready_count: u32) -> Result<u32> {
+    get_store(base_dir)?.record_auto_work_prompt(ready_count)
+}
+
+/// Forget declined auto-work prompts (the user has responded).
+///
+/// # Errors
+///
+/// Returns an error if the database operation fails.
+pub fn clear_auto_work_declines(base_dir: &Path) -> Result<()> {
+    get_store(base_dir)?.clear_auto_work_declines()
+}
+
+/// Request a pause: the next stop is allowed, and the session after it resumes the work.
+///
+/// # Errors
+///
+/// Returns an error if the database operation fails.
+pub fn request_pause(base_dir: &Path, reason: &str) -> Result<()> {
+    get_store(base_dir)?.set_pause(reason)
+}
+
+/// Get the reason for a requested pause the session has not yet stopped for.
+#[must_use]
+pub fn pending_pause(base_dir: &Path) -> Option<String> {
+    match get_store(base_dir).and_then(|s| s.pause()) {
+        Ok(Some((reason, false))) => Some(reason),
+        _ => None,
+    }
+}
+
+/// Record that the session stopped for the requested pause.
+///
+/// # Errors
+///
+/// Returns an error if the database operation fails.
+pub fn mark_pause_stopped(base_dir: &Path) -> Result<()> {
+    get_store(base_dir)?.mark_pause_stopped()
+}
+
+/// Take the reason a previous session paused for, if it stopped for a pause.
+///
+/// The pause is forgotten, so it is only reported once.
+#[must_use]
+pub fn take_paused_session(base_dir: &Path) -> Option<String> {
+    let store = get_store(base_dir).ok()?;
+    let Ok(Some((reason, true))) = store.pause() else {
+        return None;
+    };
+    if let Err(e) = store.clear_pause() {
+        tracing::warn!("Failed to clear session pause: {e}");
+    }
+    Some(reason)
+}
+
+/// Forget a pause the session already stopped for (the user is back).
+///
+/// A pause that has not been stopped for yet is kept.
+///
+/// # Errors
+///
+/// Returns an error if the database operation fails.
+pub fn clear_stopped_pause(base_dir: &Path) -> Result<()> {
+    let store = get_store(base_dir)?;
+    if matches!(store.pause()?, Some((_, true))) {
+        store.clear_pause()?;
+    }
+    Ok(())
+}
+
+/// Minutes without a hook call after which a session is no longer considered alive.
+pub const SESSION_LIVENESS_MINUTES: i64 = 15;
+
+/// Record that a hook fired for a session.
+///
+/// # Errors
+///
+/// Returns an error if the database operation fails.
+pub fn record_session_activity(
+    base_dir: &Path,
+    session_id: &str,
+    transcript_path: Option<&str>,
+    state: SessionState,
+) -> Result<()> {
+    get_store(base_dir)?.touch_session(session_id, transcript_path, state)
+}
+
+/// Get all sessions seen in this project, most recently seen first.
+///
+/// # Errors
+///
+/// Returns an error if the database operation fails.
+pub fn list_sessions(base_dir: &Path) -> Result<Vec<SessionRecord>> {
+    get_store(base_dir)?.sessions()
+}
+
+/// Check whether a session is alive: not stopped, and seen recently.
+#[must_use]
+pub fn is_session_alive(session: &SessionRecord, now: DateTime<Utc>) -> bool {
+    if session.state == SessionState::Stopped {
+        return false;
+    }
+    NaiveDateTime::parse_from_str(&session.last_seen, "%Y-%m-%d %H:%M:%S").is_ok_and(|seen| {
+        now.signed_duration_since(seen.and_utc()).num_minutes() < SESSION_LIVENESS_MINUTES
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -614,4 +1130,134 @@ mod tests {
         assert!(!base.join(".claude/problem-mode.local").exists());
         assert!(!base.join(".claude/needs-validation.local").exists());
     }
+
+    #[test]
+    fn test_record_and_clear_stop_block_streak() {
+        let dir = TempDir::new().unwrap();
+        let base = dir.path();
+
+        assert_eq!(record_stop_block(base, "sig").unwrap(), 1);
+        assert_eq!(record_stop_block(base, "sig").unwrap(), 2);
+
+        clear_stop_block_streak(base).unwrap();
+        assert_eq!(record_stop_block(base, "sig").unwrap(), 1);
+    }
+
+    #[test]
+    fn test_record_and_forget_agent_stash() {
+        let dir = TempDir::new().unwrap();
+        let base = dir.path();
+
+        record_agent_stash(base, "abc123").unwrap();
+        assert_eq!(get_agent_stashes(base).unwrap(), vec!["abc123"]);
+
+        forget_agent_stash(base, "abc123").unwrap();
+        assert!(get_agent_stashes(base).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_clear_markers() {
+        let dir = TempDir::new().unwrap();
+        let base = dir.path();
+        set_reflect_marker(base).unwrap();
+        set_needs_validation(base).unwrap();
+        enter_problem_mode(base).unwrap();
+
+        clear_markers(base, &[Marker::MustReflect, Marker::NeedsValidation]).unwrap();
+        assert_eq!(active_markers(base).unwrap(), vec![Marker::ProblemMode]);
+    }
+
+    #[test]
+    fn test_markers_are_scoped_to_the_current_session() {
+        let dir = TempDir::new().unwrap();
+        let base = dir.path();
+
+        set_current_session(Some("s1"));
+        enter_problem_mode(base).unwrap();
+        set_reflect_marker(base).unwrap();
+        assert!(is_problem_mode_active(base));
+
+        // Another session does not see shared markers, and clears them as stale
+        set_current_session(Some("s2"));
+        assert!(!is_problem_mode_active(base));
+        set_current_session(Some("s1"));
+        assert!(!is_problem_mode_active(base));
+
+        // Per-session markers are kept for each session separately
+        set_current_session(Some("s2"));
+        assert!(!has_reflect_marker(base));
+        set_current_session(Some("s1"));
+        assert!(has_reflect_marker(base));
+
+        set_current_session(None);
+    }
+
+    #[test]
+    fn test_marker_ttls_from_config() {
+        let dir = TempDir::new().unwrap();
+        let config = ProjectConfig {
+            marker_ttl_minutes: [("problem_mode".to_string(), 0), ("bogus".to_string(), 5)]
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        };
+        config.save_to(dir.path()).unwrap();
+
+        let ttls = configured_marker_ttls(dir.path());
+        assert_eq!(ttls.get(&Marker::ProblemMode), Some(&0));
+        assert_eq!(ttls.len(), 1);
+    }
+
+    #[test]
+    fn test_pause_lifecycle() {
+        let dir = TempDir::new().unwrap();
+        let base = dir.path();
+        assert_eq!(pending_pause(base), None);
+
+        request_pause(base, "stepping away").unwrap();
+        assert_eq!(pending_pause(base).as_deref(), Some("stepping away"));
+        // Not stopped yet, so neither the user nor a new session consumes it
+        clear_stopped_pause(base).unwrap();
+        assert_eq!(take_paused_session(base), None);
+
+        mark_pause_stopped(base).unwrap();
+        assert_eq!(pending_pause(base), None);
+        assert_eq!(take_paused_session(base).as_deref(), Some("stepping away"));
+        assert_eq!(take_paused_session(base), None);
+
+        request_pause(base, "again").unwrap();
+        mark_pause_stopped(base).unwrap();
+        clear_stopped_pause(base).unwrap();
+        assert_eq!(take_paused_session(base), None);
+    }
+
+    #[test]
+    fn test_record_and_list_sessions() {
+        let dir = TempDir::new().unwrap();
+        assert!(list_sessions(dir.path()).unwrap().is_empty());
+
+        record_session_activity(dir.path(), "s1", None, SessionState::Working).unwrap();
+        let sessions = list_sessions(dir.path()).unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].session_id, "s1");
+        assert!(is_session_alive(&sessions[0], Utc::now()));
+    }
+
+    #[test]
+    fn test_is_session_alive() {
+        let now = "2026-03-01T12:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let session = |state, last_seen: &str| SessionRecord {
+            session_id: "s1".to_string(),
+            transcript_path: None,
+            state,
+            started_at: "2026-03-01 11:00:00".to_string(),
+            last_seen: last_seen.to_string(),
+        };
+
+        assert!(is_session_alive(&session(SessionState::Working, "2026-03-01 11:50:00"), now));
+        assert!(is_session_alive(&session(SessionState::Blocked, "2026-03-01 11:50:00"), now));
+        assert!(!is_session_alive(&session(SessionState::Stopped, "2026-03-01 11:59:00"), now));
+        assert!(!is_session_alive(&session(SessionState::Working, "2026-03-01 11:00:00"), now));
+        assert!(!is_session_alive(&session(SessionState::Working, "garbage"), now));
+    }
 }
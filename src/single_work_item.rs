@@ -1,15 +1,21 @@
 //! Single work item mode.
 //!
 //! When the `CLAUDE_RELIABILITY_SINGLE_WORK_ITEM` environment variable is set,
-//! the session is constrained to a single assigned work item. This module
-//! provides helpers for reading, validating, and checking the status of that item.
+//! or a work item has been assigned with `work assign`, sessions are
+//! constrained to that one item. This module provides helpers for reading,
+//! validating, and checking the status of that item, and for moving a chained
+//! assignment on to the next ready item once it is done.
 
 use crate::paths;
-use crate::tasks::{SqliteTaskStore, Status, TaskStore};
+use crate::session;
+use crate::tasks::{self, SqliteTaskStore, Status, TaskStore};
 use std::path::Path;
 
 /// Environment variable name for single work item mode.
-const ENV_VAR: &str = "CLAUDE_RELIABILITY_SINGLE_WORK_ITEM";
+pub const ENV_VAR: &str = "CLAUDE_RELIABILITY_SINGLE_WORK_ITEM";
+
+/// Audit log operation recording each step of a chained assignment.
+pub const CHAIN_OPERATION: &str = "work_item_chain";
 
 /// Read the `CLAUDE_RELIABILITY_SINGLE_WORK_ITEM` environment variable.
 ///
@@ -19,6 +25,74 @@ pub fn get_single_work_item_id() -> Option<String> {
     std::env::var(ENV_VAR).ok().filter(|v| !v.is_empty())
 }
 
+/// Get the work item this project is constrained to.
+///
+/// The environment variable takes precedence over an assignment made with
+/// `work assign`.
+#[must_use]
+pub fn assigned_work_item_id(base_dir: &Path) -> Option<String> {
+    get_single_work_item_id()
+        .or_else(|| session::work_item_assignment(base_dir).map(|a| a.work_item_id))
+}
+
+/// Assign a work item to this project, returning its ID and title.
+///
+/// With `chain`, the next ready item is assigned when this one is done.
+///
+/// # Errors
+///
+/// Returns an error string if the item is not open or the assignment cannot be saved.
+pub fn assign(base_dir: &Path, id: &str, chain: bool) -> Result<(String, String), String> {
+    let (id, title) = validate_work_item(base_dir, id)?;
+    session::assign_work_item(base_dir, &id, chain)
+        .map_err(|e| format!("Failed to assign work item {id}: {e}"))?;
+    Ok((id, title))
+}
+
+/// Finish the assignment of a completed work item.
+///
+/// Does nothing unless `id` is the assigned item and it is complete. A
+/// chained assignment then moves on to the highest-priority ready item,
+/// which is returned as `(id, title)`; otherwise, or when no item is ready,
+/// the assignment is removed. Each step is recorded in the audit log.
+#[must_use]
+pub fn finish_assigned_work_item(base_dir: &Path, id: &str) -> Option<(String, String)> {
+    let assignment = session::work_item_assignment(base_dir)?;
+    if assignment.work_item_id != id || !is_work_item_complete(base_dir, id) {
+        return None;
+    }
+    let store = tasks::open_project_store(base_dir)?;
+
+    let next = if assignment.chain {
+        store.get_ready_tasks().ok().and_then(|ready| ready.into_iter().find(|t| t.id != id))
+    } else {
+        None
+    };
+
+    let Some(next) = next else {
+        if let Err(e) = session::clear_work_item_assignment(base_dir) {
+            tracing::warn!("Failed to clear work item assignment: {e}");
+        }
+        if assignment.chain {
+            record_chain(&store, &format!("{id} -> (none ready)"));
+        }
+        return None;
+    };
+
+    if let Err(e) = session::assign_work_item(base_dir, &next.id, true) {
+        tracing::warn!("Failed to assign next work item: {e}");
+        return None;
+    }
+    record_chain(&store, &format!("{id} -> {}", next.id));
+    Some((next.id, next.title))
+}
+
+fn record_chain(store: &SqliteTaskStore, details: &str) {
+    if let Err(e) = store.record_event(CHAIN_OPERATION, details) {
+        tracing::warn!("Failed to record work item chain: {e}");
+    }
+}
+
 /// Validate that the single work item exists and is open.
 ///
 /// Reads the work item ID from the environment variable. To pass the ID
@@ -376,4 +450,84 @@ mod tests {
         assert!(result.is_err());
         assert!(result.unwrap_err().contains("not found"));
     }
+
+    // -- assignment --
+
+    fn complete(store: &SqliteTaskStore, id: &str) {
+        store
+            .update_task(id, TaskUpdate { status: Some(Status::Complete), ..Default::default() })
+            .unwrap();
+    }
+
+    #[test]
+    #[serial]
+    fn test_assignment_is_used_when_env_unset() {
+        unset_env();
+        let (dir, store) = make_store();
+        let task = store.create_task("Open", "Open", Priority::High).unwrap();
+        assert_eq!(assigned_work_item_id(dir.path()), None);
+
+        assert_eq!(assign(dir.path(), &task.id, false).unwrap().1, "Open");
+        assert_eq!(assigned_work_item_id(dir.path()), Some(task.id));
+
+        set_env("from-env");
+        assert_eq!(assigned_work_item_id(dir.path()).as_deref(), Some("from-env"));
+        unset_env();
+    }
+
+    #[test]
+    fn test_assign_rejects_closed_item() {
+        let (dir, store) = make_store();
+        let task = store.create_task("Done", "Done", Priority::Medium).unwrap();
+        complete(&store, &task.id);
+        assert!(assign(dir.path(), &task.id, true).unwrap_err().contains("already closed"));
+        assert_eq!(session::work_item_assignment(dir.path()), None);
+    }
+
+    #[test]
+    fn test_finish_unchained_assignment_clears_it() {
+        let (dir, store) = make_store();
+        let task = store.create_task("Only", "Only", Priority::Medium).unwrap();
+        store.create_task("Other", "Other", Priority::High).unwrap();
+        assign(dir.path(), &task.id, false).unwrap();
+
+        assert_eq!(finish_assigned_work_item(dir.path(), &task.id), None);
+        assert!(session::work_item_assignment(dir.path()).is_some());
+
+        complete(&store, &task.id);
+        assert_eq!(finish_assigned_work_item(dir.path(), &task.id), None);
+        assert_eq!(session::work_item_assignment(dir.path()), None);
+    }
+
+    #[test]
+    fn test_finish_chained_assignment_moves_to_next_ready_item() {
+        let (dir, store) = make_store();
+        let first = store.create_task("First", "First", Priority::Medium).unwrap();
+        let second = store.create_task("Second", "Second", Priority::High).unwrap();
+        assign(dir.path(), &first.id, true).unwrap();
+
+        complete(&store, &first.id);
+        assert_eq!(
+            finish_assigned_work_item(dir.path(), &first.id),
+            Some((second.id.clone(), "Second".to_string()))
+        );
+        let assignment = session::work_item_assignment(dir.path()).unwrap();
+        assert_eq!(assignment.work_item_id, second.id);
+        assert!(assignment.chain);
+
+        complete(&store, &second.id);
+        assert_eq!(finish_assigned_work_item(dir.path(), &second.id), None);
+        assert_eq!(session::work_item_assignment(dir.path()), None);
+
+        let chain: Vec<String> = store
+            .get_audit_log(None, None)
+            .unwrap()
+            .into_iter()
+            .filter(|e| e.operation == CHAIN_OPERATION)
+            .filter_map(|e| e.details)
+            .collect();
+        assert_eq!(chain.len(), 2);
+        assert!(chain.contains(&format!("{} -> {}", first.id, second.id)));
+        assert!(chain.contains(&format!("{} -> (none ready)", second.id)));
+    }
 }
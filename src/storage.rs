@@ -1,29 +1,239 @@
 //! `SQLite`-based state storage for marker data.
 //!
 //! This module provides persistent storage for:
-//! - Boolean markers (problem mode, validation needed, etc.)
+//! - Boolean markers (problem mode, validation needed, etc.), kept together in
+//!   one row so related markers change atomically
+//! - The session registry
+//! - Metric counters and histograms
+//! - A full-text index of session transcripts
 //!
 //! All state is stored in a single `SQLite` database at
 //! `<project_dir>/.claude-reliability/working-memory.sqlite3`.
 
-use crate::error::Result;
+use crate::error::{Error, Result};
 use crate::paths;
+use crate::profiling;
 use crate::traits::StateStore;
-use rusqlite::{params, Connection, OptionalExtension};
+use crate::transcript::{TranscriptEvent, TranscriptPosition};
+use chrono::{NaiveDateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
-/// Marker name constants for consistent usage across the codebase.
+/// A boolean session marker.
+///
+/// Markers are columns of the singleton `session_state` row, so several of
+/// them can be changed together in one transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Marker {
+    /// Problem mode is active - tool use blocked until stop.
+    ProblemMode,
+    /// Validation is needed - modifying tool was used.
+    NeedsValidation,
+    /// Agent should reflect on work before stopping.
+    MustReflect,
+    /// Emergency stop requested by agent.
+    EmergencyStop,
+    /// Work item reminder has been shown this session.
+    WorkItemReminded,
+}
+
+impl Marker {
+    /// Every marker.
+    pub const ALL: [Self; 5] = [
+        Self::ProblemMode,
+        Self::NeedsValidation,
+        Self::MustReflect,
+        Self::EmergencyStop,
+        Self::WorkItemReminded,
+    ];
+
+    /// The marker's name, which is also its column in `session_state`.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::ProblemMode => "problem_mode",
+            Self::NeedsValidation => "needs_validation",
+            Self::MustReflect => "must_reflect",
+            Self::EmergencyStop => "emergency_stop",
+            Self::WorkItemReminded => "work_item_reminded",
+        }
+    }
+
+    /// Whether the marker is kept separately for each session.
+    ///
+    /// Reflection and the work item reminder belong to the session that did the
+    /// work, so concurrent sessions in one project must not share them.
+    #[must_use]
+    pub const fn is_per_session(self) -> bool {
+        matches!(self, Self::MustReflect | Self::WorkItemReminded)
+    }
+
+    /// Look up a marker by name.
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|marker| marker.as_str() == name)
+    }
+}
+
+/// Minutes after which a marker expires, unless configured otherwise.
+pub const DEFAULT_MARKER_TTL_MINUTES: u32 = 24 * 60;
+
+/// Marker constants for consistent usage across the codebase.
 pub mod markers {
+    use super::Marker;
+
     /// Problem mode is active - tool use blocked until stop.
-    pub const PROBLEM_MODE: &str = "problem_mode";
+    pub const PROBLEM_MODE: Marker = Marker::ProblemMode;
     /// Validation is needed - modifying tool was used.
-    pub const NEEDS_VALIDATION: &str = "needs_validation";
+    pub const NEEDS_VALIDATION: Marker = Marker::NeedsValidation;
     /// Agent should reflect on work before stopping.
-    pub const MUST_REFLECT: &str = "must_reflect";
+    pub const MUST_REFLECT: Marker = Marker::MustReflect;
     /// Emergency stop requested by agent.
-    pub const EMERGENCY_STOP: &str = "emergency_stop";
+    pub const EMERGENCY_STOP: Marker = Marker::EmergencyStop;
     /// Work item reminder has been shown this session.
-    pub const WORK_ITEM_REMINDED: &str = "work_item_reminded";
+    pub const WORK_ITEM_REMINDED: Marker = Marker::WorkItemReminded;
+}
+
+/// What a session is currently doing, as last reported by a hook.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionState {
+    /// The agent is working (prompting or using tools).
+    Working,
+    /// The agent tried to stop and was blocked.
+    Blocked,
+    /// The agent stopped.
+    Stopped,
+}
+
+impl SessionState {
+    /// Get the string representation stored in the database.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Working => "working",
+            Self::Blocked => "blocked",
+            Self::Stopped => "stopped",
+        }
+    }
+
+    /// Parse a state stored in the database, treating unknown values as working.
+    fn from_db(value: &str) -> Self {
+        match value {
+            "blocked" => Self::Blocked,
+            "stopped" => Self::Stopped,
+            _ => Self::Working,
+        }
+    }
+}
+
+/// A session recorded in the session registry.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct SessionRecord {
+    /// The Claude Code session ID.
+    pub session_id: String,
+    /// Path to the session's transcript, if known.
+    pub transcript_path: Option<String>,
+    /// What the session was last doing.
+    pub state: SessionState,
+    /// UTC timestamp (`YYYY-MM-DD HH:MM:SS`) of the first hook call.
+    pub started_at: String,
+    /// UTC timestamp (`YYYY-MM-DD HH:MM:SS`) of the latest hook call.
+    pub last_seen: String,
+}
+
+/// A transcript event found by [`SqliteStore::search_transcript_events`].
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct TranscriptMatch {
+    /// The session whose transcript the event is in.
+    pub session_id: String,
+    /// The 1-based line of the transcript the event is on.
+    pub line: usize,
+    /// When the event happened, as written in the transcript.
+    pub timestamp: Option<String>,
+    /// What the event records: `user`, `assistant` or `tool_call`.
+    pub kind: String,
+    /// The tool called, for tool calls.
+    pub tool: Option<String>,
+    /// The matching part of the event's text, with matches in `[brackets]`.
+    pub snippet: String,
+}
+
+/// API errors seen by the hooks, shared between hook types.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ApiErrorState {
+    /// API errors reported by tool calls since the API last worked.
+    pub consecutive_errors: u32,
+    /// Whether the latest of those errors was an overloaded error.
+    pub overloaded: bool,
+    /// RFC 3339 time before which the API should not be called again, if set.
+    pub retry_at: Option<String>,
+}
+
+/// Auto-work prompting in one session.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AutoWorkSession {
+    /// Number of times the agent was prompted to keep working.
+    pub prompts: u32,
+    /// Number of completed work items at the last check-in, or when
+    /// prompting started.
+    pub completed_at_check_in: Option<u32>,
+}
+
+/// All hooks disabled with `disable`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DisabledHooks {
+    /// RFC 3339 time the hooks are disabled until, or `None` until re-enabled.
+    pub until: Option<String>,
+}
+
+/// The work item assigned with `work assign`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkItemAssignment {
+    /// The assigned work item.
+    pub work_item_id: String,
+    /// Whether the next ready item is assigned when this one is done.
+    pub chain: bool,
+}
+
+/// A database recovery the agent has not been told about yet.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct DatabaseRecovery {
+    /// UTC timestamp (`YYYY-MM-DD HH:MM:SS`) of the recovery.
+    pub recovered_at: String,
+    /// Where the damaged database was copied to.
+    pub backup_path: String,
+    /// What was recovered.
+    pub details: String,
+}
+
+/// How many times a hook outcome was recorded.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct HookOutcomeCount {
+    /// What the outcome is of (e.g. `stop` or `quality_check`).
+    pub kind: String,
+    /// The outcome (e.g. `block` or `failed`).
+    pub outcome: String,
+    /// Why, if recorded (e.g. the check that blocked a stop).
+    pub reason: Option<String>,
+    /// Number of times it was recorded.
+    pub count: u64,
+}
+
+/// A histogram metric as stored in the database.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistogramRecord {
+    /// The metric name.
+    pub name: String,
+    /// The rendered label set (e.g. `operation="code_review"`).
+    pub labels: String,
+    /// Cumulative count of observations at or below each bucket bound.
+    pub buckets: Vec<u64>,
+    /// Sum of all observations.
+    pub sum: f64,
+    /// Number of observations.
+    pub count: u64,
 }
 
 /// SQLite-based state store.
@@ -35,6 +245,10 @@ pub mod markers {
 pub struct SqliteStore {
     /// Path to the database file.
     db_path: PathBuf,
+    /// The session markers are set for and read by, if known.
+    session_id: Option<String>,
+    /// Marker lifetimes in minutes, overriding [`DEFAULT_MARKER_TTL_MINUTES`] (0 = never expire).
+    marker_ttls: HashMap<Marker, u32>,
 }
 
 impl SqliteStore {
@@ -49,10 +263,7 @@ impl SqliteStore {
     ///
     /// Returns an error if the database cannot be initialized.
     pub fn new(project_dir: &Path) -> Result<Self> {
-        let db_path = paths::project_db_path(project_dir);
-        let store = Self { db_path };
-        store.init_schema()?;
-        Ok(store)
+        Self::with_path(paths::project_db_path(project_dir))
     }
 
     /// Create a new `SQLite` store with a specific database path.
@@ -63,11 +274,53 @@ impl SqliteStore {
     ///
     /// Returns an error if the database cannot be initialized.
     pub fn with_path(db_path: PathBuf) -> Result<Self> {
-        let store = Self { db_path };
+        crate::recovery::check_on_open(&db_path)?;
+        let store = Self { db_path, session_id: None, marker_ttls: HashMap::new() };
         store.init_schema()?;
         Ok(store)
     }
 
+    /// Scope markers to a session.
+    ///
+    /// Markers set by another session are treated as stale and cleared when read.
+    #[must_use]
+    pub fn for_session(mut self, session_id: Option<&str>) -> Self {
+        self.session_id = session_id.map(str::to_string);
+        self
+    }
+
+    /// Set how long markers last, in minutes (0 = never expire).
+    ///
+    /// Markers not listed expire after [`DEFAULT_MARKER_TTL_MINUTES`].
+    #[must_use]
+    pub fn with_marker_ttls(mut self, marker_ttls: HashMap<Marker, u32>) -> Self {
+        self.marker_ttls = marker_ttls;
+        self
+    }
+
+    /// Minutes the given marker lasts for (0 = never expire).
+    fn marker_ttl(&self, marker: Marker) -> u32 {
+        self.marker_ttls.get(&marker).copied().unwrap_or(DEFAULT_MARKER_TTL_MINUTES)
+    }
+
+    /// Check whether a set marker is still live: not expired, and set by this session.
+    fn marker_is_live(&self, marker: Marker, set_at: Option<&str>, session: Option<&str>) -> bool {
+        if let (Some(current), Some(session)) = (self.session_id.as_deref(), session) {
+            if current != session {
+                return false;
+            }
+        }
+        let ttl = self.marker_ttl(marker);
+        if ttl == 0 {
+            return true;
+        }
+        set_at
+            .and_then(|at| NaiveDateTime::parse_from_str(at, "%Y-%m-%d %H:%M:%S").ok())
+            .map_or(true, |at| {
+                Utc::now().naive_utc().signed_duration_since(at).num_minutes() < i64::from(ttl)
+            })
+    }
+
     /// Get the database path.
     #[must_use]
     pub fn db_path(&self) -> &Path {
@@ -78,101 +331,1476 @@ impl SqliteStore {
     fn open(&self) -> Result<Connection> {
         // Ensure parent directory exists
         if let Some(parent) = self.db_path.parent() {
-            std::fs::create_dir_all(parent)?;
+            std::fs::create_dir_all(parent)
+                .map_err(|source| Error::FileAccess { path: parent.to_path_buf(), source })?;
+        }
+        let mut conn = Connection::open(&self.db_path)
+            .map_err(|source| Error::DatabaseOpen { path: self.db_path.clone(), source })?;
+        if profiling::is_enabled() {
+            conn.profile(Some(profiling::record_sql));
         }
-        let conn = Connection::open(&self.db_path)?;
         // Enable foreign keys and WAL mode for better concurrency
         conn.execute_batch("PRAGMA foreign_keys = ON; PRAGMA journal_mode = WAL;")?;
         Ok(conn)
     }
 
-    /// Initialize the database schema.
-    fn init_schema(&self) -> Result<()> {
+    /// Initialize the database schema.
+    #[allow(clippy::too_many_lines)]
+    fn init_schema(&self) -> Result<()> {
+        let mut conn = self.open()?;
+        let tx = conn.transaction()?;
+
+        drop_legacy_session_state(&tx)?;
+
+        tx.execute_batch(
+            r"
+            -- Session markers (singleton row). Each marker has a column saying
+            -- whether it is set, plus `<marker>_at` and `<marker>_session`
+            -- columns recording when and by which session it was set.
+            CREATE TABLE IF NOT EXISTS session_state (
+                id INTEGER PRIMARY KEY CHECK (id = 1)
+            );
+            INSERT OR IGNORE INTO session_state (id) VALUES (1);
+
+            -- Per-session markers (see `Marker::is_per_session`), set by a known session
+            CREATE TABLE IF NOT EXISTS session_markers (
+                session_id TEXT NOT NULL,
+                marker TEXT NOT NULL,
+                set_at TEXT NOT NULL DEFAULT (datetime('now')),
+                PRIMARY KEY (session_id, marker)
+            );
+
+            -- Consecutive identical stop blocks (singleton row)
+            CREATE TABLE IF NOT EXISTS block_streak (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                signature TEXT NOT NULL,
+                count INTEGER NOT NULL DEFAULT 0
+            );
+
+            -- Stop hook blocks since the last user message or file change, by session
+            CREATE TABLE IF NOT EXISTS injection_streaks (
+                session_id TEXT PRIMARY KEY,
+                state TEXT NOT NULL,
+                checks TEXT NOT NULL DEFAULT '[]'
+            );
+
+            -- Approved change to a protected file, not yet used (singleton row)
+            CREATE TABLE IF NOT EXISTS protected_change_grant (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                justification TEXT NOT NULL,
+                expires_at TEXT NOT NULL
+            );
+
+            -- All hooks disabled with `disable`, until the given time or, when
+            -- NULL, until re-enabled (singleton row, absent when enabled)
+            CREATE TABLE IF NOT EXISTS hooks_disabled (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                until TEXT
+            );
+
+            -- Work item assigned with `work assign`, and whether to assign the
+            -- next ready item when it is done (singleton row)
+            CREATE TABLE IF NOT EXISTS work_item_assignment (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                work_item_id TEXT NOT NULL,
+                chain INTEGER NOT NULL DEFAULT 0
+            );
+
+            -- Auto-work prompts in each session, and the number of completed
+            -- work items at the session's last check-in
+            CREATE TABLE IF NOT EXISTS auto_work_sessions (
+                session_id TEXT PRIMARY KEY,
+                prompts INTEGER NOT NULL DEFAULT 0,
+                completed_at_check_in INTEGER
+            );
+
+            -- Auto-work prompting state (singleton row)
+            CREATE TABLE IF NOT EXISTS auto_work_state (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                paused_until TEXT,
+                last_ready_count INTEGER,
+                declines INTEGER NOT NULL DEFAULT 0
+            );
+
+            -- API errors reported by tool calls, and when the API may next be
+            -- called after it was overloaded (singleton row)
+            CREATE TABLE IF NOT EXISTS api_errors (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                consecutive_errors INTEGER NOT NULL DEFAULT 0,
+                overloaded INTEGER NOT NULL DEFAULT 0,
+                retry_at TEXT
+            );
+
+            -- A requested session pause (singleton row). `stopped` is set once the
+            -- stop hook has let the session stop for it.
+            CREATE TABLE IF NOT EXISTS session_pause (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                reason TEXT NOT NULL,
+                stopped INTEGER NOT NULL DEFAULT 0
+            );
+
+            -- Stashes created by the agent that have not been resolved
+            CREATE TABLE IF NOT EXISTS agent_stashes (
+                sha TEXT PRIMARY KEY
+            );
+
+            -- Files modified since validation last passed, relative to the project
+            CREATE TABLE IF NOT EXISTS touched_files (
+                path TEXT PRIMARY KEY
+            );
+
+            -- Emergency stop requests, for rate limiting and escalation
+            CREATE TABLE IF NOT EXISTS emergency_stop_attempts (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                session_id TEXT NOT NULL,
+                outcome TEXT NOT NULL,
+                created_at TEXT NOT NULL DEFAULT (datetime('now'))
+            );
+
+            -- Sessions seen by the hooks, for liveness tracking
+            CREATE TABLE IF NOT EXISTS sessions (
+                session_id TEXT PRIMARY KEY,
+                transcript_path TEXT,
+                state TEXT NOT NULL,
+                started_at TEXT NOT NULL DEFAULT (datetime('now')),
+                last_seen TEXT NOT NULL DEFAULT (datetime('now'))
+            );
+
+            -- Metric counters, keyed by name and rendered label set
+            CREATE TABLE IF NOT EXISTS metric_counters (
+                name TEXT NOT NULL,
+                labels TEXT NOT NULL,
+                value REAL NOT NULL DEFAULT 0,
+                PRIMARY KEY (name, labels)
+            );
+
+            -- When database maintenance last ran (singleton row)
+            CREATE TABLE IF NOT EXISTS maintenance_state (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                last_run_at TEXT NOT NULL
+            );
+
+            -- Database recoveries not yet reported to the agent
+            CREATE TABLE IF NOT EXISTS database_recoveries (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                recovered_at TEXT NOT NULL DEFAULT (datetime('now')),
+                backup_path TEXT NOT NULL,
+                details TEXT NOT NULL
+            );
+
+            -- Stop decisions and quality check results, for reports
+            CREATE TABLE IF NOT EXISTS hook_outcomes (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                recorded_at TEXT NOT NULL DEFAULT (datetime('now')),
+                kind TEXT NOT NULL,
+                outcome TEXT NOT NULL,
+                reason TEXT
+            );
+
+            -- Questions already sent on each delivery channel
+            CREATE TABLE IF NOT EXISTS question_deliveries (
+                question_id TEXT NOT NULL,
+                channel TEXT NOT NULL,
+                delivered_at TEXT NOT NULL DEFAULT (datetime('now')),
+                PRIMARY KEY (question_id, channel)
+            );
+
+            -- Transcripts indexed for search, and how far each has been read
+            CREATE TABLE IF NOT EXISTS indexed_transcripts (
+                session_id TEXT PRIMARY KEY,
+                path TEXT NOT NULL,
+                offset INTEGER NOT NULL DEFAULT 0,
+                lines INTEGER NOT NULL DEFAULT 0
+            );
+
+            -- Messages and tool calls from indexed transcripts
+            CREATE VIRTUAL TABLE IF NOT EXISTS transcript_events USING fts5(
+                text,
+                tool,
+                session_id UNINDEXED,
+                line UNINDEXED,
+                timestamp UNINDEXED,
+                kind UNINDEXED
+            );
+
+            -- Metric histograms, with cumulative bucket counts as a JSON array
+            CREATE TABLE IF NOT EXISTS metric_histograms (
+                name TEXT NOT NULL,
+                labels TEXT NOT NULL,
+                buckets TEXT NOT NULL,
+                sum REAL NOT NULL DEFAULT 0,
+                count INTEGER NOT NULL DEFAULT 0,
+                PRIMARY KEY (name, labels)
+            );
+            ",
+        )?;
+
+        add_marker_columns(&tx)?;
+        migrate_legacy_markers(&tx)?;
+        migrate_per_session_markers(&tx)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Get the markers that are currently set.
+    ///
+    /// Expired markers, and markers set by another session, are cleared.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    pub fn active_markers(&self) -> Result<Vec<Marker>> {
+        let mut active = Vec::new();
+        let mut stale = Vec::new();
+        for marker in Marker::ALL {
+            match self.read_marker(marker)? {
+                Some(true) => active.push(marker),
+                Some(false) => stale.push((marker, false)),
+                None => {}
+            }
+        }
+        if !stale.is_empty() {
+            self.update_markers(&stale)?;
+        }
+        Ok(active)
+    }
+
+    /// The session a marker is kept for, if it is per-session and the session is known.
+    fn marker_session(&self, marker: Marker) -> Option<&str> {
+        self.session_id.as_deref().filter(|_| marker.is_per_session())
+    }
+
+    /// Read a marker: `None` if it is not set, otherwise whether it is still live.
+    fn read_marker(&self, marker: Marker) -> Result<Option<bool>> {
+        let conn = self.open()?;
+        let column = marker.as_str();
+        if let Some(session) = self.marker_session(marker) {
+            let set_at: Option<String> = conn
+                .query_row(
+                    "SELECT set_at FROM session_markers WHERE session_id = ?1 AND marker = ?2",
+                    params![session, column],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            return Ok(set_at.map(|at| self.marker_is_live(marker, Some(&at), Some(session))));
+        }
+        let (set, set_at, session): (bool, Option<String>, Option<String>) = conn.query_row(
+            &format!(
+                "SELECT {column}, {column}_at, {column}_session FROM session_state WHERE id = 1"
+            ),
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )?;
+        Ok(set.then(|| self.marker_is_live(marker, set_at.as_deref(), session.as_deref())))
+    }
+
+    /// Set or clear several markers in a single transaction.
+    ///
+    /// Either every change is made or, if the hook is interrupted, none is.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    pub fn update_markers(&self, changes: &[(Marker, bool)]) -> Result<()> {
+        let mut conn = self.open()?;
+        let tx = conn.transaction()?;
+        for (marker, set) in changes {
+            let column = marker.as_str();
+            if let Some(session) = self.marker_session(*marker) {
+                if *set {
+                    tx.execute(
+                        "INSERT OR REPLACE INTO session_markers (session_id, marker, set_at)
+                         VALUES (?1, ?2, datetime('now'))",
+                        params![session, column],
+                    )?;
+                } else {
+                    tx.execute(
+                        "DELETE FROM session_markers WHERE session_id = ?1 AND marker = ?2",
+                        params![session, column],
+                    )?;
+                }
+                // Forget the marker for sessions that let it expire
+                let ttl = self.marker_ttl(*marker);
+                if ttl > 0 {
+                    tx.execute(
+                        "DELETE FROM session_markers
+                         WHERE marker = ?1 AND set_at < datetime('now', ?2)",
+                        params![column, format!("-{ttl} minutes")],
+                    )?;
+                }
+            } else if *set {
+                tx.execute(
+                    &format!(
+                        "UPDATE session_state
+                         SET {column} = 1, {column}_at = datetime('now'), {column}_session = ?1
+                         WHERE id = 1"
+                    ),
+                    params![self.session_id],
+                )?;
+            } else {
+                tx.execute(
+                    &format!(
+                        "UPDATE session_state
+                         SET {column} = 0, {column}_at = NULL, {column}_session = NULL
+                         WHERE id = 1"
+                    ),
+                    [],
+                )?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Clear every expired marker, whichever session set it.
+    ///
+    /// Returns the number of markers cleared.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    pub fn prune_expired_markers(&self) -> Result<usize> {
+        let mut conn = self.open()?;
+        let tx = conn.transaction()?;
+        let mut pruned = 0;
+        for marker in Marker::ALL {
+            let ttl = self.marker_ttl(marker);
+            if ttl == 0 {
+                continue;
+            }
+            let column = marker.as_str();
+            let cutoff = format!("-{ttl} minutes");
+            pruned += tx.execute(
+                "DELETE FROM session_markers WHERE marker = ?1 AND set_at < datetime('now', ?2)",
+                params![column, cutoff],
+            )?;
+            pruned += tx.execute(
+                &format!(
+                    "UPDATE session_state
+                     SET {column} = 0, {column}_at = NULL, {column}_session = NULL
+                     WHERE id = 1 AND {column} = 1 AND {column}_at < datetime('now', ?1)"
+                ),
+                params![cutoff],
+            )?;
+        }
+        tx.commit()?;
+        Ok(pruned)
+    }
+
+    /// Record a maintenance run unless one ran within the last `interval_minutes`.
+    ///
+    /// Returns whether the run was recorded, so that of several sessions
+    /// starting together only one does the maintenance.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    pub fn claim_maintenance_run(&self, interval_minutes: u32) -> Result<bool> {
+        let conn = self.open()?;
+        let claimed = conn.execute(
+            "INSERT INTO maintenance_state (id, last_run_at) VALUES (1, datetime('now'))
+             ON CONFLICT(id) DO UPDATE SET last_run_at = excluded.last_run_at
+             WHERE last_run_at <= datetime('now', ?1)",
+            params![format!("-{interval_minutes} minutes")],
+        )?;
+        Ok(claimed > 0)
+    }
+
+    /// Get when maintenance last ran, if it ever has.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    pub fn last_maintenance_run(&self) -> Result<Option<String>> {
+        let conn = self.open()?;
+        Ok(conn
+            .query_row("SELECT last_run_at FROM maintenance_state WHERE id = 1", [], |row| {
+                row.get(0)
+            })
+            .optional()?)
+    }
+
+    /// Record a database recovery to report to the agent.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    pub fn record_database_recovery(&self, backup_path: &str, details: &str) -> Result<()> {
+        let conn = self.open()?;
+        conn.execute(
+            "INSERT INTO database_recoveries (backup_path, details) VALUES (?1, ?2)",
+            params![backup_path, details],
+        )?;
+        Ok(())
+    }
+
+    /// Get the database recoveries not yet reported, and forget them.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    pub fn take_database_recoveries(&self) -> Result<Vec<DatabaseRecovery>> {
+        let mut conn = self.open()?;
+        let tx = conn.transaction()?;
+        let recoveries = {
+            let mut stmt = tx.prepare(
+                "SELECT recovered_at, backup_path, details FROM database_recoveries ORDER BY id",
+            )?;
+            let rows = stmt.query_map([], |row| {
+                Ok(DatabaseRecovery {
+                    recovered_at: row.get(0)?,
+                    backup_path: row.get(1)?,
+                    details: row.get(2)?,
+                })
+            })?;
+            rows.collect::<rusqlite::Result<Vec<_>>>()?
+        };
+        tx.execute("DELETE FROM database_recoveries", [])?;
+        tx.commit()?;
+        Ok(recoveries)
+    }
+
+    /// Record the outcome of a hook decision or check.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    pub fn record_hook_outcome(
+        &self,
+        kind: &str,
+        outcome: &str,
+        reason: Option<&str>,
+    ) -> Result<()> {
+        let conn = self.open()?;
+        conn.execute(
+            "INSERT INTO hook_outcomes (kind, outcome, reason) VALUES (?1, ?2, ?3)",
+            params![kind, outcome, reason],
+        )?;
+        Ok(())
+    }
+
+    /// Count the hook outcomes recorded at or after `since` (`YYYY-MM-DD HH:MM:SS` UTC).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    pub fn hook_outcome_counts(&self, since: &str) -> Result<Vec<HookOutcomeCount>> {
+        let conn = self.open()?;
+        let mut stmt = conn.prepare(
+            "SELECT kind, outcome, reason, COUNT(*) FROM hook_outcomes WHERE recorded_at >= ?1
+             GROUP BY kind, outcome, reason ORDER BY COUNT(*) DESC, kind, outcome, reason",
+        )?;
+        let counts = stmt
+            .query_map([since], |row| {
+                Ok(HookOutcomeCount {
+                    kind: row.get(0)?,
+                    outcome: row.get(1)?,
+                    reason: row.get(2)?,
+                    count: row.get(3)?,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(counts)
+    }
+
+    /// Record that a question was sent on a delivery channel.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    pub fn record_question_delivery(&self, question_id: &str, channel: &str) -> Result<()> {
+        let conn = self.open()?;
+        conn.execute(
+            "INSERT OR IGNORE INTO question_deliveries (question_id, channel) VALUES (?1, ?2)",
+            params![question_id, channel],
+        )?;
+        Ok(())
+    }
+
+    /// Get the IDs of the questions sent on a delivery channel.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    pub fn delivered_questions(&self, channel: &str) -> Result<Vec<String>> {
+        let conn = self.open()?;
+        let mut stmt = conn.prepare(
+            "SELECT question_id FROM question_deliveries WHERE channel = ?1 ORDER BY delivered_at",
+        )?;
+        let ids = stmt.query_map([channel], |row| row.get(0))?.collect::<rusqlite::Result<_>>()?;
+        Ok(ids)
+    }
+
+    /// Get the transcript indexed for a session, and how far it has been read.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    pub fn transcript_position(
+        &self,
+        session_id: &str,
+    ) -> Result<Option<(String, TranscriptPosition)>> {
+        let conn = self.open()?;
+        let position = conn
+            .query_row(
+                "SELECT path, offset, lines FROM indexed_transcripts WHERE session_id = ?1",
+                [session_id],
+                |row| {
+                    let position = TranscriptPosition { offset: row.get(1)?, lines: row.get(2)? };
+                    Ok((row.get(0)?, position))
+                },
+            )
+            .optional()?;
+        Ok(position)
+    }
+
+    /// Add events read from a session's transcript to the transcript index.
+    ///
+    /// With `reset`, the session's previously indexed events are replaced.
+    /// `position` is how far the transcript has now been read.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    pub fn index_transcript_events(
+        &self,
+        session_id: &str,
+        path: &str,
+        events: &[TranscriptEvent],
+        position: TranscriptPosition,
+        reset: bool,
+    ) -> Result<()> {
+        let mut conn = self.open()?;
+        let tx = conn.transaction()?;
+        if reset {
+            tx.execute("DELETE FROM transcript_events WHERE session_id = ?1", [session_id])?;
+        }
+        {
+            let mut insert = tx.prepare(
+                "INSERT INTO transcript_events (text, tool, session_id, line, timestamp, kind)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )?;
+            for event in events {
+                insert.execute(params![
+                    event.text,
+                    event.tool,
+                    session_id,
+                    event.line,
+                    event.timestamp,
+                    event.kind.as_str()
+                ])?;
+            }
+        }
+        tx.execute(
+            "INSERT INTO indexed_transcripts (session_id, path, offset, lines) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(session_id) DO UPDATE SET
+                 path = excluded.path, offset = excluded.offset, lines = excluded.lines",
+            params![session_id, path, position.offset, position.lines],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Search the transcript index with an FTS5 query, most recent events first.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the query is invalid or the database operation fails.
+    pub fn search_transcript_events(
+        &self,
+        query: &str,
+        session_id: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<TranscriptMatch>> {
+        let conn = self.open()?;
+        let mut stmt = conn.prepare(
+            "SELECT session_id, line, timestamp, kind, tool,
+                    snippet(transcript_events, 0, '[', ']', '...', 24)
+             FROM transcript_events
+             WHERE transcript_events MATCH ?1 AND (?2 IS NULL OR session_id = ?2)
+             ORDER BY timestamp DESC, session_id, line DESC
+             LIMIT ?3",
+        )?;
+        let matches = stmt
+            .query_map(params![query, session_id, limit], |row| {
+                Ok(TranscriptMatch {
+                    session_id: row.get(0)?,
+                    line: row.get(1)?,
+                    timestamp: row.get(2)?,
+                    kind: row.get(3)?,
+                    tool: row.get(4)?,
+                    snippet: row.get(5)?,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(matches)
+    }
+
+    /// Migrate state from old file-based storage.
+    ///
+    /// This checks for old marker files, migrates them to the `SQLite` database,
+    /// and removes the old files.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if migration fails.
+    pub fn migrate_from_files(&self, base_dir: &Path) -> Result<()> {
+        // Remove legacy JKW files if they exist
+        let legacy_files = [".claude/jkw-state.local.yaml", ".claude/jkw-session.local.md"];
+        for file_path in legacy_files {
+            let full_path = base_dir.join(file_path);
+            if full_path.exists() {
+                let _ = std::fs::remove_file(&full_path);
+            }
+        }
+
+        // Migrate marker files
+        let marker_migrations = [
+            (".claude/problem-mode.local", markers::PROBLEM_MODE),
+            (".claude/needs-validation.local", markers::NEEDS_VALIDATION),
+            (".claude/must-reflect.local", markers::MUST_REFLECT),
+        ];
+
+        for (file_path, marker_name) in marker_migrations {
+            let full_path = base_dir.join(file_path);
+            if full_path.exists() {
+                self.set_marker(marker_name)?;
+                // Remove old file after successful migration
+                let _ = std::fs::remove_file(&full_path);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Record a stop block with the given signature.
+    ///
+    /// If the previous block had the same signature, the streak count is
+    /// incremented; otherwise the streak restarts at 1.
+    ///
+    /// Returns the new streak count.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    pub fn record_block(&self, signature: &str) -> Result<u32> {
+        let conn = self.open()?;
+        let previous: Option<(String, u32)> = conn
+            .query_row("SELECT signature, count FROM block_streak WHERE id = 1", [], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .optional()?;
+
+        let count = match previous {
+            Some((prev_signature, prev_count)) if prev_signature == signature => {
+                prev_count.saturating_add(1)
+            }
+            _ => 1,
+        };
+
+        conn.execute(
+            "INSERT OR REPLACE INTO block_streak (id, signature, count) VALUES (1, ?1, ?2)",
+            params![signature, count],
+        )?;
+        Ok(count)
+    }
+
+    /// Clear the stop block streak.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    pub fn clear_block_streak(&self) -> Result<()> {
+        let conn = self.open()?;
+        conn.execute("DELETE FROM block_streak", [])?;
+        Ok(())
+    }
+
+    /// Record that the stop hook blocked a session, naming the check that blocked.
+    ///
+    /// Blocks made in the same `state` as the session's previous block extend
+    /// its streak; otherwise the streak restarts with this block.
+    ///
+    /// Returns the checks that blocked during the streak, oldest first.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    pub fn record_injection(
+        &self,
+        session_id: &str,
+        state: &str,
+        check: &str,
+    ) -> Result<Vec<String>> {
+        let conn = self.open()?;
+        let previous: Option<(String, String)> = conn
+            .query_row(
+                "SELECT state, checks FROM injection_streaks WHERE session_id = ?1",
+                params![session_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+
+        let mut checks: Vec<String> = match previous {
+            Some((prev_state, checks)) if prev_state == state => {
+                serde_json::from_str(&checks).unwrap_or_default()
+            }
+            _ => Vec::new(),
+        };
+        checks.push(check.to_string());
+
+        conn.execute(
+            "INSERT OR REPLACE INTO injection_streaks (session_id, state, checks)
+             VALUES (?1, ?2, ?3)",
+            params![session_id, state, serde_json::to_string(&checks)?],
+        )?;
+        Ok(checks)
+    }
+
+    /// Clear a session's streak of stop hook blocks.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    pub fn clear_injection_streak(&self, session_id: &str) -> Result<()> {
+        let conn = self.open()?;
+        conn.execute("DELETE FROM injection_streaks WHERE session_id = ?1", params![session_id])?;
+        Ok(())
+    }
+
+    /// Allow one change to a protected file until `expires_at` (RFC 3339),
+    /// replacing any earlier grant.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    pub fn grant_protected_change(&self, justification: &str, expires_at: &str) -> Result<()> {
+        let conn = self.open()?;
+        conn.execute(
+            "INSERT OR REPLACE INTO protected_change_grant (id, justification, expires_at)
+             VALUES (1, ?1, ?2)",
+            params![justification, expires_at],
+        )?;
+        Ok(())
+    }
+
+    /// Remove and return the grant for a change to a protected file, if any,
+    /// as its justification and expiry time (RFC 3339).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    pub fn take_protected_change_grant(&self) -> Result<Option<(String, String)>> {
+        let conn = self.open()?;
+        let grant = conn
+            .query_row(
+                "DELETE FROM protected_change_grant WHERE id = 1
+                 RETURNING justification, expires_at",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        Ok(grant)
+    }
+
+    /// Check whether all hooks are disabled, returning the time (RFC 3339)
+    /// they are disabled until, if any.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    pub fn hooks_disabled(&self) -> Result<Option<DisabledHooks>> {
+        let conn = self.open()?;
+        let until = conn
+            .query_row("SELECT until FROM hooks_disabled WHERE id = 1", [], |row| row.get(0))
+            .optional()?;
+        Ok(until.map(|until| DisabledHooks { until }))
+    }
+
+    /// Disable all hooks until the given time (RFC 3339), or until re-enabled with `None`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    pub fn disable_hooks(&self, until: Option<&str>) -> Result<()> {
+        let conn = self.open()?;
+        conn.execute(
+            "INSERT OR REPLACE INTO hooks_disabled (id, until) VALUES (1, ?1)",
+            params![until],
+        )?;
+        Ok(())
+    }
+
+    /// Enable hooks again, returning whether they were disabled.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    pub fn enable_hooks(&self) -> Result<bool> {
+        let conn = self.open()?;
+        Ok(conn.execute("DELETE FROM hooks_disabled WHERE id = 1", [])? > 0)
+    }
+
+    /// Get the assigned work item, if any.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    pub fn work_item_assignment(&self) -> Result<Option<WorkItemAssignment>> {
+        let conn = self.open()?;
+        let assignment = conn
+            .query_row(
+                "SELECT work_item_id, chain FROM work_item_assignment WHERE id = 1",
+                [],
+                |row| Ok(WorkItemAssignment { work_item_id: row.get(0)?, chain: row.get(1)? }),
+            )
+            .optional()?;
+        Ok(assignment)
+    }
+
+    /// Assign a work item, replacing any existing assignment.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    pub fn assign_work_item(&self, work_item_id: &str, chain: bool) -> Result<()> {
+        let conn = self.open()?;
+        conn.execute(
+            "INSERT OR REPLACE INTO work_item_assignment (id, work_item_id, chain)
+             VALUES (1, ?1, ?2)",
+            params![work_item_id, chain],
+        )?;
+        Ok(())
+    }
+
+    /// Remove the work item assignment, returning whether there was one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    pub fn clear_work_item_assignment(&self) -> Result<bool> {
+        let conn = self.open()?;
+        Ok(conn.execute("DELETE FROM work_item_assignment WHERE id = 1", [])? > 0)
+    }
+
+    /// Get the time (RFC 3339) until which auto-work prompting is paused, if set.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    pub fn auto_work_paused_until(&self) -> Result<Option<String>> {
+        let conn = self.open()?;
+        let until = conn
+            .query_row("SELECT paused_until FROM auto_work_state WHERE id = 1", [], |row| {
+                row.get(0)
+            })
+            .optional()?;
+        Ok(until.flatten())
+    }
+
+    /// Pause auto-work prompting until the given time (RFC 3339), or resume it with `None`.
+    ///
+    /// Either way the count of declined prompts starts again.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    pub fn set_auto_work_paused_until(&self, until: Option<&str>) -> Result<()> {
+        let conn = self.open()?;
+        conn.execute(
+            "INSERT OR REPLACE INTO auto_work_state (id, paused_until, last_ready_count, declines)
+             VALUES (1, ?1, NULL, 0)",
+            params![until],
+        )?;
+        Ok(())
+    }
+
+    /// Get the API errors reported so far.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    pub fn api_error_state(&self) -> Result<ApiErrorState> {
+        let conn = self.open()?;
+        let state = conn
+            .query_row(
+                "SELECT consecutive_errors, overloaded, retry_at FROM api_errors WHERE id = 1",
+                [],
+                |row| {
+                    Ok(ApiErrorState {
+                        consecutive_errors: row.get(0)?,
+                        overloaded: row.get(1)?,
+                        retry_at: row.get(2)?,
+                    })
+                },
+            )
+            .optional()?;
+        Ok(state.unwrap_or_default())
+    }
+
+    /// Record an API error reported by a tool call.
+    ///
+    /// Returns the number of API errors in a row.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    pub fn record_api_error(&self, overloaded: bool) -> Result<u32> {
+        let conn = self.open()?;
+        Ok(conn.query_row(
+            "INSERT INTO api_errors (id, consecutive_errors, overloaded) VALUES (1, 1, ?1)
+             ON CONFLICT(id) DO UPDATE SET
+                 consecutive_errors = consecutive_errors + 1, overloaded = excluded.overloaded
+             RETURNING consecutive_errors",
+            params![overloaded],
+            |row| row.get(0),
+        )?)
+    }
+
+    /// Set the time (RFC 3339) before which the API should not be called again,
+    /// or clear it with `None`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    pub fn set_api_retry_at(&self, retry_at: Option<&str>) -> Result<()> {
+        let conn = self.open()?;
+        conn.execute(
+            "INSERT INTO api_errors (id, retry_at) VALUES (1, ?1)
+             ON CONFLICT(id) DO UPDATE SET retry_at = excluded.retry_at",
+            params![retry_at],
+        )?;
+        Ok(())
+    }
+
+    /// Forget all API errors and any backoff (the API is working again).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    pub fn clear_api_errors(&self) -> Result<()> {
+        let conn = self.open()?;
+        conn.execute("DELETE FROM api_errors", [])?;
+        Ok(())
+    }
+
+    /// Record an auto-work prompt with the number of ready work items.
+    ///
+    /// If no work item was finished since the previous prompt (the ready count
+    /// did not go down), the prompt counts as declined.
+    ///
+    /// Returns the number of prompts declined in a row.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    pub fn record_auto_work_prompt(&self, ready_count: u32) -> Result<u32> {
+        let conn = self.open()?;
+        let previous: Option<(Option<String>, Option<u32>, u32)> = conn
+            .query_row(
+                "SELECT paused_until, last_ready_count, declines FROM auto_work_state WHERE id = 1",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .optional()?;
+        let (paused_until, last_ready_count, declines) = previous.unwrap_or((None, None, 0));
+
+        let declines = match last_ready_count {
+            Some(last) if ready_count >= last => declines.saturating_add(1),
+            _ => 0,
+        };
+        conn.execute(
+            "INSERT OR REPLACE INTO auto_work_state (id, paused_until, last_ready_count, declines)
+             VALUES (1, ?1, ?2, ?3)",
+            params![paused_until, ready_count, declines],
+        )?;
+        Ok(declines)
+    }
+
+    /// Get the auto-work prompting state of a session.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    pub fn auto_work_session(&self, session_id: &str) -> Result<AutoWorkSession> {
+        let conn = self.open()?;
+        let session = conn
+            .query_row(
+                "SELECT prompts, completed_at_check_in FROM auto_work_sessions
+                 WHERE session_id = ?1",
+                params![session_id],
+                |row| {
+                    Ok(AutoWorkSession { prompts: row.get(0)?, completed_at_check_in: row.get(1)? })
+                },
+            )
+            .optional()?;
+        Ok(session.unwrap_or_default())
+    }
+
+    /// Count an auto-work prompt in a session.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    pub fn record_auto_work_session_prompt(&self, session_id: &str) -> Result<()> {
+        let conn = self.open()?;
+        conn.execute(
+            "INSERT INTO auto_work_sessions (session_id, prompts) VALUES (?1, 1)
+             ON CONFLICT(session_id) DO UPDATE SET prompts = prompts + 1",
+            params![session_id],
+        )?;
+        Ok(())
+    }
+
+    /// Record the number of completed work items at a session's check-in.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    pub fn record_auto_work_check_in(&self, session_id: &str, completed: u32) -> Result<()> {
+        let conn = self.open()?;
+        conn.execute(
+            "INSERT INTO auto_work_sessions (session_id, completed_at_check_in) VALUES (?1, ?2)
+             ON CONFLICT(session_id) DO UPDATE SET completed_at_check_in = ?2",
+            params![session_id, completed],
+        )?;
+        Ok(())
+    }
+
+    /// Forget declined auto-work prompts, keeping any pause.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    pub fn clear_auto_work_declines(&self) -> Result<()> {
+        let conn = self.open()?;
+        conn.execute(
+            "UPDATE auto_work_state SET last_ready_count = NULL, declines = 0 WHERE id = 1",
+            [],
+        )?;
+        Ok(())
+    }
+
+    /// Request a pause, replacing any earlier one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    pub fn set_pause(&self, reason: &str) -> Result<()> {
+        let conn = self.open()?;
+        conn.execute(
+            "INSERT OR REPLACE INTO session_pause (id, reason, stopped) VALUES (1, ?1, 0)",
+            params![reason],
+        )?;
+        Ok(())
+    }
+
+    /// Get the requested pause, if any, as its reason and whether the session stopped for it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    pub fn pause(&self) -> Result<Option<(String, bool)>> {
+        let conn = self.open()?;
+        let pause = conn
+            .query_row("SELECT reason, stopped FROM session_pause WHERE id = 1", [], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .optional()?;
+        Ok(pause)
+    }
+
+    /// Record that the session stopped for the requested pause.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    pub fn mark_pause_stopped(&self) -> Result<()> {
+        let conn = self.open()?;
+        conn.execute("UPDATE session_pause SET stopped = 1 WHERE id = 1", [])?;
+        Ok(())
+    }
+
+    /// Forget the requested pause.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    pub fn clear_pause(&self) -> Result<()> {
+        let conn = self.open()?;
+        conn.execute("DELETE FROM session_pause", [])?;
+        Ok(())
+    }
+
+    /// Record a stash created by the agent.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    pub fn add_agent_stash(&self, sha: &str) -> Result<()> {
+        let conn = self.open()?;
+        conn.execute("INSERT OR IGNORE INTO agent_stashes (sha) VALUES (?1)", params![sha])?;
+        Ok(())
+    }
+
+    /// Get the stashes created by the agent that are still being tracked.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    pub fn agent_stashes(&self) -> Result<Vec<String>> {
+        let conn = self.open()?;
+        let mut stmt = conn.prepare("SELECT sha FROM agent_stashes ORDER BY sha")?;
+        let shas = stmt.query_map([], |row| row.get(0))?.collect::<rusqlite::Result<_>>()?;
+        Ok(shas)
+    }
+
+    /// Stop tracking a stash created by the agent.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    pub fn remove_agent_stash(&self, sha: &str) -> Result<()> {
+        let conn = self.open()?;
+        conn.execute("DELETE FROM agent_stashes WHERE sha = ?1", params![sha])?;
+        Ok(())
+    }
+
+    /// Record a file modified since validation last passed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    pub fn add_touched_file(&self, path: &str) -> Result<()> {
+        let conn = self.open()?;
+        conn.execute("INSERT OR IGNORE INTO touched_files (path) VALUES (?1)", params![path])?;
+        Ok(())
+    }
+
+    /// Get the files modified since validation last passed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    pub fn touched_files(&self) -> Result<Vec<String>> {
+        let conn = self.open()?;
+        let mut stmt = conn.prepare("SELECT path FROM touched_files ORDER BY path")?;
+        let paths = stmt.query_map([], |row| row.get(0))?.collect::<rusqlite::Result<_>>()?;
+        Ok(paths)
+    }
+
+    /// Forget the modified files, after validation passed or the user responded.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    pub fn clear_touched_files(&self) -> Result<()> {
+        let conn = self.open()?;
+        conn.execute("DELETE FROM touched_files", [])?;
+        Ok(())
+    }
+
+    /// Record an emergency stop request and its outcome (`accepted`, `rejected` or `escalated`).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    pub fn add_emergency_stop_attempt(&self, session_id: &str, outcome: &str) -> Result<()> {
+        let conn = self.open()?;
+        conn.execute(
+            "INSERT INTO emergency_stop_attempts (session_id, outcome) VALUES (?1, ?2)",
+            params![session_id, outcome],
+        )?;
+        Ok(())
+    }
+
+    /// Count a session's emergency stop requests in the last `minutes` minutes.
+    ///
+    /// Escalations are recorded alongside the rejected request that caused
+    /// them, so they are not counted again.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    pub fn recent_emergency_stop_attempts(&self, session_id: &str, minutes: u32) -> Result<u32> {
+        let conn = self.open()?;
+        let count = conn.query_row(
+            "SELECT COUNT(*) FROM emergency_stop_attempts
+             WHERE session_id = ?1 AND outcome != 'escalated'
+               AND created_at > datetime('now', ?2)",
+            params![session_id, format!("-{minutes} minutes")],
+            |row| row.get(0),
+        )?;
+        Ok(count)
+    }
+
+    /// Count a session's emergency stop rejections since its last accepted or escalated request.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    pub fn emergency_stop_rejection_streak(&self, session_id: &str) -> Result<u32> {
+        let conn = self.open()?;
+        let count = conn.query_row(
+            "SELECT COUNT(*) FROM emergency_stop_attempts
+             WHERE session_id = ?1 AND outcome = 'rejected' AND id > COALESCE(
+                 (SELECT MAX(id) FROM emergency_stop_attempts
+                  WHERE session_id = ?1 AND outcome != 'rejected'), 0)",
+            params![session_id],
+            |row| row.get(0),
+        )?;
+        Ok(count)
+    }
+
+    /// Record a hook call for a session, registering the session if it is new.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    pub fn touch_session(
+        &self,
+        session_id: &str,
+        transcript_path: Option<&str>,
+        state: SessionState,
+    ) -> Result<()> {
+        let conn = self.open()?;
+        conn.execute(
+            "INSERT INTO sessions (session_id, transcript_path, state) VALUES (?1, ?2, ?3)
+             ON CONFLICT(session_id) DO UPDATE SET
+                 transcript_path = COALESCE(excluded.transcript_path, transcript_path),
+                 state = excluded.state,
+                 last_seen = datetime('now')",
+            params![session_id, transcript_path, state.as_str()],
+        )?;
+        Ok(())
+    }
+
+    /// Get all recorded sessions, most recently seen first.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    pub fn sessions(&self) -> Result<Vec<SessionRecord>> {
+        let conn = self.open()?;
+        let mut stmt = conn.prepare(
+            "SELECT session_id, transcript_path, state, started_at, last_seen
+             FROM sessions ORDER BY last_seen DESC, session_id",
+        )?;
+        let sessions = stmt
+            .query_map([], |row| {
+                let state: String = row.get(2)?;
+                Ok(SessionRecord {
+                    session_id: row.get(0)?,
+                    transcript_path: row.get(1)?,
+                    state: SessionState::from_db(&state),
+                    started_at: row.get(3)?,
+                    last_seen: row.get(4)?,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(sessions)
+    }
+
+    /// Add to a metric counter, creating it if needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    pub fn increment_counter(&self, name: &str, labels: &str, by: f64) -> Result<()> {
+        let conn = self.open()?;
+        conn.execute(
+            "INSERT INTO metric_counters (name, labels, value) VALUES (?1, ?2, ?3)
+             ON CONFLICT(name, labels) DO UPDATE SET value = value + excluded.value",
+            params![name, labels, by],
+        )?;
+        Ok(())
+    }
+
+    /// Get all metric counters as `(name, labels, value)`, ordered by name and labels.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    pub fn counters(&self) -> Result<Vec<(String, String, f64)>> {
         let conn = self.open()?;
+        let mut stmt =
+            conn.prepare("SELECT name, labels, value FROM metric_counters ORDER BY name, labels")?;
+        let counters = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(counters)
+    }
 
-        conn.execute_batch(
-            r"
-            -- Session state for JKW mode (singleton row)
-            CREATE TABLE IF NOT EXISTS session_state (
-                id INTEGER PRIMARY KEY CHECK (id = 1),
-                iteration INTEGER NOT NULL DEFAULT 0,
-                last_issue_change_iteration INTEGER NOT NULL DEFAULT 0,
-                git_diff_hash TEXT
-            );
+    /// Record an observation in a histogram with the given bucket bounds.
+    ///
+    /// If the stored histogram has different bounds, it is restarted.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    pub fn observe_histogram(
+        &self,
+        name: &str,
+        labels: &str,
+        bounds: &[f64],
+        value: f64,
+    ) -> Result<()> {
+        let mut conn = self.open()?;
+        let tx = conn.transaction()?;
+        let existing: Option<(String, f64, u64)> = tx
+            .query_row(
+                "SELECT buckets, sum, count FROM metric_histograms WHERE name = ?1 AND labels = ?2",
+                params![name, labels],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .optional()?;
 
-            -- Issue snapshot (part of session state)
-            CREATE TABLE IF NOT EXISTS issue_snapshot (
-                issue_id TEXT PRIMARY KEY
-            );
+        let (mut buckets, mut sum, mut count) = existing
+            .and_then(|(buckets, sum, count)| {
+                let buckets: Vec<u64> = serde_json::from_str(&buckets).ok()?;
+                (buckets.len() == bounds.len()).then_some((buckets, sum, count))
+            })
+            .unwrap_or_else(|| (vec![0; bounds.len()], 0.0, 0));
+        for (bucket, bound) in buckets.iter_mut().zip(bounds) {
+            if value <= *bound {
+                *bucket += 1;
+            }
+        }
+        sum += value;
+        count += 1;
 
-            -- Boolean markers (presence = true)
-            CREATE TABLE IF NOT EXISTS markers (
-                name TEXT PRIMARY KEY
-            );
-            ",
+        tx.execute(
+            "INSERT OR REPLACE INTO metric_histograms (name, labels, buckets, sum, count)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![name, labels, serde_json::to_string(&buckets)?, sum, count],
         )?;
-
+        tx.commit()?;
         Ok(())
     }
 
-    /// Migrate state from old file-based storage.
-    ///
-    /// This checks for old marker files, migrates them to the `SQLite` database,
-    /// and removes the old files.
+    /// Get all metric histograms, ordered by name and labels.
     ///
     /// # Errors
     ///
-    /// Returns an error if migration fails.
-    pub fn migrate_from_files(&self, base_dir: &Path) -> Result<()> {
-        // Remove legacy JKW files if they exist
-        let legacy_files = [".claude/jkw-state.local.yaml", ".claude/jkw-session.local.md"];
-        for file_path in legacy_files {
-            let full_path = base_dir.join(file_path);
-            if full_path.exists() {
-                let _ = std::fs::remove_file(&full_path);
-            }
-        }
+    /// Returns an error if the database operation fails.
+    pub fn histograms(&self) -> Result<Vec<HistogramRecord>> {
+        let conn = self.open()?;
+        let mut stmt = conn.prepare(
+            "SELECT name, labels, buckets, sum, count FROM metric_histograms ORDER BY name, labels",
+        )?;
+        let histograms = stmt
+            .query_map([], |row| {
+                let buckets: String = row.get(2)?;
+                Ok(HistogramRecord {
+                    name: row.get(0)?,
+                    labels: row.get(1)?,
+                    buckets: serde_json::from_str(&buckets).unwrap_or_default(),
+                    sum: row.get(3)?,
+                    count: row.get(4)?,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(histograms)
+    }
+}
 
-        // Migrate marker files
-        let marker_migrations = [
-            (".claude/problem-mode.local", markers::PROBLEM_MODE),
-            (".claude/needs-validation.local", markers::NEEDS_VALIDATION),
-            (".claude/must-reflect.local", markers::MUST_REFLECT),
-        ];
+/// Drop the `session_state` table left over from JKW mode, which has been removed.
+fn drop_legacy_session_state(tx: &Transaction<'_>) -> Result<()> {
+    let legacy: bool = tx.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info('session_state') WHERE name = 'iteration'",
+        [],
+        |row| row.get(0),
+    )?;
+    if legacy {
+        tx.execute_batch("DROP TABLE session_state; DROP TABLE IF EXISTS issue_snapshot;")?;
+    }
+    Ok(())
+}
 
-        for (file_path, marker_name) in marker_migrations {
-            let full_path = base_dir.join(file_path);
-            if full_path.exists() {
-                self.set_marker(marker_name)?;
-                // Remove old file after successful migration
-                let _ = std::fs::remove_file(&full_path);
+/// Add the columns for any marker the `session_state` table does not have yet.
+fn add_marker_columns(tx: &Transaction<'_>) -> Result<()> {
+    let existing: Vec<String> = tx
+        .prepare("SELECT name FROM pragma_table_info('session_state')")?
+        .query_map([], |row| row.get(0))?
+        .collect::<rusqlite::Result<_>>()?;
+    for marker in Marker::ALL {
+        let column = marker.as_str();
+        if !existing.iter().any(|c| c == column) {
+            tx.execute_batch(&format!(
+                "ALTER TABLE session_state ADD COLUMN {column} INTEGER NOT NULL DEFAULT 0;"
+            ))?;
+        }
+        for (suffix, kind) in [("_at", "TEXT"), ("_session", "TEXT")] {
+            if !existing.iter().any(|c| *c == format!("{column}{suffix}")) {
+                tx.execute_batch(&format!(
+                    "ALTER TABLE session_state ADD COLUMN {column}{suffix} {kind};"
+                ))?;
             }
         }
+    }
+    Ok(())
+}
 
-        Ok(())
+/// Move markers from the old `markers` table (one row per set marker) into `session_state`.
+fn migrate_legacy_markers(tx: &Transaction<'_>) -> Result<()> {
+    let legacy: bool = tx.query_row(
+        "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = 'markers'",
+        [],
+        |row| row.get(0),
+    )?;
+    if !legacy {
+        return Ok(());
+    }
+    for marker in Marker::ALL {
+        let column = marker.as_str();
+        tx.execute(
+            &format!(
+                "UPDATE session_state SET {column} = 1, {column}_at = datetime('now')
+                 WHERE id = 1 AND EXISTS (SELECT 1 FROM markers WHERE name = ?1)"
+            ),
+            params![column],
+        )?;
     }
+    tx.execute_batch("DROP TABLE markers;")?;
+    Ok(())
+}
+
+/// Move per-session markers that were set by a known session out of `session_state`.
+fn migrate_per_session_markers(tx: &Transaction<'_>) -> Result<()> {
+    for marker in Marker::ALL.into_iter().filter(|m| m.is_per_session()) {
+        let column = marker.as_str();
+        tx.execute_batch(&format!(
+            "INSERT OR IGNORE INTO session_markers (session_id, marker, set_at)
+             SELECT {column}_session, '{column}', COALESCE({column}_at, datetime('now'))
+             FROM session_state WHERE id = 1 AND {column} = 1 AND {column}_session IS NOT NULL;
+             UPDATE session_state SET {column} = 0, {column}_at = NULL, {column}_session = NULL
+             WHERE id = 1 AND {column}_session IS NOT NULL;"
+        ))?;
+    }
+    Ok(())
 }
 
 impl StateStore for SqliteStore {
-    fn has_marker(&self, name: &str) -> bool {
-        let Ok(conn) = self.open() else {
-            return false;
-        };
-        conn.query_row("SELECT 1 FROM markers WHERE name = ?1", params![name], |_| Ok(()))
-            .optional()
-            .is_ok_and(|opt| opt.is_some())
+    fn has_marker(&self, marker: Marker) -> bool {
+        match self.read_marker(marker) {
+            Ok(Some(true)) => true,
+            Ok(Some(false)) => {
+                // Expired or left by another session: clean it up
+                if let Err(e) = self.clear_marker(marker) {
+                    tracing::warn!("Failed to clear stale marker {}: {e}", marker.as_str());
+                }
+                false
+            }
+            Ok(None) | Err(_) => false,
+        }
     }
 
-    fn set_marker(&self, name: &str) -> Result<()> {
-        let conn = self.open()?;
-        conn.execute("INSERT OR IGNORE INTO markers (name) VALUES (?1)", params![name])?;
-        Ok(())
+    fn set_marker(&self, marker: Marker) -> Result<()> {
+        self.update_markers(&[(marker, true)])
     }
 
-    fn clear_marker(&self, name: &str) -> Result<()> {
-        let conn = self.open()?;
-        conn.execute("DELETE FROM markers WHERE name = ?1", params![name])?;
-        Ok(())
+    fn clear_marker(&self, marker: Marker) -> Result<()> {
+        self.update_markers(&[(marker, false)])
     }
 }
 
@@ -202,47 +1830,312 @@ mod tests {
         let (_dir, store) = create_test_store();
 
         // Initially no markers
-        assert!(!store.has_marker("test_marker"));
+        assert!(!store.has_marker(Marker::MustReflect));
 
         // Set marker
-        store.set_marker("test_marker").unwrap();
-        assert!(store.has_marker("test_marker"));
+        store.set_marker(Marker::MustReflect).unwrap();
+        assert!(store.has_marker(Marker::MustReflect));
 
         // Set again (idempotent)
-        store.set_marker("test_marker").unwrap();
-        assert!(store.has_marker("test_marker"));
+        store.set_marker(Marker::MustReflect).unwrap();
+        assert!(store.has_marker(Marker::MustReflect));
 
         // Clear marker
-        store.clear_marker("test_marker").unwrap();
-        assert!(!store.has_marker("test_marker"));
+        store.clear_marker(Marker::MustReflect).unwrap();
+        assert!(!store.has_marker(Marker::MustReflect));
 
         // Clear again (idempotent)
-        store.clear_marker("test_marker").unwrap();
-        assert!(!store.has_marker("test_marker"));
+        store.clear_marker(Marker::MustReflect).unwrap();
+        assert!(!store.has_marker(Marker::MustReflect));
     }
 
     #[test]
     fn test_multiple_markers() {
         let (_dir, store) = create_test_store();
 
-        store.set_marker("marker1").unwrap();
-        store.set_marker("marker2").unwrap();
+        store.set_marker(Marker::ProblemMode).unwrap();
+        store.set_marker(Marker::EmergencyStop).unwrap();
+
+        assert!(store.has_marker(Marker::ProblemMode));
+        assert!(store.has_marker(Marker::EmergencyStop));
+        assert!(!store.has_marker(Marker::NeedsValidation));
+        assert_eq!(
+            store.active_markers().unwrap(),
+            vec![Marker::ProblemMode, Marker::EmergencyStop]
+        );
+
+        store.clear_marker(Marker::ProblemMode).unwrap();
+        assert!(!store.has_marker(Marker::ProblemMode));
+        assert!(store.has_marker(Marker::EmergencyStop));
+    }
+
+    #[test]
+    fn test_update_markers_together() {
+        let (_dir, store) = create_test_store();
+        store.set_marker(Marker::NeedsValidation).unwrap();
+
+        store
+            .update_markers(&[(Marker::NeedsValidation, false), (Marker::MustReflect, true)])
+            .unwrap();
+        assert_eq!(store.active_markers().unwrap(), vec![Marker::MustReflect]);
+    }
+
+    #[test]
+    fn test_marker_expires_after_ttl() {
+        let (_dir, store) = create_test_store();
+        store.set_marker(Marker::MustReflect).unwrap();
+        store.set_marker(Marker::ProblemMode).unwrap();
+        store
+            .open()
+            .unwrap()
+            .execute(
+                "UPDATE session_state SET must_reflect_at = datetime('now', '-2 hours'),
+                 problem_mode_at = datetime('now', '-2 hours')",
+                [],
+            )
+            .unwrap();
+
+        let store =
+            store.with_marker_ttls([(Marker::MustReflect, 60), (Marker::ProblemMode, 0)].into());
+        assert!(!store.has_marker(Marker::MustReflect));
+        // A TTL of 0 never expires
+        assert!(store.has_marker(Marker::ProblemMode));
+        // The expired marker was cleaned up
+        let store = store.with_marker_ttls(HashMap::new());
+        assert_eq!(store.active_markers().unwrap(), vec![Marker::ProblemMode]);
+    }
+
+    #[test]
+    fn test_prune_expired_markers() {
+        let (_dir, store) = create_test_store();
+        store.set_marker(Marker::NeedsValidation).unwrap();
+        store.set_marker(Marker::ProblemMode).unwrap();
+        let s1 = store.clone().for_session(Some("s1"));
+        s1.set_marker(Marker::MustReflect).unwrap();
+        let conn = store.open().unwrap();
+        conn.execute(
+            "UPDATE session_state SET needs_validation_at = datetime('now', '-2 days')",
+            [],
+        )
+        .unwrap();
+        conn.execute("UPDATE session_markers SET set_at = datetime('now', '-2 days')", []).unwrap();
+
+        assert_eq!(store.prune_expired_markers().unwrap(), 2);
+        assert_eq!(store.prune_expired_markers().unwrap(), 0);
+        assert_eq!(store.active_markers().unwrap(), vec![Marker::ProblemMode]);
+        assert!(!s1.has_marker(Marker::MustReflect));
+    }
+
+    #[test]
+    fn test_claim_maintenance_run() {
+        let (_dir, store) = create_test_store();
+        assert_eq!(store.last_maintenance_run().unwrap(), None);
+        assert!(store.claim_maintenance_run(60).unwrap());
+        assert!(!store.claim_maintenance_run(60).unwrap());
+        assert!(store.last_maintenance_run().unwrap().is_some());
+
+        store
+            .open()
+            .unwrap()
+            .execute("UPDATE maintenance_state SET last_run_at = datetime('now', '-2 hours')", [])
+            .unwrap();
+        assert!(store.claim_maintenance_run(60).unwrap());
+        assert!(store.claim_maintenance_run(0).unwrap());
+    }
+
+    #[test]
+    fn test_take_database_recoveries() {
+        let (_dir, store) = create_test_store();
+        assert!(store.take_database_recoveries().unwrap().is_empty());
+        store.record_database_recovery("backups/corrupt-1.sqlite3", "Recovered 3 rows").unwrap();
+
+        let recoveries = store.take_database_recoveries().unwrap();
+        assert_eq!(recoveries.len(), 1);
+        assert_eq!(recoveries[0].backup_path, "backups/corrupt-1.sqlite3");
+        assert_eq!(recoveries[0].details, "Recovered 3 rows");
+        assert!(store.take_database_recoveries().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_hook_outcome_counts() {
+        let (_dir, store) = create_test_store();
+        store.record_hook_outcome("stop", "block", Some("uncommitted_changes")).unwrap();
+        store.record_hook_outcome("stop", "block", Some("uncommitted_changes")).unwrap();
+        store.record_hook_outcome("quality_check", "failed", None).unwrap();
+
+        let counts = store.hook_outcome_counts("1970-01-01 00:00:00").unwrap();
+        assert_eq!(counts.len(), 2);
+        assert_eq!(counts[0].kind, "stop");
+        assert_eq!(counts[0].reason.as_deref(), Some("uncommitted_changes"));
+        assert_eq!(counts[0].count, 2);
+        assert_eq!(counts[1].reason, None);
+        assert!(store.hook_outcome_counts("9999-01-01 00:00:00").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_question_deliveries() {
+        let (_dir, store) = create_test_store();
+        store.record_question_delivery("q1", "github").unwrap();
+        store.record_question_delivery("q1", "github").unwrap();
+        store.record_question_delivery("q2", "email").unwrap();
+
+        assert_eq!(store.delivered_questions("github").unwrap(), vec!["q1"]);
+        assert_eq!(store.delivered_questions("email").unwrap(), vec!["q2"]);
+        assert!(store.delivered_questions("slack").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_transcript_index() {
+        use crate::transcript::EventKind;
+
+        let (_dir, store) = create_test_store();
+        let event =
+            |line: usize, kind: EventKind, tool: Option<&str>, text: &str| TranscriptEvent {
+                line,
+                timestamp: Some(format!("2026-01-01T00:00:0{line}Z")),
+                kind,
+                tool: tool.map(str::to_string),
+                text: text.to_string(),
+            };
+        assert!(store.transcript_position("s1").unwrap().is_none());
+
+        let position = TranscriptPosition { offset: 100, lines: 2 };
+        let events = [
+            event(1, EventKind::User, None, "Please fix the parser"),
+            event(2, EventKind::ToolCall, Some("Edit"), "src/parser.rs"),
+        ];
+        store.index_transcript_events("s1", "/t/s1.jsonl", &events, position, false).unwrap();
+        let other = [event(1, EventKind::Assistant, None, "The parser looks fine")];
+        store.index_transcript_events("s2", "/t/s2.jsonl", &other, position, false).unwrap();
+        assert_eq!(
+            store.transcript_position("s1").unwrap(),
+            Some(("/t/s1.jsonl".to_string(), position))
+        );
+
+        let found = store.search_transcript_events("parser", None, 10).unwrap();
+        let lines: Vec<(&str, usize)> =
+            found.iter().map(|m| (m.session_id.as_str(), m.line)).collect();
+        assert_eq!(lines, vec![("s1", 2), ("s1", 1), ("s2", 1)]);
+        assert_eq!(found[0].tool.as_deref(), Some("Edit"));
+        assert_eq!(found[0].kind, "tool_call");
+        assert_eq!(found[1].snippet, "Please fix the [parser]");
+
+        let found = store.search_transcript_events("parser", Some("s2"), 10).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(store.search_transcript_events("parser", None, 1).unwrap().len(), 1);
+        assert_eq!(store.search_transcript_events("Edit", None, 10).unwrap().len(), 1);
+
+        let position = TranscriptPosition { offset: 10, lines: 1 };
+        let events = [event(1, EventKind::User, None, "Start again")];
+        store.index_transcript_events("s1", "/t/s1.jsonl", &events, position, true).unwrap();
+        assert!(store.search_transcript_events("parser", Some("s1"), 10).unwrap().is_empty());
+        assert_eq!(store.search_transcript_events("again", None, 10).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_markers_scoped_to_session() {
+        let (_dir, store) = create_test_store();
+        let s1 = store.clone().for_session(Some("s1"));
+        let s2 = store.clone().for_session(Some("s2"));
+
+        s1.set_marker(Marker::EmergencyStop).unwrap();
+        assert!(s1.has_marker(Marker::EmergencyStop));
+        // Without a session, markers of every session count
+        assert!(store.has_marker(Marker::EmergencyStop));
+
+        assert_eq!(s2.active_markers().unwrap(), vec![]);
+        assert!(!s1.has_marker(Marker::EmergencyStop));
+    }
+
+    #[test]
+    fn test_per_session_markers_are_independent() {
+        let (_dir, store) = create_test_store();
+        let s1 = store.clone().for_session(Some("s1"));
+        let s2 = store.for_session(Some("s2"));
+
+        s1.set_marker(Marker::MustReflect).unwrap();
+        assert!(s1.has_marker(Marker::MustReflect));
+        assert!(!s2.has_marker(Marker::MustReflect));
+
+        // The other session reading or clearing its own marker leaves this one alone
+        s2.clear_marker(Marker::MustReflect).unwrap();
+        s2.set_marker(Marker::WorkItemReminded).unwrap();
+        assert!(s1.has_marker(Marker::MustReflect));
+        assert!(!s1.has_marker(Marker::WorkItemReminded));
+        assert_eq!(s2.active_markers().unwrap(), vec![Marker::WorkItemReminded]);
+    }
+
+    #[test]
+    fn test_expired_per_session_markers_are_forgotten() {
+        let (_dir, store) = create_test_store();
+        let s1 = store.clone().for_session(Some("s1"));
+        s1.set_marker(Marker::MustReflect).unwrap();
+        s1.open()
+            .unwrap()
+            .execute("UPDATE session_markers SET set_at = datetime('now', '-2 days')", [])
+            .unwrap();
+
+        // Another session setting the marker cleans up the expired one
+        store.clone().for_session(Some("s2")).set_marker(Marker::MustReflect).unwrap();
+        let remaining: u32 = store
+            .open()
+            .unwrap()
+            .query_row("SELECT COUNT(*) FROM session_markers", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(remaining, 1);
+    }
+
+    #[test]
+    fn test_migrates_session_scoped_markers_to_per_session_rows() {
+        let (dir, store) = create_test_store();
+        store
+            .open()
+            .unwrap()
+            .execute_batch(
+                "UPDATE session_state SET must_reflect = 1, must_reflect_session = 's1',
+                 work_item_reminded = 1",
+            )
+            .unwrap();
 
-        assert!(store.has_marker("marker1"));
-        assert!(store.has_marker("marker2"));
-        assert!(!store.has_marker("marker3"));
+        let store = SqliteStore::new(dir.path()).unwrap();
+        assert!(store.clone().for_session(Some("s1")).has_marker(Marker::MustReflect));
+        assert!(!store.clone().for_session(Some("s2")).has_marker(Marker::MustReflect));
+        // A marker set without a session stays shared
+        assert!(store.has_marker(Marker::WorkItemReminded));
+        assert!(!store.has_marker(Marker::MustReflect));
+    }
 
-        store.clear_marker("marker1").unwrap();
-        assert!(!store.has_marker("marker1"));
-        assert!(store.has_marker("marker2"));
+    #[test]
+    fn test_marker_names() {
+        for marker in Marker::ALL {
+            assert_eq!(Marker::from_name(marker.as_str()), Some(marker));
+        }
+        assert_eq!(Marker::from_name("unknown"), None);
+        assert_eq!(markers::PROBLEM_MODE, Marker::ProblemMode);
     }
 
     #[test]
-    fn test_marker_constants() {
-        // Verify marker constants are defined
-        assert!(!markers::PROBLEM_MODE.is_empty());
-        assert!(!markers::NEEDS_VALIDATION.is_empty());
-        assert!(!markers::MUST_REFLECT.is_empty());
+    fn test_migrates_legacy_marker_table() {
+        let dir = TempDir::new().unwrap();
+        let db_path = dir.path().join("legacy.db");
+        let conn = Connection::open(&db_path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE session_state (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                iteration INTEGER NOT NULL DEFAULT 0
+            );
+            CREATE TABLE markers (name TEXT PRIMARY KEY);
+            INSERT INTO markers (name) VALUES ('problem_mode'), ('must_reflect'), ('stale');",
+        )
+        .unwrap();
+        drop(conn);
+
+        let store = SqliteStore::with_path(db_path.clone()).unwrap();
+        assert_eq!(store.active_markers().unwrap(), vec![Marker::ProblemMode, Marker::MustReflect]);
+
+        // Opening again leaves the migrated state alone
+        let store = SqliteStore::with_path(db_path).unwrap();
+        assert!(store.has_marker(Marker::ProblemMode));
     }
 
     #[test]
@@ -310,8 +2203,302 @@ mod tests {
         assert_eq!(store.db_path(), db_path);
 
         // Should work
-        store.set_marker("test").unwrap();
-        assert!(store.has_marker("test"));
+        store.set_marker(Marker::MustReflect).unwrap();
+        assert!(store.has_marker(Marker::MustReflect));
+    }
+
+    #[test]
+    fn test_record_block_counts_identical_signatures() {
+        let (_dir, store) = create_test_store();
+
+        assert_eq!(store.record_block("sig-a").unwrap(), 1);
+        assert_eq!(store.record_block("sig-a").unwrap(), 2);
+        assert_eq!(store.record_block("sig-a").unwrap(), 3);
+    }
+
+    #[test]
+    fn test_record_block_resets_on_different_signature() {
+        let (_dir, store) = create_test_store();
+
+        store.record_block("sig-a").unwrap();
+        store.record_block("sig-a").unwrap();
+        assert_eq!(store.record_block("sig-b").unwrap(), 1);
+    }
+
+    #[test]
+    fn test_clear_block_streak() {
+        let (_dir, store) = create_test_store();
+
+        store.record_block("sig-a").unwrap();
+        store.record_block("sig-a").unwrap();
+        store.clear_block_streak().unwrap();
+        assert_eq!(store.record_block("sig-a").unwrap(), 1);
+    }
+
+    #[test]
+    fn test_injection_streak() {
+        let (_dir, store) = create_test_store();
+
+        assert_eq!(store.record_injection("s1", "state-a", "validation").unwrap(), ["validation"]);
+        assert_eq!(
+            store.record_injection("s1", "state-a", "reflection_prompt").unwrap(),
+            ["validation", "reflection_prompt"]
+        );
+        // Other sessions have their own streak
+        assert_eq!(store.record_injection("s2", "state-a", "auto_work").unwrap(), ["auto_work"]);
+        // A change of state restarts the streak
+        assert_eq!(store.record_injection("s1", "state-b", "validation").unwrap(), ["validation"]);
+
+        store.clear_injection_streak("s1").unwrap();
+        assert_eq!(store.record_injection("s1", "state-b", "validation").unwrap(), ["validation"]);
+        assert_eq!(store.record_injection("s2", "state-a", "auto_work").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_protected_change_grant() {
+        let (_dir, store) = create_test_store();
+        assert_eq!(store.take_protected_change_grant().unwrap(), None);
+
+        store.grant_protected_change("first", "2030-01-01T00:00:00+00:00").unwrap();
+        store.grant_protected_change("second", "2030-01-02T00:00:00+00:00").unwrap();
+        assert_eq!(
+            store.take_protected_change_grant().unwrap(),
+            Some(("second".to_string(), "2030-01-02T00:00:00+00:00".to_string()))
+        );
+        // A grant is used once
+        assert_eq!(store.take_protected_change_grant().unwrap(), None);
+    }
+
+    #[test]
+    fn test_agent_stashes() {
+        let (_dir, store) = create_test_store();
+        assert!(store.agent_stashes().unwrap().is_empty());
+
+        store.add_agent_stash("bbb").unwrap();
+        store.add_agent_stash("aaa").unwrap();
+        store.add_agent_stash("aaa").unwrap();
+        assert_eq!(store.agent_stashes().unwrap(), vec!["aaa", "bbb"]);
+
+        store.remove_agent_stash("aaa").unwrap();
+        assert_eq!(store.agent_stashes().unwrap(), vec!["bbb"]);
+    }
+
+    #[test]
+    fn test_touched_files() {
+        let (_dir, store) = create_test_store();
+        assert!(store.touched_files().unwrap().is_empty());
+
+        store.add_touched_file("src/lib.rs").unwrap();
+        store.add_touched_file("crates/api/src/main.rs").unwrap();
+        store.add_touched_file("src/lib.rs").unwrap();
+        assert_eq!(store.touched_files().unwrap(), vec!["crates/api/src/main.rs", "src/lib.rs"]);
+
+        store.clear_touched_files().unwrap();
+        assert!(store.touched_files().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_emergency_stop_attempts() {
+        let (_dir, store) = create_test_store();
+        assert_eq!(store.recent_emergency_stop_attempts("s1", 60).unwrap(), 0);
+
+        store.add_emergency_stop_attempt("s1", "rejected").unwrap();
+        store.add_emergency_stop_attempt("s1", "rejected").unwrap();
+        store.add_emergency_stop_attempt("s2", "rejected").unwrap();
+        assert_eq!(store.recent_emergency_stop_attempts("s1", 60).unwrap(), 2);
+        assert_eq!(store.emergency_stop_rejection_streak("s1").unwrap(), 2);
+
+        store.add_emergency_stop_attempt("s1", "escalated").unwrap();
+        store.add_emergency_stop_attempt("s1", "rejected").unwrap();
+        assert_eq!(store.emergency_stop_rejection_streak("s1").unwrap(), 1);
+        assert_eq!(store.emergency_stop_rejection_streak("s2").unwrap(), 1);
+        assert_eq!(store.recent_emergency_stop_attempts("s1", 60).unwrap(), 3);
+    }
+
+    #[test]
+    fn test_work_item_assignment() {
+        let (_dir, store) = create_test_store();
+        assert_eq!(store.work_item_assignment().unwrap(), None);
+        assert!(!store.clear_work_item_assignment().unwrap());
+
+        store.assign_work_item("a-1", false).unwrap();
+        store.assign_work_item("b-2", true).unwrap();
+        assert_eq!(
+            store.work_item_assignment().unwrap(),
+            Some(WorkItemAssignment { work_item_id: "b-2".to_string(), chain: true })
+        );
+
+        assert!(store.clear_work_item_assignment().unwrap());
+        assert_eq!(store.work_item_assignment().unwrap(), None);
+    }
+
+    #[test]
+    fn test_hooks_disabled() {
+        let (_dir, store) = create_test_store();
+        assert_eq!(store.hooks_disabled().unwrap(), None);
+        assert!(!store.enable_hooks().unwrap());
+
+        store.disable_hooks(None).unwrap();
+        assert_eq!(store.hooks_disabled().unwrap(), Some(DisabledHooks { until: None }));
+
+        store.disable_hooks(Some("2030-01-01T00:00:00+00:00")).unwrap();
+        assert_eq!(
+            store.hooks_disabled().unwrap(),
+            Some(DisabledHooks { until: Some("2030-01-01T00:00:00+00:00".to_string()) })
+        );
+
+        assert!(store.enable_hooks().unwrap());
+        assert_eq!(store.hooks_disabled().unwrap(), None);
+    }
+
+    #[test]
+    fn test_auto_work_sessions() {
+        let (_dir, store) = create_test_store();
+        assert_eq!(store.auto_work_session("s1").unwrap(), AutoWorkSession::default());
+
+        store.record_auto_work_session_prompt("s1").unwrap();
+        store.record_auto_work_session_prompt("s1").unwrap();
+        store.record_auto_work_check_in("s1", 4).unwrap();
+        store.record_auto_work_check_in("s2", 1).unwrap();
+        assert_eq!(
+            store.auto_work_session("s1").unwrap(),
+            AutoWorkSession { prompts: 2, completed_at_check_in: Some(4) }
+        );
+        assert_eq!(
+            store.auto_work_session("s2").unwrap(),
+            AutoWorkSession { prompts: 0, completed_at_check_in: Some(1) }
+        );
+    }
+
+    #[test]
+    fn test_auto_work_state() {
+        let (_dir, store) = create_test_store();
+        assert_eq!(store.auto_work_paused_until().unwrap(), None);
+
+        assert_eq!(store.record_auto_work_prompt(3).unwrap(), 0);
+        assert_eq!(store.record_auto_work_prompt(3).unwrap(), 1);
+        assert_eq!(store.record_auto_work_prompt(4).unwrap(), 2);
+        // Finishing a work item is progress
+        assert_eq!(store.record_auto_work_prompt(2).unwrap(), 0);
+        assert_eq!(store.record_auto_work_prompt(2).unwrap(), 1);
+
+        store.clear_auto_work_declines().unwrap();
+        assert_eq!(store.record_auto_work_prompt(2).unwrap(), 0);
+
+        store.set_auto_work_paused_until(Some("2030-01-01T00:00:00+00:00")).unwrap();
+        assert_eq!(store.record_auto_work_prompt(2).unwrap(), 0);
+        assert_eq!(
+            store.auto_work_paused_until().unwrap().as_deref(),
+            Some("2030-01-01T00:00:00+00:00")
+        );
+        store.set_auto_work_paused_until(None).unwrap();
+        assert_eq!(store.auto_work_paused_until().unwrap(), None);
+    }
+
+    #[test]
+    fn test_api_error_state() {
+        let (_dir, store) = create_test_store();
+        assert_eq!(store.api_error_state().unwrap(), ApiErrorState::default());
+
+        store.set_api_retry_at(Some("2030-01-01T00:00:00+00:00")).unwrap();
+        assert_eq!(store.record_api_error(true).unwrap(), 1);
+        assert_eq!(store.record_api_error(false).unwrap(), 2);
+        assert_eq!(store.record_api_error(true).unwrap(), 3);
+        store.set_api_retry_at(Some("2030-01-01T00:01:00+00:00")).unwrap();
+        assert_eq!(
+            store.api_error_state().unwrap(),
+            ApiErrorState {
+                consecutive_errors: 3,
+                overloaded: true,
+                retry_at: Some("2030-01-01T00:01:00+00:00".to_string()),
+            }
+        );
+
+        store.set_api_retry_at(None).unwrap();
+        assert_eq!(store.api_error_state().unwrap().retry_at, None);
+        store.clear_api_errors().unwrap();
+        assert_eq!(store.api_error_state().unwrap(), ApiErrorState::default());
+    }
+
+    #[test]
+    fn test_session_pause() {
+        let (_dir, store) = create_test_store();
+        assert_eq!(store.pause().unwrap(), None);
+
+        store.set_pause("lunch").unwrap();
+        assert_eq!(store.pause().unwrap(), Some(("lunch".to_string(), false)));
+        store.mark_pause_stopped().unwrap();
+        assert_eq!(store.pause().unwrap(), Some(("lunch".to_string(), true)));
+
+        // A new request starts over
+        store.set_pause("meeting").unwrap();
+        assert_eq!(store.pause().unwrap(), Some(("meeting".to_string(), false)));
+
+        store.clear_pause().unwrap();
+        assert_eq!(store.pause().unwrap(), None);
+    }
+
+    #[test]
+    fn test_touch_session() {
+        let (_dir, store) = create_test_store();
+        assert!(store.sessions().unwrap().is_empty());
+
+        store.touch_session("s1", Some("/t/s1.jsonl"), SessionState::Working).unwrap();
+        store.touch_session("s1", None, SessionState::Blocked).unwrap();
+        store.touch_session("s2", None, SessionState::Stopped).unwrap();
+
+        let sessions = store.sessions().unwrap();
+        assert_eq!(sessions.len(), 2);
+        let s1 = sessions.iter().find(|s| s.session_id == "s1").unwrap();
+        assert_eq!(s1.transcript_path.as_deref(), Some("/t/s1.jsonl"));
+        assert_eq!(s1.state, SessionState::Blocked);
+        let s2 = sessions.iter().find(|s| s.session_id == "s2").unwrap();
+        assert_eq!(s2.state, SessionState::Stopped);
+        assert!(s2.transcript_path.is_none());
+    }
+
+    #[test]
+    fn test_session_state_round_trip() {
+        for state in [SessionState::Working, SessionState::Blocked, SessionState::Stopped] {
+            assert_eq!(SessionState::from_db(state.as_str()), state);
+        }
+        assert_eq!(SessionState::from_db("unknown"), SessionState::Working);
+    }
+
+    #[test]
+    fn test_counters() {
+        let (_dir, store) = create_test_store();
+        store.increment_counter("blocks_total", r#"reason="a""#, 1.0).unwrap();
+        store.increment_counter("blocks_total", r#"reason="a""#, 2.0).unwrap();
+        store.increment_counter("blocks_total", r#"reason="b""#, 1.0).unwrap();
+
+        assert_eq!(
+            store.counters().unwrap(),
+            vec![
+                ("blocks_total".to_string(), r#"reason="a""#.to_string(), 3.0),
+                ("blocks_total".to_string(), r#"reason="b""#.to_string(), 1.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_observe_histogram() {
+        let (_dir, store) = create_test_store();
+        store.observe_histogram("duration", "", &[1.0, 5.0], 0.5).unwrap();
+        store.observe_histogram("duration", "", &[1.0, 5.0], 3.0).unwrap();
+        store.observe_histogram("duration", "", &[1.0, 5.0], 9.0).unwrap();
+
+        let histograms = store.histograms().unwrap();
+        assert_eq!(histograms.len(), 1);
+        assert_eq!(histograms[0].buckets, vec![1, 2]);
+        assert!((histograms[0].sum - 12.5).abs() < f64::EPSILON);
+        assert_eq!(histograms[0].count, 3);
+
+        // Changing the bounds restarts the histogram
+        store.observe_histogram("duration", "", &[10.0], 2.0).unwrap();
+        let histograms = store.histograms().unwrap();
+        assert_eq!(histograms[0].buckets, vec![1]);
+        assert_eq!(histograms[0].count, 1);
     }
 
     #[test]
@@ -325,9 +2512,9 @@ mod tests {
         std::fs::create_dir_all(&db_path).unwrap(); // Make db_path a directory!
 
         // Create store with path that is actually a directory (not a file)
-        let store = SqliteStore { db_path };
+        let store = SqliteStore { db_path, session_id: None, marker_ttls: HashMap::new() };
 
         // has_marker should return false when open fails (because path is a directory)
-        assert!(!store.has_marker("test_marker"));
+        assert!(!store.has_marker(Marker::MustReflect));
     }
 }
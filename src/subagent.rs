@@ -2,10 +2,13 @@
 
 use crate::error::Result;
 use crate::subagent_logging::log_subagent_event;
+use crate::tasks::AnswerSuggestion;
 use crate::templates;
 use crate::traits::{
-    CommandRunner, CreateQuestionContext, CreateQuestionDecision, EmergencyStopContext,
-    EmergencyStopDecision, QuestionContext, ReflectionContext, ReflectionDecision, SubAgent,
+    CommandRunner, CommitPushRequest, CommitSplitContext, CreateQuestionContext,
+    CreateQuestionDecision, EmergencyStopContext, EmergencyStopDecision, HandoffContext,
+    PlannedCommit, PrDescription, PrDescriptionContext, ProtectedChangeContext,
+    ProtectedChangeDecision, QuestionContext, ReflectionContext, ReflectionDecision, SubAgent,
     SubAgentDecision,
 };
 use std::time::{Duration, Instant};
@@ -26,6 +29,42 @@ const CREATE_QUESTION_TIMEOUT: Duration = Duration::from_secs(60);
 /// Timeout for reflection decisions (60 seconds).
 const REFLECTION_DECISION_TIMEOUT: Duration = Duration::from_secs(60);
 
+/// Timeout for WIP commit summaries (60 seconds).
+const WIP_SUMMARY_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Maximum number of diff characters sent when summarizing a WIP commit.
+const WIP_SUMMARY_MAX_DIFF_CHARS: usize = 20_000;
+
+/// Summary used when the sub-agent cannot produce one.
+pub const DEFAULT_WIP_SUMMARY: &str = "work in progress";
+
+/// Timeout for pull request descriptions (2 minutes).
+const PR_DESCRIPTION_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Timeout for writing a session handoff summary (120 seconds).
+const HANDOFF_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Timeout for narrating an activity report (2 minutes).
+const REPORT_NARRATIVE_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Timeout for planning a split of uncommitted changes into commits (2 minutes).
+const COMMIT_SPLIT_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Maximum number of `--stat` characters sent when planning a commit split.
+const COMMIT_SPLIT_MAX_STAT_CHARS: usize = 20_000;
+
+/// Timeout for classifying commit/push questions (60 seconds).
+const COMMIT_PUSH_QUESTION_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Timeout for drafting candidate answers to a question (60 seconds).
+const ANSWER_SUGGESTIONS_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Timeout for protected change decisions (60 seconds).
+const PROTECTED_CHANGE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Maximum number of candidate answers kept for a question.
+const MAX_ANSWER_SUGGESTIONS: usize = 3;
+
 /// Subdirectory name for running sub-agents to avoid picking up project hooks.
 const SUBAGENT_SUBDIR: &str = "claude-reliability-subagents";
 
@@ -216,6 +255,7 @@ impl SubAgent for RealSubAgent<'_> {
     ) -> Result<EmergencyStopDecision> {
         let mut ctx = Context::new();
         ctx.insert("explanation", &context.explanation);
+        ctx.insert("category", &context.category);
 
         let prompt = templates::render("prompts/emergency_stop_decision.tera", &ctx)
             .expect("emergency_stop_decision.tera template should always render");
@@ -320,6 +360,7 @@ impl SubAgent for RealSubAgent<'_> {
         let mut ctx = Context::new();
         ctx.insert("reflection_output", &context.reflection_output);
         ctx.insert("user_messages", &context.user_messages);
+        ctx.insert("unchecked_criteria", &context.unchecked_criteria);
 
         let prompt = templates::render("prompts/reflection_decision.tera", &ctx)
             .expect("reflection_decision.tera template should always render");
@@ -355,6 +396,471 @@ impl SubAgent for RealSubAgent<'_> {
 
         Ok(parse_reflection_response(response))
     }
+
+    fn summarize_wip_commit(&self, diff: &str) -> Result<String> {
+        let diff: String = diff.chars().take(WIP_SUMMARY_MAX_DIFF_CHARS).collect();
+        let mut ctx = Context::new();
+        ctx.insert("diff", &diff);
+
+        let prompt = templates::render("prompts/wip_commit_summary.tera", &ctx)
+            .expect("wip_commit_summary.tera template should always render");
+
+        let start = Instant::now();
+
+        // Run in a neutral directory to avoid picking up project hooks
+        let output = self.runner.run_in_dir(
+            self.claude_cmd(),
+            &["--print", "--model", "haiku", "-p", &prompt],
+            Some(WIP_SUMMARY_TIMEOUT),
+            &get_subagent_cwd(),
+        )?;
+
+        #[allow(clippy::cast_possible_truncation)] // Duration in ms won't overflow u64
+        let duration_ms = start.elapsed().as_millis() as u64;
+
+        if !output.success() {
+            log_subagent_event(
+                "wip_commit_summary",
+                &prompt,
+                Some(&output.stderr),
+                false,
+                Some(duration_ms),
+            );
+            // If Claude fails, fall back to a generic summary
+            return Ok(DEFAULT_WIP_SUMMARY.to_string());
+        }
+
+        let response = output.stdout.trim();
+
+        log_subagent_event("wip_commit_summary", &prompt, Some(response), true, Some(duration_ms));
+
+        Ok(parse_wip_summary(response))
+    }
+
+    fn write_pr_description(&self, context: &PrDescriptionContext) -> Result<PrDescription> {
+        let mut ctx = Context::new();
+        ctx.insert("branch", &context.branch);
+        ctx.insert("commits", &context.commits);
+        ctx.insert("work_items", &context.work_items);
+
+        let prompt = templates::render("prompts/pr_description.tera", &ctx)
+            .expect("pr_description.tera template should always render");
+
+        let start = Instant::now();
+
+        // Run in a neutral directory to avoid picking up project hooks
+        let output = self.runner.run_in_dir(
+            self.claude_cmd(),
+            &["--print", "--model", "haiku", "-p", &prompt],
+            Some(PR_DESCRIPTION_TIMEOUT),
+            &get_subagent_cwd(),
+        )?;
+
+        #[allow(clippy::cast_possible_truncation)] // Duration in ms won't overflow u64
+        let duration_ms = start.elapsed().as_millis() as u64;
+
+        if !output.success() {
+            log_subagent_event(
+                "pr_description",
+                &prompt,
+                Some(&output.stderr),
+                false,
+                Some(duration_ms),
+            );
+            // If Claude fails, fall back to a description built from the context
+            return Ok(fallback_pr_description(context));
+        }
+
+        let response = output.stdout.trim();
+
+        log_subagent_event("pr_description", &prompt, Some(response), true, Some(duration_ms));
+
+        Ok(parse_pr_description(response).unwrap_or_else(|| fallback_pr_description(context)))
+    }
+
+    fn write_handoff(&self, context: &HandoffContext) -> Result<String> {
+        let mut ctx = handoff_template_context(context);
+        ctx.insert("final_message", context.final_message.as_deref().unwrap_or_default());
+
+        let prompt = templates::render("prompts/handoff.tera", &ctx)
+            .expect("handoff.tera template should always render");
+
+        let start = Instant::now();
+
+        // Run in a neutral directory to avoid picking up project hooks
+        let output = self.runner.run_in_dir(
+            self.claude_cmd(),
+            &["--print", "--model", "haiku", "-p", &prompt],
+            Some(HANDOFF_TIMEOUT),
+            &get_subagent_cwd(),
+        )?;
+
+        #[allow(clippy::cast_possible_truncation)] // Duration in ms won't overflow u64
+        let duration_ms = start.elapsed().as_millis() as u64;
+
+        if !output.success() {
+            log_subagent_event("handoff", &prompt, Some(&output.stderr), false, Some(duration_ms));
+            // If Claude fails, fall back to a summary built from the context
+            return Ok(fallback_handoff(context));
+        }
+
+        let response = output.stdout.trim();
+
+        log_subagent_event("handoff", &prompt, Some(response), true, Some(duration_ms));
+
+        if response.is_empty() {
+            return Ok(fallback_handoff(context));
+        }
+        Ok(format!("{response}\n"))
+    }
+
+    fn narrate_report(&self, report: &str) -> Result<String> {
+        let mut ctx = Context::new();
+        ctx.insert("report", report);
+
+        let prompt = templates::render("prompts/report_narrative.tera", &ctx)
+            .expect("report_narrative.tera template should always render");
+
+        let start = Instant::now();
+
+        // Run in a neutral directory to avoid picking up project hooks
+        let output = self.runner.run_in_dir(
+            self.claude_cmd(),
+            &["--print", "--model", "haiku", "-p", &prompt],
+            Some(REPORT_NARRATIVE_TIMEOUT),
+            &get_subagent_cwd(),
+        )?;
+
+        #[allow(clippy::cast_possible_truncation)] // Duration in ms won't overflow u64
+        let duration_ms = start.elapsed().as_millis() as u64;
+
+        if !output.success() {
+            log_subagent_event(
+                "report_narrative",
+                &prompt,
+                Some(&output.stderr),
+                false,
+                Some(duration_ms),
+            );
+            // If Claude fails, the report goes out without a narrative
+            return Ok(String::new());
+        }
+
+        let response = output.stdout.trim();
+
+        log_subagent_event("report_narrative", &prompt, Some(response), true, Some(duration_ms));
+
+        Ok(response.to_string())
+    }
+
+    fn plan_commit_split(&self, context: &CommitSplitContext) -> Result<Vec<PlannedCommit>> {
+        let diff_stat: String =
+            context.diff_stat.chars().take(COMMIT_SPLIT_MAX_STAT_CHARS).collect();
+        let mut ctx = Context::new();
+        ctx.insert("files", &context.files);
+        ctx.insert("diff_stat", &diff_stat);
+
+        let prompt = templates::render("prompts/commit_split.tera", &ctx)
+            .expect("commit_split.tera template should always render");
+
+        let start = Instant::now();
+
+        // Run in a neutral directory to avoid picking up project hooks
+        let output = self.runner.run_in_dir(
+            self.claude_cmd(),
+            &["--print", "--model", "haiku", "-p", &prompt],
+            Some(COMMIT_SPLIT_TIMEOUT),
+            &get_subagent_cwd(),
+        )?;
+
+        #[allow(clippy::cast_possible_truncation)] // Duration in ms won't overflow u64
+        let duration_ms = start.elapsed().as_millis() as u64;
+
+        if !output.success() {
+            log_subagent_event(
+                "commit_split",
+                &prompt,
+                Some(&output.stderr),
+                false,
+                Some(duration_ms),
+            );
+            // If Claude fails, propose no split; the caller gives generic advice
+            return Ok(Vec::new());
+        }
+
+        let response = output.stdout.trim();
+
+        log_subagent_event("commit_split", &prompt, Some(response), true, Some(duration_ms));
+
+        Ok(parse_commit_split(response, &context.files))
+    }
+
+    fn classify_commit_push_question(&self, question: &str) -> Result<Option<CommitPushRequest>> {
+        let mut ctx = Context::new();
+        ctx.insert("question", question);
+
+        let prompt = templates::render("prompts/commit_push_question.tera", &ctx)
+            .expect("commit_push_question.tera template should always render");
+
+        let start = Instant::now();
+
+        // Run in a neutral directory to avoid picking up project hooks
+        let output = self.runner.run_in_dir(
+            self.claude_cmd(),
+            &["--print", "--model", "haiku", "-p", &prompt],
+            Some(COMMIT_PUSH_QUESTION_TIMEOUT),
+            &get_subagent_cwd(),
+        )?;
+
+        #[allow(clippy::cast_possible_truncation)] // Duration in ms won't overflow u64
+        let duration_ms = start.elapsed().as_millis() as u64;
+
+        if !output.success() {
+            log_subagent_event(
+                "commit_push_question",
+                &prompt,
+                Some(&output.stderr),
+                false,
+                Some(duration_ms),
+            );
+            // If Claude fails, don't auto-confirm anything
+            return Ok(None);
+        }
+
+        let response = output.stdout.trim();
+
+        log_subagent_event(
+            "commit_push_question",
+            &prompt,
+            Some(response),
+            true,
+            Some(duration_ms),
+        );
+
+        Ok(parse_commit_push_question(response))
+    }
+
+    fn suggest_answers(&self, question: &str) -> Result<Vec<AnswerSuggestion>> {
+        let mut ctx = Context::new();
+        ctx.insert("question", question);
+
+        let prompt = templates::render("prompts/answer_suggestions.tera", &ctx)
+            .expect("answer_suggestions.tera template should always render");
+
+        let start = Instant::now();
+
+        // Run in a neutral directory to avoid picking up project hooks
+        let output = self.runner.run_in_dir(
+            self.claude_cmd(),
+            &["--print", "--model", "haiku", "-p", &prompt],
+            Some(ANSWER_SUGGESTIONS_TIMEOUT),
+            &get_subagent_cwd(),
+        )?;
+
+        #[allow(clippy::cast_possible_truncation)] // Duration in ms won't overflow u64
+        let duration_ms = start.elapsed().as_millis() as u64;
+
+        if !output.success() {
+            log_subagent_event(
+                "answer_suggestions",
+                &prompt,
+                Some(&output.stderr),
+                false,
+                Some(duration_ms),
+            );
+            // If Claude fails, the question is asked without suggestions
+            return Ok(Vec::new());
+        }
+
+        let response = output.stdout.trim();
+
+        log_subagent_event("answer_suggestions", &prompt, Some(response), true, Some(duration_ms));
+
+        Ok(parse_answer_suggestions(response))
+    }
+
+    fn evaluate_protected_change(
+        &self,
+        context: &ProtectedChangeContext,
+    ) -> Result<ProtectedChangeDecision> {
+        let mut ctx = Context::new();
+        ctx.insert("justification", &context.justification);
+
+        let prompt = templates::render("prompts/protected_change_decision.tera", &ctx)
+            .expect("protected_change_decision.tera template should always render");
+
+        let start = Instant::now();
+
+        // Run in a neutral directory to avoid picking up project hooks
+        let output = self.runner.run_in_dir(
+            self.claude_cmd(),
+            &["--print", "--model", "haiku", "-p", &prompt],
+            Some(PROTECTED_CHANGE_TIMEOUT),
+            &get_subagent_cwd(),
+        )?;
+
+        #[allow(clippy::cast_possible_truncation)] // Duration in ms won't overflow u64
+        let duration_ms = start.elapsed().as_millis() as u64;
+
+        if !output.success() {
+            log_subagent_event(
+                "protected_change",
+                &prompt,
+                Some(&output.stderr),
+                false,
+                Some(duration_ms),
+            );
+            // If Claude fails, default to Reject (the files stay protected)
+            return Ok(ProtectedChangeDecision::Reject(
+                "The justification could not be reviewed.".to_string(),
+            ));
+        }
+
+        let response = output.stdout.trim();
+
+        log_subagent_event("protected_change", &prompt, Some(response), true, Some(duration_ms));
+
+        if response.starts_with("APPROVE") {
+            return Ok(ProtectedChangeDecision::Approve);
+        }
+        let reason = response.strip_prefix("REJECT:").map_or_else(
+            // Unrecognized format — default to Reject
+            || "The justification could not be reviewed.".to_string(),
+            |reason| reason.trim().to_string(),
+        );
+        Ok(ProtectedChangeDecision::Reject(reason))
+    }
+}
+
+/// Parse a commit/push question classification.
+///
+/// Expects `COMMIT`, `PUSH`, `BOTH` or `NONE` on the first line; anything
+/// else is treated as `NONE`.
+fn parse_commit_push_question(response: &str) -> Option<CommitPushRequest> {
+    let first_line = response.lines().next().unwrap_or("").trim();
+    match first_line.trim_matches(|c: char| !c.is_ascii_alphabetic()).to_ascii_uppercase().as_str()
+    {
+        "COMMIT" => Some(CommitPushRequest::Commit),
+        "PUSH" => Some(CommitPushRequest::Push),
+        "BOTH" => Some(CommitPushRequest::CommitAndPush),
+        _ => None,
+    }
+}
+
+/// Parse an answer suggestions response.
+///
+/// Expects `ANSWER:` lines, each followed by a `TRADEOFFS:` line. Answers
+/// without trade-offs are kept with empty ones; at most
+/// [`MAX_ANSWER_SUGGESTIONS`] are returned.
+fn parse_answer_suggestions(response: &str) -> Vec<AnswerSuggestion> {
+    let mut suggestions: Vec<AnswerSuggestion> = Vec::new();
+    for line in response.lines().map(str::trim) {
+        if let Some(answer) = line.strip_prefix("ANSWER:") {
+            suggestions.push(AnswerSuggestion {
+                answer: answer.trim().to_string(),
+                tradeoffs: String::new(),
+            });
+        } else if let (Some(tradeoffs), Some(suggestion)) =
+            (line.strip_prefix("TRADEOFFS:"), suggestions.last_mut())
+        {
+            suggestion.tradeoffs = tradeoffs.trim().to_string();
+        }
+    }
+    suggestions.retain(|suggestion| !suggestion.answer.is_empty());
+    suggestions.truncate(MAX_ANSWER_SUGGESTIONS);
+    suggestions
+}
+
+/// Parse a commit split response.
+///
+/// Expects blocks of a `COMMIT:` line followed by `- <path>` lines. Paths
+/// that are not among `known_files` are dropped, as are commits left
+/// without files.
+fn parse_commit_split(response: &str, known_files: &[String]) -> Vec<PlannedCommit> {
+    let mut commits: Vec<PlannedCommit> = Vec::new();
+    for line in response.lines().map(str::trim) {
+        if let Some(message) = line.strip_prefix("COMMIT:") {
+            commits.push(PlannedCommit { message: message.trim().to_string(), files: Vec::new() });
+        } else if let (Some(path), Some(commit)) = (line.strip_prefix("- "), commits.last_mut()) {
+            let path = path.trim().trim_matches('`');
+            if known_files.iter().any(|known| known == path) {
+                commit.files.push(path.to_string());
+            }
+        }
+    }
+    commits.retain(|commit| !commit.message.is_empty() && !commit.files.is_empty());
+    commits
+}
+
+/// Parse a pull request description response.
+///
+/// Expects a `TITLE:` line followed by the body.
+fn parse_pr_description(response: &str) -> Option<PrDescription> {
+    let trimmed = response.trim();
+    let rest = trimmed.strip_prefix("TITLE:")?;
+    let (title, body) = rest.split_once('\n').unwrap_or((rest, ""));
+    let title = title.trim();
+    if title.is_empty() {
+        return None;
+    }
+    Some(PrDescription { title: title.to_string(), body: body.trim().to_string() })
+}
+
+/// Build a plain pull request description from the context alone.
+fn fallback_pr_description(context: &PrDescriptionContext) -> PrDescription {
+    let title = context
+        .work_items
+        .first()
+        .or_else(|| context.commits.first())
+        .cloned()
+        .unwrap_or_else(|| context.branch.clone());
+    let list = |heading: &str, items: &[String]| {
+        let lines: Vec<String> = items.iter().map(|i| format!("- {i}")).collect();
+        format!("## {heading}\n\n{}", lines.join("\n"))
+    };
+    let mut sections = Vec::new();
+    if !context.work_items.is_empty() {
+        sections.push(list("Work items", &context.work_items));
+    }
+    if !context.commits.is_empty() {
+        sections.push(list("Commits", &context.commits));
+    }
+    let body = sections.join("\n\n");
+    PrDescription { title, body }
+}
+
+/// Build the template context shared by the handoff prompt and fallback.
+fn handoff_template_context(context: &HandoffContext) -> Context {
+    let mut ctx = Context::new();
+    ctx.insert("completed_work_items", &context.completed_work_items);
+    ctx.insert("commits", &context.commits);
+    ctx.insert("open_questions", &context.open_questions);
+    ctx.insert("assumptions", &context.assumptions);
+    ctx.insert("problems", &context.problems);
+    ctx.insert("open_work_items", &context.open_work_items);
+    ctx
+}
+
+/// Build a plain handoff summary from the context alone.
+fn fallback_handoff(context: &HandoffContext) -> String {
+    templates::render("messages/handoff.tera", &handoff_template_context(context))
+        .expect("handoff.tera template should always render")
+        .trim_start()
+        .to_string()
+}
+
+/// Parse a WIP commit summary response into a single clean line.
+///
+/// Takes the first non-empty line and strips any `wip:` prefix the model added.
+fn parse_wip_summary(response: &str) -> String {
+    let line = response.lines().map(str::trim).find(|l| !l.is_empty()).unwrap_or_default();
+    let line = line.strip_prefix("wip:").or_else(|| line.strip_prefix("WIP:")).unwrap_or(line);
+    let line = line.trim().trim_matches('"').trim();
+    if line.is_empty() {
+        DEFAULT_WIP_SUMMARY.to_string()
+    } else {
+        line.to_string()
+    }
 }
 
 /// Parse a reflection decision response.
@@ -495,6 +1001,126 @@ mod tests {
         assert_eq!(REFLECTION_DECISION_TIMEOUT, Duration::from_secs(60));
     }
 
+    #[test]
+    fn test_parse_pr_description() {
+        let parsed = parse_pr_description("TITLE: Add parser\n\nAdds a parser.\n\n- item").unwrap();
+        assert_eq!(parsed.title, "Add parser");
+        assert_eq!(parsed.body, "Adds a parser.\n\n- item");
+
+        assert_eq!(parse_pr_description("TITLE: Only a title").unwrap().body, "");
+        assert!(parse_pr_description("Here is a PR description").is_none());
+        assert!(parse_pr_description("TITLE:   \nbody").is_none());
+    }
+
+    #[test]
+    fn test_fallback_pr_description() {
+        let context = PrDescriptionContext {
+            branch: "feature/parser".to_string(),
+            commits: vec!["Add tokenizer".to_string()],
+            work_items: vec!["Write a parser".to_string()],
+        };
+        let description = fallback_pr_description(&context);
+        assert_eq!(description.title, "Write a parser");
+        assert!(description.body.contains("- Write a parser"));
+        assert!(description.body.contains("- Add tokenizer"));
+
+        let empty = PrDescriptionContext { branch: "feature/x".to_string(), ..Default::default() };
+        assert_eq!(fallback_pr_description(&empty).title, "feature/x");
+    }
+
+    #[test]
+    fn test_parse_commit_split() {
+        let files = vec!["src/a.rs".to_string(), "src/b.rs".to_string(), "README.md".to_string()];
+        let response = "Here is a plan:\n\nCOMMIT: Add parser\n- src/a.rs\n- `src/b.rs`\n- src/missing.rs\n\nCOMMIT: Document parser\n- README.md\n\nCOMMIT: Nothing\n\nCOMMIT:\n- README.md";
+        let plan = parse_commit_split(response, &files);
+        assert_eq!(
+            plan,
+            vec![
+                PlannedCommit {
+                    message: "Add parser".to_string(),
+                    files: vec!["src/a.rs".to_string(), "src/b.rs".to_string()],
+                },
+                PlannedCommit {
+                    message: "Document parser".to_string(),
+                    files: vec!["README.md".to_string()],
+                },
+            ]
+        );
+        assert!(parse_commit_split("- src/a.rs\nno plan", &files).is_empty());
+    }
+
+    #[test]
+    fn test_parse_answer_suggestions() {
+        let response = "ANSWER: Postgres\nTRADEOFFS: Robust, but another service to run\n\nANSWER: SQLite\nTRADEOFFS: Simple, but one writer at a time\nANSWER:\nTRADEOFFS: dropped\nANSWER: MySQL\nANSWER: Files\nTRADEOFFS: Too many";
+        assert_eq!(
+            parse_answer_suggestions(response),
+            vec![
+                AnswerSuggestion {
+                    answer: "Postgres".to_string(),
+                    tradeoffs: "Robust, but another service to run".to_string(),
+                },
+                AnswerSuggestion {
+                    answer: "SQLite".to_string(),
+                    tradeoffs: "Simple, but one writer at a time".to_string(),
+                },
+                AnswerSuggestion { answer: "MySQL".to_string(), tradeoffs: String::new() },
+            ]
+        );
+        assert!(parse_answer_suggestions("NONE").is_empty());
+    }
+
+    #[test]
+    fn test_parse_commit_push_question() {
+        assert_eq!(parse_commit_push_question("COMMIT"), Some(CommitPushRequest::Commit));
+        assert_eq!(parse_commit_push_question("push\n"), Some(CommitPushRequest::Push));
+        assert_eq!(
+            parse_commit_push_question("**BOTH**\nIt asks to commit and push."),
+            Some(CommitPushRequest::CommitAndPush)
+        );
+        assert_eq!(parse_commit_push_question("NONE"), None);
+        assert_eq!(parse_commit_push_question("I think it asks to commit"), None);
+    }
+
+    #[test]
+    fn test_fallback_handoff() {
+        let context = HandoffContext {
+            completed_work_items: vec!["Write a parser".to_string()],
+            open_questions: vec!["Which auth provider?".to_string()],
+            ..Default::default()
+        };
+        let handoff = fallback_handoff(&context);
+        assert!(handoff.starts_with("# Session Handoff"));
+        assert!(handoff.contains("## Completed\n\n- Write a parser\n"));
+        assert!(handoff.contains("## Commits\n\nNone.\n"));
+        assert!(handoff.contains("- Which auth provider?"));
+        assert!(!handoff.contains("## Assumptions"));
+
+        let context = HandoffContext {
+            assumptions: vec!["Which region? Assumed: eu-west-1".to_string()],
+            ..Default::default()
+        };
+        let handoff = fallback_handoff(&context);
+        assert!(handoff.starts_with("# Session Handoff\n\n## Assumptions Made\n"));
+        assert!(handoff
+            .contains("question reopen`.\n\n- Which region? Assumed: eu-west-1\n\n## Completed"));
+        assert!(!handoff.contains("## Problems Reported"));
+
+        let context =
+            HandoffContext { problems: vec!["[other] Stuck".to_string()], ..Default::default() };
+        let handoff = fallback_handoff(&context);
+        assert!(handoff.contains("## Problems Reported\n"));
+        assert!(handoff.contains("- [other] Stuck\n\n## Next Steps"));
+    }
+
+    #[test]
+    fn test_parse_wip_summary() {
+        assert_eq!(parse_wip_summary("Add config parsing\n"), "Add config parsing");
+        assert_eq!(parse_wip_summary("\n  wip: tidy up tests"), "tidy up tests");
+        assert_eq!(parse_wip_summary("\"Fix typo\"\nmore"), "Fix typo");
+        assert_eq!(parse_wip_summary("   "), DEFAULT_WIP_SUMMARY);
+        assert_eq!(parse_wip_summary("WIP:"), DEFAULT_WIP_SUMMARY);
+    }
+
     #[test]
     fn test_extract_json_object() {
         let text = r#"Here is my response: {"decision": "approve", "feedback": "LGTM"} end"#;
@@ -838,8 +1464,10 @@ mod tests {
             let runner = RealCommandRunner::new();
             let agent = RealSubAgent::new(&runner).with_claude_cmd(&claude_cmd);
 
-            let context =
-                EmergencyStopContext { explanation: "Cannot deploy without API key".to_string() };
+            let context = EmergencyStopContext {
+                explanation: "Cannot deploy without API key".to_string(),
+                category: "technical problem".to_string(),
+            };
             let result = agent.evaluate_emergency_stop(&context).unwrap();
 
             assert!(matches!(
@@ -856,7 +1484,10 @@ mod tests {
             let runner = RealCommandRunner::new();
             let agent = RealSubAgent::new(&runner).with_claude_cmd(&claude_cmd);
 
-            let context = EmergencyStopContext { explanation: "Blocked".to_string() };
+            let context = EmergencyStopContext {
+                explanation: "Blocked".to_string(),
+                category: "technical problem".to_string(),
+            };
             let result = agent.evaluate_emergency_stop(&context).unwrap();
 
             assert!(matches!(result, EmergencyStopDecision::Accept(None)));
@@ -874,7 +1505,10 @@ mod tests {
             let runner = RealCommandRunner::new();
             let agent = RealSubAgent::new(&runner).with_claude_cmd(&claude_cmd);
 
-            let context = EmergencyStopContext { explanation: "Too many tasks to do".to_string() };
+            let context = EmergencyStopContext {
+                explanation: "Too many tasks to do".to_string(),
+                category: "technical problem".to_string(),
+            };
             let result = agent.evaluate_emergency_stop(&context).unwrap();
 
             assert!(matches!(
@@ -883,6 +1517,47 @@ mod tests {
             ));
         }
 
+        #[test]
+        fn test_real_subagent_protected_change() {
+            let context = ProtectedChangeContext {
+                justification: "The user asked me to turn off the spelling check".to_string(),
+            };
+            let runner = RealCommandRunner::new();
+
+            let dir = TempDir::new().unwrap();
+            let claude_cmd = setup_fake_claude(&dir, "APPROVE", 0);
+            let agent = RealSubAgent::new(&runner).with_claude_cmd(&claude_cmd);
+            assert_eq!(
+                agent.evaluate_protected_change(&context).unwrap(),
+                ProtectedChangeDecision::Approve
+            );
+
+            let dir = TempDir::new().unwrap();
+            let claude_cmd = setup_fake_claude(&dir, "REJECT: Ask the user to do it", 0);
+            let agent = RealSubAgent::new(&runner).with_claude_cmd(&claude_cmd);
+            assert_eq!(
+                agent.evaluate_protected_change(&context).unwrap(),
+                ProtectedChangeDecision::Reject("Ask the user to do it".to_string())
+            );
+
+            // Unrecognised answers and failures keep the files protected
+            let dir = TempDir::new().unwrap();
+            let claude_cmd = setup_fake_claude(&dir, "Sure, go ahead", 0);
+            let agent = RealSubAgent::new(&runner).with_claude_cmd(&claude_cmd);
+            assert!(matches!(
+                agent.evaluate_protected_change(&context).unwrap(),
+                ProtectedChangeDecision::Reject(_)
+            ));
+
+            let dir = TempDir::new().unwrap();
+            let claude_cmd = setup_fake_claude(&dir, "", 1);
+            let agent = RealSubAgent::new(&runner).with_claude_cmd(&claude_cmd);
+            assert!(matches!(
+                agent.evaluate_protected_change(&context).unwrap(),
+                ProtectedChangeDecision::Reject(_)
+            ));
+        }
+
         #[test]
         fn test_real_subagent_emergency_stop_command_fails() {
             let dir = TempDir::new().unwrap();
@@ -891,7 +1566,10 @@ mod tests {
             let runner = RealCommandRunner::new();
             let agent = RealSubAgent::new(&runner).with_claude_cmd(&claude_cmd);
 
-            let context = EmergencyStopContext { explanation: "Something broke".to_string() };
+            let context = EmergencyStopContext {
+                explanation: "Something broke".to_string(),
+                category: "technical problem".to_string(),
+            };
             let result = agent.evaluate_emergency_stop(&context).unwrap();
 
             // Command failure defaults to Accept
@@ -906,7 +1584,10 @@ mod tests {
             let runner = RealCommandRunner::new();
             let agent = RealSubAgent::new(&runner).with_claude_cmd(&claude_cmd);
 
-            let context = EmergencyStopContext { explanation: "Unclear".to_string() };
+            let context = EmergencyStopContext {
+                explanation: "Unclear".to_string(),
+                category: "technical problem".to_string(),
+            };
             let result = agent.evaluate_emergency_stop(&context).unwrap();
 
             // Unrecognized format defaults to Accept
@@ -919,7 +1600,10 @@ mod tests {
             let agent = RealSubAgent::new(&runner)
                 .with_claude_cmd("/nonexistent/path/to/claude_command_that_does_not_exist");
 
-            let context = EmergencyStopContext { explanation: "test".to_string() };
+            let context = EmergencyStopContext {
+                explanation: "test".to_string(),
+                category: "technical problem".to_string(),
+            };
             let result = agent.evaluate_emergency_stop(&context);
 
             // The run() call returns Err, which propagates via ?
@@ -1031,6 +1715,7 @@ mod tests {
             let context = ReflectionContext {
                 reflection_output: "All work is done".to_string(),
                 user_messages: vec!["Fix the bug".to_string()],
+                unchecked_criteria: vec![],
             };
             let result = agent.evaluate_reflection(&context).unwrap();
 
@@ -1051,6 +1736,7 @@ mod tests {
             let context = ReflectionContext {
                 reflection_output: "Still need to fix login and add tests".to_string(),
                 user_messages: vec![],
+                unchecked_criteria: vec![],
             };
             let result = agent.evaluate_reflection(&context).unwrap();
 
@@ -1072,8 +1758,11 @@ mod tests {
             let runner = RealCommandRunner::new();
             let agent = RealSubAgent::new(&runner).with_claude_cmd(&claude_cmd);
 
-            let context =
-                ReflectionContext { reflection_output: "test".to_string(), user_messages: vec![] };
+            let context = ReflectionContext {
+                reflection_output: "test".to_string(),
+                user_messages: vec![],
+                unchecked_criteria: vec![],
+            };
             let result = agent.evaluate_reflection(&context).unwrap();
 
             // Command failure defaults to Complete
@@ -1090,8 +1779,11 @@ mod tests {
             let runner = RealCommandRunner::new();
             let agent = RealSubAgent::new(&runner).with_claude_cmd(&claude_cmd);
 
-            let context =
-                ReflectionContext { reflection_output: "test".to_string(), user_messages: vec![] };
+            let context = ReflectionContext {
+                reflection_output: "test".to_string(),
+                user_messages: vec![],
+                unchecked_criteria: vec![],
+            };
             let result = agent.evaluate_reflection(&context).unwrap();
 
             // Unrecognized format defaults to Complete
@@ -1106,8 +1798,11 @@ mod tests {
             let agent = RealSubAgent::new(&runner)
                 .with_claude_cmd("/nonexistent/path/to/claude_command_that_does_not_exist");
 
-            let context =
-                ReflectionContext { reflection_output: "test".to_string(), user_messages: vec![] };
+            let context = ReflectionContext {
+                reflection_output: "test".to_string(),
+                user_messages: vec![],
+                unchecked_criteria: vec![],
+            };
             let result = agent.evaluate_reflection(&context);
 
             // The run() call returns Err, which propagates via ?
@@ -0,0 +1,299 @@
+//! Answering questions in bulk through a Markdown document.
+//!
+//! `claude-reliability question answer-all` renders the unanswered questions
+//! with [`answer_document`], opens it in the user's editor, and hands the saved
+//! file to [`apply_answers`]. Each question has its own section:
+//!
+//! ```text
+//! ## which-database-1a2b
+//!
+//! > Which database should we use?
+//!
+//! Asked 2026-10-01 12:00:00
+//!
+//! ### Answer
+//!
+//! SQLite, it is already a dependency.
+//! ```
+//!
+//! Suggested answers drafted by the sub-agent are listed before the answer
+//! heading; writing just a suggestion's number as the answer picks it.
+//! Questions whose answer is left empty stay unanswered. Questions are put to
+//! the user in [`rank_by_impact`] order, so the ones holding up the most
+//! requested work come first.
+
+use crate::error::Result;
+use crate::tasks::models::{Question, Status};
+use crate::tasks::store::{now_timestamp, TaskFilter, TaskStore};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// Heading under which each answer is written.
+const ANSWER_HEADING: &str = "### Answer";
+
+/// What applying an answer document did.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct AnswerReport {
+    /// IDs of the questions answered.
+    pub answered: Vec<String>,
+    /// Number of questions left unanswered.
+    pub skipped: usize,
+    /// IDs in the document that match no question.
+    pub unknown: Vec<String>,
+}
+
+/// Get the questions to put to the user: unanswered and not deferred, oldest first.
+///
+/// # Errors
+///
+/// Returns an error if the store cannot be read.
+pub fn pending_questions<S: TaskStore + ?Sized>(store: &S) -> Result<Vec<Question>> {
+    let now = now_timestamp();
+    let mut questions = store.list_questions(true)?;
+    questions.retain(|q| !q.is_deferred(&now));
+    Ok(questions)
+}
+
+/// A question together with the requested work waiting on its answer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuestionImpact {
+    /// The question.
+    pub question: Question,
+    /// IDs of open requested work items blocked until the question is answered.
+    pub blocked_requested: Vec<String>,
+}
+
+/// Order questions by how much requested work answering them would unblock.
+///
+/// Questions blocking more open requested work items come first, then higher
+/// priority questions; ties keep their original order. Answered questions
+/// block nothing.
+///
+/// # Errors
+///
+/// Returns an error if the store cannot be read.
+pub fn rank_by_impact<S: TaskStore + ?Sized>(
+    store: &S,
+    questions: Vec<Question>,
+) -> Result<Vec<QuestionImpact>> {
+    let open = store.list_tasks(TaskFilter { status: Some(Status::Open), ..Default::default() })?;
+    let mut blocked: HashMap<String, Vec<String>> = HashMap::new();
+    for task in open.into_iter().filter(|t| t.requested) {
+        for question_id in store.get_task_questions(&task.id)? {
+            blocked.entry(question_id).or_default().push(task.id.clone());
+        }
+    }
+
+    let mut ranked: Vec<QuestionImpact> = questions
+        .into_iter()
+        .map(|question| {
+            let blocked_requested = if question.is_answered() {
+                Vec::new()
+            } else {
+                blocked.remove(&question.id).unwrap_or_default()
+            };
+            QuestionImpact { question, blocked_requested }
+        })
+        .collect();
+    ranked.sort_by(|a, b| {
+        b.blocked_requested
+            .len()
+            .cmp(&a.blocked_requested.len())
+            .then(a.question.priority.cmp(&b.question.priority))
+    });
+    Ok(ranked)
+}
+
+/// Render questions as a document with an empty answer section for each.
+#[must_use]
+pub fn answer_document(questions: &[Question]) -> String {
+    let mut out = String::from(
+        "# Unanswered Questions\n\n\
+         <!--\n\
+         Write each answer under its \"Answer\" heading, then save and close the editor.\n\
+         Questions left without an answer stay unanswered. Do not change the \"## \" headings.\n\
+         -->\n",
+    );
+    for question in questions {
+        let _ = write!(out, "\n## {}\n\n", question.id);
+        for line in question.text.lines() {
+            if line.is_empty() {
+                out.push_str(">\n");
+            } else {
+                let _ = writeln!(out, "> {line}");
+            }
+        }
+        let _ = write!(out, "\nAsked {}\n", question.created_at);
+        if !question.suggestions.is_empty() {
+            out.push_str("\nSuggested answers (write a number to pick one):\n\n");
+            for (i, suggestion) in question.suggestions.iter().enumerate() {
+                let _ = writeln!(out, "{}. {}", i + 1, suggestion.answer);
+                if !suggestion.tradeoffs.is_empty() {
+                    let _ = writeln!(out, "   Trade-offs: {}", suggestion.tradeoffs);
+                }
+            }
+        }
+        let _ = write!(out, "\n{ANSWER_HEADING}\n\n");
+    }
+    out
+}
+
+/// Parse a document into `(question ID, answer)` pairs, in document order.
+///
+/// Answers are trimmed, and may be empty.
+#[must_use]
+pub fn parse_answers(document: &str) -> Vec<(String, String)> {
+    let mut answers: Vec<(String, String)> = Vec::new();
+    let mut in_answer = false;
+    for line in document.lines() {
+        if let Some(id) = line.strip_prefix("## ") {
+            answers.push((id.trim().to_string(), String::new()));
+            in_answer = false;
+        } else if line.trim_end() == ANSWER_HEADING {
+            in_answer = !answers.is_empty();
+        } else if in_answer {
+            if let Some((_, answer)) = answers.last_mut() {
+                answer.push_str(line);
+                answer.push('\n');
+            }
+        }
+    }
+    for (_, answer) in &mut answers {
+        *answer = answer.trim().to_string();
+    }
+    answers
+}
+
+/// Answer the questions with non-empty answers in a document.
+///
+/// An answer that is just the number of one of the question's suggested
+/// answers is replaced by that suggestion.
+///
+/// # Errors
+///
+/// Returns an error if the store cannot be updated.
+pub fn apply_answers<S: TaskStore + ?Sized>(store: &S, document: &str) -> Result<AnswerReport> {
+    let mut report = AnswerReport::default();
+    for (id, answer) in parse_answers(document) {
+        if answer.is_empty() {
+            report.skipped += 1;
+            continue;
+        }
+        let answer = match answer.parse::<usize>() {
+            Ok(pick) => store
+                .get_question(&id)?
+                .and_then(|q| pick.checked_sub(1).and_then(|i| q.suggestions.into_iter().nth(i)))
+                .map_or(answer, |suggestion| suggestion.answer),
+            Err(_) => answer,
+        };
+        if store.answer_question(&id, &answer)?.is_some() {
+            report.answered.push(id);
+        } else {
+            report.unknown.push(id);
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tasks::{AnswerSuggestion, InMemoryTaskStore, Priority, TaskUpdate};
+
+    #[test]
+    fn test_answer_document_round_trip() {
+        let store = InMemoryTaskStore::new();
+        let first = store.create_question("Which database?\n\nWe need one soon.").unwrap();
+        let second = store.create_question("Which licence?").unwrap();
+        let questions = pending_questions(&store).unwrap();
+
+        let document = answer_document(&questions);
+        assert!(document.contains(&format!("## {}\n\n> Which database?\n>\n> We need", first.id)));
+        assert_eq!(
+            parse_answers(&document),
+            vec![(first.id.clone(), String::new()), (second.id, String::new())]
+        );
+
+        let edited = document.replacen(
+            &format!("{ANSWER_HEADING}\n"),
+            &format!("{ANSWER_HEADING}\n\nSQLite.\n\n## not-a-question\n{ANSWER_HEADING}\nHm\n"),
+            1,
+        );
+        let report = apply_answers(&store, &edited).unwrap();
+        assert_eq!(report.answered, vec![first.id.clone()]);
+        assert_eq!(report.skipped, 1);
+        assert_eq!(report.unknown, vec!["not-a-question".to_string()]);
+        assert_eq!(
+            store.get_question(&first.id).unwrap().unwrap().answer.as_deref(),
+            Some("SQLite.")
+        );
+    }
+
+    #[test]
+    fn test_answer_document_picks_suggestion() {
+        let store = InMemoryTaskStore::new();
+        let question = store.create_question("Which database?").unwrap();
+        let suggestions = vec![
+            AnswerSuggestion { answer: "Postgres".to_string(), tradeoffs: "Heavier".to_string() },
+            AnswerSuggestion { answer: "SQLite".to_string(), tradeoffs: String::new() },
+        ];
+        store.set_question_suggestions(&question.id, &suggestions).unwrap();
+
+        let document = answer_document(&pending_questions(&store).unwrap());
+        assert!(document.contains("1. Postgres\n   Trade-offs: Heavier\n2. SQLite\n"));
+
+        let edited = document.replace(ANSWER_HEADING, &format!("{ANSWER_HEADING}\n\n2"));
+        let report = apply_answers(&store, &edited).unwrap();
+        assert_eq!(report.answered, vec![question.id.clone()]);
+        assert_eq!(
+            store.get_question(&question.id).unwrap().unwrap().answer.as_deref(),
+            Some("SQLite")
+        );
+    }
+
+    #[test]
+    fn test_pending_questions_skips_deferred() {
+        let store = InMemoryTaskStore::new();
+        let deferred = store.create_question("Later?").unwrap();
+        let lapsed = store.create_question("Now?").unwrap();
+        store.defer_question(&deferred.id, Some("9999-01-01 00:00:00")).unwrap();
+        store.defer_question(&lapsed.id, Some("2000-01-01 00:00:00")).unwrap();
+
+        let pending = pending_questions(&store).unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].id, lapsed.id);
+    }
+
+    #[test]
+    fn test_rank_by_impact() {
+        let store = InMemoryTaskStore::new();
+        let minor = store.create_question("Tabs or spaces?").unwrap();
+        let urgent = store.create_question("Which cloud?").unwrap();
+        let blocking = store.create_question("Which database?").unwrap();
+        store.set_question_priority(&urgent.id, Priority::Critical).unwrap();
+
+        let requested = |title: &str| {
+            let task = store.create_task(title, "", Priority::Medium).unwrap();
+            let update = TaskUpdate { requested: Some(true), ..Default::default() };
+            store.update_task(&task.id, update).unwrap();
+            store.link_task_to_question(&task.id, &blocking.id).unwrap();
+            task.id
+        };
+        let first = requested("Add users table");
+        let second = requested("Add orders table");
+        // Work nobody asked for does not count towards a question's impact
+        let unrequested = store.create_task("Tidy schema", "", Priority::Low).unwrap();
+        store.link_task_to_question(&unrequested.id, &minor.id).unwrap();
+
+        let ranked = rank_by_impact(&store, store.list_questions(true).unwrap()).unwrap();
+        let order: Vec<&str> = ranked.iter().map(|r| r.question.id.as_str()).collect();
+        assert_eq!(order, vec![blocking.id.as_str(), urgent.id.as_str(), minor.id.as_str()]);
+        assert_eq!(ranked[0].blocked_requested, vec![first, second]);
+        assert!(ranked[2].blocked_requested.is_empty());
+
+        store.answer_question(&blocking.id, "Postgres").unwrap();
+        let ranked = rank_by_impact(&store, store.list_questions(false).unwrap()).unwrap();
+        assert!(ranked.iter().all(|r| r.blocked_requested.is_empty()));
+    }
+}
@@ -0,0 +1,993 @@
+//! Async access to task stores.
+//!
+//! The [`TaskStore`] methods block on the database, which stalls an async
+//! executor. [`AsyncTaskStore`] is the same interface with methods returning
+//! futures; [`SpawnBlocking`] provides it for any synchronous store by running
+//! each call on tokio's blocking thread pool. Hooks are synchronous, so
+//! [`BlockOn`] goes the other way and wraps an async store as a [`TaskStore`].
+//!
+//! Requires the `async` feature.
+
+use crate::error::{Error, Result};
+use crate::tasks::models::{
+    AnswerSuggestion, AuditEntry, HowTo, Note, Priority, Question, Task, UserMessage,
+};
+use crate::tasks::store::{HowToUpdate, TaskFilter, TaskStore, TaskUpdate};
+use std::future::Future;
+use std::sync::Arc;
+
+/// Async task storage operations.
+///
+/// Mirrors [`TaskStore`] method for method.
+#[allow(clippy::missing_errors_doc)]
+pub trait AsyncTaskStore: Send + Sync {
+    /// See [`TaskStore::create_task`].
+    fn create_task(
+        &self,
+        title: &str,
+        description: &str,
+        priority: Priority,
+    ) -> impl Future<Output = Result<Task>> + Send;
+
+    /// See [`TaskStore::get_task`].
+    fn get_task(&self, id: &str) -> impl Future<Output = Result<Option<Task>>> + Send;
+
+    /// See [`TaskStore::update_task`].
+    fn update_task(
+        &self,
+        id: &str,
+        update: TaskUpdate,
+    ) -> impl Future<Output = Result<Option<Task>>> + Send;
+
+    /// See [`TaskStore::delete_task`].
+    fn delete_task(&self, id: &str) -> impl Future<Output = Result<bool>> + Send;
+
+    /// See [`TaskStore::list_tasks`].
+    fn list_tasks(&self, filter: TaskFilter) -> impl Future<Output = Result<Vec<Task>>> + Send;
+
+    /// See [`TaskStore::add_dependency`].
+    fn add_dependency(
+        &self,
+        task_id: &str,
+        depends_on: &str,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    /// See [`TaskStore::remove_dependency`].
+    fn remove_dependency(
+        &self,
+        task_id: &str,
+        depends_on: &str,
+    ) -> impl Future<Output = Result<bool>> + Send;
+
+    /// See [`TaskStore::get_dependencies`].
+    fn get_dependencies(&self, task_id: &str) -> impl Future<Output = Result<Vec<String>>> + Send;
+
+    /// See [`TaskStore::get_dependents`].
+    fn get_dependents(&self, task_id: &str) -> impl Future<Output = Result<Vec<String>>> + Send;
+
+    /// See [`TaskStore::add_note`].
+    fn add_note(&self, task_id: &str, content: &str) -> impl Future<Output = Result<Note>> + Send;
+
+    /// See [`TaskStore::get_notes`].
+    fn get_notes(&self, task_id: &str) -> impl Future<Output = Result<Vec<Note>>> + Send;
+
+    /// See [`TaskStore::delete_note`].
+    fn delete_note(&self, note_id: i64) -> impl Future<Output = Result<bool>> + Send;
+
+    /// See [`TaskStore::search_tasks`].
+    fn search_tasks(&self, query: &str) -> impl Future<Output = Result<Vec<Task>>> + Send;
+
+    /// See [`TaskStore::get_audit_log`].
+    fn get_audit_log(
+        &self,
+        task_id: Option<&str>,
+        limit: Option<usize>,
+    ) -> impl Future<Output = Result<Vec<AuditEntry>>> + Send;
+
+    /// See [`TaskStore::get_ready_tasks`].
+    fn get_ready_tasks(&self) -> impl Future<Output = Result<Vec<Task>>> + Send;
+
+    /// See [`TaskStore::pick_task`].
+    fn pick_task(&self) -> impl Future<Output = Result<Option<Task>>> + Send;
+
+    /// See [`TaskStore::create_howto`].
+    fn create_howto(
+        &self,
+        title: &str,
+        instructions: &str,
+    ) -> impl Future<Output = Result<HowTo>> + Send;
+
+    /// See [`TaskStore::get_howto`].
+    fn get_howto(&self, id: &str) -> impl Future<Output = Result<Option<HowTo>>> + Send;
+
+    /// See [`TaskStore::update_howto`].
+    fn update_howto(
+        &self,
+        id: &str,
+        update: HowToUpdate,
+    ) -> impl Future<Output = Result<Option<HowTo>>> + Send;
+
+    /// See [`TaskStore::delete_howto`].
+    fn delete_howto(&self, id: &str) -> impl Future<Output = Result<bool>> + Send;
+
+    /// See [`TaskStore::list_howtos`].
+    fn list_howtos(&self) -> impl Future<Output = Result<Vec<HowTo>>> + Send;
+
+    /// See [`TaskStore::search_howtos`].
+    fn search_howtos(&self, query: &str) -> impl Future<Output = Result<Vec<HowTo>>> + Send;
+
+    /// See [`TaskStore::link_task_to_howto`].
+    fn link_task_to_howto(
+        &self,
+        task_id: &str,
+        howto_id: &str,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    /// See [`TaskStore::unlink_task_from_howto`].
+    fn unlink_task_from_howto(
+        &self,
+        task_id: &str,
+        howto_id: &str,
+    ) -> impl Future<Output = Result<bool>> + Send;
+
+    /// See [`TaskStore::get_task_guidance`].
+    fn get_task_guidance(&self, task_id: &str) -> impl Future<Output = Result<Vec<String>>> + Send;
+
+    /// See [`TaskStore::create_question`].
+    fn create_question(&self, text: &str) -> impl Future<Output = Result<Question>> + Send;
+
+    /// See [`TaskStore::get_question`].
+    fn get_question(&self, id: &str) -> impl Future<Output = Result<Option<Question>>> + Send;
+
+    /// See [`TaskStore::answer_question`].
+    fn answer_question(
+        &self,
+        id: &str,
+        answer: &str,
+    ) -> impl Future<Output = Result<Option<Question>>> + Send;
+
+    /// See [`TaskStore::defer_question`].
+    fn defer_question(
+        &self,
+        id: &str,
+        until: Option<&str>,
+    ) -> impl Future<Output = Result<Option<Question>>> + Send;
+
+    /// See [`TaskStore::set_question_priority`].
+    fn set_question_priority(
+        &self,
+        id: &str,
+        priority: Priority,
+    ) -> impl Future<Output = Result<Option<Question>>> + Send;
+
+    /// See [`TaskStore::assume_answer`].
+    fn assume_answer(
+        &self,
+        id: &str,
+        answer: &str,
+    ) -> impl Future<Output = Result<Option<Question>>> + Send;
+
+    /// See [`TaskStore::reopen_question`].
+    fn reopen_question(&self, id: &str) -> impl Future<Output = Result<Option<Question>>> + Send;
+
+    /// See [`TaskStore::set_question_suggestions`].
+    fn set_question_suggestions(
+        &self,
+        id: &str,
+        suggestions: &[AnswerSuggestion],
+    ) -> impl Future<Output = Result<Option<Question>>> + Send;
+
+    /// See [`TaskStore::delete_question`].
+    fn delete_question(&self, id: &str) -> impl Future<Output = Result<bool>> + Send;
+
+    /// See [`TaskStore::list_questions`].
+    fn list_questions(
+        &self,
+        unanswered_only: bool,
+    ) -> impl Future<Output = Result<Vec<Question>>> + Send;
+
+    /// See [`TaskStore::search_questions`].
+    fn search_questions(&self, query: &str) -> impl Future<Output = Result<Vec<Question>>> + Send;
+
+    /// See [`TaskStore::link_task_to_question`].
+    fn link_task_to_question(
+        &self,
+        task_id: &str,
+        question_id: &str,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    /// See [`TaskStore::unlink_task_from_question`].
+    fn unlink_task_from_question(
+        &self,
+        task_id: &str,
+        question_id: &str,
+    ) -> impl Future<Output = Result<bool>> + Send;
+
+    /// See [`TaskStore::get_task_questions`].
+    fn get_task_questions(&self, task_id: &str)
+        -> impl Future<Output = Result<Vec<String>>> + Send;
+
+    /// See [`TaskStore::get_blocking_questions`].
+    fn get_blocking_questions(
+        &self,
+        task_id: &str,
+    ) -> impl Future<Output = Result<Vec<Question>>> + Send;
+
+    /// See [`TaskStore::get_question_blocked_tasks`].
+    fn get_question_blocked_tasks(&self) -> impl Future<Output = Result<Vec<Task>>> + Send;
+
+    /// See [`TaskStore::has_in_progress_task`].
+    fn has_in_progress_task(&self) -> impl Future<Output = Result<bool>> + Send;
+
+    /// See [`TaskStore::get_in_progress_tasks`].
+    fn get_in_progress_tasks(&self) -> impl Future<Output = Result<Vec<Task>>> + Send;
+
+    /// See [`TaskStore::request_tasks`].
+    fn request_tasks(&self, task_ids: &[&str]) -> impl Future<Output = Result<usize>> + Send;
+
+    /// See [`TaskStore::request_all_open`].
+    fn request_all_open(&self) -> impl Future<Output = Result<usize>> + Send;
+
+    /// See [`TaskStore::is_request_mode_active`].
+    fn is_request_mode_active(&self) -> impl Future<Output = Result<bool>> + Send;
+
+    /// See [`TaskStore::clear_request_mode`].
+    fn clear_request_mode(&self) -> impl Future<Output = Result<()>> + Send;
+
+    /// See [`TaskStore::get_incomplete_requested_work`].
+    fn get_incomplete_requested_work(&self) -> impl Future<Output = Result<Vec<Task>>> + Send;
+
+    /// See [`TaskStore::record_user_message`].
+    fn record_user_message(
+        &self,
+        message: &str,
+        context: &str,
+        transcript_path: Option<&str>,
+        session_id: &str,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    /// See [`TaskStore::get_session_user_messages`].
+    fn get_session_user_messages(
+        &self,
+        session_id: &str,
+    ) -> impl Future<Output = Result<Vec<UserMessage>>> + Send;
+
+    /// See [`TaskStore::clear_user_messages_for_session`].
+    fn clear_user_messages_for_session(
+        &self,
+        session_id: &str,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    /// See [`TaskStore::mark_pre_compaction`].
+    fn mark_pre_compaction(&self, session_id: &str) -> impl Future<Output = Result<()>> + Send;
+
+    /// See [`TaskStore::scrub_user_messages`].
+    fn scrub_user_messages(&self) -> impl Future<Output = Result<usize>> + Send;
+}
+
+/// Runs a synchronous task store on tokio's blocking thread pool.
+///
+/// Must be used from within a tokio runtime.
+#[derive(Debug)]
+pub struct SpawnBlocking<S> {
+    store: Arc<S>,
+}
+
+impl<S> Clone for SpawnBlocking<S> {
+    fn clone(&self) -> Self {
+        Self { store: Arc::clone(&self.store) }
+    }
+}
+
+impl<S: TaskStore + Send + Sync + 'static> SpawnBlocking<S> {
+    /// Wrap a synchronous store.
+    pub fn new(store: S) -> Self {
+        Self { store: Arc::new(store) }
+    }
+
+    /// Run a store call on the blocking pool.
+    ///
+    /// Panics in the call are resumed in the awaiting task.
+    fn run<T, F>(&self, f: F) -> impl Future<Output = Result<T>> + Send + 'static
+    where
+        T: Send + 'static,
+        F: FnOnce(&S) -> Result<T> + Send + 'static,
+    {
+        let store = Arc::clone(&self.store);
+        async move {
+            match tokio::task::spawn_blocking(move || f(&store)).await {
+                Ok(result) => result,
+                Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+                Err(e) => Err(Error::Task(Box::new(e))),
+            }
+        }
+    }
+}
+
+impl<S: TaskStore + Send + Sync + 'static> AsyncTaskStore for SpawnBlocking<S> {
+    fn create_task(
+        &self,
+        title: &str,
+        description: &str,
+        priority: Priority,
+    ) -> impl Future<Output = Result<Task>> + Send {
+        let title = title.to_string();
+        let description = description.to_string();
+        self.run(move |store| store.create_task(&title, &description, priority))
+    }
+
+    fn get_task(&self, id: &str) -> impl Future<Output = Result<Option<Task>>> + Send {
+        let id = id.to_string();
+        self.run(move |store| store.get_task(&id))
+    }
+
+    fn update_task(
+        &self,
+        id: &str,
+        update: TaskUpdate,
+    ) -> impl Future<Output = Result<Option<Task>>> + Send {
+        let id = id.to_string();
+        self.run(move |store| store.update_task(&id, update))
+    }
+
+    fn delete_task(&self, id: &str) -> impl Future<Output = Result<bool>> + Send {
+        let id = id.to_string();
+        self.run(move |store| store.delete_task(&id))
+    }
+
+    fn list_tasks(&self, filter: TaskFilter) -> impl Future<Output = Result<Vec<Task>>> + Send {
+        self.run(move |store| store.list_tasks(filter))
+    }
+
+    fn add_dependency(
+        &self,
+        task_id: &str,
+        depends_on: &str,
+    ) -> impl Future<Output = Result<()>> + Send {
+        let task_id = task_id.to_string();
+        let depends_on = depends_on.to_string();
+        self.run(move |store| store.add_dependency(&task_id, &depends_on))
+    }
+
+    fn remove_dependency(
+        &self,
+        task_id: &str,
+        depends_on: &str,
+    ) -> impl Future<Output = Result<bool>> + Send {
+        let task_id = task_id.to_string();
+        let depends_on = depends_on.to_string();
+        self.run(move |store| store.remove_dependency(&task_id, &depends_on))
+    }
+
+    fn get_dependencies(&self, task_id: &str) -> impl Future<Output = Result<Vec<String>>> + Send {
+        let task_id = task_id.to_string();
+        self.run(move |store| store.get_dependencies(&task_id))
+    }
+
+    fn get_dependents(&self, task_id: &str) -> impl Future<Output = Result<Vec<String>>> + Send {
+        let task_id = task_id.to_string();
+        self.run(move |store| store.get_dependents(&task_id))
+    }
+
+    fn add_note(&self, task_id: &str, content: &str) -> impl Future<Output = Result<Note>> + Send {
+        let task_id = task_id.to_string();
+        let content = content.to_string();
+        self.run(move |store| store.add_note(&task_id, &content))
+    }
+
+    fn get_notes(&self, task_id: &str) -> impl Future<Output = Result<Vec<Note>>> + Send {
+        let task_id = task_id.to_string();
+        self.run(move |store| store.get_notes(&task_id))
+    }
+
+    fn delete_note(&self, note_id: i64) -> impl Future<Output = Result<bool>> + Send {
+        self.run(move |store| store.delete_note(note_id))
+    }
+
+    fn search_tasks(&self, query: &str) -> impl Future<Output = Result<Vec<Task>>> + Send {
+        let query = query.to_string();
+        self.run(move |store| store.search_tasks(&query))
+    }
+
+    fn get_audit_log(
+        &self,
+        task_id: Option<&str>,
+        limit: Option<usize>,
+    ) -> impl Future<Output = Result<Vec<AuditEntry>>> + Send {
+        let task_id = task_id.map(str::to_string);
+        self.run(move |store| store.get_audit_log(task_id.as_deref(), limit))
+    }
+
+    fn get_ready_tasks(&self) -> impl Future<Output = Result<Vec<Task>>> + Send {
+        self.run(S::get_ready_tasks)
+    }
+
+    fn pick_task(&self) -> impl Future<Output = Result<Option<Task>>> + Send {
+        self.run(S::pick_task)
+    }
+
+    fn create_howto(
+        &self,
+        title: &str,
+        instructions: &str,
+    ) -> impl Future<Output = Result<HowTo>> + Send {
+        let title = title.to_string();
+        let instructions = instructions.to_string();
+        self.run(move |store| store.create_howto(&title, &instructions))
+    }
+
+    fn get_howto(&self, id: &str) -> impl Future<Output = Result<Option<HowTo>>> + Send {
+        let id = id.to_string();
+        self.run(move |store| store.get_howto(&id))
+    }
+
+    fn update_howto(
+        &self,
+        id: &str,
+        update: HowToUpdate,
+    ) -> impl Future<Output = Result<Option<HowTo>>> + Send {
+        let id = id.to_string();
+        self.run(move |store| store.update_howto(&id, update))
+    }
+
+    fn delete_howto(&self, id: &str) -> impl Future<Output = Result<bool>> + Send {
+        let id = id.to_string();
+        self.run(move |store| store.delete_howto(&id))
+    }
+
+    fn list_howtos(&self) -> impl Future<Output = Result<Vec<HowTo>>> + Send {
+        self.run(S::list_howtos)
+    }
+
+    fn search_howtos(&self, query: &str) -> impl Future<Output = Result<Vec<HowTo>>> + Send {
+        let query = query.to_string();
+        self.run(move |store| store.search_howtos(&query))
+    }
+
+    fn link_task_to_howto(
+        &self,
+        task_id: &str,
+        howto_id: &str,
+    ) -> impl Future<Output = Result<()>> + Send {
+        let task_id = task_id.to_string();
+        let howto_id = howto_id.to_string();
+        self.run(move |store| store.link_task_to_howto(&task_id, &howto_id))
+    }
+
+    fn unlink_task_from_howto(
+        &self,
+        task_id: &str,
+        howto_id: &str,
+    ) -> impl Future<Output = Result<bool>> + Send {
+        let task_id = task_id.to_string();
+        let howto_id = howto_id.to_string();
+        self.run(move |store| store.unlink_task_from_howto(&task_id, &howto_id))
+    }
+
+    fn get_task_guidance(&self, task_id: &str) -> impl Future<Output = Result<Vec<String>>> + Send {
+        let task_id = task_id.to_string();
+        self.run(move |store| store.get_task_guidance(&task_id))
+    }
+
+    fn create_question(&self, text: &str) -> impl Future<Output = Result<Question>> + Send {
+        let text = text.to_string();
+        self.run(move |store| store.create_question(&text))
+    }
+
+    fn get_question(&self, id: &str) -> impl Future<Output = Result<Option<Question>>> + Send {
+        let id = id.to_string();
+        self.run(move |store| store.get_question(&id))
+    }
+
+    fn answer_question(
+        &self,
+        id: &str,
+        answer: &str,
+    ) -> impl Future<Output = Result<Option<Question>>> + Send {
+        let id = id.to_string();
+        let answer = answer.to_string();
+        self.run(move |store| store.answer_question(&id, &answer))
+    }
+
+    fn defer_question(
+        &self,
+        id: &str,
+        until: Option<&str>,
+    ) -> impl Future<Output = Result<Option<Question>>> + Send {
+        let id = id.to_string();
+        let until = until.map(str::to_string);
+        self.run(move |store| store.defer_question(&id, until.as_deref()))
+    }
+
+    fn set_question_priority(
+        &self,
+        id: &str,
+        priority: Priority,
+    ) -> impl Future<Output = Result<Option<Question>>> + Send {
+        let id = id.to_string();
+        self.run(move |store| store.set_question_priority(&id, priority))
+    }
+
+    fn assume_answer(
+        &self,
+        id: &str,
+        answer: &str,
+    ) -> impl Future<Output = Result<Option<Question>>> + Send {
+        let id = id.to_string();
+        let answer = answer.to_string();
+        self.run(move |store| store.assume_answer(&id, &answer))
+    }
+
+    fn reopen_question(&self, id: &str) -> impl Future<Output = Result<Option<Question>>> + Send {
+        let id = id.to_string();
+        self.run(move |store| store.reopen_question(&id))
+    }
+
+    fn set_question_suggestions(
+        &self,
+        id: &str,
+        suggestions: &[AnswerSuggestion],
+    ) -> impl Future<Output = Result<Option<Question>>> + Send {
+        let id = id.to_string();
+        let suggestions = suggestions.to_vec();
+        self.run(move |store| store.set_question_suggestions(&id, &suggestions))
+    }
+
+    fn delete_question(&self, id: &str) -> impl Future<Output = Result<bool>> + Send {
+        let id = id.to_string();
+        self.run(move |store| store.delete_question(&id))
+    }
+
+    fn list_questions(
+        &self,
+        unanswered_only: bool,
+    ) -> impl Future<Output = Result<Vec<Question>>> + Send {
+        self.run(move |store| store.list_questions(unanswered_only))
+    }
+
+    fn search_questions(&self, query: &str) -> impl Future<Output = Result<Vec<Question>>> + Send {
+        let query = query.to_string();
+        self.run(move |store| store.search_questions(&query))
+    }
+
+    fn link_task_to_question(
+        &self,
+        task_id: &str,
+        question_id: &str,
+    ) -> impl Future<Output = Result<()>> + Send {
+        let task_id = task_id.to_string();
+        let question_id = question_id.to_string();
+        self.run(move |store| store.link_task_to_question(&task_id, &question_id))
+    }
+
+    fn unlink_task_from_question(
+        &self,
+        task_id: &str,
+        question_id: &str,
+    ) -> impl Future<Output = Result<bool>> + Send {
+        let task_id = task_id.to_string();
+        let question_id = question_id.to_string();
+        self.run(move |store| store.unlink_task_from_question(&task_id, &question_id))
+    }
+
+    fn get_task_questions(
+        &self,
+        task_id: &str,
+    ) -> impl Future<Output = Result<Vec<String>>> + Send {
+        let task_id = task_id.to_string();
+        self.run(move |store| store.get_task_questions(&task_id))
+    }
+
+    fn get_blocking_questions(
+        &self,
+        task_id: &str,
+    ) -> impl Future<Output = Result<Vec<Question>>> + Send {
+        let task_id = task_id.to_string();
+        self.run(move |store| store.get_blocking_questions(&task_id))
+    }
+
+    fn get_question_blocked_tasks(&self) -> impl Future<Output = Result<Vec<Task>>> + Send {
+        self.run(S::get_question_blocked_tasks)
+    }
+
+    fn has_in_progress_task(&self) -> impl Future<Output = Result<bool>> + Send {
+        self.run(S::has_in_progress_task)
+    }
+
+    fn get_in_progress_tasks(&self) -> impl Future<Output = Result<Vec<Task>>> + Send {
+        self.run(S::get_in_progress_tasks)
+    }
+
+    fn request_tasks(&self, task_ids: &[&str]) -> impl Future<Output = Result<usize>> + Send {
+        let task_ids: Vec<String> = task_ids.iter().map(|id| (*id).to_string()).collect();
+        self.run(move |store| {
+            store.request_tasks(&task_ids.iter().map(String::as_str).collect::<Vec<_>>())
+        })
+    }
+
+    fn request_all_open(&self) -> impl Future<Output = Result<usize>> + Send {
+        self.run(S::request_all_open)
+    }
+
+    fn is_request_mode_active(&self) -> impl Future<Output = Result<bool>> + Send {
+        self.run(S::is_request_mode_active)
+    }
+
+    fn clear_request_mode(&self) -> impl Future<Output = Result<()>> + Send {
+        self.run(S::clear_request_mode)
+    }
+
+    fn get_incomplete_requested_work(&self) -> impl Future<Output = Result<Vec<Task>>> + Send {
+        self.run(S::get_incomplete_requested_work)
+    }
+
+    fn record_user_message(
+        &self,
+        message: &str,
+        context: &str,
+        transcript_path: Option<&str>,
+        session_id: &str,
+    ) -> impl Future<Output = Result<()>> + Send {
+        let message = message.to_string();
+        let context = context.to_string();
+        let transcript_path = transcript_path.map(str::to_string);
+        let session_id = session_id.to_string();
+        self.run(move |store| {
+            store.record_user_message(&message, &context, transcript_path.as_deref(), &session_id)
+        })
+    }
+
+    fn get_session_user_messages(
+        &self,
+        session_id: &str,
+    ) -> impl Future<Output = Result<Vec<UserMessage>>> + Send {
+        let session_id = session_id.to_string();
+        self.run(move |store| store.get_session_user_messages(&session_id))
+    }
+
+    fn clear_user_messages_for_session(
+        &self,
+        session_id: &str,
+    ) -> impl Future<Output = Result<()>> + Send {
+        let session_id = session_id.to_string();
+        self.run(move |store| store.clear_user_messages_for_session(&session_id))
+    }
+
+    fn mark_pre_compaction(&self, session_id: &str) -> impl Future<Output = Result<()>> + Send {
+        let session_id = session_id.to_string();
+        self.run(move |store| store.mark_pre_compaction(&session_id))
+    }
+
+    fn scrub_user_messages(&self) -> impl Future<Output = Result<usize>> + Send {
+        self.run(S::scrub_user_messages)
+    }
+}
+
+/// Wraps an async task store as a synchronous [`TaskStore`].
+///
+/// Each call is driven to completion on a private single-threaded runtime,
+/// so methods must not be called from within another tokio runtime.
+#[derive(Debug)]
+pub struct BlockOn<A> {
+    inner: A,
+    runtime: tokio::runtime::Runtime,
+}
+
+impl<A: AsyncTaskStore> BlockOn<A> {
+    /// Wrap an async store.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the runtime cannot be created.
+    pub fn new(inner: A) -> Result<Self> {
+        let runtime = tokio::runtime::Builder::new_current_thread().build()?;
+        Ok(Self { inner, runtime })
+    }
+
+    /// Get the wrapped async store.
+    pub const fn inner(&self) -> &A {
+        &self.inner
+    }
+}
+
+impl<A: AsyncTaskStore> TaskStore for BlockOn<A> {
+    fn create_task(&self, title: &str, description: &str, priority: Priority) -> Result<Task> {
+        self.runtime.block_on(self.inner.create_task(title, description, priority))
+    }
+
+    fn get_task(&self, id: &str) -> Result<Option<Task>> {
+        self.runtime.block_on(self.inner.get_task(id))
+    }
+
+    fn update_task(&self, id: &str, update: TaskUpdate) -> Result<Option<Task>> {
+        self.runtime.block_on(self.inner.update_task(id, update))
+    }
+
+    fn delete_task(&self, id: &str) -> Result<bool> {
+        self.runtime.block_on(self.inner.delete_task(id))
+    }
+
+    fn list_tasks(&self, filter: TaskFilter) -> Result<Vec<Task>> {
+        self.runtime.block_on(self.inner.list_tasks(filter))
+    }
+
+    fn add_dependency(&self, task_id: &str, depends_on: &str) -> Result<()> {
+        self.runtime.block_on(self.inner.add_dependency(task_id, depends_on))
+    }
+
+    fn remove_dependency(&self, task_id: &str, depends_on: &str) -> Result<bool> {
+        self.runtime.block_on(self.inner.remove_dependency(task_id, depends_on))
+    }
+
+    fn get_dependencies(&self, task_id: &str) -> Result<Vec<String>> {
+        self.runtime.block_on(self.inner.get_dependencies(task_id))
+    }
+
+    fn get_dependents(&self, task_id: &str) -> Result<Vec<String>> {
+        self.runtime.block_on(self.inner.get_dependents(task_id))
+    }
+
+    fn add_note(&self, task_id: &str, content: &str) -> Result<Note> {
+        self.runtime.block_on(self.inner.add_note(task_id, content))
+    }
+
+    fn get_notes(&self, task_id: &str) -> Result<Vec<Note>> {
+        self.runtime.block_on(self.inner.get_notes(task_id))
+    }
+
+    fn delete_note(&self, note_id: i64) -> Result<bool> {
+        self.runtime.block_on(self.inner.delete_note(note_id))
+    }
+
+    fn search_tasks(&self, query: &str) -> Result<Vec<Task>> {
+        self.runtime.block_on(self.inner.search_tasks(query))
+    }
+
+    fn get_audit_log(
+        &self,
+        task_id: Option<&str>,
+        limit: Option<usize>,
+    ) -> Result<Vec<AuditEntry>> {
+        self.runtime.block_on(self.inner.get_audit_log(task_id, limit))
+    }
+
+    fn get_ready_tasks(&self) -> Result<Vec<Task>> {
+        self.runtime.block_on(self.inner.get_ready_tasks())
+    }
+
+    fn pick_task(&self) -> Result<Option<Task>> {
+        self.runtime.block_on(self.inner.pick_task())
+    }
+
+    fn create_howto(&self, title: &str, instructions: &str) -> Result<HowTo> {
+        self.runtime.block_on(self.inner.create_howto(title, instructions))
+    }
+
+    fn get_howto(&self, id: &str) -> Result<Option<HowTo>> {
+        self.runtime.block_on(self.inner.get_howto(id))
+    }
+
+    fn update_howto(&self, id: &str, update: HowToUpdate) -> Result<Option<HowTo>> {
+        self.runtime.block_on(self.inner.update_howto(id, update))
+    }
+
+    fn delete_howto(&self, id: &str) -> Result<bool> {
+        self.runtime.block_on(self.inner.delete_howto(id))
+    }
+
+    fn list_howtos(&self) -> Result<Vec<HowTo>> {
+        self.runtime.block_on(self.inner.list_howtos())
+    }
+
+    fn search_howtos(&self, query: &str) -> Result<Vec<HowTo>> {
+        self.runtime.block_on(self.inner.search_howtos(query))
+    }
+
+    fn link_task_to_howto(&self, task_id: &str, howto_id: &str) -> Result<()> {
+        self.runtime.block_on(self.inner.link_task_to_howto(task_id, howto_id))
+    }
+
+    fn unlink_task_from_howto(&self, task_id: &str, howto_id: &str) -> Result<bool> {
+        self.runtime.block_on(self.inner.unlink_task_from_howto(task_id, howto_id))
+    }
+
+    fn get_task_guidance(&self, task_id: &str) -> Result<Vec<String>> {
+        self.runtime.block_on(self.inner.get_task_guidance(task_id))
+    }
+
+    fn create_question(&self, text: &str) -> Result<Question> {
+        self.runtime.block_on(self.inner.create_question(text))
+    }
+
+    fn get_question(&self, id: &str) -> Result<Option<Question>> {
+        self.runtime.block_on(self.inner.get_question(id))
+    }
+
+    fn answer_question(&self, id: &str, answer: &str) -> Result<Option<Question>> {
+        self.runtime.block_on(self.inner.answer_question(id, answer))
+    }
+
+    fn defer_question(&self, id: &str, until: Option<&str>) -> Result<Option<Question>> {
+        self.runtime.block_on(self.inner.defer_question(id, until))
+    }
+
+    fn set_question_priority(&self, id: &str, priority: Priority) -> Result<Option<Question>> {
+        self.runtime.block_on(self.inner.set_question_priority(id, priority))
+    }
+
+    fn assume_answer(&self, id: &str, answer: &str) -> Result<Option<Question>> {
+        self.runtime.block_on(self.inner.assume_answer(id, answer))
+    }
+
+    fn reopen_question(&self, id: &str) -> Result<Option<Question>> {
+        self.runtime.block_on(self.inner.reopen_question(id))
+    }
+
+    fn set_question_suggestions(
+        &self,
+        id: &str,
+        suggestions: &[AnswerSuggestion],
+    ) -> Result<Option<Question>> {
+        self.runtime.block_on(self.inner.set_question_suggestions(id, suggestions))
+    }
+
+    fn delete_question(&self, id: &str) -> Result<bool> {
+        self.runtime.block_on(self.inner.delete_question(id))
+    }
+
+    fn list_questions(&self, unanswered_only: bool) -> Result<Vec<Question>> {
+        self.runtime.block_on(self.inner.list_questions(unanswered_only))
+    }
+
+    fn search_questions(&self, query: &str) -> Result<Vec<Question>> {
+        self.runtime.block_on(self.inner.search_questions(query))
+    }
+
+    fn link_task_to_question(&self, task_id: &str, question_id: &str) -> Result<()> {
+        self.runtime.block_on(self.inner.link_task_to_question(task_id, question_id))
+    }
+
+    fn unlink_task_from_question(&self, task_id: &str, question_id: &str) -> Result<bool> {
+        self.runtime.block_on(self.inner.unlink_task_from_question(task_id, question_id))
+    }
+
+    fn get_task_questions(&self, task_id: &str) -> Result<Vec<String>> {
+        self.runtime.block_on(self.inner.get_task_questions(task_id))
+    }
+
+    fn get_blocking_questions(&self, task_id: &str) -> Result<Vec<Question>> {
+        self.runtime.block_on(self.inner.get_blocking_questions(task_id))
+    }
+
+    fn get_question_blocked_tasks(&self) -> Result<Vec<Task>> {
+        self.runtime.block_on(self.inner.get_question_blocked_tasks())
+    }
+
+    fn has_in_progress_task(&self) -> Result<bool> {
+        self.runtime.block_on(self.inner.has_in_progress_task())
+    }
+
+    fn get_in_progress_tasks(&self) -> Result<Vec<Task>> {
+        self.runtime.block_on(self.inner.get_in_progress_tasks())
+    }
+
+    fn request_tasks(&self, task_ids: &[&str]) -> Result<usize> {
+        self.runtime.block_on(self.inner.request_tasks(task_ids))
+    }
+
+    fn request_all_open(&self) -> Result<usize> {
+        self.runtime.block_on(self.inner.request_all_open())
+    }
+
+    fn is_request_mode_active(&self) -> Result<bool> {
+        self.runtime.block_on(self.inner.is_request_mode_active())
+    }
+
+    fn clear_request_mode(&self) -> Result<()> {
+        self.runtime.block_on(self.inner.clear_request_mode())
+    }
+
+    fn get_incomplete_requested_work(&self) -> Result<Vec<Task>> {
+        self.runtime.block_on(self.inner.get_incomplete_requested_work())
+    }
+
+    fn record_user_message(
+        &self,
+        message: &str,
+        context: &str,
+        transcript_path: Option<&str>,
+        session_id: &str,
+    ) -> Result<()> {
+        self.runtime.block_on(self.inner.record_user_message(
+            message,
+            context,
+            transcript_path,
+            session_id,
+        ))
+    }
+
+    fn get_session_user_messages(&self, session_id: &str) -> Result<Vec<UserMessage>> {
+        self.runtime.block_on(self.inner.get_session_user_messages(session_id))
+    }
+
+    fn clear_user_messages_for_session(&self, session_id: &str) -> Result<()> {
+        self.runtime.block_on(self.inner.clear_user_messages_for_session(session_id))
+    }
+
+    fn mark_pre_compaction(&self, session_id: &str) -> Result<()> {
+        self.runtime.block_on(self.inner.mark_pre_compaction(session_id))
+    }
+
+    fn scrub_user_messages(&self) -> Result<usize> {
+        self.runtime.block_on(self.inner.scrub_user_messages())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tasks::{InMemoryTaskStore, SqliteTaskStore, Status};
+    use tempfile::TempDir;
+
+    fn runtime() -> tokio::runtime::Runtime {
+        tokio::runtime::Builder::new_current_thread().build().unwrap()
+    }
+
+    #[test]
+    fn test_spawn_blocking_runs_store_calls() {
+        let dir = TempDir::new().unwrap();
+        let store = SpawnBlocking::new(SqliteTaskStore::new(dir.path().join("tasks.db")).unwrap());
+
+        runtime().block_on(async {
+            let a = store.create_task("A", "", Priority::High).await.unwrap();
+            let b = store.create_task("B", "", Priority::Low).await.unwrap();
+            store.add_dependency(&a.id, &b.id).await.unwrap();
+            assert_eq!(store.get_task(&a.id).await.unwrap().unwrap().status, Status::Blocked);
+
+            store.request_tasks(&[&a.id]).await.unwrap();
+            let work = store.get_incomplete_requested_work().await.unwrap();
+            assert_eq!(work.iter().map(|t| &t.id).collect::<Vec<_>>(), vec![&b.id]);
+            assert_eq!(store.get_audit_log(Some(&a.id), None).await.unwrap().len(), 3);
+        });
+    }
+
+    #[test]
+    fn test_spawn_blocking_calls_run_concurrently() {
+        let store = SpawnBlocking::new(InMemoryTaskStore::new());
+        let count = runtime().block_on(async {
+            let handles: Vec<_> = (0..8)
+                .map(|i| {
+                    let store = store.clone();
+                    tokio::spawn(async move {
+                        store.create_task(&format!("Task {i}"), "", Priority::Medium).await
+                    })
+                })
+                .collect();
+            for handle in handles {
+                handle.await.unwrap().unwrap();
+            }
+            store.list_tasks(TaskFilter::default()).await.unwrap().len()
+        });
+        assert_eq!(count, 8);
+    }
+
+    #[test]
+    fn test_block_on_provides_sync_store() {
+        let store = BlockOn::new(SpawnBlocking::new(InMemoryTaskStore::new())).unwrap();
+        let task = store.create_task("Sync", "", Priority::Medium).unwrap();
+        let question = store.create_question("Ready?").unwrap();
+        store.link_task_to_question(&task.id, &question.id).unwrap();
+        assert!(store.get_ready_tasks().unwrap().is_empty());
+
+        store.answer_question(&question.id, "Yes").unwrap();
+        assert_eq!(store.get_ready_tasks().unwrap().len(), 1);
+        assert_eq!(crate::tasks::invariants::check_invariants(&store), Ok(()));
+    }
+
+    #[test]
+    #[should_panic(expected = "store call panicked")]
+    fn test_spawn_blocking_resumes_panics() {
+        let store = SpawnBlocking::new(InMemoryTaskStore::new());
+        runtime().block_on(store.run(|_| -> Result<()> { panic!("store call panicked") })).unwrap();
+    }
+}
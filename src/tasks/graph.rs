@@ -0,0 +1,460 @@
+//! Exporting the task dependency graph for rendering.
+//!
+//! [`render_graph`] draws every task as a node with an edge to each task it
+//! depends on, and every unanswered question as a node with an edge from each
+//! task it blocks, so it is easy to see why a task is not ready. The output is
+//! Graphviz DOT or a Mermaid flowchart.
+//!
+//! Tasks are filled by state:
+//!
+//! | Fill       | State                                            |
+//! |------------|--------------------------------------------------|
+//! | light blue | ready to work on                                 |
+//! | sky blue   | in progress                                      |
+//! | salmon     | waiting on dependencies or questions             |
+//! | gold       | stuck                                            |
+//! | plum       | in review                                        |
+//! | khaki      | waiting on a third party                         |
+//! | pale green | complete                                         |
+//! | light grey | abandoned                                        |
+//!
+//! Requested tasks have a thick border, and questions are orange.
+//!
+//! [`find_blockers`] uses the same graph to rank the open tasks and questions
+//! holding up requested work by how many items each one transitively blocks.
+
+use crate::error::{Error, Result};
+use crate::tasks::models::{Question, Status, Task};
+use crate::tasks::store::{TaskFilter, TaskStore};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::str::FromStr;
+
+/// Output format for [`render_graph`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphFormat {
+    /// Graphviz DOT.
+    Dot,
+    /// A Mermaid flowchart.
+    Mermaid,
+}
+
+impl FromStr for GraphFormat {
+    type Err = Error;
+
+    fn from_str(format: &str) -> Result<Self> {
+        match format.to_lowercase().as_str() {
+            "dot" | "graphviz" => Ok(Self::Dot),
+            "mermaid" => Ok(Self::Mermaid),
+            _ => Err(Error::Config(format!(
+                "invalid graph format '{format}' (must be one of: dot, mermaid)"
+            ))),
+        }
+    }
+}
+
+/// Fill color for a state, as (Graphviz name, Mermaid hex).
+type Fill = (&'static str, &'static str);
+
+const READY: Fill = ("lightblue", "#add8e6");
+const IN_PROGRESS: Fill = ("lightskyblue", "#87cefa");
+const WAITING: Fill = ("lightsalmon", "#ffa07a");
+const STUCK: Fill = ("gold", "#ffd700");
+const IN_REVIEW: Fill = ("plum", "#dda0dd");
+const EXTERNAL: Fill = ("khaki", "#f0e68c");
+const COMPLETE: Fill = ("palegreen", "#98fb98");
+const ABANDONED: Fill = ("lightgrey", "#d3d3d3");
+const QUESTION: Fill = ("orange", "#ffa500");
+
+/// The graph's contents, read from the store.
+struct Graph {
+    tasks: Vec<Task>,
+    ready: HashSet<String>,
+    dependencies: Vec<(usize, usize)>,
+    questions: Vec<Question>,
+    /// (task index, question index) pairs for unanswered blocking questions.
+    blocks: Vec<(usize, usize)>,
+}
+
+impl Graph {
+    fn load<S: TaskStore + ?Sized>(store: &S) -> Result<Self> {
+        let tasks = store.list_tasks(TaskFilter::default())?;
+        let ready = store.get_ready_tasks()?.into_iter().map(|t| t.id).collect();
+        let index: HashMap<&str, usize> =
+            tasks.iter().enumerate().map(|(i, t)| (t.id.as_str(), i)).collect();
+
+        let mut dependencies = Vec::new();
+        let mut questions: Vec<Question> = Vec::new();
+        let mut blocks = Vec::new();
+        for (i, task) in tasks.iter().enumerate() {
+            for dep in store.get_dependencies(&task.id)? {
+                if let Some(&j) = index.get(dep.as_str()) {
+                    dependencies.push((i, j));
+                }
+            }
+            for question in store.get_blocking_questions(&task.id)? {
+                let q = questions.iter().position(|q| q.id == question.id).unwrap_or_else(|| {
+                    questions.push(question);
+                    questions.len() - 1
+                });
+                blocks.push((i, q));
+            }
+        }
+        Ok(Self { tasks, ready, dependencies, questions, blocks })
+    }
+
+    fn fill(&self, task: &Task) -> Fill {
+        match task.status {
+            Status::Complete => COMPLETE,
+            Status::Abandoned => ABANDONED,
+            Status::Stuck => STUCK,
+            Status::InReview => IN_REVIEW,
+            Status::BlockedExternal => EXTERNAL,
+            Status::Open if task.in_progress => IN_PROGRESS,
+            Status::Open if self.ready.contains(&task.id) => READY,
+            Status::Open | Status::Blocked => WAITING,
+        }
+    }
+}
+
+/// Render the store's dependency graph in the given format.
+///
+/// # Errors
+///
+/// Returns an error if the store cannot be read.
+pub fn render_graph<S: TaskStore + ?Sized>(store: &S, format: GraphFormat) -> Result<String> {
+    let graph = Graph::load(store)?;
+    Ok(match format {
+        GraphFormat::Dot => render_dot(&graph),
+        GraphFormat::Mermaid => render_mermaid(&graph),
+    })
+}
+
+fn render_dot(graph: &Graph) -> String {
+    let quote = |text: &str| text.replace('\\', "\\\\").replace('"', "\\\"");
+    let mut out = String::from("digraph work {\n    rankdir=LR;\n    node [style=filled];\n");
+    for (i, task) in graph.tasks.iter().enumerate() {
+        let penwidth = if task.requested { 3 } else { 1 };
+        let _ = writeln!(
+            out,
+            "    t{i} [label=\"{}\\n{} ({})\", shape=box, fillcolor={}, penwidth={penwidth}];",
+            quote(&task.title),
+            quote(&task.id),
+            task.status,
+            graph.fill(task).0
+        );
+    }
+    for (i, question) in graph.questions.iter().enumerate() {
+        let _ = writeln!(
+            out,
+            "    q{i} [label=\"{}\\n{}\", shape=note, fillcolor={}];",
+            quote(&question.text),
+            quote(&question.id),
+            QUESTION.0
+        );
+    }
+    for (from, to) in &graph.dependencies {
+        let _ = writeln!(out, "    t{from} -> t{to};");
+    }
+    for (task, question) in &graph.blocks {
+        let _ = writeln!(out, "    t{task} -> q{question} [style=dashed, label=\"waiting on\"];");
+    }
+    out.push_str("}\n");
+    out
+}
+
+fn render_mermaid(graph: &Graph) -> String {
+    let quote = |text: &str| text.replace('"', "#quot;");
+    let mut out = String::from("flowchart LR\n");
+    for (i, task) in graph.tasks.iter().enumerate() {
+        let _ = writeln!(
+            out,
+            "    t{i}[\"{}<br/>{} ({})\"]",
+            quote(&task.title),
+            quote(&task.id),
+            task.status
+        );
+    }
+    for (i, question) in graph.questions.iter().enumerate() {
+        let _ = writeln!(
+            out,
+            "    q{i}{{{{\"{}<br/>{}\"}}}}",
+            quote(&question.text),
+            quote(&question.id)
+        );
+    }
+    for (from, to) in &graph.dependencies {
+        let _ = writeln!(out, "    t{from} --> t{to}");
+    }
+    for (task, question) in &graph.blocks {
+        let _ = writeln!(out, "    t{task} -.->|waiting on| q{question}");
+    }
+    for (i, task) in graph.tasks.iter().enumerate() {
+        let width = if task.requested { "3px" } else { "1px" };
+        let _ = writeln!(out, "    style t{i} fill:{},stroke-width:{width}", graph.fill(task).1);
+    }
+    for i in 0..graph.questions.len() {
+        let _ = writeln!(out, "    style q{i} fill:{}", QUESTION.1);
+    }
+    out
+}
+
+/// What kind of item a [`Blocker`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BlockerKind {
+    /// An open task that other requested work depends on.
+    Task,
+    /// An unanswered question blocking requested work.
+    Question,
+}
+
+/// A task or question holding up requested work.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Blocker {
+    /// Whether this is a task or a question.
+    pub kind: BlockerKind,
+    /// The task or question ID.
+    pub id: String,
+    /// The task title or question text.
+    pub title: String,
+    /// How many requested work items wait on this, directly or transitively.
+    pub blocks: usize,
+    /// Whether it can be dealt with now: a ready task, or any question.
+    pub ready: bool,
+}
+
+/// Rank what is holding up the requested work.
+///
+/// The requested work is every open requested task plus the open tasks it
+/// transitively depends on. Each open task and unanswered question is counted
+/// against the items in that set waiting on it, directly or through other
+/// tasks. Anything blocking nothing is left out, and the rest are sorted with
+/// the biggest bottleneck first.
+///
+/// # Errors
+///
+/// Returns an error if the store cannot be read.
+pub fn find_blockers<S: TaskStore + ?Sized>(store: &S) -> Result<Vec<Blocker>> {
+    let graph = Graph::load(store)?;
+    let open = |i: usize| !graph.tasks[i].is_closed();
+
+    let mut dependencies: HashMap<usize, Vec<usize>> = HashMap::new();
+    let mut dependents: HashMap<usize, Vec<usize>> = HashMap::new();
+    for &(from, to) in &graph.dependencies {
+        if open(to) {
+            dependencies.entry(from).or_default().push(to);
+            dependents.entry(to).or_default().push(from);
+        }
+    }
+
+    let requested: Vec<usize> =
+        (0..graph.tasks.len()).filter(|&i| open(i) && graph.tasks[i].requested).collect();
+    let work = reachable(&requested, &dependencies, |_| true);
+    // Everything in the requested work waiting on any of the given tasks
+    let waiting = |starts: &[usize]| {
+        let starts: Vec<usize> = starts.iter().copied().filter(|i| work.contains(i)).collect();
+        reachable(&starts, &dependents, |i| work.contains(&i))
+    };
+
+    let mut blockers = Vec::new();
+    for &i in &work {
+        let blocks = waiting(&[i]).len() - 1;
+        if blocks > 0 {
+            let task = &graph.tasks[i];
+            blockers.push(Blocker {
+                kind: BlockerKind::Task,
+                id: task.id.clone(),
+                title: task.title.clone(),
+                blocks,
+                ready: graph.ready.contains(&task.id),
+            });
+        }
+    }
+    for (q, question) in graph.questions.iter().enumerate() {
+        let tasks: Vec<usize> =
+            graph.blocks.iter().filter(|(_, bq)| *bq == q).map(|(t, _)| *t).collect();
+        let blocks = waiting(&tasks).len();
+        if blocks > 0 {
+            blockers.push(Blocker {
+                kind: BlockerKind::Question,
+                id: question.id.clone(),
+                title: question.text.clone(),
+                blocks,
+                ready: true,
+            });
+        }
+    }
+    blockers.sort_by(|a, b| {
+        b.blocks.cmp(&a.blocks).then(b.ready.cmp(&a.ready)).then_with(|| a.id.cmp(&b.id))
+    });
+    Ok(blockers)
+}
+
+/// Every node reachable from `starts` (inclusive) along `edges`, visiting only
+/// nodes that pass `keep`.
+fn reachable(
+    starts: &[usize],
+    edges: &HashMap<usize, Vec<usize>>,
+    keep: impl Fn(usize) -> bool,
+) -> HashSet<usize> {
+    let mut seen: HashSet<usize> = starts.iter().copied().collect();
+    let mut stack = starts.to_vec();
+    while let Some(node) = stack.pop() {
+        for &next in edges.get(&node).into_iter().flatten() {
+            if keep(next) && seen.insert(next) {
+                stack.push(next);
+            }
+        }
+    }
+    seen
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tasks::models::Priority;
+    use crate::tasks::store::TaskUpdate;
+    use crate::tasks::InMemoryTaskStore;
+
+    /// A store with a requested task waiting on a dependency and a question.
+    fn blocked_store() -> (InMemoryTaskStore, Task, Task) {
+        let store = InMemoryTaskStore::new();
+        let feature = store.create_task("Ship \"feature\"", "", Priority::High).unwrap();
+        let setup = store.create_task("Set up", "", Priority::Medium).unwrap();
+        store.add_dependency(&feature.id, &setup.id).unwrap();
+        let question = store.create_question("Which database?").unwrap();
+        store.link_task_to_question(&setup.id, &question.id).unwrap();
+        store
+            .update_task(&feature.id, TaskUpdate { requested: Some(true), ..Default::default() })
+            .unwrap();
+        (store, feature, setup)
+    }
+
+    #[test]
+    fn test_parse_format() {
+        assert_eq!("dot".parse::<GraphFormat>().unwrap(), GraphFormat::Dot);
+        assert_eq!("Mermaid".parse::<GraphFormat>().unwrap(), GraphFormat::Mermaid);
+        assert!("svg".parse::<GraphFormat>().is_err());
+    }
+
+    #[test]
+    fn test_dot_shows_dependencies_and_questions() {
+        let (store, feature, setup) = blocked_store();
+        let dot = render_graph(&store, GraphFormat::Dot).unwrap();
+
+        assert!(dot.starts_with("digraph work {"));
+        assert!(dot.contains("Ship \\\"feature\\\""));
+        assert!(dot.contains(&format!(
+            "{} (blocked)\", shape=box, fillcolor=lightsalmon, penwidth=3",
+            feature.id
+        )));
+        assert!(dot.contains(&format!(
+            "{} (open)\", shape=box, fillcolor=lightsalmon, penwidth=1",
+            setup.id
+        )));
+        assert!(dot.contains("t0 -> t1;") || dot.contains("t1 -> t0;"));
+        assert!(dot.contains("Which database?"));
+        assert!(dot.contains("-> q0 [style=dashed"));
+    }
+
+    #[test]
+    fn test_answered_question_unblocks() {
+        let (store, _, setup) = blocked_store();
+        let question = store.get_blocking_questions(&setup.id).unwrap().remove(0);
+        store.answer_question(&question.id, "SQLite").unwrap();
+
+        let dot = render_graph(&store, GraphFormat::Dot).unwrap();
+        assert!(!dot.contains("Which database?"));
+        assert!(dot.contains("fillcolor=lightblue"));
+    }
+
+    #[test]
+    fn test_mermaid_output() {
+        let (store, _, _) = blocked_store();
+        let mermaid = render_graph(&store, GraphFormat::Mermaid).unwrap();
+
+        assert!(mermaid.starts_with("flowchart LR\n"));
+        assert!(mermaid.contains("Ship #quot;feature#quot;"));
+        assert!(mermaid.contains("q0{{\"Which database?"));
+        assert!(mermaid.contains("-.->|waiting on| q0"));
+        assert!(mermaid.contains("fill:#ffa07a,stroke-width:3px"));
+        assert!(mermaid.contains("style q0 fill:#ffa500"));
+    }
+
+    #[test]
+    fn test_completed_and_stuck_colors() {
+        let store = InMemoryTaskStore::new();
+        let done = store.create_task("Done", "", Priority::Low).unwrap();
+        let stuck = store.create_task("Stuck", "", Priority::Low).unwrap();
+        store
+            .update_task(
+                &done.id,
+                TaskUpdate { status: Some(Status::Complete), ..Default::default() },
+            )
+            .unwrap();
+        store
+            .update_task(
+                &stuck.id,
+                TaskUpdate { status: Some(Status::Stuck), ..Default::default() },
+            )
+            .unwrap();
+
+        let dot = render_graph(&store, GraphFormat::Dot).unwrap();
+        assert!(dot.contains("fillcolor=palegreen"));
+        assert!(dot.contains("fillcolor=gold"));
+    }
+
+    #[test]
+    fn test_find_blockers_ranks_bottleneck_first() {
+        let store = InMemoryTaskStore::new();
+        let goal = store.create_task("Goal", "", Priority::High).unwrap();
+        let middle = store.create_task("Middle", "", Priority::Medium).unwrap();
+        let root = store.create_task("Root", "", Priority::Low).unwrap();
+        let leaf = store.create_task("Leaf", "", Priority::Critical).unwrap();
+        let unrelated = store.create_task("Unrelated", "", Priority::Low).unwrap();
+        let unrelated_dep = store.create_task("Unrelated dep", "", Priority::Low).unwrap();
+        store.add_dependency(&goal.id, &middle.id).unwrap();
+        store.add_dependency(&middle.id, &root.id).unwrap();
+        store.add_dependency(&goal.id, &leaf.id).unwrap();
+        store.add_dependency(&unrelated.id, &unrelated_dep.id).unwrap();
+        let question = store.create_question("Which API?").unwrap();
+        store.link_task_to_question(&root.id, &question.id).unwrap();
+        store.request_tasks(&[goal.id.as_str()]).unwrap();
+
+        let blockers = find_blockers(&store).unwrap();
+        let summary: Vec<(&str, usize, bool)> =
+            blockers.iter().map(|b| (b.id.as_str(), b.blocks, b.ready)).collect();
+        assert_eq!(
+            summary,
+            vec![
+                (question.id.as_str(), 3, true),
+                (root.id.as_str(), 2, false),
+                (leaf.id.as_str(), 1, true),
+                (middle.id.as_str(), 1, false),
+            ]
+        );
+        assert_eq!(blockers[0].kind, BlockerKind::Question);
+    }
+
+    #[test]
+    fn test_find_blockers_ignores_closed_and_unrequested_work() {
+        let store = InMemoryTaskStore::new();
+        let goal = store.create_task("Goal", "", Priority::High).unwrap();
+        let done = store.create_task("Done", "", Priority::Low).unwrap();
+        store.add_dependency(&goal.id, &done.id).unwrap();
+        assert!(find_blockers(&store).unwrap().is_empty());
+
+        store.request_tasks(&[goal.id.as_str()]).unwrap();
+        assert_eq!(find_blockers(&store).unwrap().len(), 1);
+
+        store
+            .update_task(
+                &done.id,
+                TaskUpdate { status: Some(Status::Complete), ..Default::default() },
+            )
+            .unwrap();
+        assert!(find_blockers(&store).unwrap().is_empty());
+    }
+}
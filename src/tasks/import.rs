@@ -0,0 +1,481 @@
+//! Importing backlogs from other task systems.
+//!
+//! [`import`] turns an existing backlog into work items, so adopting this
+//! crate on a project does not start from an empty database. Priorities and
+//! dependencies are mapped on a best-effort basis:
+//!
+//! | Format | Input | Priority from | Dependencies from |
+//! |--------|-------|---------------|-------------------|
+//! | `todo-md` | a `TODO.md` checklist | `P0`-`P4`, `(high)` or `!high` in the item, else words like "urgent" or "later" in its heading | nested items: an item depends on the items under it |
+//! | `github-json` | `gh issue list --json number,title,body,labels,state` | labels such as `P1`, `priority: high` or `critical` | "depends on #N", "blocked by #N" or "requires #N" in the body |
+//! | `beads` | `.beads/issues.jsonl` or `bd list --json` | the issue priority | `blocks` dependencies |
+//!
+//! Finished items are skipped, as are items already imported (the same
+//! title, or the same beads issue), so an import can be run again after the
+//! source changes. Dependencies on skipped items are dropped.
+
+use crate::beads_sync;
+use crate::error::{Error, Result};
+use crate::tasks::bulk::{self, BulkCreateInput, TaskInput};
+use crate::tasks::models::Priority;
+use crate::tasks::store::{TaskFilter, TaskStore};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::str::FromStr;
+
+/// A backlog format [`import`] reads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    /// A markdown checklist, such as `TODO.md`.
+    TodoMd,
+    /// A JSON array of GitHub issues.
+    GithubJson,
+    /// Beads issues, as JSON lines or a JSON array.
+    Beads,
+}
+
+impl FromStr for ImportFormat {
+    type Err = Error;
+
+    fn from_str(format: &str) -> Result<Self> {
+        match format.to_lowercase().replace('_', "-").as_str() {
+            "todo-md" | "todo" | "markdown" => Ok(Self::TodoMd),
+            "github-json" | "github" => Ok(Self::GithubJson),
+            "beads" => Ok(Self::Beads),
+            _ => Err(Error::Config(format!(
+                "invalid import format '{format}' (must be one of: todo-md, github-json, beads)"
+            ))),
+        }
+    }
+}
+
+/// What an import did.
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct ImportReport {
+    /// Number of work items created.
+    pub created: usize,
+    /// Number of items skipped because they are finished or already imported.
+    pub skipped: usize,
+    /// Number of dependencies added.
+    pub dependencies_added: usize,
+    /// Items or dependencies that could not be created.
+    pub errors: Vec<String>,
+}
+
+/// An item read from a backlog.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Item {
+    /// Identifies the item within the backlog, for dependencies.
+    key: String,
+    title: String,
+    description: String,
+    priority: Priority,
+    /// Keys of the items this one depends on.
+    depends_on: Vec<String>,
+    /// Whether the item is already finished.
+    closed: bool,
+}
+
+/// Import a backlog into work items.
+///
+/// # Errors
+///
+/// Returns an error if the backlog cannot be parsed or the existing work
+/// items cannot be read.
+pub fn import<S: TaskStore + ?Sized>(
+    store: &S,
+    format: ImportFormat,
+    content: &str,
+) -> Result<ImportReport> {
+    let items = match format {
+        ImportFormat::TodoMd => parse_todo_md(content),
+        ImportFormat::GithubJson => parse_github_json(content)?,
+        ImportFormat::Beads => parse_beads(content)?,
+    };
+
+    let existing = store.list_tasks(TaskFilter::default())?;
+    let titles: HashSet<&str> = existing.iter().map(|t| t.title.as_str()).collect();
+    let beads_ids: HashSet<&str> =
+        existing.iter().filter_map(|t| beads_sync::extract_beads_id(&t.description)).collect();
+
+    let mut report = ImportReport::default();
+    let (skipped, items): (Vec<Item>, Vec<Item>) = items.into_iter().partition(|item| {
+        item.closed
+            || titles.contains(item.title.as_str())
+            || beads_sync::extract_beads_id(&item.description)
+                .is_some_and(|id| beads_ids.contains(id))
+    });
+    report.skipped = skipped.len();
+
+    let keys: HashSet<String> = items.iter().map(|item| item.key.clone()).collect();
+    let tasks = items
+        .into_iter()
+        .map(|item| TaskInput {
+            id: item.key,
+            title: item.title,
+            description: item.description,
+            priority: item.priority.as_u8(),
+            depends_on: item.depends_on.into_iter().filter(|key| keys.contains(key)).collect(),
+        })
+        .collect();
+    let output = bulk::bulk_create_tasks(store, &BulkCreateInput { tasks });
+    report.created = output.created;
+    report.dependencies_added = output.dependencies_added;
+    report.errors = output.errors;
+    Ok(report)
+}
+
+/// The priority a word such as `P1`, `urgent` or `later` stands for.
+fn priority_from_word(word: &str) -> Option<Priority> {
+    let word = word.trim().to_lowercase();
+    let word = ["priority:", "priority/", "priority-", "prio:"]
+        .iter()
+        .find_map(|prefix| word.strip_prefix(prefix))
+        .unwrap_or(&word)
+        .trim();
+    match word {
+        "p0" | "critical" | "urgent" | "blocker" => Some(Priority::Critical),
+        "p1" | "high" | "important" => Some(Priority::High),
+        "p2" | "medium" | "normal" => Some(Priority::Medium),
+        "p3" | "low" | "minor" => Some(Priority::Low),
+        "p4" | "backlog" | "someday" | "later" => Some(Priority::Backlog),
+        _ => None,
+    }
+}
+
+/// Matches a list item: indentation, checkbox state (if any) and text.
+static LIST_ITEM: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(\s*)(?:[-*+]|\d+[.)])\s+(?:\[([ xX])\]\s+)?(.+)$").unwrap());
+
+/// Matches a priority marker within an item: `P1`, `(high)` or `!high`.
+static INLINE_PRIORITY: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)\s*(?:\b(P[0-4])\b|\((\w+)\)|!(\w+)\b)").unwrap());
+
+/// Parse a markdown checklist.
+///
+/// If the file has checkboxes, only checkbox items are work items and other
+/// lines indented under an item become its description; otherwise every
+/// list item is a work item.
+fn parse_todo_md(content: &str) -> Vec<Item> {
+    let has_checkboxes = content
+        .lines()
+        .any(|line| LIST_ITEM.captures(line).is_some_and(|caps| caps.get(2).is_some()));
+    let mut items: Vec<Item> = Vec::new();
+    let mut heading_priority = Priority::Medium;
+    // Indentation and index of the items enclosing the current line
+    let mut parents: Vec<(usize, usize)> = Vec::new();
+
+    for (number, line) in content.lines().enumerate() {
+        if let Some(heading) = line.trim_start().strip_prefix('#') {
+            heading_priority = heading
+                .split(|c: char| !c.is_alphanumeric())
+                .find_map(priority_from_word)
+                .unwrap_or(Priority::Medium);
+            parents.clear();
+            continue;
+        }
+        let item = LIST_ITEM.captures(line).filter(|caps| !has_checkboxes || caps.get(2).is_some());
+        let Some(caps) = item else {
+            // Text under an item belongs to its description
+            let text = line.trim();
+            if let (false, Some(&(indent, index))) = (text.is_empty(), parents.last()) {
+                if line.len() - line.trim_start().len() > indent {
+                    let description = &mut items[index].description;
+                    if !description.is_empty() {
+                        description.push('\n');
+                    }
+                    description.push_str(text);
+                    continue;
+                }
+            }
+            if !text.is_empty() {
+                parents.clear();
+            }
+            continue;
+        };
+
+        let indent = caps[1].len();
+        let closed = caps.get(2).is_some_and(|state| state.as_str() != " ");
+        let marker = INLINE_PRIORITY.captures_iter(&caps[3]).find_map(|m| {
+            let word = m.get(1).or_else(|| m.get(2)).or_else(|| m.get(3))?.as_str();
+            priority_from_word(word).map(|p| (m.get(0).map_or("", |all| all.as_str()), p))
+        });
+        let (priority, title) = match marker {
+            Some((text, p)) => (p, caps[3].replacen(text, "", 1).trim().to_string()),
+            None => (heading_priority, caps[3].trim().to_string()),
+        };
+        if title.starts_with("~~") && title.ends_with("~~") && title.len() > 4 {
+            continue;
+        }
+
+        while parents.last().is_some_and(|&(parent_indent, _)| parent_indent >= indent) {
+            parents.pop();
+        }
+        let key = format!("line {}", number + 1);
+        if let Some(&(_, parent)) = parents.last() {
+            items[parent].depends_on.push(key.clone());
+        }
+        parents.push((indent, items.len()));
+        items.push(Item {
+            key,
+            title,
+            description: String::new(),
+            priority,
+            depends_on: Vec::new(),
+            closed,
+        });
+    }
+    items
+}
+
+/// A GitHub issue, as exported by `gh issue list --json` or the REST API.
+#[derive(Debug, Deserialize)]
+struct GithubIssue {
+    number: u64,
+    title: String,
+    #[serde(default)]
+    body: Option<String>,
+    #[serde(default)]
+    labels: Vec<GithubLabel>,
+    #[serde(default)]
+    state: String,
+    /// Set on pull requests in REST API exports.
+    #[serde(default)]
+    pull_request: Option<serde_json::Value>,
+}
+
+/// A GitHub label.
+#[derive(Debug, Deserialize)]
+struct GithubLabel {
+    name: String,
+}
+
+/// Matches a reference to an issue this one waits for.
+static ISSUE_DEPENDENCY: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\b(?:depends\s+on|blocked\s+by|requires)\s*:?\s+#(\d+)").unwrap()
+});
+
+/// Parse a JSON array of GitHub issues.
+fn parse_github_json(content: &str) -> Result<Vec<Item>> {
+    let issues: Vec<GithubIssue> = serde_json::from_str(content)?;
+    Ok(issues
+        .into_iter()
+        .filter(|issue| issue.pull_request.is_none())
+        .map(|issue| {
+            let body = issue.body.unwrap_or_default();
+            let depends_on = ISSUE_DEPENDENCY
+                .captures_iter(&body)
+                .map(|caps| format!("#{}", &caps[1]))
+                .collect();
+            let priority = issue
+                .labels
+                .iter()
+                .filter_map(|label| priority_from_word(&label.name))
+                .min()
+                .unwrap_or(Priority::Medium);
+            let source = format!("Imported from GitHub issue #{}.", issue.number);
+            let description = if body.trim().is_empty() {
+                source
+            } else {
+                format!("{}\n\n{source}", body.trim())
+            };
+            Item {
+                key: format!("#{}", issue.number),
+                title: issue.title,
+                description,
+                priority,
+                depends_on,
+                closed: issue.state.eq_ignore_ascii_case("closed"),
+            }
+        })
+        .collect())
+}
+
+/// A beads issue, as stored in `.beads/issues.jsonl` or listed by `bd list --json`.
+#[derive(Debug, Deserialize)]
+struct BeadsIssue {
+    id: String,
+    title: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    priority: Option<u8>,
+    #[serde(default, alias = "issue_type")]
+    r#type: String,
+    #[serde(default)]
+    status: String,
+    #[serde(default)]
+    dependencies: Vec<BeadsDependency>,
+}
+
+/// A dependency between beads issues.
+#[derive(Debug, Deserialize)]
+struct BeadsDependency {
+    depends_on_id: String,
+    #[serde(default, rename = "type")]
+    kind: String,
+}
+
+/// Parse beads issues, one JSON object per line or as a JSON array.
+fn parse_beads(content: &str) -> Result<Vec<Item>> {
+    let issues: Vec<BeadsIssue> = if content.trim_start().starts_with('[') {
+        serde_json::from_str(content)?
+    } else {
+        content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<std::result::Result<_, _>>()?
+    };
+    Ok(issues
+        .into_iter()
+        .map(|issue| {
+            let (title, description) = beads_sync::beads_task_text(
+                &issue.id,
+                &issue.r#type,
+                &issue.title,
+                &issue.description,
+            );
+            Item {
+                depends_on: issue
+                    .dependencies
+                    .into_iter()
+                    .filter(|dep| dep.kind.is_empty() || dep.kind == "blocks")
+                    .map(|dep| dep.depends_on_id)
+                    .collect(),
+                key: issue.id,
+                title,
+                description,
+                priority: issue
+                    .priority
+                    .and_then(|p| Priority::from_u8(p).ok())
+                    .unwrap_or(Priority::Medium),
+                closed: matches!(issue.status.as_str(), "closed" | "done" | "complete"),
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tasks::{InMemoryTaskStore, Task};
+
+    fn tasks_by_title(store: &InMemoryTaskStore) -> Vec<Task> {
+        let mut tasks = store.list_tasks(TaskFilter::default()).unwrap();
+        tasks.sort_by(|a, b| a.title.cmp(&b.title));
+        tasks
+    }
+
+    #[test]
+    fn test_parse_format() {
+        assert_eq!("todo-md".parse::<ImportFormat>().unwrap(), ImportFormat::TodoMd);
+        assert_eq!("GitHub_JSON".parse::<ImportFormat>().unwrap(), ImportFormat::GithubJson);
+        assert_eq!("beads".parse::<ImportFormat>().unwrap(), ImportFormat::Beads);
+        assert!("jira".parse::<ImportFormat>().unwrap_err().to_string().contains("todo-md"));
+    }
+
+    #[test]
+    fn test_parse_todo_md() {
+        let content = "\
+# TODO
+
+- [ ] Ship release P1
+  - [ ] Write changelog
+    Mention the new import command.
+  - [x] Bump version
+- [ ] Fix flaky test (low)
+
+## Someday
+
+* [ ] Rewrite parser
+- plain bullet, not a work item
+";
+        let items = parse_todo_md(content);
+        let summary: Vec<(&str, Priority, bool)> =
+            items.iter().map(|i| (i.title.as_str(), i.priority, i.closed)).collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("Ship release", Priority::High, false),
+                ("Write changelog", Priority::Medium, false),
+                ("Bump version", Priority::Medium, true),
+                ("Fix flaky test", Priority::Low, false),
+                ("Rewrite parser", Priority::Backlog, false),
+            ]
+        );
+        assert_eq!(items[0].depends_on, vec!["line 4", "line 6"]);
+        assert_eq!(items[1].description, "Mention the new import command.");
+        assert!(items[3].depends_on.is_empty());
+    }
+
+    #[test]
+    fn test_parse_todo_md_without_checkboxes() {
+        let items = parse_todo_md("# Urgent\n\n1. Fix login\n2. Fix logout !low\n");
+        let summary: Vec<(&str, Priority)> =
+            items.iter().map(|i| (i.title.as_str(), i.priority)).collect();
+        assert_eq!(summary, vec![("Fix login", Priority::Critical), ("Fix logout", Priority::Low)]);
+    }
+
+    #[test]
+    fn test_import_github_json() {
+        let store = InMemoryTaskStore::new();
+        let content = r#"[
+            {"number": 1, "title": "Add OAuth", "body": "Depends on #2", "state": "OPEN",
+             "labels": [{"name": "priority: high"}, {"name": "enhancement"}]},
+            {"number": 2, "title": "Upgrade HTTP client", "body": null, "state": "OPEN", "labels": []},
+            {"number": 3, "title": "Old bug", "body": "", "state": "CLOSED", "labels": []},
+            {"number": 4, "title": "A PR", "state": "open", "pull_request": {}}
+        ]"#;
+
+        let report = import(&store, ImportFormat::GithubJson, content).unwrap();
+        assert_eq!(
+            report,
+            ImportReport { created: 2, skipped: 1, dependencies_added: 1, errors: vec![] }
+        );
+        let tasks = tasks_by_title(&store);
+        assert_eq!(tasks[0].title, "Add OAuth");
+        assert_eq!(tasks[0].priority, Priority::High);
+        assert_eq!(tasks[0].description, "Depends on #2\n\nImported from GitHub issue #1.");
+        assert_eq!(store.get_dependencies(&tasks[0].id).unwrap(), vec![tasks[1].id.clone()]);
+
+        // Importing again creates nothing new
+        let report = import(&store, ImportFormat::GithubJson, content).unwrap();
+        assert_eq!((report.created, report.skipped), (0, 3));
+    }
+
+    #[test]
+    fn test_import_beads() {
+        let store = InMemoryTaskStore::new();
+        let content = r#"
+{"id": "bd-1", "title": "Design schema", "status": "open", "priority": 0, "issue_type": "task"}
+{"id": "bd-2", "title": "Build API", "description": "REST", "status": "in_progress", "priority": 3, "dependencies": [{"issue_id": "bd-2", "depends_on_id": "bd-1", "type": "blocks"}, {"issue_id": "bd-2", "depends_on_id": "bd-3", "type": "related"}]}
+{"id": "bd-3", "title": "Spike", "status": "closed"}
+"#;
+
+        let report = import(&store, ImportFormat::Beads, content).unwrap();
+        assert_eq!(
+            report,
+            ImportReport { created: 2, skipped: 1, dependencies_added: 1, errors: vec![] }
+        );
+        let tasks = tasks_by_title(&store);
+        assert_eq!(tasks[0].title, "Build API");
+        assert_eq!(tasks[0].priority, Priority::Low);
+        assert_eq!(beads_sync::extract_beads_id(&tasks[0].description), Some("bd-2"));
+        assert_eq!(tasks[1].title, "[task] Design schema");
+        assert_eq!(tasks[1].priority, Priority::Critical);
+
+        let array = r#"[{"id": "bd-1", "title": "Design schema", "type": "task"}]"#;
+        let report = import(&store, ImportFormat::Beads, array).unwrap();
+        assert_eq!((report.created, report.skipped), (0, 1));
+    }
+
+    #[test]
+    fn test_import_rejects_bad_json() {
+        let store = InMemoryTaskStore::new();
+        assert!(import(&store, ImportFormat::GithubJson, "not json").is_err());
+        assert!(import(&store, ImportFormat::Beads, "{\"id\": 1}").is_err());
+    }
+}
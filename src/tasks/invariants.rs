@@ -0,0 +1,412 @@
+//! Consistency checks for task stores.
+//!
+//! [`check_invariants`] inspects a store through the [`TaskStore`] trait and
+//! reports the first rule it finds broken. The property tests in this module
+//! drive random operation sequences through both the `SQLite` and in-memory
+//! stores, checking the invariants and that the two stores agree.
+
+use crate::tasks::models::Status;
+use crate::tasks::store::{TaskFilter, TaskStore};
+use std::collections::{HashMap, HashSet};
+
+/// Maximum number of tasks `get_incomplete_requested_work` returns.
+const MAX_REQUESTED_WORK: usize = 5;
+
+/// Check that a task store is internally consistent.
+///
+/// The rules checked are:
+/// - dependencies refer to existing tasks, and no task depends on itself
+/// - the dependency graph has no cycles
+/// - a task is ready exactly when it is open with no incomplete dependencies
+///   and no unanswered blocking questions
+/// - requested work is unblocked and unfinished, comes from requested tasks or
+///   their transitive dependencies, and is capped in size
+/// - in-progress reporting agrees with the tasks' flags
+/// - question-blocked tasks are open with an unanswered linked question
+///
+/// # Errors
+///
+/// Returns a description of the first broken rule, or of a store error.
+pub fn check_invariants<S: TaskStore + ?Sized>(store: &S) -> Result<(), String> {
+    let err = |e: crate::error::Error| e.to_string();
+    let tasks = store.list_tasks(TaskFilter::default()).map_err(err)?;
+    let by_id: HashMap<&str, &crate::tasks::Task> =
+        tasks.iter().map(|t| (t.id.as_str(), t)).collect();
+
+    let mut graph: HashMap<&str, Vec<String>> = HashMap::new();
+    for task in &tasks {
+        let deps = store.get_dependencies(&task.id).map_err(err)?;
+        for dep in &deps {
+            if dep == &task.id {
+                return Err(format!("{} depends on itself", task.id));
+            }
+            if !by_id.contains_key(dep.as_str()) {
+                return Err(format!("{} depends on missing task {dep}", task.id));
+            }
+            if !store.get_dependents(dep).map_err(err)?.contains(&task.id) {
+                return Err(format!("{dep} does not list dependent {}", task.id));
+            }
+        }
+        graph.insert(&task.id, deps);
+    }
+    if let Some(id) = find_cycle(&graph) {
+        return Err(format!("dependency cycle through {id}"));
+    }
+
+    let ready: HashSet<String> =
+        store.get_ready_tasks().map_err(err)?.into_iter().map(|t| t.id).collect();
+    let mut unblocked = HashSet::new();
+    for task in &tasks {
+        let incomplete_deps =
+            graph[task.id.as_str()].iter().any(|d| !by_id[d.as_str()].is_closed());
+        let blocking = !store.get_blocking_questions(&task.id).map_err(err)?.is_empty();
+        if !incomplete_deps && !blocking {
+            unblocked.insert(task.id.as_str());
+        }
+        let expected = task.status == Status::Open && !incomplete_deps && !blocking;
+        if ready.contains(&task.id) != expected {
+            return Err(format!("{} ready = {}, expected {expected}", task.id, !expected));
+        }
+    }
+
+    let work = store.get_incomplete_requested_work().map_err(err)?;
+    if work.len() > MAX_REQUESTED_WORK {
+        return Err(format!("{} requested work items", work.len()));
+    }
+    let unfinished = |t: &crate::tasks::Task| !t.is_closed() && !t.status.is_waiting();
+    let requested: Vec<String> =
+        tasks.iter().filter(|t| t.requested && unfinished(t)).map(|t| t.id.clone()).collect();
+    let reachable = reachable_from(&graph, &requested);
+    for task in &work {
+        if !unfinished(task) || !unblocked.contains(task.id.as_str()) {
+            return Err(format!("requested work {} cannot be started", task.id));
+        }
+        if !task.requested && !reachable.contains(task.id.as_str()) {
+            return Err(format!("{} is neither requested nor a requested dependency", task.id));
+        }
+    }
+    let startable = tasks.iter().any(|t| {
+        unfinished(t)
+            && unblocked.contains(t.id.as_str())
+            && (requested.contains(&t.id) || reachable.contains(t.id.as_str()))
+    });
+    if work.is_empty() && startable {
+        return Err("requested work can be started but none was reported".to_string());
+    }
+
+    let in_progress = store.get_in_progress_tasks().map_err(err)?;
+    if store.has_in_progress_task().map_err(err)? == in_progress.is_empty() {
+        return Err("has_in_progress_task disagrees with get_in_progress_tasks".to_string());
+    }
+    let flagged = tasks.iter().filter(|t| t.in_progress).count();
+    if flagged != in_progress.len() {
+        return Err(format!("{flagged} tasks in progress, {} reported", in_progress.len()));
+    }
+
+    for task in store.get_question_blocked_tasks().map_err(err)? {
+        if task.status != Status::Open
+            || store.get_blocking_questions(&task.id).map_err(err)?.is_empty()
+        {
+            return Err(format!("{} is not blocked by a question", task.id));
+        }
+    }
+    Ok(())
+}
+
+/// Find a task on a dependency cycle, if there is one.
+fn find_cycle<'a>(graph: &HashMap<&'a str, Vec<String>>) -> Option<&'a str> {
+    // Kahn's algorithm: whatever cannot be peeled off lies on or behind a cycle.
+    let mut remaining: HashMap<&str, usize> =
+        graph.iter().map(|(id, deps)| (*id, deps.len())).collect();
+    let mut dependents: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (id, deps) in graph {
+        for dep in deps {
+            dependents.entry(dep.as_str()).or_default().push(id);
+        }
+    }
+    let mut queue: Vec<&str> =
+        remaining.iter().filter(|(_, count)| **count == 0).map(|(id, _)| *id).collect();
+    while let Some(id) = queue.pop() {
+        for dependent in dependents.get(id).into_iter().flatten() {
+            let count = remaining.get_mut(dependent)?;
+            *count -= 1;
+            if *count == 0 {
+                queue.push(dependent);
+            }
+        }
+    }
+    graph.keys().copied().find(|id| remaining[id] > 0)
+}
+
+/// Collect every task reachable through dependencies from `roots`.
+fn reachable_from<'a>(graph: &'a HashMap<&str, Vec<String>>, roots: &[String]) -> HashSet<&'a str> {
+    let mut seen = HashSet::new();
+    let mut stack: Vec<&str> =
+        roots.iter().filter_map(|r| graph.get_key_value(r.as_str()).map(|(k, _)| *k)).collect();
+    while let Some(id) = stack.pop() {
+        for dep in &graph[id] {
+            if seen.insert(dep.as_str()) {
+                stack.push(dep);
+            }
+        }
+    }
+    seen
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tasks::{InMemoryTaskStore, Priority, SqliteTaskStore, Task, TaskUpdate};
+    use proptest::prelude::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_find_cycle() {
+        let mut graph: HashMap<&str, Vec<String>> = HashMap::new();
+        graph.insert("a", vec!["b".to_string()]);
+        graph.insert("b", vec![]);
+        assert_eq!(find_cycle(&graph), None);
+        graph.insert("b", vec!["a".to_string()]);
+        assert!(find_cycle(&graph).is_some());
+    }
+
+    #[test]
+    fn test_check_invariants_accepts_consistent_store() {
+        let dir = TempDir::new().unwrap();
+        let store = SqliteTaskStore::new(dir.path().join("tasks.db")).unwrap();
+        let a = store.create_task("A", "", Priority::Medium).unwrap();
+        let b = store.create_task("B", "", Priority::Medium).unwrap();
+        store.add_dependency(&a.id, &b.id).unwrap();
+        let question = store.create_question("Which one?").unwrap();
+        store.link_task_to_question(&b.id, &question.id).unwrap();
+        store.request_tasks(&[&a.id]).unwrap();
+        assert_eq!(check_invariants(&store), Ok(()));
+        assert!(store.get_incomplete_requested_work().unwrap().is_empty());
+    }
+
+    /// An operation on the store, referring to tasks and questions by index.
+    #[derive(Debug, Clone)]
+    enum Op {
+        Create(Priority),
+        SetStatus(usize, Status),
+        SetInProgress(usize, bool),
+        Delete(usize),
+        AddDependency(usize, usize),
+        RemoveDependency(usize, usize),
+        CreateQuestion,
+        Answer(usize),
+        LinkQuestion(usize, usize),
+        Request(usize),
+        RequestAll,
+        ClearRequestMode,
+    }
+
+    fn priority() -> impl Strategy<Value = Priority> {
+        (0u8..5).prop_map(|p| Priority::from_u8(p).unwrap())
+    }
+
+    fn status() -> impl Strategy<Value = Status> {
+        prop_oneof![
+            Just(Status::Open),
+            Just(Status::Complete),
+            Just(Status::Abandoned),
+            Just(Status::Stuck),
+            Just(Status::Blocked),
+            Just(Status::InReview),
+            Just(Status::BlockedExternal),
+        ]
+    }
+
+    fn op() -> impl Strategy<Value = Op> {
+        let i = 0usize..8;
+        prop_oneof![
+            3 => priority().prop_map(Op::Create),
+            2 => (i.clone(), status()).prop_map(|(t, s)| Op::SetStatus(t, s)),
+            1 => (i.clone(), any::<bool>()).prop_map(|(t, b)| Op::SetInProgress(t, b)),
+            1 => i.clone().prop_map(Op::Delete),
+            3 => (i.clone(), i.clone()).prop_map(|(a, b)| Op::AddDependency(a, b)),
+            1 => (i.clone(), i.clone()).prop_map(|(a, b)| Op::RemoveDependency(a, b)),
+            1 => Just(Op::CreateQuestion),
+            1 => i.clone().prop_map(Op::Answer),
+            1 => (i.clone(), i.clone()).prop_map(|(t, q)| Op::LinkQuestion(t, q)),
+            1 => i.prop_map(Op::Request),
+            1 => Just(Op::RequestAll),
+            1 => Just(Op::ClearRequestMode),
+        ]
+    }
+
+    /// A store under test, with the IDs it assigned, in creation order.
+    struct Subject<'a> {
+        store: &'a dyn TaskStore,
+        tasks: Vec<String>,
+        questions: Vec<String>,
+    }
+
+    impl Subject<'_> {
+        fn task(&self, index: usize) -> String {
+            // Indices past the end name a task that does not exist.
+            self.tasks.get(index).cloned().unwrap_or_else(|| format!("missing-{index}"))
+        }
+
+        fn question(&self, index: usize) -> String {
+            self.questions.get(index).cloned().unwrap_or_else(|| format!("missing-{index}"))
+        }
+
+        fn get(&self, index: usize) -> Option<Task> {
+            self.store.get_task(&self.task(index)).unwrap()
+        }
+
+        /// Apply an operation, returning whether it succeeded.
+        fn apply(&mut self, op: &Op) -> bool {
+            let store = self.store;
+            match *op {
+                Op::Create(priority) => {
+                    let task = store.create_task("Task", "", priority).unwrap();
+                    self.tasks.push(task.id);
+                    true
+                }
+                // Closed tasks refuse to go straight into review or waiting
+                Op::SetStatus(t, status) => matches!(
+                    store.update_task(
+                        &self.task(t),
+                        TaskUpdate { status: Some(status), ..Default::default() },
+                    ),
+                    Ok(Some(_))
+                ),
+                Op::SetInProgress(t, flag) => store
+                    .update_task(
+                        &self.task(t),
+                        TaskUpdate { in_progress: Some(flag), ..Default::default() },
+                    )
+                    .unwrap()
+                    .is_some(),
+                Op::Delete(t) => store.delete_task(&self.task(t)).unwrap(),
+                Op::AddDependency(a, b) => {
+                    store.add_dependency(&self.task(a), &self.task(b)).is_ok()
+                }
+                Op::RemoveDependency(a, b) => {
+                    store.remove_dependency(&self.task(a), &self.task(b)).unwrap()
+                }
+                Op::CreateQuestion => {
+                    let question = store.create_question("Why?").unwrap();
+                    self.questions.push(question.id);
+                    true
+                }
+                Op::Answer(q) => {
+                    store.answer_question(&self.question(q), "Because").unwrap().is_some()
+                }
+                Op::LinkQuestion(t, q) => {
+                    store.link_task_to_question(&self.task(t), &self.question(q)).is_ok()
+                }
+                Op::Request(t) => store.request_tasks(&[&self.task(t)]).unwrap() == 1,
+                Op::RequestAll => {
+                    store.request_all_open().unwrap();
+                    true
+                }
+                Op::ClearRequestMode => {
+                    store.clear_request_mode().unwrap();
+                    true
+                }
+            }
+        }
+
+        /// Describe the store's state in terms of task indices.
+        fn snapshot(&self) -> Vec<String> {
+            let index: HashMap<&str, usize> =
+                self.tasks.iter().enumerate().map(|(i, id)| (id.as_str(), i)).collect();
+            let ids: HashSet<String> =
+                self.store.get_ready_tasks().unwrap().into_iter().map(|t| t.id).collect();
+            let mut lines =
+                vec![format!("request mode {}", self.store.is_request_mode_active().unwrap())];
+            for (i, id) in self.tasks.iter().enumerate() {
+                let Some(task) = self.store.get_task(id).unwrap() else {
+                    lines.push(format!("{i}: deleted"));
+                    continue;
+                };
+                let mut deps: Vec<usize> = self
+                    .store
+                    .get_dependencies(id)
+                    .unwrap()
+                    .iter()
+                    .map(|d| index[d.as_str()])
+                    .collect();
+                deps.sort_unstable();
+                lines.push(format!(
+                    "{i}: {} {:?} in_progress={} requested={} ready={} deps={deps:?}",
+                    task.status,
+                    task.priority,
+                    task.in_progress,
+                    task.requested,
+                    ids.contains(id),
+                ));
+            }
+            lines
+        }
+    }
+
+    /// Check the postconditions an operation promises.
+    fn check_transition(subject: &Subject<'_>, op: &Op, ok: bool, request_mode: bool) {
+        match *op {
+            Op::Create(_) => {
+                let task = subject.get(subject.tasks.len() - 1).unwrap();
+                assert_eq!(task.requested, request_mode, "new task requested in request mode");
+                assert_eq!(task.status, Status::Open);
+            }
+            Op::SetStatus(t, status) if ok => {
+                let task = subject.get(t).unwrap();
+                if matches!(status, Status::Complete | Status::Abandoned) {
+                    assert!(!task.in_progress, "closing a task clears in_progress");
+                    for dependent in subject.store.get_dependents(&task.id).unwrap() {
+                        let unblocked = subject
+                            .store
+                            .get_dependencies(&dependent)
+                            .unwrap()
+                            .iter()
+                            .all(|d| subject.store.get_task(d).unwrap().unwrap().is_closed());
+                        let dependent = subject.store.get_task(&dependent).unwrap().unwrap();
+                        if unblocked {
+                            assert_ne!(dependent.status, Status::Blocked, "{task:?} closed");
+                        }
+                    }
+                }
+            }
+            Op::AddDependency(a, b) if ok => {
+                let (task, dep) = (subject.get(a).unwrap(), subject.get(b).unwrap());
+                if !dep.is_closed() {
+                    assert_ne!(task.status, Status::Open, "dependency on open task blocks");
+                }
+            }
+            _ => {}
+        }
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(32))]
+
+        #[test]
+        fn prop_stores_agree_and_stay_consistent(ops in prop::collection::vec(op(), 1..40)) {
+            let dir = TempDir::new().unwrap();
+            let sqlite = SqliteTaskStore::new(dir.path().join("tasks.db")).unwrap();
+            let memory = InMemoryTaskStore::new();
+            let mut subjects = [
+                Subject { store: &sqlite, tasks: vec![], questions: vec![] },
+                Subject { store: &memory, tasks: vec![], questions: vec![] },
+            ];
+
+            for op in &ops {
+                let mut results = vec![];
+                for subject in &mut subjects {
+                    let request_mode = subject.store.is_request_mode_active().unwrap();
+                    let ok = subject.apply(op);
+                    check_transition(subject, op, ok, request_mode);
+                    if let Err(e) = check_invariants(subject.store) {
+                        prop_assert!(false, "{e} after {op:?}");
+                    }
+                    results.push(ok);
+                }
+                prop_assert_eq!(results[0], results[1], "{:?}", op);
+                prop_assert_eq!(subjects[0].snapshot(), subjects[1].snapshot(), "{:?}", op);
+            }
+        }
+    }
+}
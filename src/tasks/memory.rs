@@ -0,0 +1,1104 @@
+//! In-memory task store.
+//!
+//! `InMemoryTaskStore` implements [`TaskStore`] with the same semantics as
+//! [`SqliteTaskStore`](super::SqliteTaskStore) but keeps everything in memory.
+//! It is much faster to set up than a database, which makes it useful for
+//! hook unit tests, and serves as a model to check the `SQLite` store against.
+//!
+//! Full-text search is approximated: every word of the query must match a
+//! word of the searched text (as a prefix, for how-tos), ignoring case.
+
+use crate::error::{Error, Result};
+use crate::tasks::id::generate_task_id;
+use crate::tasks::models::{
+    AnswerSuggestion, AuditEntry, HowTo, Note, Priority, Question, Status, Task, UserMessage,
+};
+use crate::tasks::store::{
+    now_timestamp, CircularDependency, HowToNotFound, HowToUpdate, InvalidTaskChange,
+    QuestionNotFound, TaskFilter, TaskNotFound, TaskStore, TaskUpdate,
+};
+use std::collections::{BTreeSet, HashSet};
+use std::sync::{Mutex, MutexGuard};
+
+/// The contents of an in-memory store.
+#[derive(Debug, Default)]
+struct State {
+    /// Tasks in creation order.
+    tasks: Vec<Task>,
+    /// `(task_id, depends_on)` pairs.
+    dependencies: BTreeSet<(String, String)>,
+    notes: Vec<Note>,
+    next_note_id: i64,
+    howtos: Vec<HowTo>,
+    /// `(task_id, howto_id)` pairs.
+    guidance: BTreeSet<(String, String)>,
+    questions: Vec<Question>,
+    /// `(task_id, question_id)` pairs.
+    task_questions: BTreeSet<(String, String)>,
+    audit_log: Vec<AuditEntry>,
+    request_mode_active: bool,
+    user_messages: Vec<UserMessage>,
+}
+
+impl State {
+    fn task(&self, id: &str) -> Option<&Task> {
+        self.tasks.iter().find(|t| t.id == id)
+    }
+
+    fn task_mut(&mut self, id: &str) -> Option<&mut Task> {
+        self.tasks.iter_mut().find(|t| t.id == id)
+    }
+
+    fn require_task(&self, id: &str) -> Result<()> {
+        if self.task(id).is_none() {
+            return Err(Error::Task(Box::new(TaskNotFound(id.to_string()))));
+        }
+        Ok(())
+    }
+
+    fn log(
+        &mut self,
+        operation: &str,
+        task_id: Option<&str>,
+        old_value: Option<String>,
+        new_value: Option<String>,
+        details: Option<String>,
+    ) {
+        let id = self.audit_log.last().map_or(1, |e| e.id + 1);
+        self.audit_log.push(AuditEntry {
+            id,
+            timestamp: now_timestamp(),
+            operation: operation.to_string(),
+            task_id: task_id.map(str::to_string),
+            old_value,
+            new_value,
+            details,
+        });
+    }
+
+    fn dependencies_of(&self, task_id: &str) -> Vec<String> {
+        self.dependencies.iter().filter(|(t, _)| t == task_id).map(|(_, d)| d.clone()).collect()
+    }
+
+    fn dependents_of(&self, task_id: &str) -> Vec<String> {
+        let mut dependents: Vec<String> = self
+            .dependencies
+            .iter()
+            .filter(|(_, d)| d == task_id)
+            .map(|(t, _)| t.clone())
+            .collect();
+        dependents.sort();
+        dependents
+    }
+
+    /// Count the incomplete tasks a task depends on.
+    fn incomplete_dependency_count(&self, task_id: &str) -> usize {
+        self.dependencies_of(task_id)
+            .iter()
+            .filter_map(|d| self.task(d))
+            .filter(|d| !d.is_closed())
+            .count()
+    }
+
+    /// Count the unanswered questions blocking a task.
+    fn unanswered_question_count(&self, task_id: &str) -> usize {
+        self.task_questions
+            .iter()
+            .filter(|(t, _)| t == task_id)
+            .filter_map(|(_, q)| self.questions.iter().find(|question| question.id == *q))
+            .filter(|q| q.answer.is_none())
+            .count()
+    }
+
+    fn is_ready(&self, task_id: &str) -> bool {
+        self.incomplete_dependency_count(task_id) == 0
+            && self.unanswered_question_count(task_id) == 0
+    }
+
+    fn would_create_cycle(&self, task_id: &str, depends_on: &str) -> bool {
+        let mut visited = HashSet::new();
+        let mut stack = vec![depends_on.to_string()];
+        while let Some(current) = stack.pop() {
+            if current == task_id {
+                return true;
+            }
+            if visited.insert(current.clone()) {
+                stack.extend(self.dependencies_of(&current));
+            }
+        }
+        false
+    }
+
+    /// Move a task between open and blocked to match its dependencies.
+    fn update_blocked_status(&mut self, task_id: &str) {
+        let should_be_blocked = self.incomplete_dependency_count(task_id) > 0;
+        if let Some(task) = self.task_mut(task_id) {
+            match task.status {
+                Status::Open if should_be_blocked => task.status = Status::Blocked,
+                Status::Blocked if !should_be_blocked => task.status = Status::Open,
+                _ => return,
+            }
+            task.updated_at = now_timestamp();
+        }
+    }
+
+    fn update_dependents_blocked_status(&mut self, task_id: &str) {
+        for dependent in self.dependents_of(task_id) {
+            self.update_blocked_status(&dependent);
+        }
+    }
+
+    fn transitive_dependencies(&self, task_ids: &[String]) -> HashSet<String> {
+        let mut all = HashSet::new();
+        let mut to_process = task_ids.to_vec();
+        while let Some(task_id) = to_process.pop() {
+            for dep in self.dependencies_of(&task_id) {
+                if all.insert(dep.clone()) {
+                    to_process.push(dep);
+                }
+            }
+        }
+        all
+    }
+}
+
+/// Check whether every word of `query` matches a word of one of `texts`.
+fn text_matches(query: &str, texts: &[&str], prefix: bool) -> bool {
+    let words: Vec<String> = texts
+        .iter()
+        .flat_map(|t| t.split(|c: char| !c.is_alphanumeric()))
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    let terms: Vec<String> = query
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    !terms.is_empty()
+        && terms.iter().all(|term| {
+            words.iter().any(|w| if prefix { w.starts_with(term.as_str()) } else { w == term })
+        })
+}
+
+/// Whether a task still needs work from the agent: not closed or waiting on someone else.
+const fn is_unfinished(task: &Task) -> bool {
+    !task.is_closed() && !task.status.is_waiting()
+}
+
+/// Sort key position of a status in task listings.
+const fn status_rank(status: Status) -> u8 {
+    match status {
+        Status::Open => 0,
+        Status::Stuck => 1,
+        Status::Blocked => 2,
+        Status::BlockedExternal => 3,
+        Status::InReview => 4,
+        Status::Complete => 5,
+        Status::Abandoned => 6,
+    }
+}
+
+/// A task store that keeps everything in memory.
+#[derive(Debug, Default)]
+pub struct InMemoryTaskStore {
+    state: Mutex<State>,
+}
+
+impl InMemoryTaskStore {
+    /// Create an empty store.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+#[allow(clippy::significant_drop_tightening)] // Each operation holds the lock throughout, like a transaction
+impl TaskStore for InMemoryTaskStore {
+    fn create_task(&self, title: &str, description: &str, priority: Priority) -> Result<Task> {
+        let mut state = self.state();
+        let now = now_timestamp();
+        let task = Task {
+            id: generate_task_id(title),
+            title: title.to_string(),
+            description: description.to_string(),
+            priority,
+            status: Status::Open,
+            in_progress: false,
+            requested: state.request_mode_active,
+            created_at: now.clone(),
+            updated_at: now,
+            package: None,
+        };
+        state.tasks.push(task.clone());
+        let json = serde_json::to_string(&task).unwrap_or_default();
+        state.log("create", Some(&task.id), None, Some(json), None);
+        Ok(task)
+    }
+
+    fn get_task(&self, id: &str) -> Result<Option<Task>> {
+        Ok(self.state().task(id).cloned())
+    }
+
+    fn update_task(&self, id: &str, update: TaskUpdate) -> Result<Option<Task>> {
+        let mut state = self.state();
+        let Some(task) = state.task_mut(id) else {
+            return Ok(None);
+        };
+        if update.is_empty() {
+            return Ok(Some(task.clone()));
+        }
+
+        if let Some(status) = update.status {
+            if !task.status.can_transition_to(status) {
+                return Err(Error::Task(Box::new(InvalidTaskChange(format!(
+                    "cannot change {id} from {} to {status}; reopen it first",
+                    task.status
+                )))));
+            }
+        }
+
+        let old = task.clone();
+        if let Some(title) = update.title {
+            task.title = title;
+        }
+        if let Some(description) = update.description {
+            task.description = description;
+        }
+        if let Some(priority) = update.priority {
+            task.priority = priority;
+        }
+        if let Some(status) = update.status {
+            task.status = status;
+            if matches!(status, Status::Complete | Status::Blocked | Status::Abandoned)
+                || status.is_waiting()
+            {
+                task.in_progress = false;
+            }
+        }
+        if let Some(in_progress) = update.in_progress {
+            task.in_progress = in_progress;
+        }
+        if let Some(requested) = update.requested {
+            task.requested = requested;
+        }
+        if let Some(package) = update.package {
+            task.package = (!package.is_empty()).then_some(package);
+        }
+        task.updated_at = now_timestamp();
+        let new = task.clone();
+
+        let old_json = serde_json::to_string(&Some(&old)).unwrap_or_default();
+        let new_json = serde_json::to_string(&new).unwrap_or_default();
+        state.log("update", Some(id), Some(old_json), Some(new_json), None);
+        if update.status.is_some() {
+            state.update_dependents_blocked_status(id);
+        }
+        Ok(Some(new))
+    }
+
+    fn delete_task(&self, id: &str) -> Result<bool> {
+        let mut state = self.state();
+        let Some(task) = state.task(id).cloned() else {
+            return Ok(false);
+        };
+        let dependents = state.dependents_of(id);
+
+        state.tasks.retain(|t| t.id != id);
+        state.dependencies.retain(|(t, d)| t != id && d != id);
+        state.notes.retain(|n| n.task_id != id);
+        state.guidance.retain(|(t, _)| t != id);
+        state.task_questions.retain(|(t, _)| t != id);
+
+        let json = serde_json::to_string(&Some(&task)).unwrap_or_default();
+        state.log("delete", Some(id), Some(json), None, None);
+        for dependent in dependents {
+            state.update_blocked_status(&dependent);
+        }
+        Ok(true)
+    }
+
+    fn list_tasks(&self, filter: TaskFilter) -> Result<Vec<Task>> {
+        let state = self.state();
+        let mut tasks: Vec<&Task> = state
+            .tasks
+            .iter()
+            .filter(|t| filter.status.map_or(true, |s| t.status == s))
+            .filter(|t| filter.priority.map_or(true, |p| t.priority == p))
+            .filter(|t| filter.max_priority.map_or(true, |p| t.priority <= p))
+            .filter(|t| filter.package.is_none() || t.package == filter.package)
+            .filter(|t| filter.waiting.map_or(true, |w| t.status.is_waiting() == w))
+            .filter(|t| !filter.ready_only || (t.status == Status::Open && state.is_ready(&t.id)))
+            .collect();
+        tasks.sort_by_cached_key(|t| {
+            (
+                status_rank(t.status),
+                state.incomplete_dependency_count(&t.id) + state.unanswered_question_count(&t.id),
+                !t.requested,
+                t.priority,
+                std::cmp::Reverse(state.dependents_of(&t.id).len()),
+                t.created_at.clone(),
+            )
+        });
+        Ok(tasks
+            .into_iter()
+            .skip(filter.offset.unwrap_or(0))
+            .take(filter.limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect())
+    }
+
+    fn add_dependency(&self, task_id: &str, depends_on: &str) -> Result<()> {
+        let mut state = self.state();
+        state.require_task(task_id)?;
+        state.require_task(depends_on)?;
+        if state.would_create_cycle(task_id, depends_on) {
+            return Err(Error::Task(Box::new(CircularDependency {
+                task_id: task_id.to_string(),
+                depends_on: depends_on.to_string(),
+            })));
+        }
+        state.dependencies.insert((task_id.to_string(), depends_on.to_string()));
+        state.log(
+            "add_dependency",
+            Some(task_id),
+            None,
+            None,
+            Some(format!("depends_on: {depends_on}")),
+        );
+        state.update_blocked_status(task_id);
+        Ok(())
+    }
+
+    fn remove_dependency(&self, task_id: &str, depends_on: &str) -> Result<bool> {
+        let mut state = self.state();
+        if !state.dependencies.remove(&(task_id.to_string(), depends_on.to_string())) {
+            return Ok(false);
+        }
+        state.log(
+            "remove_dependency",
+            Some(task_id),
+            None,
+            None,
+            Some(format!("removed: {depends_on}")),
+        );
+        state.update_blocked_status(task_id);
+        Ok(true)
+    }
+
+    fn get_dependencies(&self, task_id: &str) -> Result<Vec<String>> {
+        Ok(self.state().dependencies_of(task_id))
+    }
+
+    fn get_dependents(&self, task_id: &str) -> Result<Vec<String>> {
+        Ok(self.state().dependents_of(task_id))
+    }
+
+    fn add_note(&self, task_id: &str, content: &str) -> Result<Note> {
+        let mut state = self.state();
+        state.require_task(task_id)?;
+        state.next_note_id += 1;
+        let note = Note {
+            id: state.next_note_id,
+            task_id: task_id.to_string(),
+            content: content.to_string(),
+            created_at: now_timestamp(),
+        };
+        state.notes.push(note.clone());
+        state.log(
+            "add_note",
+            Some(task_id),
+            None,
+            Some(content.to_string()),
+            Some(format!("note_id: {}", note.id)),
+        );
+        Ok(note)
+    }
+
+    fn get_notes(&self, task_id: &str) -> Result<Vec<Note>> {
+        Ok(self.state().notes.iter().filter(|n| n.task_id == task_id).cloned().collect())
+    }
+
+    fn delete_note(&self, note_id: i64) -> Result<bool> {
+        let mut state = self.state();
+        let Some(index) = state.notes.iter().position(|n| n.id == note_id) else {
+            return Ok(false);
+        };
+        let note = state.notes.remove(index);
+        state.log(
+            "delete_note",
+            Some(&note.task_id),
+            Some(note.content),
+            None,
+            Some(format!("note_id: {note_id}")),
+        );
+        Ok(true)
+    }
+
+    fn search_tasks(&self, query: &str) -> Result<Vec<Task>> {
+        let state = self.state();
+        let mut tasks: Vec<Task> = state
+            .tasks
+            .iter()
+            .filter(|t| {
+                text_matches(query, &[&t.id, &t.title, &t.description], false)
+                    || state
+                        .notes
+                        .iter()
+                        .any(|n| n.task_id == t.id && text_matches(query, &[&n.content], false))
+            })
+            .cloned()
+            .collect();
+        tasks.sort_by(|a, b| {
+            a.priority.cmp(&b.priority).then_with(|| a.created_at.cmp(&b.created_at))
+        });
+        Ok(tasks)
+    }
+
+    fn get_audit_log(
+        &self,
+        task_id: Option<&str>,
+        limit: Option<usize>,
+    ) -> Result<Vec<AuditEntry>> {
+        Ok(self
+            .state()
+            .audit_log
+            .iter()
+            .rev()
+            .filter(|e| task_id.map_or(true, |id| e.task_id.as_deref() == Some(id)))
+            .take(limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect())
+    }
+
+    fn get_ready_tasks(&self) -> Result<Vec<Task>> {
+        self.list_tasks(TaskFilter { ready_only: true, ..Default::default() })
+    }
+
+    fn pick_task(&self) -> Result<Option<Task>> {
+        let ready = self.get_ready_tasks()?;
+        let Some(min_priority) = ready.iter().map(|t| t.priority).min() else {
+            return Ok(None);
+        };
+        let top: Vec<Task> = ready.into_iter().filter(|t| t.priority == min_priority).collect();
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.subsec_nanos());
+        let index = nanos as usize % top.len();
+        Ok(top.into_iter().nth(index))
+    }
+
+    fn create_howto(&self, title: &str, instructions: &str) -> Result<HowTo> {
+        let mut state = self.state();
+        let now = now_timestamp();
+        let howto = HowTo {
+            id: generate_task_id(title),
+            title: title.to_string(),
+            instructions: instructions.to_string(),
+            created_at: now.clone(),
+            updated_at: now,
+        };
+        state.howtos.push(howto.clone());
+        state.log(
+            "create_howto",
+            Some(&howto.id),
+            None,
+            Some(serde_json::to_string(&howto).unwrap_or_default()),
+            Some(format!("Created how-to: {title}")),
+        );
+        Ok(howto)
+    }
+
+    fn get_howto(&self, id: &str) -> Result<Option<HowTo>> {
+        Ok(self.state().howtos.iter().find(|h| h.id == id).cloned())
+    }
+
+    fn update_howto(&self, id: &str, update: HowToUpdate) -> Result<Option<HowTo>> {
+        let mut state = self.state();
+        let Some(howto) = state.howtos.iter_mut().find(|h| h.id == id) else {
+            return Ok(None);
+        };
+        if update.is_empty() {
+            return Ok(Some(howto.clone()));
+        }
+        let old = howto.clone();
+        if let Some(title) = update.title {
+            howto.title = title;
+        }
+        if let Some(instructions) = update.instructions {
+            howto.instructions = instructions;
+        }
+        howto.updated_at = now_timestamp();
+        let new = howto.clone();
+        state.log(
+            "update_howto",
+            Some(id),
+            Some(serde_json::to_string(&Some(&old)).unwrap_or_default()),
+            Some(serde_json::to_string(&new).unwrap_or_default()),
+            None,
+        );
+        Ok(Some(new))
+    }
+
+    fn delete_howto(&self, id: &str) -> Result<bool> {
+        let mut state = self.state();
+        let Some(index) = state.howtos.iter().position(|h| h.id == id) else {
+            return Ok(false);
+        };
+        let old = state.howtos.remove(index);
+        state.guidance.retain(|(_, h)| h != id);
+        state.log(
+            "delete_howto",
+            Some(id),
+            Some(serde_json::to_string(&Some(&old)).unwrap_or_default()),
+            None,
+            None,
+        );
+        Ok(true)
+    }
+
+    fn list_howtos(&self) -> Result<Vec<HowTo>> {
+        let mut howtos = self.state().howtos.clone();
+        howtos.sort_by(|a, b| a.title.cmp(&b.title));
+        Ok(howtos)
+    }
+
+    fn search_howtos(&self, query: &str) -> Result<Vec<HowTo>> {
+        Ok(self
+            .state()
+            .howtos
+            .iter()
+            .filter(|h| text_matches(query, &[&h.id, &h.title, &h.instructions], true))
+            .cloned()
+            .collect())
+    }
+
+    fn link_task_to_howto(&self, task_id: &str, howto_id: &str) -> Result<()> {
+        let mut state = self.state();
+        state.require_task(task_id)?;
+        if !state.howtos.iter().any(|h| h.id == howto_id) {
+            return Err(Error::Task(Box::new(HowToNotFound(howto_id.to_string()))));
+        }
+        state.guidance.insert((task_id.to_string(), howto_id.to_string()));
+        state.log(
+            "link_guidance",
+            Some(task_id),
+            None,
+            None,
+            Some(format!("Linked task {task_id} to how-to {howto_id}")),
+        );
+        Ok(())
+    }
+
+    fn unlink_task_from_howto(&self, task_id: &str, howto_id: &str) -> Result<bool> {
+        let mut state = self.state();
+        if !state.guidance.remove(&(task_id.to_string(), howto_id.to_string())) {
+            return Ok(false);
+        }
+        state.log(
+            "unlink_guidance",
+            Some(task_id),
+            None,
+            None,
+            Some(format!("Unlinked task {task_id} from how-to {howto_id}")),
+        );
+        Ok(true)
+    }
+
+    fn get_task_guidance(&self, task_id: &str) -> Result<Vec<String>> {
+        Ok(self
+            .state()
+            .guidance
+            .iter()
+            .filter(|(t, _)| t == task_id)
+            .map(|(_, h)| h.clone())
+            .collect())
+    }
+
+    fn create_question(&self, text: &str) -> Result<Question> {
+        let mut state = self.state();
+        let question = Question {
+            id: generate_task_id(text),
+            text: text.to_string(),
+            answer: None,
+            created_at: now_timestamp(),
+            answered_at: None,
+            deferred_until: None,
+            priority: Priority::Medium,
+            suggestions: Vec::new(),
+            assumed: false,
+        };
+        state.questions.push(question.clone());
+        state.log("create_question", Some(&question.id), None, None, Some(text.to_string()));
+        Ok(question)
+    }
+
+    fn get_question(&self, id: &str) -> Result<Option<Question>> {
+        Ok(self.state().questions.iter().find(|q| q.id == id).cloned())
+    }
+
+    fn answer_question(&self, id: &str, answer: &str) -> Result<Option<Question>> {
+        let mut state = self.state();
+        let Some(question) = state.questions.iter_mut().find(|q| q.id == id) else {
+            return Ok(None);
+        };
+        question.answer = Some(answer.to_string());
+        question.answered_at = Some(now_timestamp());
+        question.assumed = false;
+        let question = question.clone();
+        state.log("answer_question", Some(id), None, Some(answer.to_string()), None);
+        Ok(Some(question))
+    }
+
+    fn assume_answer(&self, id: &str, answer: &str) -> Result<Option<Question>> {
+        let mut state = self.state();
+        let Some(question) = state.questions.iter_mut().find(|q| q.id == id) else {
+            return Ok(None);
+        };
+        question.answer = Some(answer.to_string());
+        question.answered_at = Some(now_timestamp());
+        question.assumed = true;
+        let question = question.clone();
+        state.log("assume_answer", Some(id), None, Some(answer.to_string()), None);
+        Ok(Some(question))
+    }
+
+    fn reopen_question(&self, id: &str) -> Result<Option<Question>> {
+        let mut state = self.state();
+        let Some(question) = state.questions.iter_mut().find(|q| q.id == id) else {
+            return Ok(None);
+        };
+        let old_answer = question.answer.take();
+        question.answered_at = None;
+        question.assumed = false;
+        let question = question.clone();
+        state.log("reopen_question", Some(id), old_answer, None, None);
+        Ok(Some(question))
+    }
+
+    fn defer_question(&self, id: &str, until: Option<&str>) -> Result<Option<Question>> {
+        let mut state = self.state();
+        let Some(question) = state.questions.iter_mut().find(|q| q.id == id) else {
+            return Ok(None);
+        };
+        question.deferred_until = until.map(str::to_string);
+        let question = question.clone();
+        state.log("defer_question", Some(id), None, until.map(str::to_string), None);
+        Ok(Some(question))
+    }
+
+    fn set_question_priority(&self, id: &str, priority: Priority) -> Result<Option<Question>> {
+        let mut state = self.state();
+        let Some(question) = state.questions.iter_mut().find(|q| q.id == id) else {
+            return Ok(None);
+        };
+        let old_priority = question.priority;
+        question.priority = priority;
+        let question = question.clone();
+        state.log(
+            "set_question_priority",
+            Some(id),
+            Some(old_priority.as_u8().to_string()),
+            Some(priority.as_u8().to_string()),
+            None,
+        );
+        Ok(Some(question))
+    }
+
+    fn set_question_suggestions(
+        &self,
+        id: &str,
+        suggestions: &[AnswerSuggestion],
+    ) -> Result<Option<Question>> {
+        let mut state = self.state();
+        let Some(question) = state.questions.iter_mut().find(|q| q.id == id) else {
+            return Ok(None);
+        };
+        question.suggestions = suggestions.to_vec();
+        let question = question.clone();
+        let json = serde_json::to_string(suggestions)?;
+        state.log("suggest_answers", Some(id), None, Some(json), None);
+        Ok(Some(question))
+    }
+
+    fn delete_question(&self, id: &str) -> Result<bool> {
+        let mut state = self.state();
+        let Some(index) = state.questions.iter().position(|q| q.id == id) else {
+            return Ok(false);
+        };
+        state.questions.remove(index);
+        state.task_questions.retain(|(_, q)| q != id);
+        state.log("delete_question", Some(id), None, None, None);
+        Ok(true)
+    }
+
+    fn list_questions(&self, unanswered_only: bool) -> Result<Vec<Question>> {
+        Ok(self
+            .state()
+            .questions
+            .iter()
+            .filter(|q| !unanswered_only || q.answer.is_none())
+            .cloned()
+            .collect())
+    }
+
+    fn search_questions(&self, query: &str) -> Result<Vec<Question>> {
+        Ok(self
+            .state()
+            .questions
+            .iter()
+            .filter(|q| {
+                text_matches(query, &[&q.text, q.answer.as_deref().unwrap_or_default()], false)
+            })
+            .cloned()
+            .collect())
+    }
+
+    fn link_task_to_question(&self, task_id: &str, question_id: &str) -> Result<()> {
+        let mut state = self.state();
+        state.require_task(task_id)?;
+        if !state.questions.iter().any(|q| q.id == question_id) {
+            return Err(Error::Task(Box::new(QuestionNotFound(question_id.to_string()))));
+        }
+        state.task_questions.insert((task_id.to_string(), question_id.to_string()));
+        Ok(())
+    }
+
+    fn unlink_task_from_question(&self, task_id: &str, question_id: &str) -> Result<bool> {
+        Ok(self.state().task_questions.remove(&(task_id.to_string(), question_id.to_string())))
+    }
+
+    fn get_task_questions(&self, task_id: &str) -> Result<Vec<String>> {
+        Ok(self
+            .state()
+            .task_questions
+            .iter()
+            .filter(|(t, _)| t == task_id)
+            .map(|(_, q)| q.clone())
+            .collect())
+    }
+
+    fn get_blocking_questions(&self, task_id: &str) -> Result<Vec<Question>> {
+        let state = self.state();
+        Ok(state
+            .questions
+            .iter()
+            .filter(|q| q.answer.is_none())
+            .filter(|q| state.task_questions.contains(&(task_id.to_string(), q.id.clone())))
+            .cloned()
+            .collect())
+    }
+
+    fn get_question_blocked_tasks(&self) -> Result<Vec<Task>> {
+        let state = self.state();
+        let mut tasks: Vec<Task> = state
+            .tasks
+            .iter()
+            .filter(|t| t.status == Status::Open)
+            .filter(|t| state.unanswered_question_count(&t.id) > 0)
+            .filter(|t| state.incomplete_dependency_count(&t.id) == 0)
+            .cloned()
+            .collect();
+        tasks.sort_by(|a, b| {
+            a.priority.cmp(&b.priority).then_with(|| a.created_at.cmp(&b.created_at))
+        });
+        Ok(tasks)
+    }
+
+    fn has_in_progress_task(&self) -> Result<bool> {
+        Ok(self.state().tasks.iter().any(|t| t.in_progress))
+    }
+
+    fn get_in_progress_tasks(&self) -> Result<Vec<Task>> {
+        let mut tasks: Vec<Task> =
+            self.state().tasks.iter().filter(|t| t.in_progress).cloned().collect();
+        tasks.sort_by(|a, b| {
+            a.priority.cmp(&b.priority).then_with(|| a.created_at.cmp(&b.created_at))
+        });
+        Ok(tasks)
+    }
+
+    fn request_tasks(&self, task_ids: &[&str]) -> Result<usize> {
+        let mut state = self.state();
+        let now = now_timestamp();
+        let mut updated = 0;
+        for task in state.tasks.iter_mut().filter(|t| task_ids.contains(&t.id.as_str())) {
+            task.requested = true;
+            task.updated_at.clone_from(&now);
+            updated += 1;
+        }
+        for task_id in task_ids {
+            state.log(
+                "request",
+                Some(task_id),
+                None,
+                None,
+                Some("marked as requested".to_string()),
+            );
+        }
+        Ok(updated)
+    }
+
+    fn request_all_open(&self) -> Result<usize> {
+        let mut state = self.state();
+        let now = now_timestamp();
+        let mut updated = 0;
+        for task in state
+            .tasks
+            .iter_mut()
+            .filter(|t| matches!(t.status, Status::Open | Status::Stuck | Status::Blocked))
+        {
+            task.requested = true;
+            task.updated_at.clone_from(&now);
+            updated += 1;
+        }
+        state.request_mode_active = true;
+        state.log("request_all", None, None, None, Some("enabled request mode".to_string()));
+        Ok(updated)
+    }
+
+    fn is_request_mode_active(&self) -> Result<bool> {
+        Ok(self.state().request_mode_active)
+    }
+
+    fn clear_request_mode(&self) -> Result<()> {
+        let mut state = self.state();
+        state.request_mode_active = false;
+        state.log(
+            "clear_request_mode",
+            None,
+            None,
+            None,
+            Some("disabled request mode".to_string()),
+        );
+        Ok(())
+    }
+
+    fn get_incomplete_requested_work(&self) -> Result<Vec<Task>> {
+        let state = self.state();
+        let mut work: Vec<Task> =
+            state.tasks.iter().filter(|t| t.requested && is_unfinished(t)).cloned().collect();
+        if work.is_empty() {
+            return Ok(vec![]);
+        }
+        work.sort_by(|a, b| {
+            a.priority.cmp(&b.priority).then_with(|| a.created_at.cmp(&b.created_at))
+        });
+
+        let ids: Vec<String> = work.iter().map(|t| t.id.clone()).collect();
+        for dep_id in state.transitive_dependencies(&ids) {
+            if let Some(task) = state.task(&dep_id) {
+                if is_unfinished(task) && !work.iter().any(|t| t.id == task.id) {
+                    work.push(task.clone());
+                }
+            }
+        }
+
+        let mut result: Vec<Task> = work.into_iter().filter(|t| state.is_ready(&t.id)).collect();
+        result.sort_by_cached_key(|t| {
+            (t.priority, std::cmp::Reverse(state.dependents_of(&t.id).len()))
+        });
+        result.truncate(5);
+        Ok(result)
+    }
+
+    fn record_user_message(
+        &self,
+        message: &str,
+        context: &str,
+        transcript_path: Option<&str>,
+        session_id: &str,
+    ) -> Result<()> {
+        let mut state = self.state();
+        let id = state.user_messages.last().map_or(1, |m| m.id + 1);
+        state.user_messages.push(UserMessage {
+            id,
+            message: message.to_string(),
+            context: context.to_string(),
+            transcript_path: transcript_path.map(str::to_string),
+            session_id: session_id.to_string(),
+            pre_compaction: false,
+            created_at: now_timestamp(),
+        });
+        Ok(())
+    }
+
+    fn get_session_user_messages(&self, session_id: &str) -> Result<Vec<UserMessage>> {
+        Ok(self
+            .state()
+            .user_messages
+            .iter()
+            .filter(|m| m.session_id == session_id)
+            .cloned()
+            .collect())
+    }
+
+    fn clear_user_messages_for_session(&self, session_id: &str) -> Result<()> {
+        self.state().user_messages.retain(|m| m.session_id != session_id);
+        Ok(())
+    }
+
+    fn mark_pre_compaction(&self, session_id: &str) -> Result<()> {
+        for message in self.state().user_messages.iter_mut().filter(|m| m.session_id == session_id)
+        {
+            message.pre_compaction = true;
+        }
+        Ok(())
+    }
+
+    fn scrub_user_messages(&self) -> Result<usize> {
+        let mut changed = 0;
+        for message in &mut self.state().user_messages {
+            let redacted_message = crate::secrets::redact(&message.message).into_owned();
+            let redacted_context = crate::secrets::redact(&message.context).into_owned();
+            if redacted_message != message.message || redacted_context != message.context {
+                message.message = redacted_message;
+                message.context = redacted_context;
+                changed += 1;
+            }
+        }
+        Ok(changed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_task_crud() {
+        let store = InMemoryTaskStore::new();
+        let task = store.create_task("Fix login", "OAuth fails", Priority::High).unwrap();
+        assert_eq!(store.get_task(&task.id).unwrap().unwrap(), task);
+
+        let updated = store
+            .update_task(
+                &task.id,
+                TaskUpdate {
+                    status: Some(Status::Complete),
+                    in_progress: None,
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(updated.status, Status::Complete);
+        assert!(store.delete_task(&task.id).unwrap());
+        assert!(store.get_task(&task.id).unwrap().is_none());
+        assert!(!store.delete_task(&task.id).unwrap());
+
+        let ops: Vec<String> =
+            store.get_audit_log(None, None).unwrap().into_iter().map(|e| e.operation).collect();
+        assert_eq!(ops, vec!["delete", "update", "create"]);
+    }
+
+    #[test]
+    fn test_package_filter() {
+        let store = InMemoryTaskStore::new();
+        let api = store.create_task("API", "", Priority::Medium).unwrap();
+        store.create_task("Docs", "", Priority::Medium).unwrap();
+        let update = TaskUpdate { package: Some("api".to_string()), ..Default::default() };
+        store.update_task(&api.id, update).unwrap();
+
+        let filter = TaskFilter { package: Some("api".to_string()), ..Default::default() };
+        let tasks = store.list_tasks(filter).unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].package.as_deref(), Some("api"));
+
+        let update = TaskUpdate { package: Some(String::new()), ..Default::default() };
+        assert_eq!(store.update_task(&api.id, update).unwrap().unwrap().package, None);
+    }
+
+    #[test]
+    fn test_dependencies_block_and_unblock() {
+        let store = InMemoryTaskStore::new();
+        let a = store.create_task("A", "", Priority::Medium).unwrap();
+        let b = store.create_task("B", "", Priority::Medium).unwrap();
+
+        store.add_dependency(&a.id, &b.id).unwrap();
+        assert_eq!(store.get_task(&a.id).unwrap().unwrap().status, Status::Blocked);
+        assert!(store.add_dependency(&b.id, &a.id).is_err());
+        assert_eq!(store.get_ready_tasks().unwrap(), vec![store.get_task(&b.id).unwrap().unwrap()]);
+
+        store
+            .update_task(&b.id, TaskUpdate { status: Some(Status::Complete), ..Default::default() })
+            .unwrap();
+        assert_eq!(store.get_task(&a.id).unwrap().unwrap().status, Status::Open);
+    }
+
+    #[test]
+    fn test_questions_block_tasks() {
+        let store = InMemoryTaskStore::new();
+        let task = store.create_task("Deploy", "", Priority::High).unwrap();
+        let question = store.create_question("Which region?").unwrap();
+        store.link_task_to_question(&task.id, &question.id).unwrap();
+
+        assert!(store.get_ready_tasks().unwrap().is_empty());
+        assert_eq!(store.get_question_blocked_tasks().unwrap().len(), 1);
+        store.answer_question(&question.id, "eu-west").unwrap();
+        assert_eq!(store.get_ready_tasks().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_request_mode() {
+        let store = InMemoryTaskStore::new();
+        store.create_task("Existing", "", Priority::Medium).unwrap();
+        assert_eq!(store.request_all_open().unwrap(), 1);
+        assert!(store.create_task("New", "", Priority::Medium).unwrap().requested);
+        assert_eq!(store.get_incomplete_requested_work().unwrap().len(), 2);
+
+        store.clear_request_mode().unwrap();
+        assert!(!store.create_task("Later", "", Priority::Medium).unwrap().requested);
+    }
+
+    #[test]
+    fn test_search() {
+        let store = InMemoryTaskStore::new();
+        let task = store.create_task("Fix login bug", "", Priority::Medium).unwrap();
+        store.add_note(&task.id, "Caused by token expiry").unwrap();
+        store.create_howto("Deploying", "Run the deploy script").unwrap();
+
+        assert_eq!(store.search_tasks("login").unwrap().len(), 1);
+        assert_eq!(store.search_tasks("TOKEN").unwrap().len(), 1);
+        assert!(store.search_tasks("log").unwrap().is_empty());
+        assert_eq!(store.search_howtos("depl").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_waiting_statuses() {
+        let store = InMemoryTaskStore::new();
+        let task = store.create_task("Pushed", "", Priority::Medium).unwrap();
+        let set = |status| {
+            store.update_task(&task.id, TaskUpdate { status: Some(status), ..Default::default() })
+        };
+
+        set(Status::InReview).unwrap();
+        let waiting = TaskFilter { waiting: Some(true), ..Default::default() };
+        assert_eq!(store.list_tasks(waiting).unwrap().len(), 1);
+        let not_waiting = TaskFilter { waiting: Some(false), ..Default::default() };
+        assert!(store.list_tasks(not_waiting).unwrap().is_empty());
+
+        set(Status::Complete).unwrap();
+        assert!(set(Status::BlockedExternal).is_err());
+        set(Status::Open).unwrap();
+        set(Status::BlockedExternal).unwrap();
+    }
+
+    #[test]
+    fn test_user_messages() {
+        let store = InMemoryTaskStore::new();
+        store.record_user_message("password=hunter22222", "ctx", None, "s1").unwrap();
+        store.mark_pre_compaction("s1").unwrap();
+        assert_eq!(store.scrub_user_messages().unwrap(), 1);
+
+        let messages = store.get_session_user_messages("s1").unwrap();
+        assert!(messages[0].pre_compaction);
+        assert_eq!(messages[0].message, "password=[REDACTED:credential]");
+        store.clear_user_messages_for_session("s1").unwrap();
+        assert!(store.get_session_user_messages("s1").unwrap().is_empty());
+    }
+}
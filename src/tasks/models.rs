@@ -70,6 +70,12 @@ pub enum Status {
     Stuck,
     /// Task is blocked by dependencies.
     Blocked,
+    /// Work is done and waiting on review or CI.
+    #[serde(rename = "in_review")]
+    InReview,
+    /// Task is waiting on someone outside the project.
+    #[serde(rename = "blocked_external")]
+    BlockedExternal,
 }
 
 impl Status {
@@ -86,6 +92,8 @@ impl Status {
             "abandoned" => Ok(Self::Abandoned),
             "stuck" => Ok(Self::Stuck),
             "blocked" => Ok(Self::Blocked),
+            "in_review" | "in-review" => Ok(Self::InReview),
+            "blocked_external" | "blocked-external" => Ok(Self::BlockedExternal),
             _ => Err(InvalidStatus(s.to_string())),
         }
     }
@@ -99,8 +107,29 @@ impl Status {
             Self::Abandoned => "abandoned",
             Self::Stuck => "stuck",
             Self::Blocked => "blocked",
+            Self::InReview => "in_review",
+            Self::BlockedExternal => "blocked_external",
         }
     }
+
+    /// Check if the status means the work is waiting on someone else.
+    ///
+    /// Waiting tasks are not finished, so their dependents stay blocked, but
+    /// there is nothing for the agent to do on them and they don't stop it
+    /// from stopping.
+    #[must_use]
+    pub const fn is_waiting(self) -> bool {
+        matches!(self, Self::InReview | Self::BlockedExternal)
+    }
+
+    /// Check if a task may move from this status to `next`.
+    ///
+    /// A complete or abandoned task has to be reopened before it can go into
+    /// review or wait on someone else. Every other change is allowed.
+    #[must_use]
+    pub const fn can_transition_to(self, next: Self) -> bool {
+        !(next.is_waiting() && matches!(self, Self::Complete | Self::Abandoned))
+    }
 }
 
 impl std::fmt::Display for Status {
@@ -117,7 +146,7 @@ impl std::fmt::Display for InvalidStatus {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "invalid status: '{}' (must be one of: open, complete, abandoned, stuck, blocked)",
+            "invalid status: '{}' (must be one of: open, complete, abandoned, stuck, blocked, in_review, blocked_external)",
             self.0
         )
     }
@@ -141,12 +170,15 @@ pub struct Task {
     /// Whether this task is currently being worked on.
     pub in_progress: bool,
     /// Whether this task was explicitly requested by the user.
-    /// Requested tasks block the agent from stopping until complete or blocked on a question.
+    /// Requested tasks block the agent from stopping until complete, waiting or blocked on a question.
     pub requested: bool,
     /// ISO 8601 timestamp when the task was created.
     pub created_at: String,
     /// ISO 8601 timestamp when the task was last updated.
     pub updated_at: String,
+    /// Name of the workspace package the task belongs to, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package: Option<String>,
 }
 
 impl Task {
@@ -170,6 +202,57 @@ pub struct Note {
     pub created_at: String,
 }
 
+/// An acceptance criterion on a task's checklist.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChecklistItem {
+    /// ID of the task this item belongs to.
+    pub task_id: String,
+    /// Position in the task's checklist, starting at 1.
+    pub position: u32,
+    /// What has to be true for the task to be done.
+    pub text: String,
+    /// Whether the item has been ticked off.
+    pub done: bool,
+}
+
+/// A problem the agent explained to the user in problem mode.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Problem {
+    /// Unique identifier, in order of reporting.
+    pub id: i64,
+    /// Kind of blocker, such as `credentials` or `requirements`, or `other`.
+    pub category: String,
+    /// The agent's explanation.
+    pub explanation: String,
+    /// What the agent asked the user to do, if it said.
+    pub suggested_action: Option<String>,
+    /// ID of the work item the agent was working on, if any.
+    pub task_id: Option<String>,
+    /// ID of the session that reported the problem.
+    pub session_id: String,
+    /// When the problem was reported.
+    pub created_at: String,
+}
+
+/// A named starting point for work items of a common kind, such as a bug report.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaskTemplate {
+    /// Unique name used with `work create --template`.
+    pub name: String,
+    /// Description scaffold for new work items.
+    #[serde(default)]
+    pub description: String,
+    /// Priority of new work items.
+    #[serde(default)]
+    pub priority: Priority,
+    /// Acceptance criteria added to each new work item's checklist.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub checklist: Vec<String>,
+    /// IDs of how-tos linked to each new work item.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub howtos: Vec<String>,
+}
+
 /// A how-to guide for performing a particular task.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct HowTo {
@@ -198,6 +281,29 @@ pub struct Question {
     pub created_at: String,
     /// ISO 8601 timestamp when the question was answered, if applicable.
     pub answered_at: Option<String>,
+    /// UTC timestamp (`YYYY-MM-DD HH:MM:SS`) until which the question is
+    /// deferred and left out of the questions put to the user.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deferred_until: Option<String>,
+    /// How urgently the question needs an answer.
+    #[serde(default)]
+    pub priority: Priority,
+    /// Candidate answers drafted for the user to pick from.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub suggestions: Vec<AnswerSuggestion>,
+    /// Whether the answer is an assumption made because nobody answered in
+    /// time, rather than the user's answer.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub assumed: bool,
+}
+
+/// A candidate answer to a question, with its trade-offs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnswerSuggestion {
+    /// The answer.
+    pub answer: String,
+    /// What choosing this answer gains and costs.
+    pub tradeoffs: String,
 }
 
 impl Question {
@@ -206,6 +312,12 @@ impl Question {
     pub const fn is_answered(&self) -> bool {
         self.answer.is_some()
     }
+
+    /// Check if the question is unanswered and deferred past `now` (`YYYY-MM-DD HH:MM:SS` UTC).
+    #[must_use]
+    pub fn is_deferred(&self, now: &str) -> bool {
+        self.answer.is_none() && self.deferred_until.as_deref().is_some_and(|until| until > now)
+    }
 }
 
 /// A recorded user message for session tracking.
@@ -301,6 +413,9 @@ mod tests {
         assert_eq!(Status::from_str("abandoned").unwrap(), Status::Abandoned);
         assert_eq!(Status::from_str("stuck").unwrap(), Status::Stuck);
         assert_eq!(Status::from_str("blocked").unwrap(), Status::Blocked);
+        assert_eq!(Status::from_str("in_review").unwrap(), Status::InReview);
+        assert_eq!(Status::from_str("in-review").unwrap(), Status::InReview);
+        assert_eq!(Status::from_str("blocked_external").unwrap(), Status::BlockedExternal);
         assert!(Status::from_str("invalid").is_err());
     }
 
@@ -311,6 +426,31 @@ mod tests {
         assert_eq!(Status::Abandoned.as_str(), "abandoned");
         assert_eq!(Status::Stuck.as_str(), "stuck");
         assert_eq!(Status::Blocked.as_str(), "blocked");
+        assert_eq!(Status::InReview.as_str(), "in_review");
+        assert_eq!(Status::BlockedExternal.as_str(), "blocked_external");
+    }
+
+    #[test]
+    fn test_status_serializes_like_as_str() {
+        for status in [Status::InReview, Status::BlockedExternal] {
+            let json = serde_json::to_string(&status).unwrap();
+            assert_eq!(json, format!("\"{}\"", status.as_str()));
+            assert_eq!(serde_json::from_str::<Status>(&json).unwrap(), status);
+        }
+    }
+
+    #[test]
+    fn test_status_waiting_and_transitions() {
+        assert!(Status::InReview.is_waiting());
+        assert!(Status::BlockedExternal.is_waiting());
+        assert!(!Status::Stuck.is_waiting());
+
+        assert!(Status::Open.can_transition_to(Status::InReview));
+        assert!(Status::InReview.can_transition_to(Status::Complete));
+        assert!(Status::BlockedExternal.can_transition_to(Status::Open));
+        assert!(Status::Complete.can_transition_to(Status::Open));
+        assert!(!Status::Complete.can_transition_to(Status::InReview));
+        assert!(!Status::Abandoned.can_transition_to(Status::BlockedExternal));
     }
 
     #[test]
@@ -343,6 +483,7 @@ mod tests {
             requested: false,
             created_at: "2024-01-01T00:00:00Z".to_string(),
             updated_at: "2024-01-01T00:00:00Z".to_string(),
+            package: None,
         };
 
         assert!(!task.is_closed());
@@ -358,6 +499,9 @@ mod tests {
 
         task.status = Status::Blocked;
         assert!(!task.is_closed());
+
+        task.status = Status::InReview;
+        assert!(!task.is_closed());
     }
 
     #[test]
@@ -372,6 +516,7 @@ mod tests {
             requested: true,
             created_at: "2024-01-01T00:00:00Z".to_string(),
             updated_at: "2024-01-01T00:00:00Z".to_string(),
+            package: None,
         };
 
         let json = serde_json::to_string(&task).unwrap();
@@ -433,6 +578,10 @@ mod tests {
             answer: None,
             created_at: "2024-01-01T00:00:00Z".to_string(),
             answered_at: None,
+            deferred_until: None,
+            priority: Priority::Medium,
+            suggestions: Vec::new(),
+            assumed: false,
         };
 
         assert!(!question.is_answered());
@@ -442,6 +591,27 @@ mod tests {
         assert!(question.is_answered());
     }
 
+    #[test]
+    fn test_question_is_deferred() {
+        let mut question = Question {
+            id: "question-1234".to_string(),
+            text: "Which region?".to_string(),
+            answer: None,
+            created_at: "2024-01-01 00:00:00".to_string(),
+            answered_at: None,
+            deferred_until: Some("2024-01-05 00:00:00".to_string()),
+            priority: Priority::Medium,
+            suggestions: Vec::new(),
+            assumed: false,
+        };
+
+        assert!(question.is_deferred("2024-01-04 23:59:59"));
+        assert!(!question.is_deferred("2024-01-05 00:00:00"));
+
+        question.answer = Some("eu-west-1".to_string());
+        assert!(!question.is_deferred("2024-01-04 00:00:00"));
+    }
+
     #[test]
     fn test_question_serialization() {
         let question = Question {
@@ -450,6 +620,13 @@ mod tests {
             answer: Some("production".to_string()),
             created_at: "2024-01-01T00:00:00Z".to_string(),
             answered_at: Some("2024-01-02T00:00:00Z".to_string()),
+            deferred_until: None,
+            priority: Priority::High,
+            suggestions: vec![AnswerSuggestion {
+                answer: "staging".to_string(),
+                tradeoffs: "Safer, but users wait longer".to_string(),
+            }],
+            assumed: true,
         };
 
         let json = serde_json::to_string(&question).unwrap();
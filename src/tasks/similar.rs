@@ -0,0 +1,109 @@
+//! Finding open tasks that duplicate a new one.
+//!
+//! Titles are compared as sets of words, ignoring case, common filler words
+//! and plural endings, so "Add tests for the parser" matches "Add parser test".
+//! Creating work from reflection reuses a match instead of adding another item,
+//! and the CLI refuses to create one without `--force`.
+
+use crate::error::Result;
+use crate::tasks::models::Task;
+use crate::tasks::store::{TaskFilter, TaskStore};
+use std::collections::HashSet;
+
+/// Similarity (0 to 1) at which two titles count as the same work.
+pub const DUPLICATE_THRESHOLD: f64 = 0.75;
+
+/// Words that say nothing about which work a title describes.
+const FILLER_WORDS: [&str; 12] =
+    ["a", "an", "and", "for", "in", "is", "of", "on", "or", "the", "to", "with"];
+
+/// The significant words of a title.
+fn title_words(title: &str) -> HashSet<String> {
+    title
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .filter(|w| !FILLER_WORDS.contains(&w.as_str()))
+        .map(|w| match w.strip_suffix('s') {
+            Some(stem) if stem.len() >= 3 && !stem.ends_with('s') => stem.to_string(),
+            _ => w,
+        })
+        .collect()
+}
+
+/// How alike two titles are, from 0 (no words shared) to 1 (the same words).
+#[must_use]
+pub fn title_similarity(a: &str, b: &str) -> f64 {
+    let (a, b) = (title_words(a), title_words(b));
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    let shared = a.intersection(&b).count();
+    #[allow(clippy::cast_precision_loss)] // Word counts are tiny
+    let similarity = (2 * shared) as f64 / (a.len() + b.len()) as f64;
+    similarity
+}
+
+/// Find open tasks whose titles are similar enough to duplicate `title`,
+/// most similar first.
+///
+/// # Errors
+///
+/// Returns an error if the store cannot be read.
+pub fn find_similar_open_tasks<S: TaskStore + ?Sized>(store: &S, title: &str) -> Result<Vec<Task>> {
+    let mut matches: Vec<(f64, Task)> = store
+        .list_tasks(TaskFilter::default())?
+        .into_iter()
+        .filter(|task| !task.is_closed())
+        .map(|task| (title_similarity(title, &task.title), task))
+        .filter(|(similarity, _)| *similarity >= DUPLICATE_THRESHOLD)
+        .collect();
+    matches.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+    Ok(matches.into_iter().map(|(_, task)| task).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tasks::models::{Priority, Status};
+    use crate::tasks::store::TaskUpdate;
+    use crate::tasks::InMemoryTaskStore;
+
+    #[test]
+    fn test_title_similarity() {
+        assert!(
+            (title_similarity("Fix the login bug", "fix login bug") - 1.0).abs() < f64::EPSILON
+        );
+        assert!(
+            title_similarity("Add tests for the parser", "Add parser test") >= DUPLICATE_THRESHOLD
+        );
+        assert!(title_similarity("Fix X", "Fix Y") < DUPLICATE_THRESHOLD);
+        assert!(title_similarity("Fix X", "Add Y").abs() < f64::EPSILON);
+        assert!(title_similarity("", "the").abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_plural_stripping_keeps_short_and_double_s_words() {
+        assert!(title_words("Update class docs").contains("class"));
+        assert!(title_words("Update class docs").contains("doc"));
+        assert!(title_words("Fix bus").contains("bus"));
+    }
+
+    #[test]
+    fn test_find_similar_open_tasks() {
+        let store = InMemoryTaskStore::new();
+        let open = store.create_task("Fix the login bug", "", Priority::High).unwrap();
+        let done = store.create_task("Fix login bugs", "", Priority::High).unwrap();
+        store.create_task("Write release notes", "", Priority::Low).unwrap();
+        store
+            .update_task(
+                &done.id,
+                TaskUpdate { status: Some(Status::Complete), ..Default::default() },
+            )
+            .unwrap();
+
+        let similar = find_similar_open_tasks(&store, "Fix login bug").unwrap();
+        assert_eq!(similar.iter().map(|t| t.id.as_str()).collect::<Vec<_>>(), vec![open.id]);
+        assert!(find_similar_open_tasks(&store, "Deploy the service").unwrap().is_empty());
+    }
+}
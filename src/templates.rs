@@ -2,17 +2,21 @@
 //!
 //! This module provides infrastructure for loading user-facing messages from
 //! external template files, with embedded fallbacks for when files don't exist.
+//!
+//! A project can override any embedded template by putting a file with the
+//! same name in `.claude-reliability/templates/`, e.g.
+//! `.claude-reliability/templates/messages/stop/uncommitted_changes.tera`.
+//! Overrides that fail to parse or render are ignored in favour of the
+//! embedded version.
 
 use crate::error::{Error, Result};
+use crate::paths;
 use once_cell::sync::Lazy;
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::RwLock;
 use tera::{Context, Tera};
 
-/// Default templates directory relative to crate root.
-const TEMPLATES_DIR: &str = "templates";
-
 /// Embedded default templates for fallback when files don't exist.
 static EMBEDDED_TEMPLATES: Lazy<HashMap<&'static str, &'static str>> = Lazy::new(|| {
     let mut m = HashMap::new();
@@ -23,10 +27,23 @@ static EMBEDDED_TEMPLATES: Lazy<HashMap<&'static str, &'static str>> = Lazy::new
         include_str!("../templates/prompts/question_decision.tera"),
     );
     m.insert("prompts/code_review.tera", include_str!("../templates/prompts/code_review.tera"));
+    m.insert("prompts/handoff.tera", include_str!("../templates/prompts/handoff.tera"));
+    m.insert(
+        "prompts/report_narrative.tera",
+        include_str!("../templates/prompts/report_narrative.tera"),
+    );
+    m.insert(
+        "prompts/answer_suggestions.tera",
+        include_str!("../templates/prompts/answer_suggestions.tera"),
+    );
     m.insert(
         "prompts/emergency_stop_decision.tera",
         include_str!("../templates/prompts/emergency_stop_decision.tera"),
     );
+    m.insert(
+        "prompts/protected_change_decision.tera",
+        include_str!("../templates/prompts/protected_change_decision.tera"),
+    );
     m.insert(
         "prompts/create_question_decision.tera",
         include_str!("../templates/prompts/create_question_decision.tera"),
@@ -35,6 +52,23 @@ static EMBEDDED_TEMPLATES: Lazy<HashMap<&'static str, &'static str>> = Lazy::new
         "prompts/reflection_decision.tera",
         include_str!("../templates/prompts/reflection_decision.tera"),
     );
+    m.insert(
+        "prompts/wip_commit_summary.tera",
+        include_str!("../templates/prompts/wip_commit_summary.tera"),
+    );
+    m.insert("prompts/commit_split.tera", include_str!("../templates/prompts/commit_split.tera"));
+    m.insert(
+        "prompts/commit_push_question.tera",
+        include_str!("../templates/prompts/commit_push_question.tera"),
+    );
+    m.insert(
+        "prompts/pr_description.tera",
+        include_str!("../templates/prompts/pr_description.tera"),
+    );
+    m.insert(
+        "prompts/worker_session.tera",
+        include_str!("../templates/prompts/worker_session.tera"),
+    );
 
     // Stop hook messages
     m.insert(
@@ -61,6 +95,22 @@ static EMBEDDED_TEMPLATES: Lazy<HashMap<&'static str, &'static str>> = Lazy::new
         "messages/stop/unpushed_commits.tera",
         include_str!("../templates/messages/stop/unpushed_commits.tera"),
     );
+    m.insert(
+        "messages/stop/diverged_from_remote.tera",
+        include_str!("../templates/messages/stop/diverged_from_remote.tera"),
+    );
+    m.insert(
+        "messages/stop/agent_stashes.tera",
+        include_str!("../templates/messages/stop/agent_stashes.tera"),
+    );
+    m.insert(
+        "messages/stop/pr_required.tera",
+        include_str!("../templates/messages/stop/pr_required.tera"),
+    );
+    m.insert(
+        "messages/stop/missing_upstream.tera",
+        include_str!("../templates/messages/stop/missing_upstream.tera"),
+    );
     m.insert(
         "messages/stop/open_issues_remaining.tera",
         include_str!("../templates/messages/stop/open_issues_remaining.tera"),
@@ -73,10 +123,62 @@ static EMBEDDED_TEMPLATES: Lazy<HashMap<&'static str, &'static str>> = Lazy::new
         "messages/stop/auto_work_tasks.tera",
         include_str!("../templates/messages/stop/auto_work_tasks.tera"),
     );
+    m.insert(
+        "messages/stop/auto_work_check_in.tera",
+        include_str!("../templates/messages/stop/auto_work_check_in.tera"),
+    );
     m.insert(
         "messages/stop/work_item_reminder.tera",
         include_str!("../templates/messages/stop/work_item_reminder.tera"),
     );
+    m.insert(
+        "messages/stop/repeated_block.tera",
+        include_str!("../templates/messages/stop/repeated_block.tera"),
+    );
+    m.insert(
+        "messages/stop/injection_loop.tera",
+        include_str!("../templates/messages/stop/injection_loop.tera"),
+    );
+    m.insert(
+        "messages/stop/repeated_output.tera",
+        include_str!("../templates/messages/stop/repeated_output.tera"),
+    );
+    m.insert(
+        "messages/stop/session_budget.tera",
+        include_str!("../templates/messages/stop/session_budget.tera"),
+    );
+    m.insert(
+        "messages/stop/wip_commit_required.tera",
+        include_str!("../templates/messages/stop/wip_commit_required.tera"),
+    );
+    m.insert(
+        "messages/stop/wip_commit_refused.tera",
+        include_str!("../templates/messages/stop/wip_commit_refused.tera"),
+    );
+    m.insert(
+        "messages/stop/detached_head.tera",
+        include_str!("../templates/messages/stop/detached_head.tera"),
+    );
+    m.insert(
+        "messages/stop/commit_policy.tera",
+        include_str!("../templates/messages/stop/commit_policy.tera"),
+    );
+    m.insert(
+        "messages/stop/auto_push_failed.tera",
+        include_str!("../templates/messages/stop/auto_push_failed.tera"),
+    );
+    m.insert(
+        "messages/stop/operation_in_progress.tera",
+        include_str!("../templates/messages/stop/operation_in_progress.tera"),
+    );
+    m.insert(
+        "messages/stop/vcs_uncommitted_changes.tera",
+        include_str!("../templates/messages/stop/vcs_uncommitted_changes.tera"),
+    );
+    m.insert(
+        "messages/stop/vcs_unpushed_commits.tera",
+        include_str!("../templates/messages/stop/vcs_unpushed_commits.tera"),
+    );
 
     // Other hook messages
     m.insert(
@@ -87,10 +189,27 @@ static EMBEDDED_TEMPLATES: Lazy<HashMap<&'static str, &'static str>> = Lazy::new
         "messages/protect_config_write.tera",
         include_str!("../templates/messages/protect_config_write.tera"),
     );
+    m.insert(
+        "messages/protect_package_path.tera",
+        include_str!("../templates/messages/protect_package_path.tera"),
+    );
     m.insert(
         "messages/protect_config_delete.tera",
         include_str!("../templates/messages/protect_config_delete.tera"),
     );
+    m.insert(
+        "messages/protect_hooks_switch.tera",
+        include_str!("../templates/messages/protect_hooks_switch.tera"),
+    );
+    m.insert("messages/binary_files.tera", include_str!("../templates/messages/binary_files.tera"));
+    m.insert(
+        "messages/sensitive_files.tera",
+        include_str!("../templates/messages/sensitive_files.tera"),
+    );
+    m.insert(
+        "messages/spelling_warnings.tera",
+        include_str!("../templates/messages/spelling_warnings.tera"),
+    );
     m.insert(
         "messages/no_verify_block.tera",
         include_str!("../templates/messages/no_verify_block.tera"),
@@ -104,6 +223,15 @@ static EMBEDDED_TEMPLATES: Lazy<HashMap<&'static str, &'static str>> = Lazy::new
         include_str!("../templates/messages/session_intro.tera"),
     );
     m.insert("messages/require_task.tera", include_str!("../templates/messages/require_task.tera"));
+    m.insert("messages/handoff.tera", include_str!("../templates/messages/handoff.tera"));
+    m.insert(
+        "messages/database_recovered.tera",
+        include_str!("../templates/messages/database_recovered.tera"),
+    );
+    m.insert(
+        "messages/resume_context.tera",
+        include_str!("../templates/messages/resume_context.tera"),
+    );
     m.insert(
         "messages/enter_plan_mode_intent.tera",
         include_str!("../templates/messages/enter_plan_mode_intent.tera"),
@@ -117,6 +245,9 @@ static TERA: Lazy<RwLock<Option<Tera>>> = Lazy::new(|| RwLock::new(None));
 /// Initialize the template engine with templates from the specified directory.
 ///
 /// If the directory doesn't exist, templates will be loaded from embedded defaults.
+/// With no directory, the project's overrides are loaded from
+/// `.claude-reliability/templates/` in the current directory, skipping any that
+/// fail [`check_override`].
 ///
 /// # Errors
 ///
@@ -127,10 +258,12 @@ static TERA: Lazy<RwLock<Option<Tera>>> = Lazy::new(|| RwLock::new(None));
 /// Panics if an embedded template fails to add to the engine. This should never
 /// happen as embedded templates are verified by `test_all_embedded_templates_render`.
 pub fn init_templates(templates_dir: Option<&Path>) -> Result<()> {
-    let dir = templates_dir.map_or_else(
-        || std::env::current_dir().unwrap_or_default().join(TEMPLATES_DIR),
-        Path::to_path_buf,
-    );
+    let Some(dir) = templates_dir else {
+        let base_dir = std::env::current_dir().unwrap_or_default();
+        let tera = project_tera(&paths::project_templates_dir(&base_dir));
+        *TERA.write().map_err(|e| Error::Template(e.to_string()))? = Some(tera);
+        return Ok(());
+    };
 
     let mut tera = Tera::default();
 
@@ -165,6 +298,103 @@ pub fn init_templates(templates_dir: Option<&Path>) -> Result<()> {
     Ok(())
 }
 
+/// Create a template engine holding only the embedded templates.
+fn embedded_tera() -> Tera {
+    let mut tera = Tera::default();
+    tera.add_raw_templates(EMBEDDED_TEMPLATES.iter().map(|(name, content)| (*name, *content)))
+        .expect("embedded template should be valid - verified by tests");
+    tera
+}
+
+/// Create a template engine from the embedded templates and a project's overrides.
+///
+/// Overrides that fail [`check_override`] are logged and skipped.
+fn project_tera(overrides_dir: &Path) -> Tera {
+    let mut tera = embedded_tera();
+    for (name, path) in override_files(overrides_dir) {
+        let checked = std::fs::read_to_string(&path)
+            .map_err(Error::from)
+            .and_then(|content| check_override(&name, &content).map(|()| content));
+        match checked {
+            Ok(content) => tera
+                .add_raw_template(&name, &content)
+                .expect("override was checked before being added"),
+            Err(e) => tracing::warn!("Ignoring template override {}: {e}", path.display()),
+        }
+    }
+    tera
+}
+
+/// Find the template files in an overrides directory, with their template names.
+fn override_files(dir: &Path) -> Vec<(String, PathBuf)> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        let Ok(entries) = std::fs::read_dir(&current) else {
+            continue;
+        };
+        for path in entries.flatten().map(|entry| entry.path()) {
+            if path.is_dir() {
+                pending.push(path);
+            } else if path.extension().is_some_and(|ext| ext == "tera") {
+                let name = path.strip_prefix(dir).unwrap_or(&path);
+                let name: Vec<_> =
+                    name.components().map(|c| c.as_os_str().to_string_lossy()).collect();
+                // Template names use `/` on every platform
+                files.push((name.join("/"), path));
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+/// Check that a template override can replace the embedded template `name`.
+///
+/// The override must name an embedded template, parse, and render with the
+/// same variables the embedded template is given.
+///
+/// # Errors
+///
+/// Returns an error describing the first problem found.
+pub fn check_override(name: &str, content: &str) -> Result<()> {
+    if !EMBEDDED_TEMPLATES.contains_key(name) {
+        return Err(Error::Template(format!("there is no built-in template called {name}")));
+    }
+    let mut tera = embedded_tera();
+    tera.add_raw_template(name, content).map_err(|e| Error::Template(describe_tera_error(&e)))?;
+    tera.render(name, &sample_context_for(name))
+        .map_err(|e| Error::Template(describe_tera_error(&e)))?;
+    Ok(())
+}
+
+/// Check every template override in a project.
+///
+/// Returns a description of each override that will be ignored, and why.
+#[must_use]
+pub fn validate_overrides(base_dir: &Path) -> Vec<String> {
+    override_files(&paths::project_templates_dir(base_dir))
+        .into_iter()
+        .filter_map(|(name, path)| {
+            let checked = std::fs::read_to_string(&path)
+                .map_err(Error::from)
+                .and_then(|content| check_override(&name, &content));
+            checked.err().map(|e| format!("{name}: {e}"))
+        })
+        .collect()
+}
+
+/// Describe a Tera error, including the errors that caused it.
+fn describe_tera_error(error: &tera::Error) -> String {
+    let mut parts = vec![error.to_string()];
+    let mut source = std::error::Error::source(error);
+    while let Some(cause) = source {
+        parts.push(cause.to_string());
+        source = cause.source();
+    }
+    parts.join(": ")
+}
+
 /// Render a template with the given context.
 ///
 /// Templates are lazy-loaded from the filesystem on first use, with embedded
@@ -266,6 +496,7 @@ pub fn verify_all_templates() -> Result<()> {
 }
 
 /// Create a sample context with all variables a template might need.
+#[allow(clippy::too_many_lines)] // One line per template variable
 fn sample_context_for(template_name: &str) -> Context {
     let mut ctx = Context::new();
 
@@ -276,6 +507,9 @@ fn sample_context_for(template_name: &str) -> Context {
     ctx.insert("guide_section", "Sample review guidelines");
     ctx.insert("files_list", "- file1.rs\n- file2.rs");
     ctx.insert("diff", "+sample diff content");
+    ctx.insert("files", &vec!["src/parser.rs", "README.md"]);
+    ctx.insert("diff_stat", " src/parser.rs | 120 +++++\n 1 file changed, 120 insertions(+)");
+    ctx.insert("question", "Do you want these changes committed?");
 
     // Stop messages
     ctx.insert("error_count", &3_u32);
@@ -292,6 +526,40 @@ fn sample_context_for(template_name: &str) -> Context {
         "I have completed all work that I can and require human input to proceed.",
     );
     ctx.insert("commits_ahead", &2_u32);
+    ctx.insert("vcs", "jj");
+    ctx.insert("operation", "rebase");
+    ctx.insert("committer_name", "Ada Lovelace");
+    ctx.insert("committer_email", "ada@example.com");
+    ctx.insert("require_signature", &true);
+    ctx.insert("base", "abc1234~1");
+    ctx.insert("command", "git push -u origin feature");
+    ctx.insert("output", "error: failed to push some refs");
+    ctx.insert("commit_command", "jj commit -m '...'");
+    ctx.insert("push_command", "jj git push");
+    ctx.insert("commits_behind", &1_u32);
+    ctx.insert("stashes", &vec!["stash@{0}"]);
+    ctx.insert("commits", &vec!["Add parser"]);
+    ctx.insert("work_items", &vec!["Write a parser"]);
+    ctx.insert("pr_error", "no commits between main and feature");
+    ctx.insert("completed_work_items", &vec!["Write a parser"]);
+    ctx.insert("open_questions", &vec!["Which auth provider?"]);
+    ctx.insert("assumptions", &vec!["Which region? Assumed: eu-west-1"]);
+    ctx.insert("problems", &vec!["[credentials] Add an API key (work item abc)"]);
+    ctx.insert("open_work_items", &vec!["Add parser benchmarks"]);
+    ctx.insert("final_message", "All requested work is done.");
+    ctx.insert("report", "# Activity Report\n\n## Work Items\n\n- Created: 3");
+    ctx.insert("handoff", "# Session Handoff\n\n## Completed\n\n- Write a parser");
+    ctx.insert("in_progress_work_items", &vec!["add-parser-1a2b: Add parser"]);
+    ctx.insert("pre_compaction_messages", &vec!["Also fix the login bug"]);
+    ctx.insert("pause_reason", "Back after lunch");
+    ctx.insert(
+        "recoveries",
+        &vec![crate::storage::DatabaseRecovery {
+            recovered_at: "2025-01-01 12:00:00".to_string(),
+            backup_path: ".claude-reliability/backups/corrupt-20250101T120000Z.sqlite3".to_string(),
+            details: "Recovered 42 rows".to_string(),
+        }],
+    );
     ctx.insert("open_count", &3_u32);
     ctx.insert("change_type", "git");
     ctx.insert("iterations_since_change", &2_u32);
@@ -299,9 +567,35 @@ fn sample_context_for(template_name: &str) -> Context {
     ctx.insert("staleness_threshold", &5_u32);
     ctx.insert("task_count", &3_u32);
     ctx.insert("idle_minutes", &30_u32);
+    ctx.insert("completed_count", &5_u32);
+    ctx.insert("stage", "guidance");
+    ctx.insert("block_count", &3_u32);
+    ctx.insert("check_name", "uncommitted_changes");
+    ctx.insert("question_id", "q-abc123");
+    ctx.insert("problem_id", &3_i64);
+    ctx.insert("reported_to", &vec!["https://github.com/acme/app/issues/7", "BLOCKERS.md"]);
+    ctx.insert("repeat_count", &5_u32);
+    ctx.insert("spend", "$12.40 (52000 input, 3100 output, 410000 cached tokens)");
+    ctx.insert("budget", &10_u32);
+    ctx.insert("output", "Let me run the tests again.");
+    ctx.insert(
+        "work_item",
+        "[investigate-1a2b] Investigate agent stuck repeating the same message",
+    );
+    ctx.insert("blocking_checks", &vec![serde_json::json!({"name": "validation", "count": 12})]);
+    ctx.insert("branch", "wip/feature");
 
     // Emergency stop
     ctx.insert("explanation", "I cannot proceed because the API key is missing.");
+    ctx.insert("category", "missing credentials or access");
+
+    // Protected change
+    ctx.insert("justification", "The user asked me to turn off the spelling check.");
+
+    // Worker session
+    ctx.insert("work_item_id", "add-parser-1a2b");
+    ctx.insert("work_item_title", "Add parser");
+    ctx.insert("work_item_description", "Parse the config file format.");
 
     // Create question
     ctx.insert("question_text", "Should I continue with the remaining tasks?");
@@ -310,6 +604,7 @@ fn sample_context_for(template_name: &str) -> Context {
     ctx.insert("reflection_output", "I have completed all requested changes.");
     if template_name.contains("reflection_decision") {
         ctx.insert("user_messages", &vec!["Fix the login bug", "Add tests for auth"]);
+        ctx.insert("unchecked_criteria", &vec!["fix-login-1a2b #1: unit tests added"]);
     }
 
     // Other hooks
@@ -317,6 +612,17 @@ fn sample_context_for(template_name: &str) -> Context {
     ctx.insert("binary_path", "/project/.claude-reliability/bin/claude-reliability");
     ctx.insert("session_notes_path", ".claude/jkw-session.local.md");
     ctx.insert("config_path", ".claude/reliability-config.yaml");
+    ctx.insert("description", "the reliability config, which controls the reliability hooks");
+    ctx.insert("file_path", "crates/api/migrations/001_init.sql");
+    ctx.insert("ready_items", &vec!["claude-reliability work on add-parser-1a2b  # Add parser"]);
+    ctx.insert("package", "api");
+    ctx.insert("lfs_patterns", &vec!["*.bin"]);
+    ctx.insert("sensitive", &vec![".env"]);
+    ctx.insert("binaries", &vec!["assets/model.bin (2048 KB)"]);
+    ctx.insert("secrets", &vec!["src/config.rs (aws_access_key)"]);
+    ctx.insert("max_kb", &100_u64);
+    ctx.insert("blocking", &true);
+    ctx.insert("warnings", &vec!["README.md:3: \"recieve\" should be \"receive\""]);
     ctx.insert("acknowledgment", "I promise the user has said I can use --no-verify here");
 
     // For uncommitted_changes template - lists
@@ -379,6 +685,46 @@ mod tests {
         assert_eq!(result, "CUSTOM: Custom");
     }
 
+    #[test]
+    fn test_project_overrides_replace_embedded_templates() {
+        let dir = TempDir::new().unwrap();
+        let overrides = dir.path().join("messages");
+        fs::create_dir_all(&overrides).unwrap();
+        fs::write(overrides.join("problem_mode_block.tera"), "See the runbook: {{ tool_name }}")
+            .unwrap();
+        fs::write(overrides.join("no_verify_block.tera"), "{% if %}").unwrap();
+        fs::write(overrides.join("unknown.tera"), "Hello").unwrap();
+
+        let tera = project_tera(dir.path());
+        let ctx = sample_context_for("messages/problem_mode_block.tera");
+        let rendered = tera.render("messages/problem_mode_block.tera", &ctx).unwrap();
+        assert_eq!(rendered, "See the runbook: Bash");
+        let ctx = sample_context_for("messages/no_verify_block.tera");
+        assert_eq!(
+            tera.render("messages/no_verify_block.tera", &ctx).unwrap(),
+            embedded_tera().render("messages/no_verify_block.tera", &ctx).unwrap()
+        );
+        assert!(tera.get_template("messages/unknown.tera").is_err());
+    }
+
+    #[test]
+    fn test_validate_overrides() {
+        let dir = TempDir::new().unwrap();
+        assert!(validate_overrides(dir.path()).is_empty());
+
+        let overrides = paths::project_templates_dir(dir.path()).join("messages");
+        fs::create_dir_all(&overrides).unwrap();
+        fs::write(overrides.join("problem_mode_block.tera"), "{{ tool_name }}").unwrap();
+        fs::write(overrides.join("no_verify_block.tera"), "{{ missing_variable }}").unwrap();
+        fs::write(overrides.join("unknown.tera"), "Hello").unwrap();
+
+        let problems = validate_overrides(dir.path());
+        assert_eq!(problems.len(), 2, "{problems:?}");
+        assert!(problems[0].starts_with("messages/no_verify_block.tera: "));
+        assert!(problems[0].contains("`missing_variable` not found"));
+        assert!(problems[1].contains("no built-in template called messages/unknown.tera"));
+    }
+
     #[test]
     fn test_context_helper() {
         let mut ctx = context();
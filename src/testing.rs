@@ -1,19 +1,39 @@
 //! Testing utilities and mock implementations.
 //!
 //! These types are provided for use in tests. They may appear unused in
-//! the library itself but are consumed by unit tests.
+//! the library itself but are consumed by unit tests. Enable the
+//! `test-support` feature to use them from downstream projects, e.g. to
+//! unit-test a plugin's hook configuration with [`HookInputBuilder`] and
+//! [`TranscriptBuilder`].
+//!
+//! To test against real git behaviour without depending on the machine the
+//! tests run on, capture a fixture once with [`RecordingCommandRunner`] and
+//! serve it in tests with [`ReplayCommandRunner`].
 
 #![allow(dead_code)]
 #![allow(clippy::needless_pass_by_ref_mut)] // &mut self for ergonomics with RefCell
 
+use crate::command::{format_command, RealCommandRunner};
 use crate::error::Result;
+use crate::hooks::{HookInput, ToolInput};
+use crate::storage::Marker;
+use crate::tasks::AnswerSuggestion;
 use crate::traits::{
-    CommandOutput, CommandRunner, CreateQuestionContext, CreateQuestionDecision,
-    EmergencyStopContext, EmergencyStopDecision, QuestionContext, ReflectionContext,
-    ReflectionDecision, StateStore, SubAgent, SubAgentDecision,
+    CommandOutput, CommandRunner, CommitPushRequest, CommitSplitContext, CreateQuestionContext,
+    CreateQuestionDecision, EmergencyStopContext, EmergencyStopDecision, HandoffContext,
+    HookDecision, HookObserver, PlannedCommit, PrDescription, PrDescriptionContext,
+    ProtectedChangeContext, ProtectedChangeDecision, QuestionContext, ReflectionContext,
+    ReflectionDecision, RunOptions, StateStore, StreamOptions, SubAgent, SubAgentDecision,
 };
+use crate::transcript::TranscriptInfo;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use std::cell::RefCell;
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, PoisonError};
 use std::time::Duration;
 
 /// A mock command runner for testing.
@@ -21,9 +41,9 @@ use std::time::Duration;
 /// Records expected commands and their outputs, then verifies they were called.
 #[derive(Debug, Default)]
 pub struct MockCommandRunner {
-    expectations: RefCell<Vec<(String, Vec<String>, CommandOutput)>>,
-    available_programs: RefCell<Vec<String>>,
-    call_index: RefCell<usize>,
+    expectations: Mutex<Vec<(String, Vec<String>, CommandOutput)>>,
+    available_programs: Mutex<Vec<String>>,
+    call_index: AtomicUsize,
 }
 
 impl MockCommandRunner {
@@ -35,7 +55,7 @@ impl MockCommandRunner {
 
     /// Add an expected command and its output.
     pub fn expect(&mut self, program: &str, args: &[&str], output: CommandOutput) {
-        self.expectations.borrow_mut().push((
+        self.expectations.get_mut().unwrap_or_else(PoisonError::into_inner).push((
             program.to_string(),
             args.iter().map(|s| (*s).to_string()).collect(),
             output,
@@ -44,7 +64,10 @@ impl MockCommandRunner {
 
     /// Add a program as available.
     pub fn set_available(&mut self, program: &str) {
-        self.available_programs.borrow_mut().push(program.to_string());
+        self.available_programs
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(program.to_string());
     }
 
     /// Verify all expected commands were called.
@@ -53,8 +76,8 @@ impl MockCommandRunner {
     ///
     /// Panics if not all expected commands were called.
     pub fn verify(&self) {
-        let index = *self.call_index.borrow();
-        let expected = self.expectations.borrow().len();
+        let index = self.call_index.load(Ordering::SeqCst);
+        let expected = self.expectations.lock().unwrap_or_else(PoisonError::into_inner).len();
         assert_eq!(
             index, expected,
             "Expected {expected} command calls, but only {index} were made"
@@ -69,29 +92,26 @@ impl CommandRunner for MockCommandRunner {
         args: &[&str],
         _timeout: Option<Duration>,
     ) -> Result<CommandOutput> {
-        let mut index = self.call_index.borrow_mut();
-        let expectations = self.expectations.borrow();
+        let expectations = self.expectations.lock().unwrap_or_else(PoisonError::into_inner);
+        let index = self.call_index.load(Ordering::SeqCst);
 
         assert!(
-            *index < expectations.len(),
+            index < expectations.len(),
             "Unexpected command call: {program} {args:?} (no more expectations)"
         );
 
-        let (exp_program, exp_args, output) = &expectations[*index];
+        let (exp_program, exp_args, output) = &expectations[index];
         let args_vec: Vec<String> = args.iter().map(|s| (*s).to_string()).collect();
 
         assert!(
             !(program != exp_program || &args_vec != exp_args),
-            "Command mismatch at index {}:\n  Expected: {} {:?}\n  Got: {} {:?}",
-            *index,
-            exp_program,
-            exp_args,
-            program,
-            args
+            "Command mismatch at index {index}:\n  Expected: {exp_program} {exp_args:?}\n  Got: {program} {args:?}"
         );
 
-        *index += 1;
-        Ok(output.clone())
+        let output = output.clone();
+        drop(expectations);
+        self.call_index.store(index + 1, Ordering::SeqCst);
+        Ok(output)
     }
 
     fn run_in_dir(
@@ -106,7 +126,10 @@ impl CommandRunner for MockCommandRunner {
     }
 
     fn is_available(&self, program: &str) -> bool {
-        self.available_programs.borrow().contains(&program.to_string())
+        self.available_programs
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .contains(&program.to_string())
     }
 }
 
@@ -118,11 +141,27 @@ pub struct MockSubAgent {
     emergency_stop_decisions: RefCell<Vec<EmergencyStopDecision>>,
     create_question_decisions: RefCell<Vec<CreateQuestionDecision>>,
     reflection_decisions: RefCell<Vec<ReflectionDecision>>,
+    wip_summaries: RefCell<Vec<String>>,
+    pr_descriptions: RefCell<Vec<PrDescription>>,
+    handoffs: RefCell<Vec<String>>,
+    report_narratives: RefCell<Vec<String>>,
+    commit_splits: RefCell<Vec<Vec<PlannedCommit>>>,
+    commit_push_questions: RefCell<Vec<Option<CommitPushRequest>>>,
+    answer_suggestions: RefCell<Vec<Vec<AnswerSuggestion>>>,
+    protected_change_decisions: RefCell<Vec<ProtectedChangeDecision>>,
     question_index: RefCell<usize>,
     review_index: RefCell<usize>,
     emergency_stop_index: RefCell<usize>,
     create_question_index: RefCell<usize>,
     reflection_index: RefCell<usize>,
+    wip_summary_index: RefCell<usize>,
+    pr_description_index: RefCell<usize>,
+    handoff_index: RefCell<usize>,
+    report_narrative_index: RefCell<usize>,
+    commit_split_index: RefCell<usize>,
+    commit_push_question_index: RefCell<usize>,
+    answer_suggestions_index: RefCell<usize>,
+    protected_change_index: RefCell<usize>,
 }
 
 impl MockSubAgent {
@@ -156,6 +195,48 @@ impl MockSubAgent {
     pub fn expect_reflection(&mut self, decision: ReflectionDecision) {
         self.reflection_decisions.borrow_mut().push(decision);
     }
+
+    /// Add an expected WIP commit summary.
+    pub fn expect_wip_summary(&mut self, summary: &str) {
+        self.wip_summaries.borrow_mut().push(summary.to_string());
+    }
+
+    /// Add an expected pull request description.
+    pub fn expect_pr_description(&mut self, title: &str, body: &str) {
+        self.pr_descriptions
+            .borrow_mut()
+            .push(PrDescription { title: title.to_string(), body: body.to_string() });
+    }
+
+    /// Add an expected handoff summary.
+    pub fn expect_handoff(&mut self, summary: &str) {
+        self.handoffs.borrow_mut().push(summary.to_string());
+    }
+
+    /// Add an expected report narrative.
+    pub fn expect_report_narrative(&mut self, narrative: &str) {
+        self.report_narratives.borrow_mut().push(narrative.to_string());
+    }
+
+    /// Add an expected commit split plan.
+    pub fn expect_commit_split(&mut self, plan: Vec<PlannedCommit>) {
+        self.commit_splits.borrow_mut().push(plan);
+    }
+
+    /// Add an expected commit/push question classification.
+    pub fn expect_commit_push_question(&mut self, request: Option<CommitPushRequest>) {
+        self.commit_push_questions.borrow_mut().push(request);
+    }
+
+    /// Add expected candidate answers for a question.
+    pub fn expect_answer_suggestions(&mut self, suggestions: Vec<AnswerSuggestion>) {
+        self.answer_suggestions.borrow_mut().push(suggestions);
+    }
+
+    /// Add an expected protected change decision.
+    pub fn expect_protected_change(&mut self, decision: ProtectedChangeDecision) {
+        self.protected_change_decisions.borrow_mut().push(decision);
+    }
 }
 
 impl SubAgent for MockSubAgent {
@@ -234,6 +315,97 @@ impl SubAgent for MockSubAgent {
         *index += 1;
         Ok(decision)
     }
+
+    fn summarize_wip_commit(&self, _diff: &str) -> Result<String> {
+        let mut index = self.wip_summary_index.borrow_mut();
+        let summaries = self.wip_summaries.borrow();
+
+        assert!(*index < summaries.len(), "No more WIP summaries expected");
+
+        let summary = summaries[*index].clone();
+        *index += 1;
+        Ok(summary)
+    }
+
+    fn write_pr_description(&self, _context: &PrDescriptionContext) -> Result<PrDescription> {
+        let mut index = self.pr_description_index.borrow_mut();
+        let descriptions = self.pr_descriptions.borrow();
+
+        assert!(*index < descriptions.len(), "No more PR descriptions expected");
+
+        let description = descriptions[*index].clone();
+        *index += 1;
+        Ok(description)
+    }
+
+    fn write_handoff(&self, _context: &HandoffContext) -> Result<String> {
+        let mut index = self.handoff_index.borrow_mut();
+        let handoffs = self.handoffs.borrow();
+
+        assert!(*index < handoffs.len(), "No more handoffs expected");
+
+        let handoff = handoffs[*index].clone();
+        *index += 1;
+        Ok(handoff)
+    }
+
+    fn narrate_report(&self, _report: &str) -> Result<String> {
+        let mut index = self.report_narrative_index.borrow_mut();
+        let narratives = self.report_narratives.borrow();
+
+        assert!(*index < narratives.len(), "No more report narratives expected");
+
+        let narrative = narratives[*index].clone();
+        *index += 1;
+        Ok(narrative)
+    }
+
+    fn plan_commit_split(&self, _context: &CommitSplitContext) -> Result<Vec<PlannedCommit>> {
+        let mut index = self.commit_split_index.borrow_mut();
+        let plans = self.commit_splits.borrow();
+
+        assert!(*index < plans.len(), "No more commit splits expected");
+
+        let plan = plans[*index].clone();
+        *index += 1;
+        Ok(plan)
+    }
+
+    fn classify_commit_push_question(&self, _question: &str) -> Result<Option<CommitPushRequest>> {
+        let mut index = self.commit_push_question_index.borrow_mut();
+        let requests = self.commit_push_questions.borrow();
+
+        assert!(*index < requests.len(), "No more commit/push question classifications expected");
+
+        let request = requests[*index];
+        *index += 1;
+        Ok(request)
+    }
+
+    fn suggest_answers(&self, _question: &str) -> Result<Vec<AnswerSuggestion>> {
+        let mut index = self.answer_suggestions_index.borrow_mut();
+        let suggestions = self.answer_suggestions.borrow();
+
+        assert!(*index < suggestions.len(), "No more answer suggestions expected");
+
+        let result = suggestions[*index].clone();
+        *index += 1;
+        Ok(result)
+    }
+
+    fn evaluate_protected_change(
+        &self,
+        _context: &ProtectedChangeContext,
+    ) -> Result<ProtectedChangeDecision> {
+        let mut index = self.protected_change_index.borrow_mut();
+        let decisions = self.protected_change_decisions.borrow();
+
+        assert!(*index < decisions.len(), "No more protected change decisions expected");
+
+        let decision = decisions[*index].clone();
+        *index += 1;
+        Ok(decision)
+    }
 }
 
 /// A command runner that always fails, for testing error paths.
@@ -296,7 +468,6 @@ impl CommandRunner for TimeoutCommandRunner {
         args: &[&str],
         _timeout: Option<Duration>,
     ) -> Result<CommandOutput> {
-        use crate::command::format_command;
         use crate::error::Error;
         Err(Error::CommandTimeout {
             command: format_command(program, args),
@@ -319,6 +490,244 @@ impl CommandRunner for TimeoutCommandRunner {
     }
 }
 
+/// A command and the output it produced, as stored in a fixture file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedCommand {
+    /// The program that was run.
+    pub program: String,
+    /// The arguments it was run with.
+    pub args: Vec<String>,
+    /// What the command produced.
+    pub output: CommandOutput,
+}
+
+/// The contents of a command fixture file.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommandFixture {
+    /// Commands in the order they were run.
+    pub commands: Vec<RecordedCommand>,
+    /// Results of `is_available` checks, by program.
+    #[serde(default)]
+    pub available: BTreeMap<String, bool>,
+}
+
+impl CommandFixture {
+    /// Load a fixture from a JSON file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or parsed.
+    pub fn load(path: &Path) -> Result<Self> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    /// Save the fixture as a JSON file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// A command runner that records every command it runs to a fixture file.
+///
+/// Commands are passed through to the wrapped runner (a [`RealCommandRunner`]
+/// by default), and the fixture file is rewritten after every call so that
+/// nothing is lost if the process exits early.
+#[derive(Debug)]
+pub struct RecordingCommandRunner<R: CommandRunner = RealCommandRunner> {
+    inner: R,
+    path: PathBuf,
+    fixture: Mutex<CommandFixture>,
+}
+
+impl RecordingCommandRunner {
+    /// Create a runner that runs real commands and records them to `path`.
+    #[must_use]
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self::wrapping(RealCommandRunner::new(), path)
+    }
+}
+
+impl<R: CommandRunner> RecordingCommandRunner<R> {
+    /// Create a runner that records the commands run by `inner` to `path`.
+    #[must_use]
+    pub fn wrapping(inner: R, path: impl Into<PathBuf>) -> Self {
+        Self { inner, path: path.into(), fixture: Mutex::new(CommandFixture::default()) }
+    }
+
+    /// Get the commands recorded so far.
+    #[must_use]
+    pub fn fixture(&self) -> CommandFixture {
+        self.fixture.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    /// Record a command's output and rewrite the fixture file.
+    fn record(
+        &self,
+        program: &str,
+        args: &[&str],
+        result: Result<CommandOutput>,
+    ) -> Result<CommandOutput> {
+        let output = result?;
+        self.fixture.lock().unwrap_or_else(PoisonError::into_inner).commands.push(
+            RecordedCommand {
+                program: program.to_string(),
+                args: args.iter().map(|s| (*s).to_string()).collect(),
+                output: output.clone(),
+            },
+        );
+        self.fixture.lock().unwrap_or_else(PoisonError::into_inner).save(&self.path)?;
+        Ok(output)
+    }
+}
+
+impl<R: CommandRunner> CommandRunner for RecordingCommandRunner<R> {
+    fn run(
+        &self,
+        program: &str,
+        args: &[&str],
+        timeout: Option<Duration>,
+    ) -> Result<CommandOutput> {
+        self.record(program, args, self.inner.run(program, args, timeout))
+    }
+
+    fn run_in_dir(
+        &self,
+        program: &str,
+        args: &[&str],
+        timeout: Option<Duration>,
+        cwd: &Path,
+    ) -> Result<CommandOutput> {
+        self.record(program, args, self.inner.run_in_dir(program, args, timeout, cwd))
+    }
+
+    fn run_with_options(
+        &self,
+        program: &str,
+        args: &[&str],
+        options: &RunOptions,
+    ) -> Result<CommandOutput> {
+        self.record(program, args, self.inner.run_with_options(program, args, options))
+    }
+
+    fn run_streaming(
+        &self,
+        program: &str,
+        args: &[&str],
+        options: &RunOptions,
+        stream: &StreamOptions,
+    ) -> Result<CommandOutput> {
+        self.record(program, args, self.inner.run_streaming(program, args, options, stream))
+    }
+
+    fn is_available(&self, program: &str) -> bool {
+        let available = self.inner.is_available(program);
+        self.fixture
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .available
+            .insert(program.to_string(), available);
+        let _ = self.fixture.lock().unwrap_or_else(PoisonError::into_inner).save(&self.path);
+        available
+    }
+}
+
+/// A command runner that serves outputs from a recorded fixture.
+///
+/// Each call is answered by the first unused recording of the same program
+/// and arguments, so repeated commands get their outputs in recorded order
+/// while unrelated commands may run in any order. The working directory is
+/// ignored, since recordings usually come from a different checkout.
+#[derive(Debug)]
+pub struct ReplayCommandRunner {
+    fixture: CommandFixture,
+    used: Mutex<Vec<bool>>,
+}
+
+impl ReplayCommandRunner {
+    /// Create a runner serving the given fixture.
+    #[must_use]
+    pub fn new(fixture: CommandFixture) -> Self {
+        let used = Mutex::new(vec![false; fixture.commands.len()]);
+        Self { fixture, used }
+    }
+
+    /// Create a runner serving the fixture in a file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the fixture cannot be loaded.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        Ok(Self::new(CommandFixture::load(path)?))
+    }
+
+    /// Verify every recorded command was replayed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any recorded command was not run.
+    pub fn verify(&self) {
+        let unused: Vec<String> = self
+            .fixture
+            .commands
+            .iter()
+            .zip(self.used.lock().unwrap_or_else(PoisonError::into_inner).iter())
+            .filter(|(_, used)| !**used)
+            .map(|(c, _)| {
+                format_command(&c.program, &c.args.iter().map(String::as_str).collect::<Vec<_>>())
+            })
+            .collect();
+        assert!(unused.is_empty(), "Recorded commands were not run: {unused:?}");
+    }
+}
+
+impl CommandRunner for ReplayCommandRunner {
+    /// # Panics
+    ///
+    /// Panics if the fixture has no unused recording of the command.
+    fn run(
+        &self,
+        program: &str,
+        args: &[&str],
+        _timeout: Option<Duration>,
+    ) -> Result<CommandOutput> {
+        let mut used = self.used.lock().unwrap_or_else(PoisonError::into_inner);
+        let index = self
+            .fixture
+            .commands
+            .iter()
+            .enumerate()
+            .position(|(i, c)| !used[i] && c.program == program && c.args == args)
+            .unwrap_or_else(|| {
+                panic!("No recorded output for command: {}", format_command(program, args))
+            });
+        used[index] = true;
+        drop(used);
+        Ok(self.fixture.commands[index].output.clone())
+    }
+
+    fn run_in_dir(
+        &self,
+        program: &str,
+        args: &[&str],
+        timeout: Option<Duration>,
+        _cwd: &Path,
+    ) -> Result<CommandOutput> {
+        self.run(program, args, timeout)
+    }
+
+    fn is_available(&self, program: &str) -> bool {
+        self.fixture.available.get(program).copied().unwrap_or(false)
+    }
+}
+
 /// A sub-agent that always fails, for testing error paths.
 #[derive(Debug, Default)]
 pub struct FailingSubAgent {
@@ -364,6 +773,41 @@ impl SubAgent for FailingSubAgent {
     fn evaluate_reflection(&self, _context: &ReflectionContext) -> Result<ReflectionDecision> {
         Err(std::io::Error::other(self.error_message.clone()).into())
     }
+
+    fn summarize_wip_commit(&self, _diff: &str) -> Result<String> {
+        Err(std::io::Error::other(self.error_message.clone()).into())
+    }
+
+    fn write_pr_description(&self, _context: &PrDescriptionContext) -> Result<PrDescription> {
+        Err(std::io::Error::other(self.error_message.clone()).into())
+    }
+
+    fn write_handoff(&self, _context: &HandoffContext) -> Result<String> {
+        Err(std::io::Error::other(self.error_message.clone()).into())
+    }
+
+    fn narrate_report(&self, _report: &str) -> Result<String> {
+        Err(std::io::Error::other(self.error_message.clone()).into())
+    }
+
+    fn plan_commit_split(&self, _context: &CommitSplitContext) -> Result<Vec<PlannedCommit>> {
+        Err(std::io::Error::other(self.error_message.clone()).into())
+    }
+
+    fn classify_commit_push_question(&self, _question: &str) -> Result<Option<CommitPushRequest>> {
+        Err(std::io::Error::other(self.error_message.clone()).into())
+    }
+
+    fn suggest_answers(&self, _question: &str) -> Result<Vec<AnswerSuggestion>> {
+        Err(std::io::Error::other(self.error_message.clone()).into())
+    }
+
+    fn evaluate_protected_change(
+        &self,
+        _context: &ProtectedChangeContext,
+    ) -> Result<ProtectedChangeDecision> {
+        Err(std::io::Error::other(self.error_message.clone()).into())
+    }
 }
 
 /// A mock state store for testing.
@@ -371,7 +815,7 @@ impl SubAgent for FailingSubAgent {
 /// Stores state in memory and records operations for verification.
 #[derive(Debug, Default)]
 pub struct MockStateStore {
-    markers: RefCell<HashSet<String>>,
+    markers: RefCell<HashSet<Marker>>,
 }
 
 impl MockStateStore {
@@ -382,23 +826,23 @@ impl MockStateStore {
     }
 
     /// Get all currently set markers.
-    pub fn get_markers(&self) -> HashSet<String> {
+    pub fn get_markers(&self) -> HashSet<Marker> {
         self.markers.borrow().clone()
     }
 }
 
 impl StateStore for MockStateStore {
-    fn has_marker(&self, name: &str) -> bool {
-        self.markers.borrow().contains(name)
+    fn has_marker(&self, marker: Marker) -> bool {
+        self.markers.borrow().contains(&marker)
     }
 
-    fn set_marker(&self, name: &str) -> Result<()> {
-        self.markers.borrow_mut().insert(name.to_string());
+    fn set_marker(&self, marker: Marker) -> Result<()> {
+        self.markers.borrow_mut().insert(marker);
         Ok(())
     }
 
-    fn clear_marker(&self, name: &str) -> Result<()> {
-        self.markers.borrow_mut().remove(name);
+    fn clear_marker(&self, marker: Marker) -> Result<()> {
+        self.markers.borrow_mut().remove(&marker);
         Ok(())
     }
 }
@@ -418,15 +862,15 @@ impl FailingStateStore {
 }
 
 impl StateStore for FailingStateStore {
-    fn has_marker(&self, _name: &str) -> bool {
+    fn has_marker(&self, _marker: Marker) -> bool {
         false
     }
 
-    fn set_marker(&self, _name: &str) -> Result<()> {
+    fn set_marker(&self, _marker: Marker) -> Result<()> {
         Err(std::io::Error::other(self.error_message.clone()).into())
     }
 
-    fn clear_marker(&self, _name: &str) -> Result<()> {
+    fn clear_marker(&self, _marker: Marker) -> Result<()> {
         Err(std::io::Error::other(self.error_message.clone()).into())
     }
 }
@@ -466,30 +910,294 @@ impl FailingSetMarkerStore {
 }
 
 impl StateStore for FailingClearMarkerStore {
-    fn has_marker(&self, name: &str) -> bool {
-        self.inner.has_marker(name)
+    fn has_marker(&self, marker: Marker) -> bool {
+        self.inner.has_marker(marker)
     }
 
-    fn set_marker(&self, name: &str) -> Result<()> {
-        self.inner.set_marker(name)
+    fn set_marker(&self, marker: Marker) -> Result<()> {
+        self.inner.set_marker(marker)
     }
 
-    fn clear_marker(&self, _name: &str) -> Result<()> {
+    fn clear_marker(&self, _marker: Marker) -> Result<()> {
         Err(std::io::Error::other(self.error_message.clone()).into())
     }
 }
 
 impl StateStore for FailingSetMarkerStore {
-    fn has_marker(&self, name: &str) -> bool {
-        self.inner.has_marker(name)
+    fn has_marker(&self, marker: Marker) -> bool {
+        self.inner.has_marker(marker)
     }
 
-    fn set_marker(&self, _name: &str) -> Result<()> {
+    fn set_marker(&self, _marker: Marker) -> Result<()> {
         Err(std::io::Error::other(self.error_message.clone()).into())
     }
 
-    fn clear_marker(&self, name: &str) -> Result<()> {
-        self.inner.clear_marker(name)
+    fn clear_marker(&self, marker: Marker) -> Result<()> {
+        self.inner.clear_marker(marker)
+    }
+}
+
+/// A hook observer that records every event, and optionally vetoes allow decisions.
+#[derive(Debug, Default)]
+pub struct RecordingObserver {
+    events: RefCell<Vec<String>>,
+    veto: Option<String>,
+}
+
+impl RecordingObserver {
+    /// Create an observer that records events without vetoing anything.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create an observer that vetoes every allow decision with `message`.
+    #[must_use]
+    pub fn vetoing(message: &str) -> Self {
+        Self { veto: Some(message.to_string()), ..Self::default() }
+    }
+
+    /// Get the recorded events, such as `start stop/api_error_loop`,
+    /// `end stop/api_error_loop: no errors` and `decision stop: allow`.
+    pub fn events(&self) -> Vec<String> {
+        self.events.borrow().clone()
+    }
+}
+
+impl HookObserver for RecordingObserver {
+    fn on_check_start(&self, hook: &str, check: &str) {
+        self.events.borrow_mut().push(format!("start {hook}/{check}"));
+    }
+
+    fn on_check_end(&self, hook: &str, check: &str, outcome: &str) {
+        self.events.borrow_mut().push(format!("end {hook}/{check}: {outcome}"));
+    }
+
+    fn on_decision(&self, decision: &HookDecision<'_>) -> Option<String> {
+        let verdict = if decision.allow { "allow" } else { "block" };
+        self.events.borrow_mut().push(format!("decision {}: {verdict}", decision.hook));
+        self.veto.clone()
+    }
+}
+
+/// Builds [`HookInput`] values, or their JSON form for hook commands' stdin.
+#[derive(Debug, Default, Clone)]
+pub struct HookInputBuilder {
+    input: HookInput,
+}
+
+impl HookInputBuilder {
+    /// Create a builder for an empty hook input.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the transcript path.
+    #[must_use]
+    pub fn transcript_path(mut self, path: impl AsRef<Path>) -> Self {
+        self.input.transcript_path = Some(path.as_ref().to_string_lossy().into_owned());
+        self
+    }
+
+    /// Set the tool being called.
+    #[must_use]
+    pub fn tool(mut self, name: &str) -> Self {
+        self.input.tool_name = Some(name.to_string());
+        self
+    }
+
+    /// Make this a `Bash` tool call running `command`.
+    #[must_use]
+    pub fn bash(mut self, command: &str) -> Self {
+        self.input.tool_name = Some("Bash".to_string());
+        self.tool_input().command = Some(command.to_string());
+        self
+    }
+
+    /// Make this a `Skill` tool call invoking `skill`.
+    #[must_use]
+    pub fn skill(mut self, skill: &str) -> Self {
+        self.input.tool_name = Some("Skill".to_string());
+        self.tool_input().skill = Some(skill.to_string());
+        self
+    }
+
+    /// Make this a call to a file tool (e.g. `Write` or `Edit`) on `path`.
+    #[must_use]
+    pub fn file_tool(mut self, tool: &str, path: &str) -> Self {
+        self.input.tool_name = Some(tool.to_string());
+        self.tool_input().file_path = Some(path.to_string());
+        self
+    }
+
+    /// Get the tool input, creating it if needed.
+    fn tool_input(&mut self) -> &mut ToolInput {
+        self.input.tool_input.get_or_insert_with(ToolInput::default)
+    }
+
+    /// Build the hook input.
+    #[must_use]
+    pub fn build(self) -> HookInput {
+        self.input
+    }
+
+    /// Build the hook input as JSON, as Claude Code sends it on stdin.
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        let tool_input = self.input.tool_input.as_ref().map(|t| {
+            serde_json::json!({
+                "command": t.command,
+                "skill": t.skill,
+                "file_path": t.file_path,
+            })
+        });
+        serde_json::json!({
+            "transcript_path": self.input.transcript_path,
+            "tool_name": self.input.tool_name,
+            "tool_input": tool_input,
+        })
+        .to_string()
+    }
+}
+
+/// Builds [`TranscriptInfo`] values without writing a transcript.
+#[derive(Debug, Default, Clone)]
+pub struct TranscriptInfoBuilder {
+    info: TranscriptInfo,
+}
+
+impl TranscriptInfoBuilder {
+    /// Create a builder for an empty transcript.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the last assistant output.
+    #[must_use]
+    pub fn assistant_output(mut self, text: &str) -> Self {
+        self.info.last_assistant_output = Some(text.to_string());
+        self
+    }
+
+    /// Add a user message sent at `time`.
+    ///
+    /// The first call also sets the first user message.
+    #[must_use]
+    pub fn user_message(mut self, text: &str, time: DateTime<Utc>) -> Self {
+        self.info.first_user_message.get_or_insert_with(|| text.to_string());
+        self.info.last_user_message = Some(text.to_string());
+        self.info.last_user_message_time = Some(time);
+        self.info.has_modifying_tool_use_since_user = false;
+        self
+    }
+
+    /// Record a modifying tool use since the last user message.
+    #[must_use]
+    pub const fn modifying_tool_use(mut self) -> Self {
+        self.info.has_modifying_tool_use = true;
+        self.info.has_modifying_tool_use_since_user = true;
+        self
+    }
+
+    /// End the transcript with `count` consecutive API errors.
+    #[must_use]
+    pub const fn api_errors(mut self, count: u32, overloaded: bool) -> Self {
+        self.info.has_api_error = count > 0;
+        self.info.consecutive_api_errors = count;
+        self.info.last_error_is_overloaded = overloaded && count > 0;
+        self
+    }
+
+    /// Build the transcript info.
+    #[must_use]
+    pub fn build(self) -> TranscriptInfo {
+        self.info
+    }
+}
+
+/// Builds Claude Code JSONL transcripts for tests.
+#[derive(Debug, Default, Clone)]
+pub struct TranscriptBuilder {
+    entries: Vec<serde_json::Value>,
+}
+
+impl TranscriptBuilder {
+    /// Create a builder for an empty transcript.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a user message without a timestamp.
+    #[must_use]
+    pub fn user(self, text: &str) -> Self {
+        self.entry(serde_json::json!({"type": "user", "message": {"content": text}}))
+    }
+
+    /// Add a user message sent at `time`.
+    #[must_use]
+    pub fn user_at(self, text: &str, time: DateTime<Utc>) -> Self {
+        self.entry(serde_json::json!({
+            "type": "user",
+            "timestamp": time.to_rfc3339(),
+            "message": {"content": text},
+        }))
+    }
+
+    /// Add an assistant text message.
+    #[must_use]
+    pub fn assistant(self, text: &str) -> Self {
+        self.entry(serde_json::json!({
+            "type": "assistant",
+            "message": {"content": [{"type": "text", "text": text}]},
+        }))
+    }
+
+    /// Add an assistant tool call.
+    #[must_use]
+    pub fn tool_use(self, name: &str) -> Self {
+        self.entry(serde_json::json!({
+            "type": "assistant",
+            "message": {"content": [{"type": "tool_use", "id": "toolu_test", "name": name, "input": {}}]},
+        }))
+    }
+
+    /// Add an API error message.
+    #[must_use]
+    pub fn api_error(self, text: &str) -> Self {
+        self.entry(serde_json::json!({
+            "type": "assistant",
+            "isApiErrorMessage": true,
+            "message": {"content": [{"type": "text", "text": text}]},
+        }))
+    }
+
+    /// Add an arbitrary entry.
+    #[must_use]
+    pub fn entry(mut self, entry: serde_json::Value) -> Self {
+        self.entries.push(entry);
+        self
+    }
+
+    /// Get the transcript as JSONL.
+    #[must_use]
+    pub fn to_jsonl(&self) -> String {
+        self.entries.iter().fold(String::new(), |mut out, e| {
+            let _ = writeln!(out, "{e}");
+            out
+        })
+    }
+
+    /// Write the transcript to a file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    pub fn write_to(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, self.to_jsonl())?;
+        Ok(())
     }
 }
 
@@ -640,6 +1348,38 @@ impl crate::tasks::TaskStore for FailingTaskStore {
         Ok(None)
     }
 
+    fn defer_question(
+        &self,
+        _id: &str,
+        _until: Option<&str>,
+    ) -> Result<Option<crate::tasks::Question>> {
+        Ok(None)
+    }
+
+    fn set_question_priority(
+        &self,
+        _id: &str,
+        _priority: crate::tasks::Priority,
+    ) -> Result<Option<crate::tasks::Question>> {
+        Ok(None)
+    }
+
+    fn assume_answer(&self, _id: &str, _answer: &str) -> Result<Option<crate::tasks::Question>> {
+        Ok(None)
+    }
+
+    fn reopen_question(&self, _id: &str) -> Result<Option<crate::tasks::Question>> {
+        Ok(None)
+    }
+
+    fn set_question_suggestions(
+        &self,
+        _id: &str,
+        _suggestions: &[crate::tasks::AnswerSuggestion],
+    ) -> Result<Option<crate::tasks::Question>> {
+        Ok(None)
+    }
+
     fn delete_question(&self, _id: &str) -> Result<bool> {
         Ok(false)
     }
@@ -724,6 +1464,10 @@ impl crate::tasks::TaskStore for FailingTaskStore {
     fn mark_pre_compaction(&self, _session_id: &str) -> Result<()> {
         Ok(())
     }
+
+    fn scrub_user_messages(&self) -> Result<usize> {
+        Err(crate::error::Error::Config(self.error_message.clone()))
+    }
 }
 
 #[cfg(test)]
@@ -805,7 +1549,10 @@ mod tests {
             "Blocked by missing credentials".to_string(),
         )));
 
-        let context = EmergencyStopContext { explanation: "Cannot proceed".to_string() };
+        let context = EmergencyStopContext {
+            explanation: "Cannot proceed".to_string(),
+            category: "technical problem".to_string(),
+        };
         let decision = agent.evaluate_emergency_stop(&context).unwrap();
 
         assert!(matches!(
@@ -821,7 +1568,10 @@ mod tests {
             "Use the skill instead".to_string(),
         ));
 
-        let context = EmergencyStopContext { explanation: "Too much work".to_string() };
+        let context = EmergencyStopContext {
+            explanation: "Too much work".to_string(),
+            category: "technical problem".to_string(),
+        };
         let decision = agent.evaluate_emergency_stop(&context).unwrap();
 
         assert!(matches!(
@@ -834,7 +1584,10 @@ mod tests {
     #[should_panic(expected = "No more emergency stop decisions expected")]
     fn test_mock_sub_agent_emergency_stop_no_expectations() {
         let agent = MockSubAgent::new();
-        let context = EmergencyStopContext { explanation: "test".to_string() };
+        let context = EmergencyStopContext {
+            explanation: "test".to_string(),
+            category: "technical problem".to_string(),
+        };
         let _ = agent.evaluate_emergency_stop(&context);
     }
 
@@ -883,6 +1636,7 @@ mod tests {
         let context = ReflectionContext {
             reflection_output: "All done".to_string(),
             user_messages: vec!["Fix bug".to_string()],
+            unchecked_criteria: vec![],
         };
         let decision = agent.evaluate_reflection(&context).unwrap();
 
@@ -899,6 +1653,7 @@ mod tests {
         let context = ReflectionContext {
             reflection_output: "Still need to do X and Y".to_string(),
             user_messages: vec![],
+            unchecked_criteria: vec![],
         };
         let decision = agent.evaluate_reflection(&context).unwrap();
 
@@ -911,8 +1666,11 @@ mod tests {
     #[test]
     fn test_mock_sub_agent_reflection_no_expectations_defaults_to_complete() {
         let agent = MockSubAgent::new();
-        let context =
-            ReflectionContext { reflection_output: "test".to_string(), user_messages: vec![] };
+        let context = ReflectionContext {
+            reflection_output: "test".to_string(),
+            user_messages: vec![],
+            unchecked_criteria: vec![],
+        };
         let decision = agent.evaluate_reflection(&context).unwrap();
 
         assert_eq!(decision, ReflectionDecision::Complete);
@@ -924,13 +1682,30 @@ mod tests {
         let mut agent = MockSubAgent::new();
         agent.expect_reflection(ReflectionDecision::Complete);
 
-        let context =
-            ReflectionContext { reflection_output: "test".to_string(), user_messages: vec![] };
+        let context = ReflectionContext {
+            reflection_output: "test".to_string(),
+            user_messages: vec![],
+            unchecked_criteria: vec![],
+        };
         let _ = agent.evaluate_reflection(&context).unwrap();
         // Second call should panic
         let _ = agent.evaluate_reflection(&context);
     }
 
+    #[test]
+    fn test_mock_sub_agent_wip_summary() {
+        let mut agent = MockSubAgent::new();
+        agent.expect_wip_summary("Add parser");
+        assert_eq!(agent.summarize_wip_commit("diff").unwrap(), "Add parser");
+    }
+
+    #[test]
+    #[should_panic(expected = "No more WIP summaries expected")]
+    fn test_mock_sub_agent_wip_summary_too_many_calls() {
+        let agent = MockSubAgent::new();
+        let _ = agent.summarize_wip_commit("diff");
+    }
+
     #[test]
     #[should_panic(expected = "No more create_question decisions expected")]
     fn test_mock_sub_agent_create_question_too_many_calls() {
@@ -1007,7 +1782,10 @@ mod tests {
         assert!(agent.decide_on_question(&test_context("test")).is_err());
         assert!(agent.review_code("diff", &[], None).is_err());
         assert!(agent
-            .evaluate_emergency_stop(&EmergencyStopContext { explanation: "test".to_string() })
+            .evaluate_emergency_stop(&EmergencyStopContext {
+                explanation: "test".to_string(),
+                category: "technical problem".to_string(),
+            })
             .is_err());
         assert!(agent
             .evaluate_create_question(&CreateQuestionContext { question_text: "test".to_string() })
@@ -1016,8 +1794,16 @@ mod tests {
             .evaluate_reflection(&ReflectionContext {
                 reflection_output: "test".to_string(),
                 user_messages: vec![],
+                unchecked_criteria: vec![],
             })
             .is_err());
+        assert!(agent.summarize_wip_commit("diff").is_err());
+        assert!(agent.write_pr_description(&PrDescriptionContext::default()).is_err());
+        assert!(agent.write_handoff(&HandoffContext::default()).is_err());
+        assert!(agent.narrate_report("# Activity Report").is_err());
+        assert!(agent.plan_commit_split(&CommitSplitContext::default()).is_err());
+        assert!(agent.classify_commit_push_question("Commit?").is_err());
+        assert!(agent.suggest_answers("Which database?").is_err());
     }
 
     #[test]
@@ -1025,17 +1811,17 @@ mod tests {
         let store = MockStateStore::new();
 
         // Initially no markers
-        assert!(!store.has_marker("test"));
+        assert!(!store.has_marker(Marker::MustReflect));
         assert!(store.get_markers().is_empty());
 
         // Set marker
-        store.set_marker("test").unwrap();
-        assert!(store.has_marker("test"));
-        assert!(store.get_markers().contains("test"));
+        store.set_marker(Marker::MustReflect).unwrap();
+        assert!(store.has_marker(Marker::MustReflect));
+        assert!(store.get_markers().contains(&Marker::MustReflect));
 
         // Clear marker
-        store.clear_marker("test").unwrap();
-        assert!(!store.has_marker("test"));
+        store.clear_marker(Marker::MustReflect).unwrap();
+        assert!(!store.has_marker(Marker::MustReflect));
     }
 
     #[test]
@@ -1043,9 +1829,9 @@ mod tests {
         let store = FailingStateStore::new("test error");
 
         // All methods should return errors or false
-        assert!(!store.has_marker("test"));
-        assert!(store.set_marker("test").is_err());
-        assert!(store.clear_marker("test").is_err());
+        assert!(!store.has_marker(Marker::MustReflect));
+        assert!(store.set_marker(Marker::MustReflect).is_err());
+        assert!(store.clear_marker(Marker::MustReflect).is_err());
     }
 
     #[test]
@@ -1054,10 +1840,10 @@ mod tests {
         let store = FailingClearMarkerStore::new(inner, "clear failed");
 
         // Marker methods: has_marker and set_marker should delegate, clear_marker should fail
-        assert!(!store.has_marker("test"));
-        store.set_marker("test").unwrap();
-        assert!(store.has_marker("test"));
-        assert!(store.clear_marker("test").is_err()); // This should fail
+        assert!(!store.has_marker(Marker::MustReflect));
+        store.set_marker(Marker::MustReflect).unwrap();
+        assert!(store.has_marker(Marker::MustReflect));
+        assert!(store.clear_marker(Marker::MustReflect).is_err()); // This should fail
     }
 
     #[test]
@@ -1066,9 +1852,9 @@ mod tests {
         let store = FailingSetMarkerStore::new(inner, "set failed");
 
         // Marker methods: has_marker and clear_marker should delegate, set_marker should fail
-        assert!(!store.has_marker("test"));
-        assert!(store.set_marker("test").is_err());
-        store.clear_marker("test").unwrap();
+        assert!(!store.has_marker(Marker::MustReflect));
+        assert!(store.set_marker(Marker::MustReflect).is_err());
+        store.clear_marker(Marker::MustReflect).unwrap();
     }
 
     #[test]
@@ -1121,6 +1907,11 @@ mod tests {
         // Questions
         assert!(store.get_question("id").unwrap().is_none());
         assert!(store.answer_question("id", "a").unwrap().is_none());
+        assert!(store.defer_question("id", None).unwrap().is_none());
+        assert!(store.set_question_priority("id", Priority::High).unwrap().is_none());
+        assert!(store.set_question_suggestions("id", &[]).unwrap().is_none());
+        assert!(store.assume_answer("id", "yes").unwrap().is_none());
+        assert!(store.reopen_question("id").unwrap().is_none());
         assert!(!store.delete_question("id").unwrap());
         assert!(store.list_questions(false).unwrap().is_empty());
         assert!(store.search_questions("q").unwrap().is_empty());
@@ -1142,4 +1933,125 @@ mod tests {
         store.clear_user_messages_for_session("session").unwrap();
         store.mark_pre_compaction("session").unwrap();
     }
+
+    #[test]
+    fn test_record_and_replay_commands() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("fixtures/echo.json");
+
+        let mut inner = MockCommandRunner::new();
+        let output = |stdout: &str| CommandOutput {
+            exit_code: 0,
+            stdout: stdout.to_string(),
+            stderr: String::new(),
+        };
+        inner.expect("git", &["status"], output("first"));
+        inner.expect("git", &["log"], output("log"));
+        inner.expect("git", &["status"], output("second"));
+        inner.set_available("git");
+        let recorder = RecordingCommandRunner::wrapping(inner, &path);
+        recorder.run("git", &["status"], None).unwrap();
+        recorder.run_in_dir("git", &["log"], None, dir.path()).unwrap();
+        recorder.run("git", &["status"], None).unwrap();
+        assert!(recorder.is_available("git"));
+
+        let replay = ReplayCommandRunner::from_file(&path).unwrap();
+        assert_eq!(replay.run("git", &["log"], None).unwrap().stdout, "log");
+        assert_eq!(replay.run("git", &["status"], None).unwrap().stdout, "first");
+        assert_eq!(
+            replay.run_in_dir("git", &["status"], None, Path::new("/")).unwrap().stdout,
+            "second"
+        );
+        assert!(replay.is_available("git"));
+        assert!(!replay.is_available("gh"));
+        replay.verify();
+    }
+
+    #[test]
+    fn test_recording_real_commands() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("echo.json");
+        let recorder = RecordingCommandRunner::new(&path);
+        recorder.run("echo", &["hello"], None).unwrap();
+
+        let fixture = CommandFixture::load(&path).unwrap();
+        assert_eq!(fixture, recorder.fixture());
+        assert_eq!(fixture.commands[0].args, vec!["hello"]);
+        assert_eq!(fixture.commands[0].output.stdout, "hello\n");
+    }
+
+    #[test]
+    #[should_panic(expected = "No recorded output for command: git push")]
+    fn test_replay_unrecorded_command_panics() {
+        let replay = ReplayCommandRunner::new(CommandFixture::default());
+        let _ = replay.run("git", &["push"], None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Recorded commands were not run")]
+    fn test_replay_verify_unused_commands() {
+        let fixture = CommandFixture {
+            commands: vec![RecordedCommand {
+                program: "git".to_string(),
+                args: vec![],
+                output: CommandOutput::default(),
+            }],
+            ..Default::default()
+        };
+        ReplayCommandRunner::new(fixture).verify();
+    }
+
+    #[test]
+    fn test_hook_input_builder() {
+        let input = HookInputBuilder::new().transcript_path("/t.jsonl").bash("git status").build();
+        assert_eq!(input.transcript_path.as_deref(), Some("/t.jsonl"));
+        assert_eq!(input.tool_name.as_deref(), Some("Bash"));
+        assert_eq!(input.tool_input.unwrap().command.as_deref(), Some("git status"));
+
+        let json = HookInputBuilder::new().file_tool("Write", "src/lib.rs").to_json();
+        let parsed = crate::hooks::parse_hook_input(&json).unwrap();
+        assert_eq!(parsed.tool_name.as_deref(), Some("Write"));
+        assert_eq!(parsed.tool_input.unwrap().file_path.as_deref(), Some("src/lib.rs"));
+
+        let input = HookInputBuilder::new().skill("task-management").build();
+        assert_eq!(input.tool_input.unwrap().skill.as_deref(), Some("task-management"));
+    }
+
+    #[test]
+    fn test_transcript_info_builder() {
+        let time = Utc::now();
+        let info = TranscriptInfoBuilder::new()
+            .user_message("first", time)
+            .modifying_tool_use()
+            .user_message("second", time)
+            .assistant_output("Done")
+            .api_errors(2, true)
+            .build();
+        assert_eq!(info.first_user_message.as_deref(), Some("first"));
+        assert_eq!(info.last_user_message.as_deref(), Some("second"));
+        assert!(info.has_modifying_tool_use);
+        assert!(!info.has_modifying_tool_use_since_user);
+        assert_eq!(info.consecutive_api_errors, 2);
+        assert!(info.last_error_is_overloaded);
+    }
+
+    #[test]
+    fn test_transcript_builder_round_trips_through_parser() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("transcript.jsonl");
+        TranscriptBuilder::new()
+            .user_at("Fix the bug", Utc::now())
+            .tool_use("Edit")
+            .assistant("Fixed it.")
+            .api_error("API Error: 529 overloaded")
+            .write_to(&path)
+            .unwrap();
+
+        let info = crate::transcript::parse_transcript(&path).unwrap();
+        assert_eq!(info.first_user_message.as_deref(), Some("Fix the bug"));
+        assert!(info.last_user_message_time.is_some());
+        assert!(info.has_modifying_tool_use_since_user);
+        assert_eq!(info.last_assistant_output.as_deref(), Some("API Error: 529 overloaded"));
+        assert_eq!(info.consecutive_api_errors, 1);
+    }
 }
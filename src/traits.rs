@@ -1,10 +1,13 @@
 //! Core traits for testability and abstraction.
 
 use crate::error::Result;
+use crate::storage::Marker;
+use crate::tasks::AnswerSuggestion;
+use std::path::PathBuf;
 use std::time::Duration;
 
 /// Output from a command execution.
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct CommandOutput {
     /// The exit code of the command.
     pub exit_code: i32,
@@ -34,10 +37,100 @@ impl CommandOutput {
     }
 }
 
+/// Options controlling how a command is run.
+///
+/// The default runs the command in the current directory with the full
+/// environment, no timeout and no limit on output.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RunOptions {
+    /// Kill the command if it runs longer than this.
+    pub timeout: Option<Duration>,
+    /// Directory to run the command in.
+    pub cwd: Option<PathBuf>,
+    /// When set, the environment is cleared and only these variables are
+    /// passed through from the current process.
+    pub env_allowlist: Option<Vec<String>>,
+    /// Variables to set in the command's environment.
+    pub env: Vec<(String, String)>,
+    /// Scheduling priority adjustment, applied by running under `nice`.
+    pub niceness: Option<i32>,
+    /// Maximum bytes of stdout, and separately of stderr, to keep.
+    /// Output beyond this is read and discarded.
+    pub max_output_bytes: Option<usize>,
+}
+
+impl RunOptions {
+    /// Create options with the defaults.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the timeout.
+    #[must_use]
+    pub const fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Set the working directory.
+    #[must_use]
+    pub fn cwd(mut self, cwd: impl Into<PathBuf>) -> Self {
+        self.cwd = Some(cwd.into());
+        self
+    }
+
+    /// Pass through only the named variables from the current environment.
+    #[must_use]
+    pub fn env_allowlist<I, S>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.env_allowlist = Some(names.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Set a variable in the command's environment.
+    #[must_use]
+    pub fn env(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env.push((name.into(), value.into()));
+        self
+    }
+
+    /// Set the niceness.
+    #[must_use]
+    pub const fn niceness(mut self, niceness: i32) -> Self {
+        self.niceness = Some(niceness);
+        self
+    }
+
+    /// Limit how much of each output stream is kept.
+    #[must_use]
+    pub const fn max_output_bytes(mut self, max: usize) -> Self {
+        self.max_output_bytes = Some(max);
+        self
+    }
+}
+
+/// How to report a command's output while it runs.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StreamOptions {
+    /// File to write the output to as it is produced, replacing any previous contents.
+    pub log_file: Option<PathBuf>,
+    /// Whether to copy the output to stderr as it is produced.
+    pub echo: bool,
+    /// How often to print a "still running" line to stderr.
+    pub heartbeat: Option<Duration>,
+    /// Keep only the last this many lines of each stream in the result.
+    pub tail_lines: Option<usize>,
+}
+
 /// Trait for running shell commands.
 ///
-/// This trait abstracts command execution for testability.
-pub trait CommandRunner {
+/// This trait abstracts command execution for testability. Runners are
+/// shared with worker threads, so they must be `Sync`.
+pub trait CommandRunner: Sync {
     /// Run a command with the given arguments and timeout.
     ///
     /// # Arguments
@@ -80,6 +173,48 @@ pub trait CommandRunner {
         cwd: &std::path::Path,
     ) -> Result<CommandOutput>;
 
+    /// Run a command with the given options.
+    ///
+    /// The default implementation honours only the timeout and working
+    /// directory, delegating to [`run`](Self::run) or
+    /// [`run_in_dir`](Self::run_in_dir). [`RealCommandRunner`] applies
+    /// all options.
+    ///
+    /// [`RealCommandRunner`]: crate::command::RealCommandRunner
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the command cannot be spawned or executed.
+    fn run_with_options(
+        &self,
+        program: &str,
+        args: &[&str],
+        options: &RunOptions,
+    ) -> Result<CommandOutput> {
+        options.cwd.as_deref().map_or_else(
+            || self.run(program, args, options.timeout),
+            |cwd| self.run_in_dir(program, args, options.timeout, cwd),
+        )
+    }
+
+    /// Run a command, reporting its output while it runs.
+    ///
+    /// The default implementation does not stream, and runs the command with
+    /// [`run_with_options`](Self::run_with_options).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the command cannot be spawned or executed.
+    fn run_streaming(
+        &self,
+        program: &str,
+        args: &[&str],
+        options: &RunOptions,
+        _stream: &StreamOptions,
+    ) -> Result<CommandOutput> {
+        self.run_with_options(program, args, options)
+    }
+
     /// Check if a program is available in PATH.
     fn is_available(&self, program: &str) -> bool;
 }
@@ -199,6 +334,132 @@ pub trait SubAgent {
     ///
     /// Returns an error if the sub-agent call fails.
     fn evaluate_reflection(&self, context: &ReflectionContext) -> Result<ReflectionDecision>;
+
+    /// Summarize a diff as a one-line WIP commit message.
+    ///
+    /// # Arguments
+    ///
+    /// * `diff` - The staged diff to summarize.
+    ///
+    /// # Returns
+    ///
+    /// A short summary of the changes, without any `wip:` prefix.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the sub-agent call fails.
+    fn summarize_wip_commit(&self, diff: &str) -> Result<String>;
+
+    /// Write a title and description for a pull request.
+    ///
+    /// # Arguments
+    ///
+    /// * `context` - The branch, commits and work items the pull request covers.
+    ///
+    /// # Returns
+    ///
+    /// The pull request title and body.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the sub-agent call fails.
+    fn write_pr_description(&self, context: &PrDescriptionContext) -> Result<PrDescription>;
+
+    /// Write a handoff summary of a finished autonomous run.
+    ///
+    /// # Arguments
+    ///
+    /// * `context` - The work done in the run and what remains.
+    ///
+    /// # Returns
+    ///
+    /// The summary as markdown.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the sub-agent call fails.
+    fn write_handoff(&self, context: &HandoffContext) -> Result<String>;
+
+    /// Write a short narrative summary of an activity report.
+    ///
+    /// # Arguments
+    ///
+    /// * `report` - The report as markdown.
+    ///
+    /// # Returns
+    ///
+    /// A paragraph summarizing the report, or an empty string if none could
+    /// be written.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the sub-agent call fails.
+    fn narrate_report(&self, report: &str) -> Result<String>;
+
+    /// Propose how to split a large set of uncommitted changes into commits.
+    ///
+    /// # Arguments
+    ///
+    /// * `context` - The changed files and a summary of the diff.
+    ///
+    /// # Returns
+    ///
+    /// The proposed commits, in order. Empty if no split could be proposed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the sub-agent call fails.
+    fn plan_commit_split(&self, context: &CommitSplitContext) -> Result<Vec<PlannedCommit>>;
+
+    /// Classify whether a question asks for permission to commit or push.
+    ///
+    /// # Arguments
+    ///
+    /// * `question` - The final question from the agent's last message.
+    ///
+    /// # Returns
+    ///
+    /// What the question asks permission for, or `None` if it is not a
+    /// commit/push confirmation question.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the sub-agent call fails.
+    fn classify_commit_push_question(&self, question: &str) -> Result<Option<CommitPushRequest>>;
+
+    /// Draft candidate answers to a question, for the user to pick from.
+    ///
+    /// # Arguments
+    ///
+    /// * `question` - The question text.
+    ///
+    /// # Returns
+    ///
+    /// Two or three answers with their trade-offs, recommended first, or none
+    /// if the question has no sensible options.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the sub-agent call fails.
+    fn suggest_answers(&self, question: &str) -> Result<Vec<AnswerSuggestion>>;
+
+    /// Evaluate an agent's justification for changing protected plugin files.
+    ///
+    /// # Arguments
+    ///
+    /// * `context` - The justification the agent gave.
+    ///
+    /// # Returns
+    ///
+    /// The sub-agent's decision on whether to allow the change.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the sub-agent call fails.
+    fn evaluate_protected_change(
+        &self,
+        context: &ProtectedChangeContext,
+    ) -> Result<ProtectedChangeDecision>;
 }
 
 /// Context for emergency stop evaluation.
@@ -206,6 +467,8 @@ pub trait SubAgent {
 pub struct EmergencyStopContext {
     /// The agent's explanation for wanting to stop.
     pub explanation: String,
+    /// The kind of blocker the explanation refers to (e.g. "missing credentials or access").
+    pub category: String,
 }
 
 /// Decision from a sub-agent about whether to allow an emergency stop.
@@ -233,6 +496,23 @@ pub enum CreateQuestionDecision {
     Create,
 }
 
+/// Context for evaluating a request to change protected plugin files.
+#[derive(Debug, Clone)]
+pub struct ProtectedChangeContext {
+    /// Why the agent says it needs to change the plugin's configuration,
+    /// database or hook settings.
+    pub justification: String,
+}
+
+/// Decision from a sub-agent about whether to allow a protected change.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProtectedChangeDecision {
+    /// Allow the change.
+    Approve,
+    /// Refuse the change, with the reason given to the agent.
+    Reject(String),
+}
+
 /// Context for reflection evaluation.
 #[derive(Debug, Clone)]
 pub struct ReflectionContext {
@@ -240,6 +520,8 @@ pub struct ReflectionContext {
     pub reflection_output: String,
     /// User messages from the session.
     pub user_messages: Vec<String>,
+    /// Unticked acceptance criteria of the work being done.
+    pub unchecked_criteria: Vec<String>,
 }
 
 /// Decision from sub-agent about whether reflection indicates completion.
@@ -254,25 +536,178 @@ pub enum ReflectionDecision {
     },
 }
 
+/// Context for writing a pull request description.
+#[derive(Debug, Clone, Default)]
+pub struct PrDescriptionContext {
+    /// The branch the pull request is for.
+    pub branch: String,
+    /// Subjects of the commits on the branch.
+    pub commits: Vec<String>,
+    /// Titles of the completed work items the branch addresses.
+    pub work_items: Vec<String>,
+}
+
+/// A pull request title and body.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrDescription {
+    /// The pull request title.
+    pub title: String,
+    /// The pull request body (markdown).
+    pub body: String,
+}
+
+/// Context for writing a session handoff summary.
+#[derive(Debug, Clone, Default)]
+pub struct HandoffContext {
+    /// Titles of the work items completed during the run.
+    pub completed_work_items: Vec<String>,
+    /// Subjects of the commits made during the run.
+    pub commits: Vec<String>,
+    /// Questions still waiting for the user.
+    pub open_questions: Vec<String>,
+    /// Questions answered with an assumption because nobody answered them,
+    /// each with the assumed answer.
+    pub assumptions: Vec<String>,
+    /// Problems reported when the agent stopped because it could not go on.
+    pub problems: Vec<String>,
+    /// Titles of the work items still open.
+    pub open_work_items: Vec<String>,
+    /// The agent's final message, if any.
+    pub final_message: Option<String>,
+}
+
+/// Context for planning how to split uncommitted changes into commits.
+#[derive(Debug, Clone, Default)]
+pub struct CommitSplitContext {
+    /// Paths of all changed and untracked files.
+    pub files: Vec<String>,
+    /// `git diff --stat` summary of the changes to tracked files.
+    pub diff_stat: String,
+}
+
+/// One commit in a proposed split of uncommitted changes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedCommit {
+    /// The commit message.
+    pub message: String,
+    /// Paths of the files to include in the commit.
+    pub files: Vec<String>,
+}
+
+/// What a commit/push confirmation question asks permission for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommitPushRequest {
+    /// Permission to commit.
+    Commit,
+    /// Permission to push.
+    Push,
+    /// Permission to commit and then push.
+    CommitAndPush,
+}
+
+impl CommitPushRequest {
+    /// The reply that confirms the request.
+    #[must_use]
+    pub const fn confirmation(self) -> &'static str {
+        match self {
+            Self::Commit => "Yes, please commit these changes.",
+            Self::Push => "Yes, please push.",
+            Self::CommitAndPush => "Yes, please commit and push.",
+        }
+    }
+
+    /// The action asked for, e.g. `"commit and push"`.
+    #[must_use]
+    pub const fn action(self) -> &'static str {
+        match self {
+            Self::Commit => "commit",
+            Self::Push => "push",
+            Self::CommitAndPush => "commit and push",
+        }
+    }
+
+    /// Whether the request includes committing.
+    #[must_use]
+    pub const fn includes_commit(self) -> bool {
+        matches!(self, Self::Commit | Self::CommitAndPush)
+    }
+
+    /// Whether the request includes pushing.
+    #[must_use]
+    pub const fn includes_push(self) -> bool {
+        matches!(self, Self::Push | Self::CommitAndPush)
+    }
+}
+
 /// Trait for persistent state storage.
 ///
 /// This trait abstracts state storage operations for testability.
 /// The production implementation uses `SQLite`, while tests use an in-memory mock.
 pub trait StateStore {
     /// Check if a boolean marker is set.
-    fn has_marker(&self, name: &str) -> bool;
+    fn has_marker(&self, marker: Marker) -> bool;
 
     /// Set a boolean marker.
     ///
     /// # Errors
     ///
     /// Returns an error if the database operation fails.
-    fn set_marker(&self, name: &str) -> Result<()>;
+    fn set_marker(&self, marker: Marker) -> Result<()>;
 
     /// Clear a boolean marker.
     ///
     /// # Errors
     ///
     /// Returns an error if the database operation fails.
-    fn clear_marker(&self, name: &str) -> Result<()>;
+    fn clear_marker(&self, marker: Marker) -> Result<()>;
 }
+
+/// Source of how long the user has been away from their computer.
+///
+/// The stop hook uses this alongside transcript timestamps, so auto-work does
+/// not start while the user is busy in another window.
+pub trait IdleProvider {
+    /// Minutes since the user was last active, or `None` if unknown.
+    fn idle_minutes(&self) -> Option<u32>;
+}
+
+/// A decision made by a hook, reported to [`HookObserver::on_decision`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HookDecision<'a> {
+    /// The hook that decided: `stop`, `pre_tool_use`, `post_tool_use`,
+    /// `user_prompt_submit` or `session_start`.
+    pub hook: &'a str,
+    /// Whether the stop or tool call is allowed.
+    pub allow: bool,
+    /// The check that made the decision, if one did.
+    pub decided_by: Option<&'a str>,
+}
+
+/// Observer notified as hooks run their checks.
+///
+/// Library users can register one when calling a hook to add their own
+/// logging or metrics, or to veto decisions. Every method has a no-op default.
+pub trait HookObserver {
+    /// Called before a check runs.
+    fn on_check_start(&self, _hook: &str, _check: &str) {}
+
+    /// Called after a check ran, with a short description of its outcome.
+    fn on_check_end(&self, _hook: &str, _check: &str, _outcome: &str) {}
+
+    /// Called with the hook's decision before it is returned.
+    ///
+    /// Returning a message vetoes an allow decision of the stop or
+    /// `pre_tool_use` hook: the hook blocks with that message instead. Block
+    /// decisions cannot be overridden, and neither can escape hatches such as
+    /// an emergency stop. The other hooks never block, so their decisions are
+    /// only reported.
+    fn on_decision(&self, _decision: &HookDecision<'_>) -> Option<String> {
+        None
+    }
+}
+
+/// Observer that ignores everything, used when none is registered.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopObserver;
+
+impl HookObserver for NoopObserver {}
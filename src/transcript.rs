@@ -1,36 +1,111 @@
 //! Transcript parsing for Claude Code JSONL transcripts.
 
 use crate::error::{Error, Result};
+use crate::tasks::similar::title_similarity;
+use crate::usage::{MessageUsage, TokenUsage};
 use chrono::{DateTime, Utc};
+use serde::de::{self, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
 use serde::Deserialize;
+use std::collections::VecDeque;
+use std::fmt;
 use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
 use std::path::Path;
 
 /// A content block in an assistant message.
-#[derive(Debug, Clone, Deserialize)]
-#[serde(tag = "type")]
+///
+/// Only text and tool use blocks are kept. The contents of other blocks,
+/// such as tool results, are skipped while parsing rather than copied, since
+/// they can be many megabytes.
+#[derive(Debug, Clone)]
 pub enum ContentBlock {
     /// A text block.
-    #[serde(rename = "text")]
     Text {
         /// The text content.
         text: String,
     },
     /// A tool use block.
-    #[serde(rename = "tool_use")]
     ToolUse {
         /// The tool name.
         name: String,
+        /// The tool's input.
+        input: serde_json::Value,
     },
     /// Any other block type.
+    Other,
+}
+
+/// The fields of a content block that are kept.
+#[derive(Deserialize)]
+#[serde(field_identifier, rename_all = "lowercase")]
+enum BlockField {
+    Type,
+    Text,
+    Name,
+    Input,
     #[serde(other)]
     Other,
 }
 
+impl<'de> Deserialize<'de> for ContentBlock {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        struct BlockVisitor;
+
+        impl<'de> Visitor<'de> for BlockVisitor {
+            type Value = ContentBlock;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a content block")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(
+                self,
+                mut map: A,
+            ) -> std::result::Result<ContentBlock, A::Error> {
+                let mut block_type: Option<String> = None;
+                let mut text = None;
+                let mut name = None;
+                let mut input = None;
+                while let Some(field) = map.next_key()? {
+                    // A field is kept unless the block's type is known not to need it
+                    let wanted =
+                        |needed_by: &str| block_type.as_deref().map_or(true, |t| t == needed_by);
+                    match field {
+                        BlockField::Type => block_type = Some(map.next_value()?),
+                        BlockField::Text if wanted("text") => {
+                            text = Some(map.next_value::<serde_json::Value>()?);
+                        }
+                        BlockField::Name if wanted("tool_use") => {
+                            name = Some(map.next_value::<serde_json::Value>()?);
+                        }
+                        BlockField::Input if wanted("tool_use") => input = Some(map.next_value()?),
+                        _ => {
+                            map.next_value::<IgnoredAny>()?;
+                        }
+                    }
+                }
+                let string = |value: Option<serde_json::Value>, field: &'static str| {
+                    let value = value.ok_or_else(|| de::Error::missing_field(field))?;
+                    String::deserialize(value).map_err(<A::Error as de::Error>::custom)
+                };
+                match block_type.as_deref() {
+                    Some("text") => Ok(ContentBlock::Text { text: string(text, "text")? }),
+                    Some("tool_use") => Ok(ContentBlock::ToolUse {
+                        name: string(name, "name")?,
+                        input: input.unwrap_or_default(),
+                    }),
+                    Some(_) => Ok(ContentBlock::Other),
+                    None => Err(de::Error::missing_field("type")),
+                }
+            }
+        }
+
+        deserializer.deserialize_map(BlockVisitor)
+    }
+}
+
 /// Message content - can be a string (user messages) or array of blocks (assistant messages).
-#[derive(Debug, Clone, Deserialize)]
-#[serde(untagged)]
+#[derive(Debug, Clone)]
 pub enum MessageContent {
     /// Simple string content (user messages).
     Text(String),
@@ -44,12 +119,61 @@ impl Default for MessageContent {
     }
 }
 
+impl<'de> Deserialize<'de> for MessageContent {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        struct ContentVisitor;
+
+        impl<'de> Visitor<'de> for ContentVisitor {
+            type Value = MessageContent;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a string or an array of content blocks")
+            }
+
+            fn visit_str<E: de::Error>(self, text: &str) -> std::result::Result<MessageContent, E> {
+                Ok(MessageContent::Text(text.to_string()))
+            }
+
+            fn visit_string<E: de::Error>(
+                self,
+                text: String,
+            ) -> std::result::Result<MessageContent, E> {
+                Ok(MessageContent::Text(text))
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(
+                self,
+                mut seq: A,
+            ) -> std::result::Result<MessageContent, A::Error> {
+                let mut blocks = Vec::with_capacity(seq.size_hint().unwrap_or_default());
+                while let Some(block) = seq.next_element()? {
+                    blocks.push(block);
+                }
+                Ok(MessageContent::Blocks(blocks))
+            }
+        }
+
+        // Content is not buffered to try each form in turn, as an untagged
+        // enum would, so large tool results are only ever skipped over
+        deserializer.deserialize_any(ContentVisitor)
+    }
+}
+
 /// A message in the transcript.
 #[derive(Debug, Clone, Deserialize)]
 pub struct Message {
     /// The message content.
     #[serde(default)]
     pub content: MessageContent,
+    /// The model that wrote the message (for assistant messages).
+    #[serde(default)]
+    pub model: Option<String>,
+    /// The message's ID, shared by the entries of a message split into blocks.
+    #[serde(default)]
+    pub id: Option<String>,
+    /// Tokens the message used (for assistant messages).
+    #[serde(default)]
+    pub usage: Option<MessageUsage>,
 }
 
 /// A transcript entry.
@@ -67,6 +191,105 @@ pub struct TranscriptEntry {
     /// Whether this entry is an API error message.
     #[serde(rename = "isApiErrorMessage", default)]
     pub is_api_error_message: bool,
+    /// The kind of system entry (e.g. `compact_boundary`).
+    #[serde(default)]
+    pub subtype: Option<String>,
+    /// Whether this entry is the summary written when the context was compacted.
+    #[serde(rename = "isCompactSummary", default)]
+    pub is_compact_summary: bool,
+}
+
+/// Subtype of the system entry that marks where the context was compacted.
+const COMPACT_BOUNDARY_SUBTYPE: &str = "compact_boundary";
+
+/// Model name given to messages Claude Code writes itself, such as API errors.
+const SYNTHETIC_MODEL: &str = "<synthetic>";
+
+/// Similarity (0 to 1) at which two assistant messages count as repeats.
+const REPEATED_OUTPUT_SIMILARITY: f64 = 0.8;
+
+/// Maximum number of characters of a tool call's input kept in a [`TranscriptEvent`].
+const MAX_TOOL_INPUT_CHARS: usize = 2000;
+
+/// What a [`TranscriptEvent`] records.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    /// A message from the user.
+    User,
+    /// Text written by the assistant.
+    Assistant,
+    /// A tool call made by the assistant.
+    ToolCall,
+}
+
+impl EventKind {
+    /// The kind's name, as stored in the transcript index.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::User => "user",
+            Self::Assistant => "assistant",
+            Self::ToolCall => "tool_call",
+        }
+    }
+}
+
+/// A searchable event in a transcript: a message or a tool call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TranscriptEvent {
+    /// The 1-based line of the transcript the event is on.
+    pub line: usize,
+    /// When the event happened, as written in the transcript.
+    pub timestamp: Option<String>,
+    /// What the event records.
+    pub kind: EventKind,
+    /// The tool called, for tool calls.
+    pub tool: Option<String>,
+    /// The message text, or the string values of a tool call's input.
+    pub text: String,
+}
+
+/// How far a transcript has been read by [`read_events`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TranscriptPosition {
+    /// Bytes read.
+    pub offset: u64,
+    /// Lines read.
+    pub lines: usize,
+}
+
+/// Size past which the buffer a line was read into is freed rather than kept
+/// for the next line, so one huge line does not hold memory for the rest of
+/// the transcript (1 MiB).
+const MAX_KEPT_LINE_BYTES: usize = 1024 * 1024;
+
+/// Number of recent message IDs remembered so a message split into several
+/// entries has its usage counted once. The entries of a message are written
+/// together, so only the last few are needed.
+const COUNTED_MESSAGE_WINDOW: usize = 32;
+
+/// Reads a transcript a line at a time into one reused buffer.
+struct LineReader<R> {
+    reader: R,
+    buffer: Vec<u8>,
+}
+
+impl<R: BufRead> LineReader<R> {
+    const fn new(reader: R) -> Self {
+        Self { reader, buffer: Vec::new() }
+    }
+
+    /// The next line, with its newline if it has one, or `None` at the end.
+    fn next_line(&mut self) -> std::io::Result<Option<&[u8]>> {
+        if self.buffer.capacity() > MAX_KEPT_LINE_BYTES {
+            self.buffer = Vec::new();
+        }
+        self.buffer.clear();
+        if self.reader.read_until(b'\n', &mut self.buffer)? == 0 {
+            return Ok(None);
+        }
+        Ok(Some(&self.buffer))
+    }
 }
 
 /// Tools that are considered "read-only" and don't count as modifications.
@@ -95,10 +318,24 @@ pub struct TranscriptInfo {
     pub first_user_message: Option<String>,
     /// The last user message in the transcript.
     pub last_user_message: Option<String>,
+    /// Number of times the context was compacted.
+    pub compaction_count: u32,
+    /// When the context was last compacted.
+    pub last_compaction_time: Option<DateTime<Utc>>,
+    /// The model that wrote the last assistant message.
+    pub model: Option<String>,
+    /// Number of near-identical assistant messages in a row at the end of the
+    /// transcript, counting the last one, since the last user message.
+    pub repeated_output_count: u32,
+    /// Tokens used by the session's assistant messages, and what they cost.
+    pub usage: TokenUsage,
 }
 
 /// Parse a transcript file and extract relevant information.
 ///
+/// The transcript is read a line at a time and only what [`TranscriptInfo`]
+/// keeps is held on to, so memory use does not grow with the transcript.
+///
 /// # Arguments
 ///
 /// * `path` - Path to the JSONL transcript file.
@@ -115,22 +352,18 @@ pub fn parse_transcript(path: &Path) -> Result<TranscriptInfo> {
         return Err(Error::FileNotFound(path.to_path_buf()));
     }
 
-    let file = File::open(path)?;
-    let reader = BufReader::new(file);
+    let mut lines = LineReader::new(BufReader::new(File::open(path)?));
 
     let mut info = TranscriptInfo::default();
-
-    for line in reader.lines() {
-        let line = line?;
-        let line = line.trim();
-        if line.is_empty() {
+    // A compaction boundary is followed by its summary, which is the same event
+    let mut after_boundary = false;
+    // Messages split into several entries repeat their usage in each
+    let mut counted_messages = VecDeque::with_capacity(COUNTED_MESSAGE_WINDOW);
+
+    while let Some(line) = lines.next_line()? {
+        // Skip blank and malformed lines
+        let Ok(entry) = serde_json::from_slice::<TranscriptEntry>(line) else {
             continue;
-        }
-
-        // Try to parse as a transcript entry
-        let entry: TranscriptEntry = match serde_json::from_str(line) {
-            Ok(e) => e,
-            Err(_) => continue, // Skip malformed lines
         };
 
         // Check for API error
@@ -147,38 +380,43 @@ pub fn parse_transcript(path: &Path) -> Result<TranscriptInfo> {
             info.last_error_is_overloaded = false;
         }
 
+        if entry.entry_type == "system" {
+            if entry.subtype.as_deref() == Some(COMPACT_BOUNDARY_SUBTYPE) {
+                record_compaction(&mut info, &entry);
+                after_boundary = true;
+            }
+            continue;
+        }
+        let follows_boundary = std::mem::take(&mut after_boundary);
+
         match entry.entry_type.as_str() {
             "assistant" => {
-                // Extract text from assistant message and check for tool uses
                 if let Some(message) = &entry.message {
-                    if let MessageContent::Blocks(blocks) = &message.content {
-                        for block in blocks {
-                            match block {
-                                ContentBlock::Text { text } => {
-                                    info.last_assistant_output = Some(text.clone());
-                                }
-                                ContentBlock::ToolUse { name } => {
-                                    // Check if this is a modifying tool
-                                    if !READ_ONLY_TOOLS.contains(&name.as_str()) {
-                                        info.has_modifying_tool_use = true;
-                                        info.has_modifying_tool_use_since_user = true;
-                                    }
-                                }
-                                ContentBlock::Other => {}
-                            }
+                    record_assistant_message(&mut info, message, !is_api_error);
+                    if let Some(usage) = &message.usage {
+                        if message
+                            .id
+                            .as_ref()
+                            .map_or(true, |id| first_sighting(&mut counted_messages, id))
+                        {
+                            info.usage.add(usage, message.model.as_deref());
                         }
                     }
                 }
             }
             "user" => {
                 // Check if this is a compaction event or system-reminder-only (not a real user message)
-                let is_compaction = entry.message.as_ref().is_some_and(|m| {
-                    if let MessageContent::Text(text) = &m.content {
-                        is_compaction_message(text)
-                    } else {
-                        false
-                    }
-                });
+                let is_compaction = entry.is_compact_summary
+                    || entry.message.as_ref().is_some_and(|m| {
+                        if let MessageContent::Text(text) = &m.content {
+                            is_compaction_message(text)
+                        } else {
+                            false
+                        }
+                    });
+                if is_compaction && !follows_boundary {
+                    record_compaction(&mut info, &entry);
+                }
 
                 let is_system_reminder = entry.message.as_ref().is_some_and(|m| {
                     if let MessageContent::Text(text) = &m.content {
@@ -192,6 +430,7 @@ pub fn parse_transcript(path: &Path) -> Result<TranscriptInfo> {
                 // Don't reset for compaction events or system reminders
                 if !is_compaction && !is_system_reminder {
                     info.has_modifying_tool_use_since_user = false;
+                    info.repeated_output_count = 0;
                 }
 
                 // Capture user message content
@@ -223,6 +462,161 @@ pub fn parse_transcript(path: &Path) -> Result<TranscriptInfo> {
     Ok(info)
 }
 
+/// Remember a message ID in the window of recent ones, returning whether it
+/// was not already there.
+fn first_sighting(recent: &mut VecDeque<String>, id: &str) -> bool {
+    if recent.iter().any(|seen| seen == id) {
+        return false;
+    }
+    if recent.len() == COUNTED_MESSAGE_WINDOW {
+        recent.pop_front();
+    }
+    recent.push_back(id.to_string());
+    true
+}
+
+/// Record the model, text and tool uses of an assistant message.
+///
+/// Text is counted towards a streak of repeated messages unless the message
+/// is an API error.
+fn record_assistant_message(info: &mut TranscriptInfo, message: &Message, count_repeats: bool) {
+    if let Some(model) = message.model.as_ref().filter(|m| *m != SYNTHETIC_MODEL) {
+        info.model = Some(model.clone());
+    }
+    let MessageContent::Blocks(blocks) = &message.content else {
+        return;
+    };
+    for block in blocks {
+        match block {
+            ContentBlock::Text { text } => {
+                if count_repeats && !text.trim().is_empty() {
+                    info.repeated_output_count = match &info.last_assistant_output {
+                        Some(previous)
+                            if info.repeated_output_count > 0
+                                && is_near_identical(previous, text) =>
+                        {
+                            info.repeated_output_count + 1
+                        }
+                        _ => 1,
+                    };
+                }
+                info.last_assistant_output = Some(text.clone());
+            }
+            ContentBlock::ToolUse { name, .. } => {
+                // Check if this is a modifying tool
+                if !READ_ONLY_TOOLS.contains(&name.as_str()) {
+                    info.has_modifying_tool_use = true;
+                    info.has_modifying_tool_use_since_user = true;
+                }
+            }
+            ContentBlock::Other => {}
+        }
+    }
+}
+
+/// Check whether two assistant messages say the same thing, give or take a
+/// few words.
+fn is_near_identical(a: &str, b: &str) -> bool {
+    a.trim() == b.trim() || title_similarity(a, b) >= REPEATED_OUTPUT_SIMILARITY
+}
+
+/// Record that the context was compacted at a transcript entry.
+fn record_compaction(info: &mut TranscriptInfo, entry: &TranscriptEntry) {
+    info.compaction_count += 1;
+    if let Some(ts) = entry.timestamp.as_deref().and_then(|t| parse_timestamp(t).ok()) {
+        info.last_compaction_time = Some(ts);
+    }
+}
+
+/// Read the messages and tool calls of a transcript from a position onwards.
+///
+/// Only complete lines are read, so a transcript that is still being written
+/// can be read again later from the returned position. API error messages,
+/// compaction summaries and system reminders are left out.
+///
+/// # Errors
+///
+/// Returns an error if the file doesn't exist or cannot be read.
+pub fn read_events(
+    path: &Path,
+    from: TranscriptPosition,
+) -> Result<(Vec<TranscriptEvent>, TranscriptPosition)> {
+    if !path.exists() {
+        return Err(Error::FileNotFound(path.to_path_buf()));
+    }
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(from.offset))?;
+    let mut lines = LineReader::new(BufReader::new(file));
+
+    let mut events = Vec::new();
+    let mut position = from;
+    while let Some(line) = lines.next_line()? {
+        if !line.ends_with(b"\n") {
+            break;
+        }
+        position.offset += line.len() as u64;
+        position.lines += 1;
+        let Ok(entry) = serde_json::from_slice::<TranscriptEntry>(line) else {
+            continue;
+        };
+        if !entry.is_api_error_message {
+            entry_events(&entry, position.lines, &mut events);
+        }
+    }
+    Ok((events, position))
+}
+
+/// Add the searchable events of a transcript entry on `line`.
+fn entry_events(entry: &TranscriptEntry, line: usize, events: &mut Vec<TranscriptEvent>) {
+    let Some(message) = &entry.message else {
+        return;
+    };
+    let mut push = |kind: EventKind, tool: Option<&str>, text: String| {
+        if !text.trim().is_empty() {
+            events.push(TranscriptEvent {
+                line,
+                timestamp: entry.timestamp.clone(),
+                kind,
+                tool: tool.map(str::to_string),
+                text,
+            });
+        }
+    };
+    match (entry.entry_type.as_str(), &message.content) {
+        ("user", MessageContent::Text(text)) if !is_compaction_message(text) => {
+            push(EventKind::User, None, remove_system_reminder_blocks(text).trim().to_string());
+        }
+        ("assistant", MessageContent::Blocks(blocks)) => {
+            for block in blocks {
+                match block {
+                    ContentBlock::Text { text } => push(EventKind::Assistant, None, text.clone()),
+                    ContentBlock::ToolUse { name, input } => {
+                        push(EventKind::ToolCall, Some(name), tool_input_text(input));
+                    }
+                    ContentBlock::Other => {}
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The string values in a tool call's input, one per line, shortened to
+/// [`MAX_TOOL_INPUT_CHARS`].
+fn tool_input_text(input: &serde_json::Value) -> String {
+    fn collect<'a>(value: &'a serde_json::Value, out: &mut Vec<&'a str>) {
+        match value {
+            serde_json::Value::String(s) => out.push(s),
+            serde_json::Value::Array(items) => items.iter().for_each(|v| collect(v, out)),
+            serde_json::Value::Object(map) => map.values().for_each(|v| collect(v, out)),
+            _ => {}
+        }
+    }
+    let mut strings = Vec::new();
+    collect(input, &mut strings);
+    strings.join("\n").chars().take(MAX_TOOL_INPUT_CHARS).collect()
+}
+
 /// Extract text content from a transcript entry.
 fn extract_texts(entry: &TranscriptEntry) -> Vec<&str> {
     entry.message.as_ref().map_or_else(Vec::new, |message| match &message.content {
@@ -243,49 +637,36 @@ fn extract_texts(entry: &TranscriptEntry) -> Vec<&str> {
 /// Check if an entry contains a 529 overloaded API error.
 /// These are transient errors that should be retried with backoff.
 fn is_overloaded_error(entry: &TranscriptEntry) -> bool {
-    for text in extract_texts(entry) {
-        // Check for 529 status code with overloaded_error type
-        if text.contains("API Error:") && text.contains("529") {
-            return true;
-        }
-        if text.contains("overloaded_error") {
-            return true;
-        }
-    }
-    false
+    extract_texts(entry).into_iter().any(is_overloaded_error_text)
 }
 
 /// Check if an entry contains API error text patterns.
 fn is_api_error_text(entry: &TranscriptEntry) -> bool {
-    for text in extract_texts(entry) {
-        // Check for common API error patterns
-        if text.contains("API Error:") && text.contains("400") {
-            return true;
-        }
-        if text.contains("API Error:") && text.contains("529") {
-            return true;
-        }
-        if text.contains("thinking")
+    extract_texts(entry).into_iter().any(is_api_error_message)
+}
+
+/// Check if a message reports a 529 overloaded API error.
+pub(crate) fn is_overloaded_error_text(text: &str) -> bool {
+    // Check for 529 status code with overloaded_error type
+    (text.contains("API Error:") && text.contains("529")) || text.contains("overloaded_error")
+}
+
+/// Check if a message reports an API error.
+pub(crate) fn is_api_error_message(text: &str) -> bool {
+    // Check for common API error patterns
+    (text.contains("API Error:") && text.contains("400"))
+        || is_overloaded_error_text(text)
+        || (text.contains("thinking")
             && text.contains("blocks")
-            && text.contains("cannot be modified")
-        {
-            return true;
-        }
-        if text.contains("invalid_request_error") {
-            return true;
-        }
-        if text.contains("overloaded_error") {
-            return true;
-        }
-    }
-    false
+            && text.contains("cannot be modified"))
+        || text.contains("invalid_request_error")
 }
 
 /// Check if a message is a compaction event rather than a real user message.
 ///
 /// Compaction events are system-generated messages that appear as user messages
 /// but should not be counted for user activity tracking.
-fn is_compaction_message(text: &str) -> bool {
+pub(crate) fn is_compaction_message(text: &str) -> bool {
     text.starts_with("This session is being continued from a previous conversation")
 }
 
@@ -294,7 +675,7 @@ fn is_compaction_message(text: &str) -> bool {
 /// System reminders are injected by the system and wrapped in `<system-reminder>` tags.
 /// Messages that are ONLY system reminders (no other content) should not reset
 /// the `has_modifying_tool_use_since_user` flag or be considered as user questions.
-fn is_system_reminder_only(text: &str) -> bool {
+pub(crate) fn is_system_reminder_only(text: &str) -> bool {
     let trimmed = text.trim();
     // Check if it starts with system-reminder tag and has no content outside of tags
     if !trimmed.starts_with("<system-reminder>") {
@@ -306,7 +687,7 @@ fn is_system_reminder_only(text: &str) -> bool {
 }
 
 /// Remove all `<system-reminder>...</system-reminder>` blocks from text.
-fn remove_system_reminder_blocks(text: &str) -> String {
+pub(crate) fn remove_system_reminder_blocks(text: &str) -> String {
     let mut result = String::new();
     let mut remaining = text;
 
@@ -329,7 +710,7 @@ fn remove_system_reminder_blocks(text: &str) -> String {
 }
 
 /// Parse an ISO 8601 timestamp.
-fn parse_timestamp(s: &str) -> Result<DateTime<Utc>> {
+pub(crate) fn parse_timestamp(s: &str) -> Result<DateTime<Utc>> {
     // Handle Z suffix and +00:00 format
     let normalized = s.replace('Z', "+00:00");
     DateTime::parse_from_rfc3339(&normalized)
@@ -428,6 +809,51 @@ also not json
         assert_eq!(info.last_assistant_output, Some("Valid".to_string()));
     }
 
+    #[test]
+    fn test_read_events() {
+        let content = r#"{"type": "user", "timestamp": "2024-01-01T12:00:00Z", "message": {"content": "Fix the parser<system-reminder>ignore</system-reminder>"}}
+{"type": "assistant", "timestamp": "2024-01-01T12:00:05Z", "message": {"content": [{"type": "text", "text": "Looking at it."}, {"type": "tool_use", "name": "Edit", "input": {"file_path": "src/parser.rs", "replace_all": false}}]}}
+not json
+{"type": "assistant", "isApiErrorMessage": true, "message": {"content": [{"type": "text", "text": "API Error: 529"}]}}
+{"type": "user", "message": {"content": "This session is being continued from a previous conversation"}}
+{"type": "assistant", "message": {"content": [{"type": "text", "text": "Partial"#;
+        let file = create_temp_transcript(content);
+
+        let (events, position) = read_events(file.path(), TranscriptPosition::default()).unwrap();
+        let summary: Vec<(usize, EventKind, Option<&str>, &str)> =
+            events.iter().map(|e| (e.line, e.kind, e.tool.as_deref(), e.text.as_str())).collect();
+        assert_eq!(
+            summary,
+            vec![
+                (1, EventKind::User, None, "Fix the parser"),
+                (2, EventKind::Assistant, None, "Looking at it."),
+                (2, EventKind::ToolCall, Some("Edit"), "src/parser.rs"),
+            ]
+        );
+        assert_eq!(events[0].timestamp.as_deref(), Some("2024-01-01T12:00:00Z"));
+        // The incomplete last line is left for later
+        assert_eq!(position.lines, 5);
+        assert_eq!(position.offset, content.rfind('\n').unwrap() as u64 + 1);
+
+        std::fs::write(
+            file.path(),
+            format!("{content} done\"}}]}}}}\n{{\"type\": \"user\", \"message\": {{\"content\": \"Thanks\"}}}}\n"),
+        )
+        .unwrap();
+        let (events, position) = read_events(file.path(), position).unwrap();
+        let texts: Vec<(usize, &str)> = events.iter().map(|e| (e.line, e.text.as_str())).collect();
+        assert_eq!(texts, vec![(6, "Partial done"), (7, "Thanks")]);
+        assert_eq!(position.lines, 7);
+
+        assert!(read_events(Path::new("/nonexistent/file.jsonl"), position).is_err());
+    }
+
+    #[test]
+    fn test_tool_input_text_is_shortened() {
+        let input = serde_json::json!({"command": "x".repeat(MAX_TOOL_INPUT_CHARS + 10), "n": 1});
+        assert_eq!(tool_input_text(&input).len(), MAX_TOOL_INPUT_CHARS);
+    }
+
     #[test]
     fn test_parse_transcript_file_not_found() {
         let result = parse_transcript(Path::new("/nonexistent/file.jsonl"));
@@ -446,6 +872,11 @@ also not json
             has_modifying_tool_use_since_user: false,
             first_user_message: None,
             last_user_message: None,
+            compaction_count: 0,
+            last_compaction_time: None,
+            model: None,
+            repeated_output_count: 0,
+            usage: crate::usage::TokenUsage::default(),
         };
         assert!(is_user_recently_active(&info, 5));
     }
@@ -462,6 +893,11 @@ also not json
             has_modifying_tool_use_since_user: false,
             first_user_message: None,
             last_user_message: None,
+            compaction_count: 0,
+            last_compaction_time: None,
+            model: None,
+            repeated_output_count: 0,
+            usage: crate::usage::TokenUsage::default(),
         };
         assert!(!is_user_recently_active(&info, 5));
     }
@@ -651,8 +1087,13 @@ also not json
                 content: MessageContent::Blocks(vec![ContentBlock::Text {
                     text: "API Error: 400 something went wrong".to_string(),
                 }]),
+                model: None,
+                id: None,
+                usage: None,
             }),
             is_api_error_message: false,
+            subtype: None,
+            is_compact_summary: false,
         };
         assert!(is_api_error_text(&entry));
     }
@@ -667,8 +1108,13 @@ also not json
                     text: "thinking blocks in the latest assistant message cannot be modified"
                         .to_string(),
                 }]),
+                model: None,
+                id: None,
+                usage: None,
             }),
             is_api_error_message: false,
+            subtype: None,
+            is_compact_summary: false,
         };
         assert!(is_api_error_text(&entry));
     }
@@ -682,8 +1128,13 @@ also not json
                 content: MessageContent::Blocks(vec![ContentBlock::Text {
                     text: "error type: invalid_request_error".to_string(),
                 }]),
+                model: None,
+                id: None,
+                usage: None,
             }),
             is_api_error_message: false,
+            subtype: None,
+            is_compact_summary: false,
         };
         assert!(is_api_error_text(&entry));
     }
@@ -697,8 +1148,13 @@ also not json
                 content: MessageContent::Blocks(vec![ContentBlock::Text {
                     text: "This is a normal response about error handling".to_string(),
                 }]),
+                model: None,
+                id: None,
+                usage: None,
             }),
             is_api_error_message: false,
+            subtype: None,
+            is_compact_summary: false,
         };
         assert!(!is_api_error_text(&entry));
     }
@@ -710,6 +1166,8 @@ also not json
             timestamp: None,
             message: None,
             is_api_error_message: false,
+            subtype: None,
+            is_compact_summary: false,
         };
         assert!(!is_api_error_text(&entry));
     }
@@ -722,9 +1180,15 @@ also not json
             message: Some(Message {
                 content: MessageContent::Blocks(vec![ContentBlock::ToolUse {
                     name: "test".to_string(),
+                    input: serde_json::Value::Null,
                 }]),
+                model: None,
+                id: None,
+                usage: None,
             }),
             is_api_error_message: false,
+            subtype: None,
+            is_compact_summary: false,
         };
         assert!(!is_api_error_text(&entry));
     }
@@ -738,8 +1202,13 @@ also not json
                 content: MessageContent::Blocks(vec![ContentBlock::Text {
                     text: r#"API Error: 529 {"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#.to_string(),
                 }]),
+                model: None,
+                id: None,
+                usage: None,
             }),
             is_api_error_message: false,
+            subtype: None,
+            is_compact_summary: false,
         };
         assert!(is_api_error_text(&entry));
     }
@@ -754,8 +1223,13 @@ also not json
                 content: MessageContent::Blocks(vec![ContentBlock::Text {
                     text: r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#.to_string(),
                 }]),
+                model: None,
+                id: None,
+                usage: None,
             }),
             is_api_error_message: false,
+            subtype: None,
+            is_compact_summary: false,
         };
         assert!(is_api_error_text(&entry));
     }
@@ -769,8 +1243,13 @@ also not json
                 content: MessageContent::Blocks(vec![ContentBlock::Text {
                     text: r#"API Error: 529 {"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#.to_string(),
                 }]),
+                model: None,
+                id: None,
+                usage: None,
             }),
             is_api_error_message: false,
+            subtype: None,
+            is_compact_summary: false,
         };
         assert!(is_overloaded_error(&entry));
     }
@@ -784,8 +1263,13 @@ also not json
                 content: MessageContent::Blocks(vec![ContentBlock::Text {
                     text: r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#.to_string(),
                 }]),
+                model: None,
+                id: None,
+                usage: None,
             }),
             is_api_error_message: false,
+            subtype: None,
+            is_compact_summary: false,
         };
         assert!(is_overloaded_error(&entry));
     }
@@ -799,8 +1283,13 @@ also not json
                 content: MessageContent::Blocks(vec![ContentBlock::Text {
                     text: "API Error: 400 something went wrong".to_string(),
                 }]),
+                model: None,
+                id: None,
+                usage: None,
             }),
             is_api_error_message: false,
+            subtype: None,
+            is_compact_summary: false,
         };
         assert!(!is_overloaded_error(&entry));
     }
@@ -1134,6 +1623,181 @@ also not json
         assert_eq!(ts.hour(), 12);
     }
 
+    #[test]
+    fn test_compaction_events_counted() {
+        // A boundary and its summary are one compaction; a summary on its own is another
+        let content = r#"{"type": "user", "timestamp": "2024-01-01T10:00:00Z", "message": {"content": "First message"}}
+{"type": "system", "subtype": "compact_boundary", "timestamp": "2024-01-01T11:00:00Z", "content": "Conversation compacted"}
+{"type": "user", "isCompactSummary": true, "timestamp": "2024-01-01T11:00:01Z", "message": {"content": "Summary of the work so far"}}
+{"type": "user", "timestamp": "2024-01-01T12:00:00Z", "message": {"content": "Second message"}}
+{"type": "system", "subtype": "informational", "timestamp": "2024-01-01T12:30:00Z"}
+{"type": "user", "timestamp": "2024-01-01T13:00:00Z", "message": {"content": "This session is being continued from a previous conversation that ran out of context."}}
+"#;
+        let file = create_temp_transcript(content);
+        let info = parse_transcript(file.path()).unwrap();
+        assert_eq!(info.compaction_count, 2);
+        assert_eq!(info.last_compaction_time.unwrap().hour(), 13);
+        assert_eq!(info.last_user_message, Some("Second message".to_string()));
+    }
+
+    #[test]
+    fn test_model_from_last_assistant_message() {
+        let content = r#"{"type": "assistant", "message": {"model": "claude-opus-4", "content": [{"type": "text", "text": "Hi"}]}}
+{"type": "assistant", "message": {"model": "claude-haiku-4", "content": [{"type": "text", "text": "Done"}]}}
+{"type": "assistant", "isApiErrorMessage": true, "message": {"model": "<synthetic>", "content": [{"type": "text", "text": "API Error: 529"}]}}
+{"type": "user", "message": {"content": "Thanks"}}
+"#;
+        let file = create_temp_transcript(content);
+        let info = parse_transcript(file.path()).unwrap();
+        assert_eq!(info.model.as_deref(), Some("claude-haiku-4"));
+
+        let info = parse_transcript(create_temp_transcript("").path()).unwrap();
+        assert!(info.model.is_none());
+    }
+
+    #[test]
+    fn test_repeated_output_count() {
+        let says = |text: &str| {
+            format!(
+                r#"{{"type": "assistant", "message": {{"content": [{{"type": "text", "text": "{text}"}}]}}}}"#
+            )
+        };
+        let tool_call = r#"{"type": "assistant", "message": {"content": [{"type": "tool_use", "name": "Bash", "input": {}}]}}"#;
+        let lines = [
+            says("Running the tests again."),
+            tool_call.to_string(),
+            says("Running the tests again now."),
+            tool_call.to_string(),
+            says("running the tests again"),
+        ];
+        let file = create_temp_transcript(&(lines.join("\n") + "\n"));
+        assert_eq!(parse_transcript(file.path()).unwrap().repeated_output_count, 3);
+
+        // A different message restarts the streak
+        let content = [says("Running the tests again."), says("All tests pass.")].join("\n");
+        let file = create_temp_transcript(&content);
+        assert_eq!(parse_transcript(file.path()).unwrap().repeated_output_count, 1);
+
+        // A user message ends it
+        let content = [
+            says("Running the tests again."),
+            r#"{"type": "user", "message": {"content": "Keep going"}}"#.to_string(),
+        ]
+        .join("\n");
+        let file = create_temp_transcript(&content);
+        assert_eq!(parse_transcript(file.path()).unwrap().repeated_output_count, 0);
+    }
+
+    #[test]
+    fn test_repeated_api_errors_not_counted_as_output() {
+        let content = r#"{"type": "assistant", "isApiErrorMessage": true, "message": {"content": [{"type": "text", "text": "API Error: 500"}]}}
+{"type": "assistant", "isApiErrorMessage": true, "message": {"content": [{"type": "text", "text": "API Error: 500"}]}}
+"#;
+        let file = create_temp_transcript(content);
+        assert_eq!(parse_transcript(file.path()).unwrap().repeated_output_count, 0);
+    }
+
+    #[test]
+    fn test_usage_counted_once_per_message() {
+        let content = r#"{"type": "assistant", "message": {"id": "m1", "model": "claude-sonnet-4-5", "usage": {"input_tokens": 1000, "output_tokens": 200}, "content": [{"type": "text", "text": "Looking"}]}}
+{"type": "assistant", "message": {"id": "m1", "model": "claude-sonnet-4-5", "usage": {"input_tokens": 1000, "output_tokens": 200}, "content": [{"type": "tool_use", "name": "Read", "input": {}}]}}
+{"type": "user", "message": {"content": [{"type": "tool_result", "content": "ok"}]}}
+{"type": "assistant", "message": {"id": "m2", "model": "claude-sonnet-4-5", "usage": {"input_tokens": 10, "cache_read_input_tokens": 5000, "output_tokens": 50}, "content": [{"type": "text", "text": "Done"}]}}
+"#;
+        let file = create_temp_transcript(content);
+        let usage = parse_transcript(file.path()).unwrap().usage;
+        assert_eq!(usage.input_tokens, 1010);
+        assert_eq!(usage.output_tokens, 250);
+        assert_eq!(usage.cache_read_tokens, 5000);
+        assert!(usage.cost_usd > 0.0);
+    }
+
+    #[test]
+    fn test_first_sighting_remembers_a_window_of_ids() {
+        let mut recent = VecDeque::new();
+        assert!(first_sighting(&mut recent, "m0"));
+        assert!(!first_sighting(&mut recent, "m0"));
+        for n in 1..=COUNTED_MESSAGE_WINDOW {
+            assert!(first_sighting(&mut recent, &format!("m{n}")));
+        }
+        assert_eq!(recent.len(), COUNTED_MESSAGE_WINDOW);
+        assert!(!first_sighting(&mut recent, "m1"));
+        // The oldest has been forgotten
+        assert!(first_sighting(&mut recent, "m0"));
+    }
+
+    #[test]
+    fn test_content_blocks() {
+        let blocks = |json: &str| match serde_json::from_str::<MessageContent>(json) {
+            Ok(MessageContent::Blocks(blocks)) => Ok(blocks),
+            Ok(MessageContent::Text(text)) => panic!("unexpected text: {text}"),
+            Err(e) => Err(e.to_string()),
+        };
+
+        let parsed = blocks(
+            r#"[{"text": "Hi", "type": "text"},
+                {"input": {"path": "a"}, "name": "Read", "id": "1", "type": "tool_use"},
+                {"type": "tool_result", "content": [{"type": "text", "text": "big"}]},
+                {"type": "image", "text": 5, "name": null},
+                {"type": "tool_use", "name": "Bash"}]"#,
+        )
+        .unwrap();
+        assert!(matches!(&parsed[0], ContentBlock::Text { text } if text == "Hi"));
+        assert!(
+            matches!(&parsed[1], ContentBlock::ToolUse { name, input } if name == "Read" && input["path"] == "a")
+        );
+        assert!(matches!(parsed[2], ContentBlock::Other));
+        assert!(matches!(parsed[3], ContentBlock::Other));
+        assert!(matches!(&parsed[4], ContentBlock::ToolUse { input, .. } if input.is_null()));
+
+        assert!(blocks(r#"[{"text": "Hi"}]"#).unwrap_err().contains("missing field `type`"));
+        assert!(blocks(r#"[{"type": "text"}]"#).unwrap_err().contains("missing field `text`"));
+        assert!(blocks(r#"[{"type": "tool_use", "name": 1}]"#).unwrap_err().contains("a string"));
+        assert!(blocks(r#"["text"]"#).is_err());
+        assert!(blocks("5").unwrap_err().contains("a string or an array of content blocks"));
+        assert!(matches!(
+            serde_json::from_str::<MessageContent>(r#""Hello""#).unwrap(),
+            MessageContent::Text(text) if text == "Hello"
+        ));
+    }
+
+    #[test]
+    fn test_parse_transcript_skips_lines_that_are_not_utf8() {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(b"{\"type\": \"user\", \"message\": {\"content\": \"\xff\"}}\n").unwrap();
+        file.write_all(br#"{"type": "user", "message": {"content": "Fix it"}}"#).unwrap();
+        file.flush().unwrap();
+        let info = parse_transcript(file.path()).unwrap();
+        assert_eq!(info.first_user_message.as_deref(), Some("Fix it"));
+    }
+
+    #[test]
+    fn test_line_reader_frees_large_buffers() {
+        let big = "x".repeat(MAX_KEPT_LINE_BYTES * 2);
+        let content = format!("{big}\nsmall\nlast");
+        let mut lines = LineReader::new(content.as_bytes());
+        assert_eq!(lines.next_line().unwrap().unwrap().len(), big.len() + 1);
+        assert_eq!(lines.next_line().unwrap().unwrap(), b"small\n");
+        assert!(lines.buffer.capacity() <= MAX_KEPT_LINE_BYTES);
+        assert_eq!(lines.next_line().unwrap().unwrap(), b"last");
+        assert!(lines.next_line().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_compaction_boundary_without_summary() {
+        let content = r#"{"type": "system", "subtype": "compact_boundary", "timestamp": "2024-01-01T11:00:00Z"}
+{"type": "user", "timestamp": "2024-01-01T12:00:00Z", "message": {"content": "Carry on"}}
+"#;
+        let file = create_temp_transcript(content);
+        let info = parse_transcript(file.path()).unwrap();
+        assert_eq!(info.compaction_count, 1);
+        assert_eq!(info.last_compaction_time.unwrap().hour(), 11);
+
+        let info = parse_transcript(create_temp_transcript("").path()).unwrap();
+        assert_eq!(info.compaction_count, 0);
+        assert!(info.last_compaction_time.is_none());
+    }
+
     #[test]
     fn test_is_system_reminder_only_true() {
         assert!(is_system_reminder_only("<system-reminder>Some reminder text</system-reminder>"));
@@ -0,0 +1,239 @@
+//! Rendering transcripts for people to read.
+//!
+//! `claude-reliability transcript export <path> --format md` turns a JSONL
+//! transcript into Markdown for sharing an agent session with teammates:
+//! one section per user or assistant turn with its timestamp, and each tool
+//! call collapsed into a `<details>` block showing its input. Tool results,
+//! API errors and system reminders are left out, and compaction summaries
+//! are collapsed.
+
+use crate::error::{Error, Result};
+use crate::transcript::{
+    is_compaction_message, parse_timestamp, remove_system_reminder_blocks, ContentBlock,
+    MessageContent, TranscriptEntry,
+};
+use std::fmt::Write;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::str::FromStr;
+
+/// Input fields that best describe a tool call, in order of preference.
+const TOOL_SUMMARY_FIELDS: &[&str] =
+    &["file_path", "path", "command", "pattern", "url", "query", "description"];
+
+/// Maximum number of characters of a tool call's summary line.
+const MAX_SUMMARY_CHARS: usize = 80;
+
+/// Format a transcript can be exported in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TranscriptFormat {
+    /// Markdown.
+    Markdown,
+}
+
+impl FromStr for TranscriptFormat {
+    type Err = Error;
+
+    fn from_str(format: &str) -> Result<Self> {
+        match format.to_lowercase().as_str() {
+            "md" | "markdown" => Ok(Self::Markdown),
+            _ => Err(Error::Config(format!(
+                "invalid transcript format '{format}' (must be one of: md)"
+            ))),
+        }
+    }
+}
+
+/// Who a turn of the conversation belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Speaker {
+    User,
+    Assistant,
+}
+
+/// Render a transcript file in a format.
+///
+/// # Errors
+///
+/// Returns an error if the file doesn't exist or cannot be read.
+pub fn export(path: &Path, format: TranscriptFormat) -> Result<String> {
+    if !path.exists() {
+        return Err(Error::FileNotFound(path.to_path_buf()));
+    }
+    let reader = BufReader::new(std::fs::File::open(path)?);
+    let mut entries = Vec::new();
+    for line in reader.lines() {
+        if let Ok(entry) = serde_json::from_str::<TranscriptEntry>(&line?) {
+            entries.push(entry);
+        }
+    }
+    let title = path.file_stem().map_or_else(String::new, |s| s.to_string_lossy().into_owned());
+    match format {
+        TranscriptFormat::Markdown => Ok(render_markdown(&title, &entries)),
+    }
+}
+
+/// Render transcript entries as Markdown.
+fn render_markdown(title: &str, entries: &[TranscriptEntry]) -> String {
+    let mut out = format!("# Transcript: {title}\n");
+    let mut speaker = None;
+    for entry in entries {
+        let Some(message) = entry.message.as_ref().filter(|_| !entry.is_api_error_message) else {
+            continue;
+        };
+        match (entry.entry_type.as_str(), &message.content) {
+            ("user", MessageContent::Text(text)) if is_compaction_message(text) => {
+                out.push('\n');
+                push_details(&mut out, "Conversation compacted", text.trim(), "");
+                speaker = None;
+            }
+            ("user", MessageContent::Text(text)) => {
+                let text = remove_system_reminder_blocks(text);
+                if !text.trim().is_empty() {
+                    push_turn(&mut out, &mut speaker, Speaker::User, entry);
+                    let _ = writeln!(out, "\n{}", text.trim());
+                }
+            }
+            ("assistant", MessageContent::Blocks(blocks)) => {
+                for block in blocks {
+                    match block {
+                        ContentBlock::Text { text } if !text.trim().is_empty() => {
+                            push_turn(&mut out, &mut speaker, Speaker::Assistant, entry);
+                            let _ = writeln!(out, "\n{}", text.trim());
+                        }
+                        ContentBlock::ToolUse { name, input } => {
+                            push_turn(&mut out, &mut speaker, Speaker::Assistant, entry);
+                            let summary = tool_summary(name, input);
+                            let input = serde_json::to_string_pretty(input).unwrap_or_default();
+                            out.push('\n');
+                            push_details(&mut out, &summary, &input, "json");
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+/// Start a new turn with a heading, unless the speaker is already talking.
+fn push_turn(
+    out: &mut String,
+    current: &mut Option<Speaker>,
+    speaker: Speaker,
+    entry: &TranscriptEntry,
+) {
+    if *current == Some(speaker) {
+        return;
+    }
+    *current = Some(speaker);
+    let name = match speaker {
+        Speaker::User => "User",
+        Speaker::Assistant => "Assistant",
+    };
+    let time = entry
+        .timestamp
+        .as_deref()
+        .and_then(|t| parse_timestamp(t).ok())
+        .map(|t| format!(" ({})", t.format("%Y-%m-%d %H:%M:%S UTC")))
+        .unwrap_or_default();
+    let _ = writeln!(out, "\n## {name}{time}");
+}
+
+/// Add a collapsed block showing `body` in a code fence.
+fn push_details(out: &mut String, summary: &str, body: &str, language: &str) {
+    let fence = code_fence(body);
+    let _ = writeln!(
+        out,
+        "<details>\n<summary>{}</summary>\n\n{fence}{language}\n{body}\n{fence}\n\n</details>",
+        escape_html(summary)
+    );
+}
+
+/// One-line description of a tool call: its name and most telling input.
+fn tool_summary(name: &str, input: &serde_json::Value) -> String {
+    let detail = TOOL_SUMMARY_FIELDS.iter().find_map(|field| input.get(field)?.as_str());
+    let Some(detail) = detail.and_then(|d| d.lines().next()) else {
+        return name.to_string();
+    };
+    let mut summary: String = detail.chars().take(MAX_SUMMARY_CHARS).collect();
+    if detail.chars().count() > MAX_SUMMARY_CHARS {
+        summary.push_str("...");
+    }
+    format!("{name}: {summary}")
+}
+
+/// A code fence longer than any run of backticks in `body`.
+fn code_fence(body: &str) -> String {
+    let longest = body.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    "`".repeat(longest.max(2) + 1)
+}
+
+/// Escape text for use inside an HTML element.
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const TRANSCRIPT: &str = r#"{"type": "user", "timestamp": "2026-01-01T10:00:00Z", "message": {"content": "Fix the <parser> bug<system-reminder>Be careful</system-reminder>"}}
+{"type": "assistant", "timestamp": "2026-01-01T10:00:05Z", "message": {"content": [{"type": "text", "text": "Looking now."}]}}
+{"type": "assistant", "timestamp": "2026-01-01T10:00:06Z", "message": {"content": [{"type": "tool_use", "name": "Edit", "input": {"file_path": "src/parser.rs", "old_string": "```"}}]}}
+{"type": "user", "message": {"content": [{"type": "tool_result", "content": "ok"}]}}
+{"type": "assistant", "isApiErrorMessage": true, "message": {"content": [{"type": "text", "text": "API Error: 529"}]}}
+{"type": "user", "message": {"content": "This session is being continued from a previous conversation. Summary: parser work."}}
+not json
+{"type": "user", "timestamp": "2026-01-01T11:00:00Z", "message": {"content": "Thanks!"}}
+"#;
+
+    #[test]
+    fn test_export_markdown() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("abc123.jsonl");
+        std::fs::write(&path, TRANSCRIPT).unwrap();
+
+        let markdown = export(&path, TranscriptFormat::Markdown).unwrap();
+        assert_eq!(
+            markdown,
+            "# Transcript: abc123\n\
+             \n## User (2026-01-01 10:00:00 UTC)\n\
+             \nFix the <parser> bug\n\
+             \n## Assistant (2026-01-01 10:00:05 UTC)\n\
+             \nLooking now.\n\
+             \n<details>\n<summary>Edit: src/parser.rs</summary>\n\
+             \n````json\n{\n  \"file_path\": \"src/parser.rs\",\n  \"old_string\": \"```\"\n}\n````\n\
+             \n</details>\n\
+             \n<details>\n<summary>Conversation compacted</summary>\n\
+             \n```\nThis session is being continued from a previous conversation. Summary: parser work.\n```\n\
+             \n</details>\n\
+             \n## User (2026-01-01 11:00:00 UTC)\n\
+             \nThanks!\n"
+        );
+    }
+
+    #[test]
+    fn test_export_missing_file() {
+        let result = export(Path::new("/nonexistent/t.jsonl"), TranscriptFormat::Markdown);
+        assert!(matches!(result, Err(Error::FileNotFound(_))));
+    }
+
+    #[test]
+    fn test_transcript_format_from_str() {
+        assert_eq!("md".parse::<TranscriptFormat>().unwrap(), TranscriptFormat::Markdown);
+        assert_eq!("Markdown".parse::<TranscriptFormat>().unwrap(), TranscriptFormat::Markdown);
+        assert!("html".parse::<TranscriptFormat>().is_err());
+    }
+
+    #[test]
+    fn test_tool_summary_shortens_detail() {
+        let input = serde_json::json!({"command": "x".repeat(MAX_SUMMARY_CHARS + 5)});
+        let summary = tool_summary("Bash", &input);
+        assert_eq!(summary, format!("Bash: {}...", "x".repeat(MAX_SUMMARY_CHARS)));
+        assert_eq!(tool_summary("Task", &serde_json::json!({"prompt": 1})), "Task");
+    }
+}
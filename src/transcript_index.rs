@@ -0,0 +1,156 @@
+//! Full-text search across session transcripts.
+//!
+//! `claude-reliability transcript search` finds when the agent touched a file
+//! or discussed a topic, in any session the hooks have seen. Before each
+//! search, the transcripts of the sessions in the session registry are
+//! indexed into the working memory database: their messages and tool calls,
+//! with each tool call's input. Only what was appended to a transcript since
+//! it was last indexed is read.
+
+use crate::error::{Error, Result};
+use crate::storage::{SqliteStore, TranscriptMatch};
+use crate::transcript::{self, TranscriptPosition};
+use serde::Serialize;
+use std::path::Path;
+
+/// Number of matches returned by a search unless asked otherwise.
+pub const DEFAULT_LIMIT: usize = 20;
+
+/// What indexing the session transcripts did.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct IndexReport {
+    /// Number of transcripts read.
+    pub transcripts: usize,
+    /// Number of events added to the index.
+    pub events: usize,
+}
+
+/// Index the transcripts of every recorded session.
+///
+/// Transcripts that no longer exist are skipped; what was indexed from them
+/// stays searchable.
+///
+/// # Errors
+///
+/// Returns an error if a transcript cannot be read or the index updated.
+pub fn index_sessions(state: &SqliteStore) -> Result<IndexReport> {
+    let mut report = IndexReport::default();
+    for session in state.sessions()? {
+        let Some(path) = session.transcript_path else {
+            continue;
+        };
+        match index_transcript(state, &session.session_id, Path::new(&path)) {
+            Ok(events) => {
+                report.transcripts += 1;
+                report.events += events;
+            }
+            Err(Error::FileNotFound(_)) => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(report)
+}
+
+/// Index what was added to a session's transcript since it was last indexed.
+///
+/// A transcript that moved or shrank is indexed again from the start.
+/// Returns the number of events added.
+///
+/// # Errors
+///
+/// Returns an error if the transcript cannot be read or the index updated.
+pub fn index_transcript(state: &SqliteStore, session_id: &str, path: &Path) -> Result<usize> {
+    let path_str = path.to_string_lossy();
+    let len = path.metadata().map_or(0, |m| m.len());
+    let previous = state.transcript_position(session_id)?;
+    let from = match &previous {
+        Some((indexed, position)) if *indexed == path_str && position.offset <= len => *position,
+        _ => TranscriptPosition::default(),
+    };
+    let reset = from.offset == 0;
+
+    let (events, position) = transcript::read_events(path, from)?;
+    if position != from || (reset && previous.is_some()) {
+        state.index_transcript_events(session_id, &path_str, &events, position, reset)?;
+    }
+    Ok(events.len())
+}
+
+/// Search the indexed transcripts, most recent matches first.
+///
+/// Every word of the query must match. Words are matched as written, so
+/// paths such as `src/main.rs` can be searched for directly.
+///
+/// # Errors
+///
+/// Returns an error if the index cannot be searched.
+pub fn search(
+    state: &SqliteStore,
+    query: &str,
+    session_id: Option<&str>,
+    limit: usize,
+) -> Result<Vec<TranscriptMatch>> {
+    state.search_transcript_events(&fts_query(query), session_id, limit)
+}
+
+/// Quote each word of a query, so that punctuation is searched for as text
+/// rather than read as FTS5 query syntax.
+fn fts_query(query: &str) -> String {
+    query
+        .split_whitespace()
+        .map(|word| format!("\"{}\"", word.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::SessionState;
+    use tempfile::TempDir;
+
+    const FIRST: &str = r#"{"type": "user", "timestamp": "2026-01-01T10:00:00Z", "message": {"content": "Speed up the parser"}}
+{"type": "assistant", "timestamp": "2026-01-01T10:00:05Z", "message": {"content": [{"type": "tool_use", "name": "Edit", "input": {"file_path": "src/parser.rs"}}]}}
+"#;
+
+    const SECOND: &str = r#"{"type": "assistant", "timestamp": "2026-01-01T11:00:00Z", "message": {"content": [{"type": "text", "text": "The parser is now twice as fast."}]}}
+"#;
+
+    #[test]
+    fn test_fts_query() {
+        assert_eq!(fts_query("src/main.rs  parser"), r#""src/main.rs" "parser""#);
+        assert_eq!(fts_query(r#"say "hi""#), r#""say" """hi""""#);
+    }
+
+    #[test]
+    fn test_index_and_search() {
+        let dir = TempDir::new().unwrap();
+        let state = SqliteStore::new(dir.path()).unwrap();
+        let transcript = dir.path().join("s1.jsonl");
+        std::fs::write(&transcript, FIRST).unwrap();
+        let path = transcript.to_string_lossy();
+        state.touch_session("s1", Some(&path), SessionState::Working).unwrap();
+        state.touch_session("s2", Some("/nonexistent/s2.jsonl"), SessionState::Working).unwrap();
+        state.touch_session("s3", None, SessionState::Working).unwrap();
+
+        let report = index_sessions(&state).unwrap();
+        assert_eq!(report, IndexReport { transcripts: 1, events: 2 });
+        let found = search(&state, "src/parser.rs", None, DEFAULT_LIMIT).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!((found[0].line, found[0].tool.as_deref()), (2, Some("Edit")));
+
+        // Only new lines are read on the next run
+        std::fs::write(&transcript, format!("{FIRST}{SECOND}")).unwrap();
+        assert_eq!(index_sessions(&state).unwrap().events, 1);
+        assert_eq!(index_sessions(&state).unwrap().events, 0);
+        let found = search(&state, "parser", Some("s1"), DEFAULT_LIMIT).unwrap();
+        let lines: Vec<usize> = found.iter().map(|m| m.line).collect();
+        assert_eq!(lines, vec![3, 2, 1]);
+        assert!(search(&state, "parser", Some("s2"), DEFAULT_LIMIT).unwrap().is_empty());
+
+        // A rewritten transcript is indexed from the start
+        std::fs::write(&transcript, SECOND).unwrap();
+        assert_eq!(index_transcript(&state, "s1", &transcript).unwrap(), 1);
+        assert_eq!(search(&state, "parser", None, DEFAULT_LIMIT).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,144 @@
+//! Token accounting for sessions.
+//!
+//! Each assistant message in a transcript records the tokens it used. Summed
+//! over a session and priced at each model's list prices, they give an
+//! estimate of what the session has cost, which `max_session_cost_usd` caps.
+
+use serde::Deserialize;
+
+/// List prices of a model family, in dollars per million input and output tokens.
+///
+/// The first entry whose pattern is part of the model name applies; models
+/// that match none are priced like Sonnet.
+const PRICES: &[(&str, f64, f64)] = &[
+    ("opus-4-5", 5.0, 25.0),
+    ("opus", 15.0, 75.0),
+    ("haiku-4", 1.0, 5.0),
+    ("haiku", 0.8, 4.0),
+    ("sonnet", 3.0, 15.0),
+];
+
+/// Prices used for models not in [`PRICES`].
+const DEFAULT_PRICES: (f64, f64) = (3.0, 15.0);
+
+/// Price of writing a token to the prompt cache, relative to an input token.
+const CACHE_WRITE_MULTIPLIER: f64 = 1.25;
+
+/// Price of reading a token from the prompt cache, relative to an input token.
+const CACHE_READ_MULTIPLIER: f64 = 0.1;
+
+/// Tokens used by one assistant message, as recorded in the transcript.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct MessageUsage {
+    /// Input tokens not read from or written to the cache.
+    #[serde(default)]
+    pub input_tokens: u64,
+    /// Output tokens.
+    #[serde(default)]
+    pub output_tokens: u64,
+    /// Input tokens written to the prompt cache.
+    #[serde(default)]
+    pub cache_creation_input_tokens: u64,
+    /// Input tokens read from the prompt cache.
+    #[serde(default)]
+    pub cache_read_input_tokens: u64,
+}
+
+/// Tokens used by a session, and an estimate of what they cost.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TokenUsage {
+    /// Input tokens not read from or written to the cache.
+    pub input_tokens: u64,
+    /// Output tokens.
+    pub output_tokens: u64,
+    /// Input tokens written to the prompt cache.
+    pub cache_write_tokens: u64,
+    /// Input tokens read from the prompt cache.
+    pub cache_read_tokens: u64,
+    /// Estimated cost in dollars, at list prices.
+    pub cost_usd: f64,
+}
+
+impl TokenUsage {
+    /// Add the tokens used by a message written by `model`.
+    pub fn add(&mut self, usage: &MessageUsage, model: Option<&str>) {
+        let (input, output) = prices_for(model.unwrap_or_default());
+        self.input_tokens += usage.input_tokens;
+        self.output_tokens += usage.output_tokens;
+        self.cache_write_tokens += usage.cache_creation_input_tokens;
+        self.cache_read_tokens += usage.cache_read_input_tokens;
+        #[allow(clippy::cast_precision_loss)] // Token counts are far below 2^52
+        let (input_tokens, output_tokens) = (
+            (usage.cache_read_input_tokens as f64).mul_add(
+                CACHE_READ_MULTIPLIER,
+                (usage.cache_creation_input_tokens as f64)
+                    .mul_add(CACHE_WRITE_MULTIPLIER, usage.input_tokens as f64),
+            ),
+            usage.output_tokens as f64,
+        );
+        self.cost_usd += input_tokens.mul_add(input, output_tokens * output) / 1_000_000.0;
+    }
+
+    /// One-line summary of the spend, such as
+    /// `$1.24 (52000 input, 3100 output, 410000 cached tokens)`.
+    #[must_use]
+    pub fn summary(&self) -> String {
+        format!(
+            "${:.2} ({} input, {} output, {} cached tokens)",
+            self.cost_usd,
+            self.input_tokens + self.cache_write_tokens,
+            self.output_tokens,
+            self.cache_read_tokens
+        )
+    }
+}
+
+/// Dollars per million input and output tokens for a model.
+fn prices_for(model: &str) -> (f64, f64) {
+    PRICES
+        .iter()
+        .find(|(pattern, _, _)| model.contains(pattern))
+        .map_or(DEFAULT_PRICES, |&(_, input, output)| (input, output))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prices_for() {
+        assert_eq!(prices_for("claude-opus-4-5-20251101"), (5.0, 25.0));
+        assert_eq!(prices_for("claude-opus-4-1-20250805"), (15.0, 75.0));
+        assert_eq!(prices_for("claude-haiku-4-5"), (1.0, 5.0));
+        assert_eq!(prices_for("claude-sonnet-4-5"), (3.0, 15.0));
+        assert_eq!(prices_for("some-new-model"), DEFAULT_PRICES);
+    }
+
+    #[test]
+    fn test_add_usage() {
+        let mut usage = TokenUsage::default();
+        let message = MessageUsage {
+            input_tokens: 1_000_000,
+            output_tokens: 100_000,
+            cache_creation_input_tokens: 0,
+            cache_read_input_tokens: 1_000_000,
+        };
+        usage.add(&message, Some("claude-sonnet-4-5"));
+        usage.add(&MessageUsage { output_tokens: 10_000, ..Default::default() }, None);
+
+        assert_eq!(usage.input_tokens, 1_000_000);
+        assert_eq!(usage.output_tokens, 110_000);
+        assert_eq!(usage.cache_read_tokens, 1_000_000);
+        // $3 input + $1.50 output + $0.30 cache reads, then $0.15 output
+        assert!((usage.cost_usd - 4.95).abs() < 1e-9, "cost: {}", usage.cost_usd);
+        assert_eq!(usage.summary(), "$4.95 (1000000 input, 110000 output, 1000000 cached tokens)");
+    }
+
+    #[test]
+    fn test_cache_writes_cost_more_than_input() {
+        let mut usage = TokenUsage::default();
+        let message = MessageUsage { cache_creation_input_tokens: 1_000_000, ..Default::default() };
+        usage.add(&message, Some("claude-opus-4-1"));
+        assert!((usage.cost_usd - 18.75).abs() < 1e-9, "cost: {}", usage.cost_usd);
+    }
+}
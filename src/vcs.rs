@@ -0,0 +1,336 @@
+//! Version control systems the stop hook can check.
+//!
+//! The uncommitted change and push checks go through the [`Vcs`] trait, so
+//! they work on projects managed with Jujutsu (`jj`) as well as git. A project
+//! with no version control gets [`NoVcs`], for which there is never anything
+//! to commit or push. Which one a project uses is set by `vcs` in the config,
+//! or detected from the project directory.
+
+use crate::config::{GitStatusPolicy, VcsKind};
+use crate::error::Result;
+use crate::git::{self, GitStatus, UncommittedChanges, UpstreamStatus};
+use crate::traits::CommandRunner;
+use std::path::Path;
+
+/// Revset of the commits below the working copy that no remote has.
+const JJ_UNPUSHED_REVSET: &str = "(remote_bookmarks()..@-) ~ root()";
+
+/// Template printing one commit ID per line.
+const JJ_COMMIT_ID_TEMPLATE: &str = r#"commit_id ++ "\n""#;
+
+/// A version control system.
+pub trait Vcs {
+    /// Which system this is.
+    fn kind(&self) -> VcsKind;
+
+    /// Find the changes that have not been committed, within the paths the
+    /// status policy limits the check to.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the version control commands cannot be run.
+    fn uncommitted_changes(
+        &self,
+        runner: &dyn CommandRunner,
+        policy: &GitStatusPolicy,
+    ) -> Result<GitStatus>;
+
+    /// Determine how the committed work relates to the remote.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the version control commands cannot be run.
+    fn upstream_status(&self, runner: &dyn CommandRunner) -> Result<UpstreamStatus>;
+
+    /// The command that commits the uncommitted changes.
+    fn commit_command(&self) -> &'static str;
+
+    /// The command that pushes committed work to the remote.
+    fn push_command(&self) -> &'static str;
+}
+
+/// Git, through the [`git`] module.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Git;
+
+impl Vcs for Git {
+    fn kind(&self) -> VcsKind {
+        VcsKind::Git
+    }
+
+    fn uncommitted_changes(
+        &self,
+        runner: &dyn CommandRunner,
+        policy: &GitStatusPolicy,
+    ) -> Result<GitStatus> {
+        git::check_uncommitted_changes_with(runner, policy)
+    }
+
+    fn upstream_status(&self, runner: &dyn CommandRunner) -> Result<UpstreamStatus> {
+        git::upstream_status(runner)
+    }
+
+    fn commit_command(&self) -> &'static str {
+        "git add <files> && git commit -m '...'"
+    }
+
+    fn push_command(&self) -> &'static str {
+        "git push"
+    }
+}
+
+/// Jujutsu.
+///
+/// Jujutsu records the working copy as a commit (`@`), so changes in `@` are
+/// the uncommitted changes, and the commits below it are the committed work.
+/// Jujutsu has no staging area, and new files are tracked automatically.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Jujutsu;
+
+impl Vcs for Jujutsu {
+    fn kind(&self) -> VcsKind {
+        VcsKind::Jj
+    }
+
+    fn uncommitted_changes(
+        &self,
+        runner: &dyn CommandRunner,
+        policy: &GitStatusPolicy,
+    ) -> Result<GitStatus> {
+        let mut args = vec!["diff", "--name-only", "-r", "@"];
+        args.extend(policy.paths.iter().map(String::as_str));
+        let output = runner.run("jj", &args, None)?;
+        let files = if output.success() { non_empty_lines(&output.stdout) } else { Vec::new() };
+        Ok(GitStatus {
+            uncommitted: UncommittedChanges {
+                has_unstaged: !files.is_empty(),
+                ..Default::default()
+            },
+            unstaged_files: files,
+            ..Default::default()
+        })
+    }
+
+    fn upstream_status(&self, runner: &dyn CommandRunner) -> Result<UpstreamStatus> {
+        let unpushed = runner.run(
+            "jj",
+            &["log", "--no-graph", "-r", JJ_UNPUSHED_REVSET, "-T", JJ_COMMIT_ID_TEMPLATE],
+            None,
+        )?;
+        if !unpushed.success() {
+            return Ok(UpstreamStatus::Unknown);
+        }
+        let count = u32::try_from(non_empty_lines(&unpushed.stdout).len()).unwrap_or(u32::MAX);
+
+        let remote = runner.run(
+            "jj",
+            &[
+                "log",
+                "--no-graph",
+                "--limit",
+                "1",
+                "-r",
+                "remote_bookmarks()",
+                "-T",
+                JJ_COMMIT_ID_TEMPLATE,
+            ],
+            None,
+        )?;
+        Ok(if remote.success() && !remote.stdout.trim().is_empty() {
+            UpstreamStatus::Tracking { ahead: count, behind: 0 }
+        } else {
+            UpstreamStatus::Missing { unpushed: count }
+        })
+    }
+
+    fn commit_command(&self) -> &'static str {
+        "jj commit -m '...'"
+    }
+
+    fn push_command(&self) -> &'static str {
+        "jj git push"
+    }
+}
+
+/// No version control: nothing is ever uncommitted or unpushed.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoVcs;
+
+impl Vcs for NoVcs {
+    fn kind(&self) -> VcsKind {
+        VcsKind::None
+    }
+
+    fn uncommitted_changes(
+        &self,
+        _runner: &dyn CommandRunner,
+        _policy: &GitStatusPolicy,
+    ) -> Result<GitStatus> {
+        Ok(GitStatus::default())
+    }
+
+    fn upstream_status(&self, _runner: &dyn CommandRunner) -> Result<UpstreamStatus> {
+        Ok(UpstreamStatus::Unknown)
+    }
+
+    fn commit_command(&self) -> &'static str {
+        ""
+    }
+
+    fn push_command(&self) -> &'static str {
+        ""
+    }
+}
+
+/// Detect the version control system managing `base_dir`.
+///
+/// A Jujutsu repository that shares its directory with git (a colocated
+/// repository) has both `.jj` and `.git`, and is detected as Jujutsu.
+#[must_use]
+pub fn detect(base_dir: &Path) -> VcsKind {
+    if base_dir.join(".jj").is_dir() {
+        VcsKind::Jj
+    } else if base_dir.join(".git").exists() {
+        VcsKind::Git
+    } else {
+        VcsKind::None
+    }
+}
+
+/// Resolve the configured system, detecting it when set to `auto`.
+#[must_use]
+pub fn resolve(kind: VcsKind, base_dir: &Path) -> VcsKind {
+    match kind {
+        VcsKind::Auto => detect(base_dir),
+        kind => kind,
+    }
+}
+
+/// The implementation for a system. `auto`, which has not been resolved,
+/// gets [`NoVcs`].
+#[must_use]
+pub fn for_kind(kind: VcsKind) -> &'static dyn Vcs {
+    match kind {
+        VcsKind::Git => &Git,
+        VcsKind::Jj => &Jujutsu,
+        VcsKind::Auto | VcsKind::None => &NoVcs,
+    }
+}
+
+/// The trimmed, non-empty lines of command output.
+fn non_empty_lines(output: &str) -> Vec<String> {
+    output.lines().map(str::trim).filter(|s| !s.is_empty()).map(String::from).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockCommandRunner;
+    use crate::traits::CommandOutput;
+    use tempfile::TempDir;
+
+    fn output(exit_code: i32, stdout: &str) -> CommandOutput {
+        CommandOutput { exit_code, stdout: stdout.to_string(), stderr: String::new() }
+    }
+
+    fn expect_unpushed(runner: &mut MockCommandRunner, stdout: &str) {
+        runner.expect(
+            "jj",
+            &["log", "--no-graph", "-r", JJ_UNPUSHED_REVSET, "-T", JJ_COMMIT_ID_TEMPLATE],
+            output(0, stdout),
+        );
+    }
+
+    fn expect_remote_bookmarks(runner: &mut MockCommandRunner, stdout: &str) {
+        runner.expect(
+            "jj",
+            &[
+                "log",
+                "--no-graph",
+                "--limit",
+                "1",
+                "-r",
+                "remote_bookmarks()",
+                "-T",
+                JJ_COMMIT_ID_TEMPLATE,
+            ],
+            output(0, stdout),
+        );
+    }
+
+    #[test]
+    fn test_detect_prefers_jj_in_colocated_repos() {
+        let dir = TempDir::new().unwrap();
+        assert_eq!(detect(dir.path()), VcsKind::None);
+        std::fs::create_dir(dir.path().join(".git")).unwrap();
+        assert_eq!(detect(dir.path()), VcsKind::Git);
+        std::fs::create_dir(dir.path().join(".jj")).unwrap();
+        assert_eq!(detect(dir.path()), VcsKind::Jj);
+
+        assert_eq!(resolve(VcsKind::Auto, dir.path()), VcsKind::Jj);
+        assert_eq!(resolve(VcsKind::None, dir.path()), VcsKind::None);
+        assert_eq!(for_kind(VcsKind::Auto).kind(), VcsKind::None);
+    }
+
+    #[test]
+    fn test_jj_uncommitted_changes_are_the_working_copy_commit() {
+        let mut runner = MockCommandRunner::new();
+        runner.expect("jj", &["diff", "--name-only", "-r", "@"], output(0, "src/lib.rs\nnew.rs\n"));
+        let status = Jujutsu.uncommitted_changes(&runner, &GitStatusPolicy::default()).unwrap();
+        assert!(status.uncommitted.has_changes());
+        assert_eq!(status.unstaged_files, vec!["src/lib.rs", "new.rs"]);
+        assert!(status.untracked_files.is_empty());
+
+        runner.expect("jj", &["diff", "--name-only", "-r", "@"], output(0, ""));
+        assert!(!Jujutsu
+            .uncommitted_changes(&runner, &GitStatusPolicy::default())
+            .unwrap()
+            .uncommitted
+            .has_changes());
+
+        let policy =
+            GitStatusPolicy { paths: vec!["services/api".to_string()], ..Default::default() };
+        runner.expect(
+            "jj",
+            &["diff", "--name-only", "-r", "@", "services/api"],
+            output(0, "services/api/main.rs\n"),
+        );
+        let status = Jujutsu.uncommitted_changes(&runner, &policy).unwrap();
+        assert_eq!(status.unstaged_files, vec!["services/api/main.rs"]);
+        runner.verify();
+    }
+
+    #[test]
+    fn test_jj_upstream_status() {
+        let mut runner = MockCommandRunner::new();
+        expect_unpushed(&mut runner, "abc\ndef\n");
+        expect_remote_bookmarks(&mut runner, "123\n");
+        assert_eq!(
+            Jujutsu.upstream_status(&runner).unwrap(),
+            UpstreamStatus::Tracking { ahead: 2, behind: 0 }
+        );
+
+        expect_unpushed(&mut runner, "abc\n");
+        expect_remote_bookmarks(&mut runner, "");
+        assert_eq!(
+            Jujutsu.upstream_status(&runner).unwrap(),
+            UpstreamStatus::Missing { unpushed: 1 }
+        );
+
+        runner.expect(
+            "jj",
+            &["log", "--no-graph", "-r", JJ_UNPUSHED_REVSET, "-T", JJ_COMMIT_ID_TEMPLATE],
+            output(1, ""),
+        );
+        assert_eq!(Jujutsu.upstream_status(&runner).unwrap(), UpstreamStatus::Unknown);
+        runner.verify();
+    }
+
+    #[test]
+    fn test_no_vcs_has_nothing_to_commit() {
+        let runner = MockCommandRunner::new();
+        let status = NoVcs.uncommitted_changes(&runner, &GitStatusPolicy::default()).unwrap();
+        assert!(!status.uncommitted.has_changes());
+        assert_eq!(NoVcs.upstream_status(&runner).unwrap(), UpstreamStatus::Unknown);
+    }
+}
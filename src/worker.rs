@@ -0,0 +1,570 @@
+//! Headless worker sessions for ready work items (`run-worker`).
+//!
+//! The worker picks ready work items and runs a `claude -p` session for each,
+//! in single work item mode so the session cannot stop before its item is
+//! done. Up to `max_parallel` sessions run at once; when more than one may
+//! run, each gets its own git worktree on a `worker/<id>` branch so sessions
+//! never share a checkout. When a session exits, the item is left complete if
+//! the session completed it; otherwise a note is added and an item whose
+//! session failed or timed out is marked stuck. Each item is tried at most
+//! once per run, and the worker returns when no untried item is ready.
+
+use crate::error::Result;
+use crate::paths;
+use crate::single_work_item;
+use crate::tasks::{SqliteTaskStore, Status, Task, TaskStore, TaskUpdate};
+use crate::templates;
+use crate::traits::{CommandOutput, CommandRunner, RunOptions};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+use tera::Context;
+
+/// Most output kept from each session, for notes on failed items.
+const MAX_SESSION_OUTPUT_BYTES: usize = 64 * 1024;
+
+/// Lines of a failed session's output quoted in the note on its item.
+const FAILURE_NOTE_LINES: usize = 20;
+
+/// Prefix of the branch a parallel session's worktree is checked out on.
+pub const WORKTREE_BRANCH_PREFIX: &str = "worker/";
+
+/// How a worker session ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WorkerOutcome {
+    /// The session completed the work item.
+    Completed,
+    /// The session exited cleanly but left the item open.
+    Unfinished,
+    /// The session failed, with the reason; the item is marked stuck.
+    Failed(String),
+}
+
+/// The result of one worker session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkerResult {
+    /// The work item ID.
+    pub id: String,
+    /// The work item title.
+    pub title: String,
+    /// How the session ended.
+    pub outcome: WorkerOutcome,
+    /// The branch the session worked on, if it had its own worktree.
+    pub branch: Option<String>,
+}
+
+/// Where a session runs.
+struct Checkout {
+    /// The directory the session runs in.
+    dir: PathBuf,
+    /// The worktree's branch, if the session has its own worktree.
+    branch: Option<String>,
+}
+
+/// How one session went, sent back from its thread.
+struct Session {
+    task: Task,
+    branch: Option<String>,
+    output: Result<CommandOutput>,
+}
+
+/// Run worker sessions until no untried work item is ready.
+///
+/// Work items already in progress are left to whoever is working on them.
+/// Each session is killed after `session_timeout`. If the task database
+/// fails, no more items are claimed, but the sessions already running are
+/// still waited for and recorded before the error is returned.
+///
+/// # Errors
+///
+/// Returns an error if the task database cannot be read or updated.
+///
+/// # Panics
+///
+/// Panics if a session panics.
+pub fn run_worker<R: CommandRunner + Sync + ?Sized>(
+    runner: &R,
+    base_dir: &Path,
+    max_parallel: usize,
+    session_timeout: Duration,
+) -> Result<Vec<WorkerResult>> {
+    let store = SqliteTaskStore::for_project(base_dir)?;
+    let max_parallel = max_parallel.max(1);
+    // Sessions in their own worktrees must still share this project's items.
+    let shared_db = if max_parallel > 1 {
+        Some(paths::project_db_path(&base_dir.canonicalize()?))
+    } else {
+        None
+    };
+    let mut tried = HashSet::new();
+    let mut results = Vec::new();
+
+    std::thread::scope(|scope| -> Result<()> {
+        let (sender, receiver) = mpsc::channel();
+        let mut running = 0;
+        let mut error = None;
+        loop {
+            while error.is_none() && running < max_parallel {
+                let task = match claim_next_task(&store, &mut tried) {
+                    Ok(Some(task)) => task,
+                    Ok(None) => break,
+                    Err(e) => {
+                        error = Some(e);
+                        break;
+                    }
+                };
+                tracing::info!(id = %task.id, "Starting worker session");
+
+                let sender = sender.clone();
+                let shared_db = shared_db.as_deref();
+                scope.spawn(move || {
+                    let session = run_session(runner, base_dir, shared_db, session_timeout, task);
+                    // The receiver outlives every session.
+                    let _ = sender.send(session);
+                });
+                running += 1;
+            }
+            if running == 0 {
+                return error.map_or(Ok(()), Err);
+            }
+
+            let session =
+                receiver.recv().expect("a worker session is running, so the channel is open");
+            running -= 1;
+            let id = session.task.id.clone();
+            match finish_session(&store, session) {
+                Ok(result) => results.push(result),
+                Err(e) => {
+                    release(&store, &id);
+                    error.get_or_insert(e);
+                }
+            }
+        }
+    })?;
+
+    Ok(results)
+}
+
+/// Mark the highest-priority ready item that is not in progress or already
+/// tried as in progress, and return it.
+fn claim_next_task(store: &SqliteTaskStore, tried: &mut HashSet<String>) -> Result<Option<Task>> {
+    let Some(task) =
+        store.get_ready_tasks()?.into_iter().find(|t| !t.in_progress && !tried.contains(&t.id))
+    else {
+        return Ok(None);
+    };
+    tried.insert(task.id.clone());
+    store.update_task(&task.id, TaskUpdate { in_progress: Some(true), ..Default::default() })?;
+    Ok(Some(task))
+}
+
+/// Clear the in-progress flag of an item whose session could not be recorded.
+fn release(store: &SqliteTaskStore, id: &str) {
+    if let Err(e) =
+        store.update_task(id, TaskUpdate { in_progress: Some(false), ..Default::default() })
+    {
+        tracing::warn!(id, error = %e, "Could not release work item");
+    }
+}
+
+/// Run a headless session on one work item.
+///
+/// With `shared_db` set the session gets its own worktree, pointed at the
+/// project's task database, which is removed again afterwards unless the
+/// session left changes in it.
+fn run_session<R: CommandRunner + ?Sized>(
+    runner: &R,
+    base_dir: &Path,
+    shared_db: Option<&Path>,
+    timeout: Duration,
+    task: Task,
+) -> Session {
+    let checkout = match shared_db {
+        None => Checkout { dir: base_dir.to_path_buf(), branch: None },
+        Some(_) => match add_worktree(runner, base_dir, &task) {
+            Ok(checkout) => checkout,
+            Err(e) => return Session { task, branch: None, output: Err(e) },
+        },
+    };
+
+    let mut ctx = Context::new();
+    ctx.insert("work_item_id", &task.id);
+    ctx.insert("work_item_title", &task.title);
+    ctx.insert("work_item_description", &task.description);
+    let prompt = templates::render("prompts/worker_session.tera", &ctx)
+        .expect("worker_session.tera template should always render");
+
+    let mut options = RunOptions::new()
+        .cwd(&checkout.dir)
+        .env(single_work_item::ENV_VAR, &task.id)
+        .timeout(timeout)
+        .max_output_bytes(MAX_SESSION_OUTPUT_BYTES);
+    if let Some(db) = shared_db {
+        options = options.env(paths::DB_PATH_ENV, db.to_string_lossy());
+    }
+    let output = runner.run_with_options("claude", &["-p", &prompt], &options);
+
+    if checkout.branch.is_some() {
+        remove_worktree(runner, base_dir, &checkout.dir);
+    }
+    Session { task, branch: checkout.branch, output }
+}
+
+/// Add a worktree for a session on a new `worker/<id>` branch.
+fn add_worktree<R: CommandRunner + ?Sized>(
+    runner: &R,
+    base_dir: &Path,
+    task: &Task,
+) -> Result<Checkout> {
+    let dir = paths::project_data_dir(base_dir).join("worktrees").join(&task.id);
+    let branch = format!("{WORKTREE_BRANCH_PREFIX}{}", task.id);
+    let dir_arg = dir.to_string_lossy();
+    let output = runner.run_in_dir(
+        "git",
+        &["worktree", "add", "-b", &branch, &dir_arg, "HEAD"],
+        None,
+        base_dir,
+    )?;
+    if !output.success() {
+        return Err(crate::error::Error::Git(format!(
+            "could not add a worktree for {}: {}",
+            task.id,
+            output.stderr.trim()
+        )));
+    }
+    Ok(Checkout { dir, branch: Some(branch) })
+}
+
+/// Remove a session's worktree; one with uncommitted changes is kept.
+fn remove_worktree<R: CommandRunner + ?Sized>(runner: &R, base_dir: &Path, dir: &Path) {
+    let dir_arg = dir.to_string_lossy();
+    match runner.run_in_dir("git", &["worktree", "remove", &dir_arg], None, base_dir) {
+        Ok(output) if output.success() => {}
+        Ok(output) => {
+            tracing::warn!(dir = %dir.display(), stderr = %output.stderr.trim(), "Kept worker worktree");
+        }
+        Err(e) => tracing::warn!(dir = %dir.display(), error = %e, "Kept worker worktree"),
+    }
+}
+
+/// Record how a session ended on its work item.
+fn finish_session(store: &SqliteTaskStore, session: Session) -> Result<WorkerResult> {
+    let Session { task, branch, output } = session;
+    let completed = store
+        .get_task(&task.id)?
+        .is_some_and(|t| matches!(t.status, Status::Complete | Status::Abandoned));
+
+    let outcome = match output {
+        _ if completed => WorkerOutcome::Completed,
+        Ok(output) if output.success() => WorkerOutcome::Unfinished,
+        Ok(output) => {
+            WorkerOutcome::Failed(format!("exited with code {}{}", output.exit_code, tail(&output)))
+        }
+        Err(e @ crate::error::Error::CommandTimeout { .. }) => {
+            WorkerOutcome::Failed(format!("was stopped: {e}"))
+        }
+        Err(e) => WorkerOutcome::Failed(format!("could not be started: {e}")),
+    };
+
+    let on_branch =
+        branch.as_ref().map_or_else(String::new, |b| format!(" Its work is on branch {b}."));
+    match &outcome {
+        WorkerOutcome::Completed => {}
+        WorkerOutcome::Unfinished => {
+            store.add_note(
+                &task.id,
+                &format!("A worker session ended without completing this item.{on_branch}"),
+            )?;
+        }
+        WorkerOutcome::Failed(reason) => {
+            store.add_note(
+                &task.id,
+                &format!("A worker session on this item {reason}{on_branch}"),
+            )?;
+        }
+    }
+    let status = matches!(outcome, WorkerOutcome::Failed(_)).then_some(Status::Stuck);
+    store.update_task(
+        &task.id,
+        TaskUpdate { in_progress: Some(false), status, ..Default::default() },
+    )?;
+    tracing::info!(id = %task.id, ?outcome, "Worker session finished");
+
+    Ok(WorkerResult { id: task.id, title: task.title, outcome, branch })
+}
+
+/// The last lines of a session's output, for a note on its work item.
+fn tail(output: &CommandOutput) -> String {
+    let text = if output.stderr.trim().is_empty() { &output.stdout } else { &output.stderr };
+    let lines: Vec<&str> = text.trim().lines().collect();
+    if lines.is_empty() {
+        return String::new();
+    }
+    let start = lines.len().saturating_sub(FAILURE_NOTE_LINES);
+    format!(":\n\n{}", lines[start..].join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tasks::Priority;
+    use std::sync::Mutex;
+    use tempfile::TempDir;
+
+    const TIMEOUT: Duration = Duration::from_secs(60);
+
+    /// A session as the fake saw it.
+    #[derive(Debug)]
+    struct SeenSession {
+        id: String,
+        cwd: PathBuf,
+        db: Option<String>,
+        timeout: Option<Duration>,
+    }
+
+    /// A runner that can be shared between worker threads. Each session
+    /// completes its item, exits cleanly, fails, hangs or deletes its item,
+    /// depending on the title. Git commands are recorded and succeed unless
+    /// `git_fails` is set.
+    struct FakeClaude {
+        dir: PathBuf,
+        git_fails: bool,
+        sessions: Mutex<Vec<SeenSession>>,
+        git: Mutex<Vec<String>>,
+    }
+
+    impl FakeClaude {
+        fn new(dir: &Path) -> Self {
+            Self {
+                dir: dir.to_path_buf(),
+                git_fails: false,
+                sessions: Mutex::new(Vec::new()),
+                git: Mutex::new(Vec::new()),
+            }
+        }
+    }
+
+    impl CommandRunner for FakeClaude {
+        fn run(&self, _: &str, _: &[&str], _: Option<Duration>) -> Result<CommandOutput> {
+            unreachable!("git is run in the project directory")
+        }
+
+        fn run_in_dir(
+            &self,
+            program: &str,
+            args: &[&str],
+            _: Option<Duration>,
+            dir: &Path,
+        ) -> Result<CommandOutput> {
+            assert_eq!(program, "git");
+            assert_eq!(dir, self.dir);
+            self.git.lock().unwrap().push(args[..2].join(" "));
+            let exit_code = i32::from(self.git_fails);
+            Ok(CommandOutput { exit_code, stdout: String::new(), stderr: "locked".to_string() })
+        }
+
+        fn run_with_options(
+            &self,
+            program: &str,
+            args: &[&str],
+            options: &RunOptions,
+        ) -> Result<CommandOutput> {
+            assert_eq!(program, "claude");
+            let (name, id) = options.env[0].clone();
+            assert_eq!(name, single_work_item::ENV_VAR);
+            assert!(args[1].contains(&format!("work update {id} --status complete")));
+
+            let store = SqliteTaskStore::for_project(&self.dir).unwrap();
+            let title = store.get_task(&id).unwrap().unwrap().title;
+            self.sessions.lock().unwrap().push(SeenSession {
+                id: id.clone(),
+                cwd: options.cwd.clone().unwrap(),
+                db: options.env.get(1).map(|(name, value)| {
+                    assert_eq!(name, paths::DB_PATH_ENV);
+                    value.clone()
+                }),
+                timeout: options.timeout,
+            });
+            let exit_code = match title.as_str() {
+                "Crashes" => 1,
+                "Gives up" => 0,
+                "Hangs" => {
+                    return Err(crate::error::Error::CommandTimeout {
+                        command: "claude".to_string(),
+                        timeout_secs: 60,
+                    })
+                }
+                "Deletes itself" => {
+                    store.delete_task(&id).unwrap();
+                    0
+                }
+                _ => {
+                    store
+                        .update_task(
+                            &id,
+                            TaskUpdate { status: Some(Status::Complete), ..Default::default() },
+                        )
+                        .unwrap();
+                    0
+                }
+            };
+            Ok(CommandOutput { exit_code, stdout: String::new(), stderr: "API error".to_string() })
+        }
+
+        fn is_available(&self, _: &str) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn test_run_worker_runs_each_ready_item_once() {
+        let dir = TempDir::new().unwrap();
+        let store = SqliteTaskStore::for_project(dir.path()).unwrap();
+        let first = store.create_task("First", "", Priority::High).unwrap();
+        let after = store.create_task("After first", "", Priority::Medium).unwrap();
+        store.add_dependency(&after.id, &first.id).unwrap();
+        let crashes = store.create_task("Crashes", "", Priority::Medium).unwrap();
+        let gives_up = store.create_task("Gives up", "", Priority::Low).unwrap();
+        let busy = store.create_task("Busy", "", Priority::Critical).unwrap();
+        store
+            .update_task(&busy.id, TaskUpdate { in_progress: Some(true), ..Default::default() })
+            .unwrap();
+
+        let runner = FakeClaude::new(dir.path());
+        let mut results = run_worker(&runner, dir.path(), 2, TIMEOUT).unwrap();
+        results.sort_by(|a, b| a.title.cmp(&b.title));
+
+        let outcomes: Vec<(&str, &WorkerOutcome)> =
+            results.iter().map(|r| (r.title.as_str(), &r.outcome)).collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                ("After first", &WorkerOutcome::Completed),
+                ("Crashes", &WorkerOutcome::Failed("exited with code 1:\n\nAPI error".to_string())),
+                ("First", &WorkerOutcome::Completed),
+                ("Gives up", &WorkerOutcome::Unfinished),
+            ]
+        );
+        assert_eq!(runner.sessions.lock().unwrap().len(), 4);
+
+        let stuck = store.get_task(&crashes.id).unwrap().unwrap();
+        assert_eq!(stuck.status, Status::Stuck);
+        assert!(!stuck.in_progress);
+        assert!(store.get_notes(&crashes.id).unwrap()[0].content.contains("API error"));
+
+        let unfinished = store.get_task(&gives_up.id).unwrap().unwrap();
+        assert_eq!(unfinished.status, Status::Open);
+        assert_eq!(store.get_notes(&gives_up.id).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_parallel_sessions_get_their_own_worktrees() {
+        let dir = TempDir::new().unwrap();
+        let store = SqliteTaskStore::for_project(dir.path()).unwrap();
+        let done = store.create_task("Done", "", Priority::High).unwrap();
+        let gives_up = store.create_task("Gives up", "", Priority::Low).unwrap();
+
+        let runner = FakeClaude::new(dir.path());
+        let mut results = run_worker(&runner, dir.path(), 2, TIMEOUT).unwrap();
+        results.sort_by(|a, b| a.title.cmp(&b.title));
+        assert_eq!(results[0].branch, Some(format!("worker/{}", done.id)));
+        assert_eq!(results[1].branch, Some(format!("worker/{}", gives_up.id)));
+
+        let db = paths::project_db_path(&dir.path().canonicalize().unwrap());
+        let sessions = std::mem::take(&mut *runner.sessions.lock().unwrap());
+        for session in &sessions {
+            assert_eq!(
+                session.cwd,
+                paths::project_data_dir(dir.path()).join("worktrees").join(&session.id)
+            );
+            assert_eq!(session.db.as_deref(), Some(db.to_string_lossy().as_ref()));
+            assert_eq!(session.timeout, Some(TIMEOUT));
+        }
+        let mut git = runner.git.lock().unwrap().clone();
+        git.sort();
+        assert_eq!(git, ["worktree add", "worktree add", "worktree remove", "worktree remove"]);
+        assert!(store.get_notes(&gives_up.id).unwrap()[0]
+            .content
+            .ends_with(&format!("Its work is on branch worker/{}.", gives_up.id)));
+    }
+
+    #[test]
+    fn test_single_session_runs_in_the_project() {
+        let dir = TempDir::new().unwrap();
+        let store = SqliteTaskStore::for_project(dir.path()).unwrap();
+        store.create_task("Done", "", Priority::High).unwrap();
+
+        let runner = FakeClaude::new(dir.path());
+        let results = run_worker(&runner, dir.path(), 1, TIMEOUT).unwrap();
+        assert_eq!(results[0].outcome, WorkerOutcome::Completed);
+        assert_eq!(results[0].branch, None);
+
+        let sessions = std::mem::take(&mut *runner.sessions.lock().unwrap());
+        assert_eq!(sessions[0].cwd, dir.path());
+        assert_eq!(sessions[0].db, None);
+        assert_eq!(sessions[0].timeout, Some(TIMEOUT));
+        assert!(runner.git.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_timed_out_session_marks_item_stuck() {
+        let dir = TempDir::new().unwrap();
+        let store = SqliteTaskStore::for_project(dir.path()).unwrap();
+        let hangs = store.create_task("Hangs", "", Priority::High).unwrap();
+
+        let runner = FakeClaude::new(dir.path());
+        let results = run_worker(&runner, dir.path(), 1, TIMEOUT).unwrap();
+        assert_eq!(
+            results[0].outcome,
+            WorkerOutcome::Failed(
+                "was stopped: Command 'claude' timed out after 60 seconds".to_string()
+            )
+        );
+        let task = store.get_task(&hangs.id).unwrap().unwrap();
+        assert_eq!(task.status, Status::Stuck);
+        assert!(!task.in_progress);
+    }
+
+    #[test]
+    fn test_worktree_failure_marks_item_stuck_without_a_session() {
+        let dir = TempDir::new().unwrap();
+        let store = SqliteTaskStore::for_project(dir.path()).unwrap();
+        let item = store.create_task("Done", "", Priority::High).unwrap();
+
+        let mut runner = FakeClaude::new(dir.path());
+        runner.git_fails = true;
+        let results = run_worker(&runner, dir.path(), 2, TIMEOUT).unwrap();
+        let WorkerOutcome::Failed(reason) = &results[0].outcome else {
+            panic!("expected a failure, got {:?}", results[0].outcome);
+        };
+        assert!(reason.contains("could not add a worktree"), "{reason}");
+        assert!(reason.ends_with("locked"), "{reason}");
+        assert!(runner.sessions.lock().unwrap().is_empty());
+        assert_eq!(*runner.git.lock().unwrap(), ["worktree add"]);
+        assert_eq!(store.get_task(&item.id).unwrap().unwrap().status, Status::Stuck);
+    }
+
+    #[test]
+    fn test_database_error_still_records_running_sessions() {
+        let dir = TempDir::new().unwrap();
+        let store = SqliteTaskStore::for_project(dir.path()).unwrap();
+        store.create_task("Deletes itself", "", Priority::High).unwrap();
+        let done = store.create_task("Done", "", Priority::High).unwrap();
+
+        let runner = FakeClaude::new(dir.path());
+        let err = run_worker(&runner, dir.path(), 2, TIMEOUT).unwrap_err();
+        assert!(err.to_string().contains("not found"), "{err}");
+
+        let done = store.get_task(&done.id).unwrap().unwrap();
+        assert_eq!(done.status, Status::Complete);
+        assert!(!done.in_progress);
+    }
+
+    #[test]
+    fn test_run_worker_with_nothing_ready() {
+        let dir = TempDir::new().unwrap();
+        let runner = FakeClaude::new(dir.path());
+        assert_eq!(run_worker(&runner, dir.path(), 4, TIMEOUT).unwrap(), vec![]);
+    }
+}